@@ -0,0 +1,109 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // Set through RUSTFLAGS to unlock tokio's unstable runtime metrics.
+    println!("cargo:rustc-check-cfg=cfg(tokio_unstable)");
+    emit_git_sha();
+    emit_built_at();
+    #[cfg(feature = "grpc")]
+    grpc::generate();
+}
+
+/// Exposes the commit as `ANON_TICKET_GIT_SHA` for build info. Builds
+/// outside a checkout (e.g. from a source tarball) can pass it in through the
+/// environment instead and otherwise report `unknown`.
+fn emit_git_sha() {
+    println!("cargo:rerun-if-env-changed=ANON_TICKET_GIT_SHA");
+    let sha = std::env::var("ANON_TICKET_GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.is_empty())
+        .or_else(|| git(&["rev-parse", "--short=12", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=ANON_TICKET_GIT_SHA={sha}");
+
+    // Rebuild when HEAD moves, either to another branch or to a new commit.
+    // Packed refs have no file of their own; watching a missing path would
+    // rerun this script on every build.
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        let git_dir = Path::new(&git_dir);
+        let head_ref = git(&["symbolic-ref", "-q", "HEAD"]);
+        for path in std::iter::once(git_dir.join("HEAD"))
+            .chain(head_ref.map(|head_ref| git_dir.join(head_ref)))
+            .filter(|path| path.exists())
+        {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+}
+
+/// Exposes the build time as `ANON_TICKET_BUILT_AT` (unix seconds). This script
+/// only reruns when the commit changes, so it is the time the current commit
+/// was first built; reproducible builds pin it with `SOURCE_DATE_EPOCH`.
+fn emit_built_at() {
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let built_at = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=ANON_TICKET_BUILT_AT={built_at}");
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim().to_string()).filter(|out| !out.is_empty())
+}
+
+/// Generates the `Internal` service from Rust-side message types so builds do
+/// not need `protoc`; `proto/internal.proto` is the matching client contract.
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    fn unary(name: &str, route: &str, input: &str, output: &str) -> Method {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::{input}"))
+            .output_type(format!("crate::grpc::{output}"))
+            .codec_path("tonic::codec::ProstCodec")
+            .build()
+    }
+
+    pub fn generate() {
+        let service = Service::builder()
+            .name("Internal")
+            .package("anon_ticket.internal.v1")
+            .method(unary(
+                "verify_token",
+                "VerifyToken",
+                "VerifyTokenRequest",
+                "TokenStatus",
+            ))
+            .method(unary(
+                "revoke_token",
+                "RevokeToken",
+                "RevokeTokenRequest",
+                "TokenStatus",
+            ))
+            .method(unary(
+                "get_payment_status",
+                "GetPaymentStatus",
+                "GetPaymentStatusRequest",
+                "PaymentStatus",
+            ))
+            .build();
+        Builder::new().build_client(false).compile(&[service]);
+    }
+}
@@ -1,41 +1,142 @@
-use std::{
-    path::Path,
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use std::{path::Path, sync::Arc, time::Duration};
 
 #[cfg(unix)]
 use std::fs;
 
-use actix_web::{middleware::Logger, web, App, HttpServer};
-use anon_ticket_domain::config::{ApiConfig, BootstrapConfig, ConfigError};
+use actix_web::{
+    middleware::{from_fn, Logger},
+    web, App, HttpServer,
+};
+use anon_ticket_domain::config::{
+    load_env_files, ApiConfig, BootstrapConfig, ConfigError, DetectionMode, PRIMARY_WALLET,
+};
 use anon_ticket_domain::services::{
     cache::{BloomConfigError, InMemoryPidCache, PidBloom},
+    rate_limit::{InMemoryRateLimiter, RateLimit, RateLimiter},
     telemetry::{init_telemetry, TelemetryConfig, TelemetryError},
 };
+use anon_ticket_domain::storage::{
+    CreditStore, IdempotencyStore, PendingPaymentStore, QuoteStore, SkippedTransferStore,
+    StorageError, TokenStore, TombstoneStore,
+};
 use anon_ticket_domain::PidCache;
-use anon_ticket_monitor::{build_rpc_source, run_monitor, worker::MonitorHooks};
-use anon_ticket_storage::SeaOrmStorage;
+use anon_ticket_monitor::{
+    build_rpc_source, build_subaddress_source, build_wallet_sources, run_monitor,
+    spawn_refund_sender, spawn_watchdog, webhook_dispatcher, with_monitor_source,
+    worker::MonitorHooks, MonitorHeartbeat, PaymentEvents, TransferSource,
+};
+use anon_ticket_storage::{SeaOrmStorage, DEFAULT_HEDGE_AFTER};
 use cfg_if::cfg_if;
-use metrics::gauge;
+use chrono::Utc;
+use metrics::{counter, gauge};
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
 use crate::{
-    handlers::{metrics_handler, redeem_handler, revoke_token_handler, token_status_handler},
-    state::AppState,
+    auth::{verify_signed_request, InternalAuth},
+    build_info::BuildInfo,
+    changefeed::follow_changes,
+    consistency::audit_periodically,
+    fee::FeeEstimator,
+    handlers::{
+        apply_credit_handler, cache_flush_handler, cache_stats_handler, cancel_refund_handler,
+        checkout_handler, create_quote_handler, dismiss_skipped_handler, fee_estimate_handler,
+        force_claim_handler, forward_auth::ForwardAuthSource, forward_auth_handler,
+        healthz_handler, info_handler, inject_payment_handler, introspect_handler,
+        list_audit_events_handler, list_credits_handler, list_payments_handler,
+        list_refunds_handler, list_skipped_handler, list_tokens_handler, livez_handler,
+        merge_tokens_handler, payment_events_handler, payment_status_handler, quote_status_handler,
+        readyz_handler, redeem_handler, redeliver_webhook_handler, refund_credit_handler,
+        refund_sent_handler, reprocess_skipped_handler, request_refund_handler,
+        revocation_export_handler, revocations_handler, revoke_token_handler,
+        runtime_config_handler, search_handler, spend_token_handler, split_token_handler,
+        suspend_token_handler, token_balance_handler, token_status_handler, unclaim_handler,
+        unsuspend_token_handler, webhook_event_handler,
+    },
+    jwt::{TokenJwtIssuer, DEFAULT_TOKEN_JWT_TTL},
+    prewarm::prewarm_hints,
+    rate_limit::rate_limit,
+    shutdown::{self, GrpcTask, MonitorTask, Services, DEFAULT_PHASE_TIMEOUT},
+    signed_token::{TokenSigner, DEFAULT_SIGNED_TOKEN_TTL},
+    startup::{self, StartupBanner},
+    state::{AppState, EffectiveConfig, ServiceInfo},
 };
 
 const DEFAULT_PID_BLOOM_ENTRIES: u64 = 100_000;
 const DEFAULT_PID_BLOOM_FP_RATE: f64 = 0.01;
+const DEFAULT_TOMBSTONE_RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
+const TOMBSTONE_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const QUOTE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_OVERPAYMENT_REFUND_GRACE_SECS: u64 = 7 * 24 * 60 * 60;
+const CREDIT_HOLD_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+const SUSPENSION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_EXPIRED_TOKEN_RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+const DEFAULT_IDEMPOTENCY_KEY_TTL_SECS: u64 = 24 * 60 * 60;
+const DEFAULT_RATE_LIMIT_BURST: u64 = 20;
+const DEFAULT_RATE_LIMIT_PER_MINUTE: u64 = 60;
+const TOKEN_PURGE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const IDEMPOTENCY_PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Expired tokens purged per query; the janitor loops until none are left.
+const TOKEN_PURGE_BATCH: u64 = 500;
+/// Events buffered per event-stream subscriber before it skips ahead.
+const PAYMENT_EVENT_CAPACITY: usize = 1024;
+/// `/healthz` reports the monitor stale after this many missed polls, but
+/// never sooner than [`MIN_MONITOR_STALE_AFTER`].
+const MONITOR_STALE_AFTER_POLLS: u64 = 5;
+const MIN_MONITOR_STALE_AFTER: Duration = Duration::from_secs(60);
+const DEFAULT_PID_AUDIT_INTERVAL_SECS: u64 = 5 * 60;
+const DEFAULT_PID_AUDIT_SAMPLE_SIZE: u64 = 100;
+
+#[cfg(feature = "runtime-metrics")]
+static PUBLIC_RUNTIMES: crate::runtime_metrics::WorkerRuntimes =
+    crate::runtime_metrics::WorkerRuntimes::new("public");
+#[cfg(feature = "runtime-metrics")]
+static INTERNAL_RUNTIMES: crate::runtime_metrics::WorkerRuntimes =
+    crate::runtime_metrics::WorkerRuntimes::new("internal");
 
 pub async fn run() -> Result<(), BootstrapError> {
+    let env_files = load_env_files(Path::new("."))?;
     let api_config = ApiConfig::load_from_env()?;
     let monitor_config = maybe_load_monitor_config()?;
     let telemetry_config = TelemetryConfig::from_env("API");
     let telemetry = init_telemetry(&telemetry_config)?;
+    if !env_files.is_empty() {
+        info!(files = ?env_files, "filled unset variables from env and config files");
+    }
     gauge!("api_up").set(1.0);
-    let storage = SeaOrmStorage::connect(api_config.database_url()).await?;
+    let build = BuildInfo::current();
+    build.record();
+    let read_only = env_truthy("API_STORAGE_READ_ONLY");
+    if read_only {
+        warn!("storage is read-only (API_STORAGE_READ_ONLY=1); writes are rejected");
+    }
+    let mut storage = SeaOrmStorage::builder()
+        .database_url(api_config.database_url())
+        .read_only(read_only);
+    if let Some(max) = api_config.database_max_connections() {
+        storage = storage.max_connections(max);
+    }
+    if let Some(min) = api_config.database_min_connections() {
+        storage = storage.min_connections(min);
+    }
+    if let Some(secs) = api_config.database_acquire_timeout_secs() {
+        storage = storage.acquire_timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = api_config.database_idle_timeout_secs() {
+        storage = storage.idle_timeout(Duration::from_secs(secs));
+    }
+    if let Some(enabled) = api_config.database_log_statements() {
+        storage = storage.log_statements(enabled);
+    }
+    if let Some(url) = api_config.database_replica_url() {
+        let hedge_after = api_config
+            .replica_hedge_ms()
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_HEDGE_AFTER);
+        storage = storage.read_replica(url, hedge_after);
+    }
+    let storage = storage.build().await?;
     let cache_ttl = Duration::from_secs(
         api_config
             .pid_cache_ttl_secs()
@@ -64,63 +165,316 @@ pub async fn run() -> Result<(), BootstrapError> {
         bloom_fp, estimated_bloom_bytes, "configured pid bloom filter",
     );
 
-    prewarm_hints(&storage, &cache, bloom.as_deref()).await?;
+    prewarm_hints(
+        &storage,
+        cache.clone(),
+        bloom.clone(),
+        api_config.pid_bloom_snapshot_path().map(Path::new),
+    )
+    .await?;
 
+    let shared_cache = build_shared_cache(&api_config)?;
+    let rate_limiter = build_rate_limiter(&api_config)?;
     let monitor_hooks = MonitorHooks::new(
         Some(cache.clone() as Arc<dyn anon_ticket_domain::PidCache>),
         bloom.clone(),
-    );
+    )
+    .with_shared_cache(shared_cache.clone());
 
-    let monitor_task = if let Some(cfg) = monitor_config {
+    // The payment policy is published even when this process does not run
+    // the monitor itself.
+    let service_info = ServiceInfo {
+        addresses: api_config.address_book(),
+        min_payment_amount: monitor_config
+            .as_ref()
+            .map(BootstrapConfig::monitor_min_payment_amount),
+        min_confirmations: monitor_config
+            .as_ref()
+            .map(BootstrapConfig::monitor_min_confirmations),
+    };
+    let fee_estimator = match monitor_config.as_ref().and_then(|cfg| {
+        cfg.monero_daemon_rpc_url()
+            .map(|daemon| (cfg.monero_rpc_url(), daemon))
+    }) {
+        Some((wallet, daemon)) => Some(FeeEstimator::new(Arc::new(
+            build_rpc_source(wallet)?.with_daemon(daemon),
+        ))),
+        None => None,
+    };
+    // Checkout hands out the subaddresses the monitor then watches, so both
+    // share one source.
+    let subaddresses = match &monitor_config {
+        Some(cfg) if cfg.detection_mode() == DetectionMode::Subaddress => {
+            if !env_truthy("API_CHECKOUT_ENABLED") {
+                warn!("subaddress detection without API_CHECKOUT_ENABLED: no order can be paid");
+            }
+            let mut source = build_subaddress_source(
+                cfg.monero_rpc_url(),
+                cfg.subaddress_account(),
+                storage.clone(),
+            )?;
+            if let Some(daemon) = cfg.monero_daemon_rpc_url() {
+                source = source.with_daemon(daemon);
+            }
+            Some(Arc::new(source))
+        }
+        _ => None,
+    };
+    let monitor_config = match monitor_config {
+        Some(_) if read_only => {
+            warn!("embedded monitor disabled: storage is read-only");
+            None
+        }
+        cfg => cfg,
+    };
+    let effective_config = EffectiveConfig {
+        api: api_config.redacted_env(),
+        monitor: monitor_config.as_ref().map(BootstrapConfig::redacted_env),
+    };
+    let mut webhook_task = None;
+    let mut refund_task = None;
+    let mut watchdog_task = None;
+    let mut webhook_sender = None;
+    let mut monitor_heartbeat = None;
+    let (monitor_task, payment_events) = if let Some(cfg) = monitor_config {
         let storage_clone = storage.clone();
-        let hooks = monitor_hooks.clone();
-        let source = build_rpc_source(cfg.monero_rpc_url())?;
-        Some(tokio::spawn(async move {
-            run_monitor(cfg, storage_clone, source, Some(hooks)).await
-        }))
+        let events = PaymentEvents::new(PAYMENT_EVENT_CAPACITY);
+        let heartbeat = MonitorHeartbeat::new();
+        monitor_heartbeat = Some((
+            heartbeat.clone(),
+            monitor_stale_after(cfg.monitor_poll_interval_secs()),
+        ));
+        let webhooks = webhook_dispatcher(&cfg, storage.clone())?.map(|(sender, dispatcher)| {
+            webhook_task = Some(tokio::spawn(dispatcher.run()));
+            webhook_sender = Some(sender.clone());
+            sender
+        });
+        refund_task = spawn_refund_sender(&cfg, storage.clone())?;
+        watchdog_task = Some(spawn_watchdog(&cfg, heartbeat.clone())?);
+        let hooks = monitor_hooks
+            .clone()
+            .with_events(Some(events.clone()))
+            .with_webhooks(webhooks)
+            .with_heartbeat(Some(heartbeat))
+            .with_pending_store(
+                cfg.track_pending()
+                    .then(|| Arc::new(storage.clone()) as Arc<dyn PendingPaymentStore>),
+            )
+            .with_skip_journal(Some(
+                Arc::new(storage.clone()) as Arc<dyn SkippedTransferStore>
+            ));
+        let wallets: Vec<(String, Arc<dyn TransferSource>)> = match &subaddresses {
+            Some(source) => vec![(PRIMARY_WALLET.to_string(), source.clone())],
+            None => build_wallet_sources(&cfg)?
+                .into_iter()
+                .map(|(name, source)| (name, Arc::new(source) as Arc<dyn TransferSource>))
+                .collect(),
+        };
+        let wallets = with_monitor_source(&cfg, wallets)?;
+        #[cfg(feature = "fault-injection")]
+        let (storage_clone, wallets) = wrap_monitor_faults(storage_clone, wallets)?;
+        let stop = CancellationToken::new();
+        let handle = tokio::spawn(run_monitor(
+            cfg,
+            storage_clone,
+            wallets,
+            Some(hooks),
+            stop.clone(),
+        ));
+        (Some(MonitorTask { handle, stop }), Some(events))
     } else {
-        None
+        (None, None)
     };
 
-    let state = AppState::new(storage, cache, telemetry.clone(), bloom);
+    let tombstone_retention = Duration::from_secs(
+        api_config
+            .tombstone_retention_secs()
+            .unwrap_or(DEFAULT_TOMBSTONE_RETENTION_SECS),
+    );
+    let mut background: Vec<_> = webhook_task
+        .into_iter()
+        .chain(refund_task)
+        .chain(watchdog_task)
+        .collect();
+    if !read_only {
+        background.push(tokio::spawn(prune_tombstones_periodically(
+            storage.clone(),
+            tombstone_retention,
+        )));
+        background.push(tokio::spawn(expire_quotes_periodically(storage.clone())));
+        background.push(tokio::spawn(release_credit_holds_periodically(
+            storage.clone(),
+        )));
+        background.push(tokio::spawn(lift_lapsed_suspensions_periodically(
+            storage.clone(),
+        )));
+        // Tokens issued by any replica are purged, so this runs whether or
+        // not this one sets a TTL.
+        background.push(tokio::spawn(purge_expired_tokens_periodically(
+            storage.clone(),
+            Duration::from_secs(
+                api_config
+                    .expired_token_retention_secs()
+                    .unwrap_or(DEFAULT_EXPIRED_TOKEN_RETENTION_SECS),
+            ),
+        )));
+        background.push(tokio::spawn(prune_idempotency_keys_periodically(
+            storage.clone(),
+            Duration::from_secs(
+                api_config
+                    .idempotency_key_ttl_secs()
+                    .unwrap_or(DEFAULT_IDEMPOTENCY_KEY_TTL_SECS),
+            ),
+        )));
+    }
 
+    let internal_auth = InternalAuth::from_keys(api_config.internal_api_keys()).map(|auth| {
+        match api_config.internal_signature_window_secs() {
+            Some(secs) => auth.with_signature_window(Duration::from_secs(secs)),
+            None => auth,
+        }
+    });
+    if internal_auth.is_none() {
+        warn!("API_INTERNAL_KEYS not set; internal routes rely on network isolation only");
+    }
+    // The config check already vetted the name; this only converts it.
+    let forward_auth = ForwardAuthSource::new(
+        api_config.forward_auth_header(),
+        api_config.forward_auth_cookie(),
+    )
+    .map_err(|_| ConfigError::InvalidValue {
+        key: "API_FORWARD_AUTH_HEADER",
+        reason: "must be a valid header name",
+    })?;
+    let state = AppState::new(storage, cache, telemetry.clone(), bloom)
+        .with_shared_cache(shared_cache)
+        .with_rate_limiter(rate_limiter)
+        .with_internal_auth(internal_auth)
+        .with_checkout(env_truthy("API_CHECKOUT_ENABLED"))
+        .with_checkout_presets(api_config.checkout_presets().to_vec())
+        .with_token_tiers(api_config.token_tiers().to_vec())
+        .with_subscription_period(api_config.subscription_period())
+        .with_quote_ttl(api_config.quote_ttl_secs().map(Duration::from_secs))
+        .with_overpayment_policy(
+            api_config.overpayment_policy(),
+            Duration::from_secs(
+                api_config
+                    .overpayment_refund_grace_secs()
+                    .unwrap_or(DEFAULT_OVERPAYMENT_REFUND_GRACE_SECS),
+            ),
+        )
+        .with_token_ttl(api_config.token_ttl_secs().map(Duration::from_secs))
+        .with_token_jwt(api_config.token_jwt_secret().map(|secret| {
+            TokenJwtIssuer::new(
+                secret.as_bytes(),
+                api_config
+                    .token_jwt_ttl_secs()
+                    .map_or(DEFAULT_TOKEN_JWT_TTL, Duration::from_secs),
+            )
+        }))
+        .with_token_signer(TokenSigner::new(
+            api_config.token_signing_keys().to_vec(),
+            api_config
+                .signed_token_ttl_secs()
+                .map_or(DEFAULT_SIGNED_TOKEN_TTL, Duration::from_secs),
+        ))
+        .with_forward_auth(forward_auth)
+        .with_service_info(service_info)
+        .with_fee_estimator(fee_estimator)
+        .with_subaddresses(subaddresses)
+        .with_payment_events(payment_events)
+        .with_webhooks(webhook_sender)
+        .with_monitor_heartbeat(
+            monitor_heartbeat
+                .as_ref()
+                .map(|(heartbeat, _)| heartbeat.clone()),
+            monitor_heartbeat.map_or(Duration::ZERO, |(_, stale_after)| stale_after),
+        )
+        .with_effective_config(Some(effective_config));
+
+    let audit_interval = api_config
+        .pid_audit_interval_secs()
+        .unwrap_or(DEFAULT_PID_AUDIT_INTERVAL_SECS);
+    if audit_interval > 0 {
+        background.push(tokio::spawn(audit_periodically(
+            state.clone(),
+            Duration::from_secs(audit_interval),
+            api_config
+                .pid_audit_sample_size()
+                .unwrap_or(DEFAULT_PID_AUDIT_SAMPLE_SIZE),
+        )));
+    }
+    if let Some(events) = state.payment_events() {
+        background.push(tokio::spawn(
+            state.pending_payments().clone().follow(events.subscribe()),
+        ));
+    }
+    background.push(tokio::spawn(
+        crate::process_metrics::report_resources_periodically(
+            state.storage().clone(),
+            crate::process_metrics::ResourceMonitor::from_config(&api_config),
+        ),
+    ));
+    let change_feed = state.storage().listen_changes().await?;
+    let follows_changes = change_feed.is_some();
+    if let Some(listener) = change_feed {
+        background.push(tokio::spawn(follow_changes(state.clone(), listener)));
+    }
+    let phase_timeout = api_config
+        .shutdown_phase_timeout_secs()
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_PHASE_TIMEOUT);
+    let drain_secs = phase_timeout.as_secs();
+
+    #[cfg(feature = "runtime-metrics")]
+    crate::runtime_metrics::sample_main();
     let public_state = state.clone();
     let public_server = HttpServer::new(move || {
+        #[cfg(feature = "runtime-metrics")]
+        PUBLIC_RUNTIMES.sample_current();
         App::new()
             .app_data(web::Data::new(public_state.clone()))
             .wrap(Logger::default())
-            .route("/api/v1/redeem", web::post().to(redeem_handler))
-            .route("/api/v1/token/{token}", web::get().to(token_status_handler))
-    });
+            .configure(public_routes)
+    })
+    .disable_signals()
+    .shutdown_timeout(drain_secs);
 
     let internal_state = state.clone();
     let internal_server = HttpServer::new(move || {
+        #[cfg(feature = "runtime-metrics")]
+        INTERNAL_RUNTIMES.sample_current();
         App::new()
             .app_data(web::Data::new(internal_state.clone()))
+            .wrap(from_fn(verify_signed_request))
             .wrap(Logger::default())
-            .route("/metrics", web::get().to(metrics_handler))
-            .route(
-                "/api/v1/token/{token}/revoke",
-                web::post().to(revoke_token_handler),
-            )
-    });
+            .configure(internal_routes)
+    })
+    .disable_signals()
+    .shutdown_timeout(drain_secs);
 
     cfg_if! {
         if #[cfg(unix)] {
             let mut public_server = public_server;
+            let public_listeners;
             if let Some(socket) = api_config.api_unix_socket() {
                 cleanup_socket(socket)?;
                 public_server = public_server.bind_uds(socket)?;
+                public_listeners = startup::unix_listener(socket);
             } else {
                 public_server = public_server.bind(api_config.api_bind_address())?;
+                public_listeners = startup::tcp_listeners(&public_server.addrs());
             }
 
             let mut internal_server = internal_server;
+            let internal_listeners;
             if let Some(socket) = api_config.internal_unix_socket() {
                 cleanup_socket(socket)?;
                 internal_server = internal_server.bind_uds(socket)?;
+                internal_listeners = startup::unix_listener(socket);
             } else if let Some(addr) = api_config.internal_bind_address() {
                 internal_server = internal_server.bind(addr)?;
+                internal_listeners = startup::tcp_listeners(&internal_server.addrs());
             } else {
                 return Err(BootstrapError::Io(std::io::Error::other(
                     "internal listener required but no bind target provided",
@@ -129,19 +483,6 @@ pub async fn run() -> Result<(), BootstrapError> {
 
             let public_server = public_server.run();
             let internal_server = internal_server.run();
-
-            if let Some(monitor_handle) = monitor_task {
-                tokio::try_join!(
-                    async { public_server.await.map_err(BootstrapError::Io) },
-                    async { internal_server.await.map_err(BootstrapError::Io) },
-                    monitor_join(monitor_handle),
-                )?;
-            } else {
-                tokio::try_join!(
-                    async { public_server.await.map_err(BootstrapError::Io) },
-                    async { internal_server.await.map_err(BootstrapError::Io) },
-                )?;
-            }
         } else {
             if let Some(socket) = api_config.api_unix_socket() {
                 return Err(BootstrapError::Io(std::io::Error::other(format!(
@@ -154,30 +495,203 @@ pub async fn run() -> Result<(), BootstrapError> {
                 ))));
             }
 
-            let public_server = public_server.bind(api_config.api_bind_address())?.run();
+            let public_server = public_server.bind(api_config.api_bind_address())?;
+            let public_listeners = startup::tcp_listeners(&public_server.addrs());
+            let public_server = public_server.run();
             let internal_addr = api_config.internal_bind_address().ok_or_else(|| {
                 std::io::Error::other(
                     "internal listener required but no TCP bind address provided for this platform",
                 )
             })?;
-            let internal_server = internal_server.bind(internal_addr)?.run();
-
-            if let Some(monitor_handle) = monitor_task {
-                tokio::try_join!(
-                    async { public_server.await.map_err(BootstrapError::Io) },
-                    async { internal_server.await.map_err(BootstrapError::Io) },
-                    monitor_join(monitor_handle),
-                )?;
-            } else {
-                tokio::try_join!(
-                    async { public_server.await.map_err(BootstrapError::Io) },
-                    async { internal_server.await.map_err(BootstrapError::Io) },
-                )?;
-            }
+            let internal_server = internal_server.bind(internal_addr)?;
+            let internal_listeners = startup::tcp_listeners(&internal_server.addrs());
+            let internal_server = internal_server.run();
         }
     }
 
-    Ok(())
+    let grpc_server = spawn_grpc_server(&api_config, &state)?;
+    StartupBanner {
+        version: build.version,
+        git_sha: build.git_sha,
+        public: public_listeners,
+        internal: internal_listeners,
+        grpc: grpc_server
+            .as_ref()
+            .and(api_config.internal_grpc_address())
+            .map(str::to_string),
+        embedded_monitor: monitor_task.is_some(),
+        backend: state.storage().backend_name(),
+        schema: anon_ticket_storage::schema_fingerprint(),
+        read_only,
+        read_replica: api_config.database_replica_url().is_some(),
+        change_feed: follows_changes,
+    }
+    .emit();
+
+    let result = shutdown::serve(
+        Services {
+            public: public_server,
+            internal: internal_server,
+            grpc: grpc_server,
+            monitor: monitor_task,
+            background,
+            state,
+            shutdown: CancellationToken::new(),
+        },
+        phase_timeout,
+    )
+    .await;
+    telemetry.shutdown().await;
+    result
+}
+
+/// Routes served on the public (user-facing) listener. Routes that act on a
+/// PID or token are rate limited per identifier (see [`rate_limit`]).
+pub(crate) fn public_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/livez", web::get().to(livez_handler))
+        .route("/readyz", web::get().to(readyz_handler))
+        .route("/healthz", web::get().to(healthz_handler))
+        .route("/api/v1/checkout", web::post().to(checkout_handler))
+        .route("/api/v1/fee-estimate", web::get().to(fee_estimate_handler))
+        .route("/api/v1/forward-auth", web::get().to(forward_auth_handler))
+        .route("/api/v1/info", web::get().to(info_handler))
+        .service(
+            web::resource("/api/v1/payment/{pid}/events")
+                .wrap(from_fn(rate_limit))
+                .route(web::get().to(payment_events_handler)),
+        )
+        .route("/api/v1/quote", web::post().to(create_quote_handler))
+        .route("/api/v1/revocations", web::get().to(revocations_handler))
+        .service(
+            web::resource("/api/v1/quote/{pid}")
+                .wrap(from_fn(rate_limit))
+                .route(web::get().to(quote_status_handler)),
+        )
+        .service(
+            web::resource("/api/v1/payment/{pid}")
+                .wrap(from_fn(rate_limit))
+                .route(web::get().to(payment_status_handler)),
+        )
+        .service(
+            web::resource("/api/v1/redeem")
+                .wrap(from_fn(rate_limit))
+                .route(web::post().to(redeem_handler)),
+        )
+        .service(
+            web::resource("/api/v1/token/merge")
+                .wrap(from_fn(rate_limit))
+                .route(web::post().to(merge_tokens_handler)),
+        )
+        .service(
+            web::resource("/api/v1/token/{token}")
+                .wrap(from_fn(rate_limit))
+                .route(web::get().to(token_status_handler)),
+        )
+        .service(
+            web::resource("/api/v1/token/{token}/balance")
+                .wrap(from_fn(rate_limit))
+                .route(web::get().to(token_balance_handler)),
+        )
+        .service(
+            web::resource("/api/v1/token/{token}/split")
+                .wrap(from_fn(rate_limit))
+                .route(web::post().to(split_token_handler)),
+        )
+        .service(
+            web::resource("/api/v1/token/{token}/spend")
+                .wrap(from_fn(rate_limit))
+                .route(web::post().to(spend_token_handler)),
+        );
+}
+
+/// Routes served only on the internal (operator) listener.
+pub(crate) fn internal_routes(cfg: &mut web::ServiceConfig) {
+    #[cfg(feature = "metrics")]
+    cfg.route("/metrics", web::get().to(crate::handlers::metrics_handler));
+    cfg.route("/livez", web::get().to(livez_handler))
+        .route("/readyz", web::get().to(readyz_handler))
+        .route("/healthz", web::get().to(healthz_handler))
+        .route(
+            "/api/v1/token/{token}/revoke",
+            web::post().to(revoke_token_handler),
+        )
+        .route(
+            "/api/v1/token/{token}/suspend",
+            web::post().to(suspend_token_handler),
+        )
+        .route(
+            "/api/v1/token/{token}/unsuspend",
+            web::post().to(unsuspend_token_handler),
+        )
+        .route("/internal/cache/stats", web::get().to(cache_stats_handler))
+        .route("/internal/cache/flush", web::post().to(cache_flush_handler))
+        .route("/internal/config", web::get().to(runtime_config_handler))
+        .route("/internal/introspect", web::post().to(introspect_handler))
+        .route("/internal/search", web::get().to(search_handler))
+        .route("/internal/payments", web::post().to(inject_payment_handler))
+        .route(
+            "/internal/v1/payments",
+            web::get().to(list_payments_handler),
+        )
+        .route("/internal/v1/tokens", web::get().to(list_tokens_handler))
+        .route(
+            "/internal/v1/revocations",
+            web::get().to(revocation_export_handler),
+        )
+        .route(
+            "/internal/v1/audit",
+            web::get().to(list_audit_events_handler),
+        )
+        .route("/internal/v1/credits", web::get().to(list_credits_handler))
+        .route(
+            "/internal/v1/credits/{id}/refund",
+            web::post().to(refund_credit_handler),
+        )
+        .route(
+            "/internal/v1/credits/{id}/credit",
+            web::post().to(apply_credit_handler),
+        )
+        .route("/internal/v1/refunds", web::get().to(list_refunds_handler))
+        .route(
+            "/internal/v1/refunds/{id}/sent",
+            web::post().to(refund_sent_handler),
+        )
+        .route(
+            "/internal/v1/refunds/{id}/cancel",
+            web::post().to(cancel_refund_handler),
+        )
+        .route(
+            "/internal/v1/payment/{pid}/refund",
+            web::post().to(request_refund_handler),
+        )
+        .route(
+            "/internal/v1/skipped-transfers",
+            web::get().to(list_skipped_handler),
+        )
+        .route(
+            "/internal/v1/skipped-transfers/{txid}",
+            web::delete().to(dismiss_skipped_handler),
+        )
+        .route(
+            "/internal/v1/skipped-transfers/{txid}/reprocess",
+            web::post().to(reprocess_skipped_handler),
+        )
+        .route(
+            "/internal/v1/webhooks/events/{id}",
+            web::get().to(webhook_event_handler),
+        )
+        .route(
+            "/internal/v1/webhooks/events/{id}/redeliver",
+            web::post().to(redeliver_webhook_handler),
+        )
+        .route(
+            "/internal/payments/{pid}/claim",
+            web::post().to(force_claim_handler),
+        )
+        .route(
+            "/internal/payments/{pid}/unclaim",
+            web::post().to(unclaim_handler),
+        );
 }
 
 #[derive(Debug, Error)]
@@ -198,6 +712,172 @@ pub enum BootstrapError {
     InvalidBloomConfig(String),
     #[error("task join error: {0}")]
     Join(String),
+    #[error("shared cache error: {0}")]
+    SharedCache(String),
+    #[cfg(feature = "grpc")]
+    #[error("grpc server error: {0}")]
+    Grpc(#[from] tonic::transport::Error),
+    #[cfg(feature = "fault-injection")]
+    #[error("fault injection config error: {0}")]
+    Fault(#[from] anon_ticket_domain::services::fault::FaultConfigError),
+}
+
+#[cfg(feature = "fault-injection")]
+type FlakyWallets<S> = Vec<(String, anon_ticket_monitor::rpc::FlakySource<S>)>;
+
+/// Wraps the embedded monitor's dependencies in fault injectors configured by
+/// `MONITOR_FAULT_*`. Without those variables the wrappers never fail.
+#[cfg(feature = "fault-injection")]
+fn wrap_monitor_faults<S, D>(
+    storage: D,
+    wallets: Vec<(String, S)>,
+) -> Result<(anon_ticket_domain::storage::FlakyStore<D>, FlakyWallets<S>), BootstrapError> {
+    let config =
+        anon_ticket_domain::services::fault::FaultConfig::from_env("MONITOR")?.unwrap_or_default();
+    if config.error_rate() > 0.0 || !config.latency().is_zero() {
+        warn!(
+            error_rate = config.error_rate(),
+            latency_ms = config.latency().as_millis() as u64,
+            "fault injection enabled for embedded monitor"
+        );
+    }
+    // Offset each source's seed so storage and RPC faults do not fire in
+    // lockstep.
+    let wallets = (1..)
+        .zip(wallets)
+        .map(|(offset, (name, source))| {
+            let source_config = config.clone().with_seed(config.seed().wrapping_add(offset));
+            (
+                name,
+                anon_ticket_monitor::rpc::FlakySource::new(source, source_config),
+            )
+        })
+        .collect();
+    Ok((
+        anon_ticket_domain::storage::FlakyStore::new(storage, config),
+        wallets,
+    ))
+}
+
+/// Keeps tombstones for `retention`, then drops them so the table stays bounded.
+async fn prune_tombstones_periodically(storage: SeaOrmStorage, retention: Duration) {
+    let mut interval = tokio::time::interval(TOMBSTONE_PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Ok(retention) = chrono::Duration::from_std(retention) else {
+            warn!("tombstone retention out of range; pruning disabled");
+            return;
+        };
+        match storage.prune_tombstones(Utc::now() - retention).await {
+            Ok(pruned) => {
+                counter!("api_tombstones_pruned_total").increment(pruned);
+            }
+            Err(err) => warn!(?err, "tombstone pruning failed"),
+        }
+    }
+}
+
+/// Moves open quotes past their deadline to expired. Quotes issued by any
+/// replica are swept, so this runs whether or not the endpoint is enabled.
+async fn expire_quotes_periodically(storage: SeaOrmStorage) {
+    let mut interval = tokio::time::interval(QUOTE_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        match storage.expire_quotes(Utc::now()).await {
+            Ok(expired) => {
+                counter!("api_quotes_expired_total").increment(expired);
+            }
+            Err(err) => warn!(?err, "quote expiry sweep failed"),
+        }
+    }
+}
+
+fn monitor_stale_after(poll_interval_secs: u64) -> Duration {
+    Duration::from_secs(poll_interval_secs.saturating_mul(MONITOR_STALE_AFTER_POLLS))
+        .max(MIN_MONITOR_STALE_AFTER)
+}
+
+/// Credits overpayment excess whose refund grace period ran out. Holds made
+/// by any replica are released, whatever this one's policy.
+async fn release_credit_holds_periodically(storage: SeaOrmStorage) {
+    let mut interval = tokio::time::interval(CREDIT_HOLD_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        match storage.release_expired_holds(Utc::now()).await {
+            Ok(released) => {
+                counter!("api_credit_holds_released_total").increment(released);
+            }
+            Err(err) => warn!(?err, "credit hold release failed"),
+        }
+    }
+}
+
+/// Clears suspensions whose end has passed. They stop applying at
+/// `suspended_until` regardless; this keeps stored rows and listings in step.
+async fn lift_lapsed_suspensions_periodically(storage: SeaOrmStorage) {
+    let mut interval = tokio::time::interval(SUSPENSION_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        match storage.lift_lapsed_suspensions(Utc::now()).await {
+            Ok(lifted) => {
+                counter!("api_token_suspensions_lifted_total").increment(lifted);
+            }
+            Err(err) => warn!(?err, "suspension sweep failed"),
+        }
+    }
+}
+
+async fn purge_expired_tokens_periodically(storage: SeaOrmStorage, retention: Duration) {
+    let mut interval = tokio::time::interval(TOKEN_PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Ok(retention) = chrono::Duration::from_std(retention) else {
+            warn!("expired token retention out of range; purging disabled");
+            return;
+        };
+        if let Err(err) = purge_expired_tokens(&storage, Utc::now() - retention).await {
+            warn!(?err, "expired token purge failed");
+        }
+    }
+}
+
+/// Drops redeem responses kept for `Idempotency-Key` retries once `ttl` has
+/// passed, so the table stays bounded and issued tokens are not kept twice.
+async fn prune_idempotency_keys_periodically(storage: SeaOrmStorage, ttl: Duration) {
+    let mut interval = tokio::time::interval(IDEMPOTENCY_PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Ok(ttl) = chrono::Duration::from_std(ttl) else {
+            warn!("idempotency key ttl out of range; pruning disabled");
+            return;
+        };
+        match storage.prune_idempotent_responses(Utc::now() - ttl).await {
+            Ok(pruned) => {
+                counter!("api_idempotency_keys_pruned_total").increment(pruned);
+            }
+            Err(err) => warn!(?err, "idempotency key pruning failed"),
+        }
+    }
+}
+
+/// Purges tokens that expired before `before`, leaving a tombstone for each.
+pub(crate) async fn purge_expired_tokens(
+    storage: &SeaOrmStorage,
+    before: chrono::DateTime<Utc>,
+) -> Result<u64, StorageError> {
+    let mut purged = 0;
+    loop {
+        let batch = storage.expired_tokens(before, TOKEN_PURGE_BATCH).await?;
+        for token in &batch {
+            if storage.purge_token(token).await? {
+                purged += 1;
+                counter!("api_tokens_purged_total").increment(1);
+            }
+        }
+        if (batch.len() as u64) < TOKEN_PURGE_BATCH {
+            return Ok(purged);
+        }
+    }
 }
 
 fn cleanup_socket(path: &str) -> std::io::Result<()> {
@@ -235,28 +915,105 @@ fn build_bloom_filter(
             BloomConfigError::InvalidFalsePositiveRate(rate) => BootstrapError::InvalidBloomConfig(
                 format!("API_PID_BLOOM_FP_RATE must be in (0,1): {rate}"),
             ),
+            other => BootstrapError::InvalidBloomConfig(other.to_string()),
         })
 }
 
-async fn prewarm_hints(
-    storage: &SeaOrmStorage,
-    cache: &InMemoryPidCache,
-    bloom: Option<&PidBloom>,
-) -> Result<(), BootstrapError> {
-    let start = Instant::now();
-    let pids = storage.all_payment_ids().await?;
-    for pid in &pids {
-        cache.mark_present(pid);
-        if let Some(b) = bloom {
-            b.insert(pid);
+/// Internal gRPC listener on `API_INTERNAL_GRPC_ADDRESS`; only available with
+/// the `grpc` feature.
+fn spawn_grpc_server(
+    api_config: &ApiConfig,
+    state: &AppState,
+) -> Result<Option<GrpcTask>, BootstrapError> {
+    let Some(addr) = api_config.internal_grpc_address() else {
+        return Ok(None);
+    };
+    cfg_if! {
+        if #[cfg(feature = "grpc")] {
+            let addr = addr.parse().map_err(|err| {
+                std::io::Error::other(format!("invalid API_INTERNAL_GRPC_ADDRESS '{addr}': {err}"))
+            })?;
+            let stop = CancellationToken::new();
+            let server = crate::grpc::serve(state.clone(), addr, stop.clone().cancelled_owned())?;
+            let handle = tokio::spawn(async move { Ok(server.await?) });
+            Ok(Some(GrpcTask { handle, stop }))
+        } else {
+            let _ = (addr, state);
+            Err(BootstrapError::Io(std::io::Error::other(
+                "API_INTERNAL_GRPC_ADDRESS requires building with the grpc feature",
+            )))
         }
     }
-    info!(
-        count = pids.len(),
-        elapsed_ms = start.elapsed().as_millis() as u64,
-        "prefilled cache/bloom with existing payments",
+}
+
+/// Redis-backed PID cache from `API_REDIS_URL`; only available with the
+/// `redis-cache` feature.
+fn build_shared_cache(api_config: &ApiConfig) -> Result<Option<Arc<dyn PidCache>>, BootstrapError> {
+    let Some(url) = api_config.redis_url() else {
+        return Ok(None);
+    };
+    cfg_if! {
+        if #[cfg(feature = "redis-cache")] {
+            let mut cache = anon_ticket_domain::RedisPidCache::new(
+                url,
+                anon_ticket_domain::RedisPidCache::DEFAULT_KEY_PREFIX,
+            )
+            .map_err(|err| BootstrapError::SharedCache(err.to_string()))?;
+            if let Some(secs) = api_config.redis_negative_ttl_secs() {
+                cache = cache.with_negative_ttl(Duration::from_secs(secs));
+            }
+            info!(key = cache.key(), "sharing pid hints through redis");
+            Ok(Some(Arc::new(cache)))
+        } else {
+            let _ = url;
+            Err(BootstrapError::SharedCache(
+                "API_REDIS_URL requires building with the redis-cache feature".into(),
+            ))
+        }
+    }
+}
+
+/// Per-identifier limiter for public routes, shared through Redis when
+/// `API_REDIS_URL` is set and local to this replica otherwise.
+fn build_rate_limiter(
+    api_config: &ApiConfig,
+) -> Result<Option<Arc<dyn RateLimiter>>, BootstrapError> {
+    let burst = api_config
+        .rate_limit_burst()
+        .unwrap_or(DEFAULT_RATE_LIMIT_BURST);
+    if burst == 0 {
+        warn!("rate limiting disabled (API_RATE_LIMIT_BURST=0)");
+        return Ok(None);
+    }
+    let per_minute = api_config
+        .rate_limit_per_minute()
+        .unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE);
+    // Both were checked to fit when the config was loaded.
+    let limit = RateLimit::new(
+        u32::try_from(burst).unwrap_or(u32::MAX),
+        u32::try_from(per_minute).unwrap_or(u32::MAX),
     );
-    Ok(())
+    let Some(url) = api_config.redis_url() else {
+        info!(burst, per_minute, "rate limiting per pid/token in memory");
+        return Ok(Some(Arc::new(InMemoryRateLimiter::new(limit))));
+    };
+    cfg_if! {
+        if #[cfg(feature = "redis-cache")] {
+            let limiter = anon_ticket_domain::services::rate_limit::RedisRateLimiter::new(
+                url,
+                anon_ticket_domain::RedisPidCache::DEFAULT_KEY_PREFIX,
+                limit,
+            )
+            .map_err(|err| BootstrapError::SharedCache(err.to_string()))?;
+            info!(burst, per_minute, "rate limiting per pid/token through redis");
+            Ok(Some(Arc::new(limiter)))
+        } else {
+            let _ = url;
+            Err(BootstrapError::SharedCache(
+                "API_REDIS_URL requires building with the redis-cache feature".into(),
+            ))
+        }
+    }
 }
 
 fn maybe_load_monitor_config() -> Result<Option<BootstrapConfig>, BootstrapError> {
@@ -281,15 +1038,6 @@ fn allow_missing_bloom() -> bool {
     env_truthy("API_ALLOW_NO_BLOOM")
 }
 
-async fn monitor_join(
-    handle: tokio::task::JoinHandle<Result<(), anon_ticket_monitor::worker::MonitorError>>,
-) -> Result<(), BootstrapError> {
-    handle
-        .await
-        .map_err(|err| BootstrapError::Join(err.to_string()))??;
-    Ok(())
-}
-
 fn env_truthy(key: &str) -> bool {
     matches!(std::env::var(key), Ok(val) if val == "1" || val.eq_ignore_ascii_case("true"))
 }
@@ -0,0 +1,70 @@
+//! Audit trail for redemptions and operator actions.
+//!
+//! Events are emitted as structured `tracing` records under the `audit` target
+//! so they can be routed to a dedicated sink (e.g. `RUST_LOG=audit=info`), and
+//! appended to the `audit_events` table for `GET /internal/v1/audit`. Only
+//! a hash of the subject PID or token is persisted.
+
+use anon_ticket_domain::model::{audit_subject_hash, AuditActor, NewAuditEvent};
+use anon_ticket_domain::storage::AuditStore;
+use chrono::Utc;
+use metrics::counter;
+use tracing::{info, warn};
+
+use crate::state::AppState;
+
+/// A single action against a payment or token.
+#[derive(Debug)]
+pub(crate) struct AuditEvent<'a> {
+    pub actor: AuditActor,
+    pub action: &'static str,
+    pub subject: &'a str,
+    pub reason: &'a str,
+    pub operator: Option<&'a str>,
+    /// Authenticated internal key id, when keys are configured.
+    pub key_id: Option<&'a str>,
+    pub outcome: &'static str,
+}
+
+impl AuditEvent<'_> {
+    pub(crate) fn emit(&self) {
+        info!(
+            target: "audit",
+            actor = self.actor.as_str(),
+            action = self.action,
+            subject = self.subject,
+            reason = self.reason,
+            operator = self.operator.unwrap_or("unknown"),
+            key_id = self.key_id.unwrap_or("none"),
+            outcome = self.outcome,
+            "audited action"
+        );
+    }
+
+    /// Emits the event and appends it to the audit log. The action has
+    /// already happened, so a failed write is logged and counted in
+    /// `api_audit_write_errors_total` rather than failing the request.
+    /// Read-only replicas only emit.
+    pub(crate) async fn record(&self, state: &AppState) {
+        self.emit();
+        if state.storage().is_read_only() {
+            return;
+        }
+        let event = NewAuditEvent {
+            occurred_at: Utc::now(),
+            actor: self.actor,
+            action: self.action.to_string(),
+            subject_hash: audit_subject_hash(self.subject),
+            outcome: self.outcome.to_string(),
+            reason: Some(self.reason)
+                .filter(|reason| !reason.is_empty())
+                .map(str::to_string),
+            operator: self.operator.map(str::to_string),
+            key_id: self.key_id.map(str::to_string),
+        };
+        if let Err(err) = state.storage().record_audit_event(event).await {
+            warn!(error = %err, action = self.action, "failed to persist audit event");
+            counter!("api_audit_write_errors_total").increment(1);
+        }
+    }
+}
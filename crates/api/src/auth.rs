@@ -0,0 +1,287 @@
+//! Authentication and role checks for the internal listener.
+//!
+//! Keys come from `API_INTERNAL_KEYS` and can be presented two ways:
+//! - as a static bearer token (`Authorization: Bearer <secret>`), or
+//! - as an HMAC-SHA3-256 request signature (`X-Anon-Key-Id`,
+//!   `X-Anon-Timestamp`, `X-Anon-Signature`; see
+//!   `anon_ticket_domain::services::signing`), so the secret never crosses
+//!   the wire. Signed requests
+//!   must fall inside the replay window and each signature is accepted once.
+//!
+//! The gRPC listener (feature `grpc`) accepts the bearer form only, sent as
+//! `authorization` metadata.
+//!
+//! When no keys are configured the internal listener keeps relying on network
+//! isolation and every caller is treated as an anonymous admin, matching the
+//! behaviour before keys existed.
+
+use std::future::{ready, Ready};
+use std::time::Duration;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web, FromRequest, HttpMessage, HttpRequest,
+};
+use anon_ticket_domain::config::{InternalApiKey, InternalRole};
+use anon_ticket_domain::services::signing::verify_request_signature;
+use chrono::Utc;
+use metrics::counter;
+use moka::sync::Cache;
+use sha3::{Digest, Sha3_256};
+
+use crate::handlers::ApiError;
+use crate::state::AppState;
+
+pub const KEY_ID_HEADER: &str = "x-anon-key-id";
+pub const TIMESTAMP_HEADER: &str = "x-anon-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-anon-signature";
+
+/// Default tolerated clock skew for signed requests.
+pub const DEFAULT_SIGNATURE_WINDOW: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+struct KeyEntry {
+    id: String,
+    role: InternalRole,
+    secret: Vec<u8>,
+    secret_hash: [u8; 32],
+}
+
+/// Registry of accepted internal keys plus the replay guard for signatures.
+#[derive(Debug)]
+pub struct InternalAuth {
+    keys: Vec<KeyEntry>,
+    signature_window: Duration,
+    seen_signatures: Cache<Vec<u8>, ()>,
+}
+
+impl InternalAuth {
+    /// Returns `None` when no keys are configured.
+    pub fn from_keys(keys: &[InternalApiKey]) -> Option<Self> {
+        if keys.is_empty() {
+            return None;
+        }
+        Some(Self {
+            keys: keys
+                .iter()
+                .map(|key| KeyEntry {
+                    id: key.id().to_string(),
+                    role: key.role(),
+                    secret: key.secret().as_bytes().to_vec(),
+                    secret_hash: hash_secret(key.secret()),
+                })
+                .collect(),
+            signature_window: DEFAULT_SIGNATURE_WINDOW,
+            seen_signatures: replay_cache(DEFAULT_SIGNATURE_WINDOW),
+        })
+    }
+
+    pub fn with_signature_window(mut self, window: Duration) -> Self {
+        self.signature_window = window;
+        self.seen_signatures = replay_cache(window);
+        self
+    }
+
+    /// Bearer tokens are compared by digest so lookups do not short-circuit
+    /// on the raw secret bytes.
+    fn authenticate_bearer(&self, presented: &str) -> Option<&KeyEntry> {
+        let digest = hash_secret(presented);
+        self.keys.iter().find(|entry| entry.secret_hash == digest)
+    }
+
+    fn authenticate_signature(
+        &self,
+        request: &SignedRequest<'_>,
+        now: i64,
+    ) -> Result<&KeyEntry, &'static str> {
+        let entry = self
+            .keys
+            .iter()
+            .find(|entry| entry.id == request.key_id)
+            .ok_or("unknown_key")?;
+        let skew = now.abs_diff(request.timestamp);
+        if skew > self.signature_window.as_secs() {
+            return Err("stale");
+        }
+        let signature = hex::decode(request.signature).map_err(|_| "malformed")?;
+        if !verify_request_signature(
+            &entry.secret,
+            request.timestamp,
+            request.method,
+            request.path_and_query,
+            request.body,
+            &signature,
+        ) {
+            return Err("invalid");
+        }
+        // Checked and recorded in one step, so of two concurrent requests
+        // carrying the same signature only one gets through.
+        if !self
+            .seen_signatures
+            .entry(signature)
+            .or_insert(())
+            .is_fresh()
+        {
+            return Err("replay");
+        }
+        Ok(entry)
+    }
+}
+
+fn replay_cache(window: Duration) -> Cache<Vec<u8>, ()> {
+    // Signatures older than the window are rejected as stale anyway, so only
+    // the last 2x window (past and future skew) needs remembering.
+    Cache::builder().time_to_live(window * 2).build()
+}
+
+fn hash_secret(secret: &str) -> [u8; 32] {
+    Sha3_256::digest(secret.as_bytes()).into()
+}
+
+struct SignedRequest<'a> {
+    key_id: &'a str,
+    timestamp: i64,
+    signature: &'a str,
+    method: &'a str,
+    path_and_query: &'a str,
+    body: &'a [u8],
+}
+
+/// Middleware for the internal listener: verifies signed requests and stores
+/// the resulting [`Caller`] for handlers. Unsigned requests pass through to
+/// the bearer check in the `Caller` extractor.
+pub async fn verify_signed_request(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if !req.headers().contains_key(SIGNATURE_HEADER) {
+        return next.call(req).await;
+    }
+    let Some(state) = req.app_data::<web::Data<AppState>>().cloned() else {
+        return Err(ApiError::Unauthorized.into());
+    };
+    let Some(auth) = state.internal_auth() else {
+        return Err(auth_failure("not_configured").into());
+    };
+
+    let header_str = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    };
+    let (Some(key_id), Some(timestamp), Some(signature)) = (
+        header_str(KEY_ID_HEADER),
+        header_str(TIMESTAMP_HEADER).and_then(|raw| raw.parse::<i64>().ok()),
+        header_str(SIGNATURE_HEADER),
+    ) else {
+        return Err(auth_failure("malformed").into());
+    };
+    let method = req.method().as_str().to_owned();
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str().to_owned())
+        .unwrap_or_else(|| req.path().to_owned());
+
+    let body = req.extract::<web::Bytes>().await?;
+    let signed = SignedRequest {
+        key_id: &key_id,
+        timestamp,
+        signature: &signature,
+        method: &method,
+        path_and_query: &path_and_query,
+        body: &body,
+    };
+    let entry = auth
+        .authenticate_signature(&signed, Utc::now().timestamp())
+        .map_err(auth_failure)?;
+    let caller = Caller {
+        key_id: Some(entry.id.clone()),
+        role: entry.role,
+    };
+
+    req.set_payload(Payload::from(body));
+    req.extensions_mut().insert(caller);
+    next.call(req).await
+}
+
+fn auth_failure(reason: &'static str) -> ApiError {
+    counter!("api_internal_auth_failures_total", "reason" => reason).increment(1);
+    ApiError::Unauthorized
+}
+
+/// The authenticated principal behind an internal request.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    key_id: Option<String>,
+    role: InternalRole,
+}
+
+impl Caller {
+    /// Key id recorded in audit events; `None` when keys are not configured.
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    pub fn require(&self, role: InternalRole) -> Result<(), ApiError> {
+        if self.role >= role {
+            return Ok(());
+        }
+        counter!("api_internal_auth_failures_total", "reason" => "forbidden").increment(1);
+        Err(ApiError::Forbidden(role))
+    }
+}
+
+impl FromRequest for Caller {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(resolve_caller(req))
+    }
+}
+
+fn resolve_caller(req: &HttpRequest) -> Result<Caller, ApiError> {
+    if let Some(signed) = req.extensions().get::<Caller>() {
+        return Ok(signed.clone());
+    }
+    let auth = req
+        .app_data::<web::Data<AppState>>()
+        .and_then(|state| state.internal_auth());
+    let authorization = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    bearer_caller(auth, authorization)
+}
+
+/// Resolves an `Authorization` value to a caller. Shared with the gRPC
+/// listener, which only accepts bearer keys.
+pub(crate) fn bearer_caller(
+    auth: Option<&InternalAuth>,
+    authorization: Option<&str>,
+) -> Result<Caller, ApiError> {
+    let Some(auth) = auth else {
+        return Ok(Caller {
+            key_id: None,
+            role: InternalRole::Admin,
+        });
+    };
+    let presented = authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    let Some(presented) = presented else {
+        return Err(auth_failure("missing"));
+    };
+    match auth.authenticate_bearer(presented) {
+        Some(entry) => Ok(Caller {
+            key_id: Some(entry.id.clone()),
+            role: entry.role,
+        }),
+        None => Err(auth_failure("invalid")),
+    }
+}
@@ -0,0 +1,64 @@
+//! Compile-time build metadata, published in `/api/v1/info`,
+//! `/internal/config`, and the `anon_ticket_build_info` gauge so behavior
+//! changes can be lined up with deploys.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use metrics::gauge;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    /// Short commit hash, or `unknown` when built outside a checkout.
+    pub git_sha: String,
+    pub built_at: DateTime<Utc>,
+    /// `debug` or `release`.
+    pub profile: String,
+    /// Versions of the workspace crates linked into this binary.
+    pub crates: BTreeMap<String, String>,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        let built_at = env!("ANON_TICKET_BUILT_AT")
+            .parse()
+            .ok()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .unwrap_or_default();
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: env!("ANON_TICKET_GIT_SHA").to_string(),
+            built_at,
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            }
+            .to_string(),
+            crates: [
+                ("anon_ticket_api", env!("CARGO_PKG_VERSION")),
+                ("anon_ticket_domain", anon_ticket_domain::VERSION),
+                ("anon_ticket_monitor", anon_ticket_monitor::VERSION),
+                ("anon_ticket_storage", anon_ticket_storage::VERSION),
+            ]
+            .into_iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect(),
+        }
+    }
+
+    /// Sets `anon_ticket_build_info` to 1 with the build as labels, the usual
+    /// way to join build metadata onto other series.
+    pub fn record(&self) {
+        gauge!(
+            "anon_ticket_build_info",
+            "version" => self.version.clone(),
+            "git_sha" => self.git_sha.clone(),
+            "built_at" => self.built_at.to_rfc3339(),
+            "profile" => self.profile.clone()
+        )
+        .set(1.0);
+    }
+}
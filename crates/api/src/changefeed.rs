@@ -0,0 +1,75 @@
+//! Applies changes published by other instances to this replica's PID cache
+//! and Bloom filter, so a payment ingested next to another replica is
+//! redeemable here without a restart. Only Postgres deployments have a feed;
+//! see `anon_ticket_storage::ChangeListener`.
+
+use std::time::Duration;
+
+use anon_ticket_domain::services::blocking::run_blocking;
+use anon_ticket_domain::PidCache;
+use anon_ticket_storage::{Change, ChangeListener};
+use metrics::counter;
+use tracing::{info, warn};
+
+use crate::state::AppState;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+pub async fn follow_changes(state: AppState, mut listener: ChangeListener) {
+    loop {
+        match listener.recv().await {
+            Ok(change) => apply_change(&state, change).await,
+            Err(err) => {
+                warn!(error = %err, "change listener failed; reconnecting");
+                counter!("api_changefeed_events_total", "kind" => "error").increment(1);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+pub async fn apply_change(state: &AppState, change: Change) {
+    let kind = match change {
+        Change::PaymentInserted(pid) => {
+            state.cache().mark_present(&pid);
+            if let Some(bloom) = state.bloom() {
+                bloom.insert(&pid);
+            }
+            "payment_inserted"
+        }
+        // Token state is read from storage on every request, so no replica
+        // holds anything a revocation could leave stale.
+        Change::TokenRevoked(_) => "token_revoked",
+        Change::Gap => {
+            refill_bloom(state).await;
+            "gap"
+        }
+    };
+    counter!("api_changefeed_events_total", "kind" => kind).increment(1);
+}
+
+/// Payments announced while the listener was disconnected are unknown, so
+/// every persisted PID is hashed in again.
+async fn refill_bloom(state: &AppState) {
+    if state.bloom().is_none() {
+        return;
+    }
+    let rows = match state.storage().payment_heights(None).await {
+        Ok(rows) => rows,
+        Err(err) => {
+            warn!(error = %err, "failed to reload payments after a change feed gap");
+            return;
+        }
+    };
+    let count = rows.len();
+    let target = state.clone();
+    run_blocking("bloom_rebuild", move || {
+        if let Some(bloom) = target.bloom() {
+            for (pid, _) in &rows {
+                bloom.insert(pid);
+            }
+        }
+    })
+    .await;
+    info!(count, "refilled bloom filter after a change feed gap");
+}
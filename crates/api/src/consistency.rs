@@ -0,0 +1,74 @@
+//! Background audit comparing the PID cache and Bloom filter to storage.
+//!
+//! Both are fed by hooks (monitor ingest, redeem, admin tools). If a hook is
+//! skipped — e.g. the monitor task panicked between persisting and notifying —
+//! nothing fails loudly; redemptions just start returning 404. The audit
+//! samples both sides and counts disagreements in
+//! `api_pid_divergence_total{kind}` so that drift shows up on a dashboard.
+
+use std::time::Duration;
+
+use anon_ticket_domain::storage::{PaymentStore, StorageResult};
+use metrics::{counter, gauge};
+use tracing::warn;
+
+use crate::state::AppState;
+
+/// Disagreements found by one audit pass.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DivergenceReport {
+    /// Persisted PIDs the Bloom filter reports as absent. Redeem would 404
+    /// these without looking at storage.
+    pub bloom_missing: u64,
+    /// Cached PIDs that no longer exist in storage (e.g. purged).
+    pub cache_stale: u64,
+}
+
+/// Runs one audit over up to `sample_size` recent payments and cache entries.
+pub async fn audit_once(state: &AppState, sample_size: u64) -> StorageResult<DivergenceReport> {
+    let mut report = DivergenceReport::default();
+
+    if let Some(bloom) = state.bloom() {
+        for pid in state.storage().recent_payment_ids(sample_size).await? {
+            if !bloom.might_contain(&pid) {
+                warn!(pid = %pid.to_hex(), "persisted pid missing from bloom filter");
+                report.bloom_missing += 1;
+            }
+        }
+    }
+
+    let cached = state
+        .cache()
+        .sample(usize::try_from(sample_size).unwrap_or(usize::MAX));
+    for pid in cached {
+        if state.storage().find_payment(&pid).await?.is_none() {
+            warn!(pid = %pid.to_hex(), "cached pid missing from storage");
+            report.cache_stale += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+pub async fn audit_periodically(state: AppState, every: Duration, sample_size: u64) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        match audit_once(&state, sample_size).await {
+            Ok(report) => {
+                counter!("api_pid_audit_runs_total", "status" => "ok").increment(1);
+                for (kind, count) in [
+                    ("bloom_missing", report.bloom_missing),
+                    ("cache_stale", report.cache_stale),
+                ] {
+                    counter!("api_pid_divergence_total", "kind" => kind).increment(count);
+                    gauge!("api_pid_divergence_last", "kind" => kind).set(count as f64);
+                }
+            }
+            Err(err) => {
+                counter!("api_pid_audit_runs_total", "status" => "error").increment(1);
+                warn!(?err, "pid divergence audit failed");
+            }
+        }
+    }
+}
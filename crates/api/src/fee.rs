@@ -0,0 +1,42 @@
+//! Cached passthrough of the daemon fee estimate behind
+//! `GET /api/v1/fee-estimate`.
+//!
+//! Estimates move slowly, so one daemon call serves every request inside the
+//! TTL. Concurrent misses queue on the lock instead of each reaching
+//! `monerod`, which keeps the public endpoint from amplifying load.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anon_ticket_monitor::{FeeEstimate, MonitorError, TransferSource};
+use tokio::sync::Mutex;
+
+pub const DEFAULT_FEE_ESTIMATE_TTL: Duration = Duration::from_secs(60);
+
+pub struct FeeEstimator {
+    source: Arc<dyn TransferSource>,
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, FeeEstimate)>>,
+}
+
+impl FeeEstimator {
+    pub fn new(source: Arc<dyn TransferSource>) -> Self {
+        Self {
+            source,
+            ttl: DEFAULT_FEE_ESTIMATE_TTL,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub async fn estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        let mut cached = self.cached.lock().await;
+        if let Some((fetched_at, estimate)) = cached.as_ref() {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(estimate.clone());
+            }
+        }
+        let estimate = self.source.fee_estimate().await?;
+        *cached = Some((Instant::now(), estimate.clone()));
+        Ok(estimate)
+    }
+}
@@ -0,0 +1,329 @@
+//! Internal gRPC API for gRPC-native backends, served on
+//! `API_INTERNAL_GRPC_ADDRESS` next to the internal HTTP listener.
+//!
+//! The RPCs reuse the HTTP handlers' logic, roles, and [`AppState`]; only the
+//! framing differs. `proto/internal.proto` is the client contract, and the
+//! message types below mirror it field for field. The standard
+//! `grpc.health.v1.Health` service is served alongside and follows the same
+//! readiness as `/readyz`.
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::model::{PaymentId, PaymentState as StoredPaymentState};
+use anon_ticket_domain::storage::{PaymentStore, TokenStore};
+use anon_ticket_domain::HasErrorCode;
+use metrics::counter;
+use tonic::server::NamedService;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+use tonic_health::server::{health_reporter, HealthReporter};
+use tonic_health::ServingStatus;
+
+use crate::auth::{bearer_caller, Caller};
+use crate::handlers::token::{
+    self, observe_first_validation, resolve_token, revoke_token, status_response, RevokeRequest,
+    TokenStatusResponse,
+};
+use crate::handlers::ApiError;
+use crate::health::readiness;
+use crate::state::AppState;
+
+include!(concat!(
+    env!("OUT_DIR"),
+    "/anon_ticket.internal.v1.Internal.rs"
+));
+
+pub use internal_server::{Internal, InternalServer};
+
+/// Metadata key carrying the stable [`ErrorCode`](anon_ticket_domain::ErrorCode)
+/// string on failed calls.
+pub const ERROR_CODE_METADATA: &str = "x-error-code";
+
+/// How often readiness is re-evaluated for the health service; draining is
+/// reported immediately.
+const HEALTH_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct VerifyTokenRequest {
+    #[prost(string, tag = "1")]
+    pub token: String,
+    #[prost(string, optional, tag = "2")]
+    pub passphrase: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RevokeTokenRequest {
+    #[prost(string, tag = "1")]
+    pub token: String,
+    #[prost(string, optional, tag = "2")]
+    pub reason: Option<String>,
+    #[prost(int32, optional, tag = "3")]
+    pub abuse_score: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum TokenState {
+    Unspecified = 0,
+    Active = 1,
+    Revoked = 2,
+    Expired = 3,
+    Suspended = 4,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TokenStatus {
+    #[prost(enumeration = "TokenState", tag = "1")]
+    pub state: i32,
+    #[prost(int64, tag = "2")]
+    pub amount: i64,
+    #[prost(int64, tag = "3")]
+    pub issued_at: i64,
+    #[prost(int64, optional, tag = "4")]
+    pub revoked_at: Option<i64>,
+    #[prost(int32, tag = "5")]
+    pub abuse_score: i32,
+    #[prost(int64, optional, tag = "6")]
+    pub expires_at: Option<i64>,
+    #[prost(string, optional, tag = "7")]
+    pub review_reason: Option<String>,
+    #[prost(string, optional, tag = "8")]
+    pub tier: Option<String>,
+    #[prost(uint64, optional, tag = "9")]
+    pub quota: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GetPaymentStatusRequest {
+    #[prost(string, tag = "1")]
+    pub pid: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum PaymentState {
+    Unspecified = 0,
+    Unclaimed = 1,
+    Claimed = 2,
+    Expired = 3,
+    Reorged = 4,
+    Refunded = 5,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PaymentStatus {
+    #[prost(enumeration = "PaymentState", tag = "1")]
+    pub state: i32,
+    #[prost(int64, tag = "2")]
+    pub amount: i64,
+    #[prost(int64, tag = "3")]
+    pub block_height: i64,
+    #[prost(int64, tag = "4")]
+    pub detected_at: i64,
+    #[prost(int64, optional, tag = "5")]
+    pub claimed_at: Option<i64>,
+}
+
+impl From<TokenStatusResponse> for TokenStatus {
+    fn from(body: TokenStatusResponse) -> Self {
+        let state = match body.status {
+            token::TokenState::Active => TokenState::Active,
+            token::TokenState::Revoked => TokenState::Revoked,
+            token::TokenState::Expired => TokenState::Expired,
+            token::TokenState::Suspended => TokenState::Suspended,
+        };
+        Self {
+            state: state.into(),
+            amount: body.amount,
+            issued_at: body.issued_at.timestamp(),
+            revoked_at: body.revoked_at.map(|at| at.timestamp()),
+            abuse_score: body.abuse_score.into(),
+            expires_at: body.expires_at.map(|at| at.timestamp()),
+            review_reason: body.review_reason,
+            tier: body.tier,
+            quota: body.quota,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InternalService {
+    state: AppState,
+}
+
+impl InternalService {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    fn caller<T>(&self, request: &Request<T>) -> Result<Caller, ApiError> {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        bearer_caller(self.state.internal_auth(), authorization)
+    }
+
+    async fn verify(&self, request: Request<VerifyTokenRequest>) -> Result<TokenStatus, ApiError> {
+        self.caller(&request)?.require(InternalRole::ReadOnly)?;
+        let request = request.into_inner();
+        let token = resolve_token(&request.token, request.passphrase.as_deref())?;
+        let record = self
+            .state
+            .storage()
+            .find_token(&token)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        observe_first_validation(&self.state, &record).await;
+        Ok(status_response(&self.state, record).await?.into())
+    }
+
+    async fn revoke(&self, request: Request<RevokeTokenRequest>) -> Result<TokenStatus, ApiError> {
+        let caller = self.caller(&request)?;
+        let request = request.into_inner();
+        let abuse_score = request
+            .abuse_score
+            .map(i16::try_from)
+            .transpose()
+            .map_err(|_| ApiError::InvalidRequest("abuse_score out of range".into()))?;
+        let payload = RevokeRequest {
+            reason: request.reason,
+            abuse_score,
+        };
+        Ok(revoke_token(&self.state, &request.token, payload, &caller)
+            .await?
+            .into())
+    }
+
+    async fn payment_status(
+        &self,
+        request: Request<GetPaymentStatusRequest>,
+    ) -> Result<PaymentStatus, ApiError> {
+        self.caller(&request)?.require(InternalRole::ReadOnly)?;
+        let pid = PaymentId::parse(&request.into_inner().pid)?;
+        let record = self
+            .state
+            .storage()
+            .find_payment(&pid)
+            .await?
+            .ok_or(ApiError::NotFound)?;
+        let state = match record.status {
+            StoredPaymentState::Detected
+            | StoredPaymentState::Pending
+            | StoredPaymentState::Confirmed => PaymentState::Unclaimed,
+            StoredPaymentState::Claimed => PaymentState::Claimed,
+            StoredPaymentState::Expired => PaymentState::Expired,
+            StoredPaymentState::Reorged => PaymentState::Reorged,
+            StoredPaymentState::Refunded => PaymentState::Refunded,
+        };
+        Ok(PaymentStatus {
+            state: state.into(),
+            amount: record.amount,
+            block_height: record.block_height,
+            detected_at: record.created_at.timestamp(),
+            claimed_at: record.claimed_at.map(|at| at.timestamp()),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Internal for InternalService {
+    async fn verify_token(
+        &self,
+        request: Request<VerifyTokenRequest>,
+    ) -> Result<Response<TokenStatus>, Status> {
+        respond("verify_token", self.verify(request).await)
+    }
+
+    async fn revoke_token(
+        &self,
+        request: Request<RevokeTokenRequest>,
+    ) -> Result<Response<TokenStatus>, Status> {
+        respond("revoke_token", self.revoke(request).await)
+    }
+
+    async fn get_payment_status(
+        &self,
+        request: Request<GetPaymentStatusRequest>,
+    ) -> Result<Response<PaymentStatus>, Status> {
+        respond("get_payment_status", self.payment_status(request).await)
+    }
+}
+
+// The error type is fixed by the generated service trait.
+#[allow(clippy::result_large_err)]
+fn respond<T>(method: &'static str, result: Result<T, ApiError>) -> Result<Response<T>, Status> {
+    let code = match &result {
+        Ok(_) => "ok",
+        Err(err) => err.code().as_str(),
+    };
+    counter!("api_grpc_requests_total", "method" => method, "code" => code).increment(1);
+    result.map(Response::new).map_err(to_status)
+}
+
+fn to_status(err: ApiError) -> Status {
+    let code = err.code();
+    let mut status = Status::new(tonic::Code::from(code.grpc_code()), err.to_string());
+    status.metadata_mut().insert(
+        ERROR_CODE_METADATA,
+        code.as_str().parse().expect("ascii code"),
+    );
+    status
+}
+
+/// Binds `addr` eagerly so a bad address fails startup, then returns the
+/// server future, which completes once `shutdown` resolves and in-flight
+/// calls drain.
+pub fn serve(
+    state: AppState,
+    addr: SocketAddr,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> std::io::Result<impl Future<Output = Result<(), tonic::transport::Error>>> {
+    let incoming = TcpIncoming::new(addr, true, None).map_err(std::io::Error::other)?;
+    let (reporter, health) = health_reporter();
+    let server = tonic::transport::Server::builder()
+        .add_service(health)
+        .add_service(InternalServer::new(InternalService::new(state.clone())))
+        .serve_with_incoming_shutdown(incoming, shutdown);
+    Ok(async move {
+        let updater = tokio::spawn(report_health(state, reporter));
+        let result = server.await;
+        updater.abort();
+        result
+    })
+}
+
+/// Mirrors [`readiness`] into the overall (`""`) and `Internal` statuses.
+/// Statuses are only written on change so `Watch` streams see transitions,
+/// and the loop ends once draining has been reported.
+pub(crate) async fn report_health(state: AppState, mut reporter: HealthReporter) {
+    let mut current = None;
+    loop {
+        let ready = readiness(&state).await.ready;
+        if current != Some(ready) {
+            let status = if ready {
+                ServingStatus::Serving
+            } else {
+                ServingStatus::NotServing
+            };
+            reporter.set_service_status("", status).await;
+            reporter
+                .set_service_status(
+                    <InternalServer<InternalService> as NamedService>::NAME,
+                    status,
+                )
+                .await;
+            current = Some(ready);
+        }
+        if state.health().is_draining() {
+            return;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(HEALTH_REFRESH_INTERVAL) => {}
+            _ = state.health().changed() => {}
+        }
+    }
+}
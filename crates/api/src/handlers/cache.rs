@@ -0,0 +1,85 @@
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::model::PaymentId;
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::ApiError;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheStatsResponse {
+    pub entries: u64,
+    pub capacity: u64,
+    pub ttl_secs: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub bloom_enabled: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CacheFlushQuery {
+    pub pid: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheFlushResponse {
+    /// `pid` when scoped to a single payment id, otherwise `all`.
+    pub scope: String,
+    pub pid: Option<String>,
+    pub flushed: u64,
+}
+
+pub async fn cache_stats_handler(
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::ReadOnly)?;
+    let stats = state.cache().stats();
+    Ok(HttpResponse::Ok().json(CacheStatsResponse {
+        entries: stats.entries,
+        capacity: stats.capacity,
+        ttl_secs: stats.ttl.as_secs(),
+        hits: stats.hits,
+        misses: stats.misses,
+        bloom_enabled: state.bloom().is_some(),
+    }))
+}
+
+/// Flushes the PID cache, optionally scoped with `?pid=`. The bloom filter is
+/// append-only and is left untouched; it only ever yields false positives.
+pub async fn cache_flush_handler(
+    state: web::Data<AppState>,
+    query: web::Query<CacheFlushQuery>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let response = match query.into_inner().pid {
+        Some(raw) => {
+            let pid = PaymentId::parse(&raw)?;
+            let flushed = u64::from(state.cache().invalidate(&pid));
+            CacheFlushResponse {
+                scope: "pid".to_string(),
+                pid: Some(pid.into_inner()),
+                flushed,
+            }
+        }
+        None => CacheFlushResponse {
+            scope: "all".to_string(),
+            pid: None,
+            flushed: state.cache().invalidate_all(),
+        },
+    };
+    counter!("api_cache_flush_total", "scope" => response.scope.clone()).increment(1);
+    info!(
+        scope = %response.scope,
+        pid = response.pid.as_deref(),
+        flushed = response.flushed,
+        key_id = caller.key_id().unwrap_or("none"),
+        "pid cache flushed"
+    );
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::CheckoutPreset;
+use anon_ticket_domain::model::{
+    generate_client_secret, hash_client_secret, CheckoutTerms, NewCheckoutBinding, PaymentId,
+};
+use anon_ticket_domain::storage::{CheckoutStore, PaymentStore};
+use chrono::{DateTime, Duration, Utc};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::state::AppState;
+
+use super::ApiError;
+
+/// Attempts before giving up on finding an unused PID; collisions on 64
+/// random bits are not expected in practice.
+pub(crate) const MAX_PID_ATTEMPTS: usize = 3;
+
+/// Optional body; an empty body is a plain checkout without terms.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CheckoutRequest {
+    /// Name of a configured preset (`API_CHECKOUT_PRESETS`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckoutResponse {
+    pub pid: String,
+    /// Returned once; only its hash is stored. Must accompany the redeem call.
+    pub client_secret: String,
+    /// Subaddress to pay, in subaddress detection mode. Payments to any other
+    /// address are not attributed to this PID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<CheckoutPresetResponse>,
+}
+
+/// The preset as resolved for this checkout, so clients never carry pricing
+/// logic themselves.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CheckoutPresetResponse {
+    pub name: String,
+    /// Minimum payment in atomic units.
+    pub amount: i64,
+    /// Open-amount preset: any payment at or above `amount` is accepted and
+    /// the tier follows `tiers`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub donation: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    /// Tier ladder, lowest threshold first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiers: Vec<CheckoutTierResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Seconds a checkout under this preset stays payable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry_secs: Option<u64>,
+    /// Payments detected after this instant are not accepted for the PID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CheckoutTierResponse {
+    pub tier: String,
+    pub min_amount: i64,
+}
+
+pub async fn checkout_handler(
+    state: web::Data<AppState>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ApiError> {
+    if !state.checkout_enabled() {
+        return Err(ApiError::NotFound);
+    }
+    let request: CheckoutRequest = if body.is_empty() {
+        CheckoutRequest::default()
+    } else {
+        serde_json::from_slice(&body)
+            .map_err(|err| ApiError::InvalidRequest(format!("invalid checkout body: {err}")))?
+    };
+    let preset = match request.preset.as_deref() {
+        Some(name) => Some(state.checkout_preset(name).cloned().ok_or_else(|| {
+            counter!("api_checkout_requests_total", "status" => "unknown_preset").increment(1);
+            ApiError::InvalidRequest(format!("unknown checkout preset `{name}`"))
+        })?),
+        None => None,
+    };
+    let now = Utc::now();
+    let terms = preset.as_ref().map(|preset| terms_for(preset, now));
+
+    for _ in 0..MAX_PID_ATTEMPTS {
+        let pid = PaymentId::generate().map_err(random_failure)?;
+        if state.storage().find_payment(&pid).await?.is_some() {
+            continue;
+        }
+        let client_secret = generate_client_secret().map_err(random_failure)?;
+        let inserted = state
+            .storage()
+            .insert_checkout(NewCheckoutBinding {
+                secret_hash: hash_client_secret(&pid, &client_secret),
+                pid: pid.clone(),
+                created_at: now,
+                terms: terms.clone(),
+            })
+            .await?;
+        if inserted {
+            let address = match state.subaddresses() {
+                Some(source) => Some(
+                    source
+                        .create_subaddress(&pid)
+                        .await
+                        .map_err(|err| {
+                            counter!("api_checkout_requests_total", "status" => "subaddress_failed")
+                                .increment(1);
+                            ApiError::Rpc(err.to_string())
+                        })?
+                        .address,
+                ),
+                None => None,
+            };
+            counter!("api_checkout_requests_total", "status" => "created").increment(1);
+            return Ok(HttpResponse::Created().json(CheckoutResponse {
+                pid: pid.into_inner(),
+                client_secret,
+                address,
+                preset: preset.as_ref().map(|preset| {
+                    preset_response(preset, terms.as_ref().and_then(|terms| terms.expires_at))
+                }),
+            }));
+        }
+    }
+
+    counter!("api_checkout_requests_total", "status" => "pid_exhausted").increment(1);
+    Err(ApiError::Conflict(
+        "could not allocate an unused payment id".into(),
+    ))
+}
+
+pub(crate) fn preset_response(
+    preset: &CheckoutPreset,
+    expires_at: Option<DateTime<Utc>>,
+) -> CheckoutPresetResponse {
+    CheckoutPresetResponse {
+        name: preset.name().to_string(),
+        amount: preset.amount(),
+        donation: preset.is_donation(),
+        tier: preset.tier().map(str::to_string),
+        tiers: preset
+            .tier_thresholds()
+            .iter()
+            .map(|(tier, min_amount)| CheckoutTierResponse {
+                tier: tier.clone(),
+                min_amount: *min_amount,
+            })
+            .collect(),
+        scope: preset.scope().map(str::to_string),
+        expiry_secs: preset.expiry_secs(),
+        expires_at,
+        metadata: preset.metadata().clone(),
+    }
+}
+
+/// Checks the client secret for PIDs issued through checkout. PIDs without a
+/// binding are redeemable by PID alone.
+pub(crate) async fn verify_client_secret(
+    state: &AppState,
+    pid: &PaymentId,
+    client_secret: Option<&str>,
+) -> Result<bool, ApiError> {
+    let Some(expected) = state.storage().find_checkout_secret_hash(pid).await? else {
+        return Ok(true);
+    };
+    Ok(client_secret.is_some_and(|secret| hash_client_secret(pid, secret) == expected))
+}
+
+fn terms_for(preset: &CheckoutPreset, now: DateTime<Utc>) -> CheckoutTerms {
+    CheckoutTerms {
+        preset: preset.name().to_string(),
+        expected_amount: preset.amount(),
+        expires_at: preset
+            .expiry_secs()
+            .and_then(|secs| i64::try_from(secs).ok())
+            .and_then(Duration::try_seconds)
+            .map(|expiry| now + expiry),
+    }
+}
+
+/// Rejects payments that do not meet the terms captured at checkout. PIDs
+/// without terms (or not yet paid) pass through to the normal redeem path.
+pub(crate) async fn check_checkout_terms(
+    state: &AppState,
+    pid: &PaymentId,
+    terms: Option<&CheckoutTerms>,
+) -> Result<Option<&'static str>, ApiError> {
+    let Some(terms) = terms else {
+        return Ok(None);
+    };
+    let Some(payment) = state.storage().find_payment(pid).await? else {
+        return Ok(None);
+    };
+    if payment.amount < terms.expected_amount {
+        return Ok(Some("underpaid"));
+    }
+    if terms
+        .expires_at
+        .is_some_and(|expires_at| payment.created_at > expires_at)
+    {
+        return Ok(Some("checkout_expired"));
+    }
+    Ok(None)
+}
+
+pub(crate) fn random_failure(err: impl std::fmt::Display) -> ApiError {
+    ApiError::Internal(format!("random generation failed: {err}"))
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::services::cache::PidBloom;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Caller;
+use crate::build_info::BuildInfo;
+use crate::state::AppState;
+
+use super::ApiError;
+
+/// Cargo features this binary may be built with; see `Cargo.toml`.
+const FEATURES: [(&str, bool); 6] = [
+    ("fault-injection", cfg!(feature = "fault-injection")),
+    ("grpc", cfg!(feature = "grpc")),
+    ("metrics", cfg!(feature = "metrics")),
+    ("otlp", cfg!(feature = "otlp")),
+    ("redis-cache", cfg!(feature = "redis-cache")),
+    ("runtime-metrics", cfg!(feature = "runtime-metrics")),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RuntimeConfigResponse {
+    pub build: BuildInfo,
+    /// Enabled cargo features, sorted.
+    pub features: Vec<String>,
+    /// `None` when the filter is disabled (`API_PID_BLOOM_ENTRIES=0`).
+    pub bloom: Option<BloomParams>,
+    /// Redacted settings keyed by environment variable; unset options are
+    /// omitted.
+    pub api: BTreeMap<String, String>,
+    /// `None` when this process does not run the monitor.
+    pub monitor: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BloomParams {
+    pub expected_items: u64,
+    pub false_positive_rate: f64,
+    pub num_bits: u64,
+    pub num_hashes: u32,
+}
+
+impl From<&PidBloom> for BloomParams {
+    fn from(bloom: &PidBloom) -> Self {
+        Self {
+            expected_items: bloom.expected_items(),
+            false_positive_rate: bloom.false_positive_rate(),
+            num_bits: bloom.num_bits(),
+            num_hashes: bloom.num_hashes(),
+        }
+    }
+}
+
+/// Reports what this instance actually loaded so operators can check it
+/// against what they meant to deploy.
+pub async fn runtime_config_handler(
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::ReadOnly)?;
+    let Some(config) = state.effective_config() else {
+        return Err(ApiError::NotFound);
+    };
+    let owned = |env: &BTreeMap<&'static str, String>| {
+        env.iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    };
+    Ok(HttpResponse::Ok().json(RuntimeConfigResponse {
+        build: BuildInfo::current(),
+        features: FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        bloom: state.bloom().map(BloomParams::from),
+        api: owned(&config.api),
+        monitor: config.monitor.as_ref().map(owned),
+    }))
+}
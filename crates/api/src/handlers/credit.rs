@@ -0,0 +1,198 @@
+//! Overpayment credits: what happened to the excess of a quote paid above
+//! its expected amount.
+//!
+//! Redemption records one credit per token. Under the `credit` policy the
+//! excess is already part of the issued balance; under `refund` it is held
+//! so an operator can return it, and credited to the token once the grace
+//! period ends. Operators resolve holds on the internal listener.
+
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::{InternalRole, OverpaymentPolicy};
+use anon_ticket_domain::model::{
+    AuditActor, CreditStatus, NewTokenCredit, PaymentId, ServiceTokenRecord, TokenCredit,
+};
+use anon_ticket_domain::storage::{CreditStore, QuoteStore};
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::AuditEvent;
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::payment::require_reason;
+use super::ApiError;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditSummary {
+    pub id: i64,
+    pub pid: String,
+    /// Stored form of the token, as in the token listing.
+    pub token: String,
+    pub amount: i64,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub hold_until: Option<DateTime<Utc>>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+impl From<TokenCredit> for CreditSummary {
+    fn from(credit: TokenCredit) -> Self {
+        Self {
+            id: credit.id,
+            pid: credit.pid.to_hex(),
+            token: credit.token.to_hex(),
+            amount: credit.amount,
+            status: credit.status.as_str().to_string(),
+            created_at: credit.created_at,
+            hold_until: credit.hold_until,
+            resolved_at: credit.resolved_at,
+        }
+    }
+}
+
+/// Amount `received` for `pid` beyond its quote; `None` when quotes are
+/// disabled, the PID was not quoted, or it was not overpaid.
+pub(crate) async fn quote_excess(
+    state: &AppState,
+    pid: &PaymentId,
+    received: i64,
+) -> Result<Option<i64>, ApiError> {
+    if state.quote_ttl().is_none() {
+        return Ok(None);
+    }
+    Ok(state
+        .storage()
+        .find_quote(pid)
+        .await?
+        .map(|quote| received.saturating_sub(quote.expected_amount))
+        .filter(|excess| *excess > 0))
+}
+
+/// Records `excess` against the token just issued for an overpaid quote,
+/// credited or held according to the overpayment policy. A token that
+/// already has a credit keeps it.
+pub(crate) async fn record_overpayment(
+    state: &AppState,
+    token: &ServiceTokenRecord,
+    excess: i64,
+) -> Result<(), ApiError> {
+    let now = Utc::now();
+    let policy = state.overpayment_policy();
+    let (status, hold_until) = match policy {
+        OverpaymentPolicy::Credit => (CreditStatus::Credited, None),
+        OverpaymentPolicy::Refund => (
+            CreditStatus::Held,
+            Some(
+                chrono::Duration::from_std(state.refund_grace())
+                    .ok()
+                    .and_then(|grace| now.checked_add_signed(grace))
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            ),
+        ),
+    };
+    let recorded = state
+        .storage()
+        .record_credit(NewTokenCredit {
+            token: token.token.clone(),
+            pid: token.pid.clone(),
+            amount: excess,
+            status,
+            created_at: now,
+            hold_until,
+        })
+        .await?;
+    if recorded.is_some() {
+        counter!("api_overpayments_total", "policy" => policy.as_str()).increment(1);
+    }
+    Ok(())
+}
+
+/// Operator justification for resolving a held credit.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResolveCreditRequest {
+    pub reason: String,
+    pub operator: Option<String>,
+}
+
+/// `POST /internal/v1/credits/{id}/refund`: records that a held excess was
+/// returned to the payer. The transfer itself happens outside the service.
+pub async fn refund_credit_handler(
+    state: web::Data<AppState>,
+    path: web::Path<i64>,
+    payload: web::Json<ResolveCreditRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    resolve_credit(
+        &state,
+        path.into_inner(),
+        payload.into_inner(),
+        &caller,
+        CreditStatus::Refunded,
+    )
+    .await
+}
+
+/// `POST /internal/v1/credits/{id}/credit`: adds a held excess to the
+/// token's balance without waiting for the grace period.
+pub async fn apply_credit_handler(
+    state: web::Data<AppState>,
+    path: web::Path<i64>,
+    payload: web::Json<ResolveCreditRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    resolve_credit(
+        &state,
+        path.into_inner(),
+        payload.into_inner(),
+        &caller,
+        CreditStatus::Credited,
+    )
+    .await
+}
+
+async fn resolve_credit(
+    state: &AppState,
+    id: i64,
+    request: ResolveCreditRequest,
+    caller: &Caller,
+    status: CreditStatus,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Admin)?;
+    require_reason(&request.reason)?;
+    let (action, metric) = match status {
+        CreditStatus::Refunded => ("credit.refund", "credit_refund"),
+        _ => ("credit.apply", "credit_apply"),
+    };
+    let Some(existing) = state.storage().find_credit(id).await? else {
+        counter!("api_admin_actions_total", "action" => metric, "status" => "not_found")
+            .increment(1);
+        return Err(ApiError::NotFound);
+    };
+    let subject = existing.pid.to_hex();
+    let audit = |outcome| AuditEvent {
+        actor: AuditActor::Internal,
+        action,
+        subject: &subject,
+        reason: &request.reason,
+        operator: request.operator.as_deref(),
+        key_id: caller.key_id(),
+        outcome,
+    };
+    let Some(resolved) = state
+        .storage()
+        .resolve_credit(id, status, Utc::now())
+        .await?
+    else {
+        counter!("api_admin_actions_total", "action" => metric, "status" => "not_held")
+            .increment(1);
+        audit("not_held").record(state).await;
+        return Err(ApiError::Conflict(format!(
+            "credit {id} is no longer held for refund"
+        )));
+    };
+    counter!("api_admin_actions_total", "action" => metric, "status" => resolved.status.as_str())
+        .increment(1);
+    audit(resolved.status.as_str()).record(state).await;
+    Ok(HttpResponse::Ok().json(CreditSummary::from(resolved)))
+}
@@ -0,0 +1,185 @@
+use std::convert::Infallible;
+use std::time::Duration;
+
+use actix_web::{http::header, web, HttpResponse};
+use anon_ticket_domain::events::{PAYMENT_CLAIMABLE_V1, PAYMENT_CONFIRMED_V1, PAYMENT_DETECTED_V1};
+use anon_ticket_domain::model::{PaymentId, PaymentRecord, PaymentState, PaymentTransition};
+use anon_ticket_domain::storage::PaymentStore;
+use anon_ticket_monitor::{PaymentEvent, PaymentEventKind};
+use futures_util::stream;
+use metrics::{counter, gauge};
+use serde::Serialize;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio::time::{interval_at, Instant, Interval};
+use tracing::warn;
+
+use crate::state::AppState;
+
+use super::quote::awaiting_top_up;
+use super::ApiError;
+
+/// Comment frames sent while idle so proxies keep the connection open.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// `data` of every event; `confirmations` only accompanies `detected`.
+/// `schema` names the event's schema in `anon_ticket_domain::events`.
+#[derive(Debug, Serialize)]
+struct EventBody {
+    schema: &'static str,
+    pid: String,
+    amount: i64,
+    block_height: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confirmations: Option<u64>,
+}
+
+/// Streams `detected`, `confirmed`, and `claimable` server-sent events for
+/// `pid`. The stream ends after `claimable`, which is sent right away when
+/// the payment is already stored unclaimed and covers its quote.
+pub async fn payment_events_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let Some(events) = state.payment_events() else {
+        return Err(ApiError::NotFound);
+    };
+    let pid = PaymentId::parse(&path.into_inner())?;
+    // Subscribe before the lookup so a payment landing in between is seen.
+    let receiver = events.subscribe();
+    let initial = match state.storage().find_payment(&pid).await? {
+        Some(record) if !record.status.allows(PaymentTransition::Claim) => {
+            return Err(ApiError::Conflict(format!(
+                "payment already {}",
+                record.status.as_str()
+            )));
+        }
+        Some(record) if awaiting_top_up(&state, &record).await?.is_none() => {
+            Some(claimable_frame(&record).into())
+        }
+        _ => None,
+    };
+
+    let subscription = Subscription {
+        state: state.get_ref().clone(),
+        pid,
+        receiver,
+        keep_alive: interval_at(Instant::now() + KEEP_ALIVE_INTERVAL, KEEP_ALIVE_INTERVAL),
+        initial,
+        _open: OpenStream::new(),
+    };
+    let body = stream::unfold(Some(subscription), |subscription| async move {
+        let mut subscription = subscription?;
+        let (frame, open) = subscription.next_frame().await?;
+        Some((Ok::<_, Infallible>(frame), open.then_some(subscription)))
+    });
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(body))
+}
+
+struct Subscription {
+    state: AppState,
+    pid: PaymentId,
+    receiver: Receiver<PaymentEvent>,
+    keep_alive: Interval,
+    initial: Option<web::Bytes>,
+    _open: OpenStream,
+}
+
+impl Subscription {
+    /// Next chunk and whether the stream stays open after it; `None` once the
+    /// monitor is gone.
+    async fn next_frame(&mut self) -> Option<(web::Bytes, bool)> {
+        if let Some(frame) = self.initial.take() {
+            return Some((frame, false));
+        }
+        loop {
+            tokio::select! {
+                _ = self.keep_alive.tick() => {
+                    return Some((web::Bytes::from_static(b": keep-alive\n\n"), true));
+                }
+                received = self.receiver.recv() => match received {
+                    Ok(event) if event.pid == self.pid => return Some(self.on_event(event).await),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        counter!("api_payment_events_lagged_total").increment(skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            }
+        }
+    }
+
+    async fn on_event(&self, event: PaymentEvent) -> (web::Bytes, bool) {
+        let body = EventBody {
+            schema: PAYMENT_CONFIRMED_V1,
+            pid: event.pid.to_hex(),
+            amount: event.amount,
+            block_height: event.block_height,
+            confirmations: None,
+        };
+        let confirmations = match event.kind {
+            PaymentEventKind::Detected { confirmations } => confirmations,
+            PaymentEventKind::Confirmed => {
+                let mut frame = sse_frame("confirmed", &body);
+                // A renewal of a claimed PID is confirmed but not claimable,
+                // and an underpaid quote waits for its top-up.
+                match self.state.storage().find_payment(&self.pid).await {
+                    Ok(Some(record)) if record.status == PaymentState::Confirmed => {
+                        match awaiting_top_up(&self.state, &record).await {
+                            Ok(None) => frame.push_str(&claimable_frame(&record)),
+                            Ok(Some(_)) => return (frame.into(), true),
+                            Err(err) => warn!(?err, "quote lookup for event stream failed"),
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!(?err, "payment lookup for event stream failed"),
+                }
+                return (frame.into(), false);
+            }
+        };
+        let body = EventBody {
+            schema: PAYMENT_DETECTED_V1,
+            confirmations: Some(confirmations),
+            ..body
+        };
+        (sse_frame("detected", &body).into(), true)
+    }
+}
+
+fn claimable_frame(record: &PaymentRecord) -> String {
+    sse_frame(
+        "claimable",
+        &EventBody {
+            schema: PAYMENT_CLAIMABLE_V1,
+            pid: record.pid.to_hex(),
+            amount: record.amount,
+            block_height: record.block_height,
+            confirmations: None,
+        },
+    )
+}
+
+fn sse_frame(event: &'static str, body: &EventBody) -> String {
+    counter!("api_payment_events_sent_total", "event" => event).increment(1);
+    let data = serde_json::to_string(body).expect("event body serializes");
+    format!("event: {event}\ndata: {data}\n\n")
+}
+
+/// Tracks `api_payment_event_streams` for as long as a stream is open.
+struct OpenStream;
+
+impl OpenStream {
+    fn new() -> Self {
+        gauge!("api_payment_event_streams").increment(1.0);
+        Self
+    }
+}
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        gauge!("api_payment_event_streams").decrement(1.0);
+    }
+}
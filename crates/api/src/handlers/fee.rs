@@ -0,0 +1,45 @@
+use actix_web::{web, HttpResponse};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::state::AppState;
+
+use super::ApiError;
+
+/// Approximate weight in bytes of a typical two-output payment, used for
+/// `estimated_fee`.
+pub const TYPICAL_TX_WEIGHT: u64 = 2_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeeEstimateResponse {
+    /// Atomic units per byte at the default priority.
+    pub fee_per_byte: u64,
+    pub quantization_mask: u64,
+    /// Per-byte fee for each priority level, lowest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub priority_fees: Vec<u64>,
+    /// Fee for a typical payment of `TYPICAL_TX_WEIGHT` bytes.
+    pub estimated_fee: u64,
+    /// Smallest payment the monitor credits, when known, so a checkout UI can
+    /// warn from a single call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_payment_amount: Option<i64>,
+}
+
+pub async fn fee_estimate_handler(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let Some(estimator) = state.fee_estimator() else {
+        return Err(ApiError::NotFound);
+    };
+    let estimate = estimator.estimate().await.map_err(|err| {
+        counter!("api_fee_estimate_requests_total", "status" => "rpc_error").increment(1);
+        ApiError::Rpc(err.to_string())
+    })?;
+    counter!("api_fee_estimate_requests_total", "status" => "success").increment(1);
+    Ok(HttpResponse::Ok().json(FeeEstimateResponse {
+        fee_per_byte: estimate.fee_per_byte,
+        quantization_mask: estimate.quantization_mask,
+        estimated_fee: estimate.fee_for_weight(TYPICAL_TX_WEIGHT),
+        priority_fees: estimate.priority_fees,
+        min_payment_amount: state.service_info().min_payment_amount,
+    }))
+}
@@ -0,0 +1,182 @@
+//! `GET /api/v1/forward-auth`: token check for reverse proxies, following
+//! Traefik's ForwardAuth contract (Caddy's `forward_auth` and nginx's
+//! `auth_request` use the same one).
+//!
+//! The proxy forwards the client's request headers. An active token gets a
+//! `200` carrying `X-Ticket-*` headers for the proxy to copy upstream;
+//! anything else gets a `401` or `403` that the proxy returns to the client.
+//! The token itself is never echoed back. Tokens with a tier also get the
+//! tier's scope and any headers it configures in `API_TOKEN_TIERS`.
+
+use actix_web::{
+    http::header::{self, HeaderName, HeaderValue, InvalidHeaderName},
+    web, HttpRequest, HttpResponse,
+};
+use anon_ticket_domain::error::ErrorCode;
+use anon_ticket_domain::services::rate_limit::RateDecision;
+use anon_ticket_domain::storage::TokenStore;
+use metrics::counter;
+
+use crate::rate_limit::bucket_key;
+use crate::state::AppState;
+
+use super::token::{
+    observe_first_validation, resolve_token, token_expiry, token_state, TokenState,
+    PASSPHRASE_HEADER,
+};
+use super::{ApiError, ErrorBody};
+
+pub const TIER_HEADER: &str = "x-ticket-tier";
+pub const QUOTA_HEADER: &str = "x-ticket-quota";
+pub const SCOPE_HEADER: &str = "x-ticket-scope";
+pub const BALANCE_HEADER: &str = "x-ticket-balance";
+pub const EXPIRES_AT_HEADER: &str = "x-ticket-expires-at";
+
+/// Where forward auth looks for the token: a header (by default
+/// `Authorization: Bearer <token>`), then optionally a cookie.
+#[derive(Debug, Clone, Default)]
+pub struct ForwardAuthSource {
+    header: Option<HeaderName>,
+    cookie: Option<String>,
+}
+
+impl ForwardAuthSource {
+    /// `header` replaces `Authorization`; its whole value is the token.
+    pub fn new(header: Option<&str>, cookie: Option<&str>) -> Result<Self, InvalidHeaderName> {
+        let header = header.map(HeaderName::try_from).transpose()?;
+        Ok(Self {
+            header,
+            cookie: cookie.map(str::to_string),
+        })
+    }
+
+    fn token(&self, req: &HttpRequest) -> Option<String> {
+        let from_header = match &self.header {
+            Some(name) => req.headers().get(name).and_then(header_str),
+            None => req
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(header_str)
+                .and_then(|value| value.strip_prefix("Bearer ")),
+        };
+        from_header
+            .map(|value| value.trim().to_string())
+            .or_else(|| {
+                let name = self.cookie.as_deref()?;
+                Some(req.cookie(name)?.value().trim().to_string())
+            })
+            .filter(|value| !value.is_empty())
+    }
+}
+
+fn header_str(value: &HeaderValue) -> Option<&str> {
+    value.to_str().ok()
+}
+
+pub async fn forward_auth_handler(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let Some(raw) = state.forward_auth().token(&req) else {
+        return Ok(deny(ErrorCode::Unauthorized, "missing", "no service token"));
+    };
+    let passphrase = req
+        .headers()
+        .get(PASSPHRASE_HEADER)
+        .map(|value| value.to_str().unwrap_or_default());
+    // The route has no token in its path for the rate-limit middleware, so
+    // passphrase guesses are throttled here; plain lookups are not, since
+    // every proxied request makes one.
+    if passphrase.is_some() {
+        if let Some(limiter) = state.rate_limiter() {
+            if let RateDecision::Limited { retry_after } =
+                limiter.acquire(&bucket_key("token", &raw)).await
+            {
+                counter!("api_rate_limited_total", "kind" => "token").increment(1);
+                return Err(ApiError::RateLimited(
+                    retry_after.as_secs_f64().ceil().max(1.0) as u64,
+                ));
+            }
+        }
+    }
+    let Ok(token) = resolve_token(&raw, passphrase) else {
+        return Ok(deny(
+            ErrorCode::Unauthorized,
+            "invalid",
+            "malformed service token",
+        ));
+    };
+    let Some(record) = state.storage().find_token(&token).await? else {
+        return Ok(deny(
+            ErrorCode::Unauthorized,
+            "not_found",
+            "unknown service token",
+        ));
+    };
+    observe_first_validation(&state, &record).await;
+    let expires_at = token_expiry(&state, &record).await?;
+    match token_state(&record, expires_at) {
+        TokenState::Active => {}
+        TokenState::Suspended => {
+            return Ok(deny(
+                ErrorCode::Forbidden,
+                "suspended",
+                "service token is suspended",
+            ))
+        }
+        TokenState::Revoked => {
+            return Ok(deny(
+                ErrorCode::Forbidden,
+                "revoked",
+                "service token is revoked",
+            ))
+        }
+        TokenState::Expired => {
+            return Ok(deny(
+                ErrorCode::Forbidden,
+                "expired",
+                "service token is expired",
+            ))
+        }
+    }
+
+    counter!("api_token_requests_total", "endpoint" => "forward_auth", "status" => "active")
+        .increment(1);
+    let mut response = HttpResponse::Ok();
+    response.insert_header((BALANCE_HEADER, record.amount.to_string()));
+    if let Some(tier) = &record.tier {
+        response.insert_header((TIER_HEADER, tier.as_str()));
+        if let Some(tier) = state.token_tier(tier) {
+            if let Some(quota) = tier.quota() {
+                response.insert_header((QUOTA_HEADER, quota.to_string()));
+            }
+            if let Some(scope) = tier.scope() {
+                response.insert_header((SCOPE_HEADER, scope));
+            }
+            // Names and values were checked when the tiers were loaded.
+            for (name, value) in tier.headers() {
+                response.insert_header((name.as_str(), value.as_str()));
+            }
+        }
+    }
+    if let Some(expires_at) = expires_at {
+        response.insert_header((EXPIRES_AT_HEADER, expires_at.to_rfc3339()));
+    }
+    Ok(response.finish())
+}
+
+fn deny(code: ErrorCode, status: &'static str, error: &str) -> HttpResponse {
+    counter!("api_token_requests_total", "endpoint" => "forward_auth", "status" => status)
+        .increment(1);
+    let mut response = match code {
+        ErrorCode::Forbidden => HttpResponse::Forbidden(),
+        _ => HttpResponse::Unauthorized(),
+    };
+    if code == ErrorCode::Unauthorized {
+        response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+    }
+    response.json(ErrorBody {
+        code: code.as_str().to_string(),
+        error: error.to_string(),
+    })
+}
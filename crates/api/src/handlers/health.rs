@@ -0,0 +1,48 @@
+use actix_web::{web, HttpResponse};
+use metrics::counter;
+use serde_json::json;
+
+use crate::health::{health_report, readiness, CheckStatus};
+use crate::state::AppState;
+
+/// Liveness: the process answers HTTP. Never checks dependencies, so a
+/// database outage does not get the pod restarted.
+pub async fn livez_handler() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+/// Dependency report: the database, the embedded monitor's last tick and the
+/// wallets it polls. `503` only when the database is down; a stalled monitor
+/// or an unreachable wallet is `degraded` but still `200`, so probes pointed
+/// here do not restart pods over a slow wallet sync.
+pub async fn healthz_handler(state: web::Data<AppState>) -> HttpResponse {
+    let report = health_report(&state).await;
+    let status = match report.status {
+        CheckStatus::Disabled | CheckStatus::Ok => "ok",
+        CheckStatus::Degraded => "degraded",
+        CheckStatus::Down => "down",
+    };
+    counter!("api_health_checks_total", "status" => status).increment(1);
+    if report.status == CheckStatus::Down {
+        HttpResponse::ServiceUnavailable().json(report)
+    } else {
+        HttpResponse::Ok().json(report)
+    }
+}
+
+/// Readiness: `200` while this instance should receive traffic, `503`
+/// otherwise. The body says which check failed.
+pub async fn readyz_handler(state: web::Data<AppState>) -> HttpResponse {
+    let readiness = readiness(&state).await;
+    let result = if readiness.ready {
+        "ready"
+    } else {
+        "not_ready"
+    };
+    counter!("api_readiness_checks_total", "result" => result).increment(1);
+    if readiness.ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
@@ -0,0 +1,106 @@
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::PRIMARY_WALLET;
+use anon_ticket_domain::integrated_address::{
+    primary_address_fingerprint, primary_address_network,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::build_info::BuildInfo;
+use crate::state::AppState;
+
+use super::checkout::{preset_response, CheckoutPresetResponse};
+use super::ApiError;
+
+/// Path version of the public routes (`/api/v1/...`).
+pub const API_VERSION: &str = "v1";
+
+/// Operational parameters clients would otherwise have to hardcode.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InfoResponse {
+    pub api_version: String,
+    pub server_version: String,
+    pub build: BuildInfo,
+    /// Networks payments are accepted on, derived from the address book.
+    pub networks: Vec<String>,
+    /// SHA3-256 of the primary address; the address itself is not published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_address_fingerprint: Option<String>,
+    /// Every address a quote can name in `address_id`, primary first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<AddressInfo>,
+    /// Smallest payment, in atomic units, the monitor credits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_payment_amount: Option<i64>,
+    /// Confirmations a transfer needs before it becomes redeemable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confirmations: Option<u64>,
+    pub checkout_enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription: Option<SubscriptionInfo>,
+    /// Checkout presets, empty unless checkout is enabled.
+    #[serde(default)]
+    pub presets: Vec<CheckoutPresetResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AddressInfo {
+    pub id: String,
+    pub network: String,
+    /// SHA3-256 of the address, as for `primary_address_fingerprint`.
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubscriptionInfo {
+    pub period_secs: u64,
+    /// Atomic units buying one full period.
+    pub period_amount: u64,
+}
+
+pub async fn info_handler(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let info = state.service_info();
+    let primary = info.addresses.get(PRIMARY_WALLET);
+    // Validated at config load, so a failure here only drops the entry.
+    let addresses: Vec<AddressInfo> = info
+        .addresses
+        .iter()
+        .filter_map(|(id, address)| {
+            Some(AddressInfo {
+                id: id.to_string(),
+                network: primary_address_network(address).ok()?.to_string(),
+                fingerprint: primary_address_fingerprint(address),
+            })
+        })
+        .collect();
+    let mut networks: Vec<String> = Vec::new();
+    for address in &addresses {
+        if !networks.contains(&address.network) {
+            networks.push(address.network.clone());
+        }
+    }
+    let presets = if state.checkout_enabled() {
+        state
+            .checkout_presets()
+            .iter()
+            .map(|preset| preset_response(preset, None))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(HttpResponse::Ok().json(InfoResponse {
+        api_version: API_VERSION.to_string(),
+        server_version: env!("CARGO_PKG_VERSION").to_string(),
+        build: BuildInfo::current(),
+        networks,
+        primary_address_fingerprint: primary.map(primary_address_fingerprint),
+        addresses,
+        min_payment_amount: info.min_payment_amount,
+        min_confirmations: info.min_confirmations,
+        checkout_enabled: state.checkout_enabled(),
+        subscription: state.subscription_period().map(|period| SubscriptionInfo {
+            period_secs: period.secs(),
+            period_amount: period.amount(),
+        }),
+        presets,
+    }))
+}
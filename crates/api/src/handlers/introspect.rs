@@ -0,0 +1,92 @@
+//! `POST /internal/introspect`: RFC 7662 token introspection, so OAuth2-aware
+//! proxies can check anon-ticket tokens without custom code.
+//!
+//! Tokens redeemed with a passphrase are stored wrapped and cannot be found
+//! from the bare token; like unknown, malformed, suspended, revoked, and
+//! expired tokens, they introspect as `{"active": false}` with nothing else
+//! disclosed.
+
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::model::{ServiceToken, ServiceTokenRecord};
+use anon_ticket_domain::storage::{CheckoutStore, TokenStore};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::token::{observe_first_validation, token_expiry, token_state, TokenState};
+use super::ApiError;
+
+/// `application/x-www-form-urlencoded` body of an introspection request.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IntrospectRequest {
+    pub token: String,
+    /// Accepted as RFC 7662 requires and ignored: there is one token type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type_hint: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    /// Tier earned under the checkout preset the PID was issued with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Issuance time, in seconds since the epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    /// Expiry in seconds since the epoch; absent for tokens that never
+    /// expire.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+}
+
+pub async fn introspect_handler(
+    state: web::Data<AppState>,
+    form: web::Form<IntrospectRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::ReadOnly)?;
+    // A malformed token is simply not an active one.
+    let record = match ServiceToken::parse(form.token.trim()) {
+        Ok(token) => state.storage().find_token(&token).await?,
+        Err(_) => None,
+    };
+    let Some(record) = record else {
+        counter!("api_token_requests_total", "endpoint" => "introspect", "status" => "not_found")
+            .increment(1);
+        return Ok(HttpResponse::Ok().json(IntrospectResponse::default()));
+    };
+    observe_first_validation(&state, &record).await;
+    let expires_at = token_expiry(&state, &record).await?;
+    let status = token_state(&record, expires_at);
+    counter!("api_token_requests_total", "endpoint" => "introspect", "status" => status.as_ref().to_owned())
+        .increment(1);
+    if status != TokenState::Active {
+        return Ok(HttpResponse::Ok().json(IntrospectResponse::default()));
+    }
+    Ok(HttpResponse::Ok().json(IntrospectResponse {
+        active: true,
+        scope: token_tier(&state, &record).await?,
+        iat: Some(record.issued_at.timestamp()),
+        exp: expires_at.map(|at| at.timestamp()),
+    }))
+}
+
+/// Tier stored at issuance; older tokens fall back to their PID's checkout
+/// preset.
+async fn token_tier(
+    state: &AppState,
+    record: &ServiceTokenRecord,
+) -> Result<Option<String>, ApiError> {
+    if record.tier.is_some() {
+        return Ok(record.tier.clone());
+    }
+    let terms = state.storage().find_checkout_terms(&record.pid).await?;
+    Ok(terms
+        .and_then(|terms| state.checkout_preset(&terms.preset))
+        .and_then(|preset| preset.tier_for(record.amount))
+        .map(str::to_string))
+}
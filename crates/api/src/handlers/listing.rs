@@ -0,0 +1,396 @@
+//! Operator listings of payments, tokens, overpayment credits, refunds and
+//! audit events for the internal listener.
+//!
+//! All are keyset-paginated on their primary key: pass the previous page's
+//! `next_after` as `?after=` to continue. Ordering by key is stable under
+//! concurrent inserts, which an offset would not be. Credits, refunds and
+//! audit events are listed newest first, so their next page holds older
+//! entries.
+
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::model::{
+    audit_subject_hash, AuditActor, AuditEventRecord, AuditFilter, CreditStatus, PaymentFilter,
+    PaymentId, PaymentState, RefundStatus, ServiceToken, ServiceTokenRecord, TokenFilter,
+    TokenRevocation,
+};
+use anon_ticket_domain::storage::{AuditStore, CreditStore, PaymentStore, RefundStore, TokenStore};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::credit::CreditSummary;
+use super::payment::PaymentResponse;
+use super::refund::RefundSummary;
+use super::token::{token_state, TokenState};
+use super::ApiError;
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 500;
+
+/// Query string shared by both listings. Ranges are inclusive; `status` is
+/// `unclaimed`, `claimed`, `expired`, `reorged` or `refunded` for payments and `active`/`revoked` for tokens.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    pub status: Option<String>,
+    pub min_height: Option<i64>,
+    pub max_height: Option<i64>,
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+    /// Last key of the previous page.
+    pub after: Option<String>,
+    pub limit: Option<u64>,
+}
+
+pub(crate) fn page_size(limit: Option<u64>) -> Result<u64, ApiError> {
+    match limit {
+        None => Ok(DEFAULT_PAGE_SIZE),
+        Some(limit @ 1..=MAX_PAGE_SIZE) => Ok(limit),
+        Some(_) => Err(ApiError::InvalidRequest(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}"
+        ))),
+    }
+}
+
+impl ListQuery {
+    fn page_size(&self) -> Result<u64, ApiError> {
+        page_size(self.limit)
+    }
+
+    fn check_ranges(&self) -> Result<(), ApiError> {
+        for (name, min, max) in [
+            ("height", self.min_height, self.max_height),
+            ("amount", self.min_amount, self.max_amount),
+        ] {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    return Err(ApiError::InvalidRequest(format!(
+                        "min_{name} must not exceed max_{name}"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass as `after` to fetch the next page; `null` on the last page.
+    pub next_after: Option<String>,
+}
+
+/// Trims the extra row fetched to detect a following page.
+pub(crate) fn paginate<R, T>(
+    mut rows: Vec<R>,
+    limit: u64,
+    key: impl Fn(&R) -> String,
+    item: impl Fn(R) -> T,
+) -> Page<T> {
+    let has_more = rows.len() as u64 > limit;
+    rows.truncate(limit as usize);
+    let next_after = rows.last().filter(|_| has_more).map(&key);
+    Page {
+        items: rows.into_iter().map(item).collect(),
+        next_after,
+    }
+}
+
+/// `GET /internal/v1/payments`.
+pub async fn list_payments_handler(
+    state: web::Data<AppState>,
+    query: web::Query<ListQuery>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::ReadOnly)?;
+    let query = query.into_inner();
+    let limit = query.page_size()?;
+    query.check_ranges()?;
+    let status = match query.status.as_deref() {
+        None => None,
+        Some("unclaimed") => Some(PaymentState::Confirmed),
+        Some(raw @ ("claimed" | "expired" | "reorged" | "refunded")) => PaymentState::parse(raw),
+        Some(other) => {
+            return Err(ApiError::InvalidRequest(format!(
+                "unknown payment status `{other}`; expected unclaimed, claimed, expired, reorged or refunded"
+            )))
+        }
+    };
+    let after = query.after.as_deref().map(PaymentId::parse).transpose()?;
+    let filter = PaymentFilter {
+        status,
+        min_height: query.min_height,
+        max_height: query.max_height,
+        min_amount: query.min_amount,
+        max_amount: query.max_amount,
+    };
+    let rows = state
+        .storage()
+        .list_payments(&filter, after.as_ref(), limit + 1)
+        .await?;
+    Ok(HttpResponse::Ok().json(paginate(
+        rows,
+        limit,
+        |record| record.pid.to_hex(),
+        PaymentResponse::from,
+    )))
+}
+
+/// A token as stored. Passphrase-protected tokens are listed in their
+/// wrapped form, as with force-claim.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenSummary {
+    pub token: String,
+    pub pid: String,
+    /// `expired` reflects the token's own lifetime only; subscription expiry
+    /// is reported by token introspection.
+    pub status: TokenState,
+    pub amount: i64,
+    pub issued_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub revoke_reason: Option<String>,
+    pub abuse_score: i16,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspend_reason: Option<String>,
+    pub suspended_until: Option<DateTime<Utc>>,
+}
+
+impl From<ServiceTokenRecord> for TokenSummary {
+    fn from(record: ServiceTokenRecord) -> Self {
+        Self {
+            status: token_state(&record, record.expires_at),
+            token: record.token.into_inner(),
+            pid: record.pid.into_inner(),
+            amount: record.amount,
+            issued_at: record.issued_at,
+            revoked_at: record.revoked_at,
+            revoke_reason: record.revoke_reason,
+            abuse_score: record.abuse_score,
+            expires_at: record.expires_at,
+            suspended_at: record.suspended_at,
+            suspend_reason: record.suspend_reason,
+            suspended_until: record.suspended_until,
+        }
+    }
+}
+
+/// `GET /internal/v1/tokens`. Lists bearer tokens, so it needs `support`.
+pub async fn list_tokens_handler(
+    state: web::Data<AppState>,
+    query: web::Query<ListQuery>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let query = query.into_inner();
+    let limit = query.page_size()?;
+    query.check_ranges()?;
+    let revocation = match query.status.as_deref() {
+        None => None,
+        Some("active") => Some(TokenRevocation::Active),
+        Some("suspended") => Some(TokenRevocation::Suspended),
+        Some("revoked") => Some(TokenRevocation::Revoked),
+        Some(other) => {
+            return Err(ApiError::InvalidRequest(format!(
+                "unknown token status `{other}`; expected active, suspended or revoked"
+            )))
+        }
+    };
+    let after = query
+        .after
+        .as_deref()
+        .map(ServiceToken::parse)
+        .transpose()?;
+    let filter = TokenFilter {
+        revocation,
+        min_height: query.min_height,
+        max_height: query.max_height,
+        min_amount: query.min_amount,
+        max_amount: query.max_amount,
+    };
+    let rows = state
+        .storage()
+        .list_tokens(&filter, after.as_ref(), limit + 1)
+        .await?;
+    Ok(HttpResponse::Ok().json(paginate(
+        rows,
+        limit,
+        |record| record.token.to_hex(),
+        TokenSummary::from,
+    )))
+}
+
+/// Query string for the audit log. `subject` is the raw PID or token and is
+/// hashed the same way as stored; `since`/`until` are inclusive.
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub subject: Option<String>,
+    pub outcome: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Id of the last event on the previous page.
+    pub after: Option<i64>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEventSummary {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub actor: String,
+    pub action: String,
+    /// Hex of the subject hash; compare against a `subject=` query rather
+    /// than reversing it.
+    pub subject_hash: String,
+    pub outcome: String,
+    pub reason: Option<String>,
+    pub operator: Option<String>,
+    pub key_id: Option<String>,
+}
+
+impl From<AuditEventRecord> for AuditEventSummary {
+    fn from(record: AuditEventRecord) -> Self {
+        let event = record.event;
+        Self {
+            id: record.id,
+            occurred_at: event.occurred_at,
+            actor: event.actor.as_str().to_string(),
+            action: event.action,
+            subject_hash: hex::encode(event.subject_hash),
+            outcome: event.outcome,
+            reason: event.reason,
+            operator: event.operator,
+            key_id: event.key_id,
+        }
+    }
+}
+
+/// `GET /internal/v1/audit`. The log names operators and key ids, so it
+/// needs `admin`.
+pub async fn list_audit_events_handler(
+    state: web::Data<AppState>,
+    query: web::Query<AuditQuery>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Admin)?;
+    let query = query.into_inner();
+    let limit = page_size(query.limit)?;
+    if let (Some(since), Some(until)) = (query.since, query.until) {
+        if since > until {
+            return Err(ApiError::InvalidRequest(
+                "since must not be after until".into(),
+            ));
+        }
+    }
+    let actor = match query.actor.as_deref() {
+        None => None,
+        Some(raw) => Some(AuditActor::parse(raw).ok_or_else(|| {
+            ApiError::InvalidRequest(format!(
+                "unknown audit actor `{raw}`; expected public or internal"
+            ))
+        })?),
+    };
+    let filter = AuditFilter {
+        actor,
+        action: query.action,
+        subject_hash: query.subject.as_deref().map(audit_subject_hash),
+        outcome: query.outcome,
+        since: query.since,
+        until: query.until,
+    };
+    let rows = state
+        .storage()
+        .list_audit_events(&filter, query.after, limit + 1)
+        .await?;
+    Ok(HttpResponse::Ok().json(paginate(
+        rows,
+        limit,
+        |record| record.id.to_string(),
+        AuditEventSummary::from,
+    )))
+}
+
+/// Query string for overpayment credits; `status` is `credited`, `held` or
+/// `refunded`.
+#[derive(Debug, Default, Deserialize)]
+pub struct CreditQuery {
+    pub status: Option<String>,
+    /// Id of the last credit on the previous page.
+    pub after: Option<i64>,
+    pub limit: Option<u64>,
+}
+
+/// `GET /internal/v1/credits`. Credits name tokens, so this needs the same
+/// `support` role as the token listing.
+pub async fn list_credits_handler(
+    state: web::Data<AppState>,
+    query: web::Query<CreditQuery>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let query = query.into_inner();
+    let limit = page_size(query.limit)?;
+    let status = match query.status.as_deref() {
+        None => None,
+        Some(raw) => Some(CreditStatus::parse(raw).ok_or_else(|| {
+            ApiError::InvalidRequest(format!(
+                "unknown credit status `{raw}`; expected credited, held or refunded"
+            ))
+        })?),
+    };
+    let rows = state
+        .storage()
+        .list_credits(status, query.after, limit + 1)
+        .await?;
+    Ok(HttpResponse::Ok().json(paginate(
+        rows,
+        limit,
+        |credit| credit.id.to_string(),
+        CreditSummary::from,
+    )))
+}
+
+/// Query string for refunds; `status` is `pending`, `sending`, `sent` or
+/// `failed`.
+#[derive(Debug, Default, Deserialize)]
+pub struct RefundQuery {
+    pub status: Option<String>,
+    /// Id of the last refund on the previous page.
+    pub after: Option<i64>,
+    pub limit: Option<u64>,
+}
+
+/// `GET /internal/v1/refunds`. Refunds carry payer addresses, so this needs
+/// the `support` role.
+pub async fn list_refunds_handler(
+    state: web::Data<AppState>,
+    query: web::Query<RefundQuery>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let query = query.into_inner();
+    let limit = page_size(query.limit)?;
+    let status = match query.status.as_deref() {
+        None => None,
+        Some(raw) => Some(RefundStatus::parse(raw).ok_or_else(|| {
+            ApiError::InvalidRequest(format!(
+                "unknown refund status `{raw}`; expected pending, sending, sent or failed"
+            ))
+        })?),
+    };
+    let rows = state
+        .storage()
+        .list_refunds(status, query.after, limit + 1)
+        .await?;
+    Ok(HttpResponse::Ok().json(paginate(
+        rows,
+        limit,
+        |refund| refund.id.to_string(),
+        RefundSummary::from,
+    )))
+}
@@ -1,10 +1,18 @@
 use actix_web::{web::Data, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
 
+use crate::auth::Caller;
 use crate::state::AppState;
 
-pub async fn metrics_handler(state: Data<AppState>) -> HttpResponse {
+use super::ApiError;
+
+pub async fn metrics_handler(
+    state: Data<AppState>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::ReadOnly)?;
     let body = state.telemetry().render_metrics();
-    HttpResponse::Ok()
+    Ok(HttpResponse::Ok()
         .content_type("text/plain; version=0.0.4")
-        .body(body)
+        .body(body))
 }
@@ -1,48 +1,135 @@
+pub mod cache;
+pub mod checkout;
+pub mod config;
+pub mod credit;
+pub mod events;
+pub mod fee;
+pub mod forward_auth;
+pub mod health;
+pub mod info;
+pub mod introspect;
+pub mod listing;
+#[cfg(feature = "metrics")]
 pub mod metrics;
+pub mod payment;
+pub mod payment_status;
+pub mod quote;
 pub mod redeem;
+pub mod refund;
+pub mod revocations;
+pub mod search;
+pub mod skipped;
 pub mod token;
+pub mod webhook;
 
+pub use cache::{cache_flush_handler, cache_stats_handler};
+pub use checkout::checkout_handler;
+pub use config::runtime_config_handler;
+pub use credit::{apply_credit_handler, refund_credit_handler};
+pub use events::payment_events_handler;
+pub use fee::fee_estimate_handler;
+pub use forward_auth::forward_auth_handler;
+pub use health::{healthz_handler, livez_handler, readyz_handler};
+pub use info::info_handler;
+pub use introspect::introspect_handler;
+pub use listing::{
+    list_audit_events_handler, list_credits_handler, list_payments_handler, list_refunds_handler,
+    list_tokens_handler,
+};
+#[cfg(feature = "metrics")]
 pub use metrics::metrics_handler;
+pub use payment::{force_claim_handler, inject_payment_handler, unclaim_handler};
+pub use payment_status::payment_status_handler;
+pub use quote::{create_quote_handler, quote_status_handler};
 pub use redeem::redeem_handler;
-pub use token::{revoke_token_handler, token_status_handler};
+pub use refund::{cancel_refund_handler, refund_sent_handler, request_refund_handler};
+pub use revocations::{revocation_export_handler, revocations_handler};
+pub use search::search_handler;
+pub use skipped::{dismiss_skipped_handler, list_skipped_handler, reprocess_skipped_handler};
+pub use token::{
+    merge_tokens_handler, revoke_token_handler, spend_token_handler, split_token_handler,
+    suspend_token_handler, token_balance_handler, token_status_handler, unsuspend_token_handler,
+};
+pub use webhook::{redeliver_webhook_handler, webhook_event_handler};
 
-use actix_web::{http::StatusCode, HttpResponse, ResponseError};
-use serde::Serialize;
+use actix_web::{
+    http::{header, StatusCode},
+    HttpResponse, ResponseError,
+};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::error::{ErrorCode, HasErrorCode};
 use anon_ticket_domain::model::{PidFormatError, TokenFormatError};
 use anon_ticket_domain::storage::StorageError;
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum ApiError {
     #[error("invalid payment id: {0}")]
     InvalidPid(#[from] PidFormatError),
     #[error("invalid token: {0}")]
     InvalidToken(#[from] TokenFormatError),
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("missing or invalid internal api key")]
+    Unauthorized,
+    #[error("requires the `{0}` role")]
+    Forbidden(InternalRole),
     #[error("payment not found")]
     NotFound,
+    #[error("conflict: {0}")]
+    Conflict(String),
+    /// Seconds until the next request for the same PID or token is allowed.
+    #[error("too many requests; retry in {0}s")]
+    RateLimited(u64),
     #[error("storage failure: {0}")]
     Storage(#[from] StorageError),
+    #[error("rpc unavailable: {0}")]
+    Rpc(String),
+    #[error("internal error: {0}")]
+    Internal(String),
 }
 
-impl ResponseError for ApiError {
-    fn status_code(&self) -> StatusCode {
+impl HasErrorCode for ApiError {
+    fn code(&self) -> ErrorCode {
         match self {
-            ApiError::InvalidPid(_) => StatusCode::BAD_REQUEST,
-            ApiError::InvalidToken(_) => StatusCode::BAD_REQUEST,
-            ApiError::NotFound => StatusCode::NOT_FOUND,
-            ApiError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::InvalidPid(err) => err.code(),
+            ApiError::InvalidToken(err) => err.code(),
+            ApiError::InvalidRequest(_) => ErrorCode::InvalidRequest,
+            ApiError::Unauthorized => ErrorCode::Unauthorized,
+            ApiError::Forbidden(_) => ErrorCode::Forbidden,
+            ApiError::NotFound => ErrorCode::NotFound,
+            ApiError::Conflict(_) => ErrorCode::Conflict,
+            ApiError::RateLimited(_) => ErrorCode::RateLimited,
+            ApiError::Storage(err) => err.code(),
+            ApiError::Rpc(_) => ErrorCode::RpcUnavailable,
+            ApiError::Internal(_) => ErrorCode::Internal,
         }
     }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.code().http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
 
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code()).json(ErrorBody {
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::RateLimited(retry_after) = self {
+            response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+        response.json(ErrorBody {
+            code: self.code().as_str().to_string(),
             error: self.to_string(),
         })
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorBody {
+    /// Stable machine-readable code from [`ErrorCode`].
+    pub code: String,
     pub error: String,
 }
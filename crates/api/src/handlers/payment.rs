@@ -0,0 +1,323 @@
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::model::{
+    AuditActor, NewPayment, PaymentId, PaymentRecord, PaymentState as StoredPaymentState,
+    PaymentTransition,
+};
+use anon_ticket_domain::services::cache::PidCache;
+use anon_ticket_domain::storage::{PaymentStore, QuoteStore, TokenStore};
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::AuditEvent;
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::{redeem::ensure_token_record, ApiError};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentState {
+    Unclaimed,
+    Claimed,
+    Expired,
+    Reorged,
+    Refunded,
+}
+
+impl From<StoredPaymentState> for PaymentState {
+    fn from(status: StoredPaymentState) -> Self {
+        match status {
+            // Only confirmed and later states are stored.
+            StoredPaymentState::Detected
+            | StoredPaymentState::Pending
+            | StoredPaymentState::Confirmed => PaymentState::Unclaimed,
+            StoredPaymentState::Claimed => PaymentState::Claimed,
+            StoredPaymentState::Expired => PaymentState::Expired,
+            StoredPaymentState::Reorged => PaymentState::Reorged,
+            StoredPaymentState::Refunded => PaymentState::Refunded,
+        }
+    }
+}
+
+/// Manual registration of a transfer the monitor missed. `reason` is
+/// mandatory and recorded in the audit trail.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InjectPaymentRequest {
+    pub pid: String,
+    pub txid: String,
+    pub amount: i64,
+    pub block_height: i64,
+    pub reason: String,
+    pub operator: Option<String>,
+    /// Address book entry the transfer was received on, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentResponse {
+    pub pid: String,
+    pub txid: String,
+    pub amount: i64,
+    pub block_height: i64,
+    pub status: PaymentState,
+    pub detected_at: DateTime<Utc>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// Address book entry the transfer was received on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_id: Option<String>,
+}
+
+impl From<PaymentRecord> for PaymentResponse {
+    fn from(record: PaymentRecord) -> Self {
+        Self {
+            pid: record.pid.into_inner(),
+            txid: record.txid,
+            amount: record.amount,
+            block_height: record.block_height,
+            status: record.status.into(),
+            detected_at: record.created_at,
+            claimed_at: record.claimed_at,
+            address_id: record.address_id,
+        }
+    }
+}
+
+/// Registers a payment verified out-of-band. Re-submitting the same
+/// pid/txid pair is idempotent (`200`); a pid already bound to another txid
+/// is rejected with `409`.
+pub async fn inject_payment_handler(
+    state: web::Data<AppState>,
+    payload: web::Json<InjectPaymentRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Admin)?;
+    let request = payload.into_inner();
+    let pid = PaymentId::parse(&request.pid)?;
+    validate_injection(&request)?;
+    if let Some(id) = &request.address_id {
+        if !state.service_info().addresses.contains(id) {
+            return Err(ApiError::InvalidRequest(format!("unknown address_id {id}")));
+        }
+    }
+
+    let audit = |outcome| AuditEvent {
+        actor: AuditActor::Internal,
+        action: "payment.inject",
+        subject: &request.pid,
+        reason: &request.reason,
+        operator: request.operator.as_deref(),
+        key_id: caller.key_id(),
+        outcome,
+    };
+
+    if let Some(existing) = state.storage().find_payment_primary(&pid).await? {
+        if existing.txid != request.txid {
+            counter!("api_admin_actions_total", "action" => "payment_inject", "status" => "conflict")
+                .increment(1);
+            audit("conflict").record(&state).await;
+            return Err(ApiError::Conflict(
+                "payment id already bound to a different txid".to_string(),
+            ));
+        }
+        counter!("api_admin_actions_total", "action" => "payment_inject", "status" => "exists")
+            .increment(1);
+        audit("exists").record(&state).await;
+        return Ok(HttpResponse::Ok().json(PaymentResponse::from(existing)));
+    }
+
+    state
+        .storage()
+        .insert_payment(NewPayment {
+            pid: pid.clone(),
+            txid: request.txid.clone(),
+            amount: request.amount,
+            block_height: request.block_height,
+            detected_at: Utc::now(),
+            address_id: request.address_id.clone(),
+        })
+        .await?;
+    // As the monitor does, so a quoted PID reconciles against the injection.
+    state.storage().settle_quote(&pid).await?;
+    let record = state
+        .storage()
+        .find_payment_primary(&pid)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    // Mirror the monitor hooks so bloom-gated redemption sees the new PID.
+    state.cache().mark_present(&pid);
+    state.insert_bloom(&pid);
+
+    counter!("api_admin_actions_total", "action" => "payment_inject", "status" => "created")
+        .increment(1);
+    audit("created").record(&state).await;
+    Ok(HttpResponse::Created().json(PaymentResponse::from(record)))
+}
+
+/// Operator justification required by the claim override endpoints.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ClaimOverrideRequest {
+    pub reason: String,
+    pub operator: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaimOverrideResponse {
+    pub payment: PaymentResponse,
+    /// Present after a force-claim; `None` after an un-claim.
+    pub service_token: Option<String>,
+}
+
+/// Claims a payment on the customer's behalf and issues (or returns) its
+/// deterministic service token. Used when automatic redemption is stuck.
+pub async fn force_claim_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<ClaimOverrideRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let raw_pid = path.into_inner();
+    let pid = PaymentId::parse(&raw_pid)?;
+    let request = payload.into_inner();
+    require_reason(&request.reason)?;
+    let audit = |outcome| AuditEvent {
+        actor: AuditActor::Internal,
+        action: "payment.force_claim",
+        subject: &raw_pid,
+        reason: &request.reason,
+        operator: request.operator.as_deref(),
+        key_id: caller.key_id(),
+        outcome,
+    };
+
+    let Some(existing) = state.storage().find_payment_primary(&pid).await? else {
+        counter!("api_admin_actions_total", "action" => "force_claim", "status" => "not_found")
+            .increment(1);
+        audit("not_found").record(&state).await;
+        return Err(ApiError::NotFound);
+    };
+    let outcome = if existing.status == StoredPaymentState::Claimed {
+        "already_claimed"
+    } else if let Err(err) = existing.status.apply(PaymentTransition::Claim) {
+        let status = existing.status.as_str();
+        counter!("api_admin_actions_total", "action" => "force_claim", "status" => status)
+            .increment(1);
+        audit(status).record(&state).await;
+        return Err(ApiError::Conflict(err.to_string()));
+    } else {
+        state.storage().claim_payment(&pid).await?;
+        "claimed"
+    };
+    let payment = state
+        .storage()
+        .find_payment_primary(&pid)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let token = match ensure_token_record(&state, &pid, &payment, None).await? {
+        Some(token) => token,
+        // Passphrase-protected tokens are never shown unwrapped; report the
+        // stored form so operators can still revoke it.
+        None => state
+            .storage()
+            .find_token_by_pid(&pid)
+            .await?
+            .ok_or(ApiError::NotFound)?,
+    };
+    state.cache().mark_present(&pid);
+    state.insert_bloom(&pid);
+
+    counter!("api_admin_actions_total", "action" => "force_claim", "status" => outcome)
+        .increment(1);
+    audit(outcome).record(&state).await;
+    Ok(HttpResponse::Ok().json(ClaimOverrideResponse {
+        payment: payment.into(),
+        service_token: Some(token.token.into_inner()),
+    }))
+}
+
+/// Reverts a claim that never produced a token. Claims with an issued token
+/// are rejected; revoke the token instead so the customer-facing state stays
+/// consistent.
+pub async fn unclaim_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<ClaimOverrideRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let raw_pid = path.into_inner();
+    let pid = PaymentId::parse(&raw_pid)?;
+    let request = payload.into_inner();
+    require_reason(&request.reason)?;
+    let audit = |outcome| AuditEvent {
+        actor: AuditActor::Internal,
+        action: "payment.unclaim",
+        subject: &raw_pid,
+        reason: &request.reason,
+        operator: request.operator.as_deref(),
+        key_id: caller.key_id(),
+        outcome,
+    };
+    let reject = |status: &'static str, message: &'static str| {
+        let state = &state;
+        async move {
+            counter!("api_admin_actions_total", "action" => "unclaim", "status" => status)
+                .increment(1);
+            audit(status).record(state).await;
+            ApiError::Conflict(message.to_string())
+        }
+    };
+
+    let Some(existing) = state.storage().find_payment_primary(&pid).await? else {
+        counter!("api_admin_actions_total", "action" => "unclaim", "status" => "not_found")
+            .increment(1);
+        audit("not_found").record(&state).await;
+        return Err(ApiError::NotFound);
+    };
+    if !existing.status.allows(PaymentTransition::Unclaim) {
+        return Err(reject("not_claimed", "payment is not claimed").await);
+    }
+    if state.storage().find_token_by_pid(&pid).await?.is_some() {
+        return Err(reject(
+            "token_issued",
+            "a service token was already issued; revoke it instead",
+        )
+        .await);
+    }
+    let Some(payment) = state.storage().unclaim_payment(&pid).await? else {
+        return Err(reject("not_claimed", "payment is not claimed").await);
+    };
+
+    counter!("api_admin_actions_total", "action" => "unclaim", "status" => "unclaimed")
+        .increment(1);
+    audit("unclaimed").record(&state).await;
+    Ok(HttpResponse::Ok().json(ClaimOverrideResponse {
+        payment: payment.into(),
+        service_token: None,
+    }))
+}
+
+pub(super) fn require_reason(reason: &str) -> Result<(), ApiError> {
+    if reason.trim().is_empty() {
+        return Err(ApiError::InvalidRequest("reason is required".into()));
+    }
+    Ok(())
+}
+
+fn validate_injection(request: &InjectPaymentRequest) -> Result<(), ApiError> {
+    if request.txid.trim().is_empty() {
+        return Err(ApiError::InvalidRequest("txid must not be empty".into()));
+    }
+    if request.amount <= 0 {
+        return Err(ApiError::InvalidRequest("amount must be positive".into()));
+    }
+    if request.block_height < 0 {
+        return Err(ApiError::InvalidRequest(
+            "block_height must not be negative".into(),
+        ));
+    }
+    require_reason(&request.reason)
+}
@@ -0,0 +1,107 @@
+use actix_web::{http::header, web, HttpResponse};
+use anon_ticket_domain::model::{PaymentId, PaymentState};
+use anon_ticket_domain::storage::{PaymentStore, PendingPaymentStore, QuoteStore};
+use chrono::Utc;
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::state::AppState;
+
+use super::quote::{awaiting_top_up, PaymentProgress};
+use super::ApiError;
+
+/// What a payer can learn about a PID without redeeming it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PaymentStatusResponse {
+    pub pid: String,
+    /// `unseen`, `unconfirmed` (in the wallet's transaction pool), `detected`
+    /// (mined, not yet confirmed), `awaiting_top_up` (confirmed but short of
+    /// the quote), `claimable`, `claimed`, `expired`, `reorged` (claimed, then
+    /// its block was orphaned) or `refunded`.
+    pub status: String,
+    /// Whether `POST /api/v1/redeem` would issue a token now.
+    pub claimable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<i64>,
+    /// Confirmations of a pending transfer at the monitor's last poll.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmations: Option<u64>,
+    /// Confirmations the monitor waits for before storing a payment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_confirmations: Option<u64>,
+    /// Present when the PID was quoted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<PaymentProgress>,
+}
+
+pub async fn payment_status_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let pid = PaymentId::parse(&path.into_inner())?;
+    let mut response = PaymentStatusResponse {
+        pid: pid.clone().into_inner(),
+        status: "unseen".to_string(),
+        claimable: false,
+        amount: None,
+        block_height: None,
+        confirmations: None,
+        required_confirmations: state.service_info().min_confirmations,
+        progress: None,
+    };
+
+    let stored = state.storage().find_payment(&pid).await?;
+    let pending = match stored {
+        Some(_) => Vec::new(),
+        None => state.storage().find_pending(&pid).await?,
+    };
+    if let Some(record) = stored {
+        response.amount = Some(record.amount);
+        response.block_height = Some(record.block_height);
+        let status = match record.status {
+            PaymentState::Confirmed => match awaiting_top_up(&state, &record).await? {
+                Some(quote) => {
+                    response.progress = Some(PaymentProgress::from_quote(&quote));
+                    "awaiting_top_up"
+                }
+                None => {
+                    response.claimable = true;
+                    "claimable"
+                }
+            },
+            other => other.as_str(),
+        };
+        response.status = status.to_string();
+    } else if let Some(best) = pending.iter().max_by_key(|transfer| transfer.confirmations) {
+        // Several transfers to one PID are stored as one payment, so report
+        // their sum and the furthest along.
+        response.amount = Some(pending.iter().map(|transfer| transfer.amount).sum());
+        response.status = match best.state() {
+            PaymentState::Pending => "detected",
+            _ => "unconfirmed",
+        }
+        .to_string();
+        response.block_height = best.block_height;
+        response.confirmations = Some(best.confirmations);
+    } else if let Some(pending) = state.pending_payments().get(&pid, Utc::now()) {
+        response.status = "detected".to_string();
+        response.amount = Some(pending.amount);
+        response.block_height = Some(pending.block_height);
+        response.confirmations = Some(pending.confirmations);
+    }
+
+    if response.progress.is_none() && state.quote_ttl().is_some() {
+        response.progress = state
+            .storage()
+            .find_quote(&pid)
+            .await?
+            .map(|quote| PaymentProgress::from_quote(&quote));
+    }
+
+    counter!("api_payment_status_requests_total", "status" => response.status.clone()).increment(1);
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(response))
+}
@@ -0,0 +1,278 @@
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::PRIMARY_WALLET;
+use anon_ticket_domain::model::{
+    NewPaymentQuote, PaymentId, PaymentQuote, PaymentRecord, PaymentState, QuoteStatus,
+    Reconciliation,
+};
+use anon_ticket_domain::storage::{PaymentStore, QuoteStore, SubaddressStore};
+use chrono::{DateTime, Duration, Utc};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::state::AppState;
+
+use super::checkout::{random_failure, MAX_PID_ATTEMPTS};
+use super::ApiError;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuoteRequest {
+    /// Amount the payer intends to send, in atomic units.
+    pub amount: i64,
+    /// Address book entry to pay; defaults to the primary address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuoteResponse {
+    pub pid: String,
+    pub amount: i64,
+    /// Transfers mined after this instant are not credited to the PID.
+    pub expires_at: DateTime<Utc>,
+    /// `open`, `paid`, or `expired`.
+    pub status: String,
+    pub progress: PaymentProgress,
+    /// Address to pay: a subaddress in subaddress detection mode, otherwise
+    /// the integrated address of the quoted PID on `address_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Address book entry the quote was issued against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_id: Option<String>,
+    /// Present once a payment was ingested for the quote.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconciliation: Option<PaymentReconciliation>,
+}
+
+/// How much of the quoted amount has arrived so far, across all transfers.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct PaymentProgress {
+    pub received_amount: i64,
+    pub expected_amount: i64,
+    /// `received_amount / expected_amount` in whole percent, rounded down
+    /// and capped at 100. The payment becomes claimable at 100.
+    pub percent: u8,
+}
+
+impl PaymentProgress {
+    pub fn from_quote(quote: &PaymentQuote) -> Self {
+        let received = quote.received_amount.unwrap_or(0);
+        Self {
+            received_amount: received,
+            expected_amount: quote.expected_amount,
+            percent: percent_of(received, quote.expected_amount),
+        }
+    }
+}
+
+fn percent_of(received: i64, expected: i64) -> u8 {
+    if expected <= 0 {
+        return 100;
+    }
+    let percent = i128::from(received.max(0)) * 100 / i128::from(expected);
+    percent.min(100) as u8
+}
+
+/// How much was received against a quote, so clients can ask for a top-up.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct PaymentReconciliation {
+    /// `underpaid`, `exact`, or `overpaid`.
+    pub state: String,
+    pub expected_amount: i64,
+    /// Original payment plus any later transfers to the same PID.
+    pub received_amount: i64,
+    /// `received_amount - expected_amount`; negative while underpaid.
+    pub difference: i64,
+}
+
+impl PaymentReconciliation {
+    pub fn from_quote(quote: &PaymentQuote) -> Option<Self> {
+        let received = quote.received_amount?;
+        Some(Self {
+            state: Reconciliation::between(quote.expected_amount, received)
+                .as_str()
+                .to_string(),
+            expected_amount: quote.expected_amount,
+            received_amount: received,
+            difference: received.saturating_sub(quote.expected_amount),
+        })
+    }
+}
+
+/// Whether the unclaimed payment `record` still falls short of its quote.
+/// Quoted payments only become claimable once they cover the full amount;
+/// until then further transfers to the PID add up.
+pub(crate) async fn awaiting_top_up(
+    state: &AppState,
+    record: &PaymentRecord,
+) -> Result<Option<PaymentQuote>, ApiError> {
+    if state.quote_ttl().is_none() || record.status != PaymentState::Confirmed {
+        return Ok(None);
+    }
+    Ok(state
+        .storage()
+        .find_quote(&record.pid)
+        .await?
+        .filter(|quote| record.amount < quote.expected_amount))
+}
+
+/// Reconciliation for `pid`'s quote; `None` when quotes are disabled, the
+/// PID was not quoted, or nothing was received yet.
+pub(crate) async fn quote_reconciliation(
+    state: &AppState,
+    pid: &PaymentId,
+) -> Result<Option<PaymentReconciliation>, ApiError> {
+    if state.quote_ttl().is_none() {
+        return Ok(None);
+    }
+    Ok(state
+        .storage()
+        .find_quote(pid)
+        .await?
+        .as_ref()
+        .and_then(PaymentReconciliation::from_quote))
+}
+
+pub async fn create_quote_handler(
+    state: web::Data<AppState>,
+    payload: web::Json<QuoteRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let Some(ttl) = state.quote_ttl() else {
+        return Err(ApiError::NotFound);
+    };
+    let ttl =
+        Duration::from_std(ttl).map_err(|_| ApiError::Internal("quote ttl out of range".into()))?;
+    let QuoteRequest { amount, address_id } = payload.into_inner();
+    // Anything below the dust floor would be dropped by the monitor.
+    let floor = state.service_info().min_payment_amount.unwrap_or(1).max(1);
+    if amount < floor {
+        counter!("api_quote_requests_total", "status" => "below_minimum").increment(1);
+        return Err(ApiError::InvalidRequest(format!(
+            "amount must be at least {floor}"
+        )));
+    }
+
+    let book = &state.service_info().addresses;
+    let address_id = match address_id {
+        Some(id) if !book.contains(&id) => {
+            counter!("api_quote_requests_total", "status" => "unknown_address").increment(1);
+            return Err(ApiError::InvalidRequest(format!("unknown address_id {id}")));
+        }
+        // Subaddresses all belong to the primary wallet.
+        Some(id) if state.subaddresses().is_some() && id != PRIMARY_WALLET => {
+            counter!("api_quote_requests_total", "status" => "unknown_address").increment(1);
+            return Err(ApiError::InvalidRequest(
+                "subaddress mode only issues quotes on the primary address".into(),
+            ));
+        }
+        Some(id) => Some(id),
+        None => Some(PRIMARY_WALLET.to_string()).filter(|id| book.contains(id)),
+    };
+
+    let now = Utc::now();
+    for _ in 0..MAX_PID_ATTEMPTS {
+        let pid = PaymentId::generate().map_err(random_failure)?;
+        if state.storage().find_payment(&pid).await?.is_some() {
+            continue;
+        }
+        let quote = NewPaymentQuote {
+            pid: pid.clone(),
+            expected_amount: amount,
+            created_at: now,
+            expires_at: now + ttl,
+            address_id: address_id.clone(),
+        };
+        if !state.storage().insert_quote(quote.clone()).await? {
+            continue;
+        }
+        let address = match state.subaddresses() {
+            Some(source) => Some(
+                source
+                    .create_subaddress(&pid)
+                    .await
+                    .map_err(|err| {
+                        counter!("api_quote_requests_total", "status" => "subaddress_failed")
+                            .increment(1);
+                        ApiError::Rpc(err.to_string())
+                    })?
+                    .address,
+            ),
+            None => match &address_id {
+                Some(id) => Some(
+                    book.integrated_address(id, &pid)
+                        .map_err(|err| ApiError::Internal(err.to_string()))?,
+                ),
+                None => None,
+            },
+        };
+        counter!("api_quote_requests_total", "status" => "created").increment(1);
+        return Ok(HttpResponse::Created().json(QuoteResponse {
+            pid: pid.into_inner(),
+            amount,
+            expires_at: quote.expires_at,
+            status: QuoteStatus::Open.as_str().to_string(),
+            progress: PaymentProgress {
+                received_amount: 0,
+                expected_amount: amount,
+                percent: 0,
+            },
+            address,
+            address_id,
+            reconciliation: None,
+        }));
+    }
+
+    counter!("api_quote_requests_total", "status" => "pid_exhausted").increment(1);
+    Err(ApiError::Conflict(
+        "could not allocate an unused payment id".into(),
+    ))
+}
+
+pub async fn quote_status_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    if state.quote_ttl().is_none() {
+        return Err(ApiError::NotFound);
+    }
+    let pid = PaymentId::parse(&path.into_inner())?;
+    let quote = state
+        .storage()
+        .find_quote(&pid)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let address = match state.subaddresses() {
+        Some(_) => state
+            .storage()
+            .find_subaddress_by_pid(&pid)
+            .await?
+            .map(|record| record.address),
+        // The entry may have been removed from the book since.
+        None => quote.address_id.as_deref().and_then(|id| {
+            state
+                .service_info()
+                .addresses
+                .integrated_address(id, &pid)
+                .ok()
+        }),
+    };
+    Ok(HttpResponse::Ok().json(QuoteResponse {
+        status: status_at(&quote, Utc::now()).as_str().to_string(),
+        pid: pid.into_inner(),
+        amount: quote.expected_amount,
+        expires_at: quote.expires_at,
+        progress: PaymentProgress::from_quote(&quote),
+        address,
+        address_id: quote.address_id.clone(),
+        reconciliation: PaymentReconciliation::from_quote(&quote),
+    }))
+}
+
+/// The sweeper runs periodically, so an open quote past its deadline is
+/// reported as expired before it is swept.
+pub(crate) fn status_at(quote: &PaymentQuote, now: DateTime<Utc>) -> QuoteStatus {
+    match quote.status {
+        QuoteStatus::Open if quote.expires_at < now => QuoteStatus::Expired,
+        status => status,
+    }
+}
@@ -1,21 +1,42 @@
-use actix_web::{web, HttpResponse};
+use std::time::Duration;
+
+use actix_web::{
+    http::{header::ContentType, StatusCode},
+    web, HttpRequest, HttpResponse,
+};
+use anon_ticket_domain::config::{CheckoutPreset, OverpaymentPolicy, TokenTier};
 use anon_ticket_domain::model::{
-    derive_service_token, ClaimOutcome, NewServiceToken, PaymentId, PaymentRecord, PaymentStatus,
-    ServiceTokenRecord,
+    derive_service_token, hash_idempotency_key, stored_service_token, AuditActor, ClaimOutcome,
+    IdempotentResponse, NewServiceToken, PaymentId, PaymentRecord, PaymentState, ServiceToken,
+    ServiceTokenRecord, MAX_TOKEN_PASSPHRASE_LEN,
 };
-use anon_ticket_domain::storage::{PaymentStore, TokenStore};
+use anon_ticket_domain::services::telemetry::set_remote_parent;
+use anon_ticket_domain::storage::{CheckoutStore, IdempotencyStore, PaymentStore, TokenStore};
 use anon_ticket_domain::PidCache;
-use chrono::Utc;
-use metrics::counter;
+use chrono::{DateTime, Utc};
+use metrics::{counter, histogram};
 use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use tracing::{info_span, warn, Instrument};
 
-use crate::state::AppState;
+use crate::audit::AuditEvent;
+use crate::state::{expires_after, AppState};
 
+use super::checkout::{check_checkout_terms, verify_client_secret};
+use super::credit::{quote_excess, record_overpayment};
+use super::quote::{awaiting_top_up, quote_reconciliation, PaymentReconciliation};
 use super::ApiError;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RedeemRequest {
     pub pid: String,
+    /// Required when the PID was issued through checkout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    /// Opt-in PIN/passphrase. The token is stored as `HMAC(token, passphrase)`
+    /// and validation calls must present the same passphrase.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,29 +44,289 @@ pub struct RedeemResponse {
     pub status: String,
     pub service_token: String,
     pub balance: i64,
+    /// Tier earned under the checkout preset, when the PID was issued with
+    /// one that defines a tier or tier ladder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    /// Amount received against the PID's quote. An `underpaid` state means
+    /// the client should prompt for a top-up of `-difference`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconciliation: Option<PaymentReconciliation>,
+    /// Signed claims for the token, when redeemed with `format=jwt`. Left out
+    /// for revoked and expired tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwt: Option<String>,
+    /// HMAC-signed token for offline checks, when redeemed with
+    /// `format=signed`. Left out for revoked and expired tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RedeemQuery {
+    /// `json` (the default), `jwt`, which adds [`RedeemResponse::jwt`], or
+    /// `signed`, which adds [`RedeemResponse::signed_token`].
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RedeemFormat {
+    Json,
+    Jwt,
+    Signed,
 }
 
+impl RedeemFormat {
+    fn parse(state: &AppState, raw: Option<&str>) -> Result<Self, ApiError> {
+        match raw {
+            None | Some("json") => Ok(RedeemFormat::Json),
+            Some("jwt") if state.token_jwt().is_some() => Ok(RedeemFormat::Jwt),
+            Some("jwt") => Err(ApiError::InvalidRequest(
+                "format=jwt is not enabled on this server".into(),
+            )),
+            Some("signed") if state.token_signer().is_some() => Ok(RedeemFormat::Signed),
+            Some("signed") => Err(ApiError::InvalidRequest(
+                "format=signed is not enabled on this server".into(),
+            )),
+            Some(_) => Err(ApiError::InvalidRequest(
+                "format must be `json`, `jwt` or `signed`".into(),
+            )),
+        }
+    }
+}
+
+/// Optional client-chosen key; retries carrying it get the first successful
+/// response back instead of redeeming again.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Set to `true` on responses replayed for an [`IDEMPOTENCY_KEY_HEADER`].
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
 pub async fn redeem_handler(
     state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<RedeemQuery>,
     payload: web::Json<RedeemRequest>,
 ) -> Result<HttpResponse, ApiError> {
+    let format = RedeemFormat::parse(&state, query.format.as_deref())?;
+    // The PID stays out of the span: traces leave the host.
+    let span = info_span!(
+        "redeem",
+        idempotent = req.headers().contains_key(IDEMPOTENCY_KEY_HEADER)
+    );
+    set_remote_parent(&span, |name| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    });
+    redeem_with_key(&state, &req, &payload, format)
+        .instrument(span)
+        .await
+}
+
+async fn redeem_with_key(
+    state: &AppState,
+    req: &HttpRequest,
+    payload: &RedeemRequest,
+    format: RedeemFormat,
+) -> Result<HttpResponse, ApiError> {
+    let Some(key) = idempotency_key(req)? else {
+        return Ok(HttpResponse::Ok().json(redeem(state, payload, format).await?));
+    };
+    let key_hash = hash_idempotency_key(key);
+    let request_hash = request_fingerprint(payload, format);
+    if let Some(stored) = state.storage().find_idempotent_response(&key_hash).await? {
+        return replay(stored, &request_hash);
+    }
+
+    // Only successes are kept: a retry after `not found` may well succeed.
+    let response = redeem(state, payload, format).await?;
+    let record = IdempotentResponse {
+        key_hash,
+        request_hash,
+        pid: PaymentId::parse(&payload.pid)?,
+        status: StatusCode::OK.as_u16(),
+        body: serde_json::to_string(&response)
+            .map_err(|err| ApiError::Internal(err.to_string()))?,
+        created_at: Utc::now(),
+    };
+    match state
+        .storage()
+        .insert_idempotent_response(record.clone())
+        .await
+    {
+        Ok(true) => {}
+        // A concurrent retry recorded first; answer the way it did.
+        Ok(false) => {
+            if let Some(stored) = state.storage().find_idempotent_response(&key_hash).await? {
+                return replay(stored, &request_hash);
+            }
+        }
+        // The redeem went through, so report it; a retry simply redeems again.
+        Err(err) => warn!(?err, "failed to record idempotent redeem response"),
+    }
+    Ok(stored_response(&record, false))
+}
+
+fn idempotency_key(req: &HttpRequest) -> Result<Option<&str>, ApiError> {
+    let Some(value) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    match value.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => Ok(Some(key)),
+        _ => Err(ApiError::InvalidRequest(format!(
+            "idempotency key must be 1 to {MAX_IDEMPOTENCY_KEY_LEN} printable ASCII characters"
+        ))),
+    }
+}
+
+/// Hashes every request field, so a key reused for another PID or with
+/// another passphrase is rejected instead of leaking the first response.
+/// The default format adds nothing, so keys stored before `format` existed
+/// still match.
+fn request_fingerprint(request: &RedeemRequest, format: RedeemFormat) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"anon-ticket/redeem|");
+    for field in [
+        Some(request.pid.as_str()),
+        request.client_secret.as_deref(),
+        request.passphrase.as_deref(),
+    ] {
+        match field {
+            Some(value) => {
+                hasher.update([1]);
+                hasher.update((value.len() as u64).to_be_bytes());
+                hasher.update(value.as_bytes());
+            }
+            None => hasher.update([0]),
+        }
+    }
+    match format {
+        RedeemFormat::Json => {}
+        RedeemFormat::Jwt => hasher.update(b"|jwt"),
+        RedeemFormat::Signed => hasher.update(b"|signed"),
+    }
+    hasher.finalize().into()
+}
+
+fn replay(stored: IdempotentResponse, request_hash: &[u8; 32]) -> Result<HttpResponse, ApiError> {
+    if stored.request_hash != *request_hash {
+        counter!("api_redeem_idempotent_replays_total", "outcome" => "mismatch").increment(1);
+        return Err(ApiError::Conflict(
+            "idempotency key was already used with a different request".into(),
+        ));
+    }
+    counter!("api_redeem_idempotent_replays_total", "outcome" => "replayed").increment(1);
+    Ok(stored_response(&stored, true))
+}
+
+fn stored_response(record: &IdempotentResponse, replayed: bool) -> HttpResponse {
+    let mut response =
+        HttpResponse::build(StatusCode::from_u16(record.status).unwrap_or(StatusCode::OK));
+    if replayed {
+        response.insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"));
+    }
+    response
+        .content_type(ContentType::json())
+        .body(record.body.clone())
+}
+
+async fn redeem(
+    state: &AppState,
+    payload: &RedeemRequest,
+    format: RedeemFormat,
+) -> Result<RedeemResponse, ApiError> {
     let pid = PaymentId::parse(&payload.pid).inspect_err(|_| {
         counter!("api_redeem_requests_total", "status" => "invalid_pid").increment(1);
     })?;
+    let passphrase = payload.passphrase.as_deref();
+    validate_passphrase(passphrase).inspect_err(|_| {
+        counter!("api_redeem_requests_total", "status" => "invalid_passphrase").increment(1);
+    })?;
 
     let bloom_positive = state.bloom().map(|b| b.might_contain(&pid));
     if let Some(hit) = bloom_positive {
-        if !hit {
+        if hit {
+            counter!("api_redeem_cache_hints_total", "hint" => "bloom_positive").increment(1);
+        } else if state
+            .shared_cache()
+            .is_some_and(|cache| cache.might_contain(&pid))
+        {
+            // Ingested via another replica after this Bloom was populated.
+            counter!("api_redeem_cache_hints_total", "hint" => "shared_positive").increment(1);
+        } else {
             counter!("api_redeem_cache_hints_total", "hint" => "bloom_absent").increment(1);
             counter!("api_redeem_requests_total", "status" => "bloom_absent").increment(1);
             return Err(ApiError::NotFound);
         }
-        counter!("api_redeem_cache_hints_total", "hint" => "bloom_positive").increment(1);
     }
 
-    match state.storage().claim_payment(&pid).await? {
-        Some(outcome) => handle_success(&state, pid, outcome).await,
-        None => handle_absent(&state, pid, bloom_positive.unwrap_or(false)).await,
+    if !verify_client_secret(state, &pid, payload.client_secret.as_deref()).await? {
+        counter!("api_redeem_requests_total", "status" => "secret_mismatch").increment(1);
+        // Same response as an unknown PID so the binding is not an oracle.
+        return Err(ApiError::NotFound);
+    }
+    let terms = state.storage().find_checkout_terms(&pid).await?;
+    if let Some(reason) = check_checkout_terms(state, &pid, terms.as_ref()).await? {
+        counter!("api_redeem_requests_total", "status" => reason).increment(1);
+        return Err(ApiError::Conflict(format!(
+            "payment does not satisfy the checkout preset ({reason})"
+        )));
+    }
+
+    if state.quote_ttl().is_some() {
+        if let Some(record) = state.storage().find_payment(&pid).await? {
+            if let Some(quote) = awaiting_top_up(state, &record).await? {
+                counter!("api_redeem_requests_total", "status" => "underpaid").increment(1);
+                return Err(ApiError::Conflict(format!(
+                    "payment covers {} of the quoted {}; send the rest to the same payment id",
+                    record.amount, quote.expected_amount
+                )));
+            }
+        }
+    }
+
+    // Presets renamed or removed since checkout simply yield no tier.
+    let preset = terms.and_then(|terms| state.checkout_preset(&terms.preset));
+
+    let response = match state.storage().claim_payment(&pid).await? {
+        Some(outcome) => {
+            handle_success(state, pid.clone(), outcome, passphrase, preset, format).await?
+        }
+        None => {
+            handle_absent(
+                state,
+                pid.clone(),
+                passphrase,
+                bloom_positive.unwrap_or(false),
+                preset,
+                format,
+            )
+            .await?
+        }
+    };
+    Ok(RedeemResponse {
+        reconciliation: quote_reconciliation(state, &pid).await?,
+        ..response
+    })
+}
+
+/// Empty passphrases would silently disable the wrap, so they are rejected.
+pub(crate) fn validate_passphrase(passphrase: Option<&str>) -> Result<(), ApiError> {
+    match passphrase {
+        Some("") => Err(ApiError::InvalidRequest(
+            "passphrase must not be empty".into(),
+        )),
+        Some(value) if value.len() > MAX_TOKEN_PASSPHRASE_LEN => Err(ApiError::InvalidRequest(
+            format!("passphrase must be at most {MAX_TOKEN_PASSPHRASE_LEN} bytes"),
+        )),
+        // Validation calls send the passphrase in a header, so it must be
+        // representable there.
+        Some(value) if !value.bytes().all(|b| (0x20..0x7f).contains(&b)) => Err(
+            ApiError::InvalidRequest("passphrase must be printable ASCII".into()),
+        ),
+        _ => Ok(()),
     }
 }
 
@@ -53,43 +334,82 @@ async fn handle_success(
     state: &AppState,
     pid: PaymentId,
     outcome: ClaimOutcome,
-) -> Result<HttpResponse, ApiError> {
+    passphrase: Option<&str>,
+    preset: Option<&CheckoutPreset>,
+    format: RedeemFormat,
+) -> Result<RedeemResponse, ApiError> {
     let service_token = derive_service_token(&pid, &outcome.txid);
-    let token_record = state
-        .storage()
-        .insert_token(NewServiceToken {
-            token: service_token,
+    let token_record = insert_claimed_token(
+        state,
+        NewServiceToken {
+            token: stored_service_token(&service_token, passphrase),
             pid: pid.clone(),
             amount: outcome.amount,
             issued_at: outcome.claimed_at,
             abuse_score: 0,
-        })
-        .await?;
+            expires_at: state.token_expires_at(outcome.claimed_at),
+            tier: None,
+        },
+    )
+    .await?;
     counter!("api_redeem_requests_total", "status" => "success").increment(1);
+    audit_redeem(state, &pid, "claimed").await;
+    histogram!("api_payment_detect_to_claim_seconds")
+        .record(seconds_between(outcome.detected_at, outcome.claimed_at));
     state.cache().mark_present(&pid);
     state.insert_bloom(&pid);
 
-    Ok(HttpResponse::Ok().json(build_redeem_response("success", token_record)))
+    build_redeem_response(
+        state,
+        "success",
+        service_token,
+        token_record,
+        preset,
+        format,
+    )
 }
 
 async fn handle_absent(
     state: &AppState,
     pid: PaymentId,
+    passphrase: Option<&str>,
     bloom_positive: bool,
-) -> Result<HttpResponse, ApiError> {
+    preset: Option<&CheckoutPreset>,
+    format: RedeemFormat,
+) -> Result<RedeemResponse, ApiError> {
     let maybe_payment = state.storage().find_payment(&pid).await?;
     match maybe_payment {
-        Some(record) if record.status == PaymentStatus::Claimed => {
+        Some(record) if record.status == PaymentState::Claimed => {
             state.cache().mark_present(&pid);
             state.insert_bloom(&pid);
-            let token = ensure_token_record(state, &pid, &record).await?;
+            let Some(token) = ensure_token_record(state, &pid, &record, passphrase).await? else {
+                counter!("api_redeem_requests_total", "status" => "passphrase_mismatch")
+                    .increment(1);
+                audit_redeem(state, &pid, "passphrase_mismatch").await;
+                return Err(ApiError::NotFound);
+            };
             counter!("api_redeem_requests_total", "status" => "already_claimed").increment(1);
-            Ok(HttpResponse::Ok().json(build_redeem_response("already_claimed", token)))
+            audit_redeem(state, &pid, "already_claimed").await;
+            build_redeem_response(
+                state,
+                "already_claimed",
+                derive_service_token(&pid, &record.txid),
+                token,
+                preset,
+                format,
+            )
         }
-        Some(_) => {
+        Some(record) => {
             state.cache().mark_present(&pid);
             state.insert_bloom(&pid);
-            counter!("api_redeem_requests_total", "status" => "pending").increment(1);
+            // Expired, reorged and refunded payments cannot be claimed.
+            let status = match record.status {
+                PaymentState::Expired | PaymentState::Reorged | PaymentState::Refunded => {
+                    record.status.as_str()
+                }
+                _ => "pending",
+            };
+            counter!("api_redeem_requests_total", "status" => status).increment(1);
             Err(ApiError::NotFound)
         }
         None => {
@@ -103,42 +423,135 @@ async fn handle_absent(
     }
 }
 
-fn build_redeem_response(status: &str, record: ServiceTokenRecord) -> RedeemResponse {
-    RedeemResponse {
+/// Audits redemptions that reached a payment. Unknown and pending PIDs are
+/// not recorded: anyone can send those, and they change nothing.
+async fn audit_redeem(state: &AppState, pid: &PaymentId, outcome: &'static str) {
+    AuditEvent {
+        actor: AuditActor::Public,
+        action: "redeem",
+        subject: &pid.to_hex(),
+        reason: "",
+        operator: None,
+        key_id: None,
+        outcome,
+    }
+    .record(state)
+    .await;
+}
+
+/// Non-negative span in seconds, for latency histograms.
+pub(crate) fn seconds_between(start: DateTime<Utc>, end: DateTime<Utc>) -> f64 {
+    (end - start).num_milliseconds().max(0) as f64 / 1000.0
+}
+
+/// `service_token` is the unwrapped token handed to the client; the record
+/// may hold its passphrase-wrapped form.
+fn build_redeem_response(
+    state: &AppState,
+    status: &str,
+    service_token: ServiceToken,
+    record: ServiceTokenRecord,
+    preset: Option<&CheckoutPreset>,
+    format: RedeemFormat,
+) -> Result<RedeemResponse, ApiError> {
+    // Tokens issued before tiers were stored fall back to their preset.
+    let tier = record
+        .tier
+        .as_deref()
+        .or_else(|| preset.and_then(|preset| preset.tier_for(record.amount)));
+    let jwt = match (format, state.token_jwt()) {
+        (RedeemFormat::Jwt, Some(issuer)) => issuer
+            .issue(&service_token, &record, tier, Utc::now())
+            .map_err(|err| ApiError::Internal(err.to_string()))?,
+        _ => None,
+    };
+    let signed_token = match (format, state.token_signer()) {
+        (RedeemFormat::Signed, Some(signer)) => signer.issue(&record, Utc::now()),
+        _ => None,
+    };
+    Ok(RedeemResponse {
         status: status.to_string(),
-        service_token: record.token.into_inner(),
+        service_token: service_token.into_inner(),
         balance: record.amount,
-    }
+        tier: tier.map(str::to_string),
+        reconciliation: None,
+        jwt,
+        signed_token,
+    })
 }
 
-async fn ensure_token_record(
+/// Returns the token record for a claimed payment, issuing it if missing.
+/// `Ok(None)` means a token already exists under a different passphrase (or
+/// none was given for a wrapped one).
+pub(crate) async fn ensure_token_record(
     state: &AppState,
     pid: &PaymentId,
     payment: &PaymentRecord,
-) -> Result<ServiceTokenRecord, ApiError> {
-    let token = derive_service_token(pid, &payment.txid);
-    if let Some(existing) = state.storage().find_token(&token).await? {
-        return Ok(existing);
+    passphrase: Option<&str>,
+) -> Result<Option<ServiceTokenRecord>, ApiError> {
+    let token = stored_service_token(&derive_service_token(pid, &payment.txid), passphrase);
+    if let Some(existing) = state.storage().find_token_by_pid(pid).await? {
+        return Ok((existing.token == token).then_some(existing));
     }
     let issued_at = payment.claimed_at.unwrap_or_else(Utc::now);
-    match state
-        .storage()
-        .insert_token(NewServiceToken {
+    match insert_claimed_token(
+        state,
+        NewServiceToken {
             token: token.clone(),
             pid: pid.clone(),
             amount: payment.amount,
             issued_at,
             abuse_score: 0,
-        })
-        .await
-        .map_err(ApiError::from)
+            // Counted from the claim, so a token re-issued after the janitor
+            // purged it is already expired.
+            expires_at: state.token_expires_at(issued_at),
+            tier: None,
+        },
+    )
+    .await
     {
-        Ok(record) => Ok(record),
-        Err(ApiError::Storage(err)) if err.to_string().to_lowercase().contains("unique") => state
-            .storage()
-            .find_token(&token)
-            .await?
-            .ok_or(ApiError::NotFound),
+        Ok(record) => Ok(Some(record)),
+        Err(ApiError::Storage(err)) if err.to_string().to_lowercase().contains("unique") => {
+            Ok(Some(
+                state
+                    .storage()
+                    .find_token(&token)
+                    .await?
+                    .ok_or(ApiError::NotFound)?,
+            ))
+        }
         Err(other) => Err(other),
     }
 }
+
+/// Issues the token for a claimed payment. When the payment overshot its
+/// quote, the excess is recorded against the token; under the `refund`
+/// policy it is also left out of the balance until credited.
+///
+/// The token's tier follows its balance: a checkout preset's tier wins, then
+/// `API_TOKEN_TIERS`, whose `validity_secs` replaces the default lifetime.
+async fn insert_claimed_token(
+    state: &AppState,
+    mut token: NewServiceToken,
+) -> Result<ServiceTokenRecord, ApiError> {
+    let excess = quote_excess(state, &token.pid, token.amount).await?;
+    if let (Some(excess), OverpaymentPolicy::Refund) = (excess, state.overpayment_policy()) {
+        token.amount -= excess;
+    }
+    let terms = state.storage().find_checkout_terms(&token.pid).await?;
+    let preset_tier = terms
+        .and_then(|terms| state.checkout_preset(&terms.preset))
+        .and_then(|preset| preset.tier_for(token.amount));
+    let tier = state.token_tier_for(token.amount);
+    if let Some(secs) = tier.and_then(TokenTier::validity_secs) {
+        token.expires_at = Some(expires_after(token.issued_at, Duration::from_secs(secs)));
+    }
+    token.tier = preset_tier
+        .or(tier.map(TokenTier::name))
+        .map(str::to_string);
+    let record = state.storage().insert_token(token).await?;
+    if let Some(excess) = excess {
+        record_overpayment(state, &record, excess).await?;
+    }
+    Ok(record)
+}
@@ -0,0 +1,248 @@
+//! Refunds: sending a payment, or part of it, back to the payer.
+//!
+//! An operator records the destination address and amount; the refund then
+//! waits as `pending` until the monitor sends it through wallet-rpc (feature
+//! `refund-transfers`, `MONITOR_SEND_REFUNDS=1`) or an operator sends it by
+//! hand and records the txid. Refunds that have not failed never add up to
+//! more than the payment. Refunding does not revoke the PID's token.
+
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::integrated_address::{address_network, primary_address_network};
+use anon_ticket_domain::model::{
+    AuditActor, NewRefund, PaymentId, Refund, RefundOutcome, RefundStatus,
+};
+use anon_ticket_domain::storage::RefundStore;
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::AuditEvent;
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::payment::require_reason;
+use super::ApiError;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefundSummary {
+    pub id: i64,
+    pub pid: String,
+    pub address: String,
+    pub amount: i64,
+    pub status: String,
+    pub requested_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub txid: Option<String>,
+    pub error: Option<String>,
+}
+
+impl From<Refund> for RefundSummary {
+    fn from(refund: Refund) -> Self {
+        Self {
+            id: refund.id,
+            pid: refund.pid.to_hex(),
+            address: refund.address,
+            amount: refund.amount,
+            status: refund.status.as_str().to_string(),
+            requested_at: refund.requested_at,
+            resolved_at: refund.resolved_at,
+            txid: refund.txid,
+            error: refund.error,
+        }
+    }
+}
+
+/// Where to send how much of a payment, and why.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RefundRequest {
+    pub address: String,
+    /// Atomic units; at most what is left of the payment after earlier
+    /// refunds.
+    pub amount: i64,
+    pub reason: String,
+    pub operator: Option<String>,
+}
+
+/// `POST /internal/v1/payment/{pid}/refund`: queues a refund of a received
+/// payment. Answers `409` when it would exceed what is left to refund.
+pub async fn request_refund_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<RefundRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Admin)?;
+    let raw_pid = path.into_inner();
+    let pid = PaymentId::parse(&raw_pid)?;
+    let request = payload.into_inner();
+    require_reason(&request.reason)?;
+    if request.amount <= 0 {
+        return Err(ApiError::InvalidRequest("amount must be positive".into()));
+    }
+    let address = request.address.trim();
+    check_refund_address(&state, address)?;
+    let audit = |outcome| AuditEvent {
+        actor: AuditActor::Internal,
+        action: "refund.request",
+        subject: &raw_pid,
+        reason: &request.reason,
+        operator: request.operator.as_deref(),
+        key_id: caller.key_id(),
+        outcome,
+    };
+
+    let Some(payment) = state.storage().find_payment_primary(&pid).await? else {
+        counter!("api_admin_actions_total", "action" => "refund_request", "status" => "not_found")
+            .increment(1);
+        audit("not_found").record(&state).await;
+        return Err(ApiError::NotFound);
+    };
+    let Some(refund) = state
+        .storage()
+        .record_refund(NewRefund {
+            pid,
+            address: address.to_string(),
+            amount: request.amount,
+            requested_at: Utc::now(),
+        })
+        .await?
+    else {
+        counter!("api_admin_actions_total", "action" => "refund_request", "status" => "exceeds_payment")
+            .increment(1);
+        audit("exceeds_payment").record(&state).await;
+        return Err(ApiError::Conflict(format!(
+            "refunds would exceed the payment of {}",
+            payment.amount
+        )));
+    };
+    counter!("api_admin_actions_total", "action" => "refund_request", "status" => "pending")
+        .increment(1);
+    audit("pending").record(&state).await;
+    Ok(HttpResponse::Created().json(RefundSummary::from(refund)))
+}
+
+/// The refund address must parse and, when the service knows which network
+/// it is paid on, belong to it.
+fn check_refund_address(state: &AppState, address: &str) -> Result<(), ApiError> {
+    let network = address_network(address)
+        .map_err(|err| ApiError::InvalidRequest(format!("address: {err}")))?;
+    let paid_on: Vec<&str> = state
+        .service_info()
+        .addresses
+        .iter()
+        .filter_map(|(_, primary)| primary_address_network(primary).ok())
+        .collect();
+    if !paid_on.is_empty() && !paid_on.contains(&network) {
+        return Err(ApiError::InvalidRequest(format!(
+            "address is on {network}, not the network payments are received on"
+        )));
+    }
+    Ok(())
+}
+
+/// Records a refund that an operator sent outside the service.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RefundSentRequest {
+    pub txid: String,
+    pub reason: String,
+    pub operator: Option<String>,
+}
+
+/// Operator justification for cancelling a refund.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CancelRefundRequest {
+    pub reason: String,
+    pub operator: Option<String>,
+}
+
+/// `POST /internal/v1/refunds/{id}/sent`: closes an open refund with the
+/// txid of a transfer made by hand.
+pub async fn refund_sent_handler(
+    state: web::Data<AppState>,
+    path: web::Path<i64>,
+    payload: web::Json<RefundSentRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    let request = payload.into_inner();
+    let txid = request.txid.trim().to_string();
+    if txid.is_empty() {
+        return Err(ApiError::InvalidRequest("txid must not be empty".into()));
+    }
+    resolve_refund(
+        &state,
+        path.into_inner(),
+        &request.reason,
+        request.operator.as_deref(),
+        &caller,
+        RefundOutcome::Sent { txid },
+    )
+    .await
+}
+
+/// `POST /internal/v1/refunds/{id}/cancel`: fails an open refund, freeing
+/// its amount for another one.
+pub async fn cancel_refund_handler(
+    state: web::Data<AppState>,
+    path: web::Path<i64>,
+    payload: web::Json<CancelRefundRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    let request = payload.into_inner();
+    let outcome = RefundOutcome::Failed {
+        error: format!("cancelled: {}", request.reason.trim()),
+    };
+    resolve_refund(
+        &state,
+        path.into_inner(),
+        &request.reason,
+        request.operator.as_deref(),
+        &caller,
+        outcome,
+    )
+    .await
+}
+
+async fn resolve_refund(
+    state: &AppState,
+    id: i64,
+    reason: &str,
+    operator: Option<&str>,
+    caller: &Caller,
+    outcome: RefundOutcome,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Admin)?;
+    require_reason(reason)?;
+    let (action, metric) = match outcome.status() {
+        RefundStatus::Sent => ("refund.sent", "refund_sent"),
+        _ => ("refund.cancel", "refund_cancel"),
+    };
+    let Some(existing) = state.storage().find_refund(id).await? else {
+        counter!("api_admin_actions_total", "action" => metric, "status" => "not_found")
+            .increment(1);
+        return Err(ApiError::NotFound);
+    };
+    let subject = existing.pid.to_hex();
+    let audit = |outcome| AuditEvent {
+        actor: AuditActor::Internal,
+        action,
+        subject: &subject,
+        reason,
+        operator,
+        key_id: caller.key_id(),
+        outcome,
+    };
+    let Some(resolved) = state
+        .storage()
+        .resolve_refund(id, outcome, Utc::now())
+        .await?
+    else {
+        counter!("api_admin_actions_total", "action" => metric, "status" => "closed").increment(1);
+        audit("closed").record(state).await;
+        return Err(ApiError::Conflict(format!("refund {id} is already closed")));
+    };
+    counter!("api_admin_actions_total", "action" => metric, "status" => resolved.status.as_str())
+        .increment(1);
+    audit(resolved.status.as_str()).record(state).await;
+    Ok(HttpResponse::Ok().json(RefundSummary::from(resolved)))
+}
@@ -0,0 +1,179 @@
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::model::RevocationCursor;
+use anon_ticket_domain::services::token_signing::{token_digest, token_subject};
+use anon_ticket_domain::storage::TokenStore;
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::ApiError;
+
+/// Subjects returned per request; a full page sets `next_since`.
+const MAX_REVOCATIONS: u64 = 1_000;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RevocationListQuery {
+    /// Unix timestamp; usually the `generated_at` of the previous fetch.
+    #[serde(default)]
+    pub since: Option<i64>,
+}
+
+/// Tokens whose signed form may still verify but must be refused.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RevocationListResponse {
+    /// Pass as `since` next time to fetch only newer revocations.
+    pub generated_at: i64,
+    /// `sub` of each token revoked since `since`, but no longer ago than the
+    /// signed-token lifetime, oldest revocation first.
+    pub revoked: Vec<String>,
+    /// Set when the list was cut at its page size; fetch again from here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_since: Option<i64>,
+}
+
+pub async fn revocations_handler(
+    state: web::Data<AppState>,
+    query: web::Query<RevocationListQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let signer = state.token_signer().ok_or(ApiError::NotFound)?;
+    // Taken first, so a revocation during the query is in the next fetch.
+    let now = Utc::now();
+    let ttl = chrono::Duration::from_std(signer.ttl()).unwrap_or(chrono::Duration::MAX);
+    let horizon = now
+        .checked_sub_signed(ttl)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let since = query
+        .since
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map_or(horizon, |since| since.max(horizon));
+
+    let from = RevocationCursor {
+        revoked_at: since,
+        skip: 0,
+    };
+    let records = state
+        .storage()
+        .revoked_tokens(Some(from), MAX_REVOCATIONS)
+        .await?;
+    let next_since = (records.len() as u64 == MAX_REVOCATIONS)
+        .then(|| records.last().and_then(|record| record.revoked_at))
+        .flatten()
+        .map(|at| at.timestamp());
+    counter!("api_revocation_list_requests_total").increment(1);
+    Ok(HttpResponse::Ok().json(RevocationListResponse {
+        generated_at: now.timestamp(),
+        revoked: records
+            .iter()
+            .map(|record| token_subject(&record.token))
+            .collect(),
+        next_since,
+    }))
+}
+
+/// Revocations per export page unless `limit` asks for fewer.
+const MAX_EXPORT_PAGE: u64 = 1_000;
+
+/// Carries the cursor of a binary export.
+pub const EXPORT_CURSOR_HEADER: &str = "x-revocation-cursor";
+/// `true` when a binary export was cut at its page size.
+pub const EXPORT_MORE_HEADER: &str = "x-revocation-more";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RevocationExportQuery {
+    /// `cursor` of the previous page; omit to start from the first revocation.
+    pub since: Option<String>,
+    pub limit: Option<u64>,
+    /// `json` (default) or `binary`.
+    pub format: Option<String>,
+}
+
+/// A page of the full revocation history, for services that mirror it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RevocationExportResponse {
+    /// SHA3-256 (hex) of each revoked token, oldest revocation first.
+    pub revoked: Vec<String>,
+    /// Pass as `since` next time; absent until anything was revoked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Set when the page was full; fetch again right away.
+    pub more: bool,
+}
+
+fn encode_cursor(cursor: RevocationCursor) -> String {
+    let nanos = cursor.revoked_at.timestamp_nanos_opt().unwrap_or(i64::MAX);
+    format!("{nanos}.{}", cursor.skip)
+}
+
+fn parse_cursor(raw: &str) -> Result<RevocationCursor, ApiError> {
+    let invalid =
+        || ApiError::InvalidRequest("since must be a cursor from a previous export".into());
+    let (nanos, skip) = raw.split_once('.').ok_or_else(invalid)?;
+    Ok(RevocationCursor {
+        revoked_at: DateTime::from_timestamp_nanos(nanos.parse().map_err(|_| invalid())?),
+        skip: skip.parse().map_err(|_| invalid())?,
+    })
+}
+
+/// `GET /internal/v1/revocations`: every revoked token, not only those a
+/// signed token could still carry, paged by an opaque cursor. `format=binary`
+/// answers with the 32-byte hashes back to back and the cursor in headers.
+pub async fn revocation_export_handler(
+    state: web::Data<AppState>,
+    query: web::Query<RevocationExportQuery>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::ReadOnly)?;
+    let query = query.into_inner();
+    let binary = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("binary") => true,
+        Some(_) => {
+            return Err(ApiError::InvalidRequest(
+                "format must be `json` or `binary`".into(),
+            ))
+        }
+    };
+    let limit = match query.limit {
+        None => MAX_EXPORT_PAGE,
+        Some(limit @ 1..=MAX_EXPORT_PAGE) => limit,
+        Some(_) => {
+            return Err(ApiError::InvalidRequest(format!(
+                "limit must be between 1 and {MAX_EXPORT_PAGE}"
+            )))
+        }
+    };
+    let since = query.since.as_deref().map(parse_cursor).transpose()?;
+
+    let records = state.storage().revoked_tokens(since, limit).await?;
+    let cursor = RevocationCursor::advance(since, &records).map(encode_cursor);
+    let more = records.len() as u64 == limit;
+    let format = if binary { "binary" } else { "json" };
+    counter!("api_revocation_exports_total", "format" => format).increment(1);
+
+    if !binary {
+        return Ok(HttpResponse::Ok().json(RevocationExportResponse {
+            revoked: records
+                .iter()
+                .map(|record| token_subject(&record.token))
+                .collect(),
+            cursor,
+            more,
+        }));
+    }
+    let body: Vec<u8> = records
+        .iter()
+        .flat_map(|record| token_digest(&record.token))
+        .collect();
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type("application/octet-stream")
+        .insert_header((EXPORT_MORE_HEADER, more.to_string()));
+    if let Some(cursor) = cursor {
+        response.insert_header((EXPORT_CURSOR_HEADER, cursor));
+    }
+    Ok(response.body(body))
+}
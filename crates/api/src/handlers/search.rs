@@ -0,0 +1,192 @@
+//! One lookup for support: given whatever the user pasted, find the payment,
+//! token and quote behind it.
+//!
+//! The query is classified by shape. Sixteen hex characters are a PID, and
+//! also searched as a token prefix; sixty-four are a txid or a full token and
+//! are tried as both; other hex of at least [`MIN_TOKEN_PREFIX`] characters
+//! is a token prefix. Anything else must be an integrated address, whose PID
+//! is searched. Every PID found along the way is expanded into its payment,
+//! token and quote.
+
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::integrated_address::decode_integrated_address;
+use anon_ticket_domain::model::{PaymentId, PaymentQuote, ServiceToken, ServiceTokenRecord};
+use anon_ticket_domain::storage::{PaymentStore, QuoteStore, TokenStore};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::listing::TokenSummary;
+use super::payment::PaymentResponse;
+use super::quote::status_at;
+use super::ApiError;
+
+/// Shortest token prefix searched, so a stray digit does not list every
+/// token.
+pub const MIN_TOKEN_PREFIX: usize = 8;
+/// Most tokens a prefix returns, and most PIDs expanded per search.
+const SEARCH_LIMIT: u64 = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuoteSummary {
+    pub pid: String,
+    /// `open`, `paid`, or `expired`.
+    pub status: String,
+    pub expected_amount: i64,
+    pub received_amount: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub address_id: Option<String>,
+}
+
+impl QuoteSummary {
+    fn at(quote: PaymentQuote, now: DateTime<Utc>) -> Self {
+        Self {
+            status: status_at(&quote, now).as_str().to_string(),
+            pid: quote.pid.into_inner(),
+            expected_amount: quote.expected_amount,
+            received_amount: quote.received_amount,
+            created_at: quote.created_at,
+            expires_at: quote.expires_at,
+            address_id: quote.address_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    /// How the query was read: any of `pid`, `txid`, `token`,
+    /// `token_prefix` and `integrated_address`.
+    pub matched_as: Vec<String>,
+    pub payments: Vec<PaymentResponse>,
+    pub tokens: Vec<TokenSummary>,
+    pub quotes: Vec<QuoteSummary>,
+}
+
+/// What a query may be; a hex string can be several at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SearchKey {
+    Pid(PaymentId),
+    Txid(String),
+    Token(ServiceToken),
+    TokenPrefix(String),
+    IntegratedAddress(PaymentId),
+}
+
+impl SearchKey {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SearchKey::Pid(_) => "pid",
+            SearchKey::Txid(_) => "txid",
+            SearchKey::Token(_) => "token",
+            SearchKey::TokenPrefix(_) => "token_prefix",
+            SearchKey::IntegratedAddress(_) => "integrated_address",
+        }
+    }
+}
+
+fn classify(query: &str) -> Result<Vec<SearchKey>, ApiError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(ApiError::InvalidRequest("q must not be empty".into()));
+    }
+    if query.len() <= 64 && query.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        let hex = query.to_ascii_lowercase();
+        return match hex.len() {
+            64 => Ok(vec![
+                SearchKey::Txid(hex.clone()),
+                SearchKey::Token(ServiceToken::parse(&hex)?),
+            ]),
+            16 => Ok(vec![
+                SearchKey::Pid(PaymentId::parse(&hex)?),
+                SearchKey::TokenPrefix(hex),
+            ]),
+            len if len >= MIN_TOKEN_PREFIX => Ok(vec![SearchKey::TokenPrefix(hex)]),
+            _ => Err(ApiError::InvalidRequest(format!(
+                "token prefixes need at least {MIN_TOKEN_PREFIX} hex characters"
+            ))),
+        };
+    }
+    let (_, pid) = decode_integrated_address(query).map_err(|_| {
+        ApiError::InvalidRequest("q is not a txid, PID, token prefix or integrated address".into())
+    })?;
+    Ok(vec![SearchKey::IntegratedAddress(pid)])
+}
+
+/// `GET /internal/search?q=`. Returns tokens, so it needs `support`. A query
+/// that matches nothing answers `200` with empty lists.
+pub async fn search_handler(
+    state: web::Data<AppState>,
+    query: web::Query<SearchQuery>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let keys = classify(&query.q)?;
+    let storage = state.storage();
+    let mut pids: Vec<PaymentId> = Vec::new();
+    let mut tokens: Vec<ServiceTokenRecord> = Vec::new();
+    for key in &keys {
+        match key {
+            SearchKey::Pid(pid) | SearchKey::IntegratedAddress(pid) => pids.push(pid.clone()),
+            SearchKey::Txid(txid) => {
+                if let Some(payment) = storage.find_payment_by_txid(txid).await? {
+                    pids.push(payment.pid);
+                }
+            }
+            SearchKey::Token(token) => {
+                if let Some(record) = storage.find_token(token).await? {
+                    pids.push(record.pid.clone());
+                    tokens.push(record);
+                }
+            }
+            SearchKey::TokenPrefix(prefix) => {
+                let (first, last) = ServiceToken::prefix_range(prefix)?;
+                for record in storage
+                    .find_tokens_in_range(&first, &last, SEARCH_LIMIT)
+                    .await?
+                {
+                    pids.push(record.pid.clone());
+                    tokens.push(record);
+                }
+            }
+        }
+    }
+    let mut unique: Vec<PaymentId> = Vec::new();
+    for pid in pids {
+        if !unique.contains(&pid) {
+            unique.push(pid);
+        }
+    }
+    unique.truncate(SEARCH_LIMIT as usize);
+
+    let now = Utc::now();
+    let mut payments = Vec::new();
+    let mut quotes = Vec::new();
+    for pid in &unique {
+        if let Some(payment) = storage.find_payment(pid).await? {
+            payments.push(PaymentResponse::from(payment));
+        }
+        if let Some(record) = storage.find_token_by_pid(pid).await? {
+            if !tokens.iter().any(|known| known.token == record.token) {
+                tokens.push(record);
+            }
+        }
+        if let Some(quote) = storage.find_quote(pid).await? {
+            quotes.push(QuoteSummary::at(quote, now));
+        }
+    }
+    Ok(HttpResponse::Ok().json(SearchResponse {
+        matched_as: keys.iter().map(|key| key.as_str().to_string()).collect(),
+        payments,
+        tokens: tokens.into_iter().map(TokenSummary::from).collect(),
+        quotes,
+    }))
+}
@@ -0,0 +1,214 @@
+//! Transfers the monitor skipped because their PID did not parse or their
+//! amount did not convert. The monitor journals them in `skipped_transfers`;
+//! operators list them here and, once the cause is fixed, re-process or
+//! dismiss them.
+
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::model::{AuditActor, PaymentId, SkippedTransfer};
+use anon_ticket_domain::services::cache::PidCache;
+use anon_ticket_domain::storage::SkippedTransferStore;
+use anon_ticket_monitor::pipeline::{process_entry, IngestRules};
+use anon_ticket_monitor::worker::MonitorError;
+use anon_ticket_monitor::TransferEntry;
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::AuditEvent;
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::listing::{page_size, paginate};
+use super::payment::require_reason;
+use super::ApiError;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SkippedQuery {
+    /// Txid of the last transfer on the previous page.
+    pub after: Option<String>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkippedTransferSummary {
+    pub txid: String,
+    pub wallet: String,
+    pub reason: String,
+    /// As the wallet reported it, which need not be a valid PID.
+    pub payment_id: Option<String>,
+    /// Atomic units in decimal; may not fit an `i64`.
+    pub amount: String,
+    pub block_height: Option<i64>,
+    pub timestamp: u64,
+    pub skipped_at: DateTime<Utc>,
+}
+
+impl From<SkippedTransfer> for SkippedTransferSummary {
+    fn from(transfer: SkippedTransfer) -> Self {
+        Self {
+            txid: transfer.txid,
+            wallet: transfer.wallet,
+            reason: transfer.reason,
+            payment_id: transfer.payment_id,
+            amount: transfer.amount,
+            block_height: transfer.block_height,
+            timestamp: transfer.timestamp,
+            skipped_at: transfer.skipped_at,
+        }
+    }
+}
+
+/// Operator justification for re-processing or dismissing a transfer.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SkippedActionRequest {
+    pub reason: String,
+    pub operator: Option<String>,
+}
+
+/// `GET /internal/v1/skipped-transfers`, ordered by txid. Entries carry
+/// payment ids, so this needs the `support` role.
+pub async fn list_skipped_handler(
+    state: web::Data<AppState>,
+    query: web::Query<SkippedQuery>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let query = query.into_inner();
+    let limit = page_size(query.limit)?;
+    let rows = state
+        .storage()
+        .list_skipped(query.after.as_deref(), limit + 1)
+        .await?;
+    Ok(HttpResponse::Ok().json(paginate(
+        rows,
+        limit,
+        |transfer| transfer.txid.clone(),
+        SkippedTransferSummary::from,
+    )))
+}
+
+/// `POST /internal/v1/skipped-transfers/{txid}/reprocess`: runs a journaled
+/// transfer through ingestion again, with the minimum payment amount but,
+/// as for an injected payment, without requiring a quote. The entry is
+/// removed once the payment is stored; a transfer that is still rejected
+/// answers `409` and stays journaled.
+pub async fn reprocess_skipped_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<SkippedActionRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Admin)?;
+    let txid = path.into_inner();
+    let request = payload.into_inner();
+    require_reason(&request.reason)?;
+    let audit = |outcome| AuditEvent {
+        actor: AuditActor::Internal,
+        action: "transfer.reprocess",
+        subject: &txid,
+        reason: &request.reason,
+        operator: request.operator.as_deref(),
+        key_id: caller.key_id(),
+        outcome,
+    };
+    let Some(transfer) = state.storage().find_skipped(&txid).await? else {
+        counter!("api_admin_actions_total", "action" => "transfer_reprocess", "status" => "not_found")
+            .increment(1);
+        audit("not_found").record(&state).await;
+        return Err(ApiError::NotFound);
+    };
+    let (pid, outcome) = match reprocess(&state, &transfer).await? {
+        Ok(pid) => (pid, "persisted"),
+        Err((outcome, message)) => {
+            counter!("api_admin_actions_total", "action" => "transfer_reprocess", "status" => outcome)
+                .increment(1);
+            audit(outcome).record(&state).await;
+            return Err(ApiError::Conflict(message));
+        }
+    };
+    state.storage().delete_skipped(&txid).await?;
+    // Mirror the monitor hooks so bloom-gated redemption sees the new PID.
+    state.cache().mark_present(&pid);
+    state.insert_bloom(&pid);
+
+    counter!("api_admin_actions_total", "action" => "transfer_reprocess", "status" => outcome)
+        .increment(1);
+    audit(outcome).record(&state).await;
+    Ok(HttpResponse::Ok().json(SkippedTransferSummary::from(transfer)))
+}
+
+/// The PID of the stored payment, or the outcome and message to answer
+/// `409` with when the transfer is still skipped.
+async fn reprocess(
+    state: &AppState,
+    transfer: &SkippedTransfer,
+) -> Result<Result<PaymentId, (&'static str, String)>, ApiError> {
+    let Ok(amount) = transfer.amount.parse::<i64>() else {
+        return Ok(Err((
+            "amount_overflow",
+            format!("amount {} does not fit an i64", transfer.amount),
+        )));
+    };
+    let Some(pid) = transfer
+        .payment_id
+        .as_deref()
+        .and_then(|raw| PaymentId::parse(raw).ok())
+    else {
+        return Ok(Err(("invalid_pid", "payment id is still invalid".into())));
+    };
+    let entry = TransferEntry {
+        txid: transfer.txid.clone(),
+        amount,
+        height: transfer.block_height,
+        timestamp: transfer.timestamp,
+        payment_id: Some(pid.to_hex()),
+    };
+    let rules = IngestRules::new(state.service_info().min_payment_amount.unwrap_or(0));
+    let persisted = process_entry(state.storage(), &transfer.wallet, &entry, rules, None)
+        .await
+        .map_err(|err| match err {
+            MonitorError::Storage(err) => ApiError::Storage(err),
+            other => ApiError::Internal(other.to_string()),
+        })?;
+    if !persisted {
+        return Ok(Err((
+            "rejected",
+            "transfer is still rejected by ingestion; see the monitor log".into(),
+        )));
+    }
+    Ok(Ok(pid))
+}
+
+/// `DELETE /internal/v1/skipped-transfers/{txid}`: drops a journaled
+/// transfer that will not be re-processed.
+pub async fn dismiss_skipped_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<SkippedActionRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Admin)?;
+    let txid = path.into_inner();
+    let request = payload.into_inner();
+    require_reason(&request.reason)?;
+    let removed = state.storage().delete_skipped(&txid).await?;
+    let outcome = if removed { "dismissed" } else { "not_found" };
+    counter!("api_admin_actions_total", "action" => "transfer_dismiss", "status" => outcome)
+        .increment(1);
+    AuditEvent {
+        actor: AuditActor::Internal,
+        action: "transfer.dismiss",
+        subject: &txid,
+        reason: &request.reason,
+        operator: request.operator.as_deref(),
+        key_id: caller.key_id(),
+        outcome,
+    }
+    .record(&state)
+    .await;
+    if !removed {
+        return Err(ApiError::NotFound);
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,21 +1,55 @@
-use actix_web::{web, HttpResponse};
-use anon_ticket_domain::model::{RevokeTokenRequest, ServiceToken};
-use anon_ticket_domain::storage::TokenStore;
-use chrono::{DateTime, Utc};
-use metrics::counter;
+use std::collections::HashSet;
+
+use actix_web::{
+    http::header::{self, CacheControl, CacheDirective, EntityTag, Header, IfNoneMatch},
+    web, HttpRequest, HttpResponse,
+};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::model::{
+    self, stored_service_token, AuditActor, MergeTokensRequest, PaymentId, RevokeTokenRequest,
+    ServiceToken, ServiceTokenRecord, SplitTokenRequest, SuspendTokenRequest, TokenTransition,
+};
+use anon_ticket_domain::storage::{RenewalStore, TokenStore};
+use chrono::{DateTime, Duration, Utc};
+use metrics::{counter, histogram};
 use serde::{Deserialize, Serialize};
 use strum_macros::AsRefStr;
+use tracing::warn;
 
+use crate::audit::AuditEvent;
+use crate::auth::Caller;
 use crate::state::AppState;
 
+use super::checkout::random_failure;
+use super::redeem::{seconds_between, validate_passphrase};
 use super::ApiError;
 
+/// Header carrying the passphrase for tokens redeemed with one.
+pub const PASSPHRASE_HEADER: &str = "x-anon-token-passphrase";
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, AsRefStr)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum TokenState {
     Active,
+    /// Refused until an operator resumes it; unlike revocation, temporary.
+    Suspended,
     Revoked,
+    /// The token outlived `API_TOKEN_TTL_SECS`, or its subscription lapsed.
+    /// Only the latter is reactivated by a renewal payment to the same PID.
+    Expired,
+}
+
+impl From<model::TokenState> for TokenState {
+    fn from(state: model::TokenState) -> Self {
+        match state {
+            // Stored tokens are never merely issued.
+            model::TokenState::Issued | model::TokenState::Active => TokenState::Active,
+            model::TokenState::Suspended => TokenState::Suspended,
+            model::TokenState::Revoked => TokenState::Revoked,
+            model::TokenState::Expired => TokenState::Expired,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,20 +58,241 @@ pub struct TokenStatusResponse {
     pub amount: i64,
     pub issued_at: DateTime<Utc>,
     pub revoked_at: Option<DateTime<Utc>>,
+    /// Present while a suspension is in force.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suspended_at: Option<DateTime<Utc>>,
+    /// When the suspension lifts by itself; absent for one that lasts until
+    /// an operator resumes the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suspended_until: Option<DateTime<Utc>>,
     pub abuse_score: i16,
+    /// Only present for tokens that expire (a token TTL or subscriptions).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Tier earned by the payment, for gating features by payment size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    /// The tier's quota from `API_TOKEN_TIERS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<u64>,
+    /// Present while the token is flagged for review, e.g. because a reorg
+    /// orphaned its payment after it was claimed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_reason: Option<String>,
 }
 
+/// Slim projection of a token used by UIs that only need remaining credit.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenBalanceResponse {
+    pub status: TokenState,
+    pub balance: i64,
+    /// `null` for tokens that never expire.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// How long clients/proxies may reuse a balance response before revalidating.
+const BALANCE_MAX_AGE_SECS: u32 = 5;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct RevokeRequest {
     pub reason: Option<String>,
     pub abuse_score: Option<i16>,
 }
 
+/// Exactly one of `until` and `duration_secs` bounds the suspension; with
+/// neither it lasts until the token is resumed.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SuspendRequest {
+    pub reason: Option<String>,
+    pub until: Option<DateTime<Utc>>,
+    pub duration_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SplitRequest {
+    /// Atomic units moved onto the new token.
+    pub amount: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitResponse {
+    /// The new token. Returned once; it is not derivable from any payment.
+    pub service_token: String,
+    pub amount: i64,
+    /// Balance left on the original token.
+    pub remaining_balance: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SpendRequest {
+    /// Atomic units debited from the balance.
+    pub amount: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SpendResponse {
+    pub spent: i64,
+    /// Balance left after this debit.
+    pub balance: i64,
+}
+
+/// Upper bound on tokens consumed by one merge, keeping the transaction short.
+pub const MAX_MERGE_TOKENS: usize = 16;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MergeRequest {
+    /// Between 2 and [`MAX_MERGE_TOKENS`] distinct tokens.
+    pub tokens: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeResponse {
+    /// The consolidated token. Returned once.
+    pub service_token: String,
+    pub balance: i64,
+    /// Subscription time is the merged tokens' remaining validity added up;
+    /// a token TTL ends with the latest-expiring source. Only present for
+    /// tokens that expire.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub merged: usize,
+}
+
+/// Resolves the path token to its stored form, applying the passphrase wrap
+/// when the caller sends [`PASSPHRASE_HEADER`].
+fn lookup_token(raw: &str, req: &HttpRequest) -> Result<ServiceToken, ApiError> {
+    let passphrase = req
+        .headers()
+        .get(PASSPHRASE_HEADER)
+        .map(|value| {
+            value
+                .to_str()
+                .map_err(|_| ApiError::InvalidRequest("passphrase must be printable ASCII".into()))
+        })
+        .transpose()?;
+    resolve_token(raw, passphrase)
+}
+
+/// Parses `raw` and applies the passphrase wrap, if any, to get the form the
+/// token is stored under.
+pub(crate) fn resolve_token(raw: &str, passphrase: Option<&str>) -> Result<ServiceToken, ApiError> {
+    let token = ServiceToken::parse(raw)?;
+    validate_passphrase(passphrase)?;
+    Ok(stored_service_token(&token, passphrase))
+}
+
+/// Feeds the claim → first validation funnel histogram the first time a token
+/// is looked up. Failures only cost the sample, never the response.
+pub(crate) async fn observe_first_validation(state: &AppState, record: &ServiceTokenRecord) {
+    if state.storage().is_read_only() {
+        return;
+    }
+    let now = Utc::now();
+    match state
+        .storage()
+        .record_first_validation(&record.token, now)
+        .await
+    {
+        Ok(true) => histogram!("api_token_claim_to_first_validation_seconds")
+            .record(seconds_between(record.issued_at, now)),
+        Ok(false) => {}
+        Err(err) => warn!(error = %err, "failed to record first token validation"),
+    }
+}
+
+/// Effective expiry for `record`: the earlier of its own lifetime and its
+/// subscription expiry. `None` when neither applies.
+pub(super) async fn token_expiry(
+    state: &AppState,
+    record: &ServiceTokenRecord,
+) -> Result<Option<DateTime<Utc>>, ApiError> {
+    let subscription = subscription_expiry(state, record).await?;
+    Ok(earliest(record.expires_at, subscription))
+}
+
+fn earliest(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Subscription expiry for `record`: the first period starts at issuance (or
+/// at the expiry pinned by a merge) and each renewal payment to its PID
+/// extends it. `None` when subscriptions are disabled.
+async fn subscription_expiry(
+    state: &AppState,
+    record: &ServiceTokenRecord,
+) -> Result<Option<DateTime<Utc>>, ApiError> {
+    let Some(period) = state.subscription_period() else {
+        return Ok(None);
+    };
+    let renewals = state.storage().find_renewals(&record.pid).await?;
+    let renewals = renewals
+        .iter()
+        .map(|renewal| (renewal.detected_at, renewal.amount));
+    Ok(
+        match state.storage().find_pinned_expiry(&record.token).await? {
+            Some(pinned) => period.extend(Some(pinned), renewals),
+            None => period
+                .expires_at(std::iter::once((record.issued_at, record.amount)).chain(renewals)),
+        },
+    )
+}
+
+pub(super) fn token_state(
+    record: &ServiceTokenRecord,
+    expires_at: Option<DateTime<Utc>>,
+) -> TokenState {
+    record.state_at(expires_at, Utc::now()).into()
+}
+
+/// Refuses tokens that cannot move balance at `now`, counting the refusal
+/// under `endpoint`.
+fn require_usable(
+    record: &ServiceTokenRecord,
+    endpoint: &'static str,
+    now: DateTime<Utc>,
+) -> Result<(), ApiError> {
+    let state = record.state(now);
+    if state.is_usable() {
+        return Ok(());
+    }
+    Err(refuse(state, endpoint))
+}
+
+pub(crate) async fn status_response(
+    state: &AppState,
+    record: ServiceTokenRecord,
+) -> Result<TokenStatusResponse, ApiError> {
+    let expires_at = token_expiry(state, &record).await?;
+    let review = state.storage().find_token_review(&record.token).await?;
+    let suspended = record.is_suspended_at(Utc::now());
+    Ok(TokenStatusResponse {
+        status: token_state(&record, expires_at),
+        amount: record.amount,
+        issued_at: record.issued_at,
+        revoked_at: record.revoked_at,
+        suspended_at: record.suspended_at.filter(|_| suspended),
+        suspended_until: record.suspended_until.filter(|_| suspended),
+        abuse_score: record.abuse_score,
+        expires_at,
+        quota: record
+            .tier
+            .as_deref()
+            .and_then(|tier| state.token_tier(tier))
+            .and_then(|tier| tier.quota()),
+        tier: record.tier,
+        review_reason: review.map(|review| review.reason),
+    })
+}
+
 pub async fn token_status_handler(
     state: web::Data<AppState>,
     path: web::Path<String>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, ApiError> {
-    let token = ServiceToken::parse(&path.into_inner())?;
+    let token = lookup_token(&path.into_inner(), &req)?;
     let record = match state.storage().find_token(&token).await? {
         Some(record) => record,
         None => {
@@ -46,29 +301,111 @@ pub async fn token_status_handler(
             return Err(ApiError::NotFound);
         }
     };
-    let status = if record.revoked_at.is_some() {
-        TokenState::Revoked
-    } else {
-        TokenState::Active
-    };
-    let status_tag = status.as_ref().to_owned();
+    observe_first_validation(&state, &record).await;
+    let body = status_response(&state, record).await?;
+    let status_tag = body.status.as_ref().to_owned();
     counter!("api_token_requests_total", "endpoint" => "status", "status" => status_tag)
         .increment(1);
-    Ok(HttpResponse::Ok().json(TokenStatusResponse {
+    Ok(HttpResponse::Ok().json(body))
+}
+
+pub async fn token_balance_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let token = lookup_token(&path.into_inner(), &req)?;
+    let record = match state.storage().find_token(&token).await? {
+        Some(record) => record,
+        None => {
+            counter!("api_token_requests_total", "endpoint" => "balance", "status" => "not_found")
+                .increment(1);
+            return Err(ApiError::NotFound);
+        }
+    };
+    observe_first_validation(&state, &record).await;
+    let expires_at = token_expiry(&state, &record).await?;
+    let status = token_state(&record, expires_at);
+    let body = TokenBalanceResponse {
         status,
-        amount: record.amount,
-        issued_at: record.issued_at,
-        revoked_at: record.revoked_at,
-        abuse_score: record.abuse_score,
-    }))
+        balance: record.amount,
+        expires_at,
+    };
+    let etag = balance_etag(&body);
+    let cache_control = CacheControl(vec![
+        CacheDirective::Private,
+        CacheDirective::MaxAge(BALANCE_MAX_AGE_SECS),
+    ]);
+
+    let not_modified = match IfNoneMatch::parse(&req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        Err(_) => false,
+    };
+    let status_tag = if not_modified {
+        "not_modified".to_owned()
+    } else {
+        status.as_ref().to_owned()
+    };
+    counter!("api_token_requests_total", "endpoint" => "balance", "status" => status_tag)
+        .increment(1);
+
+    if not_modified {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .insert_header(cache_control)
+            .finish());
+    }
+    Ok(HttpResponse::Ok()
+        .insert_header(header::ETag(etag))
+        .insert_header(cache_control)
+        .json(body))
+}
+
+/// Weak validator covering every field of the balance projection so any
+/// change (debit, revocation, expiry) invalidates cached copies.
+fn balance_etag(body: &TokenBalanceResponse) -> EntityTag {
+    let expires = body
+        .expires_at
+        .map(|ts| ts.timestamp().to_string())
+        .unwrap_or_default();
+    EntityTag::new_weak(format!(
+        "{}-{}-{}",
+        body.status.as_ref(),
+        body.balance,
+        expires
+    ))
 }
 
 pub async fn revoke_token_handler(
     state: web::Data<AppState>,
     path: web::Path<String>,
     payload: web::Json<RevokeRequest>,
+    caller: Caller,
 ) -> Result<HttpResponse, ApiError> {
-    let token = ServiceToken::parse(&path.into_inner())?;
+    let body = revoke_token(&state, &path.into_inner(), payload.into_inner(), &caller).await?;
+    Ok(HttpResponse::Ok().json(body))
+}
+
+/// Revocation shared by the HTTP and gRPC internal APIs; revoking twice
+/// reports the existing revocation.
+pub(crate) async fn revoke_token(
+    state: &AppState,
+    raw_token: &str,
+    payload: RevokeRequest,
+    caller: &Caller,
+) -> Result<TokenStatusResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let token = ServiceToken::parse(raw_token)?;
+    let audit = |outcome| AuditEvent {
+        actor: AuditActor::Internal,
+        action: "token.revoke",
+        subject: raw_token,
+        reason: payload.reason.as_deref().unwrap_or(""),
+        operator: None,
+        key_id: caller.key_id(),
+        outcome,
+    };
     let existing = match state.storage().find_token(&token).await? {
         Some(record) => record,
         None => {
@@ -77,20 +414,15 @@ pub async fn revoke_token_handler(
             return Err(ApiError::NotFound);
         }
     };
-    if existing.revoked_at.is_some() {
+    if !existing.state(Utc::now()).allows(TokenTransition::Revoke) {
         counter!(
             "api_token_requests_total",
             "endpoint" => "revoke",
             "status" => "already_revoked"
         )
         .increment(1);
-        return Ok(HttpResponse::Ok().json(TokenStatusResponse {
-            status: TokenState::Revoked,
-            amount: existing.amount,
-            issued_at: existing.issued_at,
-            revoked_at: existing.revoked_at,
-            abuse_score: existing.abuse_score,
-        }));
+        audit("already_revoked").record(state).await;
+        return status_response(state, existing).await;
     }
     let updated = state
         .storage()
@@ -103,11 +435,315 @@ pub async fn revoke_token_handler(
         .ok_or(ApiError::NotFound)?;
     counter!("api_token_requests_total", "endpoint" => "revoke", "status" => "revoked")
         .increment(1);
-    Ok(HttpResponse::Ok().json(TokenStatusResponse {
-        status: TokenState::Revoked,
-        amount: updated.amount,
-        issued_at: updated.issued_at,
-        revoked_at: updated.revoked_at,
-        abuse_score: updated.abuse_score,
+    audit("revoked").record(state).await;
+    if let Some(webhooks) = state.webhooks() {
+        webhooks.token_revoked(&updated);
+    }
+    status_response(state, updated).await
+}
+
+/// Refuses a token until `until`, for `duration_secs`, or until it is
+/// resumed; the temporary counterpart of revocation for abuse handling.
+pub async fn suspend_token_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<SuspendRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let raw_token = path.into_inner();
+    let token = ServiceToken::parse(&raw_token)?;
+    let payload = payload.into_inner();
+    let now = Utc::now();
+    let until = suspension_end(&payload, now).inspect_err(|_| {
+        counter!("api_token_requests_total", "endpoint" => "suspend", "status" => "invalid_until")
+            .increment(1);
+    })?;
+    let existing = find_for(&state, &token, "suspend").await?;
+    let current = existing.state(now);
+    if !current.allows(TokenTransition::Suspend) {
+        return Err(refuse(current, "suspend"));
+    }
+    let Some(updated) = state
+        .storage()
+        .suspend_token(SuspendTokenRequest {
+            token,
+            reason: payload.reason.clone(),
+            until,
+        })
+        .await?
+    else {
+        // Lost a race with another state change; report where it landed.
+        let current = find_for(&state, &existing.token, "suspend")
+            .await?
+            .state(now);
+        return Err(refuse(current, "suspend"));
+    };
+    counter!("api_token_requests_total", "endpoint" => "suspend", "status" => "suspended")
+        .increment(1);
+    AuditEvent {
+        actor: AuditActor::Internal,
+        action: "token.suspend",
+        subject: &raw_token,
+        reason: payload.reason.as_deref().unwrap_or(""),
+        operator: None,
+        key_id: caller.key_id(),
+        outcome: "suspended",
+    }
+    .record(&state)
+    .await;
+    Ok(HttpResponse::Ok().json(status_response(&state, updated).await?))
+}
+
+/// Lifts a suspension before it lapses, or one with no end.
+pub async fn unsuspend_token_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let raw_token = path.into_inner();
+    let token = ServiceToken::parse(&raw_token)?;
+    let existing = find_for(&state, &token, "unsuspend").await?;
+    let current = existing.state(Utc::now());
+    if !current.allows(TokenTransition::Resume) {
+        return Err(refuse(current, "unsuspend"));
+    }
+    let Some(updated) = state.storage().resume_token(&token).await? else {
+        let current = find_for(&state, &token, "unsuspend")
+            .await?
+            .state(Utc::now());
+        return Err(refuse(current, "unsuspend"));
+    };
+    counter!("api_token_requests_total", "endpoint" => "unsuspend", "status" => "resumed")
+        .increment(1);
+    AuditEvent {
+        actor: AuditActor::Internal,
+        action: "token.unsuspend",
+        subject: &raw_token,
+        reason: "",
+        operator: None,
+        key_id: caller.key_id(),
+        outcome: "resumed",
+    }
+    .record(&state)
+    .await;
+    Ok(HttpResponse::Ok().json(status_response(&state, updated).await?))
+}
+
+/// When a suspension requested at `now` should lift, if ever.
+fn suspension_end(
+    payload: &SuspendRequest,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, ApiError> {
+    let until = match (payload.until, payload.duration_secs) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::InvalidRequest(
+                "send either until or duration_secs, not both".into(),
+            ))
+        }
+        (Some(until), None) => until,
+        (None, Some(secs)) => i64::try_from(secs)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .and_then(|duration| now.checked_add_signed(duration))
+            .ok_or_else(|| ApiError::InvalidRequest("duration_secs out of range".into()))?,
+        (None, None) => return Ok(None),
+    };
+    if until <= now {
+        return Err(ApiError::InvalidRequest(
+            "suspension must end in the future".into(),
+        ));
+    }
+    Ok(Some(until))
+}
+
+async fn find_for(
+    state: &AppState,
+    token: &ServiceToken,
+    endpoint: &'static str,
+) -> Result<ServiceTokenRecord, ApiError> {
+    match state.storage().find_token(token).await? {
+        Some(record) => Ok(record),
+        None => {
+            counter!("api_token_requests_total", "endpoint" => endpoint, "status" => "not_found")
+                .increment(1);
+            Err(ApiError::NotFound)
+        }
+    }
+}
+
+/// A state change `endpoint` cannot make from `current`.
+fn refuse(current: model::TokenState, endpoint: &'static str) -> ApiError {
+    counter!("api_token_requests_total", "endpoint" => endpoint, "status" => current.as_str())
+        .increment(1);
+    ApiError::Conflict(format!("token is {}", current.as_str()))
+}
+
+/// Debits part of a token's balance so downstream services can meter usage
+/// against it. Overdrafts are rejected; the debit never goes below zero.
+pub async fn spend_token_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<SpendRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    // Subscription periods are derived from the issued amount.
+    if state.subscription_period().is_some() {
+        return Err(ApiError::NotFound);
+    }
+    let token = lookup_token(&path.into_inner(), &req)?;
+    if payload.amount <= 0 {
+        counter!("api_token_requests_total", "endpoint" => "spend", "status" => "invalid_amount")
+            .increment(1);
+        return Err(ApiError::InvalidRequest("amount must be positive".into()));
+    }
+    if let Some(balance) = state.storage().debit_token(&token, payload.amount).await? {
+        counter!("api_token_requests_total", "endpoint" => "spend", "status" => "spent")
+            .increment(1);
+        return Ok(HttpResponse::Ok().json(SpendResponse {
+            spent: payload.amount,
+            balance,
+        }));
+    }
+
+    // Nothing was debited; look the token up only to report why.
+    let Some(record) = state.storage().find_token(&token).await? else {
+        counter!("api_token_requests_total", "endpoint" => "spend", "status" => "not_found")
+            .increment(1);
+        return Err(ApiError::NotFound);
+    };
+    require_usable(&record, "spend", Utc::now())?;
+    counter!(
+        "api_token_requests_total",
+        "endpoint" => "spend",
+        "status" => "insufficient_balance"
+    )
+    .increment(1);
+    Err(ApiError::Conflict("insufficient balance".into()))
+}
+
+/// Moves part of a token's balance onto a new, independent token so access
+/// can be shared without handing out the original.
+pub async fn split_token_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<SplitRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    // A split token would start a fresh subscription period of its own.
+    if state.subscription_period().is_some() {
+        return Err(ApiError::NotFound);
+    }
+    let token = lookup_token(&path.into_inner(), &req)?;
+    if payload.amount <= 0 {
+        counter!("api_token_requests_total", "endpoint" => "split", "status" => "invalid_amount")
+            .increment(1);
+        return Err(ApiError::InvalidRequest("amount must be positive".into()));
+    }
+    let Some(record) = state.storage().find_token(&token).await? else {
+        counter!("api_token_requests_total", "endpoint" => "split", "status" => "not_found")
+            .increment(1);
+        return Err(ApiError::NotFound);
+    };
+    require_usable(&record, "split", Utc::now())?;
+    if record.amount < payload.amount {
+        counter!(
+            "api_token_requests_total",
+            "endpoint" => "split",
+            "status" => "insufficient_balance"
+        )
+        .increment(1);
+        return Err(ApiError::Conflict("insufficient balance".into()));
+    }
+
+    let new_token = ServiceToken::generate().map_err(random_failure)?;
+    let outcome = state
+        .storage()
+        .split_token(SplitTokenRequest {
+            token,
+            amount: payload.amount,
+            new_token: new_token.clone(),
+            new_pid: PaymentId::generate().map_err(random_failure)?,
+            issued_at: Utc::now(),
+        })
+        .await?
+        .ok_or_else(|| {
+            // Lost a race with another split, a revocation or a suspension.
+            counter!("api_token_requests_total", "endpoint" => "split", "status" => "conflict")
+                .increment(1);
+            ApiError::Conflict("token changed concurrently; retry".into())
+        })?;
+    counter!("api_token_requests_total", "endpoint" => "split", "status" => "split").increment(1);
+    Ok(HttpResponse::Created().json(SplitResponse {
+        service_token: new_token.into_inner(),
+        amount: outcome.split.amount,
+        remaining_balance: outcome.source.amount,
+    }))
+}
+
+/// Consumes several tokens (revoked with reason `merged`) and issues one whose
+/// balance is their sum, for users who paid in several small transactions.
+pub async fn merge_tokens_handler(
+    state: web::Data<AppState>,
+    payload: web::Json<MergeRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let count = payload.tokens.len();
+    if !(2..=MAX_MERGE_TOKENS).contains(&count) {
+        counter!("api_token_requests_total", "endpoint" => "merge", "status" => "invalid_request")
+            .increment(1);
+        return Err(ApiError::InvalidRequest(format!(
+            "merge needs between 2 and {MAX_MERGE_TOKENS} tokens"
+        )));
+    }
+    let tokens = payload
+        .tokens
+        .iter()
+        .map(|raw| lookup_token(raw, &req))
+        .collect::<Result<Vec<_>, _>>()?;
+    if tokens.iter().collect::<HashSet<_>>().len() != count {
+        counter!("api_token_requests_total", "endpoint" => "merge", "status" => "invalid_request")
+            .increment(1);
+        return Err(ApiError::InvalidRequest("tokens must be distinct".into()));
+    }
+
+    let now = Utc::now();
+    let mut carried = Duration::zero();
+    for token in &tokens {
+        let Some(record) = state.storage().find_token(token).await? else {
+            counter!("api_token_requests_total", "endpoint" => "merge", "status" => "not_found")
+                .increment(1);
+            return Err(ApiError::NotFound);
+        };
+        require_usable(&record, "merge", now)?;
+        if let Some(expires_at) = subscription_expiry(&state, &record).await? {
+            carried += (expires_at - now).max(Duration::zero());
+        }
+    }
+
+    let new_token = ServiceToken::generate().map_err(random_failure)?;
+    let pinned = state.subscription_period().map(|_| now + carried);
+    let merged = state
+        .storage()
+        .merge_tokens(MergeTokensRequest {
+            tokens,
+            new_token: new_token.clone(),
+            new_pid: PaymentId::generate().map_err(random_failure)?,
+            issued_at: now,
+            expires_at: pinned,
+        })
+        .await?
+        .ok_or_else(|| {
+            counter!("api_token_requests_total", "endpoint" => "merge", "status" => "conflict")
+                .increment(1);
+            ApiError::Conflict("a token changed concurrently; retry".into())
+        })?;
+    counter!("api_token_requests_total", "endpoint" => "merge", "status" => "merged").increment(1);
+    Ok(HttpResponse::Created().json(MergeResponse {
+        service_token: new_token.into_inner(),
+        balance: merged.amount,
+        expires_at: earliest(pinned, merged.expires_at),
+        merged: count,
     }))
 }
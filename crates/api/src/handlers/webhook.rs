@@ -0,0 +1,137 @@
+//! Webhook events as recorded by the monitor, and operator-requested
+//! redelivery.
+//!
+//! The API does not POST webhooks itself: a redelivery request marks the
+//! event's finished deliveries in storage, and whichever monitor dispatches
+//! webhooks picks them up on its next poll, so this works whether the
+//! monitor is embedded or runs on its own.
+
+use actix_web::{web, HttpResponse};
+use anon_ticket_domain::config::InternalRole;
+use anon_ticket_domain::model::{AuditActor, WebhookDelivery, WebhookEvent};
+use anon_ticket_domain::storage::WebhookStore;
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+
+use crate::audit::AuditEvent;
+use crate::auth::Caller;
+use crate::state::AppState;
+
+use super::payment::require_reason;
+use super::ApiError;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookEventResponse {
+    pub id: String,
+    pub pid: String,
+    pub txid: String,
+    pub created_at: DateTime<Utc>,
+    /// Body POSTed to every URL, verbatim.
+    pub payload: String,
+    /// One per URL the event was sent to, ordered by URL.
+    pub deliveries: Vec<DeliverySummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeliverySummary {
+    pub url: String,
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl From<WebhookDelivery> for DeliverySummary {
+    fn from(delivery: WebhookDelivery) -> Self {
+        Self {
+            url: delivery.url,
+            status: delivery.status.as_str().to_string(),
+            attempts: delivery.attempts,
+            last_error: delivery.last_error,
+            last_attempt_at: delivery.last_attempt_at,
+            delivered_at: delivery.delivered_at,
+        }
+    }
+}
+
+/// Operator justification for sending an event again.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RedeliverRequest {
+    pub reason: String,
+    pub operator: Option<String>,
+}
+
+/// `GET /internal/v1/webhooks/events/{id}`: the event and where it was
+/// delivered.
+pub async fn webhook_event_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Support)?;
+    let event = state
+        .storage()
+        .find_webhook_event(&path.into_inner())
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    Ok(HttpResponse::Ok().json(event_response(&state, event).await?))
+}
+
+/// `POST /internal/v1/webhooks/events/{id}/redeliver`: sends the event again,
+/// with the same id and body, to every URL whose delivery has finished.
+/// Answers `202` since the monitor sends it asynchronously.
+pub async fn redeliver_webhook_handler(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<RedeliverRequest>,
+    caller: Caller,
+) -> Result<HttpResponse, ApiError> {
+    caller.require(InternalRole::Admin)?;
+    let request = payload.into_inner();
+    require_reason(&request.reason)?;
+    let id = path.into_inner();
+    let Some(event) = state.storage().find_webhook_event(&id).await? else {
+        counter!("api_admin_actions_total", "action" => "webhook_redeliver", "status" => "not_found")
+            .increment(1);
+        return Err(ApiError::NotFound);
+    };
+    let subject = event.pid.to_hex();
+    let audit = |outcome| AuditEvent {
+        actor: AuditActor::Internal,
+        action: "webhook.redeliver",
+        subject: &subject,
+        reason: &request.reason,
+        operator: request.operator.as_deref(),
+        key_id: caller.key_id(),
+        outcome,
+    };
+    if state.storage().request_redelivery(&id).await? == 0 {
+        counter!("api_admin_actions_total", "action" => "webhook_redeliver", "status" => "in_flight")
+            .increment(1);
+        audit("in_flight").record(&state).await;
+        return Err(ApiError::Conflict(format!(
+            "event {id} has no finished delivery to repeat"
+        )));
+    }
+    counter!("api_admin_actions_total", "action" => "webhook_redeliver", "status" => "requested")
+        .increment(1);
+    audit("requested").record(&state).await;
+    Ok(HttpResponse::Accepted().json(event_response(&state, event).await?))
+}
+
+async fn event_response(
+    state: &AppState,
+    event: WebhookEvent,
+) -> Result<WebhookEventResponse, ApiError> {
+    let deliveries = state.storage().list_deliveries(&event.id).await?;
+    Ok(WebhookEventResponse {
+        id: event.id,
+        pid: event.pid.to_hex(),
+        txid: event.txid,
+        created_at: event.created_at,
+        payload: event.payload,
+        deliveries: deliveries.into_iter().map(DeliverySummary::from).collect(),
+    })
+}
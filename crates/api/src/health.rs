@@ -0,0 +1,224 @@
+//! Liveness and readiness shared by the HTTP probes (`/livez`, `/readyz`) and
+//! the `grpc.health.v1.Health` service, so every listener reports the same
+//! answer, plus the per-dependency report behind `/healthz`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anon_ticket_domain::error::ErrorCode;
+use anon_ticket_monitor::WalletPulse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::state::AppState;
+
+/// A storage ping slower than this counts as unreachable.
+const STORAGE_PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Process-wide readiness switch. Shutdown flips it before any listener
+/// stops so load balancers drain traffic while requests still succeed.
+#[derive(Clone, Default)]
+pub struct Health {
+    draining: Arc<AtomicBool>,
+    changed: Arc<Notify>,
+}
+
+impl Health {
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+        self.changed.notify_waiters();
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Resolves on the next [`start_draining`](Self::start_draining).
+    #[cfg(feature = "grpc")]
+    pub async fn changed(&self) {
+        self.changed.notified().await;
+    }
+}
+
+/// Outcome of a readiness check; the body of `/readyz`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Readiness {
+    pub ready: bool,
+    pub draining: bool,
+    pub storage: bool,
+}
+
+/// Ready when the database answers and shutdown has not started. The
+/// monitor and RPC are left out: payments and tokens stay servable without
+/// them.
+pub async fn readiness(state: &AppState) -> Readiness {
+    let draining = state.health().is_draining();
+    let storage = matches!(
+        tokio::time::timeout(STORAGE_PING_TIMEOUT, state.storage().ping()).await,
+        Ok(Ok(()))
+    );
+    Readiness {
+        ready: storage && !draining,
+        draining,
+        storage,
+    }
+}
+
+/// State of one dependency in a [`HealthReport`], worst last.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    /// Not run by this process, e.g. no embedded monitor.
+    Disabled,
+    Ok,
+    /// Impaired without stopping redemption or token checks.
+    Degraded,
+    Down,
+}
+
+/// Body of `/healthz`. `status` is the worst of the checks, except that only
+/// a `down` database makes the whole report `down`: a wallet that cannot be
+/// reached stalls detection, not redemption.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HealthReport {
+    pub status: CheckStatus,
+    pub draining: bool,
+    pub database: DatabaseCheck,
+    pub monitor: MonitorCheck,
+    pub wallet_rpc: WalletRpcCheck,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DatabaseCheck {
+    pub status: CheckStatus,
+    /// Ping round trip; `None` when it failed or timed out.
+    pub latency_ms: Option<u64>,
+}
+
+/// Whether the embedded monitor is still polling. A poll in progress counts
+/// as alive however long it takes, so a slow wallet sync is not reported as
+/// a stall.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MonitorCheck {
+    pub status: CheckStatus,
+    /// End of the most recent poll of any wallet.
+    pub last_tick_at: Option<DateTime<Utc>>,
+    pub stale_after_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WalletRpcCheck {
+    pub status: CheckStatus,
+    pub wallets: Vec<WalletCheck>,
+}
+
+/// One wallet as seen by its latest poll; nothing is sent to the wallet to
+/// build the report.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WalletCheck {
+    pub name: String,
+    /// `down` when the latest poll could not reach the wallet RPC,
+    /// `degraded` when it failed for another reason.
+    pub status: CheckStatus,
+    pub polling_since: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+/// Checks the database, the embedded monitor and the wallets it polls.
+pub async fn health_report(state: &AppState) -> HealthReport {
+    let started = Instant::now();
+    let latency_ms = match tokio::time::timeout(STORAGE_PING_TIMEOUT, state.storage().ping()).await
+    {
+        Ok(Ok(())) => Some(u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)),
+        _ => None,
+    };
+    let database = DatabaseCheck {
+        status: if latency_ms.is_some() {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Down
+        },
+        latency_ms,
+    };
+    let (monitor, wallet_rpc) = monitor_checks(state, Utc::now());
+    let status = match database.status {
+        CheckStatus::Down => CheckStatus::Down,
+        _ => [database.status, monitor.status, wallet_rpc.status]
+            .into_iter()
+            .max()
+            .unwrap_or(CheckStatus::Ok)
+            .clamp(CheckStatus::Ok, CheckStatus::Degraded),
+    };
+    HealthReport {
+        status,
+        draining: state.health().is_draining(),
+        database,
+        monitor,
+        wallet_rpc,
+    }
+}
+
+fn monitor_checks(state: &AppState, now: DateTime<Utc>) -> (MonitorCheck, WalletRpcCheck) {
+    let stale_after = state.monitor_stale_after();
+    let Some(heartbeat) = state.monitor_heartbeat() else {
+        return (
+            MonitorCheck {
+                status: CheckStatus::Disabled,
+                last_tick_at: None,
+                stale_after_secs: stale_after.as_secs(),
+            },
+            WalletRpcCheck {
+                status: CheckStatus::Disabled,
+                wallets: Vec::new(),
+            },
+        );
+    };
+    let pulses = heartbeat.snapshot();
+    let last_tick_at = pulses.values().filter_map(|pulse| pulse.last_tick_at).max();
+    let polling = pulses.values().any(|pulse| pulse.polling_since.is_some());
+    // Before the first poll ends, the monitor's start is the reference.
+    let reference = last_tick_at.unwrap_or(heartbeat.started_at());
+    let fresh = chrono::Duration::from_std(stale_after)
+        .ok()
+        .and_then(|window| reference.checked_add_signed(window))
+        .is_none_or(|deadline| now <= deadline);
+    let monitor = MonitorCheck {
+        status: if fresh || polling {
+            CheckStatus::Ok
+        } else {
+            CheckStatus::Degraded
+        },
+        last_tick_at,
+        stale_after_secs: stale_after.as_secs(),
+    };
+    let wallets: Vec<WalletCheck> = pulses
+        .into_iter()
+        .map(|(name, pulse)| wallet_check(name, pulse))
+        .collect();
+    let wallet_rpc = WalletRpcCheck {
+        status: wallets
+            .iter()
+            .map(|wallet| wallet.status)
+            .max()
+            .unwrap_or(CheckStatus::Ok),
+        wallets,
+    };
+    (monitor, wallet_rpc)
+}
+
+fn wallet_check(name: String, pulse: WalletPulse) -> WalletCheck {
+    WalletCheck {
+        name,
+        status: match pulse.last_error {
+            None => CheckStatus::Ok,
+            Some(ErrorCode::RpcUnavailable) => CheckStatus::Down,
+            Some(_) => CheckStatus::Degraded,
+        },
+        polling_since: pulse.polling_since,
+        last_success_at: pulse.last_success_at,
+        error: pulse.last_error.map(|code| code.as_str().to_string()),
+    }
+}
@@ -0,0 +1,139 @@
+//! Signed token claims handed out by `POST /api/v1/redeem?format=jwt`.
+//!
+//! Integrators that would otherwise call the validation endpoint right after
+//! redeeming can verify the JWT offline with the shared HS256 key. The claims
+//! are a snapshot: revocations after issuance only show up on validation, so
+//! the lifetime is kept short.
+
+use std::time::Duration;
+
+use anon_ticket_domain::model::{ServiceToken, ServiceTokenRecord};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+pub const DEFAULT_TOKEN_JWT_TTL: Duration = Duration::from_secs(60 * 60);
+pub const TOKEN_JWT_ISSUER: &str = "anon-ticket";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenClaims {
+    pub iss: String,
+    /// SHA3-256 of the service token, hex-encoded. The token itself stays
+    /// out so the JWT can be forwarded to services that must not spend it.
+    pub sub: String,
+    pub balance: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+pub struct TokenJwtIssuer {
+    key: EncodingKey,
+    ttl: Duration,
+}
+
+impl TokenJwtIssuer {
+    pub fn new(secret: &[u8], ttl: Duration) -> Self {
+        Self {
+            key: EncodingKey::from_secret(secret),
+            ttl,
+        }
+    }
+
+    /// Signs the claims for an active token. Returns `None` for suspended,
+    /// revoked and expired ones, which have nothing to vouch for.
+    pub fn issue(
+        &self,
+        service_token: &ServiceToken,
+        record: &ServiceTokenRecord,
+        tier: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<String>, jsonwebtoken::errors::Error> {
+        if !record.state(now).is_usable() {
+            return Ok(None);
+        }
+        let ttl = chrono::Duration::from_std(self.ttl).unwrap_or(chrono::Duration::MAX);
+        let exp = now
+            .checked_add_signed(ttl)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let claims = TokenClaims {
+            iss: TOKEN_JWT_ISSUER.to_string(),
+            sub: hex::encode(Sha3_256::digest(service_token.as_bytes())),
+            balance: record.amount,
+            tier: tier.map(str::to_string),
+            iat: now.timestamp(),
+            exp: record.expires_at.map_or(exp, |at| at.min(exp)).timestamp(),
+        };
+        encode(&Header::default(), &claims, &self.key).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::PaymentId;
+    use jsonwebtoken::{decode, DecodingKey, Validation};
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn record(expires_at: Option<DateTime<Utc>>) -> ServiceTokenRecord {
+        ServiceTokenRecord {
+            token: token(),
+            pid: PaymentId::parse("0123456789abcdef").unwrap(),
+            amount: 42,
+            issued_at: Utc::now(),
+            revoked_at: None,
+            revoke_reason: None,
+            abuse_score: 0,
+            expires_at,
+            tier: None,
+            suspended_at: None,
+            suspend_reason: None,
+            suspended_until: None,
+        }
+    }
+
+    fn token() -> ServiceToken {
+        ServiceToken::from_bytes([7; 32])
+    }
+
+    #[test]
+    fn claims_expire_with_the_token_or_the_ttl() {
+        let now = Utc::now();
+        let issuer = TokenJwtIssuer::new(SECRET, Duration::from_secs(600));
+        let mut validation = Validation::default();
+        validation.set_issuer(&[TOKEN_JWT_ISSUER]);
+        let claims = |jwt: String| {
+            decode::<TokenClaims>(&jwt, &DecodingKey::from_secret(SECRET), &validation)
+                .expect("valid jwt")
+                .claims
+        };
+
+        let jwt = issuer
+            .issue(&token(), &record(None), Some("gold"), now)
+            .unwrap()
+            .expect("active token");
+        let decoded = claims(jwt);
+        assert_eq!(decoded.exp, now.timestamp() + 600);
+        assert_eq!(decoded.balance, 42);
+        assert_eq!(decoded.tier.as_deref(), Some("gold"));
+        assert_ne!(decoded.sub, token().into_inner());
+
+        let soon = now + chrono::Duration::seconds(60);
+        let jwt = issuer
+            .issue(&token(), &record(Some(soon)), None, now)
+            .unwrap()
+            .expect("active token");
+        assert_eq!(claims(jwt).exp, soon.timestamp());
+
+        let expired = record(Some(now));
+        assert_eq!(issuer.issue(&token(), &expired, None, now).unwrap(), None);
+        let revoked = ServiceTokenRecord {
+            revoked_at: Some(now),
+            ..record(None)
+        };
+        assert_eq!(issuer.issue(&token(), &revoked, None, now).unwrap(), None);
+    }
+}
@@ -1,5 +1,24 @@
 mod application;
+mod audit;
+mod auth;
+mod build_info;
+mod changefeed;
+mod consistency;
+mod fee;
+#[cfg(feature = "grpc")]
+mod grpc;
 mod handlers;
+mod health;
+mod jwt;
+mod pending;
+mod prewarm;
+mod process_metrics;
+mod rate_limit;
+#[cfg(feature = "runtime-metrics")]
+mod runtime_metrics;
+mod shutdown;
+mod signed_token;
+mod startup;
 mod state;
 
 #[cfg(test)]
@@ -0,0 +1,93 @@
+//! Transfers the embedded monitor has seen mined but not yet confirmed, so
+//! `GET /api/v1/payment/{pid}` can report them before they are stored.
+//!
+//! Fed by the monitor's `detected` events; a `confirmed` event, or
+//! [`PENDING_TTL`] without news, drops the entry. Like the events themselves
+//! this is best effort and local to the process: storage stays the source of
+//! truth.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anon_ticket_domain::model::PaymentId;
+use anon_ticket_monitor::{PaymentEvent, PaymentEventKind};
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+/// How long a detection is reported without a newer one.
+pub const PENDING_TTL: Duration = Duration::from_secs(60 * 60);
+/// Entries kept at most; detections beyond it are not tracked.
+const MAX_PENDING: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingPayment {
+    pub amount: i64,
+    pub block_height: i64,
+    pub confirmations: u64,
+    pub seen_at: DateTime<Utc>,
+}
+
+/// Shared map of the latest detection per PID. Clones observe the same
+/// entries.
+#[derive(Debug, Clone, Default)]
+pub struct PendingPayments {
+    entries: Arc<Mutex<HashMap<PaymentId, PendingPayment>>>,
+}
+
+impl PendingPayments {
+    pub fn get(&self, pid: &PaymentId, now: DateTime<Utc>) -> Option<PendingPayment> {
+        let entries = self.entries.lock().expect("pending payments lock");
+        entries
+            .get(pid)
+            .copied()
+            .filter(|pending| !is_stale(pending, now))
+    }
+
+    pub fn record(&self, event: &PaymentEvent, now: DateTime<Utc>) {
+        let mut entries = self.entries.lock().expect("pending payments lock");
+        match event.kind {
+            PaymentEventKind::Detected { confirmations } => {
+                if entries.len() >= MAX_PENDING && !entries.contains_key(&event.pid) {
+                    entries.retain(|_, pending| !is_stale(pending, now));
+                    if entries.len() >= MAX_PENDING {
+                        counter!("api_pending_payments_dropped_total").increment(1);
+                        return;
+                    }
+                }
+                entries.insert(
+                    event.pid.clone(),
+                    PendingPayment {
+                        amount: event.amount,
+                        block_height: event.block_height,
+                        confirmations,
+                        seen_at: now,
+                    },
+                );
+            }
+            PaymentEventKind::Confirmed => {
+                entries.remove(&event.pid);
+            }
+        }
+    }
+
+    /// Records every event from `events` until the monitor stops.
+    pub async fn follow(self, mut events: Receiver<PaymentEvent>) {
+        loop {
+            match events.recv().await {
+                Ok(event) => self.record(&event, Utc::now()),
+                Err(RecvError::Lagged(skipped)) => {
+                    counter!("api_pending_payments_lagged_total").increment(skipped);
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+}
+
+fn is_stale(pending: &PendingPayment, now: DateTime<Utc>) -> bool {
+    (now - pending.seen_at)
+        .to_std()
+        .is_ok_and(|age| age > PENDING_TTL)
+}
@@ -0,0 +1,158 @@
+//! Boot-time PID cache and Bloom filter fill.
+//!
+//! Without a snapshot every payment is hashed on each start. With
+//! `API_PID_BLOOM_SNAPSHOT_PATH` the filter saved by the previous start is
+//! restored and only payments at or above its watermark height are loaded.
+//! The snapshot is trusted only if the number of payments below that height
+//! is unchanged; a late insert or a purge under the watermark, a resized
+//! filter, or a damaged file all fall back to the full rebuild.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anon_ticket_domain::model::PaymentId;
+use anon_ticket_domain::services::{
+    blocking::run_blocking,
+    cache::{BloomWatermark, InMemoryPidCache, PidBloom},
+};
+use anon_ticket_domain::PidCache;
+use anon_ticket_storage::SeaOrmStorage;
+use metrics::counter;
+use tracing::{info, warn};
+
+use crate::application::BootstrapError;
+
+/// Hashes known PIDs into the cache and Bloom filter; with millions of
+/// payments this runs for seconds, so it happens on the blocking pool.
+pub(crate) async fn prewarm_hints(
+    storage: &SeaOrmStorage,
+    cache: Arc<InMemoryPidCache>,
+    bloom: Option<Arc<PidBloom>>,
+    snapshot_path: Option<&Path>,
+) -> Result<(), BootstrapError> {
+    let start = Instant::now();
+    let snapshot_path = snapshot_path.filter(|_| bloom.is_some());
+    let restored = match (&bloom, snapshot_path) {
+        (Some(bloom), Some(path)) => restore(storage, bloom, path).await?,
+        _ => None,
+    };
+    let min_height = restored.map(|watermark| watermark.height);
+    let rows = storage.payment_heights(min_height).await?;
+    let count = rows.len();
+    let watermark = advance(restored, &rows);
+
+    let filled = bloom.clone();
+    run_blocking("bloom_rebuild", move || {
+        for (pid, _) in &rows {
+            cache.mark_present(pid);
+            if let Some(b) = &filled {
+                b.insert(pid);
+            }
+        }
+    })
+    .await;
+    info!(
+        count,
+        restored = restored.is_some(),
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "prefilled cache/bloom with existing payments",
+    );
+
+    if let (Some(bloom), Some(path)) = (bloom, snapshot_path) {
+        save(bloom, path.to_path_buf(), watermark).await;
+    }
+    Ok(())
+}
+
+/// Reads and validates the snapshot at `path`, merging it into `bloom`.
+async fn restore(
+    storage: &SeaOrmStorage,
+    bloom: &Arc<PidBloom>,
+    path: &Path,
+) -> Result<Option<BloomWatermark>, BootstrapError> {
+    let read_path = path.to_path_buf();
+    let bytes = match run_blocking("bloom_snapshot_read", move || std::fs::read(read_path)).await {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            info!(path = %path.display(), "no bloom snapshot yet; rebuilding");
+            counter!("api_bloom_snapshot_loads_total", "outcome" => "missing").increment(1);
+            return Ok(None);
+        }
+        Err(err) => {
+            warn!(path = %path.display(), error = %err, "bloom snapshot unreadable; rebuilding");
+            counter!("api_bloom_snapshot_loads_total", "outcome" => "invalid").increment(1);
+            return Ok(None);
+        }
+    };
+    // Restoring into the live filter is safe even if the snapshot is then
+    // found stale: extra bits only cost false positives, and the rebuild
+    // re-inserts every payment.
+    let target = bloom.clone();
+    let watermark =
+        match run_blocking("bloom_snapshot_restore", move || target.restore(&bytes)).await {
+            Ok(watermark) => watermark,
+            Err(err) => {
+                warn!(path = %path.display(), error = %err, "bloom snapshot rejected; rebuilding");
+                counter!("api_bloom_snapshot_loads_total", "outcome" => "invalid").increment(1);
+                return Ok(None);
+            }
+        };
+    let below = storage.count_payments_below(watermark.height).await?;
+    if below != watermark.covered {
+        warn!(
+            height = watermark.height,
+            expected = watermark.covered,
+            found = below,
+            "payments changed under the bloom snapshot watermark; rebuilding"
+        );
+        counter!("api_bloom_snapshot_loads_total", "outcome" => "stale").increment(1);
+        return Ok(None);
+    }
+    counter!("api_bloom_snapshot_loads_total", "outcome" => "restored").increment(1);
+    Ok(Some(watermark))
+}
+
+/// Watermark after inserting `rows` on top of `restored`. The new height is
+/// the highest seen, and everything below it is now in the filter: the
+/// restored rows plus the loaded ones under it.
+fn advance(restored: Option<BloomWatermark>, rows: &[(PaymentId, i64)]) -> BloomWatermark {
+    let base = restored.unwrap_or(BloomWatermark {
+        height: i64::MIN,
+        covered: 0,
+    });
+    let height = rows
+        .iter()
+        .map(|(_, height)| *height)
+        .fold(base.height, i64::max);
+    let loaded = rows.iter().filter(|(_, h)| *h < height).count() as u64;
+    BloomWatermark {
+        height,
+        covered: base.covered + loaded,
+    }
+}
+
+/// Writes the snapshot next to `path` and renames it into place, so a crash
+/// mid-write never leaves a truncated file. Failures only cost the next
+/// start a full rebuild.
+async fn save(bloom: Arc<PidBloom>, path: PathBuf, watermark: BloomWatermark) {
+    let shown = path.display().to_string();
+    let result = run_blocking("bloom_snapshot_write", move || {
+        let staging = path.with_extension("tmp");
+        std::fs::write(&staging, bloom.snapshot(watermark))?;
+        std::fs::rename(&staging, &path)
+    })
+    .await;
+    match result {
+        Ok(()) => info!(
+            path = %shown,
+            height = watermark.height,
+            covered = watermark.covered,
+            "saved bloom snapshot"
+        ),
+        Err(err) => {
+            warn!(path = %shown, error = %err, "failed to save bloom snapshot");
+            counter!("api_bloom_snapshot_write_errors_total").increment(1);
+        }
+    }
+}
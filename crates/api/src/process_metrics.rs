@@ -0,0 +1,208 @@
+//! Process resource gauges, so an instance that slowly leaks memory, file
+//! descriptors or database connections shows it long before it falls over.
+//!
+//! Every [`SAMPLE_INTERVAL`] this publishes `process_resident_memory_bytes`,
+//! `process_open_fds`, `process_max_fds`, `api_db_pool_connections{state}`
+//! and `api_db_pool_max_connections`. Memory and descriptors are read from
+//! `/proc/self`, so they are only reported on Linux. A warning is logged when
+//! a resource reaches its soft limit and again, as resolved, when it drops
+//! back below; each crossing is counted in
+//! `api_soft_limit_exceeded_total{resource}`. The pool's limit is its size:
+//! with every connection checked out, queries queue for one.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use anon_ticket_domain::config::ApiConfig;
+use anon_ticket_storage::{PoolUsage, SeaOrmStorage};
+use metrics::{counter, gauge};
+use tracing::{info, warn};
+
+pub(crate) const SAMPLE_INTERVAL: Duration = Duration::from_secs(30);
+/// Share of the open-file limit used as the soft limit when
+/// `API_FD_SOFT_LIMIT` is unset.
+const DEFAULT_FD_SHARE: f64 = 0.8;
+
+/// One reading of the process; fields are `None` where the platform or the
+/// backend does not expose them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ResourceSample {
+    pub(crate) rss_bytes: Option<u64>,
+    pub(crate) open_fds: Option<u64>,
+    pub(crate) max_fds: Option<u64>,
+    pub(crate) pool: Option<PoolUsage>,
+}
+
+impl ResourceSample {
+    pub(crate) fn read(storage: &SeaOrmStorage) -> Self {
+        Self {
+            rss_bytes: std::fs::read_to_string("/proc/self/status")
+                .ok()
+                .and_then(|status| parse_vm_rss(&status)),
+            open_fds: std::fs::read_dir("/proc/self/fd")
+                .ok()
+                .map(|entries| entries.count() as u64),
+            max_fds: std::fs::read_to_string("/proc/self/limits")
+                .ok()
+                .and_then(|limits| parse_max_open_files(&limits)),
+            pool: storage.pool_usage(),
+        }
+    }
+}
+
+/// Publishes samples and tracks which soft limits are exceeded.
+#[derive(Debug)]
+pub(crate) struct ResourceMonitor {
+    rss_limit_bytes: Option<u64>,
+    fd_limit: Option<u64>,
+    over: BTreeSet<&'static str>,
+}
+
+impl ResourceMonitor {
+    pub(crate) fn new(rss_limit_bytes: Option<u64>, fd_limit: Option<u64>) -> Self {
+        Self {
+            rss_limit_bytes,
+            fd_limit,
+            over: BTreeSet::new(),
+        }
+    }
+
+    pub(crate) fn from_config(config: &ApiConfig) -> Self {
+        Self::new(
+            config
+                .rss_soft_limit_mb()
+                .map(|mb| mb.saturating_mul(1024 * 1024)),
+            config.fd_soft_limit(),
+        )
+    }
+
+    /// Sets the gauges from `sample` and returns the resources that reached
+    /// (`true`) or dropped back below (`false`) their soft limit.
+    pub(crate) fn observe(&mut self, sample: &ResourceSample) -> Vec<(&'static str, bool)> {
+        if let Some(rss) = sample.rss_bytes {
+            gauge!("process_resident_memory_bytes").set(rss as f64);
+        }
+        if let Some(open) = sample.open_fds {
+            gauge!("process_open_fds").set(open as f64);
+        }
+        if let Some(max) = sample.max_fds {
+            gauge!("process_max_fds").set(max as f64);
+        }
+        if let Some(pool) = sample.pool {
+            gauge!("api_db_pool_connections", "state" => "idle").set(f64::from(pool.idle));
+            gauge!("api_db_pool_connections", "state" => "in_use").set(f64::from(pool.in_use()));
+            gauge!("api_db_pool_max_connections").set(f64::from(pool.max));
+        }
+
+        let fd_limit = self.fd_limit.or_else(|| {
+            sample
+                .max_fds
+                .map(|max| (max as f64 * DEFAULT_FD_SHARE) as u64)
+        });
+        let checks = [
+            ("memory", sample.rss_bytes.zip(self.rss_limit_bytes)),
+            ("fds", sample.open_fds.zip(fd_limit)),
+            (
+                "db_pool",
+                sample
+                    .pool
+                    .map(|pool| (u64::from(pool.in_use()), u64::from(pool.max))),
+            ),
+        ];
+        let mut changed = Vec::new();
+        for (resource, reading) in checks {
+            let Some((value, limit)) = reading else {
+                continue;
+            };
+            let over = value >= limit;
+            let crossed = if over {
+                self.over.insert(resource)
+            } else {
+                self.over.remove(resource)
+            };
+            if !crossed {
+                continue;
+            }
+            if over {
+                counter!("api_soft_limit_exceeded_total", "resource" => resource).increment(1);
+                warn!(resource, value, limit, "resource reached its soft limit");
+            } else {
+                info!(resource, value, limit, "resource back below its soft limit");
+            }
+            changed.push((resource, over));
+        }
+        changed
+    }
+}
+
+/// Samples the process every [`SAMPLE_INTERVAL`] until aborted.
+pub(crate) async fn report_resources_periodically(
+    storage: SeaOrmStorage,
+    mut monitor: ResourceMonitor,
+) {
+    let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+    loop {
+        interval.tick().await;
+        monitor.observe(&ResourceSample::read(&storage));
+    }
+}
+
+/// `VmRSS` from `/proc/self/status`, in bytes.
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Soft `Max open files` from `/proc/self/limits`; `None` when unlimited.
+fn parse_max_open_files(limits: &str) -> Option<u64> {
+    let line = limits
+        .lines()
+        .find(|line| line.starts_with("Max open files"))?;
+    line.split_whitespace().nth(3)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_proc_files() {
+        let status = "Name:\tanon_ticket_api\nVmPeak:\t  20000 kB\nVmRSS:\t   1536 kB\n";
+        assert_eq!(parse_vm_rss(status), Some(1536 * 1024));
+        let limits = "Limit                     Soft Limit           Hard Limit           Units     \n\
+                      Max open files            1024                 524288               files     \n";
+        assert_eq!(parse_max_open_files(limits), Some(1024));
+        assert_eq!(
+            parse_max_open_files(
+                "Max open files            unlimited            unlimited            files"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn soft_limits_warn_once_per_crossing() {
+        let mut monitor = ResourceMonitor::new(Some(100 * 1024 * 1024), None);
+        let sample = |rss_mb: u64, open_fds: u64, in_use: u32| ResourceSample {
+            rss_bytes: Some(rss_mb * 1024 * 1024),
+            open_fds: Some(open_fds),
+            max_fds: Some(1000),
+            pool: Some(PoolUsage {
+                open: in_use,
+                idle: 0,
+                max: 4,
+            }),
+        };
+        assert!(monitor.observe(&sample(50, 100, 1)).is_empty());
+        assert_eq!(
+            monitor.observe(&sample(150, 900, 4)),
+            [("memory", true), ("fds", true), ("db_pool", true)]
+        );
+        assert!(monitor.observe(&sample(160, 950, 4)).is_empty());
+        assert_eq!(
+            monitor.observe(&sample(160, 100, 3)),
+            [("fds", false), ("db_pool", false)]
+        );
+    }
+}
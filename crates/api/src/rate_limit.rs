@@ -0,0 +1,88 @@
+//! Per-PID and per-token rate limiting for public routes.
+//!
+//! Buckets are keyed by a hash of the identifier a request is about rather
+//! than the client address, so brute-forcing a client secret or passphrase
+//! against one PID or token is capped without tracking who is asking. The
+//! middleware is attached per resource so path parameters are already
+//! matched; routes without a `{pid}` or `{token}` segment are keyed by the
+//! `pid` or `tokens` fields of their JSON body.
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web, Error,
+};
+use anon_ticket_domain::services::rate_limit::RateDecision;
+use metrics::counter;
+use serde::Deserialize;
+use sha3::{Digest, Sha3_256};
+
+use crate::handlers::ApiError;
+use crate::state::AppState;
+
+/// Body fields that name the PID or tokens a POST acts on.
+#[derive(Deserialize)]
+struct BodyIdentifiers {
+    #[serde(default)]
+    pid: Option<String>,
+    #[serde(default)]
+    tokens: Vec<String>,
+}
+
+pub(crate) async fn rate_limit(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+    if let Some(limiter) = state.as_ref().and_then(|state| state.rate_limiter()) {
+        for (kind, value) in identifiers(&mut req).await? {
+            if let RateDecision::Limited { retry_after } =
+                limiter.acquire(&bucket_key(kind, &value)).await
+            {
+                counter!("api_rate_limited_total", "kind" => kind).increment(1);
+                let retry_after = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+                return Ok(req
+                    .error_response(ApiError::RateLimited(retry_after))
+                    .map_into_right_body());
+            }
+        }
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+async fn identifiers(req: &mut ServiceRequest) -> Result<Vec<(&'static str, String)>, Error> {
+    for kind in ["pid", "token"] {
+        if let Some(value) = req.match_info().get(kind) {
+            return Ok(vec![(kind, value.to_string())]);
+        }
+    }
+    if req.method() != Method::POST {
+        return Ok(Vec::new());
+    }
+    let body = req.extract::<web::Bytes>().await?;
+    req.set_payload(Payload::from(body.clone()));
+    // Malformed bodies are left for the handler to reject.
+    let Ok(fields) = serde_json::from_slice::<BodyIdentifiers>(&body) else {
+        return Ok(Vec::new());
+    };
+    Ok(fields
+        .pid
+        .map(|pid| ("pid", pid))
+        .into_iter()
+        .chain(fields.tokens.into_iter().map(|token| ("token", token)))
+        .collect())
+}
+
+/// Hex identifiers are case-insensitive, so both spellings share a bucket.
+pub(crate) fn bucket_key(kind: &str, value: &str) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"anon-ticket/rate-limit|");
+    hasher.update(kind.as_bytes());
+    hasher.update(b"|");
+    hasher.update(value.to_ascii_lowercase().as_bytes());
+    hasher.finalize().into()
+}
@@ -0,0 +1,102 @@
+//! Tokio runtime gauges (`runtime-metrics` feature), to tell scheduler
+//! saturation apart from slow storage when redeem latency spikes.
+//!
+//! actix-web runs every HTTP worker on its own single-threaded runtime, so a
+//! sampler is started on each of them as well as on the main runtime; the
+//! `runtime` label is `main`, `public-N`, or `internal-N`. Blocking-pool and
+//! per-worker queue gauges need tokio's unstable metrics and are only
+//! exported when built with `RUSTFLAGS="--cfg tokio_unstable"`.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use metrics::gauge;
+use tokio::runtime::{Handle, RuntimeMetrics};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+thread_local! {
+    static SAMPLING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Numbers the per-worker runtimes of one HTTP server.
+#[derive(Debug)]
+pub(crate) struct WorkerRuntimes {
+    server: &'static str,
+    next: AtomicUsize,
+}
+
+impl WorkerRuntimes {
+    pub(crate) const fn new(server: &'static str) -> Self {
+        Self {
+            server,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Call from the `HttpServer` app factory, which runs on each worker's
+    /// runtime. Later calls on the same thread are no-ops.
+    pub(crate) fn sample_current(&self) {
+        if SAMPLING.with(|sampling| sampling.replace(true)) {
+            return;
+        }
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        spawn_sampler(format!("{}-{index}", self.server));
+    }
+}
+
+/// Starts sampling the runtime the caller is running on.
+pub(crate) fn sample_main() {
+    SAMPLING.with(|sampling| sampling.set(true));
+    spawn_sampler("main".to_string());
+}
+
+fn spawn_sampler(runtime: String) {
+    let metrics = Handle::current().metrics();
+    tokio::spawn(async move {
+        let mut busy = busy_durations(&metrics);
+        let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let now = busy_durations(&metrics);
+            record(&runtime, &metrics, &busy, &now);
+            busy = now;
+        }
+    });
+}
+
+fn busy_durations(metrics: &RuntimeMetrics) -> Vec<Duration> {
+    (0..metrics.num_workers())
+        .map(|worker| metrics.worker_total_busy_duration(worker))
+        .collect()
+}
+
+fn record(runtime: &str, metrics: &RuntimeMetrics, before: &[Duration], now: &[Duration]) {
+    let labels = [("runtime", runtime.to_string())];
+    gauge!("tokio_workers", &labels).set(metrics.num_workers() as f64);
+    gauge!("tokio_alive_tasks", &labels).set(metrics.num_alive_tasks() as f64);
+    gauge!("tokio_global_queue_depth", &labels).set(metrics.global_queue_depth() as f64);
+    // Share of the interval the workers spent polling tasks, averaged over
+    // workers: near 1.0 means tasks are queueing for a thread.
+    let busy: Duration = now
+        .iter()
+        .zip(before)
+        .map(|(now, before)| now.saturating_sub(*before))
+        .sum();
+    let capacity = SAMPLE_INTERVAL.as_secs_f64() * now.len().max(1) as f64;
+    gauge!("tokio_worker_utilization", &labels).set((busy.as_secs_f64() / capacity).min(1.0));
+
+    #[cfg(tokio_unstable)]
+    {
+        gauge!("tokio_blocking_threads", &labels).set(metrics.num_blocking_threads() as f64);
+        gauge!("tokio_idle_blocking_threads", &labels)
+            .set(metrics.num_idle_blocking_threads() as f64);
+        gauge!("tokio_blocking_queue_depth", &labels).set(metrics.blocking_queue_depth() as f64);
+        let local: usize = (0..metrics.num_workers())
+            .map(|worker| metrics.worker_local_queue_depth(worker))
+            .sum();
+        gauge!("tokio_local_queue_depth", &labels).set(local as f64);
+    }
+}
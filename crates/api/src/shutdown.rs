@@ -0,0 +1,244 @@
+//! Ordered shutdown for the API process.
+//!
+//! Once a signal arrives, [`Services::shutdown`] is cancelled, or any
+//! long-running task exits, the phases below run strictly in sequence, each
+//! bounded by the same per-phase timeout:
+//!
+//! 1. `public` – stop accepting user traffic and drain in-flight requests.
+//! 2. `internal` – same for the operator listener (and the gRPC listener, if
+//!    enabled), kept up until the public side is drained so metrics scrapes
+//!    still see the drain.
+//! 3. `monitor` – let the current tick finish, then stop the embedded monitor.
+//! 4. `background` – cancel periodic jobs (tombstone pruning, PID audits).
+//! 5. `storage` – close the database pools.
+//!
+//! There is no outbox yet; its flush belongs between `monitor` and `storage`.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::Server;
+use anon_ticket_monitor::worker::MonitorError;
+use tokio::task::{JoinError, JoinHandle};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::application::BootstrapError;
+use crate::state::AppState;
+
+pub const DEFAULT_PHASE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Embedded monitor task plus the trigger that asks it to stop.
+pub struct MonitorTask {
+    pub handle: JoinHandle<Result<(), MonitorError>>,
+    pub stop: CancellationToken,
+}
+
+/// Internal gRPC server task plus the trigger that starts its graceful stop.
+pub struct GrpcTask {
+    pub handle: JoinHandle<Result<(), BootstrapError>>,
+    pub stop: CancellationToken,
+}
+
+pub struct Services {
+    pub public: Server,
+    pub internal: Server,
+    pub grpc: Option<GrpcTask>,
+    pub monitor: Option<MonitorTask>,
+    pub background: Vec<JoinHandle<()>>,
+    pub state: AppState,
+    /// Cancel to shut down as if SIGTERM had arrived. Each task has its own
+    /// stop token so the phases still run in order.
+    pub shutdown: CancellationToken,
+}
+
+/// Runs `services` until a shutdown signal or the first task exit, then walks
+/// the shutdown phases. Returns the error of the task that triggered the
+/// shutdown, if any.
+pub async fn serve(services: Services, phase_timeout: Duration) -> Result<(), BootstrapError> {
+    let Services {
+        public,
+        internal,
+        grpc,
+        monitor,
+        background,
+        state,
+        shutdown,
+    } = services;
+    let public_handle = public.handle();
+    let internal_handle = internal.handle();
+    let mut public = Some(tokio::spawn(public));
+    let mut internal = Some(tokio::spawn(internal));
+    let (mut grpc, grpc_stop) = match grpc {
+        Some(task) => (Some(task.handle), Some(task.stop)),
+        None => (None, None),
+    };
+    let (mut monitor, monitor_stop) = match monitor {
+        Some(task) => (Some(task.handle), Some(task.stop)),
+        None => (None, None),
+    };
+
+    let outcome = tokio::select! {
+        _ = wait_for_signal() => {
+            info!("shutdown signal received");
+            Ok(())
+        }
+        _ = shutdown.cancelled() => {
+            info!("shutdown requested");
+            Ok(())
+        }
+        res = join_once(&mut public) => exited("public listener", server_result(res)),
+        res = join_once(&mut internal) => exited("internal listener", server_result(res)),
+        res = join_once(&mut grpc), if grpc.is_some() => {
+            exited("grpc listener", grpc_result(res))
+        }
+        res = join_once(&mut monitor), if monitor.is_some() => {
+            exited("monitor", monitor_result(res))
+        }
+    };
+
+    // Fail readiness first so load balancers stop routing here while the
+    // listeners still answer.
+    state.health().start_draining();
+    run_phase("public", phase_timeout, async {
+        public_handle.stop(true).await;
+        if let Some(task) = public.take() {
+            log_task_error("public listener", server_result(task.await));
+        }
+    })
+    .await;
+
+    if let Some(stop) = grpc_stop {
+        stop.cancel();
+    }
+    run_phase("internal", phase_timeout, async {
+        internal_handle.stop(true).await;
+        if let Some(task) = internal.take() {
+            log_task_error("internal listener", server_result(task.await));
+        }
+        if let Some(task) = grpc.take() {
+            log_task_error("grpc listener", grpc_result(task.await));
+        }
+    })
+    .await;
+
+    if let Some(stop) = monitor_stop {
+        stop.cancel();
+    }
+    if let Some(task) = monitor.take() {
+        let abort = task.abort_handle();
+        let joined = run_phase("monitor", phase_timeout, async {
+            log_task_error("monitor", monitor_result(task.await));
+        })
+        .await;
+        if !joined {
+            abort.abort();
+        }
+    }
+
+    run_phase("background", phase_timeout, async {
+        for task in &background {
+            task.abort();
+        }
+        for task in background {
+            let _ = task.await;
+        }
+    })
+    .await;
+
+    let storage = state.storage().clone();
+    drop(state);
+    run_phase("storage", phase_timeout, async {
+        match storage.close().await {
+            Ok(true) => {}
+            Ok(false) => warn!("storage still shared; pools close when the last handle drops"),
+            Err(err) => warn!(?err, "closing storage failed"),
+        }
+    })
+    .await;
+
+    outcome
+}
+
+/// Runs one phase under `timeout`; returns whether it completed in time.
+async fn run_phase(name: &'static str, timeout: Duration, phase: impl Future<Output = ()>) -> bool {
+    let started = Instant::now();
+    info!(phase = name, "shutdown phase started");
+    let completed = tokio::time::timeout(timeout, phase).await.is_ok();
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    if completed {
+        info!(phase = name, elapsed_ms, "shutdown phase completed");
+    } else {
+        warn!(phase = name, elapsed_ms, "shutdown phase timed out");
+    }
+    completed
+}
+
+/// Awaits a task exactly once; pending forever when it was already joined.
+async fn join_once<T>(task: &mut Option<JoinHandle<T>>) -> Result<T, JoinError> {
+    match task {
+        Some(handle) => {
+            let result = handle.await;
+            *task = None;
+            result
+        }
+        None => std::future::pending().await,
+    }
+}
+
+fn server_result(res: Result<std::io::Result<()>, JoinError>) -> Result<(), BootstrapError> {
+    res.map_err(|err| BootstrapError::Join(err.to_string()))?
+        .map_err(BootstrapError::Io)
+}
+
+fn grpc_result(res: Result<Result<(), BootstrapError>, JoinError>) -> Result<(), BootstrapError> {
+    res.map_err(|err| BootstrapError::Join(err.to_string()))?
+}
+
+fn monitor_result(res: Result<Result<(), MonitorError>, JoinError>) -> Result<(), BootstrapError> {
+    res.map_err(|err| BootstrapError::Join(err.to_string()))??;
+    Ok(())
+}
+
+fn exited(task: &'static str, result: Result<(), BootstrapError>) -> Result<(), BootstrapError> {
+    match &result {
+        Ok(()) => warn!(task, "task exited unexpectedly; shutting down"),
+        Err(err) => warn!(task, %err, "task failed; shutting down"),
+    }
+    result
+}
+
+fn log_task_error(task: &'static str, result: Result<(), BootstrapError>) {
+    if let Err(err) = result {
+        warn!(task, %err, "task reported an error while stopping");
+    }
+}
+
+async fn wait_for_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!(?err, "failed to listen for ctrl-c");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                warn!(?err, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
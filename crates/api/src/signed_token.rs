@@ -0,0 +1,99 @@
+//! Signed tokens handed out by `POST /api/v1/redeem?format=signed`.
+//!
+//! Unlike the redeem JWT these are meant to be checked on every request:
+//! services verify them offline with the shared keys and only fetch
+//! `GET /api/v1/revocations` to learn about revocations. A revoked token's
+//! signed form stays valid for at most the TTL, so the list only needs to go
+//! back that far.
+
+use std::time::Duration;
+
+use anon_ticket_domain::config::TokenSigningKey;
+use anon_ticket_domain::model::ServiceTokenRecord;
+use anon_ticket_domain::services::token_signing::{sign_token, token_subject, SignedTokenClaims};
+use chrono::{DateTime, Utc};
+
+pub const DEFAULT_SIGNED_TOKEN_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub struct TokenSigner {
+    /// Never empty; the first key signs.
+    keys: Vec<TokenSigningKey>,
+    ttl: Duration,
+}
+
+impl TokenSigner {
+    /// `None` without keys, which leaves the format disabled.
+    pub fn new(keys: Vec<TokenSigningKey>, ttl: Duration) -> Option<Self> {
+        (!keys.is_empty()).then_some(Self { keys, ttl })
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Signs the record's balance and expiry. Returns `None` for suspended,
+    /// revoked and expired tokens, which have nothing to vouch for.
+    pub fn issue(&self, record: &ServiceTokenRecord, now: DateTime<Utc>) -> Option<String> {
+        if !record.state(now).is_usable() {
+            return None;
+        }
+        let ttl = chrono::Duration::from_std(self.ttl).unwrap_or(chrono::Duration::MAX);
+        let exp = now
+            .checked_add_signed(ttl)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let claims = SignedTokenClaims {
+            kid: self.keys[0].id().to_string(),
+            sub: token_subject(&record.token),
+            balance: record.amount,
+            exp: record.expires_at.map_or(exp, |at| at.min(exp)).timestamp(),
+        };
+        Some(sign_token(&self.keys[0], &claims))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::{PaymentId, ServiceToken};
+    use anon_ticket_domain::services::token_signing::verify_signed_token;
+
+    #[test]
+    fn signs_with_the_first_key_until_the_token_expires() {
+        let keys = vec![
+            TokenSigningKey::new("k2", "0123456789abcdef0123456789abcdef"),
+            TokenSigningKey::new("k1", "fedcba9876543210fedcba9876543210"),
+        ];
+        let signer = TokenSigner::new(keys.clone(), Duration::from_secs(600)).unwrap();
+        let now = Utc::now();
+        let soon = now + chrono::Duration::seconds(60);
+        let record = ServiceTokenRecord {
+            token: ServiceToken::from_bytes([7; 32]),
+            pid: PaymentId::parse("0123456789abcdef").unwrap(),
+            amount: 42,
+            issued_at: now,
+            revoked_at: None,
+            revoke_reason: None,
+            abuse_score: 0,
+            expires_at: Some(soon),
+            tier: None,
+            suspended_at: None,
+            suspend_reason: None,
+            suspended_until: None,
+        };
+
+        let signed = signer.issue(&record, now).expect("active token");
+        let claims = verify_signed_token(&keys[1..], &signed, now);
+        assert!(claims.is_err(), "signed by the first key");
+        let claims = verify_signed_token(&keys, &signed, now).unwrap();
+        assert_eq!(claims.kid, "k2");
+        assert_eq!(claims.exp, soon.timestamp());
+        assert_eq!(claims.sub, token_subject(&record.token));
+
+        let revoked = ServiceTokenRecord {
+            revoked_at: Some(now),
+            ..record
+        };
+        assert_eq!(signer.issue(&revoked, now), None);
+        assert!(TokenSigner::new(Vec::new(), Duration::from_secs(600)).is_none());
+    }
+}
@@ -0,0 +1,110 @@
+//! The single startup line, so a misconfigured deployment can be diagnosed
+//! from its logs alone: where the process listens, what it runs, and against
+//! which database and schema.
+
+use std::net::SocketAddr;
+
+use metrics::gauge;
+use tracing::info;
+
+/// What this process ended up serving once every listener is bound.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StartupBanner {
+    pub(crate) version: String,
+    pub(crate) git_sha: String,
+    /// `tcp://addr` or `unix:path` per bound socket.
+    pub(crate) public: Vec<String>,
+    pub(crate) internal: Vec<String>,
+    pub(crate) grpc: Option<String>,
+    pub(crate) embedded_monitor: bool,
+    /// `sqlite` or `postgres`.
+    pub(crate) backend: &'static str,
+    /// See `anon_ticket_storage::schema_fingerprint`.
+    pub(crate) schema: String,
+    pub(crate) read_only: bool,
+    pub(crate) read_replica: bool,
+    /// Whether changes made by other instances are applied as they happen.
+    pub(crate) change_feed: bool,
+}
+
+impl StartupBanner {
+    /// Logs the banner and sets `api_startup_info` to 1 with the same
+    /// settings, minus the addresses, as labels.
+    pub(crate) fn emit(&self) {
+        info!(
+            version = %self.version,
+            git_sha = %self.git_sha,
+            public = %self.public.join(","),
+            internal = %self.internal.join(","),
+            grpc = self.grpc.as_deref().unwrap_or("off"),
+            embedded_monitor = self.embedded_monitor,
+            backend = self.backend,
+            schema = %self.schema,
+            read_only = self.read_only,
+            read_replica = self.read_replica,
+            change_feed = self.change_feed,
+            "api started"
+        );
+        gauge!("api_startup_info", &self.labels()).set(1.0);
+    }
+
+    fn labels(&self) -> Vec<(&'static str, String)> {
+        let flag = |on: bool| if on { "on" } else { "off" }.to_string();
+        vec![
+            ("backend", self.backend.to_string()),
+            ("schema", self.schema.clone()),
+            ("public", listener_kinds(&self.public)),
+            ("internal", listener_kinds(&self.internal)),
+            ("grpc", flag(self.grpc.is_some())),
+            ("embedded_monitor", flag(self.embedded_monitor)),
+            ("read_only", flag(self.read_only)),
+            ("read_replica", flag(self.read_replica)),
+            ("change_feed", flag(self.change_feed)),
+        ]
+    }
+}
+
+pub(crate) fn tcp_listeners(addrs: &[SocketAddr]) -> Vec<String> {
+    addrs.iter().map(|addr| format!("tcp://{addr}")).collect()
+}
+
+pub(crate) fn unix_listener(path: &str) -> Vec<String> {
+    vec![format!("unix:{path}")]
+}
+
+/// `tcp`, `unix`, or `none`, from the listeners' schemes.
+fn listener_kinds(listeners: &[String]) -> String {
+    match listeners.first() {
+        Some(listener) if listener.starts_with("unix:") => "unix".to_string(),
+        Some(_) => "tcp".to_string(),
+        None => "none".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_leave_out_addresses() {
+        let banner = StartupBanner {
+            version: "0.1.0".into(),
+            git_sha: "abc1234".into(),
+            public: tcp_listeners(&["127.0.0.1:8080".parse().unwrap()]),
+            internal: unix_listener("/run/anon-ticket/internal.sock"),
+            grpc: None,
+            embedded_monitor: true,
+            backend: "sqlite",
+            schema: "0123456789abcdef".into(),
+            read_only: false,
+            read_replica: false,
+            change_feed: false,
+        };
+        assert_eq!(banner.public, ["tcp://127.0.0.1:8080"]);
+        let labels = banner.labels();
+        assert!(labels.contains(&("public", "tcp".to_string())));
+        assert!(labels.contains(&("internal", "unix".to_string())));
+        assert!(labels.contains(&("embedded_monitor", "on".to_string())));
+        assert!(labels.contains(&("grpc", "off".to_string())));
+    }
+}
@@ -1,18 +1,82 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
 
+use anon_ticket_domain::config::{
+    CheckoutPreset, OverpaymentPolicy, SubscriptionPeriod, TokenTier,
+};
+use anon_ticket_domain::integrated_address::AddressBook;
 use anon_ticket_domain::model::PaymentId;
 use anon_ticket_domain::services::{
-    cache::{InMemoryPidCache, PidBloom},
+    cache::{InMemoryPidCache, PidBloom, PidCache},
+    rate_limit::RateLimiter,
     telemetry::TelemetryGuard,
 };
+use anon_ticket_monitor::{
+    MonitorHeartbeat, PaymentEvents, SubaddressTransferSource, WebhookSender,
+};
 use anon_ticket_storage::SeaOrmStorage;
+use chrono::{DateTime, Utc};
+
+use crate::auth::InternalAuth;
+use crate::fee::FeeEstimator;
+use crate::handlers::forward_auth::ForwardAuthSource;
+use crate::health::Health;
+use crate::jwt::TokenJwtIssuer;
+use crate::pending::PendingPayments;
+use crate::signed_token::TokenSigner;
 
 #[derive(Clone)]
 pub struct AppState {
     storage: SeaOrmStorage,
     cache: Arc<InMemoryPidCache>,
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
     telemetry: TelemetryGuard,
     bloom: Option<Arc<PidBloom>>,
+    shared_cache: Option<Arc<dyn PidCache>>,
+    rate_limiter: Option<Arc<dyn RateLimiter>>,
+    internal_auth: Option<Arc<InternalAuth>>,
+    checkout_enabled: bool,
+    checkout_presets: Arc<[CheckoutPreset]>,
+    token_tiers: Arc<[TokenTier]>,
+    subscription_period: Option<SubscriptionPeriod>,
+    quote_ttl: Option<Duration>,
+    overpayment_policy: OverpaymentPolicy,
+    refund_grace: Duration,
+    token_ttl: Option<Duration>,
+    token_jwt: Option<Arc<TokenJwtIssuer>>,
+    token_signer: Option<Arc<TokenSigner>>,
+    forward_auth: Arc<ForwardAuthSource>,
+    service_info: Arc<ServiceInfo>,
+    fee_estimator: Option<Arc<FeeEstimator>>,
+    subaddresses: Option<Arc<SubaddressTransferSource<SeaOrmStorage>>>,
+    payment_events: Option<PaymentEvents>,
+    pending_payments: PendingPayments,
+    webhooks: Option<WebhookSender>,
+    monitor_heartbeat: Option<MonitorHeartbeat>,
+    monitor_stale_after: Duration,
+    health: Health,
+    effective_config: Option<Arc<EffectiveConfig>>,
+}
+
+/// Operational parameters published on `GET /api/v1/info`. Unknown values
+/// (e.g. no embedded monitor) are left out rather than guessed.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceInfo {
+    /// Addresses quotes can be issued against; the primary address, if
+    /// configured, is filed under `PRIMARY_WALLET`.
+    pub addresses: AddressBook,
+    pub min_payment_amount: Option<i64>,
+    pub min_confirmations: Option<u64>,
+}
+
+/// Redacted settings reported by `GET /internal/config`, keyed by the
+/// variable that sets them (see `ApiConfig::redacted_env`).
+#[derive(Debug, Clone, Default)]
+pub struct EffectiveConfig {
+    pub api: BTreeMap<&'static str, String>,
+    /// `None` when this process does not run the monitor.
+    pub monitor: Option<BTreeMap<&'static str, String>>,
 }
 
 impl AppState {
@@ -27,9 +91,282 @@ impl AppState {
             cache,
             telemetry,
             bloom,
+            shared_cache: None,
+            rate_limiter: None,
+            internal_auth: None,
+            checkout_enabled: false,
+            checkout_presets: Arc::from([]),
+            token_tiers: Arc::from([]),
+            subscription_period: None,
+            quote_ttl: None,
+            overpayment_policy: OverpaymentPolicy::default(),
+            refund_grace: Duration::ZERO,
+            token_ttl: None,
+            token_jwt: None,
+            token_signer: None,
+            forward_auth: Arc::default(),
+            service_info: Arc::default(),
+            fee_estimator: None,
+            subaddresses: None,
+            payment_events: None,
+            pending_payments: PendingPayments::default(),
+            webhooks: None,
+            monitor_heartbeat: None,
+            monitor_stale_after: Duration::ZERO,
+            health: Health::default(),
+            effective_config: None,
         }
     }
 
+    /// Cache shared across replicas (e.g. Redis), consulted when the local
+    /// Bloom filter has not yet seen a PID.
+    pub fn with_shared_cache(mut self, cache: Option<Arc<dyn PidCache>>) -> Self {
+        self.shared_cache = cache;
+        self
+    }
+
+    pub fn shared_cache(&self) -> Option<&dyn PidCache> {
+        self.shared_cache.as_deref()
+    }
+
+    /// Per-PID/per-token limiter for public routes; `None` disables it.
+    pub fn with_rate_limiter(mut self, limiter: Option<Arc<dyn RateLimiter>>) -> Self {
+        self.rate_limiter = limiter;
+        self
+    }
+
+    pub fn rate_limiter(&self) -> Option<&dyn RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+
+    /// Enables bearer-key checks on internal routes.
+    pub fn with_internal_auth(mut self, auth: Option<InternalAuth>) -> Self {
+        self.internal_auth = auth.map(Arc::new);
+        self
+    }
+
+    /// Enables the public checkout endpoint that issues PID + client secret
+    /// pairs.
+    pub fn with_checkout(mut self, enabled: bool) -> Self {
+        self.checkout_enabled = enabled;
+        self
+    }
+
+    pub fn checkout_enabled(&self) -> bool {
+        self.checkout_enabled
+    }
+
+    pub fn with_checkout_presets(mut self, presets: Vec<CheckoutPreset>) -> Self {
+        self.checkout_presets = presets.into();
+        self
+    }
+
+    pub fn checkout_preset(&self, name: &str) -> Option<&CheckoutPreset> {
+        self.checkout_presets
+            .iter()
+            .find(|preset| preset.name() == name)
+    }
+
+    pub fn with_token_tiers(mut self, mut tiers: Vec<TokenTier>) -> Self {
+        tiers.sort_by_key(TokenTier::min_amount);
+        self.token_tiers = tiers.into();
+        self
+    }
+
+    /// Highest tier a payment of `amount` reaches.
+    pub fn token_tier_for(&self, amount: i64) -> Option<&TokenTier> {
+        self.token_tiers
+            .iter()
+            .rev()
+            .find(|tier| amount >= tier.min_amount())
+    }
+
+    pub fn token_tier(&self, name: &str) -> Option<&TokenTier> {
+        self.token_tiers.iter().find(|tier| tier.name() == name)
+    }
+
+    /// Makes tokens expire and lets repeat payments to their PID renew them.
+    pub fn with_subscription_period(mut self, period: Option<SubscriptionPeriod>) -> Self {
+        self.subscription_period = period;
+        self
+    }
+
+    pub fn subscription_period(&self) -> Option<SubscriptionPeriod> {
+        self.subscription_period
+    }
+
+    /// Enables the public quote endpoint; issued quotes stay payable for
+    /// `ttl`.
+    pub fn with_quote_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.quote_ttl = ttl;
+        self
+    }
+
+    pub fn quote_ttl(&self) -> Option<Duration> {
+        self.quote_ttl
+    }
+
+    /// What redemption does with the excess of an overpaid quote; under
+    /// [`OverpaymentPolicy::Refund`] it is held for `grace` before being
+    /// credited.
+    pub fn with_overpayment_policy(mut self, policy: OverpaymentPolicy, grace: Duration) -> Self {
+        self.overpayment_policy = policy;
+        self.refund_grace = grace;
+        self
+    }
+
+    pub fn overpayment_policy(&self) -> OverpaymentPolicy {
+        self.overpayment_policy
+    }
+
+    pub fn refund_grace(&self) -> Duration {
+        self.refund_grace
+    }
+
+    /// Lifetime given to newly issued tokens; `None` issues tokens that never
+    /// expire.
+    pub fn with_token_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.token_ttl = ttl;
+        self
+    }
+
+    /// Expiry for a token issued at `issued_at`.
+    pub fn token_expires_at(&self, issued_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.token_ttl.map(|ttl| expires_after(issued_at, ttl))
+    }
+
+    /// Enables `format=jwt` on redeem; `None` rejects it.
+    pub fn with_token_jwt(mut self, issuer: Option<TokenJwtIssuer>) -> Self {
+        self.token_jwt = issuer.map(Arc::new);
+        self
+    }
+
+    pub fn token_jwt(&self) -> Option<&TokenJwtIssuer> {
+        self.token_jwt.as_deref()
+    }
+
+    /// Enables `format=signed` on redeem and the revocation list; `None`
+    /// disables both.
+    pub fn with_token_signer(mut self, signer: Option<TokenSigner>) -> Self {
+        self.token_signer = signer.map(Arc::new);
+        self
+    }
+
+    pub fn token_signer(&self) -> Option<&TokenSigner> {
+        self.token_signer.as_deref()
+    }
+
+    /// Where `GET /api/v1/forward-auth` finds the token.
+    pub fn with_forward_auth(mut self, source: ForwardAuthSource) -> Self {
+        self.forward_auth = Arc::new(source);
+        self
+    }
+
+    pub fn forward_auth(&self) -> &ForwardAuthSource {
+        &self.forward_auth
+    }
+
+    pub fn with_service_info(mut self, info: ServiceInfo) -> Self {
+        self.service_info = Arc::new(info);
+        self
+    }
+
+    pub fn service_info(&self) -> &ServiceInfo {
+        &self.service_info
+    }
+
+    /// Enables `GET /api/v1/fee-estimate`.
+    pub fn with_fee_estimator(mut self, estimator: Option<FeeEstimator>) -> Self {
+        self.fee_estimator = estimator.map(Arc::new);
+        self
+    }
+
+    pub fn fee_estimator(&self) -> Option<&FeeEstimator> {
+        self.fee_estimator.as_deref()
+    }
+
+    /// Subaddress detection mode: checkout hands each PID its own
+    /// subaddress.
+    pub fn with_subaddresses(
+        mut self,
+        source: Option<Arc<SubaddressTransferSource<SeaOrmStorage>>>,
+    ) -> Self {
+        self.subaddresses = source;
+        self
+    }
+
+    pub fn subaddresses(&self) -> Option<&SubaddressTransferSource<SeaOrmStorage>> {
+        self.subaddresses.as_deref()
+    }
+
+    /// Enables `GET /api/v1/payment/{pid}/events`, fed by the embedded
+    /// monitor.
+    pub fn with_payment_events(mut self, events: Option<PaymentEvents>) -> Self {
+        self.payment_events = events;
+        self
+    }
+
+    pub fn payment_events(&self) -> Option<&PaymentEvents> {
+        self.payment_events.as_ref()
+    }
+
+    /// Transfers detected but not yet confirmed, as far as the embedded
+    /// monitor has reported them; empty without one.
+    pub fn pending_payments(&self) -> &PendingPayments {
+        &self.pending_payments
+    }
+
+    /// Queue of the embedded monitor's webhook dispatcher, which also sends
+    /// `token.revoked` for revocations made through this API.
+    pub fn with_webhooks(mut self, webhooks: Option<WebhookSender>) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    pub fn webhooks(&self) -> Option<&WebhookSender> {
+        self.webhooks.as_ref()
+    }
+
+    /// Heartbeat of the embedded monitor, reported by `/healthz`. A wallet
+    /// whose last poll ended more than `stale_after` ago, with none in
+    /// progress, counts as stale.
+    pub fn with_monitor_heartbeat(
+        mut self,
+        heartbeat: Option<MonitorHeartbeat>,
+        stale_after: Duration,
+    ) -> Self {
+        self.monitor_heartbeat = heartbeat;
+        self.monitor_stale_after = stale_after;
+        self
+    }
+
+    pub fn monitor_heartbeat(&self) -> Option<&MonitorHeartbeat> {
+        self.monitor_heartbeat.as_ref()
+    }
+
+    pub fn monitor_stale_after(&self) -> Duration {
+        self.monitor_stale_after
+    }
+
+    /// Enables `GET /internal/config`.
+    pub fn with_effective_config(mut self, config: Option<EffectiveConfig>) -> Self {
+        self.effective_config = config.map(Arc::new);
+        self
+    }
+
+    pub fn effective_config(&self) -> Option<&EffectiveConfig> {
+        self.effective_config.as_deref()
+    }
+
+    /// Readiness switch shared by every clone of this state.
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    pub fn checkout_presets(&self) -> &[CheckoutPreset] {
+        &self.checkout_presets
+    }
+
     pub fn storage(&self) -> &SeaOrmStorage {
         &self.storage
     }
@@ -38,17 +375,35 @@ impl AppState {
         self.cache.as_ref()
     }
 
+    #[cfg(feature = "metrics")]
     pub fn telemetry(&self) -> &TelemetryGuard {
         &self.telemetry
     }
 
+    pub fn internal_auth(&self) -> Option<&InternalAuth> {
+        self.internal_auth.as_deref()
+    }
+
     pub fn bloom(&self) -> Option<&PidBloom> {
         self.bloom.as_deref()
     }
 
+    /// Records a PID confirmed by storage in the Bloom filter and, when
+    /// configured, the shared cache so other replicas see it too.
     pub fn insert_bloom(&self, pid: &PaymentId) {
         if let Some(bloom) = &self.bloom {
             bloom.insert(pid);
         }
+        if let Some(cache) = &self.shared_cache {
+            cache.mark_present(pid);
+        }
     }
 }
+
+/// `issued_at + lifetime`, saturating on overflow.
+pub(crate) fn expires_after(issued_at: DateTime<Utc>, lifetime: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(lifetime)
+        .ok()
+        .and_then(|lifetime| issued_at.checked_add_signed(lifetime))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
@@ -0,0 +1,232 @@
+//! End-to-end scenario wiring the embedded monitor, the public/internal
+//! routers, and a real SQLite database. The wallet is replaced by a scripted
+//! `TransferSource` so the test can step detection → confirmation → redeem →
+//! revoke deterministically without sleeping.
+
+use std::sync::{Arc, Mutex};
+
+use actix_web::{body::to_bytes, http::StatusCode, test, web, App};
+use anon_ticket_domain::config::PRIMARY_WALLET;
+use anon_ticket_domain::model::{PaymentState, ServiceToken};
+use anon_ticket_domain::services::cache::{InMemoryPidCache, PidBloom, PidCache};
+use anon_ticket_domain::storage::{MonitorStateStore, PaymentStore, TokenStore};
+use anon_ticket_monitor::{
+    poll_once, IngestRules, MonitorError, MonitorHooks, PollOutcome, TransferEntry, TransferSource,
+    TransfersResponse, WalletCursor,
+};
+use anon_ticket_storage::SeaOrmStorage;
+use anon_ticket_testkit::{default_pid, DEFAULT_TXID};
+use async_trait::async_trait;
+
+use super::{build_state, storage};
+use crate::application::{internal_routes, public_routes};
+use crate::handlers::{
+    redeem::{RedeemRequest, RedeemResponse},
+    token::{RevokeRequest, TokenState, TokenStatusResponse},
+};
+
+const MIN_CONFIRMATIONS: u64 = 10;
+const MIN_PAYMENT_AMOUNT: i64 = 1;
+const PAYMENT_HEIGHT: i64 = 100;
+
+/// Wallet stand-in whose height and transfer list are mutated by the test.
+#[derive(Default)]
+struct ScriptedWallet {
+    height: Mutex<u64>,
+    transfers: Mutex<Vec<TransferEntry>>,
+}
+
+impl ScriptedWallet {
+    fn set_height(&self, height: u64) {
+        *self.height.lock().unwrap() = height;
+    }
+
+    fn set_transfer(&self, entry: TransferEntry) {
+        let mut transfers = self.transfers.lock().unwrap();
+        transfers.retain(|existing| existing.txid != entry.txid);
+        transfers.push(entry);
+    }
+}
+
+#[async_trait]
+impl TransferSource for ScriptedWallet {
+    async fn fetch_transfers(
+        &self,
+        start_height: u64,
+        max_height: u64,
+    ) -> Result<TransfersResponse, MonitorError> {
+        let incoming = self
+            .transfers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| match entry.height {
+                Some(h) => (start_height..=max_height).contains(&(h as u64)),
+                None => false,
+            })
+            .cloned()
+            .collect();
+        Ok(TransfersResponse {
+            incoming,
+            skipped: Vec::new(),
+        })
+    }
+
+    async fn wallet_height(&self) -> Result<u64, MonitorError> {
+        Ok(*self.height.lock().unwrap())
+    }
+}
+
+fn payment_entry(height: Option<i64>) -> TransferEntry {
+    TransferEntry {
+        txid: DEFAULT_TXID.to_string(),
+        amount: 5_000,
+        height,
+        timestamp: 1_700_000_000,
+        payment_id: Some(default_pid().to_hex()),
+    }
+}
+
+async fn step(
+    storage: &SeaOrmStorage,
+    wallet: &ScriptedWallet,
+    hooks: &MonitorHooks,
+    cursor: &mut WalletCursor,
+) -> PollOutcome {
+    let wallet_height = wallet.wallet_height().await.unwrap();
+    poll_once(
+        storage,
+        wallet,
+        cursor,
+        wallet_height,
+        IngestRules::new(MIN_PAYMENT_AMOUNT),
+        MIN_CONFIRMATIONS,
+        Some(hooks),
+    )
+    .await
+    .expect("poll succeeds")
+}
+
+#[actix_web::test]
+async fn payment_flows_from_detection_to_revocation() {
+    let storage = storage().await;
+    let cache = Arc::new(InMemoryPidCache::default());
+    let bloom = Arc::new(PidBloom::new(10_000, 0.01).unwrap());
+    let hooks = MonitorHooks::new(
+        Some(cache.clone() as Arc<dyn PidCache>),
+        Some(bloom.clone()),
+    );
+    let state = build_state(storage.clone(), cache.clone(), Some(bloom.clone()));
+    let public_app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .configure(public_routes),
+    )
+    .await;
+    let internal_app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .configure(internal_routes),
+    )
+    .await;
+
+    let wallet = ScriptedWallet::default();
+    let pid = default_pid();
+    let mut cursor = WalletCursor::primary(90);
+    let redeem = || {
+        test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .set_json(&RedeemRequest {
+                pid: pid.to_hex(),
+                client_secret: None,
+                passphrase: None,
+            })
+            .to_request()
+    };
+    // Detection: the transfer sits in the pool and must not be ingested.
+    wallet.set_height(PAYMENT_HEIGHT as u64);
+    wallet.set_transfer(payment_entry(None));
+    step(&storage, &wallet, &hooks, &mut cursor).await;
+    assert!(storage.find_payment(&pid).await.unwrap().is_none());
+    let resp = test::call_service(&public_app, redeem()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Mined but still inside the confirmation window.
+    wallet.set_transfer(payment_entry(Some(PAYMENT_HEIGHT)));
+    wallet.set_height(PAYMENT_HEIGHT as u64 + 5);
+    step(&storage, &wallet, &hooks, &mut cursor).await;
+    assert!(storage.find_payment(&pid).await.unwrap().is_none());
+    assert!(!bloom.might_contain(&pid));
+
+    // Confirmed: the monitor persists the payment and warms the hints.
+    wallet.set_height(PAYMENT_HEIGHT as u64 + MIN_CONFIRMATIONS);
+    let outcome = step(&storage, &wallet, &hooks, &mut cursor).await;
+    assert_eq!(
+        outcome,
+        PollOutcome::Advanced {
+            next_height: PAYMENT_HEIGHT as u64 + 1
+        }
+    );
+    let payment = storage.find_payment(&pid).await.unwrap().expect("ingested");
+    assert_eq!(payment.status, PaymentState::Confirmed);
+    assert_eq!(payment.block_height, PAYMENT_HEIGHT);
+    assert_eq!(
+        storage.last_processed_height(PRIMARY_WALLET).await.unwrap(),
+        Some(PAYMENT_HEIGHT as u64 + 1)
+    );
+    assert!(bloom.might_contain(&pid));
+    assert!(cache.known_present(&pid));
+
+    // Redeem through the public router.
+    let resp = test::call_service(&public_app, redeem()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let redeemed: RedeemResponse =
+        serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(redeemed.status, "success");
+    assert_eq!(redeemed.balance, 5_000);
+    let token = ServiceToken::parse(&redeemed.service_token).unwrap();
+    let claimed = storage.find_payment(&pid).await.unwrap().unwrap();
+    assert_eq!(claimed.status, PaymentState::Claimed);
+
+    // Revoke through the internal router and observe it publicly.
+    let resp = test::call_service(
+        &internal_app,
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/token/{}/revoke", token.to_hex()))
+            .set_json(&RevokeRequest {
+                reason: Some("e2e".into()),
+                abuse_score: Some(1),
+            })
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let record = storage.find_token(&token).await.unwrap().unwrap();
+    assert_eq!(record.revoke_reason.as_deref(), Some("e2e"));
+
+    let resp = test::call_service(
+        &public_app,
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/token/{}", token.to_hex()))
+            .to_request(),
+    )
+    .await;
+    let status: TokenStatusResponse =
+        serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(status.status, TokenState::Revoked);
+
+    // Both halves of the stack reported into the shared recorder.
+    #[cfg(feature = "metrics")]
+    {
+        let resp = test::call_service(
+            &internal_app,
+            test::TestRequest::get().uri("/metrics").to_request(),
+        )
+        .await;
+        let metrics =
+            String::from_utf8(to_bytes(resp.into_body()).await.unwrap().to_vec()).unwrap();
+        assert!(metrics.contains("monitor_payments_ingested_total"));
+        assert!(metrics.contains("api_redeem_requests_total"));
+        assert!(metrics.contains("api_token_requests_total"));
+    }
+}
@@ -0,0 +1,152 @@
+use anon_ticket_domain::config::{InternalApiKey, InternalRole};
+use anon_ticket_testkit::{PaymentFixture, TokenFixture};
+use tonic::server::NamedService;
+use tonic::{Code, Request};
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+use tonic_health::server::health_reporter;
+
+use super::{storage, with_cache};
+use crate::auth::InternalAuth;
+use crate::grpc::{
+    report_health, GetPaymentStatusRequest, Internal, InternalServer, InternalService,
+    PaymentState, RevokeTokenRequest, TokenState, VerifyTokenRequest, ERROR_CODE_METADATA,
+};
+
+const READ_ONLY: &str = "read-only-secret-0001";
+const SUPPORT: &str = "support-secret-000002";
+
+fn authorized<T>(message: T, secret: &str) -> Request<T> {
+    let mut request = Request::new(message);
+    request
+        .metadata_mut()
+        .insert("authorization", format!("Bearer {secret}").parse().unwrap());
+    request
+}
+
+#[actix_web::test]
+async fn grpc_serves_tokens_and_payments_with_key_roles() {
+    let storage = storage().await;
+    let payment = PaymentFixture::claimed().insert(&storage).await.unwrap();
+    let token = TokenFixture::active().insert(&storage).await.unwrap().token;
+    let keys = [
+        InternalApiKey::new("grafana", InternalRole::ReadOnly, READ_ONLY),
+        InternalApiKey::new("helpdesk", InternalRole::Support, SUPPORT),
+    ];
+    let service = InternalService::new(
+        with_cache(storage).with_internal_auth(InternalAuth::from_keys(&keys)),
+    );
+    let verify = || VerifyTokenRequest {
+        token: token.clone().into_inner(),
+        passphrase: None,
+    };
+
+    let err = service
+        .verify_token(Request::new(verify()))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+    assert_eq!(
+        err.metadata().get(ERROR_CODE_METADATA).unwrap(),
+        "unauthorized"
+    );
+
+    let status = service
+        .verify_token(authorized(verify(), READ_ONLY))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(status.state(), TokenState::Active);
+    assert_eq!(status.revoked_at, None);
+
+    let payment_status = service
+        .get_payment_status(authorized(
+            GetPaymentStatusRequest {
+                pid: payment.pid.clone().into_inner(),
+            },
+            READ_ONLY,
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(payment_status.state(), PaymentState::Claimed);
+    assert_eq!(payment_status.amount, payment.amount);
+    assert_eq!(payment_status.block_height, payment.block_height);
+
+    let err = service
+        .get_payment_status(authorized(
+            GetPaymentStatusRequest {
+                pid: anon_ticket_testkit::nth_pid(7).into_inner(),
+            },
+            READ_ONLY,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    let revoke = || RevokeTokenRequest {
+        token: token.clone().into_inner(),
+        reason: Some("abuse".into()),
+        abuse_score: Some(3),
+    };
+    let err = service
+        .revoke_token(authorized(revoke(), READ_ONLY))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::PermissionDenied);
+
+    let revoked = service
+        .revoke_token(authorized(revoke(), SUPPORT))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(revoked.state(), TokenState::Revoked);
+    assert_eq!(revoked.abuse_score, 3);
+    assert!(revoked.revoked_at.is_some());
+
+    let err = service
+        .revoke_token(authorized(
+            RevokeTokenRequest {
+                abuse_score: Some(i32::MAX),
+                ..revoke()
+            },
+            SUPPORT,
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+}
+
+#[actix_web::test]
+async fn health_service_follows_readiness() {
+    let state = with_cache(storage().await);
+    let (reporter, server) = health_reporter();
+    let updater = tokio::spawn(report_health(state.clone(), reporter));
+    let mut client = HealthClient::new(server);
+    let check = |service: &str| HealthCheckRequest {
+        service: service.to_string(),
+    };
+    let internal = <InternalServer<InternalService> as NamedService>::NAME;
+
+    // `Internal` is registered by the first refresh.
+    for _ in 0..100 {
+        if client.check(check(internal)).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    for service in ["", internal] {
+        let status = client.check(check(service)).await.unwrap().into_inner();
+        assert_eq!(status.status(), ServingStatus::Serving, "{service:?}");
+    }
+
+    state.health().start_draining();
+    updater.await.unwrap();
+    for service in ["", internal] {
+        let status = client.check(check(service)).await.unwrap().into_inner();
+        assert_eq!(status.status(), ServingStatus::NotServing, "{service:?}");
+    }
+    let err = client.check(check("unknown")).await.unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+}
@@ -0,0 +1,3608 @@
+mod e2e;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod snapshots;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use actix_web::{body::to_bytes, test, web, App};
+use anon_ticket_domain::config::{
+    ApiConfig, CheckoutPreset, OverpaymentPolicy, SubscriptionPeriod, TokenSigningKey, TokenTier,
+    PRIMARY_WALLET,
+};
+use anon_ticket_domain::integrated_address::{
+    build_integrated_address, decode_integrated_address, AddressBook,
+};
+use anon_ticket_domain::model::{
+    NewPayment, NewPaymentQuote, NewRefund, PaymentId, PaymentState as StoredPaymentState,
+    PendingTransfer, RefundOutcome, RevokeTokenRequest, ServiceToken, SkippedTransfer,
+};
+use anon_ticket_domain::services::{
+    cache::{InMemoryPidCache, PidBloom, PidCache},
+    rate_limit::{InMemoryRateLimiter, RateLimit},
+    telemetry::{init_telemetry, TelemetryConfig, TelemetryGuard},
+    token_signing::{token_digest, token_subject, verify_signed_token},
+};
+use anon_ticket_domain::storage::{
+    PaymentStore, PendingPaymentStore, QuoteStore, RefundStore, SkippedTransferStore, TokenStore,
+};
+use anon_ticket_monitor::{
+    FeeEstimate, MonitorError, MonitorHeartbeat, PaymentEvent, PaymentEventKind, PaymentEvents,
+    TransferSource, TransfersResponse,
+};
+use anon_ticket_storage::{Change, SeaOrmStorage};
+use anon_ticket_testkit::{nth_pid, PaymentFixture, TokenFixture};
+
+use crate::application::{internal_routes, public_routes, purge_expired_tokens};
+use crate::auth::{verify_signed_request, InternalAuth};
+use crate::changefeed::apply_change;
+use crate::consistency::{audit_once, DivergenceReport};
+use crate::fee::FeeEstimator;
+use crate::handlers::{
+    cache::{CacheFlushResponse, CacheStatsResponse},
+    checkout::{checkout_handler, CheckoutRequest, CheckoutResponse},
+    config::RuntimeConfigResponse,
+    credit::{CreditSummary, ResolveCreditRequest},
+    fee::{FeeEstimateResponse, TYPICAL_TX_WEIGHT},
+    forward_auth::ForwardAuthSource,
+    info::InfoResponse,
+    introspect::IntrospectResponse,
+    listing::{AuditEventSummary, Page, TokenSummary},
+    payment::{
+        ClaimOverrideRequest, ClaimOverrideResponse, InjectPaymentRequest, PaymentResponse,
+        PaymentState,
+    },
+    payment_status::PaymentStatusResponse,
+    quote::{QuoteRequest, QuoteResponse},
+    redeem::{
+        redeem_handler, RedeemRequest, RedeemResponse, IDEMPOTENCY_KEY_HEADER,
+        IDEMPOTENT_REPLAYED_HEADER,
+    },
+    refund::{CancelRefundRequest, RefundRequest, RefundSentRequest, RefundSummary},
+    revocations::{
+        RevocationExportResponse, RevocationListResponse, EXPORT_CURSOR_HEADER, EXPORT_MORE_HEADER,
+    },
+    search::SearchResponse,
+    skipped::{SkippedActionRequest, SkippedTransferSummary},
+    token::{
+        revoke_token_handler, token_balance_handler, token_status_handler, MergeRequest,
+        MergeResponse, RevokeRequest, SpendRequest, SpendResponse, SplitRequest, SplitResponse,
+        SuspendRequest, TokenBalanceResponse, TokenState, TokenStatusResponse, PASSPHRASE_HEADER,
+    },
+    webhook::{RedeliverRequest, WebhookEventResponse},
+};
+use crate::health::{CheckStatus, HealthReport, Readiness};
+use crate::jwt::{TokenClaims, TokenJwtIssuer, TOKEN_JWT_ISSUER};
+use crate::prewarm::prewarm_hints;
+use crate::signed_token::TokenSigner;
+use crate::state::{AppState, EffectiveConfig, ServiceInfo};
+
+fn test_pid() -> PaymentId {
+    anon_ticket_testkit::default_pid()
+}
+
+async fn storage() -> SeaOrmStorage {
+    SeaOrmStorage::connect("sqlite::memory:")
+        .await
+        .expect("storage inits")
+}
+
+fn telemetry() -> TelemetryGuard {
+    let config = TelemetryConfig::from_env("API_TEST");
+    init_telemetry(&config).expect("telemetry inits")
+}
+
+fn build_state(
+    storage: SeaOrmStorage,
+    cache: Arc<InMemoryPidCache>,
+    bloom: Option<Arc<PidBloom>>,
+) -> AppState {
+    let telemetry = telemetry();
+    AppState::new(storage, cache, telemetry.clone(), bloom)
+}
+
+fn with_cache(storage: SeaOrmStorage) -> AppState {
+    build_state(storage, Arc::new(InMemoryPidCache::default()), None)
+}
+
+async fn insert_token(storage: &SeaOrmStorage) -> ServiceToken {
+    TokenFixture::active().insert(storage).await.unwrap().token
+}
+
+#[actix_web::test]
+async fn rejects_invalid_pid_format() {
+    let state = with_cache(storage().await);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: "short".into(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "invalid_pid");
+}
+
+#[actix_web::test]
+async fn returns_not_found_when_pid_missing() {
+    let state = with_cache(storage().await);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: test_pid().into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn redeems_successfully() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: test_pid().into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let parsed: RedeemResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(parsed.balance, 42);
+    assert_eq!(parsed.status, "success");
+    assert!(parsed.jwt.is_none());
+}
+
+#[actix_web::test]
+async fn redeems_straight_into_a_jwt() {
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let jwt_state = with_cache(storage.clone()).with_token_jwt(Some(TokenJwtIssuer::new(
+        SECRET,
+        std::time::Duration::from_secs(600),
+    )));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(jwt_state))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+    let redeem = |format: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/redeem?format={format}"))
+            .set_json(&RedeemRequest {
+                pid: test_pid().into_inner(),
+                client_secret: None,
+                passphrase: None,
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, redeem("jwt")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let parsed: RedeemResponse = test::read_body_json(resp).await;
+    let mut validation = jsonwebtoken::Validation::default();
+    validation.set_issuer(&[TOKEN_JWT_ISSUER]);
+    let claims = jsonwebtoken::decode::<TokenClaims>(
+        parsed.jwt.as_deref().expect("jwt requested"),
+        &jsonwebtoken::DecodingKey::from_secret(SECRET),
+        &validation,
+    )
+    .expect("signed with the configured key")
+    .claims;
+    assert_eq!(claims.balance, parsed.balance);
+    assert_ne!(claims.sub, parsed.service_token);
+
+    let resp = test::call_service(&app, redeem("xml")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    // Servers without a signing key refuse rather than silently omit it.
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+    let resp = test::call_service(&app, redeem("jwt")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn signed_tokens_verify_offline_until_revoked() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let keys = vec![TokenSigningKey::new(
+        "k1",
+        "0123456789abcdef0123456789abcdef",
+    )];
+    let state = with_cache(storage.clone()).with_token_signer(TokenSigner::new(
+        keys.clone(),
+        std::time::Duration::from_secs(600),
+    ));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let revocations = || async {
+        let req = test::TestRequest::get()
+            .uri("/api/v1/revocations")
+            .to_request();
+        let list: RevocationListResponse = test::call_and_read_body_json(&app, req).await;
+        list
+    };
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem?format=signed")
+        .set_json(&RedeemRequest {
+            pid: test_pid().into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let parsed: RedeemResponse = test::call_and_read_body_json(&app, req).await;
+    let signed = parsed.signed_token.expect("signed token requested");
+    let claims = verify_signed_token(&keys, &signed, chrono::Utc::now()).expect("verifies");
+    assert_eq!(claims.balance, parsed.balance);
+    assert!(revocations().await.revoked.is_empty());
+
+    let token = ServiceToken::parse(&parsed.service_token).unwrap();
+    storage
+        .revoke_token(RevokeTokenRequest {
+            token: token.clone(),
+            reason: None,
+            abuse_score: None,
+        })
+        .await
+        .unwrap();
+    let list = revocations().await;
+    assert_eq!(list.revoked, [claims.sub]);
+    assert_eq!(list.next_since, None);
+
+    // Without keys neither the format nor the list is served.
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .configure(public_routes),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/revocations")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn revocation_export_pages_by_cursor() {
+    let storage = storage().await;
+    TokenFixture::active().insert(&storage).await.unwrap();
+    let mut revoked = Vec::new();
+    for n in 1..=3u8 {
+        let record = TokenFixture::revoked()
+            .token(ServiceToken::from_bytes([n; 32]))
+            .pid(nth_pid(u64::from(n)))
+            .insert(&storage)
+            .await
+            .unwrap();
+        revoked.push(record.token);
+    }
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .configure(internal_routes),
+    )
+    .await;
+    let export = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/internal/v1/revocations{query}"))
+            .to_request()
+    };
+
+    let first: RevocationExportResponse =
+        test::call_and_read_body_json(&app, export("?limit=2")).await;
+    assert_eq!(
+        first.revoked,
+        [token_subject(&revoked[0]), token_subject(&revoked[1])]
+    );
+    assert!(first.more);
+    let cursor = first.cursor.expect("revocations were returned");
+    let rest: RevocationExportResponse =
+        test::call_and_read_body_json(&app, export(&format!("?since={cursor}"))).await;
+    assert_eq!(rest.revoked, [token_subject(&revoked[2])]);
+    assert!(!rest.more);
+
+    // Caught up: the cursor stays put until something else is revoked.
+    let last = rest.cursor.expect("revocations were returned");
+    let resp = test::call_service(&app, export(&format!("?since={last}&format=binary"))).await;
+    assert_eq!(
+        resp.headers().get(EXPORT_CURSOR_HEADER).unwrap(),
+        last.as_str()
+    );
+    assert_eq!(resp.headers().get(EXPORT_MORE_HEADER).unwrap(), "false");
+    assert!(to_bytes(resp.into_body()).await.unwrap().is_empty());
+
+    let resp = test::call_service(&app, export("?format=binary")).await;
+    assert_eq!(
+        resp.headers()
+            .get(actix_web::http::header::CONTENT_TYPE)
+            .unwrap(),
+        "application/octet-stream"
+    );
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let digests: Vec<[u8; 32]> = revoked.iter().map(token_digest).collect();
+    assert_eq!(body.as_ref(), digests.concat());
+
+    let resp = test::call_service(&app, export("?since=yesterday")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn idempotency_key_replays_the_first_response() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+    let redeem = |key: &str, passphrase: Option<&str>| {
+        test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .insert_header((IDEMPOTENCY_KEY_HEADER, key))
+            .set_json(&RedeemRequest {
+                pid: test_pid().into_inner(),
+                client_secret: None,
+                passphrase: passphrase.map(str::to_string),
+            })
+            .to_request()
+    };
+
+    let first = test::call_service(&app, redeem("retry-1", None)).await;
+    assert_eq!(first.status(), actix_web::http::StatusCode::OK);
+    assert!(first.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+    let first = to_bytes(first.into_body()).await.unwrap();
+    assert_eq!(
+        serde_json::from_slice::<RedeemResponse>(&first)
+            .unwrap()
+            .status,
+        "success"
+    );
+
+    // Without the key this would report `already_claimed`.
+    let retry = test::call_service(&app, redeem("retry-1", None)).await;
+    assert_eq!(retry.status(), actix_web::http::StatusCode::OK);
+    assert_eq!(
+        retry.headers().get(IDEMPOTENT_REPLAYED_HEADER).unwrap(),
+        "true"
+    );
+    assert_eq!(to_bytes(retry.into_body()).await.unwrap(), first);
+
+    let fresh = test::call_service(&app, redeem("retry-2", None)).await;
+    let fresh: RedeemResponse = test::read_body_json(fresh).await;
+    assert_eq!(fresh.status, "already_claimed");
+
+    let reused = test::call_service(&app, redeem("retry-1", Some("1234"))).await;
+    assert_eq!(reused.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let invalid = test::call_service(&app, redeem(&"k".repeat(256), None)).await;
+    assert_eq!(invalid.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn public_routes_are_rate_limited_per_identifier() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let limiter = InMemoryRateLimiter::new(RateLimit::new(2, 1));
+    let state = with_cache(storage).with_rate_limiter(Some(Arc::new(limiter)));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let redeem = |pid: String| {
+        test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .set_json(&RedeemRequest {
+                pid,
+                client_secret: None,
+                passphrase: None,
+            })
+            .to_request()
+    };
+
+    // The limiter buffers the body; the handler still sees it.
+    let first = test::call_service(&app, redeem(test_pid().into_inner())).await;
+    assert_eq!(first.status(), actix_web::http::StatusCode::OK);
+    // Hex case does not buy a fresh bucket.
+    let second = test::call_service(&app, redeem(test_pid().to_hex().to_uppercase())).await;
+    assert_ne!(
+        second.status(),
+        actix_web::http::StatusCode::TOO_MANY_REQUESTS
+    );
+    let limited = test::call_service(&app, redeem(test_pid().into_inner())).await;
+    assert_eq!(
+        limited.status(),
+        actix_web::http::StatusCode::TOO_MANY_REQUESTS
+    );
+    assert_eq!(
+        limited
+            .headers()
+            .get(actix_web::http::header::RETRY_AFTER)
+            .unwrap(),
+        "60"
+    );
+    let body: serde_json::Value = test::read_body_json(limited).await;
+    assert_eq!(body["code"], "rate_limited");
+
+    // Path identifiers share the bucket; other PIDs and unkeyed routes do not.
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/quote/{}", test_pid()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(
+        resp.status(),
+        actix_web::http::StatusCode::TOO_MANY_REQUESTS
+    );
+    let other = test::call_service(&app, redeem("fedcba9876543210".into())).await;
+    assert_eq!(other.status(), actix_web::http::StatusCode::NOT_FOUND);
+    for _ in 0..3 {
+        let req = test::TestRequest::get().uri("/api/v1/info").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    }
+}
+
+#[actix_web::test]
+async fn checkout_pid_requires_client_secret() {
+    let storage = storage().await;
+    let state = with_cache(storage.clone()).with_checkout(true);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/checkout", web::post().to(checkout_handler))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/checkout")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let checkout: CheckoutResponse = test::read_body_json(resp).await;
+    let pid = PaymentId::parse(&checkout.pid).unwrap();
+    PaymentFixture::confirmed()
+        .pid(pid.clone())
+        .insert(&storage)
+        .await
+        .unwrap();
+
+    let redeem = |client_secret: Option<String>| {
+        test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .set_json(&RedeemRequest {
+                pid: pid.to_hex(),
+                client_secret,
+                passphrase: None,
+            })
+            .to_request()
+    };
+    for secret in [None, Some("00".repeat(32))] {
+        let resp = test::call_service(&app, redeem(secret)).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+    let resp = test::call_service(&app, redeem(Some(checkout.client_secret))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn quotes_issue_pids_and_report_lifecycle() {
+    let storage = storage().await;
+    let state = with_cache(storage.clone())
+        .with_quote_ttl(Some(std::time::Duration::from_secs(600)))
+        .with_service_info(ServiceInfo {
+            min_payment_amount: Some(100),
+            ..ServiceInfo::default()
+        });
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let create = |amount: i64| {
+        test::TestRequest::post()
+            .uri("/api/v1/quote")
+            .set_json(&QuoteRequest {
+                amount,
+                address_id: None,
+            })
+            .to_request()
+    };
+    let status = |pid: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/quote/{pid}"))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, create(99)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, create(100)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let quote: QuoteResponse = test::read_body_json(resp).await;
+    assert_eq!(quote.status, "open");
+    assert_eq!(quote.amount, 100);
+    let ttl = (quote.expires_at - chrono::Utc::now()).num_seconds();
+    assert!((590..=600).contains(&ttl), "{ttl}");
+
+    let pid = PaymentId::parse(&quote.pid).unwrap();
+    storage.mark_quote_paid(&pid).await.unwrap();
+    let resp = test::call_service(&app, status(&quote.pid)).await;
+    let paid: QuoteResponse = test::read_body_json(resp).await;
+    assert_eq!(paid.status, "paid");
+
+    // Past its deadline but not yet swept.
+    let stale = PaymentId::parse("0d0d0d0d0d0d0d0d").unwrap();
+    storage
+        .insert_quote(NewPaymentQuote {
+            pid: stale.clone(),
+            expected_amount: 100,
+            created_at: chrono::Utc::now() - chrono::Duration::hours(2),
+            expires_at: chrono::Utc::now() - chrono::Duration::hours(1),
+            address_id: None,
+        })
+        .await
+        .unwrap();
+    let resp = test::call_service(&app, status(&stale.to_hex())).await;
+    let expired: QuoteResponse = test::read_body_json(resp).await;
+    assert_eq!(expired.status, "expired");
+
+    let resp = test::call_service(&app, status("0e0e0e0e0e0e0e0e")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+    let disabled = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .configure(public_routes),
+    )
+    .await;
+    let resp = test::call_service(&disabled, create(100)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn quotes_build_integrated_addresses_from_the_address_book() {
+    let primary = "4ADT1BtbxqEWeMKp9GgPr2NeyJXXtNxvoDawpyA4WpzFcGcoHUvXeijE66DNfohE9r1bQYaBiQjEtKE7CtkTdLwiDznFzra";
+    let storage = storage().await;
+    let state = with_cache(storage.clone())
+        .with_quote_ttl(Some(std::time::Duration::from_secs(600)))
+        .with_service_info(ServiceInfo {
+            addresses: AddressBook::new([
+                (PRIMARY_WALLET.into(), primary.into()),
+                ("campaign".into(), primary.into()),
+            ]),
+            ..ServiceInfo::default()
+        });
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let create = |address_id: Option<&str>| {
+        test::TestRequest::post()
+            .uri("/api/v1/quote")
+            .set_json(&QuoteRequest {
+                amount: 100,
+                address_id: address_id.map(str::to_string),
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, create(None)).await;
+    let quote: QuoteResponse = test::read_body_json(resp).await;
+    assert_eq!(quote.address_id.as_deref(), Some(PRIMARY_WALLET));
+
+    let resp = test::call_service(&app, create(Some("campaign"))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let quote: QuoteResponse = test::read_body_json(resp).await;
+    assert_eq!(quote.address_id.as_deref(), Some("campaign"));
+    let (standard, pid) = decode_integrated_address(quote.address.as_deref().unwrap()).unwrap();
+    assert_eq!(standard, primary);
+    assert_eq!(pid.to_hex(), quote.pid);
+    let stored = storage.find_quote(&pid).await.unwrap().unwrap();
+    assert_eq!(stored.address_id.as_deref(), Some("campaign"));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/quote/{}", quote.pid))
+        .to_request();
+    let status: QuoteResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(status.address, quote.address);
+
+    let resp = test::call_service(&app, create(Some("unknown"))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn redeem_issues_token_for_every_transfer_to_the_pid() {
+    let storage = storage().await;
+    let pid = test_pid();
+    PaymentFixture::confirmed()
+        .amount(600)
+        .insert(&storage)
+        .await
+        .unwrap();
+    storage
+        .insert_payment(NewPayment {
+            pid: pid.clone(),
+            txid: "ee".repeat(32),
+            amount: 400,
+            block_height: 201,
+            detected_at: chrono::Utc::now(),
+            address_id: None,
+        })
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .configure(public_routes),
+    )
+    .await;
+
+    let resp: RedeemResponse = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .set_json(&RedeemRequest {
+                pid: pid.to_hex(),
+                client_secret: None,
+                passphrase: None,
+            })
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status, "success");
+    assert_eq!(resp.balance, 1_000);
+}
+
+#[actix_web::test]
+async fn underpaid_quotes_are_claimable_once_topped_up() {
+    let storage = storage().await;
+    let pid = test_pid();
+    let now = chrono::Utc::now();
+    storage
+        .insert_quote(NewPaymentQuote {
+            pid: pid.clone(),
+            expected_amount: 1_000,
+            created_at: now,
+            expires_at: now + chrono::Duration::hours(1),
+            address_id: None,
+        })
+        .await
+        .unwrap();
+    PaymentFixture::confirmed()
+        .amount(800)
+        .insert(&storage)
+        .await
+        .unwrap();
+    storage.settle_quote(&pid).await.unwrap();
+    let state =
+        with_cache(storage.clone()).with_quote_ttl(Some(std::time::Duration::from_secs(600)));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let redeem = || {
+        test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .set_json(&RedeemRequest {
+                pid: pid.to_hex(),
+                client_secret: None,
+                passphrase: None,
+            })
+            .to_request()
+    };
+
+    let status = || {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/quote/{}", pid.to_hex()))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, redeem()).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+    let quote: QuoteResponse = test::call_and_read_body_json(&app, status()).await;
+    assert_eq!(quote.progress.received_amount, 800);
+    assert_eq!(quote.progress.percent, 80);
+    let shortfall = quote.reconciliation.expect("quoted pid is reconciled");
+    assert_eq!(shortfall.state, "underpaid");
+    assert_eq!(shortfall.difference, -200);
+
+    storage
+        .insert_payment(NewPayment {
+            pid: pid.clone(),
+            txid: "ff".repeat(32),
+            amount: 200,
+            block_height: 200,
+            detected_at: now,
+            address_id: None,
+        })
+        .await
+        .unwrap();
+    storage.settle_quote(&pid).await.unwrap();
+    let redeemed: RedeemResponse = test::call_and_read_body_json(&app, redeem()).await;
+    assert_eq!(redeemed.status, "success");
+    assert_eq!(redeemed.balance, 1_000);
+    let settled = redeemed.reconciliation.unwrap();
+    assert_eq!(settled.state, "exact");
+    assert_eq!(settled.difference, 0);
+
+    let quote: QuoteResponse = test::call_and_read_body_json(&app, status()).await;
+    assert_eq!(quote.progress.percent, 100);
+    assert_eq!(quote.reconciliation, Some(settled));
+}
+
+#[actix_web::test]
+async fn overpaid_quotes_credit_or_hold_the_excess() {
+    let storage = storage().await;
+    let now = chrono::Utc::now();
+    for (n, amount) in [(1, 1_300), (2, 1_200)] {
+        let pid = nth_pid(n);
+        storage
+            .insert_quote(NewPaymentQuote {
+                pid: pid.clone(),
+                expected_amount: 1_000,
+                created_at: now,
+                expires_at: now + chrono::Duration::hours(1),
+                address_id: None,
+            })
+            .await
+            .unwrap();
+        PaymentFixture::confirmed()
+            .pid(pid.clone())
+            .amount(amount)
+            .insert(&storage)
+            .await
+            .unwrap();
+        storage.settle_quote(&pid).await.unwrap();
+    }
+    let app = |policy| {
+        let state = with_cache(storage.clone())
+            .with_quote_ttl(Some(std::time::Duration::from_secs(600)))
+            .with_overpayment_policy(policy, std::time::Duration::from_secs(3_600));
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .configure(public_routes)
+                .configure(internal_routes),
+        )
+    };
+    let redeem = |pid: PaymentId| {
+        test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .set_json(&RedeemRequest {
+                pid: pid.to_hex(),
+                client_secret: None,
+                passphrase: None,
+            })
+            .to_request()
+    };
+    let credits = |status: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/internal/v1/credits?status={status}"))
+            .to_request()
+    };
+
+    let credit_app = app(OverpaymentPolicy::Credit).await;
+    let redeemed: RedeemResponse =
+        test::call_and_read_body_json(&credit_app, redeem(nth_pid(1))).await;
+    assert_eq!(redeemed.balance, 1_300);
+    assert_eq!(redeemed.reconciliation.unwrap().difference, 300);
+    let credited: Page<CreditSummary> =
+        test::call_and_read_body_json(&credit_app, credits("credited")).await;
+    assert_eq!(credited.items.len(), 1);
+    assert_eq!(credited.items[0].amount, 300);
+    assert_eq!(credited.items[0].hold_until, None);
+
+    let refund_app = app(OverpaymentPolicy::Refund).await;
+    let redeemed: RedeemResponse =
+        test::call_and_read_body_json(&refund_app, redeem(nth_pid(2))).await;
+    assert_eq!(redeemed.balance, 1_000);
+    let held: Page<CreditSummary> =
+        test::call_and_read_body_json(&refund_app, credits("held")).await;
+    assert_eq!(held.items.len(), 1);
+    let hold = &held.items[0];
+    assert_eq!(
+        (hold.amount, hold.pid.as_str()),
+        (200, nth_pid(2).to_hex().as_str())
+    );
+    assert!(hold.hold_until.is_some());
+
+    let resolve = |action: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/internal/v1/credits/{}/{action}", hold.id))
+            .set_json(ResolveCreditRequest {
+                reason: "payer asked to keep it".into(),
+                operator: Some("support".into()),
+            })
+            .to_request()
+    };
+    let applied: CreditSummary =
+        test::call_and_read_body_json(&refund_app, resolve("credit")).await;
+    assert_eq!(applied.status, "credited");
+    let balance = storage
+        .find_token_by_pid(&nth_pid(2))
+        .await
+        .unwrap()
+        .unwrap()
+        .amount;
+    assert_eq!(balance, 1_200);
+    let resp = test::call_service(&refund_app, resolve("refund")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn refunds_are_queued_within_the_payment() {
+    use actix_web::http::StatusCode;
+
+    let primary = "4ADT1BtbxqEWeMKp9GgPr2NeyJXXtNxvoDawpyA4WpzFcGcoHUvXeijE66DNfohE9r1bQYaBiQjEtKE7CtkTdLwiDznFzra";
+    let storage = storage().await;
+    let pid = nth_pid(3);
+    PaymentFixture::confirmed()
+        .pid(pid.clone())
+        .amount(1_000)
+        .insert(&storage)
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .configure(internal_routes),
+    )
+    .await;
+    let request = |pid: &PaymentId, address: &str, amount: i64| {
+        test::TestRequest::post()
+            .uri(&format!("/internal/v1/payment/{}/refund", pid.to_hex()))
+            .set_json(RefundRequest {
+                address: address.into(),
+                amount,
+                reason: "duplicate purchase".into(),
+                operator: Some("support".into()),
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, request(&pid, primary, 600)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let first: RefundSummary = test::read_body_json(resp).await;
+    assert_eq!(first.status, "pending");
+    assert_eq!(first.address, primary);
+    let resp = test::call_service(&app, request(&pid, primary, 500)).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let resp = test::call_service(&app, request(&pid, "not-an-address", 100)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, request(&nth_pid(4), primary, 100)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let pending: Page<RefundSummary> = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/internal/v1/refunds?status=pending")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(pending.items.len(), 1);
+    assert_eq!(pending.items[0].id, first.id);
+
+    let cancelled: RefundSummary = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::post()
+            .uri(&format!("/internal/v1/refunds/{}/cancel", first.id))
+            .set_json(CancelRefundRequest {
+                reason: "payer sent a new address".into(),
+                operator: None,
+            })
+            .to_request(),
+    )
+    .await;
+    assert_eq!(cancelled.status, "failed");
+    let resp = test::call_service(&app, request(&pid, primary, 1_000)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let second: RefundSummary = test::read_body_json(resp).await;
+
+    let mark_sent = |id: i64| {
+        test::TestRequest::post()
+            .uri(&format!("/internal/v1/refunds/{id}/sent"))
+            .set_json(RefundSentRequest {
+                txid: "cd".repeat(32),
+                reason: "sent from the cold wallet".into(),
+                operator: None,
+            })
+            .to_request()
+    };
+    let sent: RefundSummary = test::call_and_read_body_json(&app, mark_sent(second.id)).await;
+    assert_eq!(sent.status, "sent");
+    assert_eq!(sent.txid, Some("cd".repeat(32)));
+    let resp = test::call_service(&app, mark_sent(second.id)).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn skipped_transfers_are_reprocessed_or_dismissed() {
+    use actix_web::http::StatusCode;
+
+    let storage = storage().await;
+    let skipped = |txid: &str, payment_id: &str, amount: &str, reason: &str| SkippedTransfer {
+        txid: txid.repeat(32),
+        wallet: PRIMARY_WALLET.into(),
+        reason: reason.into(),
+        payment_id: Some(payment_id.into()),
+        amount: amount.into(),
+        block_height: Some(120),
+        timestamp: 1_700_000_000,
+        skipped_at: chrono::Utc::now(),
+    };
+    let pid = nth_pid(5);
+    for transfer in [
+        skipped("aa", "not-a-pid", "700", "invalid_pid"),
+        skipped(
+            "bb",
+            &pid.to_hex(),
+            "18446744073709551615",
+            "amount_overflow",
+        ),
+        // Journaled before a fix; the PID parses now.
+        skipped("cc", &pid.to_hex(), "700", "invalid_pid"),
+    ] {
+        storage.record_skipped(transfer).await.unwrap();
+    }
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .configure(internal_routes),
+    )
+    .await;
+    let act = |method: &str, txid: &str, suffix: &str| {
+        test::TestRequest::default()
+            .method(method.parse().unwrap())
+            .uri(&format!(
+                "/internal/v1/skipped-transfers/{}{suffix}",
+                txid.repeat(32)
+            ))
+            .set_json(SkippedActionRequest {
+                reason: "pid parser fixed".into(),
+                operator: Some("ops".into()),
+            })
+            .to_request()
+    };
+
+    let page: Page<SkippedTransferSummary> = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/internal/v1/skipped-transfers?limit=2")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.items[1].amount, "18446744073709551615");
+    assert_eq!(page.next_after, Some("bb".repeat(32)));
+
+    for txid in ["aa", "bb"] {
+        let resp = test::call_service(&app, act("POST", txid, "/reprocess")).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT, "{txid} stays skipped");
+    }
+    let resp = test::call_service(&app, act("POST", "cc", "/reprocess")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let payment = storage.find_payment_primary(&pid).await.unwrap().unwrap();
+    assert_eq!(payment.txid, "cc".repeat(32));
+    assert_eq!(payment.amount, 700);
+    assert!(storage
+        .find_skipped(&"cc".repeat(32))
+        .await
+        .unwrap()
+        .is_none());
+    let resp = test::call_service(&app, act("POST", "cc", "/reprocess")).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = test::call_service(&app, act("DELETE", "aa", "")).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = test::call_service(&app, act("DELETE", "aa", "")).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let remaining = storage.list_skipped(None, 10).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].txid, "bb".repeat(32));
+}
+
+#[actix_web::test]
+async fn webhook_events_are_redelivered_on_request() {
+    use actix_web::http::StatusCode;
+    use anon_ticket_domain::model::{DeliveryStatus, WebhookDelivery, WebhookEvent};
+    use anon_ticket_domain::storage::WebhookStore;
+
+    let storage = storage().await;
+    let event = storage
+        .record_webhook_event(WebhookEvent {
+            id: "01HZX3Q7E3M6X1V4Y8B2C5D9FG".into(),
+            txid: "tx-hook".into(),
+            pid: test_pid(),
+            payload: "{}".into(),
+            created_at: chrono::Utc::now(),
+        })
+        .await
+        .unwrap();
+    storage
+        .record_delivery(WebhookDelivery {
+            event_id: event.id.clone(),
+            url: "https://merchant.example/hooks".into(),
+            status: DeliveryStatus::Delivered,
+            attempts: 1,
+            last_error: None,
+            last_attempt_at: Some(event.created_at),
+            delivered_at: Some(event.created_at),
+        })
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .configure(internal_routes),
+    )
+    .await;
+    let redeliver = |id: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/internal/v1/webhooks/events/{id}/redeliver"))
+            .set_json(RedeliverRequest {
+                reason: "merchant lost the callback".into(),
+                operator: None,
+            })
+            .to_request()
+    };
+
+    let shown: WebhookEventResponse = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/internal/v1/webhooks/events/{}", event.id))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(shown.txid, "tx-hook");
+    assert_eq!(shown.deliveries[0].status, "delivered");
+
+    let resp = test::call_service(&app, redeliver(&event.id)).await;
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let requested: WebhookEventResponse =
+        serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(requested.deliveries[0].status, "requested");
+    // Already queued: nothing left to repeat until the monitor sends it.
+    let resp = test::call_service(&app, redeliver(&event.id)).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let resp = test::call_service(&app, redeliver("01HZX3Q7E3M6X1V4Y8B2C5D9FH")).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn payment_events_stream_until_claimable() {
+    let storage = storage().await;
+    let events = PaymentEvents::new(16);
+    let state = with_cache(storage.clone()).with_payment_events(Some(events.clone()));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let subscribe = |pid: &PaymentId| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/payment/{}/events", pid.to_hex()))
+            .to_request()
+    };
+    let event = |pid: &PaymentId, kind| PaymentEvent {
+        pid: pid.clone(),
+        kind,
+        amount: 500,
+        block_height: 101,
+    };
+
+    let pid = anon_ticket_testkit::nth_pid(1);
+    let resp = test::call_service(&app, subscribe(&pid)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    events.publish(event(&test_pid(), PaymentEventKind::Confirmed));
+    events.publish(event(&pid, PaymentEventKind::Detected { confirmations: 1 }));
+    PaymentFixture::confirmed()
+        .pid(pid.clone())
+        .amount(500)
+        .block_height(101)
+        .insert(&storage)
+        .await
+        .unwrap();
+    events.publish(event(&pid, PaymentEventKind::Confirmed));
+    let data = |schema: &str| {
+        format!(
+            r#"{{"schema":"payment.{schema}.v1","pid":"{}","amount":500,"block_height":101"#,
+            pid.to_hex()
+        )
+    };
+    let body = test::read_body(resp).await;
+    assert_eq!(
+        body,
+        format!(
+            "event: detected\ndata: {},\"confirmations\":1}}\n\n\
+             event: confirmed\ndata: {}}}\n\n\
+             event: claimable\ndata: {}}}\n\n",
+            data("detected"),
+            data("confirmed"),
+            data("claimable"),
+        )
+    );
+    for line in std::str::from_utf8(&body).unwrap().lines() {
+        if let Some(payload) = line.strip_prefix("data: ") {
+            anon_ticket_testkit::assert_event_matches_schema(payload);
+        }
+    }
+
+    // Late subscribers learn the current state straight away.
+    let resp = test::call_service(&app, subscribe(&pid)).await;
+    assert_eq!(
+        test::read_body(resp).await,
+        format!("event: claimable\ndata: {}}}\n\n", data("claimable"))
+    );
+    let claimed = PaymentFixture::claimed().insert(&storage).await.unwrap();
+    let resp = test::call_service(&app, subscribe(&claimed.pid)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let disabled = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .configure(public_routes),
+    )
+    .await;
+    let resp = test::call_service(&disabled, subscribe(&pid)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn payment_status_reports_progress_without_claiming() {
+    let storage = storage().await;
+    let now = chrono::Utc::now();
+    let state = with_cache(storage.clone())
+        .with_quote_ttl(Some(std::time::Duration::from_secs(600)))
+        .with_payment_events(Some(PaymentEvents::new(16)));
+    let pending = state.pending_payments().clone();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let status = |pid: &PaymentId| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/payment/{}", pid.to_hex()))
+            .to_request()
+    };
+
+    let pid = nth_pid(1);
+    let resp = test::call_service(&app, status(&pid)).await;
+    assert_eq!(
+        resp.headers()
+            .get(actix_web::http::header::CACHE_CONTROL)
+            .unwrap(),
+        "no-store"
+    );
+    let body: PaymentStatusResponse = test::read_body_json(resp).await;
+    assert_eq!(body.status, "unseen");
+    assert!(!body.claimable);
+    assert_eq!(body.amount, None);
+
+    let detected = PaymentEvent {
+        pid: pid.clone(),
+        kind: PaymentEventKind::Detected { confirmations: 2 },
+        amount: 500,
+        block_height: 101,
+    };
+    pending.record(&detected, now);
+    let body: PaymentStatusResponse = test::call_and_read_body_json(&app, status(&pid)).await;
+    assert_eq!(body.status, "detected");
+    assert_eq!(body.confirmations, Some(2));
+    assert_eq!(body.amount, Some(500));
+    assert!(!body.claimable);
+
+    PaymentFixture::confirmed()
+        .pid(pid.clone())
+        .amount(500)
+        .block_height(101)
+        .insert(&storage)
+        .await
+        .unwrap();
+    pending.record(
+        &PaymentEvent {
+            kind: PaymentEventKind::Confirmed,
+            ..detected
+        },
+        now,
+    );
+    let body: PaymentStatusResponse = test::call_and_read_body_json(&app, status(&pid)).await;
+    assert_eq!(body.status, "claimable");
+    assert!(body.claimable);
+    assert_eq!(body.confirmations, None);
+    // Looking does not claim.
+    let record = storage.find_payment(&pid).await.unwrap().unwrap();
+    assert_eq!(record.status, StoredPaymentState::Confirmed);
+
+    let quoted = nth_pid(2);
+    storage
+        .insert_quote(NewPaymentQuote {
+            pid: quoted.clone(),
+            expected_amount: 1_000,
+            created_at: now,
+            expires_at: now + chrono::Duration::hours(1),
+            address_id: None,
+        })
+        .await
+        .unwrap();
+    PaymentFixture::confirmed()
+        .pid(quoted.clone())
+        .amount(800)
+        .insert(&storage)
+        .await
+        .unwrap();
+    storage.settle_quote(&quoted).await.unwrap();
+    let body: PaymentStatusResponse = test::call_and_read_body_json(&app, status(&quoted)).await;
+    assert_eq!(body.status, "awaiting_top_up");
+    assert!(!body.claimable);
+    assert_eq!(body.progress.unwrap().percent, 80);
+
+    let claimed = PaymentFixture::claimed().insert(&storage).await.unwrap();
+    let body: PaymentStatusResponse =
+        test::call_and_read_body_json(&app, status(&claimed.pid)).await;
+    assert_eq!(body.status, "claimed");
+    assert!(!body.claimable);
+
+    // Transfers tracked by `MONITOR_TRACK_PENDING` come from storage.
+    let in_pool = nth_pid(3);
+    let transfer = |txid: &str, block_height, confirmations| PendingTransfer {
+        txid: txid.repeat(32),
+        pid: in_pool.clone(),
+        amount: 300,
+        block_height,
+        confirmations,
+        first_seen_at: now,
+        last_seen_at: now,
+    };
+    storage
+        .record_pending(vec![transfer("aa", None, 0)])
+        .await
+        .unwrap();
+    let body: PaymentStatusResponse = test::call_and_read_body_json(&app, status(&in_pool)).await;
+    assert_eq!(body.status, "unconfirmed");
+    assert_eq!(body.amount, Some(300));
+    assert_eq!(body.confirmations, Some(0));
+    assert_eq!(body.block_height, None);
+
+    storage
+        .record_pending(vec![transfer("bb", Some(120), 3)])
+        .await
+        .unwrap();
+    let body: PaymentStatusResponse = test::call_and_read_body_json(&app, status(&in_pool)).await;
+    assert_eq!(body.status, "detected");
+    assert_eq!(body.amount, Some(600));
+    assert_eq!(body.confirmations, Some(3));
+    assert_eq!(body.block_height, Some(120));
+}
+
+#[actix_web::test]
+async fn checkout_preset_sets_minimum_amount() {
+    let storage = storage().await;
+    let state = with_cache(storage.clone())
+        .with_checkout(true)
+        .with_checkout_presets(vec![CheckoutPreset::new("pro", 500)
+            .with_tier("pro")
+            .with_expiry_secs(3600)]);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/checkout", web::post().to(checkout_handler))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+    let checkout = |preset: &str| {
+        test::TestRequest::post()
+            .uri("/api/v1/checkout")
+            .set_json(&CheckoutRequest {
+                preset: Some(preset.into()),
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, checkout("enterprise")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let resp = test::call_service(&app, checkout("pro")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let checkout: CheckoutResponse = test::read_body_json(resp).await;
+    let preset = checkout.preset.expect("preset echoed");
+    assert_eq!(preset.amount, 500);
+    assert_eq!(preset.tier.as_deref(), Some("pro"));
+    assert!(preset.expires_at.is_some());
+
+    let pid = PaymentId::parse(&checkout.pid).unwrap();
+    PaymentFixture::confirmed()
+        .pid(pid.clone())
+        .amount(499)
+        .insert(&storage)
+        .await
+        .unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: pid.to_hex(),
+            client_secret: Some(checkout.client_secret),
+            passphrase: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+    assert!(storage.find_token_by_pid(&pid).await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn donation_preset_tier_scales_with_amount() {
+    let storage = storage().await;
+    let state = with_cache(storage.clone())
+        .with_checkout(true)
+        .with_checkout_presets(vec![CheckoutPreset::donation("tip", 0)
+            .with_tier_threshold("bronze", 1000)
+            .with_tier_threshold("silver", 5000)]);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/checkout", web::post().to(checkout_handler))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+
+    for (amount, tier) in [(7, None), (1000, Some("bronze")), (70_000, Some("silver"))] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/checkout")
+            .set_json(&CheckoutRequest {
+                preset: Some("tip".into()),
+            })
+            .to_request();
+        let checkout: CheckoutResponse = test::call_and_read_body_json(&app, req).await;
+        assert!(checkout
+            .preset
+            .as_ref()
+            .is_some_and(|preset| preset.donation));
+        let pid = PaymentId::parse(&checkout.pid).unwrap();
+        PaymentFixture::confirmed()
+            .pid(pid.clone())
+            .amount(amount)
+            .insert(&storage)
+            .await
+            .unwrap();
+        let req = test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .set_json(&RedeemRequest {
+                pid: pid.to_hex(),
+                client_secret: Some(checkout.client_secret),
+                passphrase: None,
+            })
+            .to_request();
+        let redeemed: RedeemResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(redeemed.balance, amount);
+        assert_eq!(redeemed.tier.as_deref(), tier, "{amount}");
+    }
+}
+
+#[actix_web::test]
+async fn info_publishes_payment_policy() {
+    let primary = "4ADT1BtbxqEWeMKp9GgPr2NeyJXXtNxvoDawpyA4WpzFcGcoHUvXeijE66DNfohE9r1bQYaBiQjEtKE7CtkTdLwiDznFzra";
+    let state = with_cache(storage().await)
+        .with_checkout_presets(vec![CheckoutPreset::new("pro", 500).with_tier("pro")])
+        .with_service_info(ServiceInfo {
+            addresses: AddressBook::new([(PRIMARY_WALLET.into(), primary.into())]),
+            min_payment_amount: Some(1_000),
+            min_confirmations: Some(10),
+        });
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .configure(public_routes),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/api/v1/info").to_request();
+    let info: InfoResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(info.api_version, "v1");
+    assert_eq!(info.build.version, env!("CARGO_PKG_VERSION"));
+    assert!(!info.build.git_sha.is_empty());
+    assert_eq!(
+        info.build.crates["anon_ticket_storage"],
+        anon_ticket_storage::VERSION
+    );
+    assert_eq!(info.networks, vec!["mainnet".to_string()]);
+    let fingerprint = info.primary_address_fingerprint.expect("fingerprint");
+    assert_eq!(fingerprint.len(), 64);
+    assert!(!fingerprint.contains(primary));
+    assert_eq!(info.min_payment_amount, Some(1_000));
+    assert_eq!(info.min_confirmations, Some(10));
+    assert!(info.subscription.is_none());
+    // Presets are only usable, and so only listed, with checkout enabled.
+    assert!(info.presets.is_empty());
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.with_checkout(true)))
+            .configure(public_routes),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/api/v1/info").to_request();
+    let info: InfoResponse = test::call_and_read_body_json(&app, req).await;
+    assert!(info.checkout_enabled);
+    assert_eq!(info.presets.len(), 1);
+    assert_eq!(info.presets[0].tier.as_deref(), Some("pro"));
+}
+
+/// Fee-only source that counts daemon calls; `None` simulates an outage.
+struct CountingFees {
+    calls: AtomicUsize,
+    estimate: Option<FeeEstimate>,
+}
+
+#[async_trait::async_trait]
+impl TransferSource for CountingFees {
+    async fn fetch_transfers(&self, _: u64, _: u64) -> Result<TransfersResponse, MonitorError> {
+        Ok(TransfersResponse::default())
+    }
+
+    async fn wallet_height(&self) -> Result<u64, MonitorError> {
+        Ok(0)
+    }
+
+    async fn fee_estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.estimate
+            .clone()
+            .ok_or_else(|| MonitorError::Rpc("daemon offline".into()))
+    }
+}
+
+#[actix_web::test]
+async fn probes_report_readiness_on_both_listeners() {
+    use actix_web::http::StatusCode;
+
+    let state = with_cache(storage().await);
+    let public = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .configure(public_routes),
+    )
+    .await;
+    let internal = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .configure(internal_routes),
+    )
+    .await;
+    let get = |uri| test::TestRequest::get().uri(uri).to_request();
+
+    let resp = test::call_service(&public, get("/livez")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let ready: Readiness = test::call_and_read_body_json(&internal, get("/readyz")).await;
+    assert_eq!(
+        ready,
+        Readiness {
+            ready: true,
+            draining: false,
+            storage: true,
+        }
+    );
+
+    // Draining fails readiness everywhere; liveness stays up.
+    state.health().start_draining();
+    let resp = test::call_service(&public, get("/readyz")).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let ready: Readiness =
+        serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert!(!ready.ready && ready.draining && ready.storage);
+    let resp = test::call_service(&internal, get("/readyz")).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let resp = test::call_service(&internal, get("/livez")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn healthz_reports_each_dependency() {
+    use actix_web::http::StatusCode;
+    use anon_ticket_domain::error::ErrorCode;
+
+    let app = |state: AppState| {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .configure(internal_routes),
+        )
+    };
+    let get = || test::TestRequest::get().uri("/healthz").to_request();
+
+    // Without an embedded monitor only the database is checked.
+    let standalone = app(with_cache(storage().await)).await;
+    let report: HealthReport = test::call_and_read_body_json(&standalone, get()).await;
+    assert_eq!(report.status, CheckStatus::Ok);
+    assert_eq!(report.database.status, CheckStatus::Ok);
+    assert_eq!(report.monitor.status, CheckStatus::Disabled);
+    assert_eq!(report.wallet_rpc.status, CheckStatus::Disabled);
+
+    // An unreachable wallet degrades the report without failing the probe,
+    // and a poll still in progress keeps the monitor alive.
+    let heartbeat = MonitorHeartbeat::new();
+    let long_ago = chrono::Utc::now() - chrono::Duration::hours(1);
+    heartbeat.poll_finished("primary", long_ago, Some(ErrorCode::RpcUnavailable));
+    heartbeat.poll_started("primary", long_ago);
+    let state = with_cache(storage().await)
+        .with_monitor_heartbeat(Some(heartbeat), std::time::Duration::from_secs(60));
+    let monitored = app(state).await;
+    let resp = test::call_service(&monitored, get()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let report: HealthReport =
+        serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(report.status, CheckStatus::Degraded);
+    assert_eq!(report.monitor.status, CheckStatus::Ok);
+    assert_eq!(report.monitor.last_tick_at, Some(long_ago));
+    assert_eq!(report.wallet_rpc.status, CheckStatus::Down);
+    let wallet = &report.wallet_rpc.wallets[0];
+    assert_eq!(wallet.name, "primary");
+    assert_eq!(wallet.polling_since, Some(long_ago));
+    assert_eq!(wallet.error.as_deref(), Some("rpc_unavailable"));
+}
+
+#[actix_web::test]
+async fn cancelling_shutdown_drains_and_stops_the_monitor() {
+    use crate::shutdown::{serve, MonitorTask, Services};
+    use tokio_util::sync::CancellationToken;
+
+    let state = with_cache(storage().await);
+    let server = || {
+        actix_web::HttpServer::new(App::new)
+            .workers(1)
+            .disable_signals()
+            .bind(("127.0.0.1", 0))
+            .unwrap()
+            .run()
+    };
+    // Stands in for the embedded monitor: returns once asked to stop.
+    let monitor_stop = CancellationToken::new();
+    let stopped = monitor_stop.clone();
+    let monitor = MonitorTask {
+        handle: tokio::spawn(async move {
+            stopped.cancelled().await;
+            Ok(())
+        }),
+        stop: monitor_stop.clone(),
+    };
+    let shutdown = CancellationToken::new();
+    shutdown.cancel();
+
+    let services = Services {
+        public: server(),
+        internal: server(),
+        grpc: None,
+        monitor: Some(monitor),
+        background: vec![tokio::spawn(std::future::pending())],
+        state: state.clone(),
+        shutdown,
+    };
+    tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        serve(services, std::time::Duration::from_secs(2)),
+    )
+    .await
+    .expect("shutdown is bounded")
+    .unwrap();
+    assert!(monitor_stop.is_cancelled());
+    assert!(state.health().is_draining());
+}
+
+#[actix_web::test]
+async fn fee_estimate_is_cached_and_optional() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage().await)))
+            .configure(public_routes),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/fee-estimate")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+    let source = Arc::new(CountingFees {
+        calls: AtomicUsize::new(0),
+        estimate: Some(FeeEstimate {
+            fee_per_byte: 20_000,
+            quantization_mask: 10_000,
+            priority_fees: vec![20_000, 80_000],
+        }),
+    });
+    let state = with_cache(storage().await)
+        .with_fee_estimator(Some(FeeEstimator::new(source.clone())))
+        .with_service_info(ServiceInfo {
+            min_payment_amount: Some(1_000),
+            ..ServiceInfo::default()
+        });
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    for _ in 0..3 {
+        let req = test::TestRequest::get()
+            .uri("/api/v1/fee-estimate")
+            .to_request();
+        let fee: FeeEstimateResponse = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(fee.estimated_fee, 20_000 * TYPICAL_TX_WEIGHT);
+        assert_eq!(fee.priority_fees, vec![20_000, 80_000]);
+        assert_eq!(fee.min_payment_amount, Some(1_000));
+    }
+    assert_eq!(source.calls.load(Ordering::SeqCst), 1);
+
+    let offline = Arc::new(CountingFees {
+        calls: AtomicUsize::new(0),
+        estimate: None,
+    });
+    let state = with_cache(storage().await).with_fee_estimator(Some(FeeEstimator::new(offline)));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/fee-estimate")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(
+        resp.status(),
+        actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+    );
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "rpc_unavailable");
+}
+
+#[actix_web::test]
+async fn checkout_is_hidden_when_disabled() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage().await)))
+            .route("/api/v1/checkout", web::post().to(checkout_handler)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/checkout")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn duplicate_claims_return_existing_token() {
+    let storage = storage().await;
+    let pid = test_pid();
+    PaymentFixture::claimed().insert(&storage).await.unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: pid.clone().into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    let parsed: RedeemResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(parsed.status, "already_claimed");
+    let expected = PaymentFixture::confirmed().expected_token();
+    assert_eq!(parsed.service_token, expected.into_inner());
+}
+
+#[actix_web::test]
+async fn bloom_negative_short_circuits_even_if_payment_exists() {
+    let storage = storage().await;
+    let pid = test_pid();
+    PaymentFixture::confirmed()
+        .txid("tx-bloom-negative")
+        .amount(9)
+        .block_height(77)
+        .insert(&storage)
+        .await
+        .unwrap();
+
+    let bloom = Arc::new(PidBloom::new(10_000, 0.01).unwrap());
+    // intentionally do not insert pid into bloom
+    let state = build_state(
+        storage,
+        Arc::new(InMemoryPidCache::default()),
+        Some(bloom.clone()),
+    );
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: pid.clone().into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    // bloom remains negative
+    assert!(!bloom.might_contain(&pid));
+}
+
+#[actix_web::test]
+async fn bloom_positive_allows_redemption() {
+    let storage = storage().await;
+    let pid = test_pid();
+    PaymentFixture::confirmed()
+        .txid("tx-bloom-positive")
+        .amount(9)
+        .block_height(77)
+        .insert(&storage)
+        .await
+        .unwrap();
+
+    let bloom = Arc::new(PidBloom::new(10_000, 0.01).unwrap());
+    bloom.insert(&pid);
+    let state = build_state(storage, Arc::new(InMemoryPidCache::default()), Some(bloom));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: pid.into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn shared_cache_overrides_stale_bloom_negative() {
+    let storage = storage().await;
+    let pid = test_pid();
+    PaymentFixture::confirmed()
+        .txid("tx-shared-cache")
+        .amount(9)
+        .block_height(77)
+        .insert(&storage)
+        .await
+        .unwrap();
+
+    // Another replica's monitor ingested the PID; this replica's Bloom has
+    // not seen it.
+    let shared = Arc::new(InMemoryPidCache::default());
+    shared.mark_present(&pid);
+    let bloom = Arc::new(PidBloom::new(10_000, 0.01).unwrap());
+    let state = build_state(
+        storage,
+        Arc::new(InMemoryPidCache::default()),
+        Some(bloom.clone()),
+    )
+    .with_shared_cache(Some(shared as Arc<dyn PidCache>));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: pid.clone().into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    assert!(bloom.might_contain(&pid));
+}
+
+#[actix_web::test]
+async fn missing_pid_does_not_pollute_bloom() {
+    let storage = storage().await;
+    let pid = test_pid();
+    let bloom = Arc::new(PidBloom::new(10_000, 0.01).unwrap());
+    let state = build_state(
+        storage,
+        Arc::new(InMemoryPidCache::default()),
+        Some(bloom.clone()),
+    );
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: pid.clone().into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    assert!(!bloom.might_contain(&pid));
+}
+
+#[actix_web::test]
+async fn bloom_snapshot_restores_and_catches_up() {
+    let storage = storage().await;
+    let path = std::env::temp_dir().join(format!("anon-ticket-bloom-{}.bin", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let pay = |n: u64, height: i64| {
+        PaymentFixture::confirmed()
+            .pid(nth_pid(n))
+            .txid(format!("tx-snapshot-{n}"))
+            .block_height(height)
+            .insert(&storage)
+    };
+    pay(1, 10).await.unwrap();
+    pay(2, 20).await.unwrap();
+    let prewarm = || async {
+        let cache = Arc::new(InMemoryPidCache::default());
+        let bloom = Arc::new(PidBloom::new(10_000, 0.01).unwrap());
+        prewarm_hints(&storage, cache.clone(), Some(bloom.clone()), Some(&path))
+            .await
+            .unwrap();
+        (cache, bloom)
+    };
+
+    // No snapshot yet: everything is loaded and a snapshot is written.
+    let (cache, _) = prewarm().await;
+    assert!(cache.might_contain(&nth_pid(1)));
+    assert!(path.exists());
+
+    // Restored: only payments from the watermark height on are reloaded.
+    pay(3, 30).await.unwrap();
+    let (cache, bloom) = prewarm().await;
+    assert!((1..=3).all(|n| bloom.might_contain(&nth_pid(n))));
+    assert!(!cache.might_contain(&nth_pid(1)));
+    assert!(cache.might_contain(&nth_pid(2)));
+    assert!(cache.might_contain(&nth_pid(3)));
+
+    // A payment below the watermark invalidates the snapshot.
+    pay(4, 5).await.unwrap();
+    let (cache, bloom) = prewarm().await;
+    assert!((1..=4).all(|n| bloom.might_contain(&nth_pid(n))));
+    assert!(cache.might_contain(&nth_pid(1)));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[actix_web::test]
+async fn change_notifications_update_cache_and_bloom() {
+    let storage = storage().await;
+    PaymentFixture::confirmed()
+        .pid(nth_pid(1))
+        .txid("tx-change-1")
+        .insert(&storage)
+        .await
+        .unwrap();
+    let bloom = Arc::new(PidBloom::new(10_000, 0.01).unwrap());
+    let state = build_state(
+        storage,
+        Arc::new(InMemoryPidCache::default()),
+        Some(bloom.clone()),
+    );
+
+    // A payment announced by another instance.
+    apply_change(&state, Change::PaymentInserted(nth_pid(2))).await;
+    assert!(bloom.might_contain(&nth_pid(2)));
+    assert!(state.cache().might_contain(&nth_pid(2)));
+
+    // After a gap everything persisted is hashed in again.
+    assert!(!bloom.might_contain(&nth_pid(1)));
+    apply_change(&state, Change::Gap).await;
+    assert!(bloom.might_contain(&nth_pid(1)));
+}
+
+#[actix_web::test]
+async fn passphrase_wrapped_token_requires_passphrase() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .route("/api/v1/redeem", web::post().to(redeem_handler))
+            .route("/api/v1/token/{token}", web::get().to(token_status_handler)),
+    )
+    .await;
+    let redeem = |passphrase: Option<&str>| {
+        test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .set_json(&RedeemRequest {
+                pid: test_pid().into_inner(),
+                client_secret: None,
+                passphrase: passphrase.map(str::to_owned),
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, redeem(Some("4821"))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let issued: RedeemResponse = test::read_body_json(resp).await;
+    let status = |passphrase: Option<&str>| {
+        let mut req =
+            test::TestRequest::get().uri(&format!("/api/v1/token/{}", issued.service_token));
+        if let Some(passphrase) = passphrase {
+            req = req.insert_header((PASSPHRASE_HEADER, passphrase));
+        }
+        req.to_request()
+    };
+    for passphrase in [None, Some("0000")] {
+        let resp = test::call_service(&app, status(passphrase)).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+    let resp = test::call_service(&app, status(Some("4821"))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+
+    let resp = test::call_service(&app, redeem(None)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    let resp = test::call_service(&app, redeem(Some("4821"))).await;
+    let retried: RedeemResponse = test::read_body_json(resp).await;
+    assert_eq!(retried.status, "already_claimed");
+    assert_eq!(retried.service_token, issued.service_token);
+
+    let resp = test::call_service(&app, redeem(Some(""))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn divergence_audit_flags_bloom_gaps_and_stale_cache() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let bloom = Arc::new(PidBloom::new(10_000, 0.01).unwrap());
+    let state = build_state(
+        storage,
+        Arc::new(InMemoryPidCache::default()),
+        Some(bloom.clone()),
+    );
+    let ghost = PaymentId::parse("fedcba9876543210").unwrap();
+    state.cache().mark_present(&ghost);
+
+    let report = audit_once(&state, 10).await.unwrap();
+    assert_eq!(
+        report,
+        DivergenceReport {
+            bloom_missing: 1,
+            cache_stale: 1,
+        }
+    );
+
+    bloom.insert(&test_pid());
+    state.cache().invalidate(&ghost);
+    state.cache().mark_present(&test_pid());
+    assert_eq!(
+        audit_once(&state, 10).await.unwrap(),
+        DivergenceReport::default()
+    );
+}
+
+#[actix_web::test]
+async fn token_status_returns_active() {
+    let storage = storage().await;
+    let token = insert_token(&storage).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .route("/api/v1/token/{token}", web::get().to(token_status_handler)),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/token/{}", token.to_hex()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn token_ttl_expires_and_janitor_purges() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let state =
+        with_cache(storage.clone()).with_token_ttl(Some(std::time::Duration::from_secs(3600)));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let status = |token: &ServiceToken| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/token/{}", token.to_hex()))
+            .to_request()
+    };
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: test_pid().into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let redeemed: RedeemResponse = test::call_and_read_body_json(&app, req).await;
+    let fresh = ServiceToken::parse(&redeemed.service_token).unwrap();
+    let body: TokenStatusResponse = test::call_and_read_body_json(&app, status(&fresh)).await;
+    assert_eq!(body.status, TokenState::Active);
+    assert_eq!(
+        body.expires_at,
+        Some(body.issued_at + chrono::Duration::hours(1))
+    );
+
+    let stale = TokenFixture::active()
+        .token(ServiceToken::from_bytes([7; 32]))
+        .pid(anon_ticket_testkit::nth_pid(7))
+        .expires_at(chrono::Utc::now() - chrono::Duration::minutes(1))
+        .insert(&storage)
+        .await
+        .unwrap()
+        .token;
+    let body: TokenStatusResponse = test::call_and_read_body_json(&app, status(&stale)).await;
+    assert_eq!(body.status, TokenState::Expired);
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/v1/token/{}/spend", stale.to_hex()))
+        .set_json(&SpendRequest { amount: 1 })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    // Within the retention window nothing is purged.
+    let retained = chrono::Utc::now() - chrono::Duration::hours(1);
+    assert_eq!(purge_expired_tokens(&storage, retained).await.unwrap(), 0);
+    assert_eq!(
+        purge_expired_tokens(&storage, chrono::Utc::now())
+            .await
+            .unwrap(),
+        1
+    );
+    let resp = test::call_service(&app, status(&stale)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    let resp = test::call_service(&app, status(&fresh)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn tiers_follow_the_payment_amount() {
+    let storage = storage().await;
+    PaymentFixture::confirmed()
+        .amount(500)
+        .insert(&storage)
+        .await
+        .unwrap();
+    PaymentFixture::confirmed()
+        .pid(nth_pid(2))
+        .amount(5_000)
+        .insert(&storage)
+        .await
+        .unwrap();
+    PaymentFixture::confirmed()
+        .pid(nth_pid(3))
+        .amount(10)
+        .insert(&storage)
+        .await
+        .unwrap();
+    let state = with_cache(storage.clone())
+        .with_token_ttl(Some(std::time::Duration::from_secs(3600)))
+        .with_token_tiers(vec![
+            TokenTier::new("gold", 5_000)
+                .with_quota(1_000)
+                .with_validity_secs(86_400),
+            TokenTier::new("bronze", 100).with_quota(10),
+        ]);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let redeem_status = |pid: PaymentId| {
+        let app = &app;
+        async move {
+            let req = test::TestRequest::post()
+                .uri("/api/v1/redeem")
+                .set_json(&RedeemRequest {
+                    pid: pid.into_inner(),
+                    client_secret: None,
+                    passphrase: None,
+                })
+                .to_request();
+            let redeemed: RedeemResponse = test::call_and_read_body_json(app, req).await;
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/v1/token/{}", redeemed.service_token))
+                .to_request();
+            let status: TokenStatusResponse = test::call_and_read_body_json(app, req).await;
+            (redeemed, status)
+        }
+    };
+
+    let (redeemed, status) = redeem_status(test_pid()).await;
+    assert_eq!(redeemed.tier.as_deref(), Some("bronze"));
+    assert_eq!(status.tier.as_deref(), Some("bronze"));
+    assert_eq!(status.quota, Some(10));
+    assert_eq!(
+        status.expires_at,
+        Some(status.issued_at + chrono::Duration::hours(1))
+    );
+
+    // Validity set on the tier replaces the default token lifetime.
+    let (_, status) = redeem_status(nth_pid(2)).await;
+    assert_eq!(status.tier.as_deref(), Some("gold"));
+    assert_eq!(status.quota, Some(1_000));
+    assert_eq!(
+        status.expires_at,
+        Some(status.issued_at + chrono::Duration::days(1))
+    );
+
+    let (redeemed, status) = redeem_status(nth_pid(3)).await;
+    assert_eq!(redeemed.tier, None);
+    assert_eq!((status.tier, status.quota), (None, None));
+}
+
+#[actix_web::test]
+async fn forward_auth_injects_ticket_headers() {
+    let storage = storage().await;
+    let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
+    let active = TokenFixture::active()
+        .amount(5_000)
+        .tier("gold")
+        .expires_at(expires_at)
+        .insert(&storage)
+        .await
+        .unwrap()
+        .token;
+    let revoked = TokenFixture::revoked()
+        .token(ServiceToken::from_bytes([7; 32]))
+        .pid(nth_pid(3))
+        .insert(&storage)
+        .await
+        .unwrap()
+        .token;
+    let state = with_cache(storage.clone()).with_token_tiers(vec![TokenTier::new("gold", 5_000)
+        .with_quota(1_000)
+        .with_scope("streams:hd")
+        .with_header("X-Plan", "Gold")]);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let forward = |header: Option<(&str, String)>| {
+        let mut req = test::TestRequest::get().uri("/api/v1/forward-auth");
+        if let Some(header) = header {
+            req = req.insert_header(header);
+        }
+        req.to_request()
+    };
+    let bearer =
+        |token: &ServiceToken| Some(("authorization", format!("Bearer {}", token.to_hex())));
+
+    let resp = test::call_service(&app, forward(bearer(&active))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let header = |name: &str| {
+        resp.headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap().to_string())
+    };
+    assert_eq!(header("x-ticket-tier").as_deref(), Some("gold"));
+    assert_eq!(header("x-ticket-quota").as_deref(), Some("1000"));
+    assert_eq!(header("x-ticket-scope").as_deref(), Some("streams:hd"));
+    assert_eq!(header("x-plan").as_deref(), Some("Gold"));
+    assert_eq!(header("x-ticket-balance").as_deref(), Some("5000"));
+    assert_eq!(
+        header("x-ticket-expires-at").as_deref(),
+        Some(expires_at.to_rfc3339().as_str())
+    );
+
+    let resp = test::call_service(&app, forward(bearer(&revoked))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+    let resp = test::call_service(&app, forward(None)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+    assert!(resp.headers().contains_key("www-authenticate"));
+    let unknown = ServiceToken::from_bytes([9; 32]);
+    let resp = test::call_service(&app, forward(bearer(&unknown))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+
+    // A configured header holds the bare token, with a cookie as fallback.
+    let state = with_cache(storage.clone())
+        .with_forward_auth(ForwardAuthSource::new(Some("X-Ticket-Token"), Some("ticket")).unwrap());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let resp = test::call_service(&app, forward(Some(("x-ticket-token", active.to_hex())))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri("/api/v1/forward-auth")
+        .cookie(actix_web::cookie::Cookie::new("ticket", active.to_hex()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let resp = test::call_service(&app, forward(bearer(&active))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn first_validation_is_recorded_once() {
+    let storage = storage().await;
+    let token = insert_token(&storage).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .route("/api/v1/token/{token}", web::get().to(token_status_handler)),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/token/{}", token.to_hex()))
+        .to_request();
+    test::call_service(&app, req).await;
+    assert!(!storage
+        .record_first_validation(&token, chrono::Utc::now())
+        .await
+        .unwrap());
+}
+
+#[actix_web::test]
+async fn revoke_token_is_internal_only_and_revokes() {
+    let storage = storage().await;
+    let token = insert_token(&storage).await;
+    let state = with_cache(storage);
+
+    let public_app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .route("/api/v1/token/{token}", web::get().to(token_status_handler)),
+    )
+    .await;
+
+    let internal_app = test::init_service(App::new().app_data(web::Data::new(state)).route(
+        "/api/v1/token/{token}/revoke",
+        web::post().to(revoke_token_handler),
+    ))
+    .await;
+
+    let revoke_body = RevokeRequest {
+        reason: Some("abuse".into()),
+        abuse_score: Some(5),
+    };
+
+    let public_resp = test::call_service(
+        &public_app,
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/token/{}/revoke", token.to_hex()))
+            .set_json(&revoke_body)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(public_resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+    let internal_resp = test::call_service(
+        &internal_app,
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/token/{}/revoke", token.to_hex()))
+            .set_json(&revoke_body)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(internal_resp.status(), actix_web::http::StatusCode::OK);
+
+    let status_resp = test::call_service(
+        &public_app,
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/token/{}", token.to_hex()))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(status_resp.status(), actix_web::http::StatusCode::OK);
+    let parsed: TokenStatusResponse =
+        serde_json::from_slice(&to_bytes(status_resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(parsed.status, TokenState::Revoked);
+}
+
+#[actix_web::test]
+async fn introspection_follows_rfc_7662() {
+    let storage = storage().await;
+    let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
+    let active = TokenFixture::active()
+        .expires_at(expires_at)
+        .insert(&storage)
+        .await
+        .unwrap();
+    let revoked = TokenFixture::revoked()
+        .token(ServiceToken::from_bytes([7; 32]))
+        .pid(nth_pid(3))
+        .insert(&storage)
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .configure(internal_routes),
+    )
+    .await;
+    let introspect = |token: &str| {
+        test::TestRequest::post()
+            .uri("/internal/introspect")
+            .set_form([("token", token), ("token_type_hint", "access_token")])
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, introspect(&active.token.to_hex())).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let body: IntrospectResponse = test::read_body_json(resp).await;
+    assert_eq!(
+        body,
+        IntrospectResponse {
+            active: true,
+            scope: None,
+            iat: Some(active.issued_at.timestamp()),
+            exp: Some(expires_at.timestamp()),
+        }
+    );
+
+    // Inactive tokens disclose nothing beyond that.
+    for token in [revoked.token.to_hex(), "not-a-token".to_string()] {
+        let resp = test::call_service(&app, introspect(&token)).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body, serde_json::json!({ "active": false }));
+    }
+}
+
+#[actix_web::test]
+async fn token_balance_returns_slim_payload_with_etag() {
+    let storage = storage().await;
+    let token = insert_token(&storage).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .route(
+                "/api/v1/token/{token}/balance",
+                web::get().to(token_balance_handler),
+            ),
+    )
+    .await;
+
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/token/{}/balance", token.to_hex()))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let etag = resp
+        .headers()
+        .get(actix_web::http::header::ETAG)
+        .expect("etag present")
+        .clone();
+    assert!(resp
+        .headers()
+        .get(actix_web::http::header::CACHE_CONTROL)
+        .is_some());
+    let parsed: TokenBalanceResponse =
+        serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(
+        parsed,
+        TokenBalanceResponse {
+            status: TokenState::Active,
+            balance: 42,
+            expires_at: None,
+        }
+    );
+
+    let revalidated = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/token/{}/balance", token.to_hex()))
+            .insert_header((actix_web::http::header::IF_NONE_MATCH, etag))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(
+        revalidated.status(),
+        actix_web::http::StatusCode::NOT_MODIFIED
+    );
+}
+
+#[actix_web::test]
+async fn split_moves_balance_onto_a_new_token() {
+    let storage = storage().await;
+    let token = insert_token(&storage).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .configure(public_routes),
+    )
+    .await;
+    let split = |amount| {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/token/{}/split", token.to_hex()))
+            .set_json(&SplitRequest { amount })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, split(10)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let body: SplitResponse = test::read_body_json(resp).await;
+    assert_eq!((body.amount, body.remaining_balance), (10, 32));
+
+    let child = ServiceToken::parse(&body.service_token).unwrap();
+    let child_record = storage.find_token(&child).await.unwrap().unwrap();
+    assert_eq!(child_record.amount, 10);
+    assert_ne!(child_record.pid, test_pid());
+    // PID lookups still resolve to the original token.
+    let by_pid = storage
+        .find_token_by_pid(&test_pid())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((by_pid.token, by_pid.amount), (token.clone(), 32));
+
+    for (amount, status) in [
+        (33, actix_web::http::StatusCode::CONFLICT),
+        (0, actix_web::http::StatusCode::BAD_REQUEST),
+    ] {
+        let resp = test::call_service(&app, split(amount)).await;
+        assert_eq!(resp.status(), status, "{amount}");
+    }
+    assert_eq!(
+        storage.find_token(&token).await.unwrap().unwrap().amount,
+        32
+    );
+}
+
+#[actix_web::test]
+async fn spend_debits_balance_and_rejects_overdrafts() {
+    let storage = storage().await;
+    let token = insert_token(&storage).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .configure(public_routes),
+    )
+    .await;
+    let spend = |token: &ServiceToken, amount| {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/token/{}/spend", token.to_hex()))
+            .set_json(&SpendRequest { amount })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, spend(&token, 40)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let body: SpendResponse = test::read_body_json(resp).await;
+    assert_eq!(
+        body,
+        SpendResponse {
+            spent: 40,
+            balance: 2
+        }
+    );
+
+    let unknown = ServiceToken::parse(&"cd".repeat(32)).unwrap();
+    for (token, amount, status) in [
+        (&token, 3, actix_web::http::StatusCode::CONFLICT),
+        (&token, -1, actix_web::http::StatusCode::BAD_REQUEST),
+        (&unknown, 1, actix_web::http::StatusCode::NOT_FOUND),
+    ] {
+        let resp = test::call_service(&app, spend(token, amount)).await;
+        assert_eq!(resp.status(), status, "{amount}");
+    }
+
+    // The whole balance can be spent, but not past zero.
+    let resp = test::call_service(&app, spend(&token, 2)).await;
+    let body: SpendResponse = test::read_body_json(resp).await;
+    assert_eq!(body.balance, 0);
+    storage
+        .revoke_token(RevokeTokenRequest {
+            token: token.clone(),
+            reason: None,
+            abuse_score: None,
+        })
+        .await
+        .unwrap();
+    let resp = test::call_service(&app, spend(&token, 1)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn suspended_tokens_are_refused_until_resumed() {
+    let storage = storage().await;
+    let token = TokenFixture::suspended()
+        .insert(&storage)
+        .await
+        .unwrap()
+        .token;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage.clone())))
+            .configure(public_routes),
+    )
+    .await;
+    let status = || {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/token/{}", token.to_hex()))
+            .to_request()
+    };
+    let spend = || {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/token/{}/spend", token.to_hex()))
+            .set_json(&SpendRequest { amount: 1 })
+            .to_request()
+    };
+    let forward = || {
+        test::TestRequest::get()
+            .uri("/api/v1/forward-auth")
+            .insert_header(("authorization", format!("Bearer {}", token.to_hex())))
+            .to_request()
+    };
+
+    let body: TokenStatusResponse = test::call_and_read_body_json(&app, status()).await;
+    assert_eq!(body.status, TokenState::Suspended);
+    assert_eq!(body.revoked_at, None);
+    let resp = test::call_service(&app, spend()).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+    let resp = test::call_service(&app, forward()).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::FORBIDDEN);
+
+    storage.resume_token(&token).await.unwrap().unwrap();
+    let body: TokenStatusResponse = test::call_and_read_body_json(&app, status()).await;
+    assert_eq!(body.status, TokenState::Active);
+    let resp = test::call_service(&app, spend()).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+    let resp = test::call_service(&app, forward()).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn operators_suspend_tokens_for_a_while() {
+    let storage = storage().await;
+    let token = insert_token(&storage).await;
+    let state = with_cache(storage.clone());
+    let public = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .configure(public_routes),
+    )
+    .await;
+    let internal = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(internal_routes),
+    )
+    .await;
+    let suspend = |body: &SuspendRequest| {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/token/{}/suspend", token.to_hex()))
+            .set_json(body)
+            .to_request()
+    };
+    let unsuspend = || {
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/token/{}/unsuspend", token.to_hex()))
+            .to_request()
+    };
+    let status = || {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/token/{}", token.to_hex()))
+            .to_request()
+    };
+
+    let resp = test::call_service(&public, suspend(&SuspendRequest::default())).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    for invalid in [
+        SuspendRequest {
+            until: Some(chrono::Utc::now() - chrono::Duration::minutes(1)),
+            ..SuspendRequest::default()
+        },
+        SuspendRequest {
+            until: Some(chrono::Utc::now() + chrono::Duration::minutes(1)),
+            duration_secs: Some(60),
+            ..SuspendRequest::default()
+        },
+    ] {
+        let resp = test::call_service(&internal, suspend(&invalid)).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    let timeout = SuspendRequest {
+        reason: Some("abuse".into()),
+        duration_secs: Some(3600),
+        ..SuspendRequest::default()
+    };
+    let body: TokenStatusResponse =
+        test::call_and_read_body_json(&internal, suspend(&timeout)).await;
+    assert_eq!(body.status, TokenState::Suspended);
+    let until = body.suspended_until.expect("timed suspension");
+    assert!(until > chrono::Utc::now() + chrono::Duration::minutes(59));
+    let resp = test::call_service(&internal, suspend(&timeout)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+    let body: TokenStatusResponse = test::call_and_read_body_json(&public, status()).await;
+    assert_eq!(body.status, TokenState::Suspended);
+    assert_eq!(body.suspended_until, Some(until));
+
+    // Lifting early works once; the token is then active again.
+    let body: TokenStatusResponse = test::call_and_read_body_json(&internal, unsuspend()).await;
+    assert_eq!(body.status, TokenState::Active);
+    assert_eq!((body.suspended_at, body.suspended_until), (None, None));
+    let resp = test::call_service(&internal, unsuspend()).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    // The sweep lifts a suspension once it lapses.
+    let body: TokenStatusResponse =
+        test::call_and_read_body_json(&internal, suspend(&timeout)).await;
+    assert_eq!(body.status, TokenState::Suspended);
+    assert_eq!(storage.lift_lapsed_suspensions(until).await.unwrap(), 0);
+    assert_eq!(
+        storage
+            .lift_lapsed_suspensions(chrono::Utc::now() + chrono::Duration::hours(2))
+            .await
+            .unwrap(),
+        1
+    );
+    let body: TokenStatusResponse = test::call_and_read_body_json(&public, status()).await;
+    assert_eq!(body.status, TokenState::Active);
+
+    let unknown = ServiceToken::from_bytes([0xee; 32]);
+    let resp = test::call_service(
+        &internal,
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/token/{}/unsuspend", unknown.to_hex()))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn merge_consolidates_tokens_and_revokes_sources() {
+    let storage = storage().await;
+    let now = chrono::Utc::now();
+    let mut sources = Vec::new();
+    for (n, amount) in [(1, 10), (2, 20)] {
+        let record = TokenFixture::active()
+            .pid(anon_ticket_testkit::nth_pid(n))
+            .amount(amount)
+            .issued_at(now)
+            .insert(&storage)
+            .await
+            .unwrap();
+        sources.push(record.token);
+    }
+    // 1 atomic unit buys one day.
+    let state =
+        with_cache(storage.clone()).with_subscription_period(SubscriptionPeriod::new(86_400, 1));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(public_routes),
+    )
+    .await;
+    let merge = |tokens: &[ServiceToken]| {
+        test::TestRequest::post()
+            .uri("/api/v1/token/merge")
+            .set_json(&MergeRequest {
+                tokens: tokens.iter().map(ServiceToken::to_hex).collect(),
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, merge(&sources[..1])).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let resp = test::call_service(&app, merge(&sources)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let body: MergeResponse = test::read_body_json(resp).await;
+    assert_eq!((body.balance, body.merged), (30, 2));
+    let expires_at = body.expires_at.expect("subscriptions enabled");
+    let expected = now + chrono::Duration::days(30);
+    assert!(
+        (expires_at - expected).num_seconds().abs() < 5,
+        "{expires_at}"
+    );
+
+    for source in &sources {
+        let record = storage.find_token(source).await.unwrap().unwrap();
+        assert_eq!(record.revoke_reason.as_deref(), Some("merged"));
+    }
+    let merged = ServiceToken::parse(&body.service_token).unwrap();
+    let status: TokenStatusResponse = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/token/{}", merged.to_hex()))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(status.status, TokenState::Active);
+    assert_eq!(status.expires_at, Some(expires_at));
+
+    let resp = test::call_service(&app, merge(&sources)).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn renewal_payment_extends_token_expiry() {
+    let storage = storage().await;
+    let issued_at = chrono::Utc::now() - chrono::Duration::days(60);
+    PaymentFixture::claimed()
+        .amount(30)
+        .detected_at(issued_at)
+        .insert(&storage)
+        .await
+        .unwrap();
+    let token = TokenFixture::active()
+        .amount(30)
+        .issued_at(issued_at)
+        .insert(&storage)
+        .await
+        .unwrap()
+        .token;
+    // 1 atomic unit buys one day.
+    let state =
+        with_cache(storage.clone()).with_subscription_period(SubscriptionPeriod::new(86_400, 1));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/token/{token}", web::get().to(token_status_handler)),
+    )
+    .await;
+    let status = || {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/token/{}", token.to_hex()))
+            .to_request()
+    };
+
+    let lapsed: TokenStatusResponse = test::call_and_read_body_json(&app, status()).await;
+    assert_eq!(lapsed.status, TokenState::Expired);
+    assert_eq!(
+        lapsed.expires_at,
+        Some(issued_at + chrono::Duration::days(30))
+    );
+
+    let renewed_at = chrono::Utc::now() - chrono::Duration::days(1);
+    PaymentFixture::confirmed()
+        .txid("cd".repeat(32))
+        .amount(30)
+        .detected_at(renewed_at)
+        .insert(&storage)
+        .await
+        .unwrap();
+    let renewed: TokenStatusResponse = test::call_and_read_body_json(&app, status()).await;
+    assert_eq!(renewed.status, TokenState::Active);
+    assert_eq!(
+        renewed.expires_at,
+        Some(renewed_at + chrono::Duration::days(30))
+    );
+    assert_eq!(renewed.amount, 30);
+    assert!(storage
+        .find_token_by_pid(&test_pid())
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[actix_web::test]
+async fn cache_stats_and_scoped_flush() {
+    let state = with_cache(storage().await);
+    let other = anon_ticket_testkit::nth_pid(7);
+    state.cache().mark_present(&test_pid());
+    state.cache().mark_present(&other);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(internal_routes),
+    )
+    .await;
+
+    let stats = |app| async move {
+        let req = test::TestRequest::get()
+            .uri("/internal/cache/stats")
+            .to_request();
+        let parsed: CacheStatsResponse = test::call_and_read_body_json(app, req).await;
+        parsed
+    };
+    let before = stats(&app).await;
+    assert_eq!(before.entries, 2);
+    assert!(!before.bloom_enabled);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/internal/cache/flush?pid={}", other))
+        .to_request();
+    let scoped: CacheFlushResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(scoped.scope, "pid");
+    assert_eq!(scoped.flushed, 1);
+
+    let req = test::TestRequest::post()
+        .uri("/internal/cache/flush?pid=nothex")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri("/internal/cache/flush")
+        .to_request();
+    let all: CacheFlushResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!((all.scope.as_str(), all.flushed), ("all", 1));
+    assert_eq!(stats(&app).await.entries, 0);
+}
+
+#[actix_web::test]
+async fn runtime_config_is_redacted() {
+    let api = ApiConfig::builder("postgres://app:hunter2@db/anon", "127.0.0.1:8080")
+        .internal_bind_address("127.0.0.1:9090")
+        .pid_bloom_entries(1_000)
+        .build()
+        .unwrap();
+    let bloom = Arc::new(PidBloom::new(1_000, 0.01).unwrap());
+    let state = build_state(
+        storage().await,
+        Arc::new(InMemoryPidCache::default()),
+        Some(bloom),
+    )
+    .with_effective_config(Some(EffectiveConfig {
+        api: api.redacted_env(),
+        monitor: None,
+    }));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(internal_routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/internal/config")
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert!(!String::from_utf8_lossy(&body).contains("hunter2"));
+    let config: RuntimeConfigResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        config.api["DATABASE_URL"],
+        "postgres://app:<redacted>@db/anon"
+    );
+    assert_eq!(config.api["API_PID_BLOOM_ENTRIES"], "1000");
+    assert!(config.monitor.is_none());
+    let bloom = config.bloom.expect("bloom enabled");
+    assert_eq!(bloom.expected_items, 1_000);
+    assert!(bloom.num_bits > 0 && bloom.num_hashes > 0);
+    assert_eq!(config.build.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(
+        config.features.contains(&"grpc".to_string()),
+        cfg!(feature = "grpc")
+    );
+
+    // States built without a config (embedders, tests) do not expose one.
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage().await)))
+            .configure(internal_routes),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/internal/config")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+}
+
+fn injection(txid: &str, reason: &str) -> InjectPaymentRequest {
+    InjectPaymentRequest {
+        pid: test_pid().into_inner(),
+        txid: txid.into(),
+        amount: 42,
+        block_height: 100,
+        reason: reason.into(),
+        operator: Some("support".into()),
+        address_id: None,
+    }
+}
+
+#[actix_web::test]
+async fn injected_payment_is_redeemable_through_bloom() {
+    let bloom = Arc::new(PidBloom::new(10_000, 0.01).unwrap());
+    let state = build_state(
+        storage().await,
+        Arc::new(InMemoryPidCache::default()),
+        Some(bloom.clone()),
+    );
+    let internal = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .configure(internal_routes),
+    )
+    .await;
+    let public = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .route("/api/v1/redeem", web::post().to(redeem_handler)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/internal/payments")
+        .set_json(injection("tx-manual", "wallet rescan missed transfer"))
+        .to_request();
+    let resp = test::call_service(&internal, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let created: PaymentResponse = test::read_body_json(resp).await;
+    assert_eq!(created.status, PaymentState::Unclaimed);
+    assert!(bloom.might_contain(&test_pid()));
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: test_pid().into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let redeemed: RedeemResponse = test::call_and_read_body_json(&public, req).await;
+    assert_eq!(redeemed.balance, 42);
+}
+
+#[actix_web::test]
+async fn payment_injection_is_idempotent_and_validated() {
+    let state = with_cache(storage().await);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(internal_routes),
+    )
+    .await;
+    let post = |body: InjectPaymentRequest| {
+        test::TestRequest::post()
+            .uri("/internal/payments")
+            .set_json(body)
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, post(injection("tx-a", " "))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+    let resp = test::call_service(&app, post(injection("tx-a", "verified"))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CREATED);
+    let resp = test::call_service(&app, post(injection("tx-a", "verified"))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::OK);
+
+    let resp = test::call_service(&app, post(injection("tx-b", "verified"))).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "conflict");
+}
+
+fn override_request(uri: String) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(&uri)
+        .set_json(ClaimOverrideRequest {
+            reason: "support ticket 42".into(),
+            operator: Some("support".into()),
+        })
+}
+
+#[actix_web::test]
+async fn force_claim_issues_token_and_blocks_unclaim() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .configure(internal_routes),
+    )
+    .await;
+    let pid = test_pid();
+
+    let claim_uri = format!("/internal/payments/{pid}/claim");
+    let claimed: ClaimOverrideResponse =
+        test::call_and_read_body_json(&app, override_request(claim_uri.clone()).to_request()).await;
+    assert_eq!(claimed.payment.status, PaymentState::Claimed);
+    let token = claimed.service_token.expect("token issued");
+    assert_eq!(token, PaymentFixture::confirmed().expected_token().to_hex());
+
+    // Re-running the override returns the same token.
+    let again: ClaimOverrideResponse =
+        test::call_and_read_body_json(&app, override_request(claim_uri).to_request()).await;
+    assert_eq!(again.service_token.as_deref(), Some(token.as_str()));
+
+    let resp = test::call_service(
+        &app,
+        override_request(format!("/internal/payments/{pid}/unclaim")).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn unclaim_reverts_tokenless_claim() {
+    let storage = storage().await;
+    PaymentFixture::claimed().insert(&storage).await.unwrap();
+    let state = with_cache(storage);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .configure(internal_routes)
+            .configure(crate::application::public_routes),
+    )
+    .await;
+    let pid = test_pid();
+
+    let reverted: ClaimOverrideResponse = test::call_and_read_body_json(
+        &app,
+        override_request(format!("/internal/payments/{pid}/unclaim")).to_request(),
+    )
+    .await;
+    assert_eq!(reverted.payment.status, PaymentState::Unclaimed);
+    assert!(reverted.payment.claimed_at.is_none());
+    assert!(reverted.service_token.is_none());
+
+    let resp = test::call_service(
+        &app,
+        override_request(format!("/internal/payments/{pid}/unclaim")).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/redeem")
+        .set_json(&RedeemRequest {
+            pid: pid.into_inner(),
+            client_secret: None,
+            passphrase: None,
+        })
+        .to_request();
+    let redeemed: RedeemResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(redeemed.status, "success");
+}
+
+#[actix_web::test]
+async fn refunded_payments_cannot_be_claimed() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let pid = test_pid();
+    let refund = storage
+        .record_refund(NewRefund {
+            pid: pid.clone(),
+            address: "payer".into(),
+            amount: 42,
+            requested_at: chrono::Utc::now(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    let sent = RefundOutcome::Sent {
+        txid: "refund-tx".into(),
+    };
+    storage
+        .resolve_refund(refund.id, sent, chrono::Utc::now())
+        .await
+        .unwrap()
+        .unwrap();
+    let state = with_cache(storage);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .configure(internal_routes)
+            .configure(crate::application::public_routes),
+    )
+    .await;
+
+    let resp = test::call_service(
+        &app,
+        override_request(format!("/internal/payments/{pid}/claim")).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("a refunded payment cannot be claimed"));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/payment/{}", pid.to_hex()))
+        .to_request();
+    let body: PaymentStatusResponse = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.status, "refunded");
+    assert!(!body.claimable);
+}
+
+#[actix_web::test]
+async fn internal_listings_filter_and_paginate() {
+    let storage = storage().await;
+    for n in 1..=4 {
+        let payment = if n == 2 {
+            PaymentFixture::claimed()
+        } else {
+            PaymentFixture::confirmed()
+        };
+        payment
+            .pid(nth_pid(n))
+            .txid(format!("tx{n}"))
+            .amount(n as i64 * 100)
+            .block_height(n as i64 * 10)
+            .insert(&storage)
+            .await
+            .unwrap();
+    }
+    TokenFixture::active()
+        .pid(nth_pid(1))
+        .insert(&storage)
+        .await
+        .unwrap();
+    TokenFixture::revoked()
+        .token(ServiceToken::from_bytes([7; 32]))
+        .pid(nth_pid(3))
+        .insert(&storage)
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .configure(internal_routes),
+    )
+    .await;
+    let list = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+    let pids = |page: &Page<PaymentResponse>| -> Vec<String> {
+        page.items.iter().map(|p| p.pid.clone()).collect()
+    };
+
+    let first: Page<PaymentResponse> =
+        test::call_and_read_body_json(&app, list("/internal/v1/payments?limit=3")).await;
+    assert_eq!(
+        pids(&first),
+        [nth_pid(1), nth_pid(2), nth_pid(3)].map(PaymentId::into_inner)
+    );
+    let after = first.next_after.expect("another page");
+    let rest: Page<PaymentResponse> = test::call_and_read_body_json(
+        &app,
+        list(&format!("/internal/v1/payments?limit=3&after={after}")),
+    )
+    .await;
+    assert_eq!(pids(&rest), [nth_pid(4).into_inner()]);
+    assert!(rest.next_after.is_none());
+
+    let filtered: Page<PaymentResponse> = test::call_and_read_body_json(
+        &app,
+        list("/internal/v1/payments?status=unclaimed&min_height=20&max_amount=300"),
+    )
+    .await;
+    assert_eq!(pids(&filtered), [nth_pid(3).into_inner()]);
+
+    let revoked: Page<TokenSummary> =
+        test::call_and_read_body_json(&app, list("/internal/v1/tokens?status=revoked")).await;
+    assert_eq!(revoked.items.len(), 1);
+    assert_eq!(revoked.items[0].pid, nth_pid(3).into_inner());
+    assert_eq!(revoked.items[0].status, TokenState::Revoked);
+    let low: Page<TokenSummary> =
+        test::call_and_read_body_json(&app, list("/internal/v1/tokens?max_height=10")).await;
+    assert_eq!(low.items.len(), 1);
+    assert_eq!(low.items[0].status, TokenState::Active);
+
+    for uri in [
+        "/internal/v1/payments?status=spent",
+        "/internal/v1/payments?limit=0",
+        "/internal/v1/payments?min_amount=5&max_amount=1",
+        "/internal/v1/tokens?status=claimed",
+    ] {
+        let resp = test::call_service(&app, list(uri)).await;
+        assert_eq!(
+            resp.status(),
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "{uri}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn internal_search_finds_payments_tokens_and_quotes() {
+    let storage = storage().await;
+    let pid = nth_pid(1);
+    let txid = "aa".repeat(32);
+    PaymentFixture::confirmed()
+        .pid(pid.clone())
+        .txid(txid.clone())
+        .insert(&storage)
+        .await
+        .unwrap();
+    let token = TokenFixture::active()
+        .token(ServiceToken::from_bytes([0xab; 32]))
+        .pid(pid.clone())
+        .insert(&storage)
+        .await
+        .unwrap()
+        .token;
+    storage
+        .insert_quote(NewPaymentQuote {
+            pid: pid.clone(),
+            expected_amount: 100,
+            created_at: chrono::Utc::now(),
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+            address_id: None,
+        })
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .configure(internal_routes),
+    )
+    .await;
+    let search = |q: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/internal/search?q={q}"))
+            .to_request()
+    };
+    let primary = "4ADT1BtbxqEWeMKp9GgPr2NeyJXXtNxvoDawpyA4WpzFcGcoHUvXeijE66DNfohE9r1bQYaBiQjEtKE7CtkTdLwiDznFzra";
+    let integrated = build_integrated_address(primary, &pid).unwrap();
+
+    for (q, matched_as) in [
+        (txid.clone(), &["txid", "token"][..]),
+        (token.to_hex(), &["txid", "token"]),
+        (token.to_hex()[..12].to_uppercase(), &["token_prefix"]),
+        (pid.to_hex(), &["pid", "token_prefix"]),
+        (integrated, &["integrated_address"]),
+    ] {
+        let found: SearchResponse = test::call_and_read_body_json(&app, search(&q)).await;
+        assert_eq!(found.matched_as, matched_as, "{q}");
+        assert_eq!(found.payments.len(), 1, "{q}");
+        assert_eq!(found.payments[0].txid, txid);
+        assert_eq!(found.tokens.len(), 1, "{q}");
+        assert_eq!(found.tokens[0].token, token.to_hex());
+        assert_eq!(found.quotes.len(), 1, "{q}");
+        assert_eq!(found.quotes[0].status, "open");
+    }
+
+    let missing: SearchResponse =
+        test::call_and_read_body_json(&app, search(&nth_pid(2).to_hex())).await;
+    assert!(missing.payments.is_empty() && missing.tokens.is_empty());
+    for q in ["", "abc", "not-an-address"] {
+        let resp = test::call_service(&app, search(q)).await;
+        assert_eq!(
+            resp.status(),
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "{q:?}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn audit_log_records_redeems_and_overrides() {
+    let storage = storage().await;
+    PaymentFixture::confirmed().insert(&storage).await.unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage)))
+            .configure(internal_routes)
+            .configure(crate::application::public_routes),
+    )
+    .await;
+    let pid = test_pid();
+    let redeem = |pid: String| {
+        test::TestRequest::post()
+            .uri("/api/v1/redeem")
+            .set_json(&RedeemRequest {
+                pid,
+                client_secret: None,
+                passphrase: None,
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, redeem(pid.clone().into_inner())).await;
+    assert!(resp.status().is_success());
+    // Unknown PIDs are not audited.
+    let resp = test::call_service(&app, redeem(nth_pid(9).into_inner())).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    let resp = test::call_service(
+        &app,
+        override_request(format!("/internal/payments/{pid}/claim")).to_request(),
+    )
+    .await;
+    assert!(resp.status().is_success());
+    let resp = test::call_service(
+        &app,
+        override_request(format!("/internal/payments/{pid}/unclaim")).to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::CONFLICT);
+
+    let list = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+    let all: Page<AuditEventSummary> =
+        test::call_and_read_body_json(&app, list("/internal/v1/audit")).await;
+    let trail: Vec<(&str, &str, &str)> = all
+        .items
+        .iter()
+        .map(|e| (e.actor.as_str(), e.action.as_str(), e.outcome.as_str()))
+        .collect();
+    assert_eq!(
+        trail,
+        [
+            ("internal", "payment.unclaim", "token_issued"),
+            ("internal", "payment.force_claim", "already_claimed"),
+            ("public", "redeem", "claimed"),
+        ]
+    );
+    assert_eq!(all.items[0].reason.as_deref(), Some("support ticket 42"));
+    assert!(all.items[2].reason.is_none());
+    // Only a hash of the PID is kept.
+    assert!(all
+        .items
+        .iter()
+        .all(|e| !e.subject_hash.contains(&pid.to_hex())));
+
+    let first: Page<AuditEventSummary> =
+        test::call_and_read_body_json(&app, list("/internal/v1/audit?limit=2")).await;
+    let after = first.next_after.expect("another page");
+    let rest: Page<AuditEventSummary> = test::call_and_read_body_json(
+        &app,
+        list(&format!("/internal/v1/audit?limit=2&after={after}")),
+    )
+    .await;
+    assert_eq!(rest.items.len(), 1);
+    assert_eq!(rest.items[0].action, "redeem");
+    assert!(rest.next_after.is_none());
+
+    let public: Page<AuditEventSummary> = test::call_and_read_body_json(
+        &app,
+        list(&format!(
+            "/internal/v1/audit?actor=public&subject={}",
+            pid.to_hex().to_uppercase()
+        )),
+    )
+    .await;
+    assert_eq!(public.items.len(), 1);
+    assert_eq!(public.items[0].subject_hash, all.items[0].subject_hash);
+
+    let resp = test::call_service(&app, list("/internal/v1/audit?actor=robot")).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn claim_overrides_require_reason() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(with_cache(storage().await)))
+            .configure(internal_routes),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri(&format!("/internal/payments/{}/claim", test_pid()))
+        .set_json(ClaimOverrideRequest {
+            reason: String::new(),
+            operator: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn internal_routes_enforce_key_roles() {
+    use anon_ticket_domain::config::{InternalApiKey, InternalRole};
+
+    const READ_ONLY: &str = "read-only-secret-0001";
+    const SUPPORT: &str = "support-secret-000002";
+    const ADMIN: &str = "admin-secret-00000003";
+    let keys = [
+        InternalApiKey::new("grafana", InternalRole::ReadOnly, READ_ONLY),
+        InternalApiKey::new("helpdesk", InternalRole::Support, SUPPORT),
+        InternalApiKey::new("ops", InternalRole::Admin, ADMIN),
+    ];
+    let state = with_cache(storage().await).with_internal_auth(InternalAuth::from_keys(&keys));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .configure(internal_routes),
+    )
+    .await;
+
+    let call = |method: &str, uri: &str, key: Option<&str>| {
+        let mut req = match method {
+            "GET" => test::TestRequest::get(),
+            _ => test::TestRequest::post(),
+        }
+        .uri(uri);
+        if let Some(key) = key {
+            req = req.insert_header(("Authorization", format!("Bearer {key}")));
+        }
+        req
+    };
+    let status = |req: test::TestRequest| {
+        let app = &app;
+        async move { test::call_service(app, req.to_request()).await.status() }
+    };
+    use actix_web::http::StatusCode;
+
+    #[cfg(feature = "metrics")]
+    assert_eq!(
+        status(call("GET", "/metrics", None)).await,
+        StatusCode::UNAUTHORIZED
+    );
+    #[cfg(feature = "metrics")]
+    assert_eq!(
+        status(call("GET", "/metrics", Some("wrong-secret-000000"))).await,
+        StatusCode::UNAUTHORIZED
+    );
+    #[cfg(feature = "metrics")]
+    assert_eq!(
+        status(call("GET", "/metrics", Some(READ_ONLY))).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(call("GET", "/internal/cache/stats", Some(READ_ONLY))).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(call("POST", "/internal/cache/flush", Some(READ_ONLY))).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status(call("POST", "/internal/cache/flush", Some(SUPPORT))).await,
+        StatusCode::OK
+    );
+
+    let inject =
+        |key| call("POST", "/internal/payments", Some(key)).set_json(injection("tx", "ok"));
+    assert_eq!(status(inject(SUPPORT)).await, StatusCode::FORBIDDEN);
+    assert_eq!(status(inject(ADMIN)).await, StatusCode::CREATED);
+}
+
+#[actix_web::test]
+async fn signed_requests_authenticate_and_reject_replays() {
+    use actix_web::{http::StatusCode, middleware::from_fn};
+    use anon_ticket_domain::config::{InternalApiKey, InternalRole};
+    use anon_ticket_domain::services::signing::sign_request;
+
+    const SECRET: &str = "ops-signing-secret-0001";
+    let keys = [InternalApiKey::new("ops", InternalRole::Admin, SECRET)];
+    let state = with_cache(storage().await).with_internal_auth(InternalAuth::from_keys(&keys));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state))
+            .wrap(from_fn(verify_signed_request))
+            .configure(internal_routes),
+    )
+    .await;
+
+    let body = serde_json::to_vec(&injection("tx-signed", "verified")).unwrap();
+    let signed = |timestamp: i64, signature: String, body: Vec<u8>| {
+        test::TestRequest::post()
+            .uri("/internal/payments")
+            .insert_header(("content-type", "application/json"))
+            .insert_header(("x-anon-key-id", "ops"))
+            .insert_header(("x-anon-timestamp", timestamp.to_string()))
+            .insert_header(("x-anon-signature", signature))
+            .set_payload(body)
+            .to_request()
+    };
+    // Middleware rejections surface as service errors rather than responses.
+    let status_of = |result: Result<_, actix_web::Error>| match result {
+        Ok(resp) => actix_web::dev::ServiceResponse::status(&resp),
+        Err(err) => err.as_response_error().status_code(),
+    };
+    let now = chrono::Utc::now().timestamp();
+    let signature = sign_request(SECRET.as_bytes(), now, "POST", "/internal/payments", &body);
+
+    let resp = test::try_call_service(&app, signed(now, signature.clone(), body.clone())).await;
+    assert_eq!(status_of(resp), StatusCode::CREATED);
+
+    // Same signature again: replay.
+    let resp = test::try_call_service(&app, signed(now, signature.clone(), body.clone())).await;
+    assert_eq!(status_of(resp), StatusCode::UNAUTHORIZED);
+
+    // A fresh signature sent twice at once is still accepted only once.
+    let body_twice = serde_json::to_vec(&InjectPaymentRequest {
+        pid: "fedcba9876543210".into(),
+        ..injection("tx-twice", "verified")
+    })
+    .unwrap();
+    let twice = sign_request(
+        SECRET.as_bytes(),
+        now,
+        "POST",
+        "/internal/payments",
+        &body_twice,
+    );
+    let (first, second) = futures_util::future::join(
+        test::try_call_service(&app, signed(now, twice.clone(), body_twice.clone())),
+        test::try_call_service(&app, signed(now, twice, body_twice)),
+    )
+    .await;
+    let mut statuses = [status_of(first), status_of(second)];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::CREATED, StatusCode::UNAUTHORIZED]);
+
+    // Body no longer matches the signature.
+    let tampered = serde_json::to_vec(&injection("tx-other", "verified")).unwrap();
+    let resp = test::try_call_service(&app, signed(now, signature, tampered)).await;
+    assert_eq!(status_of(resp), StatusCode::UNAUTHORIZED);
+
+    // Outside the replay window.
+    let stale = now - 3_600;
+    let signature = sign_request(
+        SECRET.as_bytes(),
+        stale,
+        "POST",
+        "/internal/payments",
+        &body,
+    );
+    let resp = test::try_call_service(&app, signed(stale, signature, body)).await;
+    assert_eq!(status_of(resp), StatusCode::UNAUTHORIZED);
+}
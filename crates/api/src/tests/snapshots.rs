@@ -0,0 +1,563 @@
+//! Wire-format guards for every public DTO.
+//!
+//! Each type is snapshotted as JSON (reviewed via `cargo insta review`) and
+//! round-tripped through serde so renamed fields, changed casing, or dropped
+//! `Option`s show up as a snapshot diff instead of a silent client break.
+
+use chrono::{TimeZone, Utc};
+use insta::assert_json_snapshot;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::build_info::BuildInfo;
+use crate::handlers::{
+    cache::{CacheFlushResponse, CacheStatsResponse},
+    checkout::{CheckoutPresetResponse, CheckoutRequest, CheckoutResponse, CheckoutTierResponse},
+    config::{BloomParams, RuntimeConfigResponse},
+    fee::FeeEstimateResponse,
+    info::{InfoResponse, SubscriptionInfo},
+    payment::{
+        ClaimOverrideRequest, ClaimOverrideResponse, InjectPaymentRequest, PaymentResponse,
+        PaymentState,
+    },
+    quote::PaymentReconciliation,
+    redeem::{RedeemRequest, RedeemResponse},
+    token::{
+        MergeRequest, MergeResponse, RevokeRequest, SpendRequest, SpendResponse, SplitRequest,
+        SplitResponse, SuspendRequest, TokenBalanceResponse, TokenState, TokenStatusResponse,
+    },
+    ApiError, ErrorBody,
+};
+use crate::health::Readiness;
+use anon_ticket_domain::error::HasErrorCode;
+use anon_ticket_domain::model::PidFormatError;
+
+/// Serializes, deserializes, and re-serializes `value`, asserting the JSON is
+/// unchanged so `Serialize` and `Deserialize` stay symmetric.
+fn round_trip<T: Serialize + DeserializeOwned>(value: &T) {
+    let json = serde_json::to_value(value).expect("serializes");
+    let decoded: T = serde_json::from_value(json.clone()).expect("deserializes");
+    assert_eq!(serde_json::to_value(&decoded).expect("re-serializes"), json);
+}
+
+#[test]
+fn redeem_request_wire_format() {
+    let value = RedeemRequest {
+        pid: anon_ticket_testkit::DEFAULT_PID.into(),
+        client_secret: None,
+        passphrase: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn redeem_request_with_client_secret_wire_format() {
+    let value = RedeemRequest {
+        pid: anon_ticket_testkit::DEFAULT_PID.into(),
+        client_secret: Some("cd".repeat(32)),
+        passphrase: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn redeem_request_with_passphrase_wire_format() {
+    let value = RedeemRequest {
+        pid: anon_ticket_testkit::DEFAULT_PID.into(),
+        client_secret: None,
+        passphrase: Some("4821".into()),
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn checkout_request_wire_format() {
+    let value = CheckoutRequest {
+        preset: Some("pro".into()),
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn checkout_response_wire_format() {
+    let value = CheckoutResponse {
+        pid: anon_ticket_testkit::DEFAULT_PID.into(),
+        client_secret: "cd".repeat(32),
+        address: None,
+        preset: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn checkout_response_with_subaddress_wire_format() {
+    let value = CheckoutResponse {
+        pid: anon_ticket_testkit::DEFAULT_PID.into(),
+        client_secret: "cd".repeat(32),
+        address: Some("8BQ3pzN5cKXSvJ7AXkq3gLAN3yj2BBiK5k9hv7TmcoFf9DTFq1QLEqvX8WyvFbtPzEtBqwGJLbHnJ6HhvJd2nUFf7rqVybr".into()),
+        preset: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn checkout_response_with_preset_wire_format() {
+    let value = CheckoutResponse {
+        pid: anon_ticket_testkit::DEFAULT_PID.into(),
+        client_secret: "cd".repeat(32),
+        address: None,
+        preset: Some(CheckoutPresetResponse {
+            name: "pro".into(),
+            amount: 5_000_000_000,
+            donation: false,
+            tier: Some("pro".into()),
+            tiers: Vec::new(),
+            scope: Some("read".into()),
+            expiry_secs: Some(3600),
+            expires_at: Some(Utc.with_ymd_and_hms(2024, 1, 1, 1, 0, 0).unwrap()),
+            metadata: [("plan".to_string(), "starter".to_string())].into(),
+        }),
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn checkout_response_with_donation_preset_wire_format() {
+    let value = CheckoutResponse {
+        pid: anon_ticket_testkit::DEFAULT_PID.into(),
+        client_secret: "cd".repeat(32),
+        address: None,
+        preset: Some(CheckoutPresetResponse {
+            name: "tip".into(),
+            amount: 0,
+            donation: true,
+            tier: None,
+            tiers: vec![
+                CheckoutTierResponse {
+                    tier: "bronze".into(),
+                    min_amount: 1_000_000_000,
+                },
+                CheckoutTierResponse {
+                    tier: "silver".into(),
+                    min_amount: 5_000_000_000,
+                },
+            ],
+            scope: None,
+            expiry_secs: None,
+            expires_at: None,
+            metadata: Default::default(),
+        }),
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn fee_estimate_response_wire_format() {
+    let value = FeeEstimateResponse {
+        fee_per_byte: 20_000,
+        quantization_mask: 10_000,
+        priority_fees: vec![20_000, 80_000, 320_000, 4_000_000],
+        estimated_fee: 40_000_000,
+        min_payment_amount: Some(10_000_000_000),
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn readiness_wire_format() {
+    let value = Readiness {
+        ready: false,
+        draining: true,
+        storage: true,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+fn sample_build() -> BuildInfo {
+    BuildInfo {
+        version: "0.1.0".into(),
+        git_sha: "0123456789ab".into(),
+        built_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+        profile: "release".into(),
+        crates: [
+            ("anon_ticket_api", "0.1.0"),
+            ("anon_ticket_domain", "0.1.0"),
+        ]
+        .into_iter()
+        .map(|(name, version)| (name.into(), version.into()))
+        .collect(),
+    }
+}
+
+#[test]
+fn info_response_wire_format() {
+    let value = InfoResponse {
+        api_version: "v1".into(),
+        server_version: "0.1.0".into(),
+        build: sample_build(),
+        networks: vec!["mainnet".into()],
+        primary_address_fingerprint: Some("ab".repeat(32)),
+        addresses: Vec::new(),
+        min_payment_amount: Some(10_000_000_000),
+        min_confirmations: Some(10),
+        checkout_enabled: true,
+        subscription: Some(SubscriptionInfo {
+            period_secs: 2_592_000,
+            period_amount: 1_000_000_000,
+        }),
+        presets: vec![CheckoutPresetResponse {
+            name: "basic".into(),
+            amount: 1_000_000_000,
+            donation: false,
+            tier: Some("basic".into()),
+            tiers: Vec::new(),
+            scope: None,
+            expiry_secs: Some(3600),
+            expires_at: None,
+            metadata: Default::default(),
+        }],
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn redeem_response_wire_format() {
+    let value = RedeemResponse {
+        status: "success".into(),
+        service_token: "ab".repeat(32),
+        balance: 1_000_000_000_000,
+        tier: None,
+        reconciliation: None,
+        jwt: None,
+        signed_token: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn redeem_response_with_tier_wire_format() {
+    let value = RedeemResponse {
+        status: "success".into(),
+        service_token: "ab".repeat(32),
+        balance: 5_000_000_000,
+        tier: Some("silver".into()),
+        reconciliation: None,
+        jwt: None,
+        signed_token: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn redeem_response_with_reconciliation_wire_format() {
+    let value = RedeemResponse {
+        status: "success".into(),
+        service_token: "ab".repeat(32),
+        balance: 800,
+        tier: None,
+        reconciliation: Some(PaymentReconciliation {
+            state: "underpaid".into(),
+            expected_amount: 1_000,
+            received_amount: 800,
+            difference: -200,
+        }),
+        jwt: None,
+        signed_token: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn token_status_response_wire_format() {
+    let issued_at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let active = TokenStatusResponse {
+        status: TokenState::Active,
+        amount: 42,
+        issued_at,
+        revoked_at: None,
+        suspended_at: None,
+        suspended_until: None,
+        abuse_score: 0,
+        expires_at: None,
+        tier: Some("gold".into()),
+        quota: Some(1000),
+        review_reason: None,
+    };
+    let revoked = TokenStatusResponse {
+        status: TokenState::Revoked,
+        amount: 42,
+        issued_at,
+        revoked_at: Some(Utc.with_ymd_and_hms(2024, 1, 2, 12, 30, 0).unwrap()),
+        suspended_at: None,
+        suspended_until: None,
+        abuse_score: 7,
+        expires_at: None,
+        tier: None,
+        quota: None,
+        review_reason: None,
+    };
+    let expired = TokenStatusResponse {
+        status: TokenState::Expired,
+        amount: 42,
+        issued_at,
+        revoked_at: None,
+        suspended_at: None,
+        suspended_until: None,
+        abuse_score: 0,
+        expires_at: Some(Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap()),
+        tier: None,
+        quota: None,
+        review_reason: None,
+    };
+    let under_review = TokenStatusResponse {
+        status: TokenState::Active,
+        amount: 42,
+        issued_at,
+        revoked_at: None,
+        suspended_at: None,
+        suspended_until: None,
+        abuse_score: 0,
+        expires_at: None,
+        tier: None,
+        quota: None,
+        review_reason: Some("reorg at height 3100000".into()),
+    };
+    let suspended = TokenStatusResponse {
+        status: TokenState::Suspended,
+        amount: 42,
+        issued_at,
+        revoked_at: None,
+        suspended_at: Some(Utc.with_ymd_and_hms(2024, 1, 2, 12, 30, 0).unwrap()),
+        suspended_until: Some(Utc.with_ymd_and_hms(2024, 1, 3, 12, 30, 0).unwrap()),
+        abuse_score: 0,
+        expires_at: None,
+        tier: None,
+        quota: None,
+        review_reason: None,
+    };
+    round_trip(&active);
+    round_trip(&revoked);
+    round_trip(&expired);
+    round_trip(&under_review);
+    round_trip(&suspended);
+    assert_json_snapshot!("token_status_response_active", active);
+    assert_json_snapshot!("token_status_response_revoked", revoked);
+    assert_json_snapshot!("token_status_response_expired", expired);
+    assert_json_snapshot!("token_status_response_under_review", under_review);
+    assert_json_snapshot!("token_status_response_suspended", suspended);
+}
+
+#[test]
+fn token_balance_response_wire_format() {
+    let value = TokenBalanceResponse {
+        status: TokenState::Active,
+        balance: 42,
+        expires_at: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn revoke_request_wire_format() {
+    let value = RevokeRequest {
+        reason: Some("chargeback".into()),
+        abuse_score: Some(5),
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+    let empty: RevokeRequest = serde_json::from_str("{}").expect("fields are optional");
+    assert!(empty.reason.is_none() && empty.abuse_score.is_none());
+}
+
+#[test]
+fn suspend_request_wire_format() {
+    let value = SuspendRequest {
+        reason: Some("credential stuffing".into()),
+        until: Some(Utc.with_ymd_and_hms(2024, 1, 3, 12, 30, 0).unwrap()),
+        duration_secs: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+    let empty: SuspendRequest = serde_json::from_str("{}").expect("fields are optional");
+    assert!(empty.reason.is_none() && empty.until.is_none() && empty.duration_secs.is_none());
+}
+
+#[test]
+fn merge_request_wire_format() {
+    let value = MergeRequest {
+        tokens: vec!["ab".repeat(32), "cd".repeat(32)],
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn merge_response_wire_format() {
+    let value = MergeResponse {
+        service_token: "ef".repeat(32),
+        balance: 3_000_000_000,
+        expires_at: Some(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()),
+        merged: 2,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn split_request_wire_format() {
+    let value = SplitRequest {
+        amount: 250_000_000,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn split_response_wire_format() {
+    let value = SplitResponse {
+        service_token: "ef".repeat(32),
+        amount: 250_000_000,
+        remaining_balance: 750_000_000,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn spend_request_wire_format() {
+    let value = SpendRequest { amount: 1_000_000 };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn spend_response_wire_format() {
+    let value = SpendResponse {
+        spent: 1_000_000,
+        balance: 749_000_000,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn error_body_wire_format() {
+    let err = ApiError::InvalidPid(PidFormatError::WrongLength);
+    let value = ErrorBody {
+        code: err.code().as_str().into(),
+        error: err.to_string(),
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn cache_stats_response_wire_format() {
+    let value = CacheStatsResponse {
+        entries: 3,
+        capacity: 100_000,
+        ttl_secs: 60,
+        hits: 10,
+        misses: 2,
+        bloom_enabled: true,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn runtime_config_response_wire_format() {
+    let value = RuntimeConfigResponse {
+        build: sample_build(),
+        features: vec!["grpc".into()],
+        bloom: Some(BloomParams {
+            expected_items: 100_000,
+            false_positive_rate: 0.01,
+            num_bits: 958_528,
+            num_hashes: 7,
+        }),
+        api: [("DATABASE_URL", "postgres://app:<redacted>@db/anon")]
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect(),
+        monitor: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn cache_flush_response_wire_format() {
+    let value = CacheFlushResponse {
+        scope: "pid".into(),
+        pid: Some(anon_ticket_testkit::DEFAULT_PID.into()),
+        flushed: 1,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn inject_payment_request_wire_format() {
+    let value = InjectPaymentRequest {
+        pid: anon_ticket_testkit::DEFAULT_PID.into(),
+        txid: "tx-manual".into(),
+        amount: 42,
+        block_height: 100,
+        reason: "wallet rescan missed transfer".into(),
+        operator: Some("support".into()),
+        address_id: None,
+    };
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+fn sample_payment_response() -> PaymentResponse {
+    PaymentResponse {
+        pid: anon_ticket_testkit::DEFAULT_PID.into(),
+        txid: "tx-manual".into(),
+        amount: 42,
+        block_height: 100,
+        status: PaymentState::Claimed,
+        detected_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+        claimed_at: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 5, 0).unwrap()),
+        address_id: Some("primary".into()),
+    }
+}
+
+#[test]
+fn payment_response_wire_format() {
+    let value = sample_payment_response();
+    round_trip(&value);
+    assert_json_snapshot!(value);
+}
+
+#[test]
+fn claim_override_wire_format() {
+    let request = ClaimOverrideRequest {
+        reason: "support ticket 42".into(),
+        operator: Some("support".into()),
+    };
+    let response = ClaimOverrideResponse {
+        payment: sample_payment_response(),
+        service_token: Some("ab".repeat(32)),
+    };
+    round_trip(&request);
+    round_trip(&response);
+    assert_json_snapshot!("claim_override_request", request);
+    assert_json_snapshot!("claim_override_response", response);
+}
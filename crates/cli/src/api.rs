@@ -0,0 +1,70 @@
+//! Minimal client for the internal listener. Operations that change payments
+//! or tokens go through it rather than the database so they are audited and
+//! the API's caches stay coherent.
+
+use std::env;
+use std::time::Duration;
+
+use reqwest::{header, Client, RequestBuilder};
+use serde::Serialize;
+
+use crate::AdminError;
+
+pub const API_URL_VAR: &str = "ANON_TICKET_ADMIN_API_URL";
+pub const API_KEY_VAR: &str = "ANON_TICKET_ADMIN_KEY";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct InternalApi {
+    client: Client,
+    base_url: String,
+    key: Option<String>,
+}
+
+impl InternalApi {
+    /// Reads the listener URL and optional key; without a key the listener
+    /// must be running without `API_INTERNAL_KEYS`.
+    pub fn from_env() -> Result<Self, AdminError> {
+        let base_url = env::var(API_URL_VAR)
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .ok_or(AdminError::MissingEnv(API_URL_VAR))?;
+        Ok(Self {
+            client: Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+            key: env::var(API_KEY_VAR).ok().filter(|key| !key.is_empty()),
+        })
+    }
+
+    pub async fn get(&self, path: &str, query: &[(&str, String)]) -> Result<String, AdminError> {
+        self.send(self.client.get(self.url(path)).query(query))
+            .await
+    }
+
+    pub async fn post(&self, path: &str, body: &impl Serialize) -> Result<String, AdminError> {
+        self.send(self.client.post(self.url(path)).json(body)).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url)
+    }
+
+    /// Body of a successful response; any other status is an error carrying
+    /// the API's own message.
+    async fn send(&self, request: RequestBuilder) -> Result<String, AdminError> {
+        let request = match &self.key {
+            Some(key) => request.header(header::AUTHORIZATION, format!("Bearer {key}")),
+            None => request,
+        };
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(AdminError::Api {
+                status: status.as_u16(),
+                body,
+            });
+        }
+        Ok(body)
+    }
+}
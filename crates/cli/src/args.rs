@@ -0,0 +1,245 @@
+//! Command-line parsing. Kept by hand like the other operator binaries: the
+//! grammar is a handful of subcommands with a few `--flag value` options.
+
+use std::collections::BTreeMap;
+
+pub const USAGE: &str = "\
+Usage: anon-ticket-admin <command> [options]
+
+Payments (internal API):
+  payments list [--status unclaimed|claimed|expired|reorged|refunded] [--after <pid>] [--limit <n>]
+  payments claim <pid> --reason <text> [--operator <name>]
+  payments unclaim <pid> --reason <text> [--operator <name>]
+Payments (database):
+  payments expire                  expire lapsed quotes now
+Tokens (internal API):
+  tokens revoke <token> [--reason <text>] [--abuse-score <n>]
+Monitor (database):
+  rescan <height> [--wallet <name>]
+Other:
+  metrics                          print the internal listener's /metrics
+  address <primary_address> [--count <n>]
+
+Environment:
+  ANON_TICKET_ADMIN_API_URL   internal listener, e.g. http://127.0.0.1:9090
+  ANON_TICKET_ADMIN_KEY       internal API key secret, sent as a bearer token
+  DATABASE_URL                database for `payments expire` and `rescan`";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    ListPayments {
+        status: Option<String>,
+        after: Option<String>,
+        limit: Option<u64>,
+    },
+    ClaimPayment {
+        pid: String,
+        reason: String,
+        operator: Option<String>,
+    },
+    UnclaimPayment {
+        pid: String,
+        reason: String,
+        operator: Option<String>,
+    },
+    ExpireQuotes,
+    RevokeToken {
+        token: String,
+        reason: Option<String>,
+        abuse_score: Option<i16>,
+    },
+    Rescan {
+        height: u64,
+        wallet: Option<String>,
+    },
+    Metrics,
+    Address {
+        primary_address: String,
+        count: u32,
+    },
+}
+
+/// Positional arguments and `--flag value` options of one invocation.
+struct Parsed {
+    positional: Vec<String>,
+    flags: BTreeMap<String, String>,
+}
+
+impl Parsed {
+    fn new(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Parsed {
+            positional: Vec::new(),
+            flags: BTreeMap::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg);
+                continue;
+            };
+            let value = args
+                .next()
+                .ok_or_else(|| format!("--{flag} needs a value"))?;
+            if parsed.flags.insert(flag.to_string(), value).is_some() {
+                return Err(format!("--{flag} given more than once"));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&mut self, name: &str) -> Option<String> {
+        self.flags.remove(name)
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, name: &str) -> Result<Option<T>, String> {
+        self.flag(name)
+            .map(|raw| {
+                raw.parse()
+                    .map_err(|_| format!("--{name} must be a number, got `{raw}`"))
+            })
+            .transpose()
+    }
+
+    /// Fails on anything the command did not consume.
+    fn finish(self, command: Command, positional: usize) -> Result<Command, String> {
+        if let Some(extra) = self.positional.get(positional) {
+            return Err(format!("unexpected argument `{extra}`"));
+        }
+        if let Some(flag) = self.flags.keys().next() {
+            return Err(format!("unknown option --{flag}"));
+        }
+        Ok(command)
+    }
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut parsed = Parsed::new(args)?;
+    let positional = parsed.positional.clone();
+    let words: Vec<&str> = positional.iter().map(String::as_str).collect();
+    let (command, consumed) = match words.as_slice() {
+        ["payments", "list", ..] => (
+            Command::ListPayments {
+                status: parsed.flag("status"),
+                after: parsed.flag("after"),
+                limit: parsed.number("limit")?,
+            },
+            2,
+        ),
+        ["payments", action @ ("claim" | "unclaim"), pid, ..] => {
+            let pid = pid.to_string();
+            let claim = *action == "claim";
+            let reason = parsed
+                .flag("reason")
+                .ok_or_else(|| format!("payments {action} needs --reason"))?;
+            let operator = parsed.flag("operator");
+            let command = if claim {
+                Command::ClaimPayment {
+                    pid,
+                    reason,
+                    operator,
+                }
+            } else {
+                Command::UnclaimPayment {
+                    pid,
+                    reason,
+                    operator,
+                }
+            };
+            (command, 3)
+        }
+        ["payments", "expire", ..] => (Command::ExpireQuotes, 2),
+        ["tokens", "revoke", token, ..] => (
+            Command::RevokeToken {
+                token: token.to_string(),
+                reason: parsed.flag("reason"),
+                abuse_score: parsed.number("abuse-score")?,
+            },
+            3,
+        ),
+        ["rescan", height, ..] => (
+            Command::Rescan {
+                height: height
+                    .parse()
+                    .map_err(|_| format!("rescan height must be a number, got `{height}`"))?,
+                wallet: parsed.flag("wallet"),
+            },
+            2,
+        ),
+        ["metrics", ..] => (Command::Metrics, 1),
+        ["address", primary_address, ..] => (
+            Command::Address {
+                primary_address: primary_address.to_string(),
+                count: parsed.number("count")?.unwrap_or(1),
+            },
+            2,
+        ),
+        [] => return Err("missing command".to_string()),
+        _ => return Err(format!("unknown command `{}`", positional.join(" "))),
+    };
+    parsed.finish(command, consumed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_line(line: &str) -> Result<Command, String> {
+        parse(line.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn parses_subcommands_and_options() {
+        assert_eq!(
+            parse_line("payments claim 0123456789abcdef --reason stuck --operator alice"),
+            Ok(Command::ClaimPayment {
+                pid: "0123456789abcdef".to_string(),
+                reason: "stuck".to_string(),
+                operator: Some("alice".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_line("payments list --status claimed --limit 10"),
+            Ok(Command::ListPayments {
+                status: Some("claimed".to_string()),
+                after: None,
+                limit: Some(10),
+            })
+        );
+        assert_eq!(
+            parse_line("rescan 3100000 --wallet cold"),
+            Ok(Command::Rescan {
+                height: 3_100_000,
+                wallet: Some("cold".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_line("address 4abc"),
+            Ok(Command::Address {
+                primary_address: "4abc".to_string(),
+                count: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_what_the_command_does_not_take() {
+        assert_eq!(
+            parse_line("payments unclaim 0123456789abcdef"),
+            Err("payments unclaim needs --reason".to_string())
+        );
+        assert_eq!(
+            parse_line("metrics --verbose yes"),
+            Err("unknown option --verbose".to_string())
+        );
+        assert_eq!(
+            parse_line("payments expire now"),
+            Err("unexpected argument `now`".to_string())
+        );
+        assert_eq!(
+            parse_line("rescan soon"),
+            Err("rescan height must be a number, got `soon`".to_string())
+        );
+        assert!(parse_line("").is_err());
+        assert!(parse_line("tokens list").is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! `anon-ticket-admin`: operator tasks against a running deployment.
+//!
+//! Payment and token changes go through the internal API so they are
+//! authorised, audited and reflected in the API's caches. Chores the API
+//! does not expose (quote expiry, monitor rescans) use the database
+//! directly, and address generation needs neither.
+
+mod api;
+mod args;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+use anon_ticket_domain::config::{load_env_files, ConfigError, PRIMARY_WALLET};
+use anon_ticket_domain::integrated_address::build_integrated_address;
+use anon_ticket_domain::model::PaymentId;
+use anon_ticket_domain::storage::{QuoteStore, StorageError};
+use anon_ticket_storage::SeaOrmStorage;
+use chrono::Utc;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::api::InternalApi;
+use crate::args::{Command, USAGE};
+
+#[derive(Debug, Error)]
+pub enum AdminError {
+    #[error("{0}")]
+    Usage(String),
+    #[error("{0} is not set")]
+    MissingEnv(&'static str),
+    #[error("config error: {0}")]
+    Config(#[from] ConfigError),
+    #[error("storage error: {0}")]
+    Storage(#[from] StorageError),
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("internal API answered {status}: {body}")]
+    Api { status: u16, body: String },
+    #[error("{0}")]
+    Address(String),
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return;
+    }
+    if let Err(err) = run(args).await {
+        eprintln!("error: {err}");
+        if matches!(err, AdminError::Usage(_)) {
+            eprintln!("\n{USAGE}");
+            process::exit(2);
+        }
+        process::exit(1);
+    }
+}
+
+async fn run(args: Vec<String>) -> Result<(), AdminError> {
+    let command = args::parse(args).map_err(AdminError::Usage)?;
+    load_env_files(Path::new("."))?;
+    match command {
+        Command::ListPayments {
+            status,
+            after,
+            limit,
+        } => {
+            let query: Vec<(&str, String)> = [
+                ("status", status),
+                ("after", after),
+                ("limit", limit.map(|limit| limit.to_string())),
+            ]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect();
+            let body = InternalApi::from_env()?
+                .get("/internal/v1/payments", &query)
+                .await?;
+            print_body(&body);
+        }
+        Command::ClaimPayment {
+            pid,
+            reason,
+            operator,
+        } => {
+            let body = InternalApi::from_env()?
+                .post(
+                    &format!("/internal/payments/{pid}/claim"),
+                    &json!({ "reason": reason, "operator": operator }),
+                )
+                .await?;
+            print_body(&body);
+        }
+        Command::UnclaimPayment {
+            pid,
+            reason,
+            operator,
+        } => {
+            let body = InternalApi::from_env()?
+                .post(
+                    &format!("/internal/payments/{pid}/unclaim"),
+                    &json!({ "reason": reason, "operator": operator }),
+                )
+                .await?;
+            print_body(&body);
+        }
+        Command::RevokeToken {
+            token,
+            reason,
+            abuse_score,
+        } => {
+            let body = InternalApi::from_env()?
+                .post(
+                    &format!("/api/v1/token/{token}/revoke"),
+                    &json!({ "reason": reason, "abuse_score": abuse_score }),
+                )
+                .await?;
+            print_body(&body);
+        }
+        Command::Metrics => {
+            let body = InternalApi::from_env()?.get("/metrics", &[]).await?;
+            print!("{body}");
+        }
+        Command::ExpireQuotes => {
+            let expired = open_storage().await?.expire_quotes(Utc::now()).await?;
+            println!("expired quotes: {expired}");
+        }
+        Command::Rescan { height, wallet } => {
+            let wallet = wallet.as_deref().unwrap_or(PRIMARY_WALLET);
+            match open_storage().await?.rewind_cursor(wallet, height).await? {
+                Some(previous) if previous <= height => {
+                    println!("{wallet}: cursor already at {previous}, nothing to rescan");
+                }
+                previous => {
+                    let previous =
+                        previous.map_or_else(|| "unset".to_string(), |height| height.to_string());
+                    println!(
+                        "{wallet}: cursor moved from {previous} to {height}; \
+                         the monitor rescans from there on its next poll"
+                    );
+                }
+            }
+        }
+        Command::Address {
+            primary_address,
+            count,
+        } => {
+            for _ in 0..count {
+                let pid = PaymentId::generate()
+                    .map_err(|err| AdminError::Address(format!("payment id: {err}")))?;
+                let address = build_integrated_address(&primary_address, &pid)
+                    .map_err(|err| AdminError::Address(err.to_string()))?;
+                println!("{pid} {address}");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn open_storage() -> Result<SeaOrmStorage, AdminError> {
+    let url = env::var("DATABASE_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .ok_or(AdminError::MissingEnv("DATABASE_URL"))?;
+    Ok(SeaOrmStorage::connect(url.trim()).await?)
+}
+
+/// Pretty-prints JSON responses; anything else is printed as received.
+fn print_body(body: &str) {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(value) => println!(
+            "{}",
+            serde_json::to_string_pretty(&value).unwrap_or_else(|_| body.to_string())
+        ),
+        Err(_) => println!("{body}"),
+    }
+}
@@ -0,0 +1,171 @@
+//! Validation rules for payment IDs and service tokens, shared by the servers
+//! and by embedded verifiers.
+//!
+//! The crate is `#![no_std]` and needs only `alloc` (for [`encode_hex`]), so
+//! firmware can check PIDs and tokens exactly as the API does. Everything
+//! else lives in `anon_ticket_domain`, which re-exports these items from its
+//! `model` module.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use core::fmt;
+
+/// Required length (in hex characters) for externally supplied payment IDs.
+pub const PID_LENGTH: usize = 16;
+
+/// Required length (in hex characters) for service tokens.
+pub const TOKEN_LENGTH: usize = 64;
+
+/// Errors emitted when user-supplied payment IDs fail validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PidFormatError {
+    WrongLength,
+    NonHex,
+}
+
+impl fmt::Display for PidFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongLength => {
+                write!(f, "payment id must be exactly {PID_LENGTH} hex characters")
+            }
+            Self::NonHex => f.write_str("payment id contains non-hex characters"),
+        }
+    }
+}
+
+impl core::error::Error for PidFormatError {}
+
+/// Errors emitted when user-supplied service tokens fail validation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TokenFormatError {
+    WrongLength,
+    NonHex,
+}
+
+impl fmt::Display for TokenFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongLength => {
+                write!(
+                    f,
+                    "service token must be exactly {TOKEN_LENGTH} hex characters"
+                )
+            }
+            Self::NonHex => f.write_str("service token contains non-hex characters"),
+        }
+    }
+}
+
+impl core::error::Error for TokenFormatError {}
+
+/// Validates that the supplied PID matches the 16 hex-character contract.
+pub fn validate_pid(pid: &str) -> Result<(), PidFormatError> {
+    match check_hex(pid, PID_LENGTH) {
+        Ok(()) => Ok(()),
+        Err(HexError::WrongLength) => Err(PidFormatError::WrongLength),
+        Err(HexError::NonHex) => Err(PidFormatError::NonHex),
+    }
+}
+
+/// Validates that the supplied token matches the 64 hex-character contract.
+pub fn validate_token(token: &str) -> Result<(), TokenFormatError> {
+    match check_hex(token, TOKEN_LENGTH) {
+        Ok(()) => Ok(()),
+        Err(HexError::WrongLength) => Err(TokenFormatError::WrongLength),
+        Err(HexError::NonHex) => Err(TokenFormatError::NonHex),
+    }
+}
+
+/// Validates and decodes a PID. Either hex case is accepted.
+pub fn decode_pid(pid: &str) -> Result<[u8; PID_LENGTH / 2], PidFormatError> {
+    validate_pid(pid)?;
+    Ok(decode_checked(pid))
+}
+
+/// Validates and decodes a service token. Either hex case is accepted.
+pub fn decode_token(token: &str) -> Result<[u8; TOKEN_LENGTH / 2], TokenFormatError> {
+    validate_token(token)?;
+    Ok(decode_checked(token))
+}
+
+/// Lowercase hex, the canonical form of PIDs and tokens.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push(char::from(DIGITS[usize::from(byte >> 4)]));
+        out.push(char::from(DIGITS[usize::from(byte & 0x0f)]));
+    }
+    out
+}
+
+enum HexError {
+    WrongLength,
+    NonHex,
+}
+
+fn check_hex(input: &str, len: usize) -> Result<(), HexError> {
+    if input.len() != len {
+        return Err(HexError::WrongLength);
+    }
+    if !input.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(HexError::NonHex);
+    }
+    Ok(())
+}
+
+/// Decodes input already accepted by [`check_hex`] with length `2 * N`.
+fn decode_checked<const N: usize>(input: &str) -> [u8; N] {
+    let mut out = [0u8; N];
+    for (byte, pair) in out.iter_mut().zip(input.as_bytes().chunks_exact(2)) {
+        *byte = (nibble(pair[0]) << 4) | nibble(pair[1]);
+    }
+    out
+}
+
+fn nibble(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        _ => digit - b'A' + 10,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn pids_decode_in_either_case_and_encode_lowercase() {
+        let bytes = decode_pid("0123456789ABCDEF").unwrap();
+        assert_eq!(bytes, [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+        assert_eq!(encode_hex(&bytes), "0123456789abcdef");
+    }
+
+    #[test]
+    fn malformed_input_is_classified() {
+        assert_eq!(decode_pid("abc"), Err(PidFormatError::WrongLength));
+        assert_eq!(decode_pid("0123456789abcdeg"), Err(PidFormatError::NonHex));
+        // Length is measured in bytes, so multibyte characters cannot pad it.
+        assert_eq!(validate_pid("é123456789abcde"), Err(PidFormatError::NonHex));
+        assert_eq!(
+            validate_token(&"f".repeat(63)),
+            Err(TokenFormatError::WrongLength)
+        );
+        assert_eq!(
+            decode_token(&"0".repeat(TOKEN_LENGTH)),
+            Ok([0u8; TOKEN_LENGTH / 2])
+        );
+        assert_eq!(
+            TokenFormatError::NonHex.to_string(),
+            "service token contains non-hex characters"
+        );
+    }
+}
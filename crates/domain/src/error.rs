@@ -0,0 +1,206 @@
+//! Stable error taxonomy shared by the API, monitor, and client SDKs.
+//!
+//! Every failure surfaced to operators or clients maps onto one `ErrorCode`.
+//! The snake_case string form is part of the wire contract (API bodies, log
+//! fields, metric labels) and must never change once released; add new
+//! variants instead of renaming existing ones.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "runtime")]
+use crate::config::ConfigError;
+use crate::model::{PidFormatError, TokenFormatError};
+#[cfg(feature = "runtime")]
+use crate::services::telemetry::TelemetryError;
+#[cfg(feature = "runtime")]
+use crate::storage::StorageError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    InvalidPid,
+    InvalidToken,
+    InvalidRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    RateLimited,
+    StorageUnavailable,
+    RpcUnavailable,
+    ConfigInvalid,
+    TelemetryFailure,
+    Internal,
+}
+
+/// Canonical gRPC status codes used by [`ErrorCode::grpc_code`].
+pub mod grpc {
+    pub const INVALID_ARGUMENT: i32 = 3;
+    pub const NOT_FOUND: i32 = 5;
+    pub const ALREADY_EXISTS: i32 = 6;
+    pub const PERMISSION_DENIED: i32 = 7;
+    pub const RESOURCE_EXHAUSTED: i32 = 8;
+    pub const FAILED_PRECONDITION: i32 = 9;
+    pub const INTERNAL: i32 = 13;
+    pub const UNAVAILABLE: i32 = 14;
+    pub const UNAUTHENTICATED: i32 = 16;
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 13] = [
+        ErrorCode::InvalidPid,
+        ErrorCode::InvalidToken,
+        ErrorCode::InvalidRequest,
+        ErrorCode::Unauthorized,
+        ErrorCode::Forbidden,
+        ErrorCode::NotFound,
+        ErrorCode::Conflict,
+        ErrorCode::RateLimited,
+        ErrorCode::StorageUnavailable,
+        ErrorCode::RpcUnavailable,
+        ErrorCode::ConfigInvalid,
+        ErrorCode::TelemetryFailure,
+        ErrorCode::Internal,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidPid => "invalid_pid",
+            ErrorCode::InvalidToken => "invalid_token",
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::StorageUnavailable => "storage_unavailable",
+            ErrorCode::RpcUnavailable => "rpc_unavailable",
+            ErrorCode::ConfigInvalid => "config_invalid",
+            ErrorCode::TelemetryFailure => "telemetry_failure",
+            ErrorCode::Internal => "internal",
+        }
+    }
+
+    pub const fn http_status(self) -> u16 {
+        match self {
+            ErrorCode::InvalidPid | ErrorCode::InvalidToken | ErrorCode::InvalidRequest => 400,
+            ErrorCode::Unauthorized => 401,
+            ErrorCode::Forbidden => 403,
+            ErrorCode::NotFound => 404,
+            ErrorCode::Conflict => 409,
+            ErrorCode::RateLimited => 429,
+            ErrorCode::StorageUnavailable | ErrorCode::RpcUnavailable => 503,
+            ErrorCode::ConfigInvalid | ErrorCode::TelemetryFailure | ErrorCode::Internal => 500,
+        }
+    }
+
+    pub const fn grpc_code(self) -> i32 {
+        match self {
+            ErrorCode::InvalidPid | ErrorCode::InvalidToken | ErrorCode::InvalidRequest => {
+                grpc::INVALID_ARGUMENT
+            }
+            ErrorCode::Unauthorized => grpc::UNAUTHENTICATED,
+            ErrorCode::Forbidden => grpc::PERMISSION_DENIED,
+            ErrorCode::NotFound => grpc::NOT_FOUND,
+            ErrorCode::Conflict => grpc::ALREADY_EXISTS,
+            ErrorCode::RateLimited => grpc::RESOURCE_EXHAUSTED,
+            ErrorCode::StorageUnavailable | ErrorCode::RpcUnavailable => grpc::UNAVAILABLE,
+            ErrorCode::ConfigInvalid => grpc::FAILED_PRECONDITION,
+            ErrorCode::TelemetryFailure | ErrorCode::Internal => grpc::INTERNAL,
+        }
+    }
+
+    /// Whether a client may reasonably retry the same request later.
+    pub const fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::RateLimited | ErrorCode::StorageUnavailable | ErrorCode::RpcUnavailable
+        )
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown error code `{0}`")]
+pub struct UnknownErrorCode(pub String);
+
+impl FromStr for ErrorCode {
+    type Err = UnknownErrorCode;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ErrorCode::ALL
+            .into_iter()
+            .find(|code| code.as_str() == value)
+            .ok_or_else(|| UnknownErrorCode(value.to_string()))
+    }
+}
+
+/// Implemented by every error type that crosses a crate boundary.
+pub trait HasErrorCode {
+    fn code(&self) -> ErrorCode;
+}
+
+#[cfg(feature = "runtime")]
+impl HasErrorCode for StorageError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            StorageError::Database(_) => ErrorCode::StorageUnavailable,
+            StorageError::ReadOnly => ErrorCode::Forbidden,
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl HasErrorCode for ConfigError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::ConfigInvalid
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl HasErrorCode for TelemetryError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::TelemetryFailure
+    }
+}
+
+impl HasErrorCode for PidFormatError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidPid
+    }
+}
+
+impl HasErrorCode for TokenFormatError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidToken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_codes_round_trip() {
+        for code in ErrorCode::ALL {
+            assert_eq!(code.as_str().parse::<ErrorCode>(), Ok(code));
+        }
+        assert!("bogus".parse::<ErrorCode>().is_err());
+    }
+
+    #[test]
+    fn transport_mappings_are_stable() {
+        assert_eq!(ErrorCode::InvalidPid.http_status(), 400);
+        assert_eq!(ErrorCode::NotFound.grpc_code(), grpc::NOT_FOUND);
+        assert_eq!(ErrorCode::StorageUnavailable.http_status(), 503);
+        assert!(ErrorCode::RpcUnavailable.is_retryable());
+        assert!(!ErrorCode::InvalidToken.is_retryable());
+        #[cfg(feature = "runtime")]
+        assert_eq!(StorageError::ReadOnly.code().http_status(), 403);
+    }
+}
@@ -0,0 +1,99 @@
+//! Registry of the JSON schemas for outbound events.
+//!
+//! Every webhook body and event-stream frame carries a `schema` field naming
+//! the schema it follows, such as `payment.confirmed.v1`. A change that a
+//! strict consumer could trip over (a removed or retyped field, a new
+//! required one) ships as a new version next to the old, so consumers can
+//! upgrade on their own schedule; the schema files live in
+//! `crates/domain/schemas/events/`.
+
+/// One version of one event's payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventSchema {
+    /// Event name, e.g. `payment.confirmed`.
+    pub event: &'static str,
+    pub version: u32,
+    /// `<event>.v<version>`, the value of the payload's `schema` field.
+    pub id: &'static str,
+    /// JSON Schema (draft-07) document.
+    pub schema: &'static str,
+}
+
+pub const PAYMENT_DETECTED_V1: &str = "payment.detected.v1";
+pub const PAYMENT_CONFIRMED_V1: &str = "payment.confirmed.v1";
+pub const PAYMENT_CLAIMABLE_V1: &str = "payment.claimable.v1";
+pub const TOKEN_REVOKED_V1: &str = "token.revoked.v1";
+pub const MONITOR_ALERT_V1: &str = "monitor.alert.v1";
+
+/// Every published schema, oldest version of each event first.
+pub const EVENT_SCHEMAS: &[EventSchema] = &[
+    EventSchema {
+        event: "payment.detected",
+        version: 1,
+        id: PAYMENT_DETECTED_V1,
+        schema: include_str!("../schemas/events/payment.detected.v1.json"),
+    },
+    EventSchema {
+        event: "payment.confirmed",
+        version: 1,
+        id: PAYMENT_CONFIRMED_V1,
+        schema: include_str!("../schemas/events/payment.confirmed.v1.json"),
+    },
+    EventSchema {
+        event: "payment.claimable",
+        version: 1,
+        id: PAYMENT_CLAIMABLE_V1,
+        schema: include_str!("../schemas/events/payment.claimable.v1.json"),
+    },
+    EventSchema {
+        event: "token.revoked",
+        version: 1,
+        id: TOKEN_REVOKED_V1,
+        schema: include_str!("../schemas/events/token.revoked.v1.json"),
+    },
+    EventSchema {
+        event: "monitor.alert",
+        version: 1,
+        id: MONITOR_ALERT_V1,
+        schema: include_str!("../schemas/events/monitor.alert.v1.json"),
+    },
+];
+
+/// Schema named by a payload's `schema` field.
+pub fn event_schema(id: &str) -> Option<&'static EventSchema> {
+    EVENT_SCHEMAS.iter().find(|schema| schema.id == id)
+}
+
+/// Newest version published for `event`.
+pub fn latest_event_schema(event: &str) -> Option<&'static EventSchema> {
+    EVENT_SCHEMAS
+        .iter()
+        .filter(|schema| schema.event == event)
+        .max_by_key(|schema| schema.version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_follow_the_event_and_version() {
+        for schema in EVENT_SCHEMAS {
+            assert_eq!(schema.id, format!("{}.v{}", schema.event, schema.version));
+            assert!(
+                schema.schema.contains(&format!(
+                    "\"$id\": \"urn:anon-ticket:events:{}\"",
+                    schema.id
+                )),
+                "{} names another schema",
+                schema.id
+            );
+            assert_eq!(event_schema(schema.id), Some(schema));
+        }
+        assert_eq!(
+            latest_event_schema("token.revoked").map(|schema| schema.id),
+            Some(TOKEN_REVOKED_V1)
+        );
+        assert_eq!(event_schema("token.revoked.v0"), None);
+    }
+}
@@ -1,14 +1,17 @@
 use std::str::FromStr;
 
+use hex::encode as hex_encode;
 use monero::{
     util::address::{AddressType, PaymentId as MoneroPaymentId},
-    Address,
+    Address, Network,
 };
+use sha3::{Digest, Sha3_256};
 use thiserror::Error;
 
 use crate::model::{PaymentId, PidFormatError};
 
 #[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum IntegratedAddressError {
     #[error("invalid primary address: {0}")]
     InvalidPrimary(String),
@@ -20,6 +23,10 @@ pub enum IntegratedAddressError {
     MissingPaymentId,
     #[error("embedded payment id is invalid: {0}")]
     InvalidPaymentId(String),
+    #[error("no address `{0}` in the address book")]
+    UnknownAddress(String),
+    #[error("invalid address: {0}")]
+    InvalidAddress(String),
 }
 
 /// Build an integrated address from a standard primary address and a validated payment id.
@@ -43,6 +50,98 @@ pub fn build_integrated_address(
     Ok(integrated.to_string())
 }
 
+/// Network of a standard primary address (`mainnet`, `stagenet`, or
+/// `testnet`).
+pub fn primary_address_network(
+    primary_address: &str,
+) -> Result<&'static str, IntegratedAddressError> {
+    let address = Address::from_str(primary_address)
+        .map_err(|err| IntegratedAddressError::InvalidPrimary(err.to_string()))?;
+    if !matches!(address.addr_type, AddressType::Standard) {
+        return Err(IntegratedAddressError::NonStandardPrimary);
+    }
+    Ok(network_name(address.network))
+}
+
+/// Network of any address a wallet can send to: standard, subaddress or
+/// integrated.
+pub fn address_network(address: &str) -> Result<&'static str, IntegratedAddressError> {
+    let address = Address::from_str(address)
+        .map_err(|err| IntegratedAddressError::InvalidAddress(err.to_string()))?;
+    Ok(network_name(address.network))
+}
+
+fn network_name(network: Network) -> &'static str {
+    match network {
+        Network::Mainnet => "mainnet",
+        Network::Stagenet => "stagenet",
+        Network::Testnet => "testnet",
+    }
+}
+
+/// Hex SHA3-256 of the primary address, so clients can check the address a
+/// wallet shows them against the one the service watches without it being
+/// published.
+pub fn primary_address_fingerprint(primary_address: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"anon-ticket/primary-address|");
+    hasher.update(primary_address.as_bytes());
+    hex_encode(hasher.finalize())
+}
+
+/// Standard addresses payments may be sent to, by id. Ids are the wallet
+/// names the monitor polls, with `"primary"` (`config::PRIMARY_WALLET`) for
+/// `API_PRIMARY_ADDRESS`, so the `address_id` recorded on a quote or payment
+/// names both the address and the wallet that watches it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressBook {
+    entries: Vec<(String, String)>,
+}
+
+impl AddressBook {
+    /// Entries in lookup order; later duplicates of an id are unreachable, so
+    /// config validation rejects them.
+    pub fn new(entries: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(entry_id, _)| entry_id == id)
+            .map(|(_, address)| address.as_str())
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// `(id, address)` pairs, primary first.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(id, address)| (id.as_str(), address.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Integrated address for `payment_id` on the address with id `id`.
+    pub fn integrated_address(
+        &self,
+        id: &str,
+        payment_id: &PaymentId,
+    ) -> Result<String, IntegratedAddressError> {
+        let primary = self
+            .get(id)
+            .ok_or_else(|| IntegratedAddressError::UnknownAddress(id.to_string()))?;
+        build_integrated_address(primary, payment_id)
+    }
+}
+
 /// Parse an integrated address, extracting both the embedded payment id and the underlying
 /// standard address.
 pub fn decode_integrated_address(
@@ -70,6 +169,9 @@ pub fn decode_integrated_address(
 mod tests {
     use super::*;
 
+    // `config::PRIMARY_WALLET`, which needs `runtime`.
+    const PRIMARY_WALLET: &str = "primary";
+
     const PRIMARY_MAINNET: &str =
         "4ADT1BtbxqEWeMKp9GgPr2NeyJXXtNxvoDawpyA4WpzFcGcoHUvXeijE66DNfohE9r1bQYaBiQjEtKE7CtkTdLwiDznFzra";
     const SAMPLE_PID: &str = "0123456789abcdef";
@@ -95,5 +197,32 @@ mod tests {
 
         let err = build_integrated_address(&integrated, &pid).unwrap_err();
         assert_eq!(err, IntegratedAddressError::NonStandardPrimary);
+        assert_eq!(
+            primary_address_network(&integrated),
+            Err(IntegratedAddressError::NonStandardPrimary)
+        );
+        assert_eq!(primary_address_network(PRIMARY_MAINNET), Ok("mainnet"));
+        assert_eq!(address_network(&integrated), Ok("mainnet"));
+        assert!(matches!(
+            address_network("not-an-address"),
+            Err(IntegratedAddressError::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn address_book_builds_on_the_selected_address() {
+        let pid = PaymentId::parse(SAMPLE_PID).expect("valid pid");
+        let book = AddressBook::new([("campaign".to_string(), PRIMARY_MAINNET.to_string())]);
+
+        let integrated = book.integrated_address("campaign", &pid).unwrap();
+        let (standard, recovered) = decode_integrated_address(&integrated).unwrap();
+        assert_eq!(standard, PRIMARY_MAINNET);
+        assert_eq!(recovered, pid);
+        assert_eq!(
+            book.integrated_address(PRIMARY_WALLET, &pid),
+            Err(IntegratedAddressError::UnknownAddress(
+                PRIMARY_WALLET.into()
+            ))
+        );
     }
 }
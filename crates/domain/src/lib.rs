@@ -1,19 +1,59 @@
 //! Domain-level building blocks shared across API and monitor crates.
 //!
 //! The crate now exposes cohesive modules for configuration (`config`),
-//! data models (`model`), reusable services such as telemetry (`services`),
+//! the shared error taxonomy (`error`), data models (`model`), outbound event schemas (`events`), reusable services such as telemetry (`services`),
 //! and storage contracts (`storage`). Downstream crates can import individual
 //! modules directly or rely on the curated re-exports below.
+//!
+//! The root re-exports and [`prelude`] are the supported surface: items are
+//! listed by name so nothing becomes public by being added to a module, and
+//! the public error enums are `#[non_exhaustive]` so new variants are not
+//! breaking changes. Everything else is reached through its module path.
+//!
+//! With `default-features = false` only `model` and `error` are built: PID
+//! and token types, their derivations, and the error codes. The `address`
+//! feature adds the Monero address helpers, and `runtime` everything a server
+//! needs.
+
+/// Version of this crate, reported in the API's build info.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+#[cfg(feature = "runtime")]
 pub mod config;
+pub mod error;
+pub mod events;
+#[cfg(feature = "address")]
 pub mod integrated_address;
 pub mod model;
+pub mod prelude;
+#[cfg(feature = "runtime")]
 pub mod services;
+#[cfg(feature = "runtime")]
 pub mod storage;
 
-pub use config::{ApiConfig, BootstrapConfig, ConfigError};
-pub use integrated_address::*;
-pub use model::*;
-pub use services::cache::*;
-pub use services::telemetry::*;
-pub use storage::traits::*;
+#[cfg(feature = "runtime")]
+pub use config::{
+    load_env_files, ApiConfig, ApiConfigBuilder, BootstrapConfig, BootstrapConfigBuilder,
+    CheckoutPreset, ConfigError, ConfigFile, ConfigFormat, ConfigReport, DetectionMode, EnvLayers,
+    EnvProfile, InternalApiKey, InternalRole, SubscriptionPeriod, WalletEndpoint, PRIMARY_WALLET,
+};
+pub use error::{ErrorCode, HasErrorCode};
+pub use model::{
+    derive_pid_fingerprint, derive_service_token, ClaimOutcome, NewPayment, NewServiceToken,
+    PaymentId, PaymentRecord, PaymentState, PidFormatError, ServiceToken, ServiceTokenRecord,
+    TokenFormatError, TokenState,
+};
+#[cfg(feature = "redis-cache")]
+pub use services::cache::RedisPidCache;
+#[cfg(feature = "runtime")]
+pub use services::cache::{
+    BloomConfigError, InMemoryPidCache, PidBloom, PidCache, PidCacheStats, PidPresence,
+};
+#[cfg(feature = "runtime")]
+pub use services::telemetry::{init_telemetry, TelemetryConfig, TelemetryError, TelemetryGuard};
+#[cfg(feature = "runtime")]
+pub use storage::traits::{
+    AuditStore, CheckoutStore, IdempotencyStore, MonitorStateStore, PaymentStore, QuoteStore,
+    RenewalStore, StorageError, StorageResult, SubaddressStore, TokenStore, TombstoneStore,
+    WebhookStore,
+};
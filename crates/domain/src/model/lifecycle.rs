@@ -0,0 +1,197 @@
+//! The payment lifecycle as a state machine.
+//!
+//! A transfer is [`PaymentState::Detected`] while in the wallet's pool,
+//! [`PaymentState::Pending`] once mined but short of the confirmation depth,
+//! and [`PaymentState::Confirmed`] when the monitor stores it as a payment.
+//! Redemption moves it to [`PaymentState::Claimed`]. Expired and refunded
+//! payments are final. Only confirmed and later states are stored in
+//! `payments`; the earlier ones describe rows in `pending_payments`.
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaymentState {
+    /// Seen in the wallet's transaction pool.
+    Detected,
+    /// Mined, short of the confirmation depth.
+    Pending,
+    /// Stored and unclaimed; further transfers to the PID add up.
+    Confirmed,
+    Claimed,
+    /// Dropped from the pool before it was mined, or claimed and its last
+    /// token purged after expiry.
+    Expired,
+    /// Its block was orphaned after it was claimed; claimed again if the
+    /// transfer is re-mined. Unclaimed payments are removed instead, and
+    /// stored again if the transfer is re-mined.
+    Reorged,
+    /// Sent back to the payer in full.
+    Refunded,
+}
+
+/// What moves a payment from one [`PaymentState`] to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaymentTransition {
+    Mine,
+    Confirm,
+    Claim,
+    /// Reverts a claim that never produced a token.
+    Unclaim,
+    Expire,
+    Reorg,
+    Refund,
+}
+
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+#[error("a {from} payment cannot {transition}", from = .from.as_str(), transition = .transition.as_str())]
+pub struct InvalidTransition {
+    pub from: PaymentState,
+    pub transition: PaymentTransition,
+}
+
+impl PaymentState {
+    pub const ALL: [PaymentState; 7] = [
+        PaymentState::Detected,
+        PaymentState::Pending,
+        PaymentState::Confirmed,
+        PaymentState::Claimed,
+        PaymentState::Expired,
+        PaymentState::Reorged,
+        PaymentState::Refunded,
+    ];
+
+    /// Where a transfer the monitor has not stored yet stands.
+    pub const fn unconfirmed(block_height: Option<i64>) -> Self {
+        match block_height {
+            Some(_) => PaymentState::Pending,
+            None => PaymentState::Detected,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            PaymentState::Detected => "detected",
+            PaymentState::Pending => "pending",
+            PaymentState::Confirmed => "confirmed",
+            PaymentState::Claimed => "claimed",
+            PaymentState::Expired => "expired",
+            PaymentState::Reorged => "reorged",
+            PaymentState::Refunded => "refunded",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|state| state.as_str() == value)
+    }
+
+    /// The state `transition` leads to from this one.
+    pub const fn apply(self, transition: PaymentTransition) -> Result<Self, InvalidTransition> {
+        use PaymentState::*;
+        use PaymentTransition::*;
+        let next = match (self, transition) {
+            (Detected, Mine) => Pending,
+            // With no confirmation depth a transfer is stored as it is mined.
+            (Detected | Pending, Confirm) => Confirmed,
+            (Detected | Pending, Expire) => Expired,
+            // Back to the pool; a stored, unclaimed payment is removed until
+            // the transfer is mined again.
+            (Pending | Confirmed, Reorg) => Detected,
+            (Confirmed, Claim) => Claimed,
+            (Claimed, Unclaim) => Confirmed,
+            (Claimed, Expire) => Expired,
+            (Claimed, Reorg) => Reorged,
+            (Reorged, Confirm) => Claimed,
+            (Confirmed | Claimed, Refund) => Refunded,
+            (from, transition) => return Err(InvalidTransition { from, transition }),
+        };
+        Ok(next)
+    }
+
+    pub const fn allows(self, transition: PaymentTransition) -> bool {
+        self.apply(transition).is_ok()
+    }
+
+    /// No transition leaves a final state.
+    pub const fn is_final(self) -> bool {
+        matches!(self, PaymentState::Expired | PaymentState::Refunded)
+    }
+}
+
+impl PaymentTransition {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            PaymentTransition::Mine => "be mined",
+            PaymentTransition::Confirm => "be confirmed",
+            PaymentTransition::Claim => "be claimed",
+            PaymentTransition::Unclaim => "be unclaimed",
+            PaymentTransition::Expire => "expire",
+            PaymentTransition::Reorg => "be reorged",
+            PaymentTransition::Refund => "be refunded",
+        }
+    }
+
+    /// States this transition may start from, for conditional updates.
+    pub fn sources(self) -> impl Iterator<Item = PaymentState> {
+        PaymentState::ALL
+            .into_iter()
+            .filter(move |state| state.allows(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payments_move_forward_until_final() {
+        let claimed = PaymentState::unconfirmed(None)
+            .apply(PaymentTransition::Mine)
+            .and_then(|state| state.apply(PaymentTransition::Confirm))
+            .and_then(|state| state.apply(PaymentTransition::Claim))
+            .unwrap();
+        assert_eq!(claimed, PaymentState::Claimed);
+        assert_eq!(
+            claimed.apply(PaymentTransition::Unclaim),
+            Ok(PaymentState::Confirmed)
+        );
+        assert_eq!(
+            claimed.apply(PaymentTransition::Claim),
+            Err(InvalidTransition {
+                from: PaymentState::Claimed,
+                transition: PaymentTransition::Claim,
+            })
+        );
+        assert_eq!(
+            PaymentState::Pending.apply(PaymentTransition::Reorg),
+            Ok(PaymentState::Detected)
+        );
+        let reorged = claimed.apply(PaymentTransition::Reorg).unwrap();
+        assert_eq!(reorged.apply(PaymentTransition::Confirm), Ok(claimed));
+        assert!(!reorged.allows(PaymentTransition::Claim));
+
+        for state in PaymentState::ALL {
+            assert_eq!(PaymentState::parse(state.as_str()), Some(state));
+        }
+        for transition in [
+            PaymentTransition::Mine,
+            PaymentTransition::Confirm,
+            PaymentTransition::Claim,
+            PaymentTransition::Unclaim,
+            PaymentTransition::Expire,
+            PaymentTransition::Reorg,
+            PaymentTransition::Refund,
+        ] {
+            assert!(transition.sources().all(|from| !from.is_final()));
+        }
+        let refundable: Vec<_> = PaymentTransition::Refund.sources().collect();
+        assert_eq!(refundable, [PaymentState::Confirmed, PaymentState::Claimed]);
+        assert_eq!(
+            InvalidTransition {
+                from: PaymentState::Refunded,
+                transition: PaymentTransition::Claim,
+            }
+            .to_string(),
+            "a refunded payment cannot be claimed"
+        );
+    }
+}
@@ -1,11 +1,23 @@
 //! Data structures and helpers shared across the API and monitor binaries.
 
+/// PID and token validation, shared with `no_std` verifiers.
+pub use anon_ticket_core::{
+    validate_pid, validate_token, PidFormatError, TokenFormatError, PID_LENGTH, TOKEN_LENGTH,
+};
+use std::collections::BTreeMap;
+
 use cfg_if::cfg_if;
 use chrono::{DateTime, Utc};
 use getrandom::fill;
-use hex::{decode as hex_decode, encode as hex_encode, FromHexError};
+use hex::encode as hex_encode;
+use hmac::{Hmac, Mac};
 use sha3::{Digest, Sha3_256};
-use thiserror::Error;
+
+mod lifecycle;
+mod token_lifecycle;
+
+pub use lifecycle::{InvalidTransition, PaymentState, PaymentTransition};
+pub use token_lifecycle::{InvalidTokenTransition, TokenState, TokenTransition};
 
 /// Returns a static readiness message shared by sibling crates.
 pub fn workspace_ready_message() -> &'static str {
@@ -22,6 +34,23 @@ pub fn derive_pid_fingerprint(pid: &str) -> String {
     hex_encode(digest)
 }
 
+/// Batches smaller than this are hashed inline even with `parallel`; handing
+/// them to the pool costs more than it saves.
+#[cfg(feature = "parallel")]
+const PARALLEL_FINGERPRINT_MIN_BATCH: usize = 1024;
+
+/// [`derive_pid_fingerprint`] of each PID's hex form, in input order. With the
+/// `parallel` feature, large batches are spread across rayon's thread pool.
+pub fn derive_pid_fingerprints(pids: &[PaymentId]) -> Vec<String> {
+    let fingerprint = |pid: &PaymentId| derive_pid_fingerprint(&pid.to_hex());
+    #[cfg(feature = "parallel")]
+    if pids.len() >= PARALLEL_FINGERPRINT_MIN_BATCH {
+        use rayon::prelude::*;
+        return pids.par_iter().map(fingerprint).collect();
+    }
+    pids.iter().map(fingerprint).collect()
+}
+
 /// Generates a deterministic SHA3-256 service token from the PID + TXID pair.
 /// A separator is inserted between components to avoid accidental collisions if
 /// their lengths diverge in future formats.
@@ -34,39 +63,15 @@ pub fn derive_service_token(pid: &PaymentId, txid: &str) -> ServiceToken {
     ServiceToken::from_bytes(digest.into())
 }
 
-/// Required length (in hex characters) for externally supplied payment IDs.
-pub const PID_LENGTH: usize = 16;
-
-/// Errors emitted when user-supplied payment IDs fail validation.
-#[derive(Debug, Error, Clone, PartialEq, Eq)]
-pub enum PidFormatError {
-    #[error("payment id must be exactly {PID_LENGTH} hex characters")]
-    WrongLength,
-    #[error("payment id contains non-hex characters")]
-    NonHex,
-}
-
-/// Validates that the supplied PID matches the 16 hex-character contract.
-pub fn validate_pid(pid: &str) -> Result<(), PidFormatError> {
-    if pid.len() != PID_LENGTH {
-        return Err(PidFormatError::WrongLength);
-    }
-
-    if !pid.chars().all(|c| c.is_ascii_hexdigit()) {
-        return Err(PidFormatError::NonHex);
-    }
-
-    Ok(())
+/// Identifies a stored token (passphrase-wrapped ones by their wrapped form)
+/// without revealing it.
+pub fn token_subject(token: &ServiceToken) -> String {
+    hex_encode(token_digest(token))
 }
 
-fn decode_pid_hex(pid: &str) -> Result<[u8; 8], PidFormatError> {
-    let bytes = hex_decode(pid).map_err(map_hex_error_to_pid)?;
-    if bytes.len() != 8 {
-        return Err(PidFormatError::WrongLength);
-    }
-    let mut array = [0u8; 8];
-    array.copy_from_slice(&bytes);
-    Ok(array)
+/// The raw SHA3-256 behind [`token_subject`].
+pub fn token_digest(token: &ServiceToken) -> [u8; 32] {
+    Sha3_256::digest(token.as_bytes()).into()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -85,14 +90,14 @@ cfg_if! {
 }
 
 impl PaymentId {
+    #[cfg(test)]
     pub(crate) fn new(hex: impl AsRef<str>) -> Self {
-        let bytes = decode_pid_hex(hex.as_ref()).expect("caller validated pid hex");
+        let bytes = anon_ticket_core::decode_pid(hex.as_ref()).expect("caller validated pid hex");
         Self(bytes)
     }
 
     pub fn parse(pid: &str) -> Result<Self, PidFormatError> {
-        validate_pid(pid)?;
-        Ok(Self::new(pid))
+        anon_ticket_core::decode_pid(pid).map(Self)
     }
 
     pub fn generate() -> Result<Self, getrandom::Error> {
@@ -106,7 +111,7 @@ impl PaymentId {
     }
 
     pub fn to_hex(&self) -> String {
-        hex_encode(self.0)
+        anon_ticket_core::encode_hex(&self.0)
     }
 
     pub fn into_inner(self) -> String {
@@ -145,34 +150,32 @@ impl std::fmt::Display for PaymentId {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
-pub enum TokenFormatError {
-    #[error("service token must be exactly 64 hex characters")]
-    WrongLength,
-    #[error("service token contains non-hex characters")]
-    NonHex,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ServiceToken([u8; 32]);
 
 impl ServiceToken {
     pub fn parse(hex: &str) -> Result<Self, TokenFormatError> {
-        validate_hex_64(hex)?;
-        let bytes = decode_token_hex(hex)?;
-        Ok(Self(bytes))
+        anon_ticket_core::decode_token(hex).map(Self)
     }
 
     pub fn from_bytes(bytes: [u8; 32]) -> Self {
         Self(bytes)
     }
 
+    /// Random token for balances not backed by a payment of their own, e.g.
+    /// splits.
+    pub fn generate() -> Result<Self, getrandom::Error> {
+        let mut bytes = [0u8; 32];
+        fill(&mut bytes)?;
+        Ok(Self(bytes))
+    }
+
     pub fn as_bytes(&self) -> &[u8; 32] {
         &self.0
     }
 
     pub fn to_hex(&self) -> String {
-        hex_encode(self.0)
+        anon_ticket_core::encode_hex(&self.0)
     }
 
     pub fn into_inner(self) -> String {
@@ -182,6 +185,39 @@ impl ServiceToken {
     pub fn into_bytes(self) -> [u8; 32] {
         self.0
     }
+
+    /// First and last token starting with the hex `prefix`, for range
+    /// lookups on the stored bytes.
+    pub fn prefix_range(prefix: &str) -> Result<(Self, Self), TokenFormatError> {
+        if prefix.len() > TOKEN_LENGTH {
+            return Err(TokenFormatError::WrongLength);
+        }
+        let pad = TOKEN_LENGTH - prefix.len();
+        let first = Self::parse(&format!("{prefix}{}", "0".repeat(pad)))?;
+        let last = Self::parse(&format!("{prefix}{}", "f".repeat(pad)))?;
+        Ok((first, last))
+    }
+}
+
+/// Maximum accepted length for a token passphrase, in bytes.
+pub const MAX_TOKEN_PASSPHRASE_LEN: usize = 1024;
+
+/// Derives the stored form of a passphrase-protected token:
+/// `HMAC-SHA3-256(key = passphrase, msg = token)`. Only the wrapped value is
+/// persisted, so the token alone no longer resolves to a record.
+pub fn wrap_service_token(token: &ServiceToken, passphrase: &str) -> ServiceToken {
+    let mut mac = Hmac::<Sha3_256>::new_from_slice(passphrase.as_bytes())
+        .expect("hmac accepts keys of any length");
+    mac.update(token.as_bytes());
+    ServiceToken(mac.finalize().into_bytes().into())
+}
+
+/// Stored form of `token`: wrapped when a passphrase is given, raw otherwise.
+pub fn stored_service_token(token: &ServiceToken, passphrase: Option<&str>) -> ServiceToken {
+    match passphrase {
+        Some(passphrase) => wrap_service_token(token, passphrase),
+        None => token.clone(),
+    }
 }
 
 impl std::fmt::Display for ServiceToken {
@@ -203,61 +239,101 @@ impl TryFrom<Vec<u8>> for ServiceToken {
     }
 }
 
-fn validate_hex_64(input: &str) -> Result<(), TokenFormatError> {
-    if input.len() != 64 {
-        return Err(TokenFormatError::WrongLength);
-    }
-    if !input.chars().all(|c| c.is_ascii_hexdigit()) {
-        return Err(TokenFormatError::NonHex);
-    }
-    Ok(())
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentRecord {
+    pub pid: PaymentId,
+    pub txid: String,
+    pub amount: i64,
+    pub block_height: i64,
+    /// [`PaymentState::Confirmed`] or later.
+    pub status: PaymentState,
+    pub created_at: DateTime<Utc>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// Address book id of the address the first transfer was sent to.
+    pub address_id: Option<String>,
 }
 
-fn decode_token_hex(token: &str) -> Result<[u8; 32], TokenFormatError> {
-    let bytes = hex_decode(token).map_err(map_hex_error_to_token)?;
-    if bytes.len() != 32 {
-        return Err(TokenFormatError::WrongLength);
-    }
-    let mut array = [0u8; 32];
-    array.copy_from_slice(&bytes);
-    Ok(array)
+/// Operator listing filters. `None` matches everything; ranges are
+/// inclusive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaymentFilter {
+    pub status: Option<PaymentState>,
+    pub min_height: Option<i64>,
+    pub max_height: Option<i64>,
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
 }
 
-fn map_hex_error_to_pid(err: FromHexError) -> PidFormatError {
-    match err {
-        FromHexError::InvalidHexCharacter { .. } => PidFormatError::NonHex,
-        FromHexError::InvalidStringLength => PidFormatError::WrongLength,
-        _ => PidFormatError::NonHex,
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewPayment {
+    pub pid: PaymentId,
+    pub txid: String,
+    pub amount: i64,
+    pub block_height: i64,
+    pub detected_at: DateTime<Utc>,
+    /// Address book id of the receiving address: the name of the wallet
+    /// that saw the transfer. `None` when unknown, e.g. manual injections.
+    pub address_id: Option<String>,
 }
 
-fn map_hex_error_to_token(err: FromHexError) -> TokenFormatError {
-    match err {
-        FromHexError::InvalidHexCharacter { .. } => TokenFormatError::NonHex,
-        FromHexError::InvalidStringLength => TokenFormatError::WrongLength,
-        _ => TokenFormatError::NonHex,
-    }
+/// A later payment to a PID whose payment was already claimed. The first
+/// payment stays in `payments`; renewals extend the subscription of the token
+/// issued for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenewalRecord {
+    pub pid: PaymentId,
+    pub txid: String,
+    pub amount: i64,
+    pub block_height: i64,
+    pub detected_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PaymentStatus {
-    Unclaimed,
-    Claimed,
+/// A transfer the monitor has seen but not ingested yet: in the mempool, or
+/// mined but short of `MONITOR_MIN_CONFIRMATIONS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingTransfer {
+    pub txid: String,
+    pub pid: PaymentId,
+    pub amount: i64,
+    /// `None` while the transfer is in the mempool.
+    pub block_height: Option<i64>,
+    /// At the monitor's last poll; 0 in the mempool.
+    pub confirmations: u64,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
 }
 
+impl PendingTransfer {
+    /// [`PaymentState::Detected`] in the mempool, [`PaymentState::Pending`]
+    /// once mined.
+    pub const fn state(&self) -> PaymentState {
+        PaymentState::unconfirmed(self.block_height)
+    }
+}
+
+/// A transfer the monitor could not ingest, journaled as reported so an
+/// operator can review it and re-process it once the cause is fixed.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct PaymentRecord {
-    pub pid: PaymentId,
+pub struct SkippedTransfer {
     pub txid: String,
-    pub amount: i64,
-    pub block_height: i64,
-    pub status: PaymentStatus,
-    pub created_at: DateTime<Utc>,
-    pub claimed_at: Option<DateTime<Utc>>,
+    pub wallet: String,
+    /// `invalid_pid` or `amount_overflow`.
+    pub reason: String,
+    /// As reported by the wallet, which need not be a valid PID.
+    pub payment_id: Option<String>,
+    /// Atomic units in decimal, as reported; it may not fit an `i64`.
+    pub amount: String,
+    pub block_height: Option<i64>,
+    /// Block time reported by the wallet, in Unix seconds.
+    pub timestamp: u64,
+    pub skipped_at: DateTime<Utc>,
 }
 
+/// A further transfer to a PID that was still unclaimed. Its amount is added
+/// to the payment's, so the token issued at redemption covers every
+/// transfer. The payment keeps its first `txid`, which seeds the token.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct NewPayment {
+pub struct PaymentTransfer {
     pub pid: PaymentId,
     pub txid: String,
     pub amount: i64,
@@ -271,6 +347,7 @@ pub struct ClaimOutcome {
     pub txid: String,
     pub amount: i64,
     pub block_height: i64,
+    pub detected_at: DateTime<Utc>,
     pub claimed_at: DateTime<Utc>,
 }
 
@@ -281,6 +358,11 @@ pub struct NewServiceToken {
     pub amount: i64,
     pub issued_at: DateTime<Utc>,
     pub abuse_score: i16,
+    /// End of the token's lifetime; `None` never expires.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Tier earned by the payment, from `API_TOKEN_TIERS` or the checkout
+    /// preset.
+    pub tier: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -292,6 +374,80 @@ pub struct ServiceTokenRecord {
     pub revoked_at: Option<DateTime<Utc>>,
     pub revoke_reason: Option<String>,
     pub abuse_score: i16,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub tier: Option<String>,
+    /// Set while the token is suspended; cleared when it is resumed.
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspend_reason: Option<String>,
+    /// When a timed suspension lapses; `None` lasts until resumed.
+    pub suspended_until: Option<DateTime<Utc>>,
+}
+
+/// Where a reader of the revocation feed left off: past every revocation
+/// before `revoked_at` and the first `skip` at it, in token order. Tokens
+/// revoked in the same instant share a timestamp, so the count keeps pages
+/// from repeating or dropping them without naming a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RevocationCursor {
+    pub revoked_at: DateTime<Utc>,
+    pub skip: u64,
+}
+
+impl RevocationCursor {
+    /// The cursor past `page`, which was read from `previous`.
+    pub fn advance(previous: Option<Self>, page: &[ServiceTokenRecord]) -> Option<Self> {
+        let Some(last) = page.last().and_then(|record| record.revoked_at) else {
+            return previous;
+        };
+        let at_last = page
+            .iter()
+            .filter(|record| record.revoked_at == Some(last))
+            .count() as u64;
+        let carried = previous
+            .filter(|cursor| cursor.revoked_at == last)
+            .map_or(0, |cursor| cursor.skip);
+        Some(Self {
+            revoked_at: last,
+            skip: carried + at_last,
+        })
+    }
+}
+
+/// Stored state a token listing can be narrowed to. Expiry is not a stored
+/// state and is left to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenRevocation {
+    /// Neither revoked nor suspended.
+    Active,
+    /// Suspended, not revoked, and the suspension has not lapsed.
+    Suspended,
+    Revoked,
+}
+
+/// Operator listing filters. Height ranges match the block height of the
+/// payment a token was issued for, so split and merged tokens, which have no
+/// payment of their own, are excluded once a height bound is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenFilter {
+    pub revocation: Option<TokenRevocation>,
+    pub min_height: Option<i64>,
+    pub max_height: Option<i64>,
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+}
+
+impl ServiceTokenRecord {
+    /// Whether the token's own lifetime ran out by `at`. Subscription expiry
+    /// is tracked separately.
+    pub fn is_expired_at(&self, at: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= at)
+    }
+
+    /// Whether a suspension is in force at `at`. A timed one stops applying
+    /// once `suspended_until` passes, before any sweep clears it.
+    pub fn is_suspended_at(&self, at: DateTime<Utc>) -> bool {
+        self.suspended_at.is_some() && self.suspended_until.is_none_or(|until| until > at)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -301,6 +457,588 @@ pub struct RevokeTokenRequest {
     pub abuse_score: Option<i16>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuspendTokenRequest {
+    pub token: ServiceToken,
+    pub reason: Option<String>,
+    /// Lifts the suspension automatically; `None` waits for a resume.
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Moves `amount` of a token's balance onto a new, independent token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitTokenRequest {
+    pub token: ServiceToken,
+    pub amount: i64,
+    pub new_token: ServiceToken,
+    /// Fresh PID with no payment behind it, so PID lookups keep resolving to
+    /// the original token.
+    pub new_pid: PaymentId,
+    pub issued_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitTokenOutcome {
+    /// The original token with its reduced balance.
+    pub source: ServiceTokenRecord,
+    pub split: ServiceTokenRecord,
+}
+
+/// Revokes several tokens (reason `merged`) and issues one holding their
+/// combined balance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeTokensRequest {
+    pub tokens: Vec<ServiceToken>,
+    pub new_token: ServiceToken,
+    /// Fresh PID with no payment behind it, like a split token's.
+    pub new_pid: PaymentId,
+    pub issued_at: DateTime<Utc>,
+    /// Pinned subscription expiry for the merged token, if any.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Revoke reason recorded on tokens consumed by a merge.
+pub const MERGED_REVOKE_REASON: &str = "merged";
+
+/// Length in bytes of checkout client secrets (hex encoded on the wire).
+pub const CLIENT_SECRET_LEN: usize = 32;
+
+/// Generates a random, hex-encoded client secret for a checkout binding.
+pub fn generate_client_secret() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; CLIENT_SECRET_LEN];
+    fill(&mut bytes)?;
+    Ok(hex_encode(bytes))
+}
+
+/// Hashes a client secret together with the PID it is bound to, so stored
+/// hashes cannot be replayed against another PID.
+pub fn hash_client_secret(pid: &PaymentId, secret: &str) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"anon-ticket/checkout|");
+    hasher.update(pid.as_bytes());
+    hasher.update(b"|");
+    hasher.update(secret.as_bytes());
+    hasher.finalize().into()
+}
+
+/// A PID reserved at checkout whose redemption requires the client secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewCheckoutBinding {
+    pub pid: PaymentId,
+    pub secret_hash: [u8; 32],
+    pub created_at: DateTime<Utc>,
+    /// Set when the checkout referenced a preset.
+    pub terms: Option<CheckoutTerms>,
+}
+
+/// Payment terms captured from a preset at checkout and enforced at redeem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckoutTerms {
+    pub preset: String,
+    /// Minimum payment in atomic units.
+    pub expected_amount: i64,
+    /// Payments detected after this instant do not satisfy the checkout.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Lifecycle of a [`PaymentQuote`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStatus {
+    /// Awaiting payment.
+    Open,
+    /// A payment detected before the deadline was ingested.
+    Paid,
+    /// The deadline passed without an ingested payment.
+    Expired,
+}
+
+impl QuoteStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            QuoteStatus::Open => "open",
+            QuoteStatus::Paid => "paid",
+            QuoteStatus::Expired => "expired",
+        }
+    }
+}
+
+/// A PID issued with an expected amount and a payment deadline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewPaymentQuote {
+    pub pid: PaymentId,
+    /// Amount the payer was asked for, in atomic units.
+    pub expected_amount: i64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Address book id of the address the payer was given.
+    pub address_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentQuote {
+    pub pid: PaymentId,
+    pub expected_amount: i64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub status: QuoteStatus,
+    pub address_id: Option<String>,
+    /// Everything ingested for the PID so far, renewals included; `None`
+    /// until the first payment.
+    pub received_amount: Option<i64>,
+}
+
+impl PaymentQuote {
+    /// Whether a transfer detected at `at` may be ingested for this quote.
+    /// Only the block time counts, not the status: a payment mined before the
+    /// deadline is accepted even if the sweeper expired the quote while it
+    /// waited for confirmations. Paid quotes keep accepting renewals.
+    pub fn accepts_payment_at(&self, at: DateTime<Utc>) -> bool {
+        self.status == QuoteStatus::Paid || at <= self.expires_at
+    }
+
+    /// How the amount received compares with the amount quoted; `None`
+    /// before any payment.
+    pub fn reconciliation(&self) -> Option<Reconciliation> {
+        self.received_amount
+            .map(|received| Reconciliation::between(self.expected_amount, received))
+    }
+}
+
+/// Outcome of matching a quote's payments against its expected amount. A
+/// quote only moves forward as payments arrive: `Underpaid` can become
+/// `Exact` or `Overpaid` after a top-up, never the reverse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reconciliation {
+    Underpaid,
+    Exact,
+    Overpaid,
+}
+
+impl Reconciliation {
+    pub fn between(expected: i64, received: i64) -> Self {
+        match received.cmp(&expected) {
+            std::cmp::Ordering::Less => Reconciliation::Underpaid,
+            std::cmp::Ordering::Equal => Reconciliation::Exact,
+            std::cmp::Ordering::Greater => Reconciliation::Overpaid,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Reconciliation::Underpaid => "underpaid",
+            Reconciliation::Exact => "exact",
+            Reconciliation::Overpaid => "overpaid",
+        }
+    }
+}
+
+/// Where the excess of an overpaid quote went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreditStatus {
+    /// Added to the token's balance.
+    Credited,
+    /// Waiting for an operator to refund it, until `hold_until`.
+    Held,
+    /// Returned to the payer out of band.
+    Refunded,
+}
+
+impl CreditStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            CreditStatus::Credited => "credited",
+            CreditStatus::Held => "held",
+            CreditStatus::Refunded => "refunded",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "credited" => Some(CreditStatus::Credited),
+            "held" => Some(CreditStatus::Held),
+            "refunded" => Some(CreditStatus::Refunded),
+            _ => None,
+        }
+    }
+}
+
+/// The excess of an overpaid quote, recorded against the token issued for
+/// it. A token has at most one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewTokenCredit {
+    /// Stored (possibly passphrase-wrapped) form, as in `service_tokens`.
+    pub token: ServiceToken,
+    pub pid: PaymentId,
+    pub amount: i64,
+    pub status: CreditStatus,
+    pub created_at: DateTime<Utc>,
+    /// When a [`CreditStatus::Held`] credit falls back to the token.
+    pub hold_until: Option<DateTime<Utc>>,
+}
+
+/// A stored [`NewTokenCredit`]. Ids increase with insertion order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenCredit {
+    pub id: i64,
+    pub token: ServiceToken,
+    pub pid: PaymentId,
+    pub amount: i64,
+    pub status: CreditStatus,
+    pub created_at: DateTime<Utc>,
+    pub hold_until: Option<DateTime<Utc>>,
+    /// When a hold was credited or refunded.
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Progress of a [`Refund`] back to the payer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefundStatus {
+    /// Waiting for an operator or the monitor to send it.
+    Pending,
+    /// Claimed by the monitor; the wallet transfer may or may not have gone
+    /// out, so it is never retried automatically.
+    Sending,
+    Sent,
+    /// Cancelled by an operator, or rejected by the wallet.
+    Failed,
+}
+
+impl RefundStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            RefundStatus::Pending => "pending",
+            RefundStatus::Sending => "sending",
+            RefundStatus::Sent => "sent",
+            RefundStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(RefundStatus::Pending),
+            "sending" => Some(RefundStatus::Sending),
+            "sent" => Some(RefundStatus::Sent),
+            "failed" => Some(RefundStatus::Failed),
+            _ => None,
+        }
+    }
+
+    /// Sent and failed refunds are final.
+    pub const fn is_open(self) -> bool {
+        matches!(self, RefundStatus::Pending | RefundStatus::Sending)
+    }
+}
+
+/// Part of a payment to return to `address`. Refunds that have not failed
+/// never add up to more than the payment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewRefund {
+    pub pid: PaymentId,
+    /// Standard address, subaddress or integrated address on the network
+    /// the service is paid on.
+    pub address: String,
+    pub amount: i64,
+    pub requested_at: DateTime<Utc>,
+}
+
+/// A stored [`NewRefund`]. Ids increase with insertion order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Refund {
+    pub id: i64,
+    pub pid: PaymentId,
+    pub address: String,
+    pub amount: i64,
+    pub status: RefundStatus,
+    pub requested_at: DateTime<Utc>,
+    /// When it was sent or failed.
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// Wallet transaction hash of a sent refund.
+    pub txid: Option<String>,
+    /// Why it failed.
+    pub error: Option<String>,
+}
+
+/// How an open [`Refund`] ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefundOutcome {
+    Sent { txid: String },
+    Failed { error: String },
+}
+
+impl RefundOutcome {
+    pub const fn status(&self) -> RefundStatus {
+        match self {
+            RefundOutcome::Sent { .. } => RefundStatus::Sent,
+            RefundOutcome::Failed { .. } => RefundStatus::Failed,
+        }
+    }
+}
+
+/// An outbound webhook event. Recorded once per txid, so a payment that is
+/// ingested again after a reorg keeps its original id and payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookEvent {
+    /// ULID, sent to receivers as the idempotency key.
+    pub id: String,
+    /// Transaction the event is about; `token.revoked:<token>` for a
+    /// revocation.
+    pub txid: String,
+    pub pid: PaymentId,
+    /// Exact body POSTed to every URL.
+    pub payload: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Progress of one [`WebhookEvent`] towards one URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    /// Being attempted or retried.
+    Pending,
+    Delivered,
+    /// Attempts ran out; a dead letter was recorded.
+    Failed,
+    /// An operator asked for it to be sent again.
+    Requested,
+}
+
+impl DeliveryStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Requested => "requested",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(DeliveryStatus::Pending),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "failed" => Some(DeliveryStatus::Failed),
+            "requested" => Some(DeliveryStatus::Requested),
+            _ => None,
+        }
+    }
+}
+
+/// Delivery state of an event for one URL. `attempts` counts every POST,
+/// including those of earlier redeliveries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDelivery {
+    pub event_id: String,
+    pub url: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// Snapshot of the webhook outbox, i.e. deliveries not yet done.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutboxStats {
+    /// Deliveries `pending` or `requested`.
+    pub backlog: u64,
+    /// Creation time of the oldest event with a delivery in the backlog.
+    pub oldest_undelivered_at: Option<DateTime<Utc>>,
+    /// Deliveries that ran out of attempts, per URL.
+    pub failed_by_url: BTreeMap<String, u64>,
+}
+
+/// Webhook delivery that exhausted its retries, kept so operators can
+/// inspect or replay it. One record per event and URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDeadLetter {
+    /// Id of the [`WebhookEvent`].
+    pub event_id: String,
+    pub url: String,
+    pub pid: PaymentId,
+    pub payload: String,
+    pub attempts: u32,
+    pub last_error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Response recorded under a client-supplied `Idempotency-Key` and replayed
+/// verbatim when the same request is retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotentResponse {
+    /// See [`hash_idempotency_key`]; the key itself is not stored.
+    pub key_hash: [u8; 32],
+    /// Fingerprint of the request the key was first used with, so reusing
+    /// the key for a different request is detected.
+    pub request_hash: [u8; 32],
+    /// Payment the request was about; purging it drops the record.
+    pub pid: PaymentId,
+    pub status: u16,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Hashes an `Idempotency-Key` header for storage.
+pub fn hash_idempotency_key(key: &str) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"anon-ticket/idempotency|");
+    hasher.update(key.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Wallet subaddress reserved for one order in subaddress detection mode.
+/// Incoming transfers are matched to the PID through `(account_index,
+/// minor_index)` instead of an embedded payment id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubaddressRecord {
+    pub pid: PaymentId,
+    pub account_index: u32,
+    pub minor_index: u32,
+    pub address: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One processed monitor batch. The rolling history gives reorg detection
+/// and rescans known-good restart points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorCheckpoint {
+    /// Highest height the batch covered.
+    pub height: u64,
+    /// Main-chain hash at `height`; `None` when the source cannot report
+    /// block hashes. Later ticks compare it to detect reorgs.
+    pub block_hash: Option<String>,
+    pub tick_time: DateTime<Utc>,
+    /// Transfers the batch fetched.
+    pub batch_size: u32,
+}
+
+/// What a reorg rollback undid.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReorgRollback {
+    /// Unclaimed payments removed so they are re-verified when re-mined.
+    pub payments_removed: u64,
+    pub renewals_removed: u64,
+    /// Top-up transfers dropped, and subtracted from their unclaimed payment.
+    pub transfers_removed: u64,
+    /// Claimed payments marked reorged until the transfer is mined again.
+    pub payments_reorged: u64,
+    /// Tokens already issued against orphaned payments, now under review.
+    pub tokens_flagged: u64,
+}
+
+/// Marks a token whose backing payment needs manual review, e.g. because a
+/// reorg orphaned it after the token was claimed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenReview {
+    pub token: ServiceToken,
+    pub reason: String,
+    pub flagged_at: DateTime<Utc>,
+}
+
+/// Which kind of row a tombstone stands in for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TombstoneKind {
+    Payment,
+    Token,
+}
+
+impl TombstoneKind {
+    fn domain_separator(self) -> &'static [u8] {
+        match self {
+            TombstoneKind::Payment => b"anon-ticket/tombstone/payment|",
+            TombstoneKind::Token => b"anon-ticket/tombstone/token|",
+        }
+    }
+}
+
+/// Marker left behind when a payment or token row is purged. Only a hash of
+/// the identifier is kept, so holders of the original id can match it but the
+/// tombstone table alone does not reveal purged identifiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TombstoneRecord {
+    pub kind: TombstoneKind,
+    pub id_hash: [u8; 32],
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Hashes a raw identifier for tombstone storage and lookup.
+pub fn tombstone_hash(kind: TombstoneKind, id: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(kind.domain_separator());
+    hasher.update(id);
+    hasher.finalize().into()
+}
+
+/// Which listener an audited action arrived on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditActor {
+    /// A customer-facing request such as redeem.
+    Public,
+    /// An operator call on the internal listener.
+    Internal,
+}
+
+impl AuditActor {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            AuditActor::Public => "public",
+            AuditActor::Internal => "internal",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "public" => Some(AuditActor::Public),
+            "internal" => Some(AuditActor::Internal),
+            _ => None,
+        }
+    }
+}
+
+/// One entry for the append-only audit log. The PID or token acted on is
+/// kept only as [`audit_subject_hash`], and nothing about the client is
+/// recorded, so the log supports incident forensics without tying requests
+/// to the people who made them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewAuditEvent {
+    pub occurred_at: DateTime<Utc>,
+    pub actor: AuditActor,
+    /// Dotted action name, e.g. `token.revoke`.
+    pub action: String,
+    pub subject_hash: [u8; 32],
+    pub outcome: String,
+    /// Operator justification; `None` for public actions.
+    pub reason: Option<String>,
+    pub operator: Option<String>,
+    /// Internal API key the call was authenticated with.
+    pub key_id: Option<String>,
+}
+
+/// A stored [`NewAuditEvent`]. Ids increase with insertion order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEventRecord {
+    pub id: i64,
+    pub event: NewAuditEvent,
+}
+
+/// Conjunctive filter over the audit log; `None` fields match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditFilter {
+    pub actor: Option<AuditActor>,
+    pub action: Option<String>,
+    pub subject_hash: Option<[u8; 32]>,
+    pub outcome: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Hashes the PID or token an audit event is about. Hex identifiers are
+/// case-insensitive, so both spellings hash alike.
+pub fn audit_subject_hash(subject: &str) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"anon-ticket/audit-subject|");
+    hasher.update(subject.to_ascii_lowercase().as_bytes());
+    hasher.finalize().into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -314,6 +1052,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn wrapped_token_depends_on_passphrase() {
+        let token = ServiceToken::from_bytes([7u8; 32]);
+        let wrapped = wrap_service_token(&token, "1234");
+        assert_ne!(wrapped, token);
+        assert_eq!(wrapped, wrap_service_token(&token, "1234"));
+        assert_ne!(wrapped, wrap_service_token(&token, "1235"));
+        assert_eq!(stored_service_token(&token, None), token);
+        assert_eq!(stored_service_token(&token, Some("1234")), wrapped);
+    }
+
+    #[test]
+    fn client_secret_hash_is_bound_to_pid() {
+        let secret = generate_client_secret().unwrap();
+        assert_eq!(secret.len(), CLIENT_SECRET_LEN * 2);
+        let pid = PaymentId::new(VALID_PID);
+        let other = PaymentId::new("fedcba9876543210");
+        assert_eq!(
+            hash_client_secret(&pid, &secret),
+            hash_client_secret(&pid, &secret)
+        );
+        assert_ne!(
+            hash_client_secret(&pid, &secret),
+            hash_client_secret(&other, &secret)
+        );
+    }
+
+    #[test]
+    fn tombstone_hash_separates_kinds() {
+        let id = [7u8; 8];
+        assert_eq!(
+            tombstone_hash(TombstoneKind::Payment, &id),
+            tombstone_hash(TombstoneKind::Payment, &id)
+        );
+        assert_ne!(
+            tombstone_hash(TombstoneKind::Payment, &id),
+            tombstone_hash(TombstoneKind::Token, &id)
+        );
+    }
+
     #[test]
     fn pid_fingerprint_is_deterministic() {
         let left = derive_pid_fingerprint("abcd");
@@ -322,6 +1100,19 @@ mod tests {
         assert_eq!(left.len(), 64);
     }
 
+    #[test]
+    fn batch_fingerprints_match_single_ones_in_order() {
+        // Large enough to take the parallel path when it is compiled in.
+        let pids: Vec<PaymentId> = (0..2048u64)
+            .map(|n| PaymentId::parse(&format!("{n:016x}")).unwrap())
+            .collect();
+        let batch = derive_pid_fingerprints(&pids);
+        assert_eq!(batch.len(), pids.len());
+        for (pid, fingerprint) in pids.iter().zip(&batch) {
+            assert_eq!(fingerprint, &derive_pid_fingerprint(&pid.to_hex()));
+        }
+    }
+
     #[test]
     fn pid_validation_rejects_invalid_inputs() {
         assert_eq!(validate_pid("deadbeef"), Err(PidFormatError::WrongLength));
@@ -341,7 +1132,7 @@ mod tests {
     #[test]
     fn payment_id_canonicalizes_case() {
         let uppercase = "ABCDEFAB12345678";
-        let pid = PaymentId::parse(&uppercase).unwrap();
+        let pid = PaymentId::parse(uppercase).unwrap();
         assert_eq!(pid.to_hex(), "abcdefab12345678");
 
         let raw = PaymentId::new("FEDCBA9876543210");
@@ -0,0 +1,192 @@
+//! The service token lifecycle as a state machine.
+//!
+//! Redemption mints a token [`TokenState::Issued`] and storing it makes it
+//! [`TokenState::Active`]. Suspension is temporary and lifted by resuming;
+//! revocation is permanent. Expiry follows from the token's lifetime or its
+//! subscription and is never stored, so a renewal payment can lift it.
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use super::ServiceTokenRecord;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenState {
+    /// Minted, not stored yet.
+    Issued,
+    Active,
+    /// Refused until resumed; balance and expiry are kept.
+    Suspended,
+    Revoked,
+    /// Past its lifetime or subscription period.
+    Expired,
+}
+
+/// What moves a token from one [`TokenState`] to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenTransition {
+    Activate,
+    Suspend,
+    Resume,
+    Revoke,
+    Expire,
+    /// A renewal payment extends a lapsed subscription.
+    Renew,
+}
+
+#[derive(Debug, Clone, Copy, Error, PartialEq, Eq)]
+#[error("a {from} token cannot {transition}", from = .from.as_str(), transition = .transition.as_str())]
+pub struct InvalidTokenTransition {
+    pub from: TokenState,
+    pub transition: TokenTransition,
+}
+
+impl TokenState {
+    pub const ALL: [TokenState; 5] = [
+        TokenState::Issued,
+        TokenState::Active,
+        TokenState::Suspended,
+        TokenState::Revoked,
+        TokenState::Expired,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            TokenState::Issued => "issued",
+            TokenState::Active => "active",
+            TokenState::Suspended => "suspended",
+            TokenState::Revoked => "revoked",
+            TokenState::Expired => "expired",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|state| state.as_str() == value)
+    }
+
+    /// The state `transition` leads to from this one.
+    pub const fn apply(self, transition: TokenTransition) -> Result<Self, InvalidTokenTransition> {
+        use TokenState::*;
+        use TokenTransition::*;
+        let next = match (self, transition) {
+            (Issued, Activate) => Active,
+            (Active, Suspend) => Suspended,
+            (Suspended, Resume) => Active,
+            (Issued | Active | Suspended | Expired, Revoke) => Revoked,
+            (Issued | Active | Suspended, Expire) => Expired,
+            (Expired, Renew) => Active,
+            (from, transition) => return Err(InvalidTokenTransition { from, transition }),
+        };
+        Ok(next)
+    }
+
+    pub const fn allows(self, transition: TokenTransition) -> bool {
+        self.apply(transition).is_ok()
+    }
+
+    /// Whether the token may be presented, spent, split or merged.
+    pub const fn is_usable(self) -> bool {
+        matches!(self, TokenState::Active)
+    }
+
+    /// No transition leaves a final state.
+    pub const fn is_final(self) -> bool {
+        matches!(self, TokenState::Revoked)
+    }
+}
+
+impl TokenTransition {
+    pub const ALL: [TokenTransition; 6] = [
+        TokenTransition::Activate,
+        TokenTransition::Suspend,
+        TokenTransition::Resume,
+        TokenTransition::Revoke,
+        TokenTransition::Expire,
+        TokenTransition::Renew,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            TokenTransition::Activate => "be activated",
+            TokenTransition::Suspend => "be suspended",
+            TokenTransition::Resume => "be resumed",
+            TokenTransition::Revoke => "be revoked",
+            TokenTransition::Expire => "expire",
+            TokenTransition::Renew => "be renewed",
+        }
+    }
+
+    /// States this transition may start from, for conditional updates.
+    pub fn sources(self) -> impl Iterator<Item = TokenState> {
+        TokenState::ALL
+            .into_iter()
+            .filter(move |state| state.allows(self))
+    }
+}
+
+impl ServiceTokenRecord {
+    /// Where the stored token stands at `at`, given its effective expiry
+    /// (its own lifetime or its subscription's, whichever ends first).
+    /// Revocation outranks expiry, which outranks suspension.
+    pub fn state_at(&self, expires_at: Option<DateTime<Utc>>, at: DateTime<Utc>) -> TokenState {
+        if self.revoked_at.is_some() {
+            TokenState::Revoked
+        } else if expires_at.is_some_and(|expires_at| expires_at <= at) {
+            TokenState::Expired
+        } else if self.is_suspended_at(at) {
+            TokenState::Suspended
+        } else {
+            TokenState::Active
+        }
+    }
+
+    /// [`ServiceTokenRecord::state_at`] ignoring subscriptions, for callers
+    /// that only have the record.
+    pub fn state(&self, at: DateTime<Utc>) -> TokenState {
+        self.state_at(self.expires_at, at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suspension_is_lifted_and_revocation_is_final() {
+        let active = TokenState::Issued.apply(TokenTransition::Activate).unwrap();
+        let suspended = active.apply(TokenTransition::Suspend).unwrap();
+        assert_eq!(suspended, TokenState::Suspended);
+        assert!(!suspended.is_usable());
+        assert_eq!(
+            suspended.apply(TokenTransition::Suspend),
+            Err(InvalidTokenTransition {
+                from: TokenState::Suspended,
+                transition: TokenTransition::Suspend,
+            })
+        );
+        assert_eq!(suspended.apply(TokenTransition::Resume), Ok(active));
+        assert!(!active.allows(TokenTransition::Resume));
+
+        let expired = active.apply(TokenTransition::Expire).unwrap();
+        assert!(!expired.allows(TokenTransition::Suspend));
+        assert_eq!(expired.apply(TokenTransition::Renew), Ok(active));
+
+        let revoked = suspended.apply(TokenTransition::Revoke).unwrap();
+        assert!(revoked.is_final());
+        for transition in TokenTransition::ALL {
+            assert!(!revoked.allows(transition));
+            assert!(transition.sources().all(|from| !from.is_final()));
+        }
+        for state in TokenState::ALL {
+            assert_eq!(TokenState::parse(state.as_str()), Some(state));
+        }
+        assert_eq!(
+            InvalidTokenTransition {
+                from: TokenState::Revoked,
+                transition: TokenTransition::Resume,
+            }
+            .to_string(),
+            "a revoked token cannot be resumed"
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! The types most API and monitor code needs, for glob import:
+//!
+//! ```
+//! use anon_ticket_domain::prelude::*;
+//! ```
+
+pub use crate::error::{ErrorCode, HasErrorCode};
+pub use crate::model::{
+    NewPayment, NewServiceToken, PaymentId, PaymentRecord, PaymentState, ServiceToken,
+    ServiceTokenRecord,
+};
+#[cfg(feature = "runtime")]
+pub use crate::services::cache::{InMemoryPidCache, PidBloom, PidCache};
+#[cfg(feature = "runtime")]
+pub use crate::storage::traits::{PaymentStore, StorageError, StorageResult, TokenStore};
@@ -0,0 +1,53 @@
+//! Keeps CPU-bound work (bulk hashing, filter rebuilds, signature checks) off
+//! the async workers.
+//!
+//! actix-web gives each HTTP worker a single-threaded runtime, so a long loop
+//! in a handler stalls every request queued on that worker. Such work goes
+//! through [`run_blocking`] instead, which moves it to tokio's blocking pool.
+
+use std::time::Instant;
+
+use metrics::{gauge, histogram};
+
+/// Runs `work` on the blocking pool and waits for it without holding up the
+/// calling worker. `task` labels `blocking_task_duration_seconds` and
+/// `blocking_tasks_in_flight`. A panic inside `work` resumes on the caller.
+///
+/// Must be called from within a tokio runtime.
+pub async fn run_blocking<T, F>(task: &'static str, work: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let in_flight = gauge!("blocking_tasks_in_flight", "task" => task);
+    in_flight.increment(1.0);
+    let start = Instant::now();
+    let result = tokio::task::spawn_blocking(work).await;
+    in_flight.decrement(1.0);
+    histogram!("blocking_task_duration_seconds", "task" => task)
+        .record(start.elapsed().as_secs_f64());
+    match result {
+        Ok(value) => value,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        // Only happens while the runtime shuts down.
+        Err(err) => panic!("blocking task `{task}` was cancelled: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn runs_off_the_calling_thread() {
+        let caller = std::thread::current().id();
+        let worker = run_blocking("test", || std::thread::current().id()).await;
+        assert_ne!(caller, worker);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "boom")]
+    async fn panics_resume_on_the_caller() {
+        run_blocking("test", || panic!("boom")).await
+    }
+}
@@ -1,7 +1,9 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
 use fastbloom::AtomicBloomFilter;
 use moka::sync::Cache;
+use sha3::{Digest, Sha3_256};
 use thiserror::Error;
 
 use crate::model::PaymentId;
@@ -25,11 +27,26 @@ pub trait PidCache: Send + Sync {
 #[derive(Debug)]
 pub struct InMemoryPidCache {
     positives: Cache<[u8; 8], ()>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Point-in-time view of an [`InMemoryPidCache`] for operator tooling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PidCacheStats {
+    pub entries: u64,
+    pub capacity: u64,
+    pub ttl: Duration,
+    pub hits: u64,
+    pub misses: u64,
 }
 
 impl PidCache for InMemoryPidCache {
     fn might_contain(&self, pid: &PaymentId) -> bool {
-        self.positives.contains_key(pid.as_bytes())
+        let hit = self.positives.contains_key(pid.as_bytes());
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        hit
     }
 
     fn mark_present(&self, pid: &PaymentId) {
@@ -52,12 +69,53 @@ impl InMemoryPidCache {
                 .time_to_live(ttl)
                 .max_capacity(capacity)
                 .build(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
         }
     }
 
     pub fn known_present(&self, pid: &PaymentId) -> bool {
         self.positives.contains_key(pid.as_bytes())
     }
+
+    /// Drops a single PID; returns whether it was cached.
+    pub fn invalidate(&self, pid: &PaymentId) -> bool {
+        self.positives.remove(pid.as_bytes()).is_some()
+    }
+
+    /// Drops every entry; returns how many were cached beforehand.
+    pub fn invalidate_all(&self) -> u64 {
+        let before = self.entry_count();
+        self.positives.invalidate_all();
+        self.positives.run_pending_tasks();
+        before
+    }
+
+    pub fn stats(&self) -> PidCacheStats {
+        let policy = self.positives.policy();
+        PidCacheStats {
+            entries: self.entry_count(),
+            capacity: policy.max_capacity().unwrap_or(Self::DEFAULT_CAPACITY),
+            ttl: policy.time_to_live().unwrap_or(Self::DEFAULT_TTL),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Up to `limit` cached PIDs, in no particular order.
+    pub fn sample(&self, limit: usize) -> Vec<PaymentId> {
+        self.positives
+            .iter()
+            .take(limit)
+            .filter_map(|(bytes, _)| PaymentId::try_from(bytes.to_vec()).ok())
+            .collect()
+    }
+
+    fn entry_count(&self) -> u64 {
+        // moka maintains counts lazily; flush pending work so stats are exact.
+        self.positives.run_pending_tasks();
+        self.positives.entry_count()
+    }
 }
 
 impl Default for InMemoryPidCache {
@@ -66,11 +124,135 @@ impl Default for InMemoryPidCache {
     }
 }
 
+/// [`PidCache`] backed by a Redis set shared by every replica, so PIDs the
+/// monitor marks on one node are visible to all of them.
+///
+/// Lookups are fronted by local positive and negative caches: positives are
+/// kept for the usual TTL, negatives only for `negative_ttl` so a PID ingested
+/// elsewhere is picked up quickly. Redis errors fail open (the PID is
+/// reported as possibly present) and fall through to storage.
+#[cfg(feature = "redis-cache")]
+pub struct RedisPidCache {
+    client: redis::Client,
+    connection: std::sync::Mutex<Option<redis::Connection>>,
+    key: String,
+    positives: Cache<[u8; 8], ()>,
+    negatives: Cache<[u8; 8], ()>,
+}
+
+#[cfg(feature = "redis-cache")]
+impl RedisPidCache {
+    pub const DEFAULT_KEY_PREFIX: &'static str = "anon-ticket";
+    pub const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(5);
+    /// Bounds every Redis round trip; lookups sit on the redeem path.
+    const IO_TIMEOUT: Duration = Duration::from_millis(250);
+
+    /// Opens a client for `url`; the connection itself is established lazily
+    /// and re-established after errors.
+    pub fn new(url: &str, key_prefix: &str) -> redis::RedisResult<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            connection: std::sync::Mutex::new(None),
+            key: format!("{key_prefix}:pids"),
+            positives: Cache::builder()
+                .time_to_live(InMemoryPidCache::DEFAULT_TTL)
+                .max_capacity(InMemoryPidCache::DEFAULT_CAPACITY)
+                .build(),
+            negatives: Cache::builder()
+                .time_to_live(Self::DEFAULT_NEGATIVE_TTL)
+                .max_capacity(InMemoryPidCache::DEFAULT_CAPACITY)
+                .build(),
+        })
+    }
+
+    /// Overrides how long a Redis miss is trusted locally.
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negatives = Cache::builder()
+            .time_to_live(ttl)
+            .max_capacity(InMemoryPidCache::DEFAULT_CAPACITY)
+            .build();
+        self
+    }
+
+    /// Name of the shared Redis set.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn query<T: redis::FromRedisValue>(&self, cmd: &redis::Cmd) -> redis::RedisResult<T> {
+        let mut guard = self
+            .connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if guard.is_none() {
+            let connection = self.client.get_connection_with_timeout(Self::IO_TIMEOUT)?;
+            connection.set_read_timeout(Some(Self::IO_TIMEOUT))?;
+            connection.set_write_timeout(Some(Self::IO_TIMEOUT))?;
+            *guard = Some(connection);
+        }
+        let result = cmd.query(guard.as_mut().expect("connection established above"));
+        if result.is_err() {
+            // Drop the connection so the next call reconnects.
+            *guard = None;
+        }
+        result
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+impl PidCache for RedisPidCache {
+    fn might_contain(&self, pid: &PaymentId) -> bool {
+        let bytes = pid.as_bytes();
+        if self.positives.contains_key(bytes) {
+            return true;
+        }
+        if self.negatives.contains_key(bytes) {
+            return false;
+        }
+        match self.query::<bool>(redis::cmd("SISMEMBER").arg(&self.key).arg(pid.to_hex())) {
+            Ok(true) => {
+                self.positives.insert(*bytes, ());
+                true
+            }
+            Ok(false) => {
+                self.negatives.insert(*bytes, ());
+                false
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, "redis pid lookup failed; treating pid as present");
+                metrics::counter!("pid_cache_redis_errors_total", "op" => "lookup").increment(1);
+                true
+            }
+        }
+    }
+
+    fn mark_present(&self, pid: &PaymentId) {
+        let bytes = pid.as_bytes();
+        self.negatives.invalidate(bytes);
+        self.positives.insert(*bytes, ());
+        if let Err(err) = self.query::<()>(redis::cmd("SADD").arg(&self.key).arg(pid.to_hex())) {
+            tracing::warn!(error = %err, "redis pid insert failed");
+            metrics::counter!("pid_cache_redis_errors_total", "op" => "insert").increment(1);
+        }
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+impl std::fmt::Debug for RedisPidCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisPidCache")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Bloom filter for PID hints. False positives are allowed; false negatives are
 /// not expected from the underlying implementation.
 #[derive(Debug)]
 pub struct PidBloom {
     filter: AtomicBloomFilter,
+    expected_items: u64,
+    false_positive_rate: f64,
 }
 
 impl PidBloom {
@@ -86,7 +268,29 @@ impl PidBloom {
         let filter = AtomicBloomFilter::with_false_pos(false_positive_rate)
             .seed(&0_u128)
             .expected_items(expected_items as usize);
-        Ok(Self { filter })
+        Ok(Self {
+            filter,
+            expected_items,
+            false_positive_rate,
+        })
+    }
+
+    /// Sizing hint the filter was built for.
+    pub fn expected_items(&self) -> u64 {
+        self.expected_items
+    }
+
+    /// Target false-positive rate at [`expected_items`](Self::expected_items).
+    pub fn false_positive_rate(&self) -> f64 {
+        self.false_positive_rate
+    }
+
+    pub fn num_bits(&self) -> u64 {
+        self.filter.num_bits() as u64
+    }
+
+    pub fn num_hashes(&self) -> u32 {
+        self.filter.num_hashes()
     }
 
     #[inline]
@@ -98,9 +302,95 @@ impl PidBloom {
     pub fn might_contain(&self, pid: &PaymentId) -> bool {
         self.filter.contains(pid.as_bytes())
     }
+
+    /// Serializes the filter bits together with the storage position they
+    /// reflect. The format is private to this type: a fixed header, the bit
+    /// words little-endian, and a SHA3-256 checksum over both.
+    pub fn snapshot(&self, watermark: BloomWatermark) -> Vec<u8> {
+        let words = self.filter.as_slice();
+        let mut out = Vec::with_capacity(SNAPSHOT_HEADER_LEN + words.len() * 8 + 32);
+        out.extend_from_slice(SNAPSHOT_MAGIC);
+        out.extend_from_slice(&self.num_bits().to_le_bytes());
+        out.extend_from_slice(&self.num_hashes().to_le_bytes());
+        out.extend_from_slice(&self.filter.source_hash(SNAPSHOT_PROBE).to_le_bytes());
+        out.extend_from_slice(&watermark.height.to_le_bytes());
+        out.extend_from_slice(&watermark.covered.to_le_bytes());
+        for word in self.filter.iter() {
+            out.extend_from_slice(&word.to_le_bytes());
+        }
+        let checksum = Sha3_256::digest(&out);
+        out.extend_from_slice(&checksum);
+        out
+    }
+
+    /// Merges a [`snapshot`](Self::snapshot) into this filter and returns
+    /// its watermark. Only snapshots of an identically sized filter hashing
+    /// the same way are accepted, so resizing the filter or a change in the
+    /// hasher falls back to a full rebuild instead of false negatives.
+    pub fn restore(&self, snapshot: &[u8]) -> Result<BloomWatermark, BloomSnapshotError> {
+        let words = self.filter.as_slice();
+        if snapshot.len() != SNAPSHOT_HEADER_LEN + words.len() * 8 + 32 {
+            return Err(BloomSnapshotError::Shape);
+        }
+        let (body, checksum) = snapshot.split_at(snapshot.len() - 32);
+        if Sha3_256::digest(body).as_slice() != checksum {
+            return Err(BloomSnapshotError::Corrupt);
+        }
+        let (header, bits) = body.split_at(SNAPSHOT_HEADER_LEN);
+        let field = |at: usize, len: usize| &header[at..at + len];
+        if field(0, 8) != SNAPSHOT_MAGIC {
+            return Err(BloomSnapshotError::Corrupt);
+        }
+        let num_bits = u64::from_le_bytes(field(8, 8).try_into().expect("8 bytes"));
+        let num_hashes = u32::from_le_bytes(field(16, 4).try_into().expect("4 bytes"));
+        if num_bits != self.num_bits() || num_hashes != self.num_hashes() {
+            return Err(BloomSnapshotError::Shape);
+        }
+        let probe = u64::from_le_bytes(field(20, 8).try_into().expect("8 bytes"));
+        if probe != self.filter.source_hash(SNAPSHOT_PROBE) {
+            return Err(BloomSnapshotError::Hasher);
+        }
+        let watermark = BloomWatermark {
+            height: i64::from_le_bytes(field(28, 8).try_into().expect("8 bytes")),
+            covered: u64::from_le_bytes(field(36, 8).try_into().expect("8 bytes")),
+        };
+        for (word, bytes) in words.iter().zip(bits.chunks_exact(8)) {
+            let stored = u64::from_le_bytes(bytes.try_into().expect("8 bytes"));
+            word.fetch_or(stored, Ordering::Relaxed);
+        }
+        Ok(watermark)
+    }
+}
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"ATBLOOM1";
+/// Magic, bit count, hash count, probe, watermark height and count.
+const SNAPSHOT_HEADER_LEN: usize = 8 + 8 + 4 + 8 + 8 + 8;
+/// Hashed into every snapshot; a different hash on restore means the stored
+/// bit positions no longer match the hasher.
+const SNAPSHOT_PROBE: &[u8] = b"anon-ticket/pid-bloom-probe";
+
+/// How much of the payments table a [`PidBloom`] snapshot covers: every
+/// payment below `height`, of which there were `covered` when it was taken.
+/// Payments at or above `height` are re-inserted on restore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BloomWatermark {
+    pub height: i64,
+    pub covered: u64,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BloomSnapshotError {
+    #[error("bloom snapshot is corrupt")]
+    Corrupt,
+    #[error("bloom snapshot was taken with a different filter size")]
+    Shape,
+    #[error("bloom snapshot was taken with a different hasher")]
+    Hasher,
 }
 
 #[derive(Debug, Error, PartialEq)]
+#[non_exhaustive]
 pub enum BloomConfigError {
     #[error("expected_items must be greater than zero")]
     InvalidEntries,
@@ -123,6 +413,46 @@ mod tests {
         assert!(cache.known_present(&pid));
     }
 
+    #[test]
+    fn invalidation_and_stats() {
+        let cache = InMemoryPidCache::with_capacity(Duration::from_secs(30), 10);
+        let first = PaymentId::new("0123456789abcdef");
+        let second = PaymentId::new("fedcba9876543210");
+        cache.mark_present(&first);
+        cache.mark_present(&second);
+        assert!(cache.might_contain(&first));
+
+        assert!(cache.invalidate(&first));
+        assert!(!cache.invalidate(&first));
+        assert!(!cache.might_contain(&first));
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.capacity, 10);
+        assert_eq!(stats.ttl, Duration::from_secs(30));
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+
+        assert_eq!(cache.invalidate_all(), 1);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[cfg(feature = "redis-cache")]
+    #[test]
+    fn redis_cache_fails_open_and_keeps_local_state() {
+        // Nothing listens on port 1, so every Redis call errors.
+        let cache = RedisPidCache::new("redis://127.0.0.1:1/", "test").expect("valid url");
+        assert_eq!(cache.key(), "test:pids");
+        let pid = PaymentId::new("0123456789abcdef");
+        assert!(cache.might_contain(&pid));
+
+        cache.negatives.insert(*pid.as_bytes(), ());
+        assert!(!cache.might_contain(&pid));
+        cache.mark_present(&pid);
+        assert!(cache.might_contain(&pid));
+
+        assert!(RedisPidCache::new("not a url", "test").is_err());
+    }
+
     #[test]
     fn bloom_inserts_without_false_negative() {
         let pid = PaymentId::new("0123456789abcdef");
@@ -131,4 +461,40 @@ mod tests {
         bloom.insert(&pid);
         assert!(bloom.might_contain(&pid));
     }
+
+    #[test]
+    fn bloom_snapshot_round_trips() {
+        let pids: Vec<_> = (0..100u64)
+            .map(|n| PaymentId::new(format!("{n:016x}")))
+            .collect();
+        let bloom = PidBloom::new(10_000, 0.01).expect("bloom config ok");
+        for pid in &pids {
+            bloom.insert(pid);
+        }
+        let watermark = BloomWatermark {
+            height: 42,
+            covered: 100,
+        };
+        let snapshot = bloom.snapshot(watermark);
+
+        let restored = PidBloom::new(10_000, 0.01).expect("bloom config ok");
+        assert_eq!(restored.restore(&snapshot), Ok(watermark));
+        assert!(pids.iter().all(|pid| restored.might_contain(pid)));
+
+        let resized = PidBloom::new(20_000, 0.01).expect("bloom config ok");
+        assert_eq!(resized.restore(&snapshot), Err(BloomSnapshotError::Shape));
+
+        let mut corrupt = snapshot.clone();
+        corrupt[SNAPSHOT_HEADER_LEN] ^= 1;
+        assert_eq!(restored.restore(&corrupt), Err(BloomSnapshotError::Corrupt));
+    }
+
+    #[test]
+    fn bloom_reports_its_sizing() {
+        let bloom = PidBloom::new(10_000, 0.01).expect("bloom config ok");
+        assert_eq!(bloom.expected_items(), 10_000);
+        assert_eq!(bloom.false_positive_rate(), 0.01);
+        assert!(bloom.num_bits() >= 10_000);
+        assert!(bloom.num_hashes() > 0);
+    }
 }
@@ -0,0 +1,189 @@
+//! Feature-gated fault injection (`fault-injection`) used to exercise retry
+//! and backoff paths in tests and staging. Decisions come from a seeded
+//! SplitMix64 sequence, so a given seed always fails the same calls.
+
+use std::{
+    env,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use metrics::counter;
+use thiserror::Error;
+
+/// Error rate, added latency, and seed for a [`FaultInjector`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FaultConfig {
+    error_rate: f64,
+    latency: Duration,
+    seed: u64,
+}
+
+impl FaultConfig {
+    pub fn new(error_rate: f64, latency: Duration) -> Result<Self, FaultConfigError> {
+        if !(0.0..=1.0).contains(&error_rate) {
+            return Err(FaultConfigError::InvalidErrorRate(error_rate));
+        }
+        Ok(Self {
+            error_rate,
+            latency,
+            seed: 0,
+        })
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Reads `<PREFIX>_FAULT_ERROR_RATE`, `<PREFIX>_FAULT_LATENCY_MS`, and
+    /// `<PREFIX>_FAULT_SEED`. Returns `None` when none of them are set so
+    /// production environments stay untouched.
+    pub fn from_env(prefix: &str) -> Result<Option<Self>, FaultConfigError> {
+        let upper = prefix.trim().to_ascii_uppercase();
+        let rate = read_var(&format!("{upper}_FAULT_ERROR_RATE"))?;
+        let latency = read_var(&format!("{upper}_FAULT_LATENCY_MS"))?;
+        let seed = read_var(&format!("{upper}_FAULT_SEED"))?;
+        if rate.is_none() && latency.is_none() && seed.is_none() {
+            return Ok(None);
+        }
+        let config = Self::new(
+            rate.unwrap_or(0.0),
+            Duration::from_millis(latency.unwrap_or(0)),
+        )?;
+        Ok(Some(config.with_seed(seed.unwrap_or(0))))
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        self.error_rate
+    }
+
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+fn read_var<T: std::str::FromStr>(key: &str) -> Result<Option<T>, FaultConfigError> {
+    match env::var(key) {
+        Ok(value) if !value.trim().is_empty() => {
+            value
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|_| FaultConfigError::InvalidValue {
+                    key: key.to_string(),
+                    value,
+                })
+        }
+        _ => Ok(None),
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[non_exhaustive]
+pub enum FaultConfigError {
+    #[error("fault error rate must be within [0,1]: {0}")]
+    InvalidErrorRate(f64),
+    #[error("invalid value `{value}` for `{key}`")]
+    InvalidValue { key: String, value: String },
+}
+
+/// Error surfaced by wrappers when the injector decides a call should fail.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("injected fault in `{operation}`")]
+pub struct InjectedFault {
+    pub operation: &'static str,
+}
+
+/// Shared decision engine used by `FlakyStore` and `FlakySource`.
+#[derive(Debug)]
+pub struct FaultInjector {
+    config: FaultConfig,
+    state: AtomicU64,
+}
+
+impl FaultInjector {
+    pub fn new(config: FaultConfig) -> Self {
+        let state = AtomicU64::new(config.seed);
+        Self { config, state }
+    }
+
+    pub fn config(&self) -> &FaultConfig {
+        &self.config
+    }
+
+    /// Applies the configured latency, then decides whether `operation` fails.
+    pub async fn inject(&self, operation: &'static str) -> Result<(), InjectedFault> {
+        if !self.config.latency.is_zero() {
+            tokio::time::sleep(self.config.latency).await;
+        }
+        if self.should_fail() {
+            counter!("fault_injections_total", "operation" => operation).increment(1);
+            return Err(InjectedFault { operation });
+        }
+        Ok(())
+    }
+
+    fn should_fail(&self) -> bool {
+        if self.config.error_rate <= 0.0 {
+            return false;
+        }
+        if self.config.error_rate >= 1.0 {
+            return true;
+        }
+        let sample = splitmix64(self.state.fetch_add(GOLDEN_GAMMA, Ordering::Relaxed));
+        // Use the top 53 bits to build a uniform f64 in [0, 1).
+        let unit = (sample >> 11) as f64 / (1u64 << 53) as f64;
+        unit < self.config.error_rate
+    }
+}
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(GOLDEN_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn extreme_rates_are_absolute() {
+        let never = FaultInjector::new(FaultConfig::new(0.0, Duration::ZERO).unwrap());
+        let always = FaultInjector::new(FaultConfig::new(1.0, Duration::ZERO).unwrap());
+        for _ in 0..100 {
+            assert!(never.inject("op").await.is_ok());
+            assert!(always.inject("op").await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn same_seed_yields_same_sequence() {
+        let config = FaultConfig::new(0.5, Duration::ZERO).unwrap().with_seed(7);
+        let left = FaultInjector::new(config.clone());
+        let right = FaultInjector::new(config);
+        let mut failures = 0;
+        for _ in 0..200 {
+            let l = left.inject("op").await.is_err();
+            assert_eq!(l, right.inject("op").await.is_err());
+            failures += usize::from(l);
+        }
+        assert!((50..150).contains(&failures), "failures = {failures}");
+    }
+
+    #[test]
+    fn rejects_out_of_range_rate() {
+        assert_eq!(
+            FaultConfig::new(1.5, Duration::ZERO).unwrap_err(),
+            FaultConfigError::InvalidErrorRate(1.5)
+        );
+    }
+}
@@ -1,7 +1,11 @@
-//! Shared service helpers such as PID caching and telemetry wiring.
+//! Shared service helpers such as PID caching, rate limiting, request and
+//! token signing, offloading CPU-bound work, and telemetry wiring.
 
+pub mod blocking;
 pub mod cache;
+#[cfg(feature = "fault-injection")]
+pub mod fault;
+pub mod rate_limit;
+pub mod signing;
 pub mod telemetry;
-
-pub use cache::*;
-pub use telemetry::*;
+pub mod token_signing;
@@ -0,0 +1,295 @@
+//! Token-bucket rate limiting keyed by an opaque 32-byte hash, so callers can
+//! limit per PID or per token without the limiter ever seeing either.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use moka::sync::Cache;
+
+/// Bucket shape: up to `burst` requests at once, refilled at `per_minute`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    burst: u32,
+    per_minute: u32,
+}
+
+impl RateLimit {
+    /// Both values must be non-zero; configuration validates them.
+    pub fn new(burst: u32, per_minute: u32) -> Self {
+        Self {
+            burst: burst.max(1),
+            per_minute: per_minute.max(1),
+        }
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    pub fn per_minute(&self) -> u32 {
+        self.per_minute
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        f64::from(self.per_minute) / 60.0
+    }
+
+    /// After this long without requests a bucket is full again, so it can be
+    /// forgotten.
+    pub fn full_refill(&self) -> Duration {
+        Duration::from_secs_f64(f64::from(self.burst) / self.refill_per_sec())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Allowed,
+    Limited { retry_after: Duration },
+}
+
+/// Shared by every replica (Redis) or local to one (in-memory).
+#[async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// Takes one request from `key`'s bucket.
+    async fn acquire(&self, key: &[u8; 32]) -> RateDecision;
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn take(&mut self, limit: &RateLimit, now: Instant) -> RateDecision {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.refill_per_sec()).min(f64::from(limit.burst));
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            RateDecision::Allowed
+        } else {
+            RateDecision::Limited {
+                retry_after: Duration::from_secs_f64((1.0 - self.tokens) / limit.refill_per_sec()),
+            }
+        }
+    }
+}
+
+/// Per-process buckets. Idle buckets are dropped once they would have
+/// refilled; beyond `capacity` keys the least recently used are evicted,
+/// which only ever resets a bucket to full.
+#[derive(Debug)]
+pub struct InMemoryRateLimiter {
+    limit: RateLimit,
+    buckets: Cache<[u8; 32], Arc<Mutex<Bucket>>>,
+}
+
+impl InMemoryRateLimiter {
+    pub const DEFAULT_CAPACITY: u64 = 100_000;
+
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Cache::builder()
+                .time_to_idle(limit.full_refill())
+                .max_capacity(Self::DEFAULT_CAPACITY)
+                .build(),
+        }
+    }
+
+    fn acquire_at(&self, key: &[u8; 32], now: Instant) -> RateDecision {
+        let bucket = self.buckets.get_with(*key, || {
+            Arc::new(Mutex::new(Bucket {
+                tokens: f64::from(self.limit.burst),
+                updated: now,
+            }))
+        });
+        let mut bucket = bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        bucket.take(&self.limit, now)
+    }
+}
+
+#[async_trait]
+impl RateLimiter for InMemoryRateLimiter {
+    async fn acquire(&self, key: &[u8; 32]) -> RateDecision {
+        self.acquire_at(key, Instant::now())
+    }
+}
+
+/// Buckets in Redis hashes, so every replica draws from the same budget.
+/// Checks share one multiplexed connection, so concurrent requests are
+/// pipelined rather than queued. Redis errors fail open: the request is
+/// allowed and counted in `rate_limit_redis_errors_total`.
+#[cfg(feature = "redis-cache")]
+pub struct RedisRateLimiter {
+    client: redis::Client,
+    connection: Mutex<Option<redis::aio::MultiplexedConnection>>,
+    key_prefix: String,
+    limit: RateLimit,
+}
+
+/// Refills, takes one token if it can, and returns the wait in
+/// milliseconds (0 when allowed). The caller's clock is used so the script
+/// stays deterministic.
+#[cfg(feature = "redis-cache")]
+const TOKEN_BUCKET_SCRIPT: &str = r"
+local burst = tonumber(ARGV[1])
+local per_ms = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'at')
+local tokens = tonumber(state[1]) or burst
+local at = tonumber(state[2]) or now
+tokens = math.min(burst, tokens + math.max(0, now - at) * per_ms)
+local wait = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+else
+  wait = math.ceil((1 - tokens) / per_ms)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'at', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(burst / per_ms))
+return wait
+";
+
+#[cfg(feature = "redis-cache")]
+impl RedisRateLimiter {
+    /// Bounds every Redis round trip; the check sits in front of handlers.
+    const IO_TIMEOUT: Duration = Duration::from_millis(250);
+
+    /// Opens a client for `url`; the connection itself is established lazily
+    /// and re-established after errors.
+    pub fn new(url: &str, key_prefix: &str, limit: RateLimit) -> redis::RedisResult<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            connection: Mutex::new(None),
+            key_prefix: format!("{key_prefix}:rate"),
+            limit,
+        })
+    }
+
+    /// Only guards the shared handle; never held across a round trip.
+    fn cached_connection(
+        &self,
+    ) -> std::sync::MutexGuard<'_, Option<redis::aio::MultiplexedConnection>> {
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    async fn query<T: redis::FromRedisValue>(&self, cmd: &redis::Cmd) -> redis::RedisResult<T> {
+        let cached = self.cached_connection().clone();
+        let mut connection = match cached {
+            Some(connection) => connection,
+            None => {
+                let connection = self
+                    .client
+                    .get_multiplexed_async_connection_with_config(
+                        &redis::AsyncConnectionConfig::new()
+                            .set_connection_timeout(Self::IO_TIMEOUT)
+                            .set_response_timeout(Self::IO_TIMEOUT),
+                    )
+                    .await?;
+                *self.cached_connection() = Some(connection.clone());
+                connection
+            }
+        };
+        let result = cmd.query_async(&mut connection).await;
+        if result.is_err() {
+            // Drop the connection so the next call reconnects.
+            *self.cached_connection() = None;
+        }
+        result
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+#[async_trait]
+impl RateLimiter for RedisRateLimiter {
+    async fn acquire(&self, key: &[u8; 32]) -> RateDecision {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        let per_ms = self.limit.refill_per_sec() / 1000.0;
+        let mut cmd = redis::cmd("EVAL");
+        cmd.arg(TOKEN_BUCKET_SCRIPT)
+            .arg(1)
+            .arg(format!("{}:{}", self.key_prefix, hex::encode(key)))
+            .arg(self.limit.burst)
+            .arg(per_ms.to_string())
+            .arg(now_ms);
+        match self.query::<u64>(&cmd).await {
+            Ok(0) => RateDecision::Allowed,
+            Ok(wait_ms) => RateDecision::Limited {
+                retry_after: Duration::from_millis(wait_ms),
+            },
+            Err(err) => {
+                tracing::warn!(error = %err, "redis rate limit check failed; allowing request");
+                metrics::counter!("rate_limit_redis_errors_total").increment(1);
+                RateDecision::Allowed
+            }
+        }
+    }
+}
+
+#[cfg(feature = "redis-cache")]
+impl std::fmt::Debug for RedisRateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisRateLimiter")
+            .field("key_prefix", &self.key_prefix)
+            .field("limit", &self.limit)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_burst_then_refills() {
+        // One request per second sustained.
+        let limiter = InMemoryRateLimiter::new(RateLimit::new(2, 60));
+        let key = [1u8; 32];
+        let start = Instant::now();
+        assert_eq!(limiter.acquire_at(&key, start), RateDecision::Allowed);
+        assert_eq!(limiter.acquire_at(&key, start), RateDecision::Allowed);
+        let RateDecision::Limited { retry_after } = limiter.acquire_at(&key, start) else {
+            panic!("burst exhausted");
+        };
+        assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(1));
+
+        // Other keys have their own bucket.
+        assert_eq!(limiter.acquire_at(&[2u8; 32], start), RateDecision::Allowed);
+
+        let later = start + Duration::from_secs(1);
+        assert_eq!(limiter.acquire_at(&key, later), RateDecision::Allowed);
+        assert!(matches!(
+            limiter.acquire_at(&key, later),
+            RateDecision::Limited { .. }
+        ));
+    }
+
+    #[test]
+    fn full_refill_covers_the_whole_burst() {
+        assert_eq!(
+            RateLimit::new(30, 60).full_refill(),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[cfg(feature = "redis-cache")]
+    #[tokio::test]
+    async fn redis_limiter_fails_open() {
+        // Nothing listens on port 1, so every Redis call errors.
+        let limiter =
+            RedisRateLimiter::new("redis://127.0.0.1:1/", "test", RateLimit::new(1, 1)).unwrap();
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire(&[0u8; 32]).await, RateDecision::Allowed);
+        }
+    }
+}
@@ -1,18 +1,28 @@
-use std::{env, net::SocketAddr, sync::Arc};
+use std::{backtrace::Backtrace, env, panic, thread};
+#[cfg(feature = "prometheus")]
+use std::{net::SocketAddr, sync::Arc};
 
+use metrics::counter;
+#[cfg(feature = "prometheus")]
 use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
 use once_cell::sync::OnceCell;
 use thiserror::Error;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 static SUBSCRIBER_INSTALLED: OnceCell<()> = OnceCell::new();
+#[cfg(feature = "prometheus")]
 static METRICS_HANDLE: OnceCell<Arc<PrometheusHandle>> = OnceCell::new();
+static PANIC_HOOK_INSTALLED: OnceCell<()> = OnceCell::new();
+#[cfg(feature = "otlp")]
+static TRACER_PROVIDER: OnceCell<opentelemetry_sdk::trace::TracerProvider> = OnceCell::new();
 
 /// Shared observability options for binaries.
 #[derive(Debug, Clone)]
 pub struct TelemetryConfig {
     log_filter: String,
     metrics_address: Option<String>,
+    otlp_endpoint: Option<String>,
+    service_name: String,
 }
 
 impl TelemetryConfig {
@@ -23,18 +33,23 @@ impl TelemetryConfig {
         let upper = prefix.trim().to_ascii_uppercase();
         let log_key = format!("{}_LOG_FILTER", upper);
         let metrics_key = format!("{}_METRICS_ADDRESS", upper);
+        let otlp_key = format!("{}_OTLP_ENDPOINT", upper);
+        let non_empty = |key: String| {
+            env::var(key).ok().and_then(|value| {
+                if value.trim().is_empty() {
+                    None
+                } else {
+                    Some(value)
+                }
+            })
+        };
 
         let log_filter = env::var(log_key).unwrap_or_else(|_| "info".to_string());
-        let metrics_address = env::var(metrics_key).ok().and_then(|value| {
-            if value.trim().is_empty() {
-                None
-            } else {
-                Some(value)
-            }
-        });
         Self {
             log_filter,
-            metrics_address,
+            metrics_address: non_empty(metrics_key),
+            otlp_endpoint: non_empty(otlp_key),
+            service_name: format!("anon-ticket-{}", upper.to_ascii_lowercase()),
         }
     }
 
@@ -45,26 +60,103 @@ impl TelemetryConfig {
     pub fn metrics_address(&self) -> Option<&str> {
         self.metrics_address.as_deref()
     }
+
+    /// OTLP/gRPC collector spans are exported to, e.g.
+    /// `http://127.0.0.1:4317`. Needs the `otlp` feature.
+    pub fn otlp_endpoint(&self) -> Option<&str> {
+        self.otlp_endpoint.as_deref()
+    }
+
+    /// `service.name` reported with exported spans, e.g. `anon-ticket-api`.
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
 }
 
 /// Guard returned after telemetry initialization.
 #[derive(Clone)]
 pub struct TelemetryGuard {
+    #[cfg(feature = "prometheus")]
     metrics: Arc<PrometheusHandle>,
 }
 
 impl TelemetryGuard {
+    /// Prometheus text exposition; empty without the `prometheus` feature.
     pub fn render_metrics(&self) -> String {
-        self.metrics.render()
+        #[cfg(feature = "prometheus")]
+        return self.metrics.render();
+        #[cfg(not(feature = "prometheus"))]
+        String::new()
+    }
+
+    /// Flushes spans still queued for the OTLP exporter. Call once, right
+    /// before the process exits; a no-op when no exporter was installed.
+    pub async fn shutdown(&self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = TRACER_PROVIDER.get().cloned() {
+            // Shutdown blocks until the queue drains, while the exporter's
+            // connection is driven by the calling runtime.
+            match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => tracing::warn!(%err, "failed to flush trace spans"),
+                Err(err) => tracing::warn!(%err, "trace flush task failed"),
+            }
+        }
     }
 }
 
 /// Centralized helper to wire up tracing + metrics exporters once per process.
 pub fn init_telemetry(config: &TelemetryConfig) -> Result<TelemetryGuard, TelemetryError> {
     install_tracing(config)?;
-    let metrics = install_metrics(config)?;
+    #[cfg(feature = "prometheus")]
+    let guard = TelemetryGuard {
+        metrics: install_metrics(config)?,
+    };
+    #[cfg(not(feature = "prometheus"))]
+    let guard = {
+        if let Some(addr) = config.metrics_address() {
+            tracing::warn!(
+                addr,
+                "built without the prometheus feature; metrics address ignored"
+            );
+        }
+        TelemetryGuard {}
+    };
+    #[cfg(not(feature = "otlp"))]
+    if let Some(endpoint) = config.otlp_endpoint() {
+        tracing::warn!(
+            endpoint,
+            "built without the otlp feature; OTLP endpoint ignored"
+        );
+    }
+    install_panic_hook();
+
+    Ok(guard)
+}
 
-    Ok(TelemetryGuard { metrics })
+/// Replaces the default stderr panic message with an error-level event and a
+/// `process_panics_total` increment, so panics in spawned tasks (which the
+/// runtime otherwise swallows) reach logs and alerts.
+fn install_panic_hook() {
+    PANIC_HOOK_INSTALLED.get_or_init(|| {
+        panic::set_hook(Box::new(|info| {
+            counter!("process_panics_total").increment(1);
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("<non-string panic payload>");
+            let location = info.location().map(ToString::to_string).unwrap_or_default();
+            let thread = thread::current();
+            tracing::error!(
+                thread = thread.name().unwrap_or("<unnamed>"),
+                %location,
+                backtrace = %Backtrace::force_capture(),
+                "panicked: {message}"
+            );
+        }));
+    });
 }
 
 fn install_tracing(config: &TelemetryConfig) -> Result<(), TelemetryError> {
@@ -76,9 +168,12 @@ fn install_tracing(config: &TelemetryConfig) -> Result<(), TelemetryError> {
         .map_err(|err| TelemetryError::InvalidLogFilter(err.to_string()))?;
 
     if SUBSCRIBER_INSTALLED.set(()).is_ok() {
-        tracing_subscriber::registry()
+        let registry = tracing_subscriber::registry()
             .with(env_filter)
-            .with(tracing_subscriber::fmt::layer().with_target(true))
+            .with(tracing_subscriber::fmt::layer().with_target(true));
+        #[cfg(feature = "otlp")]
+        let registry = registry.with(otlp_layer(config)?);
+        registry
             .try_init()
             .map_err(|err| TelemetryError::Tracing(err.to_string()))?;
     }
@@ -86,6 +181,83 @@ fn install_tracing(config: &TelemetryConfig) -> Result<(), TelemetryError> {
     Ok(())
 }
 
+/// Exports spans in batches to `config.otlp_endpoint()`. The batch task
+/// runs on its own thread, so it works under actix's single-threaded
+/// runtime too; building the gRPC channel still needs a tokio context.
+#[cfg(feature = "otlp")]
+fn otlp_layer<S>(
+    config: &TelemetryConfig,
+) -> Result<
+    Option<tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>>,
+    TelemetryError,
+>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let Some(endpoint) = config.otlp_endpoint() else {
+        return Ok(None);
+    };
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|err| TelemetryError::Otlp(err.to_string()))?;
+    let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::TokioCurrentThread)
+        .with_resource(opentelemetry_sdk::Resource::new([
+            opentelemetry::KeyValue::new("service.name", config.service_name().to_string()),
+        ]))
+        .build();
+    let tracer = provider.tracer("anon_ticket");
+    opentelemetry::global::set_text_map_propagator(
+        opentelemetry_sdk::propagation::TraceContextPropagator::new(),
+    );
+    let _ = TRACER_PROVIDER.set(provider);
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Makes the W3C trace context found through `header` (`traceparent`,
+/// `tracestate`) the parent of `span`, so it joins the caller's trace.
+/// A no-op without the `otlp` feature or an exporter.
+pub fn set_remote_parent<'a>(span: &tracing::Span, header: impl Fn(&str) -> Option<&'a str>) {
+    #[cfg(feature = "otlp")]
+    {
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+            let carrier: std::collections::HashMap<String, String> = propagator
+                .fields()
+                .filter_map(|name| Some((name.to_string(), header(name)?.to_string())))
+                .collect();
+            propagator.extract(&carrier)
+        });
+        span.set_parent(parent);
+    }
+    #[cfg(not(feature = "otlp"))]
+    let _ = (span, header);
+}
+
+/// W3C trace context headers for the current span, to forward on outgoing
+/// requests. Empty without the `otlp` feature or an exporter.
+pub fn trace_headers() -> Vec<(String, String)> {
+    #[cfg(feature = "otlp")]
+    {
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let mut headers = std::collections::HashMap::new();
+        opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&tracing::Span::current().context(), &mut headers)
+        });
+        headers.into_iter().collect()
+    }
+    #[cfg(not(feature = "otlp"))]
+    Vec::new()
+}
+
+#[cfg(feature = "prometheus")]
 fn install_metrics(config: &TelemetryConfig) -> Result<Arc<PrometheusHandle>, TelemetryError> {
     METRICS_HANDLE
         .get_or_try_init(|| {
@@ -107,6 +279,7 @@ fn install_metrics(config: &TelemetryConfig) -> Result<Arc<PrometheusHandle>, Te
 }
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum TelemetryError {
     #[error("invalid log filter: {0}")]
     InvalidLogFilter(String),
@@ -116,6 +289,8 @@ pub enum TelemetryError {
     InvalidMetricsAddress(String, String),
     #[error("failed to install metrics recorder: {0}")]
     Metrics(String),
+    #[error("failed to build OTLP exporter: {0}")]
+    Otlp(String),
 }
 
 #[cfg(test)]
@@ -141,11 +316,50 @@ mod tests {
         let _guard = ENV_GUARD.lock().unwrap();
         env::set_var("API_LOG_FILTER", "debug");
         env::set_var("API_METRICS_ADDRESS", "127.0.0.1:9898");
+        env::set_var("API_OTLP_ENDPOINT", "http://127.0.0.1:4317");
         let cfg = TelemetryConfig::from_env("API");
         assert_eq!(cfg.log_filter(), "debug");
         assert_eq!(cfg.metrics_address(), Some("127.0.0.1:9898"));
+        assert_eq!(cfg.otlp_endpoint(), Some("http://127.0.0.1:4317"));
+        assert_eq!(cfg.service_name(), "anon-ticket-api");
         env::remove_var("API_LOG_FILTER");
         env::remove_var("API_METRICS_ADDRESS");
+        env::remove_var("API_OTLP_ENDPOINT");
+    }
+
+    #[cfg(feature = "otlp")]
+    #[test]
+    fn trace_context_is_continued_and_forwarded() {
+        use opentelemetry::trace::TracerProvider as _;
+
+        opentelemetry::global::set_text_map_propagator(
+            opentelemetry_sdk::propagation::TraceContextPropagator::new(),
+        );
+        let provider = opentelemetry_sdk::trace::TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        tracing::subscriber::with_default(subscriber, || {
+            let incoming = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+            let span = tracing::info_span!("redeem");
+            set_remote_parent(&span, |name| (name == "traceparent").then_some(incoming));
+            let headers = span.in_scope(trace_headers);
+            let (_, forwarded) = headers
+                .iter()
+                .find(|(name, _)| name == "traceparent")
+                .expect("traceparent forwarded");
+            // Same trace, but our span is the new parent.
+            assert!(forwarded.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+            assert!(!forwarded.contains("00f067aa0ba902b7"));
+        });
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn panics_are_counted() {
+        let telemetry = init_telemetry(&TelemetryConfig::from_env("DOMAIN_TEST")).unwrap();
+        let joined = thread::spawn(|| panic!("boom")).join();
+        assert!(joined.is_err());
+        assert!(telemetry.render_metrics().contains("process_panics_total"));
     }
 
     #[test]
@@ -0,0 +1,139 @@
+//! HMAC-signed service tokens that downstream services verify offline.
+//!
+//! A signed token reads `at1.<kid>.<sub>.<balance>.<exp>.<mac>`: the id of
+//! the [`TokenSigningKey`] that signed it, the SHA3-256 of the stored token
+//! (hex), the balance, the expiry as a Unix timestamp, and the hex
+//! HMAC-SHA3-256 of everything before the last dot. Services holding the key
+//! check it without a lookup; revocations come from the revocation list,
+//! which names tokens by the same `sub`.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha3::Sha3_256;
+use thiserror::Error;
+
+use crate::config::TokenSigningKey;
+pub use crate::model::{token_digest, token_subject};
+
+type HmacSha3 = Hmac<Sha3_256>;
+
+const VERSION: &str = "at1";
+
+/// What a signed token vouches for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedTokenClaims {
+    pub kid: String,
+    /// See [`token_subject`].
+    pub sub: String,
+    pub balance: i64,
+    /// Unix timestamp after which the token is no longer accepted.
+    pub exp: i64,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SignedTokenError {
+    #[error("signed token is malformed")]
+    Malformed,
+    #[error("signed token names unknown key `{0}`")]
+    UnknownKey(String),
+    #[error("signed token signature does not match")]
+    BadSignature,
+    #[error("signed token expired")]
+    Expired,
+}
+
+fn mac(key: &TokenSigningKey, payload: &str) -> HmacSha3 {
+    let mut mac =
+        HmacSha3::new_from_slice(key.secret().as_bytes()).expect("hmac accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// Signs `claims` with `key`; `claims.kid` is replaced by the key's id.
+pub fn sign_token(key: &TokenSigningKey, claims: &SignedTokenClaims) -> String {
+    let payload = format!(
+        "{VERSION}.{}.{}.{}.{}",
+        key.id(),
+        claims.sub,
+        claims.balance,
+        claims.exp
+    );
+    let signature = hex::encode(mac(key, &payload).finalize().into_bytes());
+    format!("{payload}.{signature}")
+}
+
+/// Checks `token` against whichever of `keys` it names and its expiry.
+pub fn verify_signed_token(
+    keys: &[TokenSigningKey],
+    token: &str,
+    now: DateTime<Utc>,
+) -> Result<SignedTokenClaims, SignedTokenError> {
+    let (payload, signature) = token.rsplit_once('.').ok_or(SignedTokenError::Malformed)?;
+    let fields: Vec<&str> = payload.split('.').collect();
+    let [VERSION, kid, sub, balance, exp] = fields[..] else {
+        return Err(SignedTokenError::Malformed);
+    };
+    let signature = hex::decode(signature).map_err(|_| SignedTokenError::Malformed)?;
+    let key = keys
+        .iter()
+        .find(|key| key.id() == kid)
+        .ok_or_else(|| SignedTokenError::UnknownKey(kid.to_string()))?;
+    mac(key, payload)
+        .verify_slice(&signature)
+        .map_err(|_| SignedTokenError::BadSignature)?;
+    let claims = SignedTokenClaims {
+        kid: kid.to_string(),
+        sub: sub.to_string(),
+        balance: balance.parse().map_err(|_| SignedTokenError::Malformed)?,
+        exp: exp.parse().map_err(|_| SignedTokenError::Malformed)?,
+    };
+    if claims.exp <= now.timestamp() {
+        return Err(SignedTokenError::Expired);
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ServiceToken;
+
+    #[test]
+    fn signed_tokens_verify_with_any_listed_key() {
+        let current = TokenSigningKey::new("k2", "0123456789abcdef0123456789abcdef");
+        let previous = TokenSigningKey::new("k1", "fedcba9876543210fedcba9876543210");
+        let now = Utc::now();
+        let claims = SignedTokenClaims {
+            kid: String::new(),
+            sub: token_subject(&ServiceToken::from_bytes([7; 32])),
+            balance: 42,
+            exp: now.timestamp() + 60,
+        };
+        let keyring = [current.clone(), previous.clone()];
+
+        let signed = sign_token(&previous, &claims);
+        let verified = verify_signed_token(&keyring, &signed, now).unwrap();
+        assert_eq!(verified.kid, "k1");
+        assert_eq!(verified.balance, 42);
+        assert_eq!(verified.sub, claims.sub);
+
+        assert_eq!(
+            verify_signed_token(std::slice::from_ref(&current), &signed, now),
+            Err(SignedTokenError::UnknownKey("k1".into()))
+        );
+        let forged = signed.replace(".42.", ".4200.");
+        assert_eq!(
+            verify_signed_token(&keyring, &forged, now),
+            Err(SignedTokenError::BadSignature)
+        );
+        assert_eq!(
+            verify_signed_token(&keyring, "at1.k1.abc", now),
+            Err(SignedTokenError::Malformed)
+        );
+        let later = now + chrono::Duration::seconds(60);
+        assert_eq!(
+            verify_signed_token(&keyring, &sign_token(&current, &claims), later),
+            Err(SignedTokenError::Expired)
+        );
+    }
+}
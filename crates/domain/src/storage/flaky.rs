@@ -0,0 +1,597 @@
+//! `FlakyStore`: a storage wrapper that injects latency and failures in front
+//! of any backend implementing the domain storage traits.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::model::{
+    AuditEventRecord, AuditFilter, CheckoutTerms, ClaimOutcome, CreditStatus, IdempotentResponse,
+    MergeTokensRequest, MonitorCheckpoint, NewAuditEvent, NewCheckoutBinding, NewPayment,
+    NewPaymentQuote, NewRefund, NewServiceToken, NewTokenCredit, OutboxStats, PaymentFilter,
+    PaymentId, PaymentQuote, PaymentRecord, PaymentTransfer, PendingTransfer, Refund,
+    RefundOutcome, RefundStatus, RenewalRecord, ReorgRollback, RevocationCursor,
+    RevokeTokenRequest, ServiceToken, ServiceTokenRecord, SkippedTransfer, SplitTokenOutcome,
+    SplitTokenRequest, SubaddressRecord, SuspendTokenRequest, TokenCredit, TokenFilter,
+    TokenReview, TombstoneKind, TombstoneRecord, WebhookDeadLetter, WebhookDelivery, WebhookEvent,
+};
+use crate::services::fault::{FaultConfig, FaultInjector, InjectedFault};
+use crate::storage::traits::{
+    AuditStore, CheckoutStore, CreditStore, IdempotencyStore, MonitorStateStore, PaymentStore,
+    PendingPaymentStore, QuoteStore, RefundStore, RenewalStore, SkippedTransferStore, StorageError,
+    StorageResult, SubaddressStore, TokenStore, TombstoneStore, WebhookStore,
+};
+
+#[derive(Debug)]
+pub struct FlakyStore<S> {
+    inner: S,
+    faults: FaultInjector,
+}
+
+impl<S> FlakyStore<S> {
+    pub fn new(inner: S, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            faults: FaultInjector::new(config),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    async fn gate(&self, operation: &'static str) -> StorageResult<()> {
+        self.faults
+            .inject(operation)
+            .await
+            .map_err(|err: InjectedFault| StorageError::Database(err.to_string()))
+    }
+}
+
+#[async_trait]
+impl<S: PaymentStore> PaymentStore for FlakyStore<S> {
+    async fn insert_payment(&self, payment: NewPayment) -> StorageResult<()> {
+        self.gate("insert_payment").await?;
+        self.inner.insert_payment(payment).await
+    }
+
+    async fn claim_payment(&self, pid: &PaymentId) -> StorageResult<Option<ClaimOutcome>> {
+        self.gate("claim_payment").await?;
+        self.inner.claim_payment(pid).await
+    }
+
+    async fn find_payment(&self, pid: &PaymentId) -> StorageResult<Option<PaymentRecord>> {
+        self.gate("find_payment").await?;
+        self.inner.find_payment(pid).await
+    }
+
+    async fn unclaim_payment(&self, pid: &PaymentId) -> StorageResult<Option<PaymentRecord>> {
+        self.gate("unclaim_payment").await?;
+        self.inner.unclaim_payment(pid).await
+    }
+
+    async fn list_payments(
+        &self,
+        filter: &PaymentFilter,
+        after: Option<&PaymentId>,
+        limit: u64,
+    ) -> StorageResult<Vec<PaymentRecord>> {
+        self.gate("list_payments").await?;
+        self.inner.list_payments(filter, after, limit).await
+    }
+
+    async fn find_transfers(&self, pid: &PaymentId) -> StorageResult<Vec<PaymentTransfer>> {
+        self.gate("find_transfers").await?;
+        self.inner.find_transfers(pid).await
+    }
+
+    async fn find_payment_by_txid(&self, txid: &str) -> StorageResult<Option<PaymentRecord>> {
+        self.gate("find_payment_by_txid").await?;
+        self.inner.find_payment_by_txid(txid).await
+    }
+}
+
+#[async_trait]
+impl<S: TokenStore> TokenStore for FlakyStore<S> {
+    async fn insert_token(&self, token: NewServiceToken) -> StorageResult<ServiceTokenRecord> {
+        self.gate("insert_token").await?;
+        self.inner.insert_token(token).await
+    }
+
+    async fn find_token(&self, token: &ServiceToken) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.gate("find_token").await?;
+        self.inner.find_token(token).await
+    }
+
+    async fn find_token_by_pid(
+        &self,
+        pid: &PaymentId,
+    ) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.gate("find_token_by_pid").await?;
+        self.inner.find_token_by_pid(pid).await
+    }
+
+    async fn revoke_token(
+        &self,
+        request: RevokeTokenRequest,
+    ) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.gate("revoke_token").await?;
+        self.inner.revoke_token(request).await
+    }
+
+    async fn suspend_token(
+        &self,
+        request: SuspendTokenRequest,
+    ) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.gate("suspend_token").await?;
+        self.inner.suspend_token(request).await
+    }
+
+    async fn resume_token(
+        &self,
+        token: &ServiceToken,
+    ) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.gate("resume_token").await?;
+        self.inner.resume_token(token).await
+    }
+
+    async fn lift_lapsed_suspensions(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        self.gate("lift_lapsed_suspensions").await?;
+        self.inner.lift_lapsed_suspensions(now).await
+    }
+
+    async fn debit_token(&self, token: &ServiceToken, amount: i64) -> StorageResult<Option<i64>> {
+        self.gate("debit_token").await?;
+        self.inner.debit_token(token, amount).await
+    }
+
+    async fn split_token(
+        &self,
+        request: SplitTokenRequest,
+    ) -> StorageResult<Option<SplitTokenOutcome>> {
+        self.gate("split_token").await?;
+        self.inner.split_token(request).await
+    }
+
+    async fn merge_tokens(
+        &self,
+        request: MergeTokensRequest,
+    ) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.gate("merge_tokens").await?;
+        self.inner.merge_tokens(request).await
+    }
+
+    async fn find_pinned_expiry(
+        &self,
+        token: &ServiceToken,
+    ) -> StorageResult<Option<DateTime<Utc>>> {
+        self.gate("find_pinned_expiry").await?;
+        self.inner.find_pinned_expiry(token).await
+    }
+
+    async fn record_first_validation(
+        &self,
+        token: &ServiceToken,
+        at: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        self.gate("record_first_validation").await?;
+        self.inner.record_first_validation(token, at).await
+    }
+
+    async fn find_token_review(&self, token: &ServiceToken) -> StorageResult<Option<TokenReview>> {
+        self.gate("find_token_review").await?;
+        self.inner.find_token_review(token).await
+    }
+
+    async fn expired_tokens(
+        &self,
+        before: DateTime<Utc>,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceToken>> {
+        self.gate("expired_tokens").await?;
+        self.inner.expired_tokens(before, limit).await
+    }
+
+    async fn revoked_tokens(
+        &self,
+        after: Option<RevocationCursor>,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceTokenRecord>> {
+        self.gate("revoked_tokens").await?;
+        self.inner.revoked_tokens(after, limit).await
+    }
+
+    async fn list_tokens(
+        &self,
+        filter: &TokenFilter,
+        after: Option<&ServiceToken>,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceTokenRecord>> {
+        self.gate("list_tokens").await?;
+        self.inner.list_tokens(filter, after, limit).await
+    }
+
+    async fn find_tokens_in_range(
+        &self,
+        first: &ServiceToken,
+        last: &ServiceToken,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceTokenRecord>> {
+        self.gate("find_tokens_in_range").await?;
+        self.inner.find_tokens_in_range(first, last, limit).await
+    }
+}
+
+#[async_trait]
+impl<S: RenewalStore> RenewalStore for FlakyStore<S> {
+    async fn find_renewals(&self, pid: &PaymentId) -> StorageResult<Vec<RenewalRecord>> {
+        self.gate("find_renewals").await?;
+        self.inner.find_renewals(pid).await
+    }
+}
+
+#[async_trait]
+impl<S: PendingPaymentStore> PendingPaymentStore for FlakyStore<S> {
+    async fn record_pending(&self, transfers: Vec<PendingTransfer>) -> StorageResult<()> {
+        self.gate("record_pending").await?;
+        self.inner.record_pending(transfers).await
+    }
+
+    async fn find_pending(&self, pid: &PaymentId) -> StorageResult<Vec<PendingTransfer>> {
+        self.gate("find_pending").await?;
+        self.inner.find_pending(pid).await
+    }
+
+    async fn prune_pending(&self, before: DateTime<Utc>) -> StorageResult<u64> {
+        self.gate("prune_pending").await?;
+        self.inner.prune_pending(before).await
+    }
+}
+
+#[async_trait]
+impl<S: SkippedTransferStore> SkippedTransferStore for FlakyStore<S> {
+    async fn record_skipped(&self, transfer: SkippedTransfer) -> StorageResult<()> {
+        self.gate("record_skipped").await?;
+        self.inner.record_skipped(transfer).await
+    }
+
+    async fn find_skipped(&self, txid: &str) -> StorageResult<Option<SkippedTransfer>> {
+        self.gate("find_skipped").await?;
+        self.inner.find_skipped(txid).await
+    }
+
+    async fn list_skipped(
+        &self,
+        after: Option<&str>,
+        limit: u64,
+    ) -> StorageResult<Vec<SkippedTransfer>> {
+        self.gate("list_skipped").await?;
+        self.inner.list_skipped(after, limit).await
+    }
+
+    async fn delete_skipped(&self, txid: &str) -> StorageResult<bool> {
+        self.gate("delete_skipped").await?;
+        self.inner.delete_skipped(txid).await
+    }
+}
+
+#[async_trait]
+impl<S: SubaddressStore> SubaddressStore for FlakyStore<S> {
+    async fn insert_subaddress(&self, subaddress: SubaddressRecord) -> StorageResult<()> {
+        self.gate("insert_subaddress").await?;
+        self.inner.insert_subaddress(subaddress).await
+    }
+
+    async fn find_subaddress_by_index(
+        &self,
+        account_index: u32,
+        minor_index: u32,
+    ) -> StorageResult<Option<SubaddressRecord>> {
+        self.gate("find_subaddress_by_index").await?;
+        self.inner
+            .find_subaddress_by_index(account_index, minor_index)
+            .await
+    }
+
+    async fn find_subaddress_by_pid(
+        &self,
+        pid: &PaymentId,
+    ) -> StorageResult<Option<SubaddressRecord>> {
+        self.gate("find_subaddress_by_pid").await?;
+        self.inner.find_subaddress_by_pid(pid).await
+    }
+}
+
+#[async_trait]
+impl<S: MonitorStateStore> MonitorStateStore for FlakyStore<S> {
+    async fn last_processed_height(&self, wallet: &str) -> StorageResult<Option<u64>> {
+        self.gate("last_processed_height").await?;
+        self.inner.last_processed_height(wallet).await
+    }
+
+    async fn upsert_last_processed_height(&self, wallet: &str, height: u64) -> StorageResult<u64> {
+        self.gate("upsert_last_processed_height").await?;
+        self.inner
+            .upsert_last_processed_height(wallet, height)
+            .await
+    }
+
+    async fn record_checkpoint(&self, checkpoint: MonitorCheckpoint) -> StorageResult<()> {
+        self.gate("record_checkpoint").await?;
+        self.inner.record_checkpoint(checkpoint).await
+    }
+
+    async fn recent_checkpoints(&self, limit: u64) -> StorageResult<Vec<MonitorCheckpoint>> {
+        self.gate("recent_checkpoints").await?;
+        self.inner.recent_checkpoints(limit).await
+    }
+
+    async fn find_checkpoint_at_or_below(
+        &self,
+        height: u64,
+    ) -> StorageResult<Option<MonitorCheckpoint>> {
+        self.gate("find_checkpoint_at_or_below").await?;
+        self.inner.find_checkpoint_at_or_below(height).await
+    }
+
+    async fn rollback_to_height(&self, height: u64) -> StorageResult<ReorgRollback> {
+        self.gate("rollback_to_height").await?;
+        self.inner.rollback_to_height(height).await
+    }
+}
+
+#[async_trait]
+impl<S: TombstoneStore> TombstoneStore for FlakyStore<S> {
+    async fn purge_payment(&self, pid: &PaymentId) -> StorageResult<bool> {
+        self.gate("purge_payment").await?;
+        self.inner.purge_payment(pid).await
+    }
+
+    async fn purge_token(&self, token: &ServiceToken) -> StorageResult<bool> {
+        self.gate("purge_token").await?;
+        self.inner.purge_token(token).await
+    }
+
+    async fn find_tombstone(
+        &self,
+        kind: TombstoneKind,
+        id_hash: &[u8; 32],
+    ) -> StorageResult<Option<TombstoneRecord>> {
+        self.gate("find_tombstone").await?;
+        self.inner.find_tombstone(kind, id_hash).await
+    }
+
+    async fn tombstones_since(
+        &self,
+        since: DateTime<Utc>,
+        limit: u64,
+    ) -> StorageResult<Vec<TombstoneRecord>> {
+        self.gate("tombstones_since").await?;
+        self.inner.tombstones_since(since, limit).await
+    }
+
+    async fn prune_tombstones(&self, before: DateTime<Utc>) -> StorageResult<u64> {
+        self.gate("prune_tombstones").await?;
+        self.inner.prune_tombstones(before).await
+    }
+}
+
+#[async_trait]
+impl<S: CheckoutStore> CheckoutStore for FlakyStore<S> {
+    async fn insert_checkout(&self, binding: NewCheckoutBinding) -> StorageResult<bool> {
+        self.gate("insert_checkout").await?;
+        self.inner.insert_checkout(binding).await
+    }
+
+    async fn find_checkout_secret_hash(&self, pid: &PaymentId) -> StorageResult<Option<[u8; 32]>> {
+        self.gate("find_checkout_secret_hash").await?;
+        self.inner.find_checkout_secret_hash(pid).await
+    }
+
+    async fn find_checkout_terms(&self, pid: &PaymentId) -> StorageResult<Option<CheckoutTerms>> {
+        self.gate("find_checkout_terms").await?;
+        self.inner.find_checkout_terms(pid).await
+    }
+}
+
+#[async_trait]
+impl<S: QuoteStore> QuoteStore for FlakyStore<S> {
+    async fn insert_quote(&self, quote: NewPaymentQuote) -> StorageResult<bool> {
+        self.gate("insert_quote").await?;
+        self.inner.insert_quote(quote).await
+    }
+
+    async fn find_quote(&self, pid: &PaymentId) -> StorageResult<Option<PaymentQuote>> {
+        self.gate("find_quote").await?;
+        self.inner.find_quote(pid).await
+    }
+
+    async fn mark_quote_paid(&self, pid: &PaymentId) -> StorageResult<bool> {
+        self.gate("mark_quote_paid").await?;
+        self.inner.mark_quote_paid(pid).await
+    }
+
+    async fn settle_quote(&self, pid: &PaymentId) -> StorageResult<Option<PaymentQuote>> {
+        self.gate("settle_quote").await?;
+        self.inner.settle_quote(pid).await
+    }
+
+    async fn expire_quotes(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        self.gate("expire_quotes").await?;
+        self.inner.expire_quotes(now).await
+    }
+}
+
+#[async_trait]
+impl<S: CreditStore> CreditStore for FlakyStore<S> {
+    async fn record_credit(&self, credit: NewTokenCredit) -> StorageResult<Option<TokenCredit>> {
+        self.gate("record_credit").await?;
+        self.inner.record_credit(credit).await
+    }
+
+    async fn find_credit(&self, id: i64) -> StorageResult<Option<TokenCredit>> {
+        self.gate("find_credit").await?;
+        self.inner.find_credit(id).await
+    }
+
+    async fn list_credits(
+        &self,
+        status: Option<CreditStatus>,
+        before: Option<i64>,
+        limit: u64,
+    ) -> StorageResult<Vec<TokenCredit>> {
+        self.gate("list_credits").await?;
+        self.inner.list_credits(status, before, limit).await
+    }
+
+    async fn resolve_credit(
+        &self,
+        id: i64,
+        status: CreditStatus,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<TokenCredit>> {
+        self.gate("resolve_credit").await?;
+        self.inner.resolve_credit(id, status, at).await
+    }
+
+    async fn release_expired_holds(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        self.gate("release_expired_holds").await?;
+        self.inner.release_expired_holds(now).await
+    }
+}
+
+#[async_trait]
+impl<S: RefundStore> RefundStore for FlakyStore<S> {
+    async fn record_refund(&self, refund: NewRefund) -> StorageResult<Option<Refund>> {
+        self.gate("record_refund").await?;
+        self.inner.record_refund(refund).await
+    }
+
+    async fn find_refund(&self, id: i64) -> StorageResult<Option<Refund>> {
+        self.gate("find_refund").await?;
+        self.inner.find_refund(id).await
+    }
+
+    async fn list_refunds(
+        &self,
+        status: Option<RefundStatus>,
+        before: Option<i64>,
+        limit: u64,
+    ) -> StorageResult<Vec<Refund>> {
+        self.gate("list_refunds").await?;
+        self.inner.list_refunds(status, before, limit).await
+    }
+
+    async fn claim_refund(&self, id: i64) -> StorageResult<Option<Refund>> {
+        self.gate("claim_refund").await?;
+        self.inner.claim_refund(id).await
+    }
+
+    async fn resolve_refund(
+        &self,
+        id: i64,
+        outcome: RefundOutcome,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<Refund>> {
+        self.gate("resolve_refund").await?;
+        self.inner.resolve_refund(id, outcome, at).await
+    }
+}
+
+#[async_trait]
+impl<S: WebhookStore> WebhookStore for FlakyStore<S> {
+    async fn record_webhook_event(&self, event: WebhookEvent) -> StorageResult<WebhookEvent> {
+        self.gate("record_webhook_event").await?;
+        self.inner.record_webhook_event(event).await
+    }
+
+    async fn find_webhook_event(&self, id: &str) -> StorageResult<Option<WebhookEvent>> {
+        self.gate("find_webhook_event").await?;
+        self.inner.find_webhook_event(id).await
+    }
+
+    async fn record_delivery(&self, delivery: WebhookDelivery) -> StorageResult<()> {
+        self.gate("record_delivery").await?;
+        self.inner.record_delivery(delivery).await
+    }
+
+    async fn find_delivery(
+        &self,
+        event_id: &str,
+        url: &str,
+    ) -> StorageResult<Option<WebhookDelivery>> {
+        self.gate("find_delivery").await?;
+        self.inner.find_delivery(event_id, url).await
+    }
+
+    async fn list_deliveries(&self, event_id: &str) -> StorageResult<Vec<WebhookDelivery>> {
+        self.gate("list_deliveries").await?;
+        self.inner.list_deliveries(event_id).await
+    }
+
+    async fn request_redelivery(&self, event_id: &str) -> StorageResult<u64> {
+        self.gate("request_redelivery").await?;
+        self.inner.request_redelivery(event_id).await
+    }
+
+    async fn claim_redeliveries(&self, limit: u64) -> StorageResult<Vec<WebhookDelivery>> {
+        self.gate("claim_redeliveries").await?;
+        self.inner.claim_redeliveries(limit).await
+    }
+
+    async fn outbox_stats(&self) -> StorageResult<OutboxStats> {
+        self.gate("outbox_stats").await?;
+        self.inner.outbox_stats().await
+    }
+
+    async fn record_dead_letter(&self, letter: WebhookDeadLetter) -> StorageResult<()> {
+        self.gate("record_dead_letter").await?;
+        self.inner.record_dead_letter(letter).await
+    }
+
+    async fn recent_dead_letters(&self, limit: u64) -> StorageResult<Vec<WebhookDeadLetter>> {
+        self.gate("recent_dead_letters").await?;
+        self.inner.recent_dead_letters(limit).await
+    }
+}
+
+#[async_trait]
+impl<S: AuditStore> AuditStore for FlakyStore<S> {
+    async fn record_audit_event(&self, event: NewAuditEvent) -> StorageResult<()> {
+        self.gate("record_audit_event").await?;
+        self.inner.record_audit_event(event).await
+    }
+
+    async fn list_audit_events(
+        &self,
+        filter: &AuditFilter,
+        before: Option<i64>,
+        limit: u64,
+    ) -> StorageResult<Vec<AuditEventRecord>> {
+        self.gate("list_audit_events").await?;
+        self.inner.list_audit_events(filter, before, limit).await
+    }
+}
+
+#[async_trait]
+impl<S: IdempotencyStore> IdempotencyStore for FlakyStore<S> {
+    async fn insert_idempotent_response(
+        &self,
+        response: IdempotentResponse,
+    ) -> StorageResult<bool> {
+        self.gate("insert_idempotent_response").await?;
+        self.inner.insert_idempotent_response(response).await
+    }
+
+    async fn find_idempotent_response(
+        &self,
+        key_hash: &[u8; 32],
+    ) -> StorageResult<Option<IdempotentResponse>> {
+        self.gate("find_idempotent_response").await?;
+        self.inner.find_idempotent_response(key_hash).await
+    }
+
+    async fn prune_idempotent_responses(&self, before: DateTime<Utc>) -> StorageResult<u64> {
+        self.gate("prune_idempotent_responses").await?;
+        self.inner.prune_idempotent_responses(before).await
+    }
+}
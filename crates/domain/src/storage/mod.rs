@@ -1,5 +1,13 @@
 //! Storage trait definitions consumed by API and monitor crates.
 
+#[cfg(feature = "fault-injection")]
+pub mod flaky;
 pub mod traits;
 
-pub use traits::*;
+#[cfg(feature = "fault-injection")]
+pub use flaky::FlakyStore;
+pub use traits::{
+    AuditStore, CheckoutStore, CreditStore, IdempotencyStore, MonitorStateStore, PaymentStore,
+    PendingPaymentStore, QuoteStore, RefundStore, RenewalStore, SkippedTransferStore, StorageError,
+    StorageResult, SubaddressStore, TokenStore, TombstoneStore, WebhookStore,
+};
@@ -1,18 +1,30 @@
 use async_trait::async_trait;
 use thiserror::Error;
 
+use chrono::{DateTime, Utc};
+
 use crate::model::{
-    ClaimOutcome, NewPayment, NewServiceToken, PaymentId, PaymentRecord, RevokeTokenRequest,
-    ServiceToken, ServiceTokenRecord,
+    AuditEventRecord, AuditFilter, CheckoutTerms, ClaimOutcome, CreditStatus, IdempotentResponse,
+    MergeTokensRequest, MonitorCheckpoint, NewAuditEvent, NewCheckoutBinding, NewPayment,
+    NewPaymentQuote, NewRefund, NewServiceToken, NewTokenCredit, OutboxStats, PaymentFilter,
+    PaymentId, PaymentQuote, PaymentRecord, PaymentTransfer, PendingTransfer, Refund,
+    RefundOutcome, RefundStatus, RenewalRecord, ReorgRollback, RevocationCursor,
+    RevokeTokenRequest, ServiceToken, ServiceTokenRecord, SkippedTransfer, SplitTokenOutcome,
+    SplitTokenRequest, SubaddressRecord, SuspendTokenRequest, TokenCredit, TokenFilter,
+    TokenReview, TombstoneKind, TombstoneRecord, WebhookDeadLetter, WebhookDelivery, WebhookEvent,
 };
 
 /// Common result alias for storage operations.
 pub type StorageResult<T> = Result<T, StorageError>;
 
 #[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum StorageError {
     #[error("database error: {0}")]
     Database(String),
+    /// A write was attempted through a handle opened in read-only mode.
+    #[error("storage is read-only")]
+    ReadOnly,
 }
 
 impl StorageError {
@@ -23,23 +35,379 @@ impl StorageError {
 
 #[async_trait]
 pub trait PaymentStore: Send + Sync {
+    /// Records a transfer to `payment.pid`. The first creates the payment;
+    /// later ones are added to its amount while it is unclaimed and kept as
+    /// renewals once it is claimed. A txid seen before changes nothing,
+    /// except that a reorged payment's first transfer, mined again, makes it
+    /// claimed again.
     async fn insert_payment(&self, payment: NewPayment) -> StorageResult<()>;
+    /// Returns `None` unless the payment is confirmed and unclaimed.
     async fn claim_payment(&self, pid: &PaymentId) -> StorageResult<Option<ClaimOutcome>>;
     async fn find_payment(&self, pid: &PaymentId) -> StorageResult<Option<PaymentRecord>>;
+    /// Reverts a claimed payment to unclaimed. Returns `None` when the payment
+    /// does not exist or is not currently claimed.
+    async fn unclaim_payment(&self, pid: &PaymentId) -> StorageResult<Option<PaymentRecord>>;
+    /// Up to `limit` payments matching `filter`, ordered by PID and starting
+    /// after `after` (keyset pagination).
+    async fn list_payments(
+        &self,
+        filter: &PaymentFilter,
+        after: Option<&PaymentId>,
+        limit: u64,
+    ) -> StorageResult<Vec<PaymentRecord>>;
+    /// Transfers added to `pid`'s payment after the first, oldest first.
+    async fn find_transfers(&self, pid: &PaymentId) -> StorageResult<Vec<PaymentTransfer>>;
+    /// The payment `txid` was credited to, as its first transfer, a later
+    /// one, or a renewal.
+    async fn find_payment_by_txid(&self, txid: &str) -> StorageResult<Option<PaymentRecord>>;
 }
 
 #[async_trait]
 pub trait TokenStore: Send + Sync {
     async fn insert_token(&self, token: NewServiceToken) -> StorageResult<ServiceTokenRecord>;
     async fn find_token(&self, token: &ServiceToken) -> StorageResult<Option<ServiceTokenRecord>>;
+    /// Token issued for `pid`, if any. Used where the stored token may be
+    /// passphrase-wrapped and cannot be re-derived from the payment alone.
+    async fn find_token_by_pid(&self, pid: &PaymentId)
+        -> StorageResult<Option<ServiceTokenRecord>>;
     async fn revoke_token(
         &self,
         request: RevokeTokenRequest,
     ) -> StorageResult<Option<ServiceTokenRecord>>;
+    /// Suspends an active token in one guarded update. Returns `None` when
+    /// the token is missing or its stored state does not allow
+    /// [`TokenTransition::Suspend`](crate::model::TokenTransition::Suspend).
+    async fn suspend_token(
+        &self,
+        request: SuspendTokenRequest,
+    ) -> StorageResult<Option<ServiceTokenRecord>>;
+    /// Lifts a suspension, keeping the token's balance and expiry. Returns
+    /// `None` when the token is missing, not suspended, or revoked.
+    async fn resume_token(&self, token: &ServiceToken)
+        -> StorageResult<Option<ServiceTokenRecord>>;
+    /// Clears suspensions whose `suspended_until` is not after `now`; returns
+    /// how many were lifted.
+    async fn lift_lapsed_suspensions(&self, now: DateTime<Utc>) -> StorageResult<u64>;
+    /// Subtracts `amount` from the token's balance in one guarded update and
+    /// returns the new balance. Returns `None`, debiting nothing, when the
+    /// token is missing, revoked, suspended, expired, or short of `amount`.
+    async fn debit_token(&self, token: &ServiceToken, amount: i64) -> StorageResult<Option<i64>>;
+    /// Debits the source token and inserts the new one atomically. The new
+    /// token inherits the source's expiry. Returns `None` when the source is
+    /// missing, revoked, suspended, expired, or short of `amount`.
+    async fn split_token(
+        &self,
+        request: SplitTokenRequest,
+    ) -> StorageResult<Option<SplitTokenOutcome>>;
+    /// Revokes every source token and inserts the merged one atomically. The
+    /// merged token expires with the latest-expiring source, or never if any
+    /// source has no lifetime. Returns `None`
+    /// (and changes nothing) when any source is missing, already revoked,
+    /// suspended, or expired.
+    async fn merge_tokens(
+        &self,
+        request: MergeTokensRequest,
+    ) -> StorageResult<Option<ServiceTokenRecord>>;
+    /// Expiry pinned at issuance for tokens not backed by a payment of their
+    /// own (merged tokens).
+    async fn find_pinned_expiry(
+        &self,
+        token: &ServiceToken,
+    ) -> StorageResult<Option<DateTime<Utc>>>;
+    /// Records the first successful validation of `token`. Returns `true`
+    /// only for the call that recorded it; later validations return `false`.
+    async fn record_first_validation(
+        &self,
+        token: &ServiceToken,
+        at: DateTime<Utc>,
+    ) -> StorageResult<bool>;
+    async fn find_token_review(&self, token: &ServiceToken) -> StorageResult<Option<TokenReview>>;
+    /// Up to `limit` tokens whose lifetime ended before `before`, oldest
+    /// first, for the janitor to purge.
+    async fn expired_tokens(
+        &self,
+        before: DateTime<Utc>,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceToken>>;
+    /// Up to `limit` revoked tokens past `after` (from the first revocation
+    /// when `None`), by revocation time and then token. Advance the cursor
+    /// with [`RevocationCursor::advance`]; one with `skip` 0 starts at a time.
+    async fn revoked_tokens(
+        &self,
+        after: Option<RevocationCursor>,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceTokenRecord>>;
+    /// Up to `limit` tokens matching `filter`, ordered by token and starting
+    /// after `after` (keyset pagination).
+    async fn list_tokens(
+        &self,
+        filter: &TokenFilter,
+        after: Option<&ServiceToken>,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceTokenRecord>>;
+    /// Up to `limit` tokens from `first` to `last` inclusive, ordered by
+    /// token. [`ServiceToken::prefix_range`] gives the bounds for a prefix.
+    async fn find_tokens_in_range(
+        &self,
+        first: &ServiceToken,
+        last: &ServiceToken,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceTokenRecord>>;
+}
+
+/// Repeat payments to an already-paid PID. `insert_payment` records them here
+/// instead of dropping them.
+#[async_trait]
+pub trait RenewalStore: Send + Sync {
+    /// Renewals for `pid`, oldest first.
+    async fn find_renewals(&self, pid: &PaymentId) -> StorageResult<Vec<RenewalRecord>>;
+}
+
+/// Transfers the monitor saw before it could ingest them. `insert_payment`
+/// drops a transfer's row once it is ingested.
+#[async_trait]
+pub trait PendingPaymentStore: Send + Sync {
+    /// Inserts each transfer, or refreshes the one with the same txid while
+    /// keeping its `first_seen_at`.
+    async fn record_pending(&self, transfers: Vec<PendingTransfer>) -> StorageResult<()>;
+    /// Pending transfers to `pid`, oldest first.
+    async fn find_pending(&self, pid: &PaymentId) -> StorageResult<Vec<PendingTransfer>>;
+    /// Drops transfers not seen since `before`, such as ones that left the
+    /// mempool unmined; returns how many.
+    async fn prune_pending(&self, before: DateTime<Utc>) -> StorageResult<u64>;
+}
+
+/// Journal of transfers the monitor skipped as unparseable, kept until an
+/// operator re-processes or dismisses them.
+#[async_trait]
+pub trait SkippedTransferStore: Send + Sync {
+    /// Inserts the transfer, or refreshes the one with the same txid while
+    /// keeping its `skipped_at`.
+    async fn record_skipped(&self, transfer: SkippedTransfer) -> StorageResult<()>;
+    async fn find_skipped(&self, txid: &str) -> StorageResult<Option<SkippedTransfer>>;
+    /// Up to `limit` transfers ordered by txid, starting after `after`.
+    async fn list_skipped(
+        &self,
+        after: Option<&str>,
+        limit: u64,
+    ) -> StorageResult<Vec<SkippedTransfer>>;
+    /// Returns whether a row was removed.
+    async fn delete_skipped(&self, txid: &str) -> StorageResult<bool>;
+}
+
+/// Subaddress-to-PID mapping for subaddress detection mode.
+#[async_trait]
+pub trait SubaddressStore: Send + Sync {
+    /// Fails if the PID or the subaddress index is already mapped.
+    async fn insert_subaddress(&self, subaddress: SubaddressRecord) -> StorageResult<()>;
+    async fn find_subaddress_by_index(
+        &self,
+        account_index: u32,
+        minor_index: u32,
+    ) -> StorageResult<Option<SubaddressRecord>>;
+    async fn find_subaddress_by_pid(
+        &self,
+        pid: &PaymentId,
+    ) -> StorageResult<Option<SubaddressRecord>>;
 }
 
 #[async_trait]
 pub trait MonitorStateStore: Send + Sync {
-    async fn last_processed_height(&self) -> StorageResult<Option<u64>>;
-    async fn upsert_last_processed_height(&self, height: u64) -> StorageResult<()>;
+    /// Cursor of the wallet named `wallet`; each wallet the monitor polls
+    /// has its own.
+    async fn last_processed_height(&self, wallet: &str) -> StorageResult<Option<u64>>;
+    /// Advances `wallet`'s cursor to `height`, never backwards: a lower value
+    /// is ignored. Returns the stored cursor, which is above `height` when
+    /// another writer already got further. Reorg rewinds go through
+    /// `rollback_to_height` instead.
+    async fn upsert_last_processed_height(&self, wallet: &str, height: u64) -> StorageResult<u64>;
+    /// Appends to the checkpoint history, replacing any checkpoint at the
+    /// same height. Only the most recent checkpoints are retained.
+    async fn record_checkpoint(&self, checkpoint: MonitorCheckpoint) -> StorageResult<()>;
+    /// Up to `limit` checkpoints, highest first.
+    async fn recent_checkpoints(&self, limit: u64) -> StorageResult<Vec<MonitorCheckpoint>>;
+    /// Newest checkpoint at or below `height`: a restart point for rescans.
+    async fn find_checkpoint_at_or_below(
+        &self,
+        height: u64,
+    ) -> StorageResult<Option<MonitorCheckpoint>>;
+    /// Undoes ingestion at or above `height` after a reorg, atomically:
+    /// checkpoints and renewals there are dropped, unclaimed payments are
+    /// removed so they are re-verified once re-mined, claimed ones are marked
+    /// reorged and their tokens flagged for review, and every wallet cursor
+    /// past `height` is rewound to it; cursors below it are left alone.
+    async fn rollback_to_height(&self, height: u64) -> StorageResult<ReorgRollback>;
+}
+
+/// Deletion path for payments and tokens. Every purge removes the row and
+/// writes a tombstone in the same transaction so accidental deletes stay
+/// detectable and downstream caches can be told about them.
+#[async_trait]
+pub trait TombstoneStore: Send + Sync {
+    /// Returns `false` when the payment did not exist.
+    async fn purge_payment(&self, pid: &PaymentId) -> StorageResult<bool>;
+    /// Returns `false` when the token did not exist. Purging the last token
+    /// of a claimed payment after it expired marks the payment expired.
+    async fn purge_token(&self, token: &ServiceToken) -> StorageResult<bool>;
+    async fn find_tombstone(
+        &self,
+        kind: TombstoneKind,
+        id_hash: &[u8; 32],
+    ) -> StorageResult<Option<TombstoneRecord>>;
+    /// Tombstones created at or after `since`, oldest first.
+    async fn tombstones_since(
+        &self,
+        since: DateTime<Utc>,
+        limit: u64,
+    ) -> StorageResult<Vec<TombstoneRecord>>;
+    /// Drops tombstones older than `before`; returns how many were removed.
+    async fn prune_tombstones(&self, before: DateTime<Utc>) -> StorageResult<u64>;
+}
+
+/// Checkout bindings tie a server-issued PID to a client secret that must be
+/// presented at redemption.
+#[async_trait]
+pub trait CheckoutStore: Send + Sync {
+    /// Returns `false` when the PID is already bound.
+    async fn insert_checkout(&self, binding: NewCheckoutBinding) -> StorageResult<bool>;
+    async fn find_checkout_secret_hash(&self, pid: &PaymentId) -> StorageResult<Option<[u8; 32]>>;
+    async fn find_checkout_terms(&self, pid: &PaymentId) -> StorageResult<Option<CheckoutTerms>>;
+}
+
+/// Payment quotes: PIDs issued with an expected amount and a deadline. The
+/// monitor consults them before ingesting a transfer.
+#[async_trait]
+pub trait QuoteStore: Send + Sync {
+    /// Returns `false` when the PID already has a quote.
+    async fn insert_quote(&self, quote: NewPaymentQuote) -> StorageResult<bool>;
+    async fn find_quote(&self, pid: &PaymentId) -> StorageResult<Option<PaymentQuote>>;
+    /// Moves an open or expired quote to paid. Returns `false` when the PID
+    /// has no quote or it is already paid.
+    async fn mark_quote_paid(&self, pid: &PaymentId) -> StorageResult<bool>;
+    /// Marks the quote for `pid` paid and records everything ingested for
+    /// the PID, the payment with its transfers plus renewals, as its received
+    /// amount. Returns the updated quote; `None` if the PID has no quote or
+    /// no payment. Recomputed from stored payments, so repeating it is
+    /// harmless.
+    async fn settle_quote(&self, pid: &PaymentId) -> StorageResult<Option<PaymentQuote>>;
+    /// Moves open quotes whose deadline is before `now` to expired; returns
+    /// how many changed.
+    async fn expire_quotes(&self, now: DateTime<Utc>) -> StorageResult<u64>;
+}
+
+/// Overpayment excess recorded against tokens, either already credited or
+/// held for refund.
+#[async_trait]
+pub trait CreditStore: Send + Sync {
+    /// Returns `None`, keeping the existing record, when the token already
+    /// has a credit.
+    async fn record_credit(&self, credit: NewTokenCredit) -> StorageResult<Option<TokenCredit>>;
+    async fn find_credit(&self, id: i64) -> StorageResult<Option<TokenCredit>>;
+    /// Newest first, starting below the credit id `before`.
+    async fn list_credits(
+        &self,
+        status: Option<CreditStatus>,
+        before: Option<i64>,
+        limit: u64,
+    ) -> StorageResult<Vec<TokenCredit>>;
+    /// Moves a held credit to `status`; crediting adds its amount to the
+    /// token's balance in the same transaction. Returns `None` when the id
+    /// is unknown or the credit is no longer held.
+    async fn resolve_credit(
+        &self,
+        id: i64,
+        status: CreditStatus,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<TokenCredit>>;
+    /// Credits every hold whose grace period ended before `now`; returns
+    /// how many were released.
+    async fn release_expired_holds(&self, now: DateTime<Utc>) -> StorageResult<u64>;
+}
+
+/// Payments, or parts of them, to be sent back to the payer.
+#[async_trait]
+pub trait RefundStore: Send + Sync {
+    /// Returns `None` when the payment is unknown or the refunds on it that
+    /// have not failed would add up to more than its amount.
+    async fn record_refund(&self, refund: NewRefund) -> StorageResult<Option<Refund>>;
+    async fn find_refund(&self, id: i64) -> StorageResult<Option<Refund>>;
+    /// Newest first, starting below the refund id `before`.
+    async fn list_refunds(
+        &self,
+        status: Option<RefundStatus>,
+        before: Option<i64>,
+        limit: u64,
+    ) -> StorageResult<Vec<Refund>>;
+    /// Moves a pending refund to sending, so only one sender transfers it.
+    /// Returns `None` when the id is unknown or the refund is not pending.
+    async fn claim_refund(&self, id: i64) -> StorageResult<Option<Refund>>;
+    /// Closes a pending or sending refund. Returns `None` when the id is
+    /// unknown or the refund is already closed. Once sent refunds cover the
+    /// whole payment, it is marked refunded.
+    async fn resolve_refund(
+        &self,
+        id: i64,
+        outcome: RefundOutcome,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<Refund>>;
+}
+
+/// Outbound webhook events, their per-URL deliveries, and the deliveries
+/// the monitor gave up on.
+#[async_trait]
+pub trait WebhookStore: Send + Sync {
+    /// Stores `event` unless its txid already has one; returns the stored
+    /// event either way.
+    async fn record_webhook_event(&self, event: WebhookEvent) -> StorageResult<WebhookEvent>;
+    async fn find_webhook_event(&self, id: &str) -> StorageResult<Option<WebhookEvent>>;
+    /// Replaces any earlier state for the same event and URL.
+    async fn record_delivery(&self, delivery: WebhookDelivery) -> StorageResult<()>;
+    async fn find_delivery(
+        &self,
+        event_id: &str,
+        url: &str,
+    ) -> StorageResult<Option<WebhookDelivery>>;
+    /// Ordered by URL.
+    async fn list_deliveries(&self, event_id: &str) -> StorageResult<Vec<WebhookDelivery>>;
+    /// Marks the event's delivered and failed deliveries as
+    /// `requested`, leaving those in flight alone.
+    /// Returns how many were marked.
+    async fn request_redelivery(&self, event_id: &str) -> StorageResult<u64>;
+    /// Moves up to `limit` requested deliveries back to `pending` and
+    /// returns them. A delivery is handed to
+    /// one caller only.
+    async fn claim_redeliveries(&self, limit: u64) -> StorageResult<Vec<WebhookDelivery>>;
+    async fn outbox_stats(&self) -> StorageResult<OutboxStats>;
+    /// Replaces any earlier record for the same event and URL.
+    async fn record_dead_letter(&self, letter: WebhookDeadLetter) -> StorageResult<()>;
+    /// Newest first.
+    async fn recent_dead_letters(&self, limit: u64) -> StorageResult<Vec<WebhookDeadLetter>>;
+}
+
+/// Append-only audit trail of redeems and operator actions. There is
+/// deliberately no way to change or remove an event.
+#[async_trait]
+pub trait AuditStore: Send + Sync {
+    async fn record_audit_event(&self, event: NewAuditEvent) -> StorageResult<()>;
+    /// Newest first, starting below the event id `before`.
+    async fn list_audit_events(
+        &self,
+        filter: &AuditFilter,
+        before: Option<i64>,
+        limit: u64,
+    ) -> StorageResult<Vec<AuditEventRecord>>;
+}
+
+/// Responses kept for client `Idempotency-Key`s.
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Returns `false`, keeping the existing record, when the key is taken.
+    async fn insert_idempotent_response(&self, response: IdempotentResponse)
+        -> StorageResult<bool>;
+    async fn find_idempotent_response(
+        &self,
+        key_hash: &[u8; 32],
+    ) -> StorageResult<Option<IdempotentResponse>>;
+    /// Drops responses recorded before `before`; returns how many were removed.
+    async fn prune_idempotent_responses(&self, before: DateTime<Utc>) -> StorageResult<u64>;
 }
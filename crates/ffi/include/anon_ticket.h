@@ -0,0 +1,58 @@
+/*
+ * C interface to anon_ticket_ffi (crates/ffi/src/lib.rs).
+ *
+ * Every function returns an AnonTicketStatus and, on ANON_TICKET_OK, writes a
+ * NUL-terminated string into the caller's buffer. On any other status the
+ * buffer is left untouched. Nothing returned needs to be freed.
+ */
+
+#ifndef ANON_TICKET_H
+#define ANON_TICKET_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Buffer sizes, terminator included, that always fit each result. */
+#define ANON_TICKET_PID_BUFFER_LEN 17
+#define ANON_TICKET_TOKEN_BUFFER_LEN 65
+#define ANON_TICKET_INTEGRATED_ADDRESS_BUFFER_LEN 107
+
+/* Values are stable; new ones are only appended. */
+typedef enum AnonTicketStatus {
+  ANON_TICKET_OK = 0,
+  ANON_TICKET_NULL_POINTER = 1,
+  ANON_TICKET_INVALID_UTF8 = 2,
+  ANON_TICKET_INVALID_PID = 3,
+  ANON_TICKET_INVALID_ADDRESS = 4,
+  ANON_TICKET_NON_STANDARD_ADDRESS = 5,
+  ANON_TICKET_BUFFER_TOO_SMALL = 6,
+  ANON_TICKET_RANDOM_UNAVAILABLE = 7,
+} AnonTicketStatus;
+
+/* Static description of `status`; never NULL, never freed. Values this build
+ * does not know get "unknown status". */
+const char *anon_ticket_status_message(int status);
+
+/* A random 16-hex-character payment ID. */
+AnonTicketStatus anon_ticket_generate_pid(char *out, size_t out_len);
+
+/* The integrated address paying `pid` into the standard `primary_address`. */
+AnonTicketStatus anon_ticket_build_integrated_address(const char *primary_address,
+                                                      const char *pid,
+                                                      char *out,
+                                                      size_t out_len);
+
+/* The 64-hex-character service token the API issues for `pid` paid by `txid`. */
+AnonTicketStatus anon_ticket_derive_token(const char *pid,
+                                          const char *txid,
+                                          char *out,
+                                          size_t out_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ANON_TICKET_H */
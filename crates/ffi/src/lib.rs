@@ -0,0 +1,287 @@
+//! C ABI over the domain helpers a non-Rust service needs to create a payment
+//! request on its own: generating a PID, building its integrated address, and
+//! deriving the service token the API will hand out for it.
+//!
+//! Every export returns an [`AnonTicketStatus`] and writes its result, NUL
+//! terminated, into a caller-owned buffer, so nothing allocated here ever has
+//! to be freed by the caller. `include/anon_ticket.h` declares the same
+//! surface for C, cgo, and PHP FFI.
+
+use std::ffi::{c_char, c_int, CStr};
+
+use anon_ticket_domain::integrated_address::{build_integrated_address, IntegratedAddressError};
+use anon_ticket_domain::model::{derive_service_token, PaymentId};
+
+/// Buffer size, terminator included, that always fits a PID.
+pub const ANON_TICKET_PID_BUFFER_LEN: usize = 17;
+/// Buffer size, terminator included, that always fits a service token.
+pub const ANON_TICKET_TOKEN_BUFFER_LEN: usize = 65;
+/// Buffer size, terminator included, that always fits an integrated address.
+pub const ANON_TICKET_INTEGRATED_ADDRESS_BUFFER_LEN: usize = 107;
+
+/// Result of every export. Values are stable: new ones are only appended.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnonTicketStatus {
+    Ok = 0,
+    /// A required pointer argument was NULL.
+    NullPointer = 1,
+    /// A string argument was not valid UTF-8.
+    InvalidUtf8 = 2,
+    /// The PID is not 16 hex characters.
+    InvalidPid = 3,
+    /// The primary address does not parse.
+    InvalidAddress = 4,
+    /// The primary address is an integrated address or a subaddress.
+    NonStandardAddress = 5,
+    /// The output buffer cannot hold the result and its terminator; nothing
+    /// was written.
+    BufferTooSmall = 6,
+    /// The OS random number generator failed.
+    RandomUnavailable = 7,
+}
+
+impl AnonTicketStatus {
+    const fn message(self) -> &'static CStr {
+        match self {
+            AnonTicketStatus::Ok => c"ok",
+            AnonTicketStatus::NullPointer => c"required pointer argument is null",
+            AnonTicketStatus::InvalidUtf8 => c"string argument is not valid utf-8",
+            AnonTicketStatus::InvalidPid => c"pid must be 16 hex characters",
+            AnonTicketStatus::InvalidAddress => c"invalid primary address",
+            AnonTicketStatus::NonStandardAddress => {
+                c"primary address must be a standard address (not integrated/subaddress)"
+            }
+            AnonTicketStatus::BufferTooSmall => c"output buffer too small",
+            AnonTicketStatus::RandomUnavailable => c"random number generator unavailable",
+        }
+    }
+}
+
+impl TryFrom<c_int> for AnonTicketStatus {
+    type Error = c_int;
+
+    fn try_from(value: c_int) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => AnonTicketStatus::Ok,
+            1 => AnonTicketStatus::NullPointer,
+            2 => AnonTicketStatus::InvalidUtf8,
+            3 => AnonTicketStatus::InvalidPid,
+            4 => AnonTicketStatus::InvalidAddress,
+            5 => AnonTicketStatus::NonStandardAddress,
+            6 => AnonTicketStatus::BufferTooSmall,
+            7 => AnonTicketStatus::RandomUnavailable,
+            _ => return Err(value),
+        })
+    }
+}
+
+impl From<IntegratedAddressError> for AnonTicketStatus {
+    fn from(err: IntegratedAddressError) -> Self {
+        match err {
+            IntegratedAddressError::NonStandardPrimary => AnonTicketStatus::NonStandardAddress,
+            IntegratedAddressError::InvalidPaymentId(_) => AnonTicketStatus::InvalidPid,
+            _ => AnonTicketStatus::InvalidAddress,
+        }
+    }
+}
+
+/// Static, NUL-terminated description of `status`, for logs. Never NULL and
+/// never freed.
+///
+/// Takes a plain `int` rather than the enum, since C callers can pass any
+/// value; ones this build does not know get `"unknown status"`.
+#[no_mangle]
+pub extern "C" fn anon_ticket_status_message(status: c_int) -> *const c_char {
+    AnonTicketStatus::try_from(status)
+        .map_or(c"unknown status", AnonTicketStatus::message)
+        .as_ptr()
+}
+
+/// Writes a random PID (16 hex characters) into `out`.
+///
+/// # Safety
+///
+/// `out` must be NULL or valid for writes of `out_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn anon_ticket_generate_pid(
+    out: *mut c_char,
+    out_len: usize,
+) -> AnonTicketStatus {
+    match PaymentId::generate() {
+        Ok(pid) => write_str(&pid.to_hex(), out, out_len),
+        Err(_) => AnonTicketStatus::RandomUnavailable,
+    }
+}
+
+/// Writes the integrated address paying `pid` into the standard
+/// `primary_address` into `out`.
+///
+/// # Safety
+///
+/// `primary_address` and `pid` must be NULL or NUL-terminated strings, and
+/// `out` NULL or valid for writes of `out_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn anon_ticket_build_integrated_address(
+    primary_address: *const c_char,
+    pid: *const c_char,
+    out: *mut c_char,
+    out_len: usize,
+) -> AnonTicketStatus {
+    let result = (|| {
+        let primary_address = read_str(primary_address)?;
+        let pid = read_pid(pid)?;
+        Ok(build_integrated_address(primary_address, &pid)?)
+    })();
+    match result {
+        Ok(address) => write_str(&address, out, out_len),
+        Err(status) => status,
+    }
+}
+
+/// Writes the service token the API issues for `pid` paid by `txid` (64 hex
+/// characters) into `out`.
+///
+/// # Safety
+///
+/// `pid` and `txid` must be NULL or NUL-terminated strings, and `out` NULL or
+/// valid for writes of `out_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn anon_ticket_derive_token(
+    pid: *const c_char,
+    txid: *const c_char,
+    out: *mut c_char,
+    out_len: usize,
+) -> AnonTicketStatus {
+    let result = (|| {
+        let pid = read_pid(pid)?;
+        let txid = read_str(txid)?;
+        Ok(derive_service_token(&pid, txid))
+    })();
+    match result {
+        Ok(token) => write_str(&token.to_hex(), out, out_len),
+        Err(status) => status,
+    }
+}
+
+/// # Safety
+///
+/// `ptr` must be NULL or a NUL-terminated string that outlives `'a`.
+unsafe fn read_str<'a>(ptr: *const c_char) -> Result<&'a str, AnonTicketStatus> {
+    if ptr.is_null() {
+        return Err(AnonTicketStatus::NullPointer);
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| AnonTicketStatus::InvalidUtf8)
+}
+
+/// # Safety
+///
+/// As [`read_str`].
+unsafe fn read_pid(ptr: *const c_char) -> Result<PaymentId, AnonTicketStatus> {
+    PaymentId::parse(read_str(ptr)?).map_err(|_| AnonTicketStatus::InvalidPid)
+}
+
+/// # Safety
+///
+/// `out` must be NULL or valid for writes of `out_len` bytes.
+unsafe fn write_str(value: &str, out: *mut c_char, out_len: usize) -> AnonTicketStatus {
+    if out.is_null() {
+        return AnonTicketStatus::NullPointer;
+    }
+    if value.len() >= out_len {
+        return AnonTicketStatus::BufferTooSmall;
+    }
+    std::ptr::copy_nonoverlapping(value.as_ptr(), out.cast::<u8>(), value.len());
+    *out.add(value.len()) = 0;
+    AnonTicketStatus::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use anon_ticket_domain::integrated_address::decode_integrated_address;
+
+    use super::*;
+
+    const PRIMARY_MAINNET: &str =
+        "4ADT1BtbxqEWeMKp9GgPr2NeyJXXtNxvoDawpyA4WpzFcGcoHUvXeijE66DNfohE9r1bQYaBiQjEtKE7CtkTdLwiDznFzra";
+
+    fn output(buf: &[c_char]) -> &str {
+        unsafe { CStr::from_ptr(buf.as_ptr()) }.to_str().unwrap()
+    }
+
+    #[test]
+    fn exports_match_the_domain_helpers() {
+        let mut pid = [0 as c_char; ANON_TICKET_PID_BUFFER_LEN];
+        let status = unsafe { anon_ticket_generate_pid(pid.as_mut_ptr(), pid.len()) };
+        assert_eq!(status, AnonTicketStatus::Ok);
+        let parsed = PaymentId::parse(output(&pid)).unwrap();
+
+        let primary = CString::new(PRIMARY_MAINNET).unwrap();
+        let mut address = [0 as c_char; ANON_TICKET_INTEGRATED_ADDRESS_BUFFER_LEN];
+        let status = unsafe {
+            anon_ticket_build_integrated_address(
+                primary.as_ptr(),
+                pid.as_ptr(),
+                address.as_mut_ptr(),
+                address.len(),
+            )
+        };
+        assert_eq!(status, AnonTicketStatus::Ok);
+        let (standard, embedded) = decode_integrated_address(output(&address)).unwrap();
+        assert_eq!((standard.as_str(), &embedded), (PRIMARY_MAINNET, &parsed));
+
+        let txid = CString::new("ab".repeat(32)).unwrap();
+        let mut token = [0 as c_char; ANON_TICKET_TOKEN_BUFFER_LEN];
+        let status = unsafe {
+            anon_ticket_derive_token(pid.as_ptr(), txid.as_ptr(), token.as_mut_ptr(), token.len())
+        };
+        assert_eq!(status, AnonTicketStatus::Ok);
+        assert_eq!(
+            output(&token),
+            derive_service_token(&parsed, &"ab".repeat(32)).to_hex()
+        );
+    }
+
+    #[test]
+    fn failures_report_a_status_and_leave_the_buffer_alone() {
+        let primary = CString::new(PRIMARY_MAINNET).unwrap();
+        let pid = CString::new("0123456789abcdef").unwrap();
+        let bad_pid = CString::new("0123").unwrap();
+        let mut out = [1 as c_char; ANON_TICKET_INTEGRATED_ADDRESS_BUFFER_LEN];
+        let build = |primary: *const c_char, pid: *const c_char, out: &mut [c_char], len| unsafe {
+            anon_ticket_build_integrated_address(primary, pid, out.as_mut_ptr(), len)
+        };
+
+        assert_eq!(
+            build(primary.as_ptr(), bad_pid.as_ptr(), &mut out, 107),
+            AnonTicketStatus::InvalidPid
+        );
+        assert_eq!(
+            build(pid.as_ptr(), pid.as_ptr(), &mut out, 107),
+            AnonTicketStatus::InvalidAddress
+        );
+        assert_eq!(
+            build(std::ptr::null(), pid.as_ptr(), &mut out, 107),
+            AnonTicketStatus::NullPointer
+        );
+        assert_eq!(
+            build(primary.as_ptr(), pid.as_ptr(), &mut out, 106),
+            AnonTicketStatus::BufferTooSmall
+        );
+        assert!(out.iter().all(|&byte| byte == 1));
+
+        let status = unsafe { anon_ticket_generate_pid(std::ptr::null_mut(), 17) };
+        assert_eq!(status, AnonTicketStatus::NullPointer);
+        let message = |status| unsafe { CStr::from_ptr(anon_ticket_status_message(status)) };
+        assert_eq!(
+            message(status as c_int),
+            c"required pointer argument is null"
+        );
+        assert_eq!(message(99), c"unknown status");
+        assert_eq!(message(-1), c"unknown status");
+    }
+}
@@ -0,0 +1,50 @@
+//! Payment lifecycle events for live subscribers such as the API's
+//! `/api/v1/payment/{pid}/events` stream. Delivery is best effort: events are
+//! dropped when nobody listens and lagging receivers skip ahead, so storage
+//! stays the source of truth.
+
+use anon_ticket_domain::PaymentId;
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentEventKind {
+    /// Mined, but still short of the confirmation depth.
+    Detected { confirmations: u64 },
+    /// Persisted by the monitor.
+    Confirmed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentEvent {
+    pub pid: PaymentId,
+    pub kind: PaymentEventKind,
+    pub amount: i64,
+    pub block_height: i64,
+}
+
+/// Broadcast channel the monitor publishes [`PaymentEvent`]s into.
+#[derive(Clone)]
+pub struct PaymentEvents {
+    sender: broadcast::Sender<PaymentEvent>,
+}
+
+impl PaymentEvents {
+    /// `capacity` events are buffered per receiver before it lags.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PaymentEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    pub fn publish(&self, event: PaymentEvent) {
+        // Fails only when nobody is subscribed.
+        let _ = self.sender.send(event);
+    }
+}
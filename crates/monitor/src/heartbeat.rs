@@ -0,0 +1,132 @@
+//! Per-wallet record of the monitor's latest polls, so an embedding process
+//! can tell a monitor that is slowly syncing from one that stopped.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use anon_ticket_domain::error::ErrorCode;
+use chrono::{DateTime, Utc};
+
+/// Shared handle updated by [`run_monitor`](crate::run_monitor) around every
+/// wallet poll. Clones observe the same state.
+#[derive(Debug, Clone)]
+pub struct MonitorHeartbeat {
+    started_at: DateTime<Utc>,
+    wallets: Arc<RwLock<BTreeMap<String, WalletPulse>>>,
+}
+
+/// What is known about one wallet's polls.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalletPulse {
+    /// Start of the poll in progress; `None` between polls.
+    pub polling_since: Option<DateTime<Utc>>,
+    /// End of the latest finished poll, successful or not.
+    pub last_tick_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// Why the latest finished poll failed; `None` if it succeeded.
+    pub last_error: Option<ErrorCode>,
+    /// Polls in a row that failed to reach the wallet or daemon. A success
+    /// resets it; other failures leave it alone.
+    pub consecutive_rpc_failures: u32,
+    /// Blocks from the wallet's height down to the cursor, as of the latest
+    /// poll that got the height.
+    pub lag_blocks: Option<u64>,
+}
+
+impl MonitorHeartbeat {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            wallets: Arc::default(),
+        }
+    }
+
+    /// When this heartbeat was created, i.e. roughly when the monitor
+    /// started.
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    pub fn poll_started(&self, wallet: &str, at: DateTime<Utc>) {
+        self.update(wallet, |pulse| pulse.polling_since = Some(at));
+    }
+
+    /// Returns the wallet's pulse after the update.
+    pub fn poll_finished(
+        &self,
+        wallet: &str,
+        at: DateTime<Utc>,
+        error: Option<ErrorCode>,
+    ) -> WalletPulse {
+        self.update(wallet, |pulse| {
+            pulse.polling_since = None;
+            pulse.last_tick_at = Some(at);
+            match error {
+                None => {
+                    pulse.last_success_at = Some(at);
+                    pulse.consecutive_rpc_failures = 0;
+                }
+                Some(ErrorCode::RpcUnavailable) => pulse.consecutive_rpc_failures += 1,
+                Some(_) => {}
+            }
+            pulse.last_error = error;
+        })
+    }
+
+    pub fn record_lag(&self, wallet: &str, blocks: u64) {
+        self.update(wallet, |pulse| pulse.lag_blocks = Some(blocks));
+    }
+
+    /// Every wallet polled so far, by name.
+    pub fn snapshot(&self) -> BTreeMap<String, WalletPulse> {
+        self.wallets
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn update(&self, wallet: &str, apply: impl FnOnce(&mut WalletPulse)) -> WalletPulse {
+        let mut wallets = self
+            .wallets
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let pulse = wallets.entry(wallet.to_string()).or_default();
+        apply(pulse);
+        pulse.clone()
+    }
+}
+
+impl Default for MonitorHeartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_keep_the_last_success() {
+        let heartbeat = MonitorHeartbeat::new();
+        let first = Utc::now();
+        heartbeat.poll_started("primary", first);
+        assert_eq!(heartbeat.snapshot()["primary"].polling_since, Some(first));
+        heartbeat.poll_finished("primary", first, None);
+        let later = first + chrono::Duration::seconds(30);
+        let pulse = heartbeat.poll_finished("primary", later, Some(ErrorCode::RpcUnavailable));
+        assert_eq!(pulse.consecutive_rpc_failures, 1);
+        heartbeat.poll_finished("primary", later, Some(ErrorCode::RpcUnavailable));
+        heartbeat.record_lag("primary", 12);
+
+        let pulse = &heartbeat.clone().snapshot()["primary"];
+        assert_eq!(pulse.polling_since, None);
+        assert_eq!(pulse.last_tick_at, Some(later));
+        assert_eq!(pulse.last_success_at, Some(first));
+        assert_eq!(pulse.last_error, Some(ErrorCode::RpcUnavailable));
+        assert_eq!(pulse.consecutive_rpc_failures, 2);
+        assert_eq!(pulse.lag_blocks, Some(12));
+        let pulse = heartbeat.poll_finished("primary", later, None);
+        assert_eq!(pulse.consecutive_rpc_failures, 0);
+    }
+}
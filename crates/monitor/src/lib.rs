@@ -3,9 +3,33 @@
 //! development/CI use but production should prefer in-process co-location so
 //! the Bloom/cache can be updated immediately after ingestion.
 
+/// Version of this crate, reported in the API's build info.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub mod events;
+pub mod heartbeat;
+pub mod outbox;
 pub mod pipeline;
+pub mod refund;
 pub mod rpc;
+pub mod throttle;
+pub mod watchdog;
+pub mod webhook;
 pub mod worker;
 
-pub use rpc::{RpcTransferSource, TransferEntry, TransferSource, TransfersResponse};
-pub use worker::{build_rpc_source, run_monitor, MonitorError, MonitorHooks};
+pub use events::{PaymentEvent, PaymentEventKind, PaymentEvents};
+pub use heartbeat::{MonitorHeartbeat, WalletPulse};
+pub use outbox::{OutboxAlert, OutboxMonitor};
+pub use pipeline::IngestRules;
+pub use refund::{spawn_refund_sender, RefundSender, RefundWallet, TransferError};
+pub use rpc::{
+    FeeEstimate, RpcTransferSource, SimulatedReorg, SimulatedTransferSource, SkippedEntry,
+    SubaddressTransferSource, TransferEntry, TransferSource, TransfersResponse,
+};
+pub use throttle::WarnThrottle;
+pub use watchdog::{spawn_watchdog, AlertKind, MonitorAlert, MonitorWatchdog};
+pub use webhook::{webhook_dispatcher, WebhookDispatcher, WebhookSender};
+pub use worker::{
+    build_rpc_source, build_subaddress_source, build_wallet_sources, poll_once, run_monitor,
+    with_monitor_source, MonitorError, MonitorHooks, PollOutcome, WalletCursor,
+};
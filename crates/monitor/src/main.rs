@@ -1,11 +1,21 @@
 //! Monitor binary that tails monero-wallet-rpc for qualifying transfers.
 
 use std::io;
+use std::path::Path;
+use std::sync::Arc;
 
-use anon_ticket_domain::config::BootstrapConfig;
+use anon_ticket_domain::config::{load_env_files, BootstrapConfig, DetectionMode, PRIMARY_WALLET};
 use anon_ticket_domain::services::telemetry::{init_telemetry, TelemetryConfig};
-use anon_ticket_monitor::{build_rpc_source, run_monitor, worker::MonitorError};
+use anon_ticket_domain::storage::{PendingPaymentStore, SkippedTransferStore};
+use anon_ticket_monitor::{
+    build_subaddress_source, build_wallet_sources, run_monitor, spawn_refund_sender,
+    spawn_watchdog, webhook_dispatcher, with_monitor_source,
+    worker::{MonitorError, MonitorHooks},
+    MonitorHeartbeat,
+};
 use anon_ticket_storage::SeaOrmStorage;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
@@ -28,10 +38,90 @@ async fn main() -> io::Result<()> {
 }
 
 async fn bootstrap() -> Result<(), MonitorError> {
+    let env_files = load_env_files(Path::new("."))?;
     let config = BootstrapConfig::load_from_env()?;
     let telemetry_config = TelemetryConfig::from_env("MONITOR");
-    init_telemetry(&telemetry_config)?;
+    let telemetry = init_telemetry(&telemetry_config)?;
+    if !env_files.is_empty() {
+        info!(files = ?env_files, "filled unset variables from env and config files");
+    }
     let storage = SeaOrmStorage::connect(config.database_url()).await?;
-    let source = build_rpc_source(config.monero_rpc_url())?;
-    run_monitor(config, storage, source, None).await
+    let webhooks = webhook_dispatcher(&config, storage.clone())?.map(|(sender, dispatcher)| {
+        tokio::spawn(dispatcher.run());
+        sender
+    });
+    spawn_refund_sender(&config, storage.clone())?;
+    let heartbeat = MonitorHeartbeat::new();
+    spawn_watchdog(&config, heartbeat.clone())?;
+    let hooks = Some(
+        MonitorHooks::new(None, None)
+            .with_webhooks(webhooks)
+            .with_heartbeat(Some(heartbeat))
+            .with_pending_store(
+                config
+                    .track_pending()
+                    .then(|| Arc::new(storage.clone()) as Arc<dyn PendingPaymentStore>),
+            )
+            .with_skip_journal(Some(
+                Arc::new(storage.clone()) as Arc<dyn SkippedTransferStore>
+            )),
+    );
+    let shutdown = CancellationToken::new();
+    tokio::spawn(cancel_on_signal(shutdown.clone()));
+    let result = match config.detection_mode() {
+        DetectionMode::PaymentId => {
+            let wallets = with_monitor_source(&config, build_wallet_sources(&config)?)?;
+            run_monitor(config, storage.clone(), wallets, hooks, shutdown).await
+        }
+        DetectionMode::Subaddress => {
+            let mut source = build_subaddress_source(
+                config.monero_rpc_url(),
+                config.subaddress_account(),
+                storage.clone(),
+            )?;
+            if let Some(daemon) = config.monero_daemon_rpc_url() {
+                source = source.with_daemon(daemon);
+            }
+            let wallets = with_monitor_source(&config, vec![(PRIMARY_WALLET.to_string(), source)])?;
+            run_monitor(config, storage.clone(), wallets, hooks, shutdown).await
+        }
+    };
+    if let Err(err) = storage.close().await {
+        warn!(?err, "closing storage failed");
+    }
+    telemetry.shutdown().await;
+    result
+}
+
+/// Cancels `shutdown` on ctrl-c or SIGTERM; the monitor then finishes its
+/// current tick and returns.
+async fn cancel_on_signal(shutdown: CancellationToken) {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!(?err, "failed to listen for ctrl-c");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                warn!(?err, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    info!("shutdown signal received; stopping after the current tick");
+    shutdown.cancel();
 }
@@ -0,0 +1,185 @@
+//! Health of the webhook outbox: deliveries still to make and those that
+//! gave up.
+//!
+//! The [`WebhookDispatcher`](crate::WebhookDispatcher) checks storage
+//! periodically, publishes the snapshot as gauges, and compares it with the
+//! previous one to raise [`OutboxAlert`]s, so a receiver that went away is
+//! noticed before the merchant behind it is.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anon_ticket_domain::model::OutboxStats;
+use chrono::{DateTime, Utc};
+use metrics::{counter, gauge};
+use reqwest::Url;
+use tracing::warn;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutboxAlert {
+    /// The oldest undelivered event has waited longer than
+    /// `MONITOR_WEBHOOK_LAG_ALERT_SECS`.
+    Lagging { age: Duration },
+    /// Deliveries to `endpoint` ran out of attempts since the last check.
+    EndpointFailing { endpoint: String, new_failures: u64 },
+}
+
+impl OutboxAlert {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OutboxAlert::Lagging { .. } => "lagging",
+            OutboxAlert::EndpointFailing { .. } => "endpoint_failing",
+        }
+    }
+}
+
+/// Compares consecutive [`OutboxStats`]. The first snapshot only sets the
+/// baseline for failures, so a restart does not re-announce old ones.
+#[derive(Debug)]
+pub struct OutboxMonitor {
+    lag_alert: Duration,
+    failed: Option<BTreeMap<String, u64>>,
+}
+
+impl OutboxMonitor {
+    pub fn new(lag_alert: Duration) -> Self {
+        Self {
+            lag_alert,
+            failed: None,
+        }
+    }
+
+    /// Publishes `stats` as gauges and returns the alerts it raises, each
+    /// also logged and counted in `monitor_webhook_alerts_total{kind}`.
+    pub fn observe(&mut self, stats: &OutboxStats, now: DateTime<Utc>) -> Vec<OutboxAlert> {
+        let age = stats
+            .oldest_undelivered_at
+            .and_then(|oldest| (now - oldest).to_std().ok())
+            .unwrap_or_default();
+        gauge!("monitor_webhook_outbox_backlog").set(stats.backlog as f64);
+        gauge!("monitor_webhook_outbox_oldest_age_seconds").set(age.as_secs_f64());
+
+        let mut failed = BTreeMap::new();
+        for (url, count) in &stats.failed_by_url {
+            *failed.entry(endpoint_label(url)).or_default() += count;
+        }
+        let previous = self.failed.replace(failed.clone());
+        // Endpoints whose failures were all redelivered drop back to zero.
+        for endpoint in previous.iter().flat_map(BTreeMap::keys) {
+            if !failed.contains_key(endpoint) {
+                gauge!("monitor_webhook_failed_deliveries", "endpoint" => endpoint.clone())
+                    .set(0.0);
+            }
+        }
+        for (endpoint, count) in &failed {
+            gauge!("monitor_webhook_failed_deliveries", "endpoint" => endpoint.clone())
+                .set(*count as f64);
+        }
+
+        let mut alerts = Vec::new();
+        if stats.backlog > 0 && age > self.lag_alert {
+            alerts.push(OutboxAlert::Lagging { age });
+        }
+        if let Some(previous) = previous {
+            for (endpoint, count) in failed {
+                let new_failures =
+                    count.saturating_sub(previous.get(&endpoint).copied().unwrap_or(0));
+                if new_failures > 0 {
+                    alerts.push(OutboxAlert::EndpointFailing {
+                        endpoint,
+                        new_failures,
+                    });
+                }
+            }
+        }
+        for alert in &alerts {
+            counter!("monitor_webhook_alerts_total", "kind" => alert.kind()).increment(1);
+            match alert {
+                OutboxAlert::Lagging { age } => warn!(
+                    age_secs = age.as_secs(),
+                    backlog = stats.backlog,
+                    "webhook outbox is lagging"
+                ),
+                OutboxAlert::EndpointFailing {
+                    endpoint,
+                    new_failures,
+                } => warn!(endpoint, new_failures, "webhook endpoint is failing"),
+            }
+        }
+        alerts
+    }
+}
+
+/// `host[:port]` of a webhook URL, used as a metric label; paths and query
+/// strings may carry credentials and are left out.
+pub fn endpoint_label(url: &str) -> String {
+    let Ok(url) = Url::parse(url) else {
+        return "invalid".to_string();
+    };
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_string(),
+        (None, _) => "invalid".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(backlog: u64, oldest: Option<DateTime<Utc>>, failed: &[(&str, u64)]) -> OutboxStats {
+        OutboxStats {
+            backlog,
+            oldest_undelivered_at: oldest,
+            failed_by_url: failed
+                .iter()
+                .map(|(url, count)| (url.to_string(), *count))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn alerts_on_lag_and_on_new_failures_only() {
+        let now = Utc::now();
+        let mut monitor = OutboxMonitor::new(Duration::from_secs(300));
+        // Failures already there at start-up set the baseline.
+        let old_failures = [("https://a.example/hook?key=1", 2)];
+        assert!(monitor
+            .observe(&stats(1, Some(now), &old_failures), now)
+            .is_empty());
+
+        let late = now - chrono::Duration::minutes(10);
+        let alerts = monitor.observe(
+            &stats(
+                3,
+                Some(late),
+                &[
+                    ("https://a.example/hook?key=1", 2),
+                    ("https://a.example/other", 1),
+                    ("http://b.example:8080/hook", 1),
+                ],
+            ),
+            now,
+        );
+        assert_eq!(
+            alerts,
+            vec![
+                OutboxAlert::Lagging {
+                    age: Duration::from_secs(600)
+                },
+                OutboxAlert::EndpointFailing {
+                    endpoint: "a.example".to_string(),
+                    new_failures: 1,
+                },
+                OutboxAlert::EndpointFailing {
+                    endpoint: "b.example:8080".to_string(),
+                    new_failures: 1,
+                },
+            ]
+        );
+
+        // Redelivered failures and an empty backlog raise nothing.
+        assert!(monitor.observe(&stats(0, None, &[]), now).is_empty());
+        assert_eq!(endpoint_label("not a url"), "invalid");
+    }
+}
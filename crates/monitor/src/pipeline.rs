@@ -1,29 +1,83 @@
-use anon_ticket_domain::model::{NewPayment, PaymentId};
-use anon_ticket_domain::storage::PaymentStore;
-use chrono::{DateTime, Utc};
+use anon_ticket_domain::config::BootstrapConfig;
+use anon_ticket_domain::model::{
+    NewPayment, PaymentId, PaymentQuote, Reconciliation, SkippedTransfer,
+};
+use anon_ticket_domain::storage::{PaymentStore, QuoteStore};
+use chrono::{DateTime, Duration, Utc};
 use metrics::counter;
-use tracing::warn;
+use tracing::{info, warn};
 
+use crate::events::{PaymentEvent, PaymentEventKind};
 use crate::rpc::TransferEntry;
 use crate::worker::{MonitorError, MonitorHooks};
 
+/// Mainnet genesis block time; no chain has blocks older than this.
+const GENESIS_TIMESTAMP: i64 = 1_397_818_193;
+/// Consensus rejects blocks more than two hours ahead of the node's clock
+/// (`CRYPTONOTE_BLOCK_FUTURE_TIME_LIMIT`), so a later timestamp is a wallet or
+/// clock fault rather than a real block time.
+const MAX_FUTURE_SKEW: Duration = Duration::hours(2);
+/// Monero has never targeted less than 60 s a block (120 s since v2). Half of
+/// that leaves room for lucky runs; `MAX_FUTURE_SKEW` covers short spans.
+const MIN_BLOCK_SECS: i64 = 30;
+
+/// Per-transfer acceptance rules applied before a payment is persisted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IngestRules {
+    /// Transfers below this are skipped as dust.
+    pub min_payment_amount: i64,
+    /// Skip transfers whose PID has no payment quote. Quoted PIDs are held to
+    /// their deadline either way.
+    pub require_quote: bool,
+    /// Height of the wallet the transfers were fetched from. Blocks mined on
+    /// top of a transfer's block bound how recent its time can be; `None`
+    /// skips that check.
+    pub wallet_height: Option<u64>,
+}
+
+impl IngestRules {
+    pub fn new(min_payment_amount: i64) -> Self {
+        Self {
+            min_payment_amount,
+            require_quote: false,
+            wallet_height: None,
+        }
+    }
+
+    pub fn from_config(config: &BootstrapConfig) -> Self {
+        Self {
+            min_payment_amount: config.monitor_min_payment_amount(),
+            require_quote: config.monitor_require_quote(),
+            wallet_height: None,
+        }
+    }
+
+    pub fn with_wallet_height(mut self, wallet_height: u64) -> Self {
+        self.wallet_height = Some(wallet_height);
+        self
+    }
+}
+
+/// Persists `entry` if it passes `rules`. `wallet` names the wallet that saw
+/// the transfer and is recorded as the payment's address book id.
 pub async fn process_entry<S>(
     storage: &S,
+    wallet: &str,
     entry: &TransferEntry,
-    min_payment_amount: i64,
+    rules: IngestRules,
     hooks: Option<&MonitorHooks>,
 ) -> Result<bool, MonitorError>
 where
-    S: PaymentStore,
+    S: PaymentStore + QuoteStore,
 {
     let (Some(pid), Some(height)) = (&entry.payment_id, entry.height) else {
         return Ok(false);
     };
 
-    if entry.amount < min_payment_amount {
+    if entry.amount < rules.min_payment_amount && !tops_up_underpaid_quote(storage, pid).await? {
         warn!(
             amount = entry.amount,
-            min_payment_amount,
+            min_payment_amount = rules.min_payment_amount,
             txid = entry.txid,
             "skipping dust payment below minimum amount"
         );
@@ -35,38 +89,181 @@ where
         return Ok(false);
     }
 
-    let detected_at = DateTime::from_timestamp(entry.timestamp as i64, 0).unwrap_or_else(Utc::now);
+    let detected_at = detected_at(entry, height, rules.wallet_height, Utc::now());
     let pid = match PaymentId::parse(pid) {
         Ok(pid) => pid,
         Err(_) => {
             warn!(pid, "skipping invalid pid");
             counter!("monitor_payments_ingested_total", "result" => "invalid_pid").increment(1);
+            if let Some(hooks) = hooks {
+                hooks
+                    .journal_skipped(SkippedTransfer {
+                        txid: entry.txid.clone(),
+                        wallet: wallet.to_string(),
+                        reason: "invalid_pid".to_string(),
+                        payment_id: Some(pid.clone()),
+                        amount: entry.amount.to_string(),
+                        block_height: Some(height),
+                        timestamp: entry.timestamp,
+                        skipped_at: Utc::now(),
+                    })
+                    .await;
+            }
             return Ok(false);
         }
     };
 
-    storage
-        .insert_payment(NewPayment {
-            pid: pid.clone(),
-            txid: entry.txid.clone(),
+    let quote = storage.find_quote(&pid).await?;
+    if let Some(reason) = quote_rejection(quote.as_ref(), detected_at, rules.require_quote) {
+        warn!(
+            txid = entry.txid,
+            reason, "skipping transfer without a payable quote"
+        );
+        counter!("monitor_payments_ingested_total", "result" => reason).increment(1);
+        return Ok(false);
+    }
+
+    let payment = NewPayment {
+        pid,
+        txid: entry.txid.clone(),
+        amount: entry.amount,
+        block_height: height,
+        detected_at,
+        address_id: Some(wallet.to_string()),
+    };
+    storage.insert_payment(payment.clone()).await?;
+    if let Some(quote) = &quote {
+        if let Some(settled) = storage.settle_quote(&payment.pid).await? {
+            record_reconciliation(quote, &settled, &entry.txid);
+        }
+    }
+    if let Some(hooks) = hooks {
+        if let Some(webhooks) = hooks.webhooks() {
+            webhooks.payment_persisted(&payment);
+        }
+        // After the hints so a subscriber redeeming right away finds them.
+        hooks.mark_present(&payment.pid);
+        hooks.publish(PaymentEvent {
+            pid: payment.pid,
+            kind: PaymentEventKind::Confirmed,
             amount: entry.amount,
             block_height: height,
-            detected_at,
-        })
-        .await?;
-    if let Some(hooks) = hooks {
-        hooks.mark_present(&pid);
+        });
     }
     counter!("monitor_payments_ingested_total", "result" => "persisted").increment(1);
 
     Ok(true)
 }
 
+/// Top-ups toward an underpaid quote are accepted below the dust floor;
+/// otherwise a shortfall smaller than the floor could never be paid.
+async fn tops_up_underpaid_quote<S: QuoteStore>(
+    storage: &S,
+    pid: &str,
+) -> Result<bool, MonitorError> {
+    let Ok(pid) = PaymentId::parse(pid) else {
+        return Ok(false);
+    };
+    Ok(storage
+        .find_quote(&pid)
+        .await?
+        .and_then(|quote| quote.reconciliation())
+        == Some(Reconciliation::Underpaid))
+}
+
+/// Counts and logs a quote whose reconciliation changed with this transfer.
+/// Re-delivered transfers leave it unchanged and are not counted again.
+fn record_reconciliation(before: &PaymentQuote, after: &PaymentQuote, txid: &str) {
+    let Some(state) = after.reconciliation() else {
+        return;
+    };
+    if before.reconciliation() == Some(state) {
+        return;
+    }
+    counter!("monitor_quote_reconciliations_total", "state" => state.as_str()).increment(1);
+    let received = after.received_amount.unwrap_or_default();
+    let expected = after.expected_amount;
+    match state {
+        Reconciliation::Exact => info!(txid, expected, "quote paid in full"),
+        Reconciliation::Underpaid => warn!(
+            txid,
+            expected,
+            received,
+            shortfall = expected - received,
+            "quote underpaid; awaiting top-up"
+        ),
+        Reconciliation::Overpaid => warn!(
+            txid,
+            expected,
+            received,
+            excess = received - expected,
+            "quote overpaid"
+        ),
+    }
+}
+
+/// Why a transfer detected at `detected_at` must not be ingested, as a
+/// metric label; `None` when the quote (or its absence) allows it.
+fn quote_rejection(
+    quote: Option<&PaymentQuote>,
+    detected_at: DateTime<Utc>,
+    require_quote: bool,
+) -> Option<&'static str> {
+    match quote {
+        Some(quote) if !quote.accepts_payment_at(detected_at) => Some("quote_expired"),
+        Some(_) => None,
+        None if require_quote => Some("unquoted"),
+        None => None,
+    }
+}
+
+/// Block time of `entry`, or `now` when the wallet reports a time no block at
+/// `height` can have. With a `wallet_height`, the blocks mined since `height`
+/// must fit between the reported time and now; a lagging wallet only loosens
+/// that bound. Clamps are logged and counted so skewed wallets or clocks show
+/// up instead of silently shifting `detected_at`.
+fn detected_at(
+    entry: &TransferEntry,
+    height: i64,
+    wallet_height: Option<u64>,
+    now: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let reported = i64::try_from(entry.timestamp)
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0));
+    let blocks_since = wallet_height.map_or(0, |tip| tip.saturating_sub(height.max(0) as u64));
+    let min_elapsed = i64::try_from(blocks_since)
+        .unwrap_or(i64::MAX)
+        .saturating_mul(MIN_BLOCK_SECS);
+    let latest = (now + MAX_FUTURE_SKEW)
+        .timestamp()
+        .saturating_sub(min_elapsed);
+    let reason = match reported {
+        Some(at) if at.timestamp() < GENESIS_TIMESTAMP => "before_genesis",
+        Some(at) if at > now + MAX_FUTURE_SKEW => "future",
+        Some(at) if at.timestamp() > latest => "ahead_of_height",
+        Some(at) => return at,
+        None => "out_of_range",
+    };
+    warn!(
+        timestamp = entry.timestamp,
+        height,
+        txid = entry.txid,
+        reason,
+        "clamping implausible transfer timestamp to now"
+    );
+    counter!("monitor_timestamp_clamped_total", "reason" => reason).increment(1);
+    now
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use anon_ticket_domain::model::{ClaimOutcome, PaymentRecord};
-    use anon_ticket_domain::storage::{PaymentStore, StorageResult};
+    use anon_ticket_domain::config::PRIMARY_WALLET;
+    use anon_ticket_domain::model::{
+        ClaimOutcome, NewPaymentQuote, PaymentFilter, PaymentRecord, PaymentTransfer, QuoteStatus,
+    };
+    use anon_ticket_domain::storage::{PaymentStore, SkippedTransferStore, StorageResult};
     use async_trait::async_trait;
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
@@ -74,6 +271,7 @@ mod tests {
     #[derive(Clone, Default)]
     struct MockStorage {
         inserted: Arc<AtomicUsize>,
+        quote: Option<PaymentQuote>,
     }
 
     #[async_trait]
@@ -90,6 +288,50 @@ mod tests {
         async fn find_payment(&self, _pid: &PaymentId) -> StorageResult<Option<PaymentRecord>> {
             Ok(None)
         }
+
+        async fn unclaim_payment(&self, _pid: &PaymentId) -> StorageResult<Option<PaymentRecord>> {
+            Ok(None)
+        }
+
+        async fn list_payments(
+            &self,
+            _filter: &PaymentFilter,
+            _after: Option<&PaymentId>,
+            _limit: u64,
+        ) -> StorageResult<Vec<PaymentRecord>> {
+            Ok(Vec::new())
+        }
+
+        async fn find_transfers(&self, _pid: &PaymentId) -> StorageResult<Vec<PaymentTransfer>> {
+            Ok(Vec::new())
+        }
+
+        async fn find_payment_by_txid(&self, _txid: &str) -> StorageResult<Option<PaymentRecord>> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait]
+    impl QuoteStore for MockStorage {
+        async fn insert_quote(&self, _quote: NewPaymentQuote) -> StorageResult<bool> {
+            Ok(false)
+        }
+
+        async fn find_quote(&self, _pid: &PaymentId) -> StorageResult<Option<PaymentQuote>> {
+            Ok(self.quote.clone())
+        }
+
+        async fn mark_quote_paid(&self, _pid: &PaymentId) -> StorageResult<bool> {
+            Ok(true)
+        }
+
+        async fn settle_quote(&self, _pid: &PaymentId) -> StorageResult<Option<PaymentQuote>> {
+            Ok(self.quote.clone())
+        }
+
+        async fn expire_quotes(&self, _now: DateTime<Utc>) -> StorageResult<u64> {
+            Ok(0)
+        }
     }
 
     fn sample_entry(amount: i64) -> TransferEntry {
@@ -107,24 +349,211 @@ mod tests {
         let storage = MockStorage::default();
         let min_payment_amount = 10;
 
-        let result = process_entry(&storage, &sample_entry(5), min_payment_amount, None)
-            .await
-            .expect("processing succeeds");
+        let result = process_entry(
+            &storage,
+            PRIMARY_WALLET,
+            &sample_entry(5),
+            IngestRules::new(min_payment_amount),
+            None,
+        )
+        .await
+        .expect("processing succeeds");
 
         assert!(!result);
         assert_eq!(storage.inserted.load(Ordering::SeqCst), 0);
     }
 
+    #[tokio::test]
+    async fn top_ups_of_underpaid_quotes_bypass_the_dust_floor() {
+        let quote = |received_amount| PaymentQuote {
+            pid: PaymentId::parse("1111111111111111").unwrap(),
+            expected_amount: 100,
+            created_at: Utc::now(),
+            expires_at: Utc::now(),
+            status: QuoteStatus::Paid,
+            received_amount,
+            address_id: None,
+        };
+        let ingests = |quote| async move {
+            let storage = MockStorage {
+                quote: Some(quote),
+                ..MockStorage::default()
+            };
+            process_entry(
+                &storage,
+                PRIMARY_WALLET,
+                &sample_entry(5),
+                IngestRules::new(10),
+                None,
+            )
+            .await
+            .expect("processing succeeds")
+        };
+
+        assert!(ingests(quote(Some(95))).await);
+        assert!(!ingests(quote(Some(100))).await);
+        assert!(!ingests(quote(None)).await);
+    }
+
+    #[test]
+    fn implausible_timestamps_are_clamped_to_now() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let at = |timestamp: u64| {
+            let entry = TransferEntry {
+                timestamp,
+                ..sample_entry(10)
+            };
+            detected_at(&entry, 10, None, now)
+        };
+
+        assert_eq!(at(1_699_999_000).timestamp(), 1_699_999_000);
+        // Within the consensus future limit: kept as reported.
+        assert_eq!(at(1_700_007_000).timestamp(), 1_700_007_000);
+        assert_eq!(at(1_700_007_201), now);
+        assert_eq!(at(0), now);
+        assert_eq!(at(u64::MAX), now);
+    }
+
+    #[test]
+    fn timestamps_too_recent_for_their_depth_are_clamped() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        // 100 000 blocks on top take at least about five weeks.
+        let at = |timestamp: u64, wallet_height| {
+            let entry = TransferEntry {
+                timestamp,
+                ..sample_entry(10)
+            };
+            detected_at(&entry, 10, Some(wallet_height), now)
+        };
+
+        // A day old is plausible in itself, but not that deep in the chain.
+        assert_eq!(at(1_699_913_600, 100_010), now);
+        assert_eq!(at(1_699_913_600, 10).timestamp(), 1_699_913_600);
+        assert_eq!(at(1_680_000_000, 100_010).timestamp(), 1_680_000_000);
+        // Older than its depth suggests is left alone: the wallet may lag.
+        assert_eq!(at(1_500_000_000, 20).timestamp(), 1_500_000_000);
+    }
+
     #[tokio::test]
     async fn persists_payments_at_threshold() {
         let storage = MockStorage::default();
         let min_payment_amount = 10;
 
-        let result = process_entry(&storage, &sample_entry(10), min_payment_amount, None)
-            .await
-            .expect("processing succeeds");
+        let result = process_entry(
+            &storage,
+            PRIMARY_WALLET,
+            &sample_entry(10),
+            IngestRules::new(min_payment_amount),
+            None,
+        )
+        .await
+        .expect("processing succeeds");
 
         assert!(result);
         assert_eq!(storage.inserted.load(Ordering::SeqCst), 1);
     }
+
+    #[derive(Default)]
+    struct Journal(std::sync::Mutex<Vec<SkippedTransfer>>);
+
+    #[async_trait]
+    impl SkippedTransferStore for Journal {
+        async fn record_skipped(&self, transfer: SkippedTransfer) -> StorageResult<()> {
+            self.0.lock().unwrap().push(transfer);
+            Ok(())
+        }
+
+        async fn find_skipped(&self, _txid: &str) -> StorageResult<Option<SkippedTransfer>> {
+            Ok(None)
+        }
+
+        async fn list_skipped(
+            &self,
+            _after: Option<&str>,
+            _limit: u64,
+        ) -> StorageResult<Vec<SkippedTransfer>> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        async fn delete_skipped(&self, _txid: &str) -> StorageResult<bool> {
+            Ok(false)
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_pids_are_journaled() {
+        let storage = MockStorage::default();
+        let journal = Arc::new(Journal::default());
+        let hooks = MonitorHooks::new(None, None)
+            .with_skip_journal(Some(journal.clone() as Arc<dyn SkippedTransferStore>));
+        let entry = TransferEntry {
+            payment_id: Some("not-a-pid".to_string()),
+            ..sample_entry(10)
+        };
+
+        let result = process_entry(
+            &storage,
+            PRIMARY_WALLET,
+            &entry,
+            IngestRules::new(1),
+            Some(&hooks),
+        )
+        .await
+        .expect("processing succeeds");
+
+        assert!(!result);
+        let journaled = journal.list_skipped(None, 10).await.unwrap();
+        assert_eq!(journaled.len(), 1);
+        assert_eq!(journaled[0].reason, "invalid_pid");
+        assert_eq!(journaled[0].payment_id.as_deref(), Some("not-a-pid"));
+        assert_eq!(journaled[0].amount, "10");
+        assert_eq!(journaled[0].block_height, Some(10));
+    }
+
+    #[tokio::test]
+    async fn quotes_gate_ingestion_by_block_time() {
+        let entry = TransferEntry {
+            timestamp: 1_700_000_000,
+            ..sample_entry(10)
+        };
+        let block_time = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let quote = |expires_at, status| PaymentQuote {
+            pid: PaymentId::parse("1111111111111111").unwrap(),
+            expected_amount: 10,
+            created_at: block_time - Duration::hours(1),
+            expires_at,
+            status,
+            received_amount: None,
+            address_id: None,
+        };
+        let entry = &entry;
+        let ingests = |storage: MockStorage, rules: IngestRules| async move {
+            process_entry(&storage, PRIMARY_WALLET, entry, rules, None)
+                .await
+                .expect("processing succeeds")
+        };
+        let strict = IngestRules {
+            require_quote: true,
+            ..IngestRules::new(1)
+        };
+
+        assert!(ingests(MockStorage::default(), IngestRules::new(1)).await);
+        assert!(!ingests(MockStorage::default(), strict).await);
+        // Mined before the deadline: accepted even once swept to expired.
+        let swept = MockStorage {
+            quote: Some(quote(block_time, QuoteStatus::Expired)),
+            ..MockStorage::default()
+        };
+        assert!(ingests(swept, strict).await);
+        let late = MockStorage {
+            quote: Some(quote(block_time - Duration::seconds(1), QuoteStatus::Open)),
+            ..MockStorage::default()
+        };
+        assert!(!ingests(late, IngestRules::new(1)).await);
+        let renewal = MockStorage {
+            quote: Some(quote(block_time - Duration::days(1), QuoteStatus::Paid)),
+            ..MockStorage::default()
+        };
+        assert!(ingests(renewal, strict).await);
+    }
 }
@@ -0,0 +1,299 @@
+//! Sends pending refunds from the wallet payments are received on (feature
+//! `refund-transfers`, `MONITOR_SEND_REFUNDS=1`).
+//!
+//! Each refund is claimed, moving it from `pending` to `sending`, before the
+//! wallet is called, so two senders never transfer the same one. A transfer
+//! the wallet rejects is marked failed with its error. One whose outcome is
+//! unknown, because of a crash, a timeout after the wallet relayed it, or an
+//! unreadable reply, stays `sending` until an operator checks the wallet and
+//! closes it by hand: a second attempt could pay twice.
+
+use std::time::Duration;
+
+use anon_ticket_domain::config::BootstrapConfig;
+use anon_ticket_domain::model::{Refund, RefundOutcome, RefundStatus};
+use anon_ticket_domain::storage::RefundStore;
+use async_trait::async_trait;
+use chrono::Utc;
+use metrics::counter;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{info, warn};
+
+use crate::worker::MonitorError;
+
+/// How often pending refunds are looked for.
+pub const REFUND_POLL: Duration = Duration::from_secs(30);
+const REFUND_BATCH: u64 = 20;
+
+/// Why a refund transfer did not return a txid.
+#[derive(Debug, Error)]
+pub enum TransferError {
+    /// The wallet answered with an error; nothing was sent.
+    #[error("{0}")]
+    Rejected(String),
+    /// No usable answer came back; the transfer may have been relayed.
+    #[error("{0}")]
+    Unknown(String),
+}
+
+/// The wallet side of a refund.
+#[async_trait]
+pub trait RefundWallet: Send + Sync {
+    /// Sends `amount` atomic units to `address`, returning the txid.
+    async fn transfer(&self, address: &str, amount: u64) -> Result<String, TransferError>;
+}
+
+pub struct RefundSender<S, W> {
+    storage: S,
+    wallet: W,
+    poll: Duration,
+}
+
+impl<S, W> RefundSender<S, W>
+where
+    S: RefundStore,
+    W: RefundWallet,
+{
+    pub fn new(storage: S, wallet: W) -> Self {
+        Self {
+            storage,
+            wallet,
+            poll: REFUND_POLL,
+        }
+    }
+
+    pub fn with_poll(mut self, poll: Duration) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    /// Sends one batch of pending refunds; returns how many went out.
+    pub async fn send_pending(&self) -> Result<u64, MonitorError> {
+        let pending = self
+            .storage
+            .list_refunds(Some(RefundStatus::Pending), None, REFUND_BATCH)
+            .await?;
+        let mut sent = 0;
+        // Listed newest first; send in the order they were requested.
+        for refund in pending.into_iter().rev() {
+            // Another sender or an operator may have taken it since the scan.
+            let Some(refund) = self.storage.claim_refund(refund.id).await? else {
+                continue;
+            };
+            if self.send(refund).await? {
+                sent += 1;
+            }
+        }
+        Ok(sent)
+    }
+
+    async fn send(&self, refund: Refund) -> Result<bool, MonitorError> {
+        let outcome = match u64::try_from(refund.amount) {
+            Ok(amount) => match self.wallet.transfer(&refund.address, amount).await {
+                Ok(txid) => RefundOutcome::Sent { txid },
+                Err(TransferError::Rejected(error)) => RefundOutcome::Failed { error },
+                Err(TransferError::Unknown(error)) => {
+                    // Left `sending`: only the wallet can tell whether it went out.
+                    counter!("monitor_refunds_total", "result" => "unknown").increment(1);
+                    warn!(
+                        id = refund.id,
+                        error, "refund outcome unknown, left sending"
+                    );
+                    return Ok(false);
+                }
+            },
+            Err(_) => RefundOutcome::Failed {
+                error: "negative amount".to_string(),
+            },
+        };
+        let status = outcome.status();
+        counter!("monitor_refunds_total", "result" => status.as_str()).increment(1);
+        match &outcome {
+            RefundOutcome::Sent { txid } => info!(id = refund.id, txid, "refund sent"),
+            RefundOutcome::Failed { error } => warn!(id = refund.id, error, "refund failed"),
+        }
+        self.storage
+            .resolve_refund(refund.id, outcome, Utc::now())
+            .await?;
+        Ok(status == RefundStatus::Sent)
+    }
+
+    /// Sends pending refunds every poll interval until aborted.
+    pub async fn run(self) {
+        let mut poll = interval(self.poll);
+        poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            poll.tick().await;
+            if let Err(err) = self.send_pending().await {
+                warn!(?err, "sending refunds failed");
+            }
+        }
+    }
+}
+
+/// Starts a [`RefundSender`] on the primary wallet when
+/// `MONITOR_SEND_REFUNDS` is set. Builds without the `refund-transfers`
+/// feature reject the setting instead.
+pub fn spawn_refund_sender<S>(
+    config: &BootstrapConfig,
+    storage: S,
+) -> Result<Option<JoinHandle<()>>, MonitorError>
+where
+    S: RefundStore + 'static,
+{
+    if !config.send_refunds() {
+        return Ok(None);
+    }
+    #[cfg(feature = "refund-transfers")]
+    {
+        let wallet = RpcRefundWallet::new(config)?;
+        Ok(Some(tokio::spawn(RefundSender::new(storage, wallet).run())))
+    }
+    #[cfg(not(feature = "refund-transfers"))]
+    {
+        drop(storage);
+        Err(MonitorError::Config(
+            anon_ticket_domain::config::ConfigError::InvalidValue {
+                key: "MONITOR_SEND_REFUNDS",
+                reason: "needs a build with the `refund-transfers` feature",
+            },
+        ))
+    }
+}
+
+/// `transfer` on `MONERO_RPC_URL`, from the account payments arrive on.
+#[cfg(feature = "refund-transfers")]
+pub struct RpcRefundWallet {
+    wallet: monero_rpc::WalletClient,
+    account: u32,
+}
+
+#[cfg(feature = "refund-transfers")]
+impl RpcRefundWallet {
+    pub fn new(config: &BootstrapConfig) -> Result<Self, MonitorError> {
+        use anon_ticket_domain::config::DetectionMode;
+
+        let account = match config.detection_mode() {
+            DetectionMode::Subaddress => config.subaddress_account(),
+            DetectionMode::PaymentId => 0,
+        };
+        Ok(Self {
+            wallet: crate::worker::wallet_client(config.monero_rpc_url())?,
+            account,
+        })
+    }
+}
+
+#[cfg(feature = "refund-transfers")]
+#[async_trait]
+impl RefundWallet for RpcRefundWallet {
+    async fn transfer(&self, address: &str, amount: u64) -> Result<String, TransferError> {
+        use std::collections::HashMap;
+        use std::str::FromStr;
+
+        // wallet-rpc takes the monero types of the version it was built with.
+        use monero_rpc::monero::{Address, Amount};
+        use monero_rpc::{TransferOptions, TransferPriority};
+
+        let address = Address::from_str(address)
+            .map_err(|err| TransferError::Rejected(format!("refund address: {err}")))?;
+        let options = TransferOptions {
+            account_index: Some(self.account),
+            ..TransferOptions::default()
+        };
+        let transfer = self
+            .wallet
+            .transfer(
+                HashMap::from([(address, Amount::from_pico(amount))]),
+                TransferPriority::Default,
+                options,
+            )
+            .await
+            .map_err(|err| {
+                // An error object from wallet-rpc means it refused the transfer;
+                // transport, timeout and decoding errors say nothing either way.
+                match err.downcast_ref::<jsonrpc_core::Error>() {
+                    Some(rpc) => TransferError::Rejected(rpc.to_string()),
+                    None => TransferError::Unknown(err.to_string()),
+                }
+            })?;
+        Ok(transfer.tx_hash.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::{NewPayment, NewRefund, PaymentId};
+    use anon_ticket_domain::storage::{PaymentStore, RefundStore};
+    use anon_ticket_storage::SeaOrmStorage;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeWallet {
+        sent: Mutex<Vec<(String, u64)>>,
+    }
+
+    #[async_trait]
+    impl RefundWallet for &FakeWallet {
+        async fn transfer(&self, address: &str, amount: u64) -> Result<String, TransferError> {
+            if address == "unreachable" {
+                return Err(TransferError::Rejected("not enough unlocked money".into()));
+            }
+            let mut sent = self.sent.lock().unwrap();
+            sent.push((address.to_string(), amount));
+            if address == "timeout" {
+                // Relayed, but the reply never arrived.
+                return Err(TransferError::Unknown("operation timed out".into()));
+            }
+            Ok(format!("{:064x}", sent.len()))
+        }
+    }
+
+    #[tokio::test]
+    async fn pending_refunds_are_sent_once() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let pid = PaymentId::parse("0123456789abcdef").unwrap();
+        storage
+            .insert_payment(NewPayment {
+                pid: pid.clone(),
+                txid: "a".repeat(64),
+                amount: 1_000,
+                block_height: 10,
+                detected_at: Utc::now(),
+                address_id: None,
+            })
+            .await
+            .unwrap();
+        let mut ids = Vec::new();
+        for (address, amount) in [("payer", 300), ("unreachable", 200), ("timeout", 100)] {
+            let refund = NewRefund {
+                pid: pid.clone(),
+                address: address.to_string(),
+                amount,
+                requested_at: Utc::now(),
+            };
+            ids.push(storage.record_refund(refund).await.unwrap().unwrap().id);
+        }
+        let wallet = FakeWallet::default();
+        let sender = RefundSender::new(storage.clone(), &wallet);
+
+        assert_eq!(sender.send_pending().await.unwrap(), 1);
+        assert_eq!(sender.send_pending().await.unwrap(), 0);
+        assert_eq!(
+            *wallet.sent.lock().unwrap(),
+            vec![("payer".to_string(), 300), ("timeout".to_string(), 100)]
+        );
+        let sent = storage.find_refund(ids[0]).await.unwrap().unwrap();
+        assert_eq!(sent.status, RefundStatus::Sent);
+        assert_eq!(sent.txid, Some(format!("{:064x}", 1)));
+        let failed = storage.find_refund(ids[1]).await.unwrap().unwrap();
+        assert_eq!(failed.status, RefundStatus::Failed);
+        assert!(failed.error.unwrap().contains("unlocked money"));
+        let unknown = storage.find_refund(ids[2]).await.unwrap().unwrap();
+        assert_eq!(unknown.status, RefundStatus::Sending);
+        assert_eq!(unknown.error, None);
+    }
+}
@@ -0,0 +1,174 @@
+//! Minimal `monerod` JSON-RPC client for the calls the wallet RPC does not
+//! expose: fee estimates and block hashes.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::FeeEstimate;
+use crate::worker::MonitorError;
+
+pub(super) struct DaemonClient {
+    url: String,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct DaemonReply<T> {
+    result: Option<T>,
+    error: Option<DaemonReplyError>,
+}
+
+#[derive(Deserialize)]
+struct DaemonReplyError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct FeeEstimateResult {
+    fee: u64,
+    quantization_mask: u64,
+    #[serde(default)]
+    fees: Vec<u64>,
+}
+
+#[derive(Deserialize)]
+struct BlockHeaderResult {
+    block_header: BlockHeader,
+}
+
+#[derive(Deserialize)]
+struct BlockHeader {
+    hash: String,
+}
+
+impl DaemonClient {
+    pub(super) fn new(url: &str) -> Self {
+        let base = url.strip_suffix("/json_rpc").unwrap_or(url);
+        Self {
+            url: format!("{}/json_rpc", base.trim_end_matches('/')),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub(super) async fn fee_estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        let result: FeeEstimateResult =
+            self.call("get_fee_estimate", serde_json::json!({})).await?;
+        Ok(FeeEstimate {
+            fee_per_byte: result.fee,
+            quantization_mask: result.quantization_mask,
+            priority_fees: result.fees,
+        })
+    }
+
+    pub(super) async fn block_hash(&self, height: u64) -> Result<String, MonitorError> {
+        let result: BlockHeaderResult = self
+            .call(
+                "get_block_header_by_height",
+                serde_json::json!({ "height": height }),
+            )
+            .await?;
+        Ok(result.block_header.hash)
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T, MonitorError> {
+        let reply: DaemonReply<T> = self
+            .http
+            .post(&self.url)
+            .json(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": "0",
+                "method": method,
+                "params": params,
+            }))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| MonitorError::Rpc(err.to_string()))?
+            .json()
+            .await
+            .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+        unwrap_reply(method, reply)
+    }
+}
+
+fn unwrap_reply<T>(method: &str, reply: DaemonReply<T>) -> Result<T, MonitorError> {
+    if let Some(error) = reply.error {
+        return Err(MonitorError::Rpc(error.message));
+    }
+    reply
+        .result
+        .ok_or_else(|| MonitorError::Rpc(format!("empty {method} reply")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_daemon_fee_estimate() {
+        let reply: DaemonReply<FeeEstimateResult> = serde_json::from_value(serde_json::json!({
+            "id": "0",
+            "jsonrpc": "2.0",
+            "result": {
+                "fee": 20000,
+                "fees": [20000, 80000, 320000, 4000000],
+                "quantization_mask": 10000,
+                "status": "OK"
+            }
+        }))
+        .unwrap();
+        let result = unwrap_reply("get_fee_estimate", reply).unwrap();
+        let estimate = FeeEstimate {
+            fee_per_byte: result.fee,
+            quantization_mask: result.quantization_mask,
+            priority_fees: result.fees,
+        };
+        assert_eq!(estimate.fee_per_byte, 20_000);
+        assert_eq!(estimate.priority_fees.len(), 4);
+        // Rounded up to the quantization mask.
+        assert_eq!(estimate.fee_for_weight(3), 60_000);
+        assert_eq!(
+            FeeEstimate {
+                quantization_mask: 7,
+                ..estimate
+            }
+            .fee_for_weight(1),
+            20_006
+        );
+
+        let reply: DaemonReply<FeeEstimateResult> = serde_json::from_value(serde_json::json!({
+            "error": { "code": -1, "message": "busy syncing" }
+        }))
+        .unwrap();
+        assert!(matches!(
+            unwrap_reply("get_fee_estimate", reply),
+            Err(MonitorError::Rpc(message)) if message == "busy syncing"
+        ));
+    }
+
+    #[test]
+    fn parses_block_header_hash() {
+        let reply: DaemonReply<BlockHeaderResult> = serde_json::from_value(serde_json::json!({
+            "id": "0",
+            "jsonrpc": "2.0",
+            "result": {
+                "block_header": {
+                    "hash": "e22cf75f39ae720e8b71b3d120a5ac03f0db50bba6379e2850975b4859190bc6",
+                    "height": 912345,
+                    "timestamp": 1452793716
+                },
+                "status": "OK"
+            }
+        }))
+        .unwrap();
+        let result = unwrap_reply("get_block_header_by_height", reply).unwrap();
+        assert_eq!(
+            result.block_header.hash,
+            "e22cf75f39ae720e8b71b3d120a5ac03f0db50bba6379e2850975b4859190bc6"
+        );
+    }
+}
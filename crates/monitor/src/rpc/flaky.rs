@@ -0,0 +1,78 @@
+//! `FlakySource`: a `TransferSource` wrapper that injects latency and RPC
+//! failures (feature `fault-injection`).
+
+use anon_ticket_domain::services::fault::{FaultConfig, FaultInjector};
+use async_trait::async_trait;
+
+use super::{FeeEstimate, TransferSource, TransfersResponse};
+use crate::worker::MonitorError;
+
+#[derive(Debug)]
+pub struct FlakySource<S> {
+    inner: S,
+    faults: FaultInjector,
+}
+
+impl<S> FlakySource<S> {
+    pub fn new(inner: S, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            faults: FaultInjector::new(config),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<S: TransferSource> TransferSource for FlakySource<S> {
+    async fn fetch_transfers(
+        &self,
+        start_height: u64,
+        max_height: u64,
+    ) -> Result<TransfersResponse, MonitorError> {
+        self.faults
+            .inject("fetch_transfers")
+            .await
+            .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+        self.inner.fetch_transfers(start_height, max_height).await
+    }
+
+    async fn wallet_height(&self) -> Result<u64, MonitorError> {
+        self.faults
+            .inject("wallet_height")
+            .await
+            .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+        self.inner.wallet_height().await
+    }
+
+    async fn fetch_pool_transfers(&self) -> Result<TransfersResponse, MonitorError> {
+        self.faults
+            .inject("fetch_pool_transfers")
+            .await
+            .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+        self.inner.fetch_pool_transfers().await
+    }
+
+    async fn fee_estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        self.faults
+            .inject("fee_estimate")
+            .await
+            .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+        self.inner.fee_estimate().await
+    }
+
+    async fn block_hash(&self, height: u64) -> Result<Option<String>, MonitorError> {
+        self.faults
+            .inject("block_hash")
+            .await
+            .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+        self.inner.block_hash(height).await
+    }
+
+    async fn wait_for_activity(&self) {
+        self.inner.wait_for_activity().await
+    }
+}
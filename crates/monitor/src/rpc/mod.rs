@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::worker::MonitorError;
 use anon_ticket_domain::model::PaymentId;
@@ -8,9 +9,23 @@ use monero_rpc::{
     BlockHeightFilter, GetTransfersCategory, GetTransfersSelector, TransferHeight, WalletClient,
 };
 
+mod daemon;
+#[cfg(feature = "fault-injection")]
+mod flaky;
+mod simulated;
+mod subaddress;
 mod types;
+#[cfg(feature = "zmq")]
+mod zmq;
 
-pub use types::{TransferEntry, TransfersResponse};
+use daemon::DaemonClient;
+#[cfg(feature = "fault-injection")]
+pub use flaky::FlakySource;
+pub use simulated::{SimulatedReorg, SimulatedTransferSource, DEFAULT_BLOCK_INTERVAL};
+pub use subaddress::SubaddressTransferSource;
+pub use types::{FeeEstimate, SkippedEntry, TransferEntry, TransfersResponse};
+#[cfg(feature = "zmq")]
+pub use zmq::{ZmqNotifier, ZmqTransferSource};
 
 #[async_trait]
 pub trait TransferSource: Send + Sync {
@@ -21,15 +36,81 @@ pub trait TransferSource: Send + Sync {
         max_height: u64,
     ) -> Result<TransfersResponse, MonitorError>;
     async fn wallet_height(&self) -> Result<u64, MonitorError>;
+    /// Incoming transfers still in the mempool, with no height. Sources that
+    /// cannot see the pool report none.
+    async fn fetch_pool_transfers(&self) -> Result<TransfersResponse, MonitorError> {
+        Ok(TransfersResponse::default())
+    }
+    /// Current network fee estimate. Sources without access to a daemon
+    /// report it as unavailable.
+    async fn fee_estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        Err(MonitorError::Rpc("fee estimate not available".to_string()))
+    }
+
+    /// Hash of the main-chain block at `height`, used to detect reorgs.
+    /// `None` means the source cannot tell, which disables detection.
+    async fn block_hash(&self, _height: u64) -> Result<Option<String>, MonitorError> {
+        Ok(None)
+    }
+
+    /// Resolves when the chain has likely moved, so the monitor can poll
+    /// before `MONITOR_POLL_INTERVAL_SECS` is up. The default never
+    /// resolves, leaving the interval alone.
+    async fn wait_for_activity(&self) {
+        std::future::pending::<()>().await
+    }
+}
+
+/// Lets one source (e.g. a subaddress source that checkout also uses) be
+/// shared with the monitor loop.
+#[async_trait]
+impl<T: TransferSource + ?Sized> TransferSource for Arc<T> {
+    async fn fetch_transfers(
+        &self,
+        start_height: u64,
+        max_height: u64,
+    ) -> Result<TransfersResponse, MonitorError> {
+        (**self).fetch_transfers(start_height, max_height).await
+    }
+
+    async fn wallet_height(&self) -> Result<u64, MonitorError> {
+        (**self).wallet_height().await
+    }
+
+    async fn fetch_pool_transfers(&self) -> Result<TransfersResponse, MonitorError> {
+        (**self).fetch_pool_transfers().await
+    }
+
+    async fn fee_estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        (**self).fee_estimate().await
+    }
+
+    async fn block_hash(&self, height: u64) -> Result<Option<String>, MonitorError> {
+        (**self).block_hash(height).await
+    }
+
+    async fn wait_for_activity(&self) {
+        (**self).wait_for_activity().await
+    }
 }
 
 pub struct RpcTransferSource {
     wallet: WalletClient,
+    daemon: Option<DaemonClient>,
 }
 
 impl RpcTransferSource {
     pub fn new(wallet: WalletClient) -> Self {
-        Self { wallet }
+        Self {
+            wallet,
+            daemon: None,
+        }
+    }
+
+    /// Enables `fee_estimate` and `block_hash` against the daemon at `url`.
+    pub fn with_daemon(mut self, url: &str) -> Self {
+        self.daemon = Some(DaemonClient::new(url));
+        self
     }
 }
 
@@ -40,53 +121,113 @@ impl TransferSource for RpcTransferSource {
         start_height: u64,
         max_height: u64,
     ) -> Result<TransfersResponse, MonitorError> {
-        let mut categories = HashMap::new();
-        categories.insert(GetTransfersCategory::In, true);
-
-        let selector = GetTransfersSelector {
-            category_selector: categories,
-            account_index: None,
-            subaddr_indices: None,
-            block_height_filter: Some(BlockHeightFilter {
-                min_height: Some(start_height),
-                max_height: Some(max_height),
-            }),
-        };
-
-        let mut result = self
-            .wallet
-            .get_transfers(selector)
-            .await
-            .map_err(|err| MonitorError::Rpc(err.to_string()))?;
-
-        let incoming = result.remove(&GetTransfersCategory::In).unwrap_or_default();
-
-        let mut entries = Vec::with_capacity(incoming.len());
-        for transfer in incoming {
-            if let Some(entry) = convert_transfer(transfer)? {
-                entries.push(entry);
-            }
-        }
-
-        Ok(TransfersResponse { incoming: entries })
+        let incoming = fetch_incoming(&self.wallet, None, start_height, max_height).await?;
+        convert_transfers(incoming)
     }
 
     async fn wallet_height(&self) -> Result<u64, MonitorError> {
-        Ok(self
-            .wallet
-            .get_height()
-            .await
-            .map_err(|err| MonitorError::Rpc(err.to_string()))?
-            .get())
+        wallet_height(&self.wallet).await
+    }
+
+    async fn fetch_pool_transfers(&self) -> Result<TransfersResponse, MonitorError> {
+        convert_transfers(fetch_pool(&self.wallet, None).await?)
+    }
+
+    async fn fee_estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        daemon(&self.daemon)?.fee_estimate().await
     }
+
+    async fn block_hash(&self, height: u64) -> Result<Option<String>, MonitorError> {
+        match &self.daemon {
+            Some(daemon) => daemon.block_hash(height).await.map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Incoming transfers in the inclusive height range, optionally limited to one
+/// account.
+async fn fetch_incoming(
+    wallet: &WalletClient,
+    account_index: Option<u32>,
+    start_height: u64,
+    max_height: u64,
+) -> Result<Vec<monero_rpc::GotTransfer>, MonitorError> {
+    let filter = BlockHeightFilter {
+        min_height: Some(start_height),
+        max_height: Some(max_height),
+    };
+    get_transfers(
+        wallet,
+        GetTransfersCategory::In,
+        account_index,
+        Some(filter),
+    )
+    .await
+}
+
+/// Incoming transfers in the mempool, optionally limited to one account.
+async fn fetch_pool(
+    wallet: &WalletClient,
+    account_index: Option<u32>,
+) -> Result<Vec<monero_rpc::GotTransfer>, MonitorError> {
+    get_transfers(wallet, GetTransfersCategory::Pool, account_index, None).await
+}
+
+async fn get_transfers(
+    wallet: &WalletClient,
+    category: GetTransfersCategory,
+    account_index: Option<u32>,
+    block_height_filter: Option<BlockHeightFilter>,
+) -> Result<Vec<monero_rpc::GotTransfer>, MonitorError> {
+    let mut categories = HashMap::new();
+    categories.insert(category.clone(), true);
+
+    let selector = GetTransfersSelector {
+        category_selector: categories,
+        account_index,
+        subaddr_indices: None,
+        block_height_filter,
+    };
+
+    let mut result = wallet
+        .get_transfers(selector)
+        .await
+        .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+
+    Ok(result.remove(&category).unwrap_or_default())
 }
 
-fn convert_transfer(
-    transfer: monero_rpc::GotTransfer,
-) -> Result<Option<TransferEntry>, MonitorError> {
-    let amount = i64::try_from(transfer.amount.as_pico())
-        .map_err(|_| MonitorError::Rpc("amount overflow".to_string()))?;
+async fn wallet_height(wallet: &WalletClient) -> Result<u64, MonitorError> {
+    Ok(wallet
+        .get_height()
+        .await
+        .map_err(|err| MonitorError::Rpc(err.to_string()))?
+        .get())
+}
+
+fn daemon(daemon: &Option<DaemonClient>) -> Result<&DaemonClient, MonitorError> {
+    daemon
+        .as_ref()
+        .ok_or_else(|| MonitorError::Rpc("no daemon RPC configured".to_string()))
+}
 
+fn convert_transfers(
+    transfers: Vec<monero_rpc::GotTransfer>,
+) -> Result<TransfersResponse, MonitorError> {
+    let mut response = TransfersResponse::default();
+    for transfer in transfers {
+        match convert_transfer(transfer) {
+            Ok(entry) => response.incoming.push(entry),
+            Err(skipped) => response.skipped.push(skipped),
+        }
+    }
+    Ok(response)
+}
+
+/// Fails with the transfer as reported when it does not fit a
+/// [`TransferEntry`], rather than failing the whole batch.
+fn convert_transfer(transfer: monero_rpc::GotTransfer) -> Result<TransferEntry, SkippedEntry> {
     let height = match transfer.height {
         TransferHeight::Confirmed(h) => Some(h.get() as i64),
         TransferHeight::InPool => None,
@@ -99,14 +240,26 @@ fn convert_transfer(
     };
 
     let timestamp = transfer.timestamp.timestamp() as u64;
+    let txid = transfer.txid.to_string();
 
-    Ok(Some(TransferEntry {
-        txid: transfer.txid.to_string(),
+    let Ok(amount) = i64::try_from(transfer.amount.as_pico()) else {
+        return Err(SkippedEntry {
+            txid,
+            reason: "amount_overflow",
+            amount: transfer.amount.as_pico().to_string(),
+            height,
+            timestamp,
+            payment_id: Some(transfer.payment_id.to_string()),
+        });
+    };
+
+    Ok(TransferEntry {
+        txid,
         amount,
         height,
         timestamp,
         payment_id,
-    }))
+    })
 }
 
 #[cfg(test)]
@@ -121,8 +274,9 @@ mod tests {
     use std::num::NonZeroU64;
     use std::str::FromStr;
 
-    #[test]
-    fn converts_got_transfer_into_entry() {
+    /// A confirmed 1_000_000 piconero transfer carrying payment id
+    /// `0001020304050607`, received on the primary address.
+    pub(super) fn sample_transfer() -> monero_rpc::GotTransfer {
         let address = Address::from_str(
             "4ADT1BtbxqEWeMKp9GgPr2NeyJXXtNxvoDawpyA4WpzFcGcoHUvXeijE66DNfohE9r1bQYaBiQjEtKE7CtkTdLwiDznFzra",
         )
@@ -133,7 +287,7 @@ mod tests {
                 .unwrap(),
         );
 
-        let transfer = monero_rpc::GotTransfer {
+        monero_rpc::GotTransfer {
             address,
             amount: Amount::from_pico(1_000_000),
             confirmations: Some(1),
@@ -149,14 +303,31 @@ mod tests {
             txid,
             transfer_type: GetTransfersCategory::In,
             unlock_time: 0,
-        };
+        }
+    }
+
+    #[test]
+    fn converts_got_transfer_into_entry() {
+        let transfer = sample_transfer();
 
-        let entry = convert_transfer(transfer)
-            .expect("conversion succeeds")
-            .expect("entry present");
+        let entry = convert_transfer(transfer).expect("conversion succeeds");
 
         assert_eq!(entry.amount, 1_000_000);
         assert_eq!(entry.height, Some(123456));
         assert_eq!(entry.payment_id.as_deref(), Some("0001020304050607"));
     }
+
+    #[test]
+    fn amounts_beyond_i64_are_skipped_not_fatal() {
+        let mut transfer = sample_transfer();
+        transfer.amount = Amount::from_pico(u64::MAX);
+
+        let response = convert_transfers(vec![transfer, sample_transfer()]).unwrap();
+        assert_eq!(response.incoming.len(), 1);
+        assert_eq!(response.skipped.len(), 1);
+        let skipped = &response.skipped[0];
+        assert_eq!(skipped.reason, "amount_overflow");
+        assert_eq!(skipped.amount, u64::MAX.to_string());
+        assert_eq!(skipped.payment_id.as_deref(), Some("0001020304050607"));
+    }
 }
@@ -0,0 +1,608 @@
+//! Deterministic chain simulator implementing `TransferSource`.
+//!
+//! The chain tip advances on a virtual clock (`advance_time`/`advance_blocks`)
+//! instead of wall time, transfers are scheduled at fixed heights (or replayed
+//! from recorded entries), and reorgs fire when the tip reaches a configured
+//! height. Combined with `poll_once` this lets tests step cursor advancement and
+//! confirmation logic without sleeping.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::{FeeEstimate, TransferEntry, TransferSource, TransfersResponse};
+use crate::worker::MonitorError;
+
+/// Monero's target block time.
+pub const DEFAULT_BLOCK_INTERVAL: Duration = Duration::from_secs(120);
+
+/// A reorg that orphans the top `depth` blocks once the tip reaches `at_height`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulatedReorg {
+    pub at_height: u64,
+    pub depth: u64,
+    /// Blocks after the reorg point at which orphaned transfers are re-mined;
+    /// `None` drops them (e.g. a double spend won the race).
+    pub remine_after: Option<u64>,
+}
+
+#[derive(Debug)]
+struct SimState {
+    tip: u64,
+    elapsed: Duration,
+    transfers: Vec<TransferEntry>,
+    reorgs: Vec<SimulatedReorg>,
+    reorgs_applied: usize,
+    /// First orphaned height of every applied reorg; block hashes at or above
+    /// one change when it fires.
+    fork_heights: Vec<u64>,
+}
+
+#[derive(Debug)]
+pub struct SimulatedTransferSource {
+    start_height: u64,
+    genesis_timestamp: u64,
+    block_interval: Duration,
+    fee_estimate: FeeEstimate,
+    state: Mutex<SimState>,
+}
+
+impl SimulatedTransferSource {
+    /// Starts a chain whose tip is `start_height` at virtual time zero.
+    pub fn new(start_height: u64) -> Self {
+        Self {
+            start_height,
+            genesis_timestamp: 1_700_000_000,
+            block_interval: DEFAULT_BLOCK_INTERVAL,
+            fee_estimate: FeeEstimate {
+                fee_per_byte: 20_000,
+                quantization_mask: 10_000,
+                priority_fees: Vec::new(),
+            },
+            state: Mutex::new(SimState {
+                tip: start_height,
+                elapsed: Duration::ZERO,
+                transfers: Vec::new(),
+                reorgs: Vec::new(),
+                reorgs_applied: 0,
+                fork_heights: Vec::new(),
+            }),
+        }
+    }
+
+    /// Replays previously recorded entries; entries without a height are ignored.
+    pub fn from_recorded(
+        start_height: u64,
+        entries: impl IntoIterator<Item = TransferEntry>,
+    ) -> Self {
+        let source = Self::new(start_height);
+        {
+            let mut state = source.lock();
+            state
+                .transfers
+                .extend(entries.into_iter().filter(|entry| entry.height.is_some()));
+        }
+        source
+    }
+
+    pub fn with_block_interval(mut self, interval: Duration) -> Self {
+        self.block_interval = interval.max(Duration::from_millis(1));
+        self
+    }
+
+    pub fn with_genesis_timestamp(mut self, timestamp: u64) -> Self {
+        self.genesis_timestamp = timestamp;
+        self
+    }
+
+    pub fn with_fee_estimate(mut self, estimate: FeeEstimate) -> Self {
+        self.fee_estimate = estimate;
+        self
+    }
+
+    /// Schedules a synthetic incoming transfer mined at `height`.
+    pub fn schedule_transfer(
+        &self,
+        height: u64,
+        txid: impl Into<String>,
+        payment_id: Option<&str>,
+        amount: i64,
+    ) {
+        let timestamp = self.block_timestamp(height);
+        self.lock().transfers.push(TransferEntry {
+            txid: txid.into(),
+            amount,
+            height: Some(height as i64),
+            timestamp,
+            payment_id: payment_id.map(str::to_owned),
+        });
+    }
+
+    pub fn schedule_reorg(&self, reorg: SimulatedReorg) {
+        let mut state = self.lock();
+        state.reorgs.push(reorg);
+        state.reorgs.sort_by_key(|r| r.at_height);
+    }
+
+    /// Advances the virtual clock, mining one block per elapsed block interval.
+    pub fn advance_time(&self, delta: Duration) -> u64 {
+        let mut state = self.lock();
+        state.elapsed += delta;
+        let mined = (state.elapsed.as_millis() / self.block_interval.as_millis()) as u64;
+        let target = self.start_height + mined;
+        self.mine_to(&mut state, target);
+        state.tip
+    }
+
+    /// Mines exactly `blocks` blocks, moving the virtual clock accordingly.
+    pub fn advance_blocks(&self, blocks: u64) -> u64 {
+        self.advance_time(self.block_interval * blocks as u32)
+    }
+
+    pub fn tip(&self) -> u64 {
+        self.lock().tip
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.lock().elapsed
+    }
+
+    /// Number of scheduled reorgs that have fired so far.
+    pub fn reorgs_applied(&self) -> usize {
+        self.lock().reorgs_applied
+    }
+
+    fn mine_to(&self, state: &mut SimState, target: u64) {
+        while state.tip < target {
+            state.tip += 1;
+            let tip = state.tip;
+            let due: Vec<SimulatedReorg> = state
+                .reorgs
+                .iter()
+                .filter(|r| r.at_height == tip)
+                .copied()
+                .collect();
+            for reorg in due {
+                self.apply_reorg(state, reorg);
+            }
+        }
+    }
+
+    fn apply_reorg(&self, state: &mut SimState, reorg: SimulatedReorg) {
+        let orphaned_from = reorg.at_height.saturating_sub(reorg.depth) + 1;
+        let remine_height = reorg.remine_after.map(|delay| reorg.at_height + delay);
+        let mut kept = Vec::with_capacity(state.transfers.len());
+        for mut entry in state.transfers.drain(..) {
+            let height = entry.height.unwrap_or_default() as u64;
+            if (orphaned_from..=reorg.at_height).contains(&height) {
+                match remine_height {
+                    Some(new_height) => {
+                        entry.height = Some(new_height as i64);
+                        entry.timestamp = self.block_timestamp(new_height);
+                    }
+                    None => continue,
+                }
+            }
+            kept.push(entry);
+        }
+        state.transfers = kept;
+        state.reorgs_applied += 1;
+        state.fork_heights.push(orphaned_from);
+    }
+
+    fn block_timestamp(&self, height: u64) -> u64 {
+        let offset = height.saturating_sub(self.start_height);
+        self.genesis_timestamp + offset * self.block_interval.as_secs()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SimState> {
+        self.state.lock().expect("simulated chain lock poisoned")
+    }
+}
+
+#[async_trait]
+impl TransferSource for SimulatedTransferSource {
+    async fn fetch_transfers(
+        &self,
+        start_height: u64,
+        max_height: u64,
+    ) -> Result<TransfersResponse, MonitorError> {
+        let state = self.lock();
+        let upper = max_height.min(state.tip);
+        let incoming = state
+            .transfers
+            .iter()
+            .filter(|entry| {
+                let height = entry.height.unwrap_or_default() as u64;
+                height >= start_height && height <= upper
+            })
+            .cloned()
+            .collect();
+        Ok(TransfersResponse {
+            incoming,
+            skipped: Vec::new(),
+        })
+    }
+
+    async fn wallet_height(&self) -> Result<u64, MonitorError> {
+        Ok(self.tip())
+    }
+
+    /// Transfers scheduled for the next block are in the pool.
+    async fn fetch_pool_transfers(&self) -> Result<TransfersResponse, MonitorError> {
+        let state = self.lock();
+        let next = state.tip.saturating_add(1) as i64;
+        let incoming = state
+            .transfers
+            .iter()
+            .filter(|entry| entry.height == Some(next))
+            .map(|entry| TransferEntry {
+                height: None,
+                ..entry.clone()
+            })
+            .collect();
+        Ok(TransfersResponse {
+            incoming,
+            skipped: Vec::new(),
+        })
+    }
+
+    async fn fee_estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        Ok(self.fee_estimate.clone())
+    }
+
+    /// Deterministic per height, and different on each side of a reorg.
+    async fn block_hash(&self, height: u64) -> Result<Option<String>, MonitorError> {
+        let state = self.lock();
+        if height > state.tip {
+            return Ok(None);
+        }
+        let generation = state
+            .fork_heights
+            .iter()
+            .filter(|fork| **fork <= height)
+            .count();
+        Ok(Some(format!("{height:032x}{generation:032x}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::IngestRules;
+    use crate::worker::{poll_once, run_monitor, MonitorHooks, PollOutcome, WalletCursor};
+    use anon_ticket_domain::config::{BootstrapConfig, PRIMARY_WALLET};
+    use anon_ticket_domain::model::{
+        derive_service_token, NewServiceToken, PaymentId, PaymentState,
+    };
+    use anon_ticket_domain::storage::{
+        MonitorStateStore, PaymentStore, PendingPaymentStore, TokenStore,
+    };
+    use anon_ticket_storage::SeaOrmStorage;
+    use chrono::Utc;
+
+    const PID: &str = "2222222222222222";
+    const MIN_CONFIRMATIONS: u64 = 10;
+
+    async fn storage() -> SeaOrmStorage {
+        SeaOrmStorage::connect("sqlite::memory:")
+            .await
+            .expect("storage inits")
+    }
+
+    async fn step(
+        storage: &SeaOrmStorage,
+        chain: &SimulatedTransferSource,
+        cursor: &mut WalletCursor,
+    ) -> PollOutcome {
+        let tip = chain.wallet_height().await.unwrap();
+        poll_once(
+            storage,
+            chain,
+            cursor,
+            tip,
+            IngestRules::new(1),
+            MIN_CONFIRMATIONS,
+            None,
+        )
+        .await
+        .expect("poll succeeds")
+    }
+
+    /// [`step`] with pending transfers recorded, at 3 confirmations.
+    async fn step_tracking(
+        storage: &SeaOrmStorage,
+        chain: &SimulatedTransferSource,
+        cursor: &mut WalletCursor,
+        hooks: &MonitorHooks,
+    ) -> PollOutcome {
+        let tip = chain.tip();
+        poll_once(
+            storage,
+            chain,
+            cursor,
+            tip,
+            IngestRules::new(1),
+            3,
+            Some(hooks),
+        )
+        .await
+        .expect("poll succeeds")
+    }
+
+    #[tokio::test]
+    async fn virtual_clock_mines_blocks_deterministically() {
+        let chain = SimulatedTransferSource::new(100);
+        assert_eq!(chain.advance_time(Duration::from_secs(119)), 100);
+        assert_eq!(chain.advance_time(Duration::from_secs(1)), 101);
+        assert_eq!(chain.advance_blocks(4), 105);
+        assert_eq!(chain.elapsed(), Duration::from_secs(600));
+    }
+
+    #[tokio::test]
+    async fn pending_transfers_are_tracked_until_ingested() {
+        let storage = storage().await;
+        let chain = SimulatedTransferSource::new(100);
+        chain.schedule_transfer(101, "a".repeat(64), Some(PID), 500);
+        let pid = PaymentId::parse(PID).unwrap();
+        let mut cursor = WalletCursor::primary(100);
+        let hooks = MonitorHooks::new(None, None)
+            .with_pending_store(Some(
+                std::sync::Arc::new(storage.clone()) as std::sync::Arc<dyn PendingPaymentStore>
+            ));
+
+        // Scheduled for the next block, so still in the pool.
+        step_tracking(&storage, &chain, &mut cursor, &hooks).await;
+        let pending = storage.find_pending(&pid).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].block_height, None);
+        assert_eq!(pending[0].confirmations, 0);
+
+        chain.advance_blocks(1);
+        step_tracking(&storage, &chain, &mut cursor, &hooks).await;
+        let pending = storage.find_pending(&pid).await.unwrap();
+        assert_eq!(pending[0].block_height, Some(101));
+        assert_eq!(pending[0].confirmations, 1);
+        assert!(storage.find_payment(&pid).await.unwrap().is_none());
+
+        chain.advance_blocks(2);
+        step_tracking(&storage, &chain, &mut cursor, &hooks).await;
+        assert!(storage.find_payment(&pid).await.unwrap().is_some());
+        assert!(storage.find_pending(&pid).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn payment_ingested_only_after_confirmations() {
+        let storage = storage().await;
+        let chain = SimulatedTransferSource::new(100);
+        chain.schedule_transfer(105, "tx-sim", Some(PID), 500);
+        let pid = PaymentId::parse(PID).unwrap();
+        let mut cursor = WalletCursor::primary(100);
+
+        chain.advance_blocks(10); // tip 110, safe height 101
+        step(&storage, &chain, &mut cursor).await;
+        assert!(storage.find_payment(&pid).await.unwrap().is_none());
+        assert_eq!(cursor.height, 102);
+
+        chain.advance_blocks(4); // tip 114, safe height 105
+        step(&storage, &chain, &mut cursor).await;
+        let payment = storage.find_payment(&pid).await.unwrap().expect("ingested");
+        assert_eq!(payment.block_height, 105);
+        assert_eq!(cursor.height, 106);
+        assert_eq!(
+            storage.last_processed_height(PRIMARY_WALLET).await.unwrap(),
+            Some(106)
+        );
+
+        // No new blocks: the cursor sits past the safe window.
+        let outcome = step(&storage, &chain, &mut cursor).await;
+        assert_eq!(
+            outcome,
+            PollOutcome::AwaitingConfirmations { safe_height: 105 }
+        );
+    }
+
+    #[tokio::test]
+    async fn reorg_inside_confirmation_window_is_absorbed() {
+        let storage = storage().await;
+        let chain = SimulatedTransferSource::new(100);
+        chain.schedule_transfer(105, "tx-reorged", Some(PID), 500);
+        chain.schedule_reorg(SimulatedReorg {
+            at_height: 108,
+            depth: 5,
+            remine_after: Some(3),
+        });
+        let pid = PaymentId::parse(PID).unwrap();
+        let mut cursor = WalletCursor::primary(100);
+
+        for _ in 0..25 {
+            chain.advance_blocks(1);
+            step(&storage, &chain, &mut cursor).await;
+        }
+
+        assert_eq!(chain.reorgs_applied(), 1);
+        let payment = storage.find_payment(&pid).await.unwrap().expect("ingested");
+        assert_eq!(payment.block_height, 111);
+    }
+
+    #[tokio::test]
+    async fn dropped_transfers_are_never_ingested() {
+        let storage = storage().await;
+        let chain = SimulatedTransferSource::new(100);
+        chain.schedule_transfer(103, "tx-double-spent", Some(PID), 500);
+        chain.schedule_reorg(SimulatedReorg {
+            at_height: 104,
+            depth: 2,
+            remine_after: None,
+        });
+        let mut cursor = WalletCursor::primary(100);
+        chain.advance_blocks(30);
+        step(&storage, &chain, &mut cursor).await;
+
+        let pid = PaymentId::parse(PID).unwrap();
+        assert!(storage.find_payment(&pid).await.unwrap().is_none());
+        assert_eq!(cursor.height, 122);
+    }
+
+    /// Mines one block at a time, stepping the monitor after each, up to `tip`.
+    async fn mine_until(
+        storage: &SeaOrmStorage,
+        chain: &SimulatedTransferSource,
+        cursor: &mut WalletCursor,
+        tip: u64,
+    ) {
+        while chain.tip() < tip {
+            chain.advance_blocks(1);
+            step(storage, chain, cursor).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn wallets_share_storage_but_not_cursors() {
+        let storage = storage().await;
+        let primary = SimulatedTransferSource::new(100);
+        let hot = SimulatedTransferSource::new(100);
+        primary.schedule_transfer(105, "tx-primary", Some(PID), 500);
+        hot.schedule_transfer(108, "tx-hot", Some("3333333333333333"), 700);
+        primary.advance_blocks(20);
+        hot.advance_blocks(20);
+        let config = BootstrapConfig::builder("sqlite::memory:", "http://127.0.0.1:18082", 100)
+            .monitor_min_payment_amount(1)
+            .monitor_min_confirmations(MIN_CONFIRMATIONS)
+            .extra_wallet("hot", "http://127.0.0.1:18083")
+            .build()
+            .unwrap();
+        let wallets = vec![
+            (PRIMARY_WALLET.to_string(), primary),
+            ("hot".to_string(), hot),
+        ];
+
+        // Shutting down right away still lets the first tick finish.
+        let shutdown = tokio_util::sync::CancellationToken::new();
+        shutdown.cancel();
+        run_monitor(config, storage.clone(), wallets, None, shutdown)
+            .await
+            .unwrap();
+
+        for (pid, amount) in [(PID, 500), ("3333333333333333", 700)] {
+            let pid = PaymentId::parse(pid).unwrap();
+            let payment = storage.find_payment(&pid).await.unwrap().expect("ingested");
+            assert_eq!(payment.amount, amount);
+        }
+        assert_eq!(
+            storage.last_processed_height(PRIMARY_WALLET).await.unwrap(),
+            Some(106)
+        );
+        assert_eq!(
+            storage.last_processed_height("hot").await.unwrap(),
+            Some(109)
+        );
+    }
+
+    #[tokio::test]
+    async fn reorg_below_safe_height_rolls_back_and_reingests() {
+        let storage = storage().await;
+        let chain = SimulatedTransferSource::new(100);
+        chain.schedule_transfer(105, "tx-reorged", Some(PID), 500);
+        chain.schedule_reorg(SimulatedReorg {
+            at_height: 121,
+            depth: 20,
+            remine_after: Some(3),
+        });
+        let pid = PaymentId::parse(PID).unwrap();
+        let mut cursor = WalletCursor::primary(100);
+
+        mine_until(&storage, &chain, &mut cursor, 120).await;
+        assert_eq!(
+            storage
+                .find_payment(&pid)
+                .await
+                .unwrap()
+                .unwrap()
+                .block_height,
+            105
+        );
+
+        // Blocks 102..=121 are replaced, so the payment is rolled back until
+        // its transaction is re-mined and confirmed again.
+        mine_until(&storage, &chain, &mut cursor, 121).await;
+        assert!(storage.find_payment(&pid).await.unwrap().is_none());
+
+        mine_until(&storage, &chain, &mut cursor, 140).await;
+        assert_eq!(
+            storage
+                .find_payment(&pid)
+                .await
+                .unwrap()
+                .unwrap()
+                .block_height,
+            124
+        );
+    }
+
+    #[tokio::test]
+    async fn reorg_flags_tokens_of_claimed_payments() {
+        let storage = storage().await;
+        let chain = SimulatedTransferSource::new(100);
+        chain.schedule_transfer(105, "tx-double-spent", Some(PID), 500);
+        chain.schedule_reorg(SimulatedReorg {
+            at_height: 121,
+            depth: 20,
+            remine_after: None,
+        });
+        let pid = PaymentId::parse(PID).unwrap();
+        let mut cursor = WalletCursor::primary(100);
+
+        mine_until(&storage, &chain, &mut cursor, 120).await;
+        let claimed = storage.claim_payment(&pid).await.unwrap().unwrap();
+        let token = derive_service_token(&pid, &claimed.txid);
+        storage
+            .insert_token(NewServiceToken {
+                token: token.clone(),
+                pid: pid.clone(),
+                amount: claimed.amount,
+                issued_at: Utc::now(),
+                abuse_score: 0,
+                expires_at: None,
+                tier: None,
+            })
+            .await
+            .unwrap();
+
+        mine_until(&storage, &chain, &mut cursor, 140).await;
+        let review = storage.find_token_review(&token).await.unwrap();
+        assert_eq!(review.unwrap().reason, "reorg at height 102");
+        // The transfer is never re-mined, so the payment stays reorged; only
+        // the review decides the token's fate.
+        assert_eq!(
+            storage.find_payment(&pid).await.unwrap().unwrap().status,
+            PaymentState::Reorged
+        );
+    }
+
+    #[tokio::test]
+    async fn recorded_entries_replay_by_height() {
+        let chain = SimulatedTransferSource::from_recorded(
+            50,
+            vec![TransferEntry {
+                txid: "recorded".into(),
+                amount: 1,
+                height: Some(52),
+                timestamp: 0,
+                payment_id: Some(PID.into()),
+            }],
+        );
+        assert!(chain
+            .fetch_transfers(50, 60)
+            .await
+            .unwrap()
+            .incoming
+            .is_empty());
+        chain.advance_blocks(2);
+        assert_eq!(
+            chain.fetch_transfers(50, 60).await.unwrap().incoming.len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! `SubaddressTransferSource`: attributes incoming transfers to orders by the
+//! wallet subaddress they arrive on instead of an embedded payment id.
+//!
+//! Each order gets its own subaddress from [`create_subaddress`], and the
+//! `(account, minor)` index is stored against the order's PID. Transfers are
+//! mapped back through that index, so the pipeline still receives entries
+//! keyed by PID. Transfers to unmapped indices, including the primary
+//! address, carry no PID and are skipped like any other unattributed payment.
+//!
+//! [`create_subaddress`]: SubaddressTransferSource::create_subaddress
+
+use anon_ticket_domain::model::{PaymentId, SubaddressRecord};
+use anon_ticket_domain::storage::SubaddressStore;
+use async_trait::async_trait;
+use chrono::Utc;
+use monero_rpc::WalletClient;
+
+use super::{
+    convert_transfer, daemon, fetch_incoming, fetch_pool, wallet_height, DaemonClient, FeeEstimate,
+    SkippedEntry, TransferEntry, TransferSource, TransfersResponse,
+};
+use crate::worker::MonitorError;
+
+pub struct SubaddressTransferSource<S> {
+    wallet: WalletClient,
+    account_index: u32,
+    store: S,
+    daemon: Option<DaemonClient>,
+}
+
+impl<S: SubaddressStore> SubaddressTransferSource<S> {
+    pub fn new(wallet: WalletClient, account_index: u32, store: S) -> Self {
+        Self {
+            wallet,
+            account_index,
+            store,
+            daemon: None,
+        }
+    }
+
+    /// Enables `fee_estimate` and `block_hash` against the daemon at `url`.
+    pub fn with_daemon(mut self, url: &str) -> Self {
+        self.daemon = Some(DaemonClient::new(url));
+        self
+    }
+
+    pub fn account_index(&self) -> u32 {
+        self.account_index
+    }
+
+    /// Derives a fresh subaddress for `pid` and records the mapping. The PID
+    /// is used as the wallet label so operators can match them by hand.
+    pub async fn create_subaddress(
+        &self,
+        pid: &PaymentId,
+    ) -> Result<SubaddressRecord, MonitorError> {
+        let (address, minor_index) = self
+            .wallet
+            .create_address(self.account_index, Some(pid.to_hex()))
+            .await
+            .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+        let record = SubaddressRecord {
+            pid: pid.clone(),
+            account_index: self.account_index,
+            minor_index,
+            address: address.to_string(),
+            created_at: Utc::now(),
+        };
+        self.store.insert_subaddress(record.clone()).await?;
+        Ok(record)
+    }
+}
+
+#[async_trait]
+impl<S: SubaddressStore> TransferSource for SubaddressTransferSource<S> {
+    async fn fetch_transfers(
+        &self,
+        start_height: u64,
+        max_height: u64,
+    ) -> Result<TransfersResponse, MonitorError> {
+        let incoming = fetch_incoming(
+            &self.wallet,
+            Some(self.account_index),
+            start_height,
+            max_height,
+        )
+        .await?;
+        attribute_transfers(&self.store, incoming).await
+    }
+
+    async fn wallet_height(&self) -> Result<u64, MonitorError> {
+        wallet_height(&self.wallet).await
+    }
+
+    async fn fetch_pool_transfers(&self) -> Result<TransfersResponse, MonitorError> {
+        let pool = fetch_pool(&self.wallet, Some(self.account_index)).await?;
+        attribute_transfers(&self.store, pool).await
+    }
+
+    async fn fee_estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        daemon(&self.daemon)?.fee_estimate().await
+    }
+
+    async fn block_hash(&self, height: u64) -> Result<Option<String>, MonitorError> {
+        match &self.daemon {
+            Some(daemon) => daemon.block_hash(height).await.map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+async fn attribute_transfers<S: SubaddressStore>(
+    store: &S,
+    transfers: Vec<monero_rpc::GotTransfer>,
+) -> Result<TransfersResponse, MonitorError> {
+    let mut response = TransfersResponse::default();
+    for transfer in transfers {
+        match attribute_transfer(store, transfer).await? {
+            Ok(entry) => response.incoming.push(entry),
+            Err(skipped) => response.skipped.push(skipped),
+        }
+    }
+    Ok(response)
+}
+
+/// Replaces any embedded payment id with the PID mapped to the receiving
+/// subaddress.
+async fn attribute_transfer<S: SubaddressStore>(
+    store: &S,
+    transfer: monero_rpc::GotTransfer,
+) -> Result<Result<TransferEntry, SkippedEntry>, MonitorError> {
+    let index = transfer.subaddr_index;
+    let mut converted = convert_transfer(transfer);
+    let pid = store
+        .find_subaddress_by_index(index.major, index.minor)
+        .await?
+        .map(|record| record.pid.to_hex());
+    match &mut converted {
+        Ok(entry) => entry.payment_id = pid,
+        Err(skipped) => skipped.payment_id = pid,
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::tests::sample_transfer;
+    use anon_ticket_storage::SeaOrmStorage;
+    use monero_rpc::monero::cryptonote::subaddress;
+
+    #[tokio::test]
+    async fn transfers_are_keyed_by_subaddress_not_payment_id() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let pid = PaymentId::parse("aaaaaaaaaaaaaaaa").unwrap();
+        storage
+            .insert_subaddress(SubaddressRecord {
+                pid: pid.clone(),
+                account_index: 0,
+                minor_index: 3,
+                address: "8subaddress".into(),
+                created_at: Utc::now(),
+            })
+            .await
+            .unwrap();
+
+        let mut transfer = sample_transfer();
+        transfer.subaddr_index = subaddress::Index { major: 0, minor: 3 };
+        let entry = attribute_transfer(&storage, transfer)
+            .await
+            .unwrap()
+            .expect("converted");
+        assert_eq!(entry.payment_id, Some(pid.to_hex()));
+
+        // The primary address is never handed out per order, so its embedded
+        // payment id is ignored in this mode.
+        let entry = attribute_transfer(&storage, sample_transfer())
+            .await
+            .unwrap()
+            .expect("converted");
+        assert_eq!(entry.payment_id, None);
+    }
+}
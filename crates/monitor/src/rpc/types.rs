@@ -1,6 +1,8 @@
 #[derive(Debug, Clone, Default)]
 pub struct TransfersResponse {
     pub incoming: Vec<TransferEntry>,
+    /// Transfers that could not be converted into entries.
+    pub skipped: Vec<SkippedEntry>,
 }
 
 #[derive(Debug, Clone)]
@@ -12,3 +14,37 @@ pub struct TransferEntry {
     pub timestamp: u64,
     pub payment_id: Option<String>,
 }
+
+/// A transfer as the wallet reported it, dropped because it could not be
+/// converted into a [`TransferEntry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+    pub txid: String,
+    /// Why it was dropped, e.g. `amount_overflow`.
+    pub reason: &'static str,
+    /// Atomic units in decimal.
+    pub amount: String,
+    pub height: Option<i64>,
+    pub timestamp: u64,
+    pub payment_id: Option<String>,
+}
+
+/// Daemon fee estimate (`get_fee_estimate`), in atomic units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub fee_per_byte: u64,
+    /// Fees are rounded up to a multiple of this value.
+    pub quantization_mask: u64,
+    /// Per-byte fee for each priority level, lowest first; empty on daemons
+    /// that do not report them.
+    pub priority_fees: Vec<u64>,
+}
+
+impl FeeEstimate {
+    /// Fee for a transaction of `weight` bytes at the default priority.
+    pub fn fee_for_weight(&self, weight: u64) -> u64 {
+        let fee = weight.saturating_mul(self.fee_per_byte);
+        let mask = self.quantization_mask.max(1);
+        fee.div_ceil(mask).saturating_mul(mask)
+    }
+}
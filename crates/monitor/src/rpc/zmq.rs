@@ -0,0 +1,192 @@
+//! `ZmqTransferSource`: wakes the monitor as soon as `monerod` publishes a
+//! new block over ZMQ (feature `zmq`, `MONITOR_SOURCE=zmq`).
+//!
+//! Transfers are still read from the wrapped RPC source, which fetches by
+//! height range from the stored cursor. A dropped connection or a missed
+//! notification therefore costs latency, never payments: the regular poll
+//! interval keeps running underneath and catches up on the gap.
+//!
+//! Only `chain_main` is subscribed. Incoming transfers are read once mined,
+//! so pool transactions would wake the monitor without giving it anything
+//! new to fetch.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use metrics::counter;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use tracing::{info, warn};
+use zeromq::{Socket, SocketRecv, SubSocket};
+
+use super::{FeeEstimate, TransferSource, TransfersResponse};
+use crate::worker::MonitorError;
+
+/// `monerod --zmq-pub` topic announcing blocks added to the main chain.
+pub const CHAIN_MAIN_TOPIC: &str = "json-minimal-chain_main";
+
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+/// Blocks arrive every two minutes on average; a subscription this quiet is
+/// assumed dead and replaced. A false alarm only costs a reconnect.
+const SILENCE_LIMIT: Duration = Duration::from_secs(15 * 60);
+
+/// One subscription to `monerod`'s block notifications, shared by every
+/// wallet source it wakes. The subscriber task stops with the last clone.
+#[derive(Clone)]
+pub struct ZmqNotifier {
+    notify: Arc<Notify>,
+    _task: Arc<AbortOnDrop>,
+}
+
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl ZmqNotifier {
+    /// Subscribes to `endpoint` (e.g. `tcp://127.0.0.1:18084`) in the
+    /// background, reconnecting with backoff until dropped.
+    pub fn subscribe(endpoint: impl Into<String>) -> Self {
+        let notify = Arc::new(Notify::new());
+        let task = tokio::spawn(subscribe_forever(endpoint.into(), notify.clone()));
+        Self {
+            notify,
+            _task: Arc::new(AbortOnDrop(task)),
+        }
+    }
+
+    /// Resolves on the next block, or at once if one arrived while nobody
+    /// was waiting.
+    pub async fn next_block(&self) {
+        self.notify.notified().await;
+    }
+}
+
+async fn subscribe_forever(endpoint: String, notify: Arc<Notify>) {
+    let mut backoff = RECONNECT_MIN;
+    loop {
+        let err = receive(&endpoint, &notify, &mut backoff).await;
+        counter!("monitor_zmq_reconnects_total").increment(1);
+        warn!(
+            endpoint = endpoint.as_str(),
+            err,
+            retry_in_secs = backoff.as_secs(),
+            "zmq block subscription lost; polling continues on the interval"
+        );
+        sleep(backoff).await;
+        backoff = (backoff * 2).min(RECONNECT_MAX);
+    }
+}
+
+/// Forwards notifications until the subscription fails, returning why.
+async fn receive(endpoint: &str, notify: &Notify, backoff: &mut Duration) -> String {
+    let mut socket = SubSocket::new();
+    if let Err(err) = socket.connect(endpoint).await {
+        return err.to_string();
+    }
+    if let Err(err) = socket.subscribe(CHAIN_MAIN_TOPIC).await {
+        return err.to_string();
+    }
+    info!(endpoint, "subscribed to monerod block notifications");
+    loop {
+        match timeout(SILENCE_LIMIT, socket.recv()).await {
+            Ok(Ok(_)) => {
+                *backoff = RECONNECT_MIN;
+                counter!("monitor_zmq_blocks_total").increment(1);
+                notify.notify_one();
+            }
+            Ok(Err(err)) => return err.to_string(),
+            Err(_) => return format!("no block for {}s", SILENCE_LIMIT.as_secs()),
+        }
+    }
+}
+
+/// Wraps a wallet source so the monitor also polls when a block arrives.
+pub struct ZmqTransferSource<S> {
+    inner: S,
+    notifier: ZmqNotifier,
+}
+
+impl<S> ZmqTransferSource<S> {
+    pub fn new(inner: S, notifier: ZmqNotifier) -> Self {
+        Self { inner, notifier }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<S: TransferSource> TransferSource for ZmqTransferSource<S> {
+    async fn fetch_transfers(
+        &self,
+        start_height: u64,
+        max_height: u64,
+    ) -> Result<TransfersResponse, MonitorError> {
+        self.inner.fetch_transfers(start_height, max_height).await
+    }
+
+    async fn wallet_height(&self) -> Result<u64, MonitorError> {
+        self.inner.wallet_height().await
+    }
+
+    async fn fetch_pool_transfers(&self) -> Result<TransfersResponse, MonitorError> {
+        self.inner.fetch_pool_transfers().await
+    }
+
+    async fn fee_estimate(&self) -> Result<FeeEstimate, MonitorError> {
+        self.inner.fee_estimate().await
+    }
+
+    async fn block_hash(&self, height: u64) -> Result<Option<String>, MonitorError> {
+        self.inner.block_hash(height).await
+    }
+
+    async fn wait_for_activity(&self) {
+        self.notifier.next_block().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::SimulatedTransferSource;
+    use zeromq::{PubSocket, SocketSend};
+
+    #[tokio::test]
+    async fn published_blocks_wake_the_source() {
+        let mut publisher = PubSocket::new();
+        let endpoint = publisher
+            .bind("tcp://127.0.0.1:0")
+            .await
+            .expect("bind publisher");
+        let source = ZmqTransferSource::new(
+            SimulatedTransferSource::new(100),
+            ZmqNotifier::subscribe(endpoint.to_string()),
+        );
+        assert_eq!(source.wallet_height().await.unwrap(), 100);
+
+        // Subscriptions propagate asynchronously, so keep publishing until
+        // one gets through.
+        let woke = timeout(Duration::from_secs(10), async {
+            loop {
+                tokio::select! {
+                    _ = source.wait_for_activity() => break,
+                    _ = sleep(Duration::from_millis(50)) => {
+                        let block = format!("{CHAIN_MAIN_TOPIC}:{{\"first_height\":101}}");
+                        publisher.send(block.into()).await.expect("publish");
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(woke.is_ok(), "no notification within 10s");
+    }
+}
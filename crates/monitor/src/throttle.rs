@@ -0,0 +1,130 @@
+//! Keeps a failing wallet from flooding the logs: the run loop retries every
+//! tick, and a wallet that stays down would otherwise log the same warning
+//! each time.
+//!
+//! The first failure of a kind is logged at once. Repeats within
+//! [`WARN_SUMMARY_INTERVAL`] are only counted, and the next one after it is
+//! logged with that count. Suppressed lines are counted in
+//! `monitor_warnings_suppressed_total{wallet}`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use metrics::counter;
+
+/// How often a recurring failure is logged at most.
+pub const WARN_SUMMARY_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+struct Repeats {
+    logged_at: DateTime<Utc>,
+    /// Since `logged_at`.
+    suppressed: u64,
+    total: u64,
+}
+
+/// Repeated failures per wallet and kind, where a kind is e.g. an error code.
+#[derive(Debug)]
+pub struct WarnThrottle {
+    interval: Duration,
+    open: HashMap<(String, &'static str), Repeats>,
+}
+
+impl Default for WarnThrottle {
+    fn default() -> Self {
+        Self::new(WARN_SUMMARY_INTERVAL)
+    }
+}
+
+impl WarnThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            open: HashMap::new(),
+        }
+    }
+
+    /// Records a failure of `kind` on `wallet`. Returns the number of
+    /// failures suppressed since the last logged one when this one should be
+    /// logged, and `None` when it should be dropped.
+    pub fn record(&mut self, wallet: &str, kind: &'static str, now: DateTime<Utc>) -> Option<u64> {
+        let interval = chrono::Duration::from_std(self.interval).unwrap_or_default();
+        let key = (wallet.to_string(), kind);
+        let Some(repeats) = self.open.get_mut(&key) else {
+            self.open.insert(
+                key,
+                Repeats {
+                    logged_at: now,
+                    suppressed: 0,
+                    total: 1,
+                },
+            );
+            return Some(0);
+        };
+        repeats.total += 1;
+        if now - repeats.logged_at < interval {
+            repeats.suppressed += 1;
+            counter!("monitor_warnings_suppressed_total", "wallet" => wallet.to_string())
+                .increment(1);
+            return None;
+        }
+        let suppressed = repeats.suppressed;
+        repeats.logged_at = now;
+        repeats.suppressed = 0;
+        Some(suppressed)
+    }
+
+    /// Forgets `wallet`'s failures once it succeeds again, returning how many
+    /// there were in a row, if any.
+    pub fn resolve(&mut self, wallet: &str) -> Option<u64> {
+        let mut failures = None;
+        self.open.retain(|(name, _), repeats| {
+            if name != wallet {
+                return true;
+            }
+            *failures.get_or_insert(0) += repeats.total;
+            false
+        });
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_are_summarized_once_per_interval() {
+        let mut throttle = WarnThrottle::new(Duration::from_secs(60));
+        let start = Utc::now();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+
+        assert_eq!(
+            throttle.record("primary", "rpc_unavailable", at(0)),
+            Some(0)
+        );
+        for secs in 1..30 {
+            assert_eq!(
+                throttle.record("primary", "rpc_unavailable", at(secs)),
+                None
+            );
+        }
+        // Another kind or wallet is logged on its own.
+        assert_eq!(throttle.record("primary", "storage", at(30)), Some(0));
+        assert_eq!(throttle.record("hot-2", "rpc_unavailable", at(30)), Some(0));
+        assert_eq!(
+            throttle.record("primary", "rpc_unavailable", at(60)),
+            Some(29)
+        );
+        assert_eq!(throttle.record("primary", "rpc_unavailable", at(61)), None);
+
+        assert_eq!(throttle.resolve("primary"), Some(33));
+        assert_eq!(throttle.resolve("primary"), None);
+        assert_eq!(
+            throttle.record("primary", "rpc_unavailable", at(62)),
+            Some(0)
+        );
+        assert_eq!(throttle.resolve("hot-2"), Some(1));
+    }
+}
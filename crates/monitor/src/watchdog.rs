@@ -0,0 +1,315 @@
+//! Watchdog over the monitor's own progress, so a stuck monitor is noticed
+//! before users report missing tokens.
+//!
+//! Every [`WATCHDOG_CHECK`] it reads the [`MonitorHeartbeat`], publishes
+//! `monitor_seconds_since_last_success{wallet}`, and checks each wallet
+//! against `MONITOR_STALL_ALERT_SECS` and `MONITOR_LAG_ALERT_BLOCKS`. An
+//! alert is raised when a condition starts and again, as resolved, when it
+//! clears. Alerts are logged and, with `MONITOR_ALERT_WEBHOOK_URL`, posted as
+//! `monitor.alert.v1` events signed like webhook deliveries.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use anon_ticket_domain::config::{BootstrapConfig, ConfigError};
+use anon_ticket_domain::events::MONITOR_ALERT_V1;
+use chrono::{DateTime, Utc};
+use metrics::{counter, gauge};
+use reqwest::Url;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, sleep, MissedTickBehavior};
+use tracing::{info, warn};
+use ulid::Ulid;
+
+use crate::heartbeat::MonitorHeartbeat;
+use crate::webhook::{send, signed_post};
+use crate::worker::MonitorError;
+
+/// How often the watchdog reads the heartbeat.
+pub const WATCHDOG_CHECK: Duration = Duration::from_secs(15);
+const ALERT_TIMEOUT: Duration = Duration::from_secs(10);
+const ALERT_ATTEMPTS: u32 = 3;
+const ALERT_BACKOFF: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertKind {
+    /// No successful poll for longer than `MONITOR_STALL_ALERT_SECS`.
+    Stalled,
+    /// More than `MONITOR_LAG_ALERT_BLOCKS` blocks behind the wallet.
+    Lagging,
+}
+
+impl AlertKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlertKind::Stalled => "stalled",
+            AlertKind::Lagging => "lagging",
+        }
+    }
+}
+
+/// A wallet entering or leaving an alerting condition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorAlert {
+    pub kind: AlertKind,
+    pub wallet: String,
+    /// `false` once the condition has cleared.
+    pub firing: bool,
+    pub since_success: Duration,
+    pub lag_blocks: Option<u64>,
+    pub consecutive_rpc_failures: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct AlertPayload<'a> {
+    schema: &'static str,
+    id: &'a str,
+    event: &'static str,
+    kind: &'static str,
+    wallet: &'a str,
+    firing: bool,
+    seconds_since_success: u64,
+    lag_blocks: Option<u64>,
+    consecutive_rpc_failures: u32,
+    at: DateTime<Utc>,
+}
+
+/// Compares consecutive heartbeat snapshots against the alert thresholds;
+/// either threshold may be off, leaving that condition to the metrics.
+#[derive(Debug)]
+pub struct MonitorWatchdog {
+    stall_after: Option<Duration>,
+    lag_alert: Option<u64>,
+    firing: BTreeSet<(String, AlertKind)>,
+}
+
+impl MonitorWatchdog {
+    pub fn new(stall_after: Option<Duration>, lag_alert: Option<u64>) -> Self {
+        Self {
+            stall_after,
+            lag_alert,
+            firing: BTreeSet::new(),
+        }
+    }
+
+    pub fn from_config(config: &BootstrapConfig) -> Self {
+        Self::new(
+            config.stall_alert_secs().map(Duration::from_secs),
+            config.lag_alert_blocks(),
+        )
+    }
+
+    /// Publishes the time since each wallet's last successful poll and
+    /// returns the alerts whose state changed, each also logged; firing ones
+    /// are counted in `monitor_alerts_total{kind}`.
+    pub fn observe(
+        &mut self,
+        heartbeat: &MonitorHeartbeat,
+        now: DateTime<Utc>,
+    ) -> Vec<MonitorAlert> {
+        let mut alerts = Vec::new();
+        for (wallet, pulse) in heartbeat.snapshot() {
+            // A wallet that never succeeded counts from the monitor's start.
+            let last_success = pulse.last_success_at.unwrap_or(heartbeat.started_at());
+            let since_success = (now - last_success).to_std().unwrap_or_default();
+            gauge!("monitor_seconds_since_last_success", "wallet" => wallet.clone())
+                .set(since_success.as_secs_f64());
+            let conditions = [
+                (
+                    AlertKind::Stalled,
+                    self.stall_after.is_some_and(|after| since_success > after),
+                ),
+                (
+                    AlertKind::Lagging,
+                    self.lag_alert
+                        .zip(pulse.lag_blocks)
+                        .is_some_and(|(limit, lag)| lag > limit),
+                ),
+            ];
+            for (kind, active) in conditions {
+                let key = (wallet.clone(), kind);
+                let changed = if active {
+                    self.firing.insert(key)
+                } else {
+                    self.firing.remove(&key)
+                };
+                if changed {
+                    alerts.push(MonitorAlert {
+                        kind,
+                        wallet: wallet.clone(),
+                        firing: active,
+                        since_success,
+                        lag_blocks: pulse.lag_blocks,
+                        consecutive_rpc_failures: pulse.consecutive_rpc_failures,
+                    });
+                }
+            }
+        }
+        for alert in &alerts {
+            let (kind, wallet) = (alert.kind.as_str(), alert.wallet.as_str());
+            let since_success_secs = alert.since_success.as_secs();
+            if alert.firing {
+                counter!("monitor_alerts_total", "kind" => kind).increment(1);
+                warn!(
+                    kind,
+                    wallet,
+                    since_success_secs,
+                    lag_blocks = alert.lag_blocks,
+                    consecutive_rpc_failures = alert.consecutive_rpc_failures,
+                    "monitor alert"
+                );
+            } else {
+                info!(
+                    kind,
+                    wallet,
+                    lag_blocks = alert.lag_blocks,
+                    "monitor alert resolved"
+                );
+            }
+        }
+        alerts
+    }
+
+    /// Checks `heartbeat` every [`WATCHDOG_CHECK`] until aborted, posting
+    /// alerts to `webhook` when one is set.
+    pub async fn run(mut self, heartbeat: MonitorHeartbeat, webhook: Option<AlertWebhook>) {
+        let mut check = interval(WATCHDOG_CHECK);
+        check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            check.tick().await;
+            let now = Utc::now();
+            for alert in self.observe(&heartbeat, now) {
+                if let Some(webhook) = &webhook {
+                    // Retries must not hold up the next check.
+                    tokio::spawn(webhook.clone().deliver(alert, now));
+                }
+            }
+        }
+    }
+}
+
+/// `MONITOR_ALERT_WEBHOOK_URL`, signed with `MONITOR_WEBHOOK_SECRET`.
+#[derive(Clone)]
+pub struct AlertWebhook {
+    client: reqwest::Client,
+    url: Url,
+    secret: Vec<u8>,
+}
+
+impl AlertWebhook {
+    pub fn new(url: &str, secret: &str) -> Result<Self, MonitorError> {
+        let url = Url::parse(url).map_err(|_| ConfigError::InvalidValue {
+            key: "MONITOR_ALERT_WEBHOOK_URL",
+            reason: "must be a valid URL",
+        })?;
+        let client = reqwest::Client::builder()
+            .timeout(ALERT_TIMEOUT)
+            .build()
+            .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+        Ok(Self {
+            client,
+            url,
+            secret: secret.as_bytes().to_vec(),
+        })
+    }
+
+    async fn deliver(self, alert: MonitorAlert, at: DateTime<Utc>) {
+        let id = Ulid::new().to_string();
+        let payload = alert_payload(&alert, &id, at);
+        let mut backoff = ALERT_BACKOFF;
+        for attempt in 1..=ALERT_ATTEMPTS {
+            let request = signed_post(&self.client, &self.secret, &self.url, &id, &payload);
+            match send(request).await {
+                Ok(()) => {
+                    counter!("monitor_alert_deliveries_total", "result" => "delivered")
+                        .increment(1);
+                    return;
+                }
+                Err(err) if attempt == ALERT_ATTEMPTS => {
+                    counter!("monitor_alert_deliveries_total", "result" => "failed").increment(1);
+                    warn!(
+                        err,
+                        kind = alert.kind.as_str(),
+                        "monitor alert not delivered"
+                    );
+                }
+                Err(_) => {
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+}
+
+fn alert_payload(alert: &MonitorAlert, id: &str, at: DateTime<Utc>) -> String {
+    serde_json::to_string(&AlertPayload {
+        schema: MONITOR_ALERT_V1,
+        id,
+        event: "monitor.alert",
+        kind: alert.kind.as_str(),
+        wallet: &alert.wallet,
+        firing: alert.firing,
+        seconds_since_success: alert.since_success.as_secs(),
+        lag_blocks: alert.lag_blocks,
+        consecutive_rpc_failures: alert.consecutive_rpc_failures,
+        at,
+    })
+    .expect("alert payload serializes")
+}
+
+/// Starts a [`MonitorWatchdog`] over `heartbeat` with `config`'s thresholds
+/// and alert webhook.
+pub fn spawn_watchdog(
+    config: &BootstrapConfig,
+    heartbeat: MonitorHeartbeat,
+) -> Result<JoinHandle<()>, MonitorError> {
+    let webhook = match (config.alert_webhook_url(), config.webhook_secret()) {
+        (Some(url), Some(secret)) => Some(AlertWebhook::new(url, secret)?),
+        _ => None,
+    };
+    let watchdog = MonitorWatchdog::from_config(config);
+    Ok(tokio::spawn(watchdog.run(heartbeat, webhook)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::error::ErrorCode;
+    use anon_ticket_testkit::assert_event_matches_schema;
+
+    #[test]
+    fn alerts_when_a_wallet_stalls_or_lags_and_when_it_recovers() {
+        let heartbeat = MonitorHeartbeat::new();
+        let start = heartbeat.started_at();
+        let mut watchdog = MonitorWatchdog::new(Some(Duration::from_secs(60)), Some(20));
+        heartbeat.poll_finished("primary", start, None);
+        heartbeat.record_lag("primary", 10);
+        assert!(watchdog.observe(&heartbeat, start).is_empty());
+
+        let later = start + chrono::Duration::minutes(2);
+        heartbeat.poll_finished("primary", later, Some(ErrorCode::RpcUnavailable));
+        heartbeat.record_lag("primary", 25);
+        let alerts = watchdog.observe(&heartbeat, later);
+        let fired: Vec<_> = alerts
+            .iter()
+            .map(|alert| (alert.kind, alert.firing))
+            .collect();
+        assert_eq!(
+            fired,
+            [(AlertKind::Stalled, true), (AlertKind::Lagging, true)]
+        );
+        assert_eq!(alerts[0].since_success, Duration::from_secs(120));
+        assert_eq!(alerts[0].consecutive_rpc_failures, 1);
+        assert_event_matches_schema(&alert_payload(&alerts[0], &Ulid::new().to_string(), later));
+        // Still firing: nothing new.
+        assert!(watchdog.observe(&heartbeat, later).is_empty());
+
+        heartbeat.poll_finished("primary", later, None);
+        let alerts = watchdog.observe(&heartbeat, later);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, AlertKind::Stalled);
+        assert!(!alerts[0].firing);
+    }
+}
@@ -0,0 +1,719 @@
+//! Webhook notifications for persisted payments and revoked tokens.
+//!
+//! [`process_entry`](crate::pipeline::process_entry) hands each persisted
+//! payment to a [`WebhookSender`] without waiting on the network, as does
+//! the API for token revocations when it embeds the monitor. Bodies follow
+//! the schemas in [`anon_ticket_domain::events`], named by their `schema`
+//! field. The
+//! [`WebhookDispatcher`] records it as a [`WebhookEvent`] with a ULID, POSTs
+//! it to every configured URL, retrying with exponential backoff, and
+//! records the outcome per URL; a dead letter is added once the attempts run
+//! out. It also polls storage for deliveries an operator asked to send
+//! again, and reports the outbox's backlog, lag and failures (see
+//! [`crate::outbox`]). Deliveries are signed like internal API requests, with
+//! [`sign_request`] over `POST`, the URL's path and query, and the body,
+//! which carries the event id. With the `otlp` feature they also carry the
+//! trace context of the monitor tick that ingested the payment.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anon_ticket_domain::config::{BootstrapConfig, ConfigError};
+use anon_ticket_domain::events::{PAYMENT_CONFIRMED_V1, TOKEN_REVOKED_V1};
+use anon_ticket_domain::model::{
+    DeliveryStatus, NewPayment, ServiceTokenRecord, WebhookDeadLetter, WebhookDelivery,
+    WebhookEvent,
+};
+use anon_ticket_domain::services::signing::sign_request;
+use anon_ticket_domain::services::telemetry::trace_headers;
+use anon_ticket_domain::storage::WebhookStore;
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use reqwest::{header::CONTENT_TYPE, Url};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time::{interval, sleep, MissedTickBehavior};
+use tracing::warn;
+use ulid::Ulid;
+
+use crate::outbox::{endpoint_label, OutboxMonitor};
+use crate::worker::MonitorError;
+
+/// Unix seconds covered by the signature.
+pub const TIMESTAMP_HEADER: &str = "x-anon-timestamp";
+/// Hex HMAC-SHA3-256 of the signing payload under `MONITOR_WEBHOOK_SECRET`.
+pub const SIGNATURE_HEADER: &str = "x-anon-signature";
+/// ULID of the event; repeats across retries, redeliveries and reorg
+/// re-ingestion.
+pub const EVENT_ID_HEADER: &str = "x-anon-event-id";
+
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the dispatcher looks for requested redeliveries.
+const REDELIVERY_POLL: Duration = Duration::from_secs(5);
+const REDELIVERY_BATCH: u64 = 100;
+/// How often the dispatcher reports on the outbox.
+const OUTBOX_CHECK: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize)]
+struct PaymentPayload<'a> {
+    schema: &'static str,
+    id: &'a str,
+    event: &'static str,
+    pid: String,
+    txid: &'a str,
+    amount: i64,
+    block_height: i64,
+    detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct TokenRevokedPayload<'a> {
+    schema: &'static str,
+    id: &'a str,
+    event: &'static str,
+    pid: String,
+    reason: Option<&'a str>,
+    abuse_score: i16,
+    revoked_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct QueuedEvent {
+    event: WebhookEvent,
+    /// `traceparent` and friends, captured where the payment was persisted.
+    trace: Vec<(String, String)>,
+}
+
+/// Queues events for the [`WebhookDispatcher`]; cheap to clone.
+#[derive(Clone)]
+pub struct WebhookSender {
+    queue: mpsc::UnboundedSender<QueuedEvent>,
+}
+
+impl WebhookSender {
+    pub fn payment_persisted(&self, payment: &NewPayment) {
+        let id = Ulid::new().to_string();
+        let payload = serde_json::to_string(&PaymentPayload {
+            schema: PAYMENT_CONFIRMED_V1,
+            id: &id,
+            event: "payment.confirmed",
+            pid: payment.pid.to_hex(),
+            txid: &payment.txid,
+            amount: payment.amount,
+            block_height: payment.block_height,
+            detected_at: payment.detected_at,
+        })
+        .expect("webhook payload serializes");
+        self.queue(WebhookEvent {
+            id,
+            txid: payment.txid.clone(),
+            pid: payment.pid.clone(),
+            payload,
+            created_at: Utc::now(),
+        });
+    }
+
+    /// Queues `token.revoked` for a token that was just revoked. The body
+    /// names the PID only, since the token is a bearer credential.
+    pub fn token_revoked(&self, token: &ServiceTokenRecord) {
+        let Some(revoked_at) = token.revoked_at else {
+            return;
+        };
+        let id = Ulid::new().to_string();
+        let payload = serde_json::to_string(&TokenRevokedPayload {
+            schema: TOKEN_REVOKED_V1,
+            id: &id,
+            event: "token.revoked",
+            pid: token.pid.to_hex(),
+            reason: token.revoke_reason.as_deref(),
+            abuse_score: token.abuse_score,
+            revoked_at,
+        })
+        .expect("webhook payload serializes");
+        self.queue(WebhookEvent {
+            id,
+            // Unique like a txid, so the event is recorded once per token.
+            txid: format!("token.revoked:{}", token.token.to_hex()),
+            pid: token.pid.clone(),
+            payload,
+            created_at: Utc::now(),
+        });
+    }
+
+    fn queue(&self, event: WebhookEvent) {
+        let txid = event.txid.clone();
+        let queued = QueuedEvent {
+            event,
+            trace: trace_headers(),
+        };
+        if self.queue.send(queued).is_err() {
+            counter!("monitor_webhook_deliveries_total", "result" => "dropped").increment(1);
+            warn!(txid, "webhook dispatcher stopped; event dropped");
+        }
+    }
+}
+
+struct Settings {
+    client: reqwest::Client,
+    secret: Vec<u8>,
+    max_attempts: u32,
+    backoff: Duration,
+}
+
+/// Delivers queued events until every [`WebhookSender`] is dropped.
+pub struct WebhookDispatcher<S> {
+    urls: Arc<[Url]>,
+    settings: Settings,
+    storage: S,
+    queue: mpsc::UnboundedReceiver<QueuedEvent>,
+    outbox: OutboxMonitor,
+}
+
+/// Builds the sender/dispatcher pair for `config`, or `None` when no
+/// webhook URLs are configured.
+pub fn webhook_dispatcher<S>(
+    config: &BootstrapConfig,
+    storage: S,
+) -> Result<Option<(WebhookSender, WebhookDispatcher<S>)>, MonitorError> {
+    let (Some(secret), false) = (config.webhook_secret(), config.webhook_urls().is_empty()) else {
+        return Ok(None);
+    };
+    let urls = config
+        .webhook_urls()
+        .iter()
+        .map(|url| Url::parse(url))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ConfigError::InvalidValue {
+            key: "MONITOR_WEBHOOK_URLS",
+            reason: "entries must be valid URLs",
+        })?;
+    let client = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .map_err(|err| MonitorError::Rpc(err.to_string()))?;
+    let (queue, receiver) = mpsc::unbounded_channel();
+    Ok(Some((
+        WebhookSender { queue },
+        WebhookDispatcher {
+            urls: urls.into(),
+            settings: Settings {
+                client,
+                secret: secret.as_bytes().to_vec(),
+                max_attempts: config.webhook_max_attempts(),
+                backoff: DEFAULT_BACKOFF,
+            },
+            storage,
+            queue: receiver,
+            outbox: OutboxMonitor::new(Duration::from_secs(config.webhook_lag_alert_secs())),
+        },
+    )))
+}
+
+impl<S> WebhookDispatcher<S>
+where
+    S: WebhookStore + Clone + 'static,
+{
+    /// Delay before the first retry; doubles per attempt up to a minute.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.settings.backoff = backoff;
+        self
+    }
+
+    /// Each delivery runs in its own task so a slow receiver does not hold
+    /// up the others. Returns once the senders are gone and in-flight
+    /// deliveries have finished.
+    pub async fn run(self) {
+        let Self {
+            urls,
+            settings,
+            storage,
+            mut queue,
+            mut outbox,
+        } = self;
+        let settings = Arc::new(settings);
+        let mut deliveries = JoinSet::new();
+        let mut redelivery_poll = interval(REDELIVERY_POLL);
+        redelivery_poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut outbox_check = interval(OUTBOX_CHECK);
+        outbox_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                queued = queue.recv() => {
+                    let Some(queued) = queued else { break };
+                    let Some(event) = record_event(&storage, queued.event).await else {
+                        counter!("monitor_webhook_deliveries_total", "result" => "duplicate")
+                            .increment(urls.len() as u64);
+                        continue;
+                    };
+                    let trace: Arc<[(String, String)]> = queued.trace.into();
+                    for url in urls.iter() {
+                        deliveries.spawn(deliver(
+                            settings.clone(),
+                            storage.clone(),
+                            url.clone(),
+                            event.clone(),
+                            trace.clone(),
+                            0,
+                        ));
+                    }
+                }
+                _ = redelivery_poll.tick() => {
+                    let claimed = match storage.claim_redeliveries(REDELIVERY_BATCH).await {
+                        Ok(claimed) => claimed,
+                        Err(err) => {
+                            warn!(?err, "failed to claim webhook redeliveries");
+                            continue;
+                        }
+                    };
+                    for delivery in claimed {
+                        deliveries.spawn(redeliver(settings.clone(), storage.clone(), delivery));
+                    }
+                }
+                _ = outbox_check.tick() => match storage.outbox_stats().await {
+                    Ok(stats) => {
+                        outbox.observe(&stats, Utc::now());
+                    }
+                    Err(err) => warn!(?err, "failed to read webhook outbox stats"),
+                },
+                Some(_) = deliveries.join_next(), if !deliveries.is_empty() => {}
+            }
+        }
+        while deliveries.join_next().await.is_some() {}
+    }
+}
+
+/// Stores `event`, or returns `None` when its txid already has one, as
+/// happens when a reorg re-ingests the payment; that event was queued
+/// already and is only sent again on request. Delivery goes ahead if the
+/// event cannot be stored.
+async fn record_event<S: WebhookStore>(
+    storage: &S,
+    event: WebhookEvent,
+) -> Option<Arc<WebhookEvent>> {
+    match storage.record_webhook_event(event.clone()).await {
+        Ok(stored) if stored.id != event.id => None,
+        Ok(stored) => Some(Arc::new(stored)),
+        Err(err) => {
+            warn!(event_id = event.id, ?err, "failed to record webhook event");
+            Some(Arc::new(event))
+        }
+    }
+}
+
+/// Sends an event again after an operator requested it.
+async fn redeliver<S: WebhookStore>(settings: Arc<Settings>, storage: S, claimed: WebhookDelivery) {
+    let event = match storage.find_webhook_event(&claimed.event_id).await {
+        Ok(Some(event)) => event,
+        Ok(None) => return,
+        Err(err) => {
+            warn!(
+                event_id = claimed.event_id,
+                ?err,
+                "failed to load webhook event"
+            );
+            return;
+        }
+    };
+    let Ok(url) = Url::parse(&claimed.url) else {
+        record_delivery(
+            &storage,
+            WebhookDelivery {
+                status: DeliveryStatus::Failed,
+                last_error: Some("invalid URL".to_string()),
+                ..claimed
+            },
+        )
+        .await;
+        return;
+    };
+    counter!("monitor_webhook_deliveries_total", "result" => "redelivery").increment(1);
+    deliver(
+        settings,
+        storage,
+        url,
+        Arc::new(event),
+        Arc::new([]),
+        claimed.attempts,
+    )
+    .await;
+}
+
+/// `prior_attempts` carries the count over from earlier deliveries of the
+/// same event to `url`.
+async fn deliver<S: WebhookStore>(
+    settings: Arc<Settings>,
+    storage: S,
+    url: Url,
+    event: Arc<WebhookEvent>,
+    trace: Arc<[(String, String)]>,
+    prior_attempts: u32,
+) {
+    let host = url.host_str().unwrap_or_default().to_string();
+    let endpoint = endpoint_label(url.as_str());
+    let mut backoff = settings.backoff;
+    let mut last_error = String::new();
+    for attempt in 1..=settings.max_attempts {
+        let result = post(&settings, &url, &event, &trace).await;
+        let now = Utc::now();
+        let mut delivery = WebhookDelivery {
+            event_id: event.id.clone(),
+            url: url.to_string(),
+            status: DeliveryStatus::Pending,
+            attempts: prior_attempts.saturating_add(attempt),
+            last_error: None,
+            last_attempt_at: Some(now),
+            delivered_at: None,
+        };
+        match result {
+            Ok(()) => {
+                counter!("monitor_webhook_deliveries_total", "result" => "delivered").increment(1);
+                delivery.status = DeliveryStatus::Delivered;
+                delivery.delivered_at = Some(now);
+                record_delivery(&storage, delivery).await;
+                return;
+            }
+            Err(err) => {
+                counter!("monitor_webhook_deliveries_total", "result" => "failed").increment(1);
+                counter!("monitor_webhook_delivery_failures_total", "endpoint" => endpoint.clone())
+                    .increment(1);
+                warn!(
+                    host,
+                    attempt,
+                    event_id = event.id,
+                    error = err,
+                    "webhook delivery failed"
+                );
+                if attempt == settings.max_attempts {
+                    delivery.status = DeliveryStatus::Failed;
+                }
+                delivery.last_error = Some(err.clone());
+                record_delivery(&storage, delivery).await;
+                last_error = err;
+            }
+        }
+        if attempt < settings.max_attempts {
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    counter!("monitor_webhook_deliveries_total", "result" => "dead_letter").increment(1);
+    let letter = WebhookDeadLetter {
+        event_id: event.id.clone(),
+        url: url.to_string(),
+        pid: event.pid.clone(),
+        payload: event.payload.clone(),
+        attempts: settings.max_attempts,
+        last_error,
+        failed_at: Utc::now(),
+    };
+    if let Err(err) = storage.record_dead_letter(letter).await {
+        warn!(host, ?err, "failed to record webhook dead letter");
+    }
+}
+
+async fn record_delivery<S: WebhookStore>(storage: &S, delivery: WebhookDelivery) {
+    if let Err(err) = storage.record_delivery(delivery).await {
+        warn!(?err, "failed to record webhook delivery");
+    }
+}
+
+async fn post(
+    settings: &Settings,
+    url: &Url,
+    event: &WebhookEvent,
+    trace: &[(String, String)],
+) -> Result<(), String> {
+    let mut request = signed_post(
+        &settings.client,
+        &settings.secret,
+        url,
+        &event.id,
+        &event.payload,
+    );
+    for (name, value) in trace {
+        request = request.header(name, value);
+    }
+    send(request).await
+}
+
+/// A `POST` of the JSON `payload` to `url`, signed as deliveries are.
+pub(crate) fn signed_post(
+    client: &reqwest::Client,
+    secret: &[u8],
+    url: &Url,
+    event_id: &str,
+    payload: &str,
+) -> reqwest::RequestBuilder {
+    let timestamp = Utc::now().timestamp();
+    let path_and_query = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    };
+    let signature = sign_request(
+        secret,
+        timestamp,
+        "POST",
+        &path_and_query,
+        payload.as_bytes(),
+    );
+    client
+        .post(url.clone())
+        .header(CONTENT_TYPE, "application/json")
+        .header(TIMESTAMP_HEADER, timestamp)
+        .header(SIGNATURE_HEADER, signature)
+        .header(EVENT_ID_HEADER, event_id)
+        .body(payload.to_string())
+}
+
+pub(crate) async fn send(request: reqwest::RequestBuilder) -> Result<(), String> {
+    let response = request
+        .send()
+        .await
+        // The URL may carry credentials.
+        .map_err(|err| err.without_url().to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("receiver answered {}", response.status()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::{PaymentId, ServiceToken};
+    use anon_ticket_domain::services::signing::verify_request_signature;
+    use anon_ticket_storage::SeaOrmStorage;
+    use anon_ticket_testkit::assert_event_matches_schema;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn payment() -> NewPayment {
+        NewPayment {
+            pid: PaymentId::parse("aaaaaaaaaaaaaaaa").unwrap(),
+            txid: "tx-webhook".into(),
+            amount: 42,
+            block_height: 7,
+            detected_at: Utc::now(),
+            address_id: None,
+        }
+    }
+
+    fn config(url: String) -> BootstrapConfig {
+        BootstrapConfig::builder("sqlite::memory:", "http://127.0.0.1:18082", 0)
+            .webhook_url(url)
+            .webhook_secret("hook-secret")
+            .webhook_max_attempts(2)
+            .build()
+            .unwrap()
+    }
+
+    /// Accepts one request and answers 204; returns the raw request text.
+    async fn accept_one(listener: TcpListener) -> String {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut chunk = [0u8; 1024];
+        loop {
+            let read = socket.read(&mut chunk).await.unwrap();
+            request.extend_from_slice(&chunk[..read]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: "))
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    break;
+                }
+            }
+        }
+        socket
+            .write_all(b"HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    }
+
+    fn header<'a>(request: &'a str, name: &str) -> &'a str {
+        request
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{name}: ")))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn deliveries_are_signed_over_path_and_query() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks?shop=1", listener.local_addr().unwrap());
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let (sender, dispatcher) = webhook_dispatcher(&config(url), storage.clone())
+            .unwrap()
+            .unwrap();
+        let server = tokio::spawn(accept_one(listener));
+        sender.payment_persisted(&payment());
+        drop(sender);
+        dispatcher.run().await;
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hooks?shop=1 "));
+        let event_id = header(&request, EVENT_ID_HEADER);
+        assert!(event_id.parse::<Ulid>().is_ok());
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["id"], event_id);
+        assert_eq!(json["event"], "payment.confirmed");
+        assert_eq!(json["pid"], "aaaaaaaaaaaaaaaa");
+        assert_eq!(json["amount"], 42);
+        assert_event_matches_schema(body);
+        let timestamp = header(&request, TIMESTAMP_HEADER).parse().unwrap();
+        let signature = hex::decode(header(&request, SIGNATURE_HEADER)).unwrap();
+        assert!(verify_request_signature(
+            b"hook-secret",
+            timestamp,
+            "POST",
+            "/hooks?shop=1",
+            body.as_bytes(),
+            &signature,
+        ));
+        assert!(storage.recent_dead_letters(10).await.unwrap().is_empty());
+        let event = storage.find_webhook_event(event_id).await.unwrap().unwrap();
+        assert_eq!(event.txid, "tx-webhook");
+        assert_eq!(event.payload, body);
+        let deliveries = storage.list_deliveries(event_id).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, DeliveryStatus::Delivered);
+        assert_eq!(deliveries[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn revocations_are_sent_without_the_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let (sender, dispatcher) = webhook_dispatcher(&config(url), storage.clone())
+            .unwrap()
+            .unwrap();
+        let server = tokio::spawn(accept_one(listener));
+        let token = ServiceToken::from_bytes([7; 32]);
+        let mut record = ServiceTokenRecord {
+            token: token.clone(),
+            pid: payment().pid,
+            amount: 42,
+            issued_at: Utc::now(),
+            revoked_at: None,
+            revoke_reason: Some("abuse".into()),
+            abuse_score: 80,
+            expires_at: None,
+            tier: None,
+            suspended_at: None,
+            suspend_reason: None,
+            suspended_until: None,
+        };
+        // Only revoked tokens are announced.
+        sender.token_revoked(&record);
+        record.revoked_at = Some(Utc::now());
+        sender.token_revoked(&record);
+        drop(sender);
+        dispatcher.run().await;
+
+        let request = server.await.unwrap();
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        assert_event_matches_schema(body);
+        assert!(!body.contains(&token.to_hex()));
+        let json: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(json["event"], "token.revoked");
+        assert_eq!(json["pid"], "aaaaaaaaaaaaaaaa");
+        assert_eq!(json["reason"], "abuse");
+        assert_eq!(json["abuse_score"], 80);
+    }
+
+    #[tokio::test]
+    async fn requested_redeliveries_resend_the_original_event() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let (sender, dispatcher) = webhook_dispatcher(&config(url.clone()), storage.clone())
+            .unwrap()
+            .unwrap();
+        let server = tokio::spawn(accept_one(listener));
+        sender.payment_persisted(&payment());
+        // A reorg re-ingests the payment before the first delivery is done.
+        sender.payment_persisted(&payment());
+        drop(sender);
+        dispatcher.run().await;
+        let first = server.await.unwrap();
+        let event_id = header(&first, EVENT_ID_HEADER).to_string();
+
+        assert_eq!(storage.request_redelivery(&event_id).await.unwrap(), 1);
+        let listener =
+            TcpListener::bind(url.trim_start_matches("http://").trim_end_matches("/hooks"))
+                .await
+                .unwrap();
+        let server = tokio::spawn(accept_one(listener));
+        let (sender, dispatcher) = webhook_dispatcher(&config(url.clone()), storage.clone())
+            .unwrap()
+            .unwrap();
+        let dispatcher = tokio::spawn(dispatcher.run());
+        let second = server.await.unwrap();
+        drop(sender);
+        dispatcher.await.unwrap();
+
+        assert_eq!(header(&second, EVENT_ID_HEADER), event_id);
+        assert_eq!(
+            first.split_once("\r\n\r\n").unwrap().1,
+            second.split_once("\r\n\r\n").unwrap().1
+        );
+        let delivery = storage
+            .find_delivery(&event_id, &url)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivery.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn exhausted_retries_leave_a_dead_letter() {
+        // Bind then drop so the port refuses connections.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+        drop(listener);
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let (sender, dispatcher) = webhook_dispatcher(&config(url.clone()), storage.clone())
+            .unwrap()
+            .unwrap();
+        sender.payment_persisted(&payment());
+        drop(sender);
+        dispatcher
+            .with_backoff(Duration::from_millis(1))
+            .run()
+            .await;
+
+        let letters = storage.recent_dead_letters(10).await.unwrap();
+        assert_eq!(letters.len(), 1);
+        let event = storage
+            .find_webhook_event(&letters[0].event_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.txid, "tx-webhook");
+        let delivery = storage
+            .find_delivery(&event.id, &url)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(
+            delivery.last_error,
+            letters.first().map(|l| l.last_error.clone())
+        );
+        assert_eq!(letters[0].url, url);
+        assert_eq!(letters[0].attempts, 2);
+        assert!(letters[0].payload.contains("\"amount\":42"));
+    }
+}
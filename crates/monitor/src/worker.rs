@@ -1,27 +1,47 @@
+use std::sync::Arc;
 use std::time::Duration;
 
+use chrono::Utc;
+use futures_util::future::select_all;
 use metrics::{counter, gauge, histogram};
 use thiserror::Error;
 use tokio::time::sleep;
-use tracing::warn;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, info_span, warn, Instrument};
 
 use anon_ticket_domain::{
-    config::ConfigError,
+    config::{BootstrapConfig, ConfigError, MonitorSource, PRIMARY_WALLET},
+    error::{ErrorCode, HasErrorCode},
+    model::{MonitorCheckpoint, PaymentState, PendingTransfer, SkippedTransfer},
     services::{
         cache::{PidBloom, PidCache},
         telemetry::TelemetryError,
     },
-    storage::{MonitorStateStore, PaymentStore, StorageError},
+    storage::{
+        MonitorStateStore, PaymentStore, PendingPaymentStore, QuoteStore, SkippedTransferStore,
+        StorageError, SubaddressStore,
+    },
     PaymentId,
 };
 use monero_rpc::RpcClientBuilder;
 
 use crate::{
-    pipeline::process_entry,
+    events::{PaymentEvent, PaymentEventKind, PaymentEvents},
+    heartbeat::MonitorHeartbeat,
+    pipeline::{process_entry, IngestRules},
     rpc::{TransferSource, TransfersResponse},
+    throttle::WarnThrottle,
+    webhook::WebhookSender,
 };
 
+/// Stored checkpoints compared against the source on each tick.
+const REORG_SCAN_CHECKPOINTS: u64 = 64;
+/// Pending transfers not seen for this long, such as ones dropped from the
+/// mempool, are forgotten.
+pub const PENDING_RETENTION: Duration = Duration::from_secs(30 * 60);
+
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum MonitorError {
     #[error("config error: {0}")]
     Config(#[from] ConfigError),
@@ -33,81 +53,261 @@ pub enum MonitorError {
     Telemetry(#[from] TelemetryError),
 }
 
+impl HasErrorCode for MonitorError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            MonitorError::Config(err) => err.code(),
+            MonitorError::Storage(err) => err.code(),
+            MonitorError::Rpc(_) => ErrorCode::RpcUnavailable,
+            MonitorError::Telemetry(err) => err.code(),
+        }
+    }
+}
+
+/// Where a wallet's scan stands: the next height to fetch, persisted in
+/// `monitor_state` under the wallet's name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletCursor {
+    pub wallet: String,
+    pub height: u64,
+}
+
+impl WalletCursor {
+    pub fn new(wallet: impl Into<String>, height: u64) -> Self {
+        Self {
+            wallet: wallet.into(),
+            height,
+        }
+    }
+
+    /// Cursor of the wallet at `MONERO_RPC_URL`.
+    pub fn primary(height: u64) -> Self {
+        Self::new(PRIMARY_WALLET, height)
+    }
+}
+
+/// Polls every `(name, source)` wallet in turn on each tick. Their transfers
+/// land in the same storage, while each advances its own cursor.
+///
+/// Returns once `shutdown` is cancelled. A tick already in progress finishes
+/// first, so every batch it fetched is stored and its cursor persisted.
 pub async fn run_monitor<S, D>(
-    config: anon_ticket_domain::config::BootstrapConfig,
+    config: BootstrapConfig,
     storage: D,
-    source: S,
+    wallets: Vec<(String, S)>,
     hooks: Option<MonitorHooks>,
+    shutdown: CancellationToken,
 ) -> Result<(), MonitorError>
 where
     S: TransferSource,
-    D: MonitorStateStore + PaymentStore,
+    D: MonitorStateStore + PaymentStore + QuoteStore,
 {
-    let mut height = storage
-        .last_processed_height()
-        .await?
-        .unwrap_or(config.monitor_start_height());
-    let min_payment_amount = config.monitor_min_payment_amount();
+    let mut cursors = Vec::with_capacity(wallets.len());
+    for (name, _) in &wallets {
+        let height = storage
+            .last_processed_height(name)
+            .await?
+            .unwrap_or(config.monitor_start_height());
+        cursors.push(WalletCursor::new(name.as_str(), height));
+    }
+    let rules = IngestRules::from_config(&config);
     let min_confirmations = config.monitor_min_confirmations();
     let poll_interval = Duration::from_secs(config.monitor_poll_interval_secs());
+    // Kept even without a hook, since the failure and lag gauges come from it.
+    let heartbeat = hooks
+        .as_ref()
+        .and_then(MonitorHooks::heartbeat)
+        .cloned()
+        .unwrap_or_default();
+    let mut warnings = WarnThrottle::default();
 
     loop {
-        let wallet_height = match source.wallet_height().await {
-            Ok(height) => height,
-            Err(err) => {
-                warn!(?err, "rpc height fetch failed");
-                sleep(poll_interval).await;
-                continue;
+        for ((_, source), cursor) in wallets.iter().zip(&mut cursors) {
+            let span = info_span!("monitor.tick", wallet = cursor.wallet.as_str());
+            heartbeat.poll_started(&cursor.wallet, Utc::now());
+            let result = poll_wallet(
+                &storage,
+                source,
+                cursor,
+                rules,
+                min_confirmations,
+                &hooks,
+                &heartbeat,
+            )
+            .instrument(span)
+            .await;
+            let error = result.as_ref().err().map(HasErrorCode::code);
+            let pulse = heartbeat.poll_finished(&cursor.wallet, Utc::now(), error);
+            gauge!("monitor_consecutive_rpc_failures", "wallet" => cursor.wallet.clone())
+                .set(pulse.consecutive_rpc_failures as f64);
+            match result {
+                Ok(_) => {
+                    if let Some(failures) = warnings.resolve(&cursor.wallet) {
+                        info!(wallet = cursor.wallet, failures, "wallet poll recovered");
+                    }
+                }
+                Err(err) => {
+                    let code = err.code();
+                    counter!("monitor_errors_total", "code" => code.as_str()).increment(1);
+                    if let Some(suppressed) =
+                        warnings.record(&cursor.wallet, code.as_str(), Utc::now())
+                    {
+                        warn!(
+                            wallet = cursor.wallet,
+                            code = code.as_str(),
+                            suppressed,
+                            consecutive_rpc_failures = pulse.consecutive_rpc_failures,
+                            ?err,
+                            "wallet poll failed, retrying in next cycle"
+                        );
+                    }
+                }
             }
-        };
-
-        gauge!("monitor_wallet_height").set(wallet_height as f64);
-        gauge!("monitor_last_height").set(height as f64);
+        }
+        if wait_or_shutdown(poll_interval, &wallets, &shutdown).await {
+            return Ok(());
+        }
+    }
+}
 
-        let safe_height = wallet_height
-            .saturating_add(1)
-            .saturating_sub(min_confirmations);
+async fn poll_wallet<S, D>(
+    storage: &D,
+    source: &S,
+    cursor: &mut WalletCursor,
+    rules: IngestRules,
+    min_confirmations: u64,
+    hooks: &Option<MonitorHooks>,
+    heartbeat: &MonitorHeartbeat,
+) -> Result<PollOutcome, MonitorError>
+where
+    S: TransferSource,
+    D: MonitorStateStore + PaymentStore + QuoteStore,
+{
+    // A reorg found through another wallet rewinds this one's stored cursor.
+    if let Some(stored) = storage.last_processed_height(&cursor.wallet).await? {
+        cursor.height = stored;
+    }
+    let wallet_height = source.wallet_height().await?;
+    let outcome = poll_once(
+        storage,
+        source,
+        cursor,
+        wallet_height,
+        rules,
+        min_confirmations,
+        hooks.as_ref(),
+    )
+    .await;
+    // Includes the blocks still short of `min_confirmations`, so a healthy
+    // monitor sits at about that many.
+    let lag = wallet_height
+        .saturating_add(1)
+        .saturating_sub(cursor.height);
+    gauge!("monitor_height_lag_blocks", "wallet" => cursor.wallet.clone()).set(lag as f64);
+    heartbeat.record_lag(&cursor.wallet, lag);
+    outcome
+}
 
-        if height > safe_height {
-            // wait for more confirmations before progressing
-            sleep(poll_interval).await;
-            continue;
+/// Sleeps for `interval` or until a wallet source reports activity; returns
+/// `true` if shutdown was requested first.
+async fn wait_or_shutdown<S: TransferSource>(
+    interval: Duration,
+    wallets: &[(String, S)],
+    shutdown: &CancellationToken,
+) -> bool {
+    let activity = async {
+        if wallets.is_empty() {
+            return std::future::pending().await;
         }
-
-        match monitor_tick(
-            &storage,
-            &source,
-            &mut height,
-            min_payment_amount,
-            safe_height,
-            hooks.as_ref(),
-        )
-        .await
-        {
-            Ok(()) => {}
-            Err(err) => warn!(?err, "batch processing failed, retrying in next cycle"),
+        select_all(wallets.iter().map(|(_, source)| source.wait_for_activity())).await;
+    };
+    tokio::select! {
+        _ = sleep(interval) => false,
+        _ = activity => {
+            counter!("monitor_early_polls_total").increment(1);
+            false
+        }
+        _ = shutdown.cancelled() => {
+            info!("monitor stopping after current tick");
+            true
         }
-        sleep(poll_interval).await;
     }
 }
 
+/// Outcome of a single [`poll_once`] cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollOutcome {
+    /// The cursor is already beyond the confirmation-safe height; nothing was fetched.
+    AwaitingConfirmations { safe_height: u64 },
+    /// The safe window was ingested and the cursor now points at `next_height`.
+    Advanced { next_height: u64 },
+}
+
+/// Runs one monitor cycle against an already-fetched wallet height: rewinds
+/// `cursor` if a reorg replaced blocks it already processed, derives the
+/// confirmation-safe window, ingests it, and advances `cursor`. The run loop
+/// calls this on every tick; harnesses call it directly to step the monitor
+/// deterministically instead of sleeping.
+pub async fn poll_once<S, D>(
+    storage: &D,
+    source: &S,
+    cursor: &mut WalletCursor,
+    wallet_height: u64,
+    rules: IngestRules,
+    min_confirmations: u64,
+    hooks: Option<&MonitorHooks>,
+) -> Result<PollOutcome, MonitorError>
+where
+    S: TransferSource,
+    D: MonitorStateStore + PaymentStore + QuoteStore,
+{
+    let wallet = cursor.wallet.clone();
+    gauge!("monitor_wallet_height", "wallet" => wallet.clone()).set(wallet_height as f64);
+    gauge!("monitor_last_height", "wallet" => wallet).set(cursor.height as f64);
+
+    if let Some(fork_height) = find_fork_height(storage, source).await? {
+        roll_back(storage, cursor, fork_height).await?;
+    }
+
+    let safe_height = wallet_height
+        .saturating_add(1)
+        .saturating_sub(min_confirmations);
+    if let Some(hooks) = hooks {
+        observe_pending(source, hooks, &cursor.wallet, safe_height, wallet_height).await;
+    }
+
+    if cursor.height > safe_height {
+        // wait for more confirmations before progressing
+        return Ok(PollOutcome::AwaitingConfirmations { safe_height });
+    }
+
+    let rules = rules.with_wallet_height(wallet_height);
+    monitor_tick(storage, source, cursor, rules, safe_height, hooks).await?;
+    Ok(PollOutcome::Advanced {
+        next_height: cursor.height,
+    })
+}
+
 async fn monitor_tick<S, D>(
     storage: &D,
     source: &S,
-    current_height: &mut u64,
-    min_payment_amount: i64,
+    cursor: &mut WalletCursor,
+    rules: IngestRules,
     safe_height: u64,
     hooks: Option<&MonitorHooks>,
 ) -> Result<(), MonitorError>
 where
     S: TransferSource,
-    D: MonitorStateStore + PaymentStore,
+    D: MonitorStateStore + PaymentStore + QuoteStore,
 {
-    if *current_height > safe_height {
+    if cursor.height > safe_height {
         return Ok(());
     }
 
-    let transfers = match source.fetch_transfers(*current_height, safe_height).await {
+    // Taken before the transfers so a reorg in between shows up as a mismatch
+    // on the next tick instead of being recorded as the processed chain.
+    let block_hash = source.block_hash(safe_height).await?;
+    let transfers = match source.fetch_transfers(cursor.height, safe_height).await {
         Ok(resp) => resp,
         Err(err) => {
             counter!("monitor_rpc_calls_total", "result" => "error").increment(1);
@@ -115,39 +315,227 @@ where
         }
     };
 
-    handle_batch(
-        storage,
-        transfers,
-        current_height,
-        min_payment_amount,
-        safe_height,
-        hooks,
-    )
-    .await
+    let batch_size = transfers.incoming.len() as u32;
+    handle_batch(storage, transfers, cursor, rules, safe_height, hooks).await?;
+    storage
+        .record_checkpoint(MonitorCheckpoint {
+            height: safe_height,
+            block_hash,
+            tick_time: Utc::now(),
+            batch_size,
+        })
+        .await?;
+    Ok(())
+}
+
+/// Looks at transfers the cursor has not reached yet: mined ones still short
+/// of the confirmation depth and, with a pending store, the mempool. Mined
+/// ones are published as `detected` events while someone is subscribed, and
+/// both are recorded in the pending store. Fetches only what one of the two
+/// wants, and a failed fetch never holds up ingestion.
+async fn observe_pending<S>(
+    source: &S,
+    hooks: &MonitorHooks,
+    wallet: &str,
+    safe_height: u64,
+    wallet_height: u64,
+) where
+    S: TransferSource,
+{
+    let events = hooks.events().filter(|events| events.has_subscribers());
+    let store = hooks.pending_store();
+    if events.is_none() && store.is_none() {
+        return;
+    }
+    let mut mined = Vec::new();
+    if safe_height < wallet_height {
+        match source
+            .fetch_transfers(safe_height.saturating_add(1), wallet_height)
+            .await
+        {
+            Ok(resp) => mined = resp.incoming,
+            Err(err) => {
+                counter!("monitor_rpc_calls_total", "result" => "error").increment(1);
+                warn!(?err, "pending transfer fetch failed");
+                return;
+            }
+        }
+    }
+    let confirmations = |height: i64| {
+        wallet_height
+            .saturating_add(1)
+            .saturating_sub(height as u64)
+    };
+    if let Some(events) = events {
+        for entry in &mined {
+            let (Some(pid), Some(height)) = (entry.payment_id.as_deref(), entry.height) else {
+                continue;
+            };
+            let Ok(pid) = PaymentId::parse(pid) else {
+                continue;
+            };
+            events.publish(PaymentEvent {
+                pid,
+                kind: PaymentEventKind::Detected {
+                    confirmations: confirmations(height),
+                },
+                amount: entry.amount,
+                block_height: height,
+            });
+        }
+    }
+
+    let Some(store) = store else {
+        return;
+    };
+    let pool = match source.fetch_pool_transfers().await {
+        Ok(resp) => resp.incoming,
+        Err(err) => {
+            counter!("monitor_rpc_calls_total", "result" => "error").increment(1);
+            warn!(?err, "mempool transfer fetch failed");
+            Vec::new()
+        }
+    };
+    let now = Utc::now();
+    let pending: Vec<_> = mined
+        .iter()
+        .chain(&pool)
+        .filter_map(|entry| {
+            let pid = PaymentId::parse(entry.payment_id.as_deref()?).ok()?;
+            Some(PendingTransfer {
+                txid: entry.txid.clone(),
+                pid,
+                amount: entry.amount,
+                block_height: entry.height,
+                confirmations: entry.height.map_or(0, confirmations),
+                first_seen_at: now,
+                last_seen_at: now,
+            })
+        })
+        .collect();
+    let in_pool = pending
+        .iter()
+        .filter(|transfer| transfer.state() == PaymentState::Detected)
+        .count();
+    gauge!("monitor_pending_transfers", "wallet" => wallet.to_string(), "state" => "pool")
+        .set(in_pool as f64);
+    gauge!("monitor_pending_transfers", "wallet" => wallet.to_string(), "state" => "mined")
+        .set((pending.len() - in_pool) as f64);
+    if let Err(err) = store.record_pending(pending).await {
+        warn!(?err, "recording pending transfers failed");
+    }
+    let retention = chrono::Duration::from_std(PENDING_RETENTION).unwrap_or_default();
+    if let Err(err) = store.prune_pending(now - retention).await {
+        warn!(?err, "pruning pending transfers failed");
+    }
+}
+
+/// Compares stored checkpoints, newest first, with the source's chain.
+/// Returns `None` while the newest still matches (or the source cannot report
+/// hashes); otherwise the height just above the newest checkpoint that still
+/// matches, or the oldest checkpoint when none does.
+async fn find_fork_height<S, D>(storage: &D, source: &S) -> Result<Option<u64>, MonitorError>
+where
+    S: TransferSource,
+    D: MonitorStateStore,
+{
+    let mut fork_height = None;
+    for checkpoint in storage.recent_checkpoints(REORG_SCAN_CHECKPOINTS).await? {
+        // Recorded while the source could not report hashes.
+        let Some(recorded) = checkpoint.block_hash else {
+            continue;
+        };
+        match source.block_hash(checkpoint.height).await? {
+            None => return Ok(None),
+            Some(hash) if hash == recorded => {
+                return Ok(fork_height.map(|_| checkpoint.height + 1));
+            }
+            Some(_) => fork_height = Some(checkpoint.height),
+        }
+    }
+    Ok(fork_height)
+}
+
+async fn roll_back<D>(
+    storage: &D,
+    cursor: &mut WalletCursor,
+    fork_height: u64,
+) -> Result<(), MonitorError>
+where
+    D: MonitorStateStore,
+{
+    let height = fork_height.min(cursor.height);
+    let report = storage.rollback_to_height(height).await?;
+    counter!("monitor_reorgs_total").increment(1);
+    counter!("monitor_reorg_payments_removed_total").increment(report.payments_removed);
+    counter!("monitor_reorg_payments_reorged_total").increment(report.payments_reorged);
+    counter!("monitor_reorg_tokens_flagged_total").increment(report.tokens_flagged);
+    warn!(
+        wallet = cursor.wallet,
+        height,
+        payments_removed = report.payments_removed,
+        payments_reorged = report.payments_reorged,
+        renewals_removed = report.renewals_removed,
+        transfers_removed = report.transfers_removed,
+        tokens_flagged = report.tokens_flagged,
+        "chain reorg detected, rescanning from fork height"
+    );
+    gauge!("monitor_last_height", "wallet" => cursor.wallet.clone()).set(height as f64);
+    cursor.height = height;
+    Ok(())
 }
 
 async fn handle_batch<D>(
     storage: &D,
     transfers: TransfersResponse,
-    current_height: &mut u64,
-    min_payment_amount: i64,
+    cursor: &mut WalletCursor,
+    rules: IngestRules,
     safe_height: u64,
     hooks: Option<&MonitorHooks>,
 ) -> Result<(), MonitorError>
 where
-    D: MonitorStateStore + PaymentStore,
+    D: MonitorStateStore + PaymentStore + QuoteStore,
 {
     counter!("monitor_rpc_calls_total", "result" => "ok").increment(1);
     histogram!("monitor_batch_entries").record(transfers.incoming.len() as f64);
 
     let mut observed_height: Option<u64> = None;
 
+    for skipped in transfers.skipped {
+        warn!(
+            wallet = cursor.wallet,
+            txid = skipped.txid,
+            amount = skipped.amount,
+            reason = skipped.reason,
+            "skipping transfer the wallet reported in an unusable form"
+        );
+        counter!("monitor_payments_ingested_total", "result" => skipped.reason).increment(1);
+        if let Some(h) = skipped.height {
+            let h = h as u64;
+            observed_height = Some(observed_height.map_or(h, |current| current.max(h)));
+        }
+        if let Some(hooks) = hooks {
+            hooks
+                .journal_skipped(SkippedTransfer {
+                    txid: skipped.txid,
+                    wallet: cursor.wallet.clone(),
+                    reason: skipped.reason.to_string(),
+                    payment_id: skipped.payment_id,
+                    amount: skipped.amount,
+                    block_height: skipped.height,
+                    timestamp: skipped.timestamp,
+                    skipped_at: Utc::now(),
+                })
+                .await;
+        }
+    }
+
     for entry in &transfers.incoming {
         if let Some(h) = entry.height {
             let h = h as u64;
             observed_height = Some(observed_height.map_or(h, |current| current.max(h)));
         }
-        process_entry(storage, entry, min_payment_amount, hooks).await?;
+        process_entry(storage, &cursor.wallet, entry, rules, hooks).await?;
     }
 
     let mut next_height = if let Some(max_height) = observed_height {
@@ -157,9 +545,21 @@ where
     };
     next_height = next_height.min(safe_height.saturating_add(1));
 
-    storage.upsert_last_processed_height(next_height).await?;
-    gauge!("monitor_last_height").set(next_height as f64);
-    *current_height = next_height;
+    let stored = storage
+        .upsert_last_processed_height(&cursor.wallet, next_height)
+        .await?;
+    if stored > next_height {
+        // Rewinding here would re-ingest everything in between.
+        warn!(
+            wallet = cursor.wallet,
+            next_height,
+            stored,
+            "stored cursor is ahead of this batch; is another monitor running?"
+        );
+        counter!("monitor_cursor_regressions_rejected_total").increment(1);
+    }
+    gauge!("monitor_last_height", "wallet" => cursor.wallet.clone()).set(stored as f64);
+    cursor.height = stored;
     Ok(())
 }
 
@@ -167,6 +567,12 @@ where
 pub struct MonitorHooks {
     pid_cache: Option<std::sync::Arc<dyn PidCache>>, // marks present after persistence
     pid_bloom: Option<std::sync::Arc<PidBloom>>,     // inserts after persistence
+    shared_cache: Option<std::sync::Arc<dyn PidCache>>, // cross-replica hints
+    events: Option<PaymentEvents>,                   // live subscribers
+    webhooks: Option<WebhookSender>,                 // persisted payments
+    heartbeat: Option<MonitorHeartbeat>,             // poll liveness
+    pending: Option<Arc<dyn PendingPaymentStore>>,   // not yet ingested
+    skipped: Option<Arc<dyn SkippedTransferStore>>,  // failed to ingest
 }
 
 impl MonitorHooks {
@@ -177,6 +583,83 @@ impl MonitorHooks {
         Self {
             pid_cache,
             pid_bloom,
+            shared_cache: None,
+            events: None,
+            webhooks: None,
+            heartbeat: None,
+            pending: None,
+            skipped: None,
+        }
+    }
+
+    /// Also marks ingested PIDs in a cache shared with other replicas.
+    pub fn with_shared_cache(mut self, cache: Option<std::sync::Arc<dyn PidCache>>) -> Self {
+        self.shared_cache = cache;
+        self
+    }
+
+    /// Publishes payment lifecycle events for live subscribers.
+    pub fn with_events(mut self, events: Option<PaymentEvents>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Queues a webhook notification for every persisted payment.
+    pub fn with_webhooks(mut self, webhooks: Option<WebhookSender>) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    /// Records the start and end of every wallet poll.
+    pub fn with_heartbeat(mut self, heartbeat: Option<MonitorHeartbeat>) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Records transfers in the mempool or short of the confirmation depth
+    /// (`MONITOR_TRACK_PENDING`).
+    pub fn with_pending_store(mut self, store: Option<Arc<dyn PendingPaymentStore>>) -> Self {
+        self.pending = store;
+        self
+    }
+
+    pub fn pending_store(&self) -> Option<&dyn PendingPaymentStore> {
+        self.pending.as_deref()
+    }
+
+    /// Journals transfers skipped for an invalid PID or an unconvertible
+    /// amount so operators can review and re-process them.
+    pub fn with_skip_journal(mut self, store: Option<Arc<dyn SkippedTransferStore>>) -> Self {
+        self.skipped = store;
+        self
+    }
+
+    /// Best effort: a journal failure is logged and never fails the batch.
+    pub async fn journal_skipped(&self, transfer: SkippedTransfer) {
+        let Some(store) = &self.skipped else {
+            return;
+        };
+        let txid = transfer.txid.clone();
+        if let Err(err) = store.record_skipped(transfer).await {
+            warn!(?err, txid, "journaling skipped transfer failed");
+        }
+    }
+
+    pub fn heartbeat(&self) -> Option<&MonitorHeartbeat> {
+        self.heartbeat.as_ref()
+    }
+
+    pub fn webhooks(&self) -> Option<&WebhookSender> {
+        self.webhooks.as_ref()
+    }
+
+    pub fn events(&self) -> Option<&PaymentEvents> {
+        self.events.as_ref()
+    }
+
+    pub fn publish(&self, event: PaymentEvent) {
+        if let Some(events) = &self.events {
+            events.publish(event);
         }
     }
 
@@ -187,21 +670,96 @@ impl MonitorHooks {
         if let Some(bloom) = &self.pid_bloom {
             bloom.insert(pid);
         }
+        if let Some(cache) = &self.shared_cache {
+            cache.mark_present(pid);
+        }
     }
 }
 
 pub fn build_rpc_source(url: &str) -> Result<crate::rpc::RpcTransferSource, MonitorError> {
+    Ok(crate::rpc::RpcTransferSource::new(wallet_client(url)?))
+}
+
+/// Payment-ID sources for every configured wallet, each paired with the
+/// name its cursor is stored under.
+pub fn build_wallet_sources(
+    config: &BootstrapConfig,
+) -> Result<Vec<(String, crate::rpc::RpcTransferSource)>, MonitorError> {
+    config
+        .wallets()
+        .into_iter()
+        .map(|wallet| {
+            let mut source = build_rpc_source(wallet.url())?;
+            if let Some(daemon) = config.monero_daemon_rpc_url() {
+                source = source.with_daemon(daemon);
+            }
+            Ok((wallet.name().to_string(), source))
+        })
+        .collect()
+}
+
+/// Source for [`DetectionMode::Subaddress`](anon_ticket_domain::config::DetectionMode),
+/// watching `account_index` and mapping subaddresses through `store`.
+pub fn build_subaddress_source<S: SubaddressStore>(
+    url: &str,
+    account_index: u32,
+    store: S,
+) -> Result<crate::rpc::SubaddressTransferSource<S>, MonitorError> {
+    Ok(crate::rpc::SubaddressTransferSource::new(
+        wallet_client(url)?,
+        account_index,
+        store,
+    ))
+}
+
+/// Named wallet sources of mixed types, as [`run_monitor`] takes them.
+pub type DynWallets = Vec<(String, Arc<dyn TransferSource>)>;
+
+/// Applies `MONITOR_SOURCE`: under [`MonitorSource::Zmq`] every wallet also
+/// polls as soon as `monerod` announces a block, through one shared
+/// subscription. Builds without the `zmq` feature reject it.
+pub fn with_monitor_source<S: TransferSource + 'static>(
+    config: &BootstrapConfig,
+    wallets: Vec<(String, S)>,
+) -> Result<DynWallets, MonitorError> {
+    match (config.monitor_source(), config.monero_daemon_zmq_url()) {
+        #[cfg(feature = "zmq")]
+        (MonitorSource::Zmq, Some(endpoint)) => {
+            let notifier = crate::rpc::ZmqNotifier::subscribe(endpoint);
+            Ok(wallets
+                .into_iter()
+                .map(|(name, source)| {
+                    let source = crate::rpc::ZmqTransferSource::new(source, notifier.clone());
+                    (name, Arc::new(source) as Arc<dyn TransferSource>)
+                })
+                .collect())
+        }
+        (MonitorSource::Zmq, _) => Err(MonitorError::Config(ConfigError::InvalidValue {
+            key: "MONITOR_SOURCE",
+            reason: "zmq needs a build with the `zmq` feature",
+        })),
+        (MonitorSource::Rpc, _) => Ok(wallets
+            .into_iter()
+            .map(|(name, source)| (name, Arc::new(source) as Arc<dyn TransferSource>))
+            .collect()),
+    }
+}
+
+pub(crate) fn wallet_client(url: &str) -> Result<monero_rpc::WalletClient, MonitorError> {
     let normalized = url.strip_suffix("/json_rpc").unwrap_or(url);
     let rpc_client = RpcClientBuilder::new()
         .build(normalized.to_string())
         .map_err(|err| MonitorError::Rpc(err.to_string()))?;
-    Ok(crate::rpc::RpcTransferSource::new(rpc_client.wallet()))
+    Ok(rpc_client.wallet())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use anon_ticket_domain::model::{ClaimOutcome, NewPayment, PaymentId, PaymentRecord};
+    use anon_ticket_domain::model::{
+        ClaimOutcome, NewPayment, NewPaymentQuote, PaymentFilter, PaymentId, PaymentQuote,
+        PaymentRecord, PaymentTransfer, ReorgRollback,
+    };
     use anon_ticket_domain::storage::{PaymentStore, StorageResult};
     use async_trait::async_trait;
     use std::sync::atomic::{AtomicBool, Ordering};
@@ -214,12 +772,31 @@ mod tests {
 
     #[async_trait]
     impl MonitorStateStore for MockStorage {
-        async fn last_processed_height(&self) -> StorageResult<Option<u64>> {
+        async fn last_processed_height(&self, _wallet: &str) -> StorageResult<Option<u64>> {
             Ok(Some(100))
         }
-        async fn upsert_last_processed_height(&self, _height: u64) -> StorageResult<()> {
+        async fn upsert_last_processed_height(
+            &self,
+            _wallet: &str,
+            height: u64,
+        ) -> StorageResult<u64> {
+            Ok(height)
+        }
+        async fn record_checkpoint(&self, _checkpoint: MonitorCheckpoint) -> StorageResult<()> {
             Ok(())
         }
+        async fn recent_checkpoints(&self, _limit: u64) -> StorageResult<Vec<MonitorCheckpoint>> {
+            Ok(Vec::new())
+        }
+        async fn find_checkpoint_at_or_below(
+            &self,
+            _height: u64,
+        ) -> StorageResult<Option<MonitorCheckpoint>> {
+            Ok(None)
+        }
+        async fn rollback_to_height(&self, _height: u64) -> StorageResult<ReorgRollback> {
+            Ok(ReorgRollback::default())
+        }
     }
 
     #[async_trait]
@@ -236,6 +813,42 @@ mod tests {
         async fn find_payment(&self, _pid: &PaymentId) -> StorageResult<Option<PaymentRecord>> {
             Ok(None)
         }
+        async fn unclaim_payment(&self, _pid: &PaymentId) -> StorageResult<Option<PaymentRecord>> {
+            Ok(None)
+        }
+        async fn list_payments(
+            &self,
+            _filter: &PaymentFilter,
+            _after: Option<&PaymentId>,
+            _limit: u64,
+        ) -> StorageResult<Vec<PaymentRecord>> {
+            Ok(Vec::new())
+        }
+        async fn find_transfers(&self, _pid: &PaymentId) -> StorageResult<Vec<PaymentTransfer>> {
+            Ok(Vec::new())
+        }
+        async fn find_payment_by_txid(&self, _txid: &str) -> StorageResult<Option<PaymentRecord>> {
+            Ok(None)
+        }
+    }
+
+    #[async_trait]
+    impl QuoteStore for MockStorage {
+        async fn insert_quote(&self, _quote: NewPaymentQuote) -> StorageResult<bool> {
+            Ok(false)
+        }
+        async fn find_quote(&self, _pid: &PaymentId) -> StorageResult<Option<PaymentQuote>> {
+            Ok(None)
+        }
+        async fn mark_quote_paid(&self, _pid: &PaymentId) -> StorageResult<bool> {
+            Ok(false)
+        }
+        async fn settle_quote(&self, _pid: &PaymentId) -> StorageResult<Option<PaymentQuote>> {
+            Ok(None)
+        }
+        async fn expire_quotes(&self, _now: chrono::DateTime<Utc>) -> StorageResult<u64> {
+            Ok(0)
+        }
     }
 
     #[tokio::test]
@@ -244,7 +857,7 @@ mod tests {
         let storage = MockStorage {
             should_fail: should_fail.clone(),
         };
-        let mut height = 100;
+        let mut height = WalletCursor::primary(100);
 
         let transfers = TransfersResponse {
             incoming: vec![crate::rpc::TransferEntry {
@@ -254,15 +867,32 @@ mod tests {
                 height: Some(101),
                 timestamp: 0,
             }],
+            skipped: Vec::new(),
         };
 
         // Should fail
-        let result = handle_batch(&storage, transfers.clone(), &mut height, 1, 200, None).await;
+        let result = handle_batch(
+            &storage,
+            transfers.clone(),
+            &mut height,
+            IngestRules::new(1),
+            200,
+            None,
+        )
+        .await;
         assert!(result.is_err());
 
         // Should succeed
         should_fail.store(false, Ordering::SeqCst);
-        let result = handle_batch(&storage, transfers, &mut height, 1, 200, None).await;
+        let result = handle_batch(
+            &storage,
+            transfers,
+            &mut height,
+            IngestRules::new(1),
+            200,
+            None,
+        )
+        .await;
         assert!(result.is_ok());
     }
 
@@ -279,7 +909,7 @@ mod tests {
             _max_height: u64,
         ) -> Result<TransfersResponse, MonitorError> {
             self.fetch_called.store(true, Ordering::SeqCst);
-            Ok(TransfersResponse { incoming: vec![] })
+            Ok(TransfersResponse::default())
         }
 
         async fn wallet_height(&self) -> Result<u64, MonitorError> {
@@ -295,17 +925,24 @@ mod tests {
         let source = RecordingSource {
             fetch_called: Arc::new(AtomicBool::new(false)),
         };
-        let mut height = 60;
+        let mut height = WalletCursor::primary(60);
         let safe_height = 40;
 
-        monitor_tick(&storage, &source, &mut height, 1, safe_height, None)
-            .await
-            .expect("tick succeeds");
+        monitor_tick(
+            &storage,
+            &source,
+            &mut height,
+            IngestRules::new(1),
+            safe_height,
+            None,
+        )
+        .await
+        .expect("tick succeeds");
 
         // Should not call fetch because current height is beyond the safe window.
         assert!(!source.fetch_called.load(Ordering::SeqCst));
         // Cursor should remain unchanged.
-        assert_eq!(height, 60);
+        assert_eq!(height.height, 60);
     }
 
     #[derive(Clone)]
@@ -322,6 +959,7 @@ mod tests {
         ) -> Result<TransfersResponse, MonitorError> {
             Ok(TransfersResponse {
                 incoming: self.transfers.as_ref().clone(),
+                skipped: Vec::new(),
             })
         }
 
@@ -345,13 +983,109 @@ mod tests {
         let source = PreparedSource {
             transfers: Arc::new(transfers),
         };
-        let mut height = 110;
+        let mut height = WalletCursor::primary(110);
         let safe_height = 115;
 
-        monitor_tick(&storage, &source, &mut height, 1, safe_height, None)
+        monitor_tick(
+            &storage,
+            &source,
+            &mut height,
+            IngestRules::new(1),
+            safe_height,
+            None,
+        )
+        .await
+        .expect("tick succeeds");
+
+        assert_eq!(height.height, safe_height.saturating_add(1));
+    }
+
+    #[tokio::test]
+    async fn subscribers_see_detected_then_confirmed() {
+        let storage = MockStorage {
+            should_fail: Arc::new(AtomicBool::new(false)),
+        };
+        let source = crate::rpc::SimulatedTransferSource::new(100);
+        source.schedule_transfer(101, "tx1", Some("1111111111111111"), 500);
+        let events = PaymentEvents::new(16);
+        let mut receiver = events.subscribe();
+        let hooks = MonitorHooks::new(None, None).with_events(Some(events));
+        let mut cursor = WalletCursor::primary(100);
+
+        for (blocks, advanced) in [(1, false), (2, true)] {
+            let tip = source.advance_blocks(blocks);
+            let outcome = poll_once(
+                &storage,
+                &source,
+                &mut cursor,
+                tip,
+                IngestRules::new(1),
+                3,
+                Some(&hooks),
+            )
             .await
-            .expect("tick succeeds");
+            .unwrap();
+            assert_eq!(matches!(outcome, PollOutcome::Advanced { .. }), advanced);
+        }
+
+        let detected = receiver.try_recv().unwrap();
+        assert_eq!(detected.pid.to_hex(), "1111111111111111");
+        assert_eq!(
+            detected.kind,
+            PaymentEventKind::Detected { confirmations: 1 }
+        );
+        let confirmed = receiver.try_recv().unwrap();
+        assert_eq!(confirmed.kind, PaymentEventKind::Confirmed);
+        assert_eq!((confirmed.amount, confirmed.block_height), (500, 101));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn shutdown_interrupts_the_poll_sleep() {
+        let shutdown = CancellationToken::new();
+        let wallets: [(String, RecordingSource); 0] = [];
+        assert!(!wait_or_shutdown(Duration::from_millis(1), &wallets, &shutdown).await);
+
+        shutdown.cancel();
+        assert!(wait_or_shutdown(Duration::from_secs(3600), &wallets, &shutdown).await);
+    }
+
+    struct BusySource;
+
+    #[async_trait]
+    impl TransferSource for BusySource {
+        async fn fetch_transfers(
+            &self,
+            _start_height: u64,
+            _max_height: u64,
+        ) -> Result<TransfersResponse, MonitorError> {
+            Ok(TransfersResponse::default())
+        }
+
+        async fn wallet_height(&self) -> Result<u64, MonitorError> {
+            Ok(1)
+        }
+
+        async fn wait_for_activity(&self) {}
+    }
+
+    #[tokio::test]
+    async fn source_activity_cuts_the_poll_sleep_short() {
+        let shutdown = CancellationToken::new();
+        let idle = [(
+            PRIMARY_WALLET.to_string(),
+            RecordingSource {
+                fetch_called: Arc::new(AtomicBool::new(false)),
+            },
+        )];
+        let waited = tokio::time::timeout(
+            Duration::from_millis(50),
+            wait_or_shutdown(Duration::from_secs(3600), &idle, &shutdown),
+        )
+        .await;
+        assert!(waited.is_err(), "idle sources leave the interval alone");
 
-        assert_eq!(height, safe_height.saturating_add(1));
+        let busy = [(PRIMARY_WALLET.to_string(), BusySource)];
+        assert!(!wait_or_shutdown(Duration::from_secs(3600), &busy, &shutdown).await);
     }
 }
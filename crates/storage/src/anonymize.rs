@@ -0,0 +1,541 @@
+//! Scrubs a database copy so staging can run on production-shaped data.
+//!
+//! Every identifier that could be linked back to a real payment is replaced:
+//! PIDs are regenerated (consistently across `payments`, `payment_renewals`,
+//! `payment_transfers`, `service_tokens`, `checkout_bindings`, `checkout_terms`, `payment_quotes`,
+//! `credits`, `refunds`, and `subaddresses`), txids become random hex, and tokens are
+//! re-derived from the new PID/txid pair. Values that cannot be re-derived — passphrase
+//! wrapped tokens, checkout secret hashes, subaddresses, refund addresses, tombstone hashes —
+//! are replaced with random bytes. Webhook events and dead letters embed
+//! whole payloads and audit events name operators, so they are deleted, as
+//! are pending transfers, which the monitor records again. Row counts,
+//! amounts, heights, statuses, and timestamps are left alone. Everything runs
+//! in one transaction.
+
+use std::collections::{HashMap, HashSet};
+
+use anon_ticket_domain::model::{derive_service_token, PaymentId};
+use anon_ticket_domain::storage::StorageResult;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseTransaction, EntityTrait, QueryFilter, TransactionTrait};
+
+use crate::entity::{
+    audit_events, checkout_bindings, checkout_terms, credits, idempotency_keys, payment_quotes,
+    payment_renewals, payment_transfers, payments, pending_payments, refunds, service_tokens,
+    skipped_transfers, subaddresses, token_expiries, token_reviews, token_validations, tombstones,
+    webhook_dead_letters, webhook_deliveries, webhook_events,
+};
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+/// Replacement for free-text revoke reasons, which may quote real users.
+const SCRUBBED_REASON: &str = "anonymized";
+
+/// Rows rewritten per table by [`SeaOrmStorage::anonymize`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AnonymizeReport {
+    pub payments: u64,
+    pub payment_renewals: u64,
+    pub payment_transfers: u64,
+    pub service_tokens: u64,
+    pub token_validations: u64,
+    pub checkout_bindings: u64,
+    pub payment_quotes: u64,
+    pub credits: u64,
+    pub refunds: u64,
+    pub subaddresses: u64,
+    pub tombstones: u64,
+    /// Deleted rather than rewritten.
+    pub webhook_dead_letters: u64,
+    /// Deleted rather than rewritten, with their deliveries.
+    pub webhook_events: u64,
+    /// Deleted rather than rewritten; the stored responses carry tokens.
+    pub idempotency_keys: u64,
+    /// Deleted rather than rewritten; they name operators and quote reasons.
+    pub audit_events: u64,
+    /// Deleted rather than rewritten; they only mirror the wallet.
+    pub pending_payments: u64,
+    /// Deleted rather than rewritten; they hold raw txids and payment ids.
+    pub skipped_transfers: u64,
+}
+
+impl SeaOrmStorage {
+    /// Irreversibly rewrites identifying columns in place. Only ever point
+    /// this at a copy; the original PIDs and tokens are not recoverable.
+    pub async fn anonymize(&self) -> StorageResult<AnonymizeReport> {
+        self.ensure_writable()?;
+        let txn = self
+            .connection()
+            .begin()
+            .await
+            .map_err(StorageError::from_source)?;
+        let mut report = AnonymizeReport::default();
+
+        let payments = payments::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        let mut used: HashSet<Vec<u8>> = payments.iter().map(|row| row.pid.clone()).collect();
+        // old pid -> (new pid, old txid, new txid)
+        let mut pid_map: HashMap<Vec<u8>, (PaymentId, String, String)> = HashMap::new();
+        for row in payments {
+            let new_pid = fresh_pid(&mut used)?;
+            let new_txid = hex::encode(random_bytes::<32>()?);
+            payments::Entity::update_many()
+                .col_expr(
+                    payments::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                )
+                .col_expr(payments::Column::Txid, Expr::value(new_txid.clone()))
+                .filter(payments::Column::Pid.eq(row.pid.clone()))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            pid_map.insert(row.pid, (new_pid, row.txid, new_txid));
+            report.payments += 1;
+        }
+
+        let renewals = payment_renewals::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for row in renewals {
+            let new_pid = match pid_map.get(&row.pid) {
+                Some((new_pid, _, _)) => new_pid.clone(),
+                None => fresh_pid(&mut used)?,
+            };
+            payment_renewals::Entity::update_many()
+                .col_expr(
+                    payment_renewals::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                )
+                .col_expr(
+                    payment_renewals::Column::Txid,
+                    Expr::value(hex::encode(random_bytes::<32>()?)),
+                )
+                .filter(payment_renewals::Column::Txid.eq(row.txid))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            report.payment_renewals += 1;
+        }
+
+        let transfers = payment_transfers::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for row in transfers {
+            let new_pid = match pid_map.get(&row.pid) {
+                Some((new_pid, _, _)) => new_pid.clone(),
+                None => fresh_pid(&mut used)?,
+            };
+            payment_transfers::Entity::update_many()
+                .col_expr(
+                    payment_transfers::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                )
+                .col_expr(
+                    payment_transfers::Column::Txid,
+                    Expr::value(hex::encode(random_bytes::<32>()?)),
+                )
+                .filter(payment_transfers::Column::Txid.eq(row.txid))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            report.payment_transfers += 1;
+        }
+
+        let tokens = service_tokens::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        let mut token_map: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        for row in tokens {
+            let (new_pid, new_token) = match pid_map.get(&row.pid) {
+                Some((new_pid, old_txid, new_txid)) => {
+                    let old_pid = PaymentId::try_from(row.pid.clone())
+                        .map_err(|err| StorageError::Database(err.to_string()))?;
+                    let derived =
+                        derive_service_token(&old_pid, old_txid).as_bytes() == &row.token[..];
+                    let token = if derived {
+                        derive_service_token(new_pid, new_txid).into_bytes()
+                    } else {
+                        random_bytes::<32>()?
+                    };
+                    (new_pid.clone(), token)
+                }
+                None => (fresh_pid(&mut used)?, random_bytes::<32>()?),
+            };
+            let mut update = service_tokens::Entity::update_many()
+                .col_expr(
+                    service_tokens::Column::Token,
+                    Expr::value(new_token.to_vec()),
+                )
+                .col_expr(
+                    service_tokens::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                );
+            if row.revoke_reason.is_some() {
+                update = update.col_expr(
+                    service_tokens::Column::RevokeReason,
+                    Expr::value(SCRUBBED_REASON),
+                );
+            }
+            update
+                .filter(service_tokens::Column::Token.eq(row.token.clone()))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            token_map.insert(row.token, new_token.to_vec());
+            report.service_tokens += 1;
+        }
+
+        report.token_validations = rewrite_validations(&txn, &token_map).await?;
+        rewrite_expiries(&txn, &token_map).await?;
+        rewrite_reviews(&txn, &token_map).await?;
+
+        let credits = credits::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for row in credits {
+            let new_pid = match pid_map.get(&row.pid) {
+                Some((new_pid, _, _)) => new_pid.clone(),
+                None => fresh_pid(&mut used)?,
+            };
+            let new_token = match token_map.get(&row.token) {
+                Some(token) => token.clone(),
+                None => random_bytes::<32>()?.to_vec(),
+            };
+            credits::Entity::update_many()
+                .col_expr(credits::Column::Token, Expr::value(new_token))
+                .col_expr(
+                    credits::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                )
+                .filter(credits::Column::Id.eq(row.id))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            report.credits += 1;
+        }
+
+        let refunds = refunds::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for row in refunds {
+            let new_pid = match pid_map.get(&row.pid) {
+                Some((new_pid, _, _)) => new_pid.clone(),
+                None => fresh_pid(&mut used)?,
+            };
+            let new_txid = match row.txid {
+                Some(_) => Some(hex::encode(random_bytes::<32>()?)),
+                None => None,
+            };
+            refunds::Entity::update_many()
+                .col_expr(
+                    refunds::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                )
+                .col_expr(
+                    refunds::Column::Address,
+                    Expr::value(hex::encode(random_bytes::<32>()?)),
+                )
+                .col_expr(refunds::Column::Txid, Expr::value(new_txid))
+                // Wallet errors may quote the address.
+                .col_expr(
+                    refunds::Column::Error,
+                    Expr::value(row.error.map(|_| SCRUBBED_REASON.to_string())),
+                )
+                .filter(refunds::Column::Id.eq(row.id))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            report.refunds += 1;
+        }
+
+        let bindings = checkout_bindings::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for row in bindings {
+            let new_pid = match pid_map.get(&row.pid) {
+                Some((new_pid, _, _)) => new_pid.clone(),
+                None => fresh_pid(&mut used)?,
+            };
+            checkout_bindings::Entity::update_many()
+                .col_expr(
+                    checkout_bindings::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                )
+                .col_expr(
+                    checkout_bindings::Column::SecretHash,
+                    Expr::value(random_bytes::<32>()?.to_vec()),
+                )
+                .filter(checkout_bindings::Column::Pid.eq(row.pid.clone()))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            checkout_terms::Entity::update_many()
+                .col_expr(
+                    checkout_terms::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                )
+                .filter(checkout_terms::Column::Pid.eq(row.pid))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            report.checkout_bindings += 1;
+        }
+
+        let quotes = payment_quotes::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for row in quotes {
+            let new_pid = match pid_map.get(&row.pid) {
+                Some((new_pid, _, _)) => new_pid.clone(),
+                None => fresh_pid(&mut used)?,
+            };
+            payment_quotes::Entity::update_many()
+                .col_expr(
+                    payment_quotes::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                )
+                .filter(payment_quotes::Column::Pid.eq(row.pid))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            report.payment_quotes += 1;
+        }
+
+        let subaddresses = subaddresses::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for row in subaddresses {
+            let new_pid = match pid_map.get(&row.pid) {
+                Some((new_pid, _, _)) => new_pid.clone(),
+                None => fresh_pid(&mut used)?,
+            };
+            subaddresses::Entity::update_many()
+                .col_expr(
+                    subaddresses::Column::Pid,
+                    Expr::value(new_pid.as_bytes().to_vec()),
+                )
+                .col_expr(
+                    subaddresses::Column::Address,
+                    Expr::value(hex::encode(random_bytes::<32>()?)),
+                )
+                .filter(subaddresses::Column::Pid.eq(row.pid))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            report.subaddresses += 1;
+        }
+
+        let tombstones = tombstones::Entity::find()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for row in tombstones {
+            tombstones::Entity::update_many()
+                .col_expr(
+                    tombstones::Column::IdHash,
+                    Expr::value(random_bytes::<32>()?.to_vec()),
+                )
+                .filter(tombstones::Column::IdHash.eq(row.id_hash))
+                .filter(tombstones::Column::Kind.eq(row.kind))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            report.tombstones += 1;
+        }
+
+        report.webhook_dead_letters = webhook_dead_letters::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        webhook_deliveries::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        report.webhook_events = webhook_events::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        report.idempotency_keys = idempotency_keys::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        report.audit_events = audit_events::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        report.pending_payments = pending_payments::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        report.skipped_transfers = skipped_transfers::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+
+        txn.commit().await.map_err(StorageError::from_source)?;
+        Ok(report)
+    }
+}
+
+async fn rewrite_validations(
+    txn: &DatabaseTransaction,
+    token_map: &HashMap<Vec<u8>, Vec<u8>>,
+) -> StorageResult<u64> {
+    let rows = token_validations::Entity::find()
+        .all(txn)
+        .await
+        .map_err(StorageError::from_source)?;
+    let mut rewritten = 0;
+    for row in rows {
+        let new_token = match token_map.get(&row.token) {
+            Some(token) => token.clone(),
+            None => random_bytes::<32>()?.to_vec(),
+        };
+        token_validations::Entity::update_many()
+            .col_expr(token_validations::Column::Token, Expr::value(new_token))
+            .filter(token_validations::Column::Token.eq(row.token))
+            .exec(txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
+
+/// Re-keys pinned expiries onto the rewritten tokens.
+async fn rewrite_expiries(
+    txn: &DatabaseTransaction,
+    token_map: &HashMap<Vec<u8>, Vec<u8>>,
+) -> StorageResult<()> {
+    let rows = token_expiries::Entity::find()
+        .all(txn)
+        .await
+        .map_err(StorageError::from_source)?;
+    for row in rows {
+        let new_token = match token_map.get(&row.token) {
+            Some(token) => token.clone(),
+            None => random_bytes::<32>()?.to_vec(),
+        };
+        token_expiries::Entity::update_many()
+            .col_expr(token_expiries::Column::Token, Expr::value(new_token))
+            .filter(token_expiries::Column::Token.eq(row.token))
+            .exec(txn)
+            .await
+            .map_err(StorageError::from_source)?;
+    }
+    Ok(())
+}
+
+/// Re-keys review flags onto the rewritten tokens. Reasons are generated by
+/// the monitor, not users, and are kept.
+async fn rewrite_reviews(
+    txn: &DatabaseTransaction,
+    token_map: &HashMap<Vec<u8>, Vec<u8>>,
+) -> StorageResult<()> {
+    let rows = token_reviews::Entity::find()
+        .all(txn)
+        .await
+        .map_err(StorageError::from_source)?;
+    for row in rows {
+        let new_token = match token_map.get(&row.token) {
+            Some(token) => token.clone(),
+            None => random_bytes::<32>()?.to_vec(),
+        };
+        token_reviews::Entity::update_many()
+            .col_expr(token_reviews::Column::Token, Expr::value(new_token))
+            .filter(token_reviews::Column::Token.eq(row.token))
+            .exec(txn)
+            .await
+            .map_err(StorageError::from_source)?;
+    }
+    Ok(())
+}
+
+/// Random PID that collides with neither an existing nor an assigned one, so
+/// in-place primary key updates never conflict.
+fn fresh_pid(used: &mut HashSet<Vec<u8>>) -> StorageResult<PaymentId> {
+    loop {
+        let pid = PaymentId::generate().map_err(StorageError::from_source)?;
+        if used.insert(pid.as_bytes().to_vec()) {
+            return Ok(pid);
+        }
+    }
+}
+
+fn random_bytes<const N: usize>() -> StorageResult<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::fill(&mut bytes).map_err(StorageError::from_source)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::{NewPayment, NewServiceToken, ServiceToken};
+    use anon_ticket_domain::storage::{PaymentStore, TokenStore};
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn anonymize_preserves_shape_and_relinks_tokens() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let pid = PaymentId::parse("0123456789abcdef").unwrap();
+        let txid = "ab".repeat(32);
+        storage
+            .insert_payment(NewPayment {
+                pid: pid.clone(),
+                txid: txid.clone(),
+                amount: 42,
+                block_height: 7,
+                detected_at: Utc::now(),
+                address_id: None,
+            })
+            .await
+            .unwrap();
+        storage.claim_payment(&pid).await.unwrap();
+        let token = derive_service_token(&pid, &txid);
+        storage
+            .insert_token(NewServiceToken {
+                token: token.clone(),
+                pid: pid.clone(),
+                amount: 42,
+                issued_at: Utc::now(),
+                abuse_score: 0,
+                expires_at: None,
+                tier: None,
+            })
+            .await
+            .unwrap();
+
+        let report = storage.anonymize().await.unwrap();
+        assert_eq!(report.payments, 1);
+        assert_eq!(report.service_tokens, 1);
+
+        assert!(storage.find_payment(&pid).await.unwrap().is_none());
+        assert!(storage.find_token(&token).await.unwrap().is_none());
+        let ids = storage.all_payment_ids().await.unwrap();
+        assert_eq!(ids.len(), 1);
+        let payment = storage.find_payment(&ids[0]).await.unwrap().unwrap();
+        assert_ne!(payment.txid, txid);
+        assert_eq!(payment.amount, 42);
+        assert!(payment.claimed_at.is_some());
+        let new_token: ServiceToken = derive_service_token(&ids[0], &payment.txid);
+        let record = storage.find_token(&new_token).await.unwrap().unwrap();
+        assert_eq!(record.pid, ids[0]);
+    }
+}
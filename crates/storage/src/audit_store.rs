@@ -0,0 +1,162 @@
+use anon_ticket_domain::model::{AuditActor, AuditEventRecord, AuditFilter, NewAuditEvent};
+use anon_ticket_domain::storage::{AuditStore, StorageResult};
+use sea_orm::{ColumnTrait, Condition, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set};
+
+use crate::entity::audit_events;
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl AuditStore for SeaOrmStorage {
+    async fn record_audit_event(&self, event: NewAuditEvent) -> StorageResult<()> {
+        self.ensure_writable()?;
+        audit_events::Entity::insert(audit_events::ActiveModel {
+            occurred_at: Set(event.occurred_at),
+            actor: Set(event.actor.as_str().to_string()),
+            action: Set(event.action),
+            subject_hash: Set(event.subject_hash.to_vec()),
+            outcome: Set(event.outcome),
+            reason: Set(event.reason),
+            operator: Set(event.operator),
+            key_id: Set(event.key_id),
+            ..Default::default()
+        })
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        Ok(())
+    }
+
+    async fn list_audit_events(
+        &self,
+        filter: &AuditFilter,
+        before: Option<i64>,
+        limit: u64,
+    ) -> StorageResult<Vec<AuditEventRecord>> {
+        let condition = Condition::all()
+            .add_option(
+                filter
+                    .actor
+                    .map(|actor| audit_events::Column::Actor.eq(actor.as_str())),
+            )
+            .add_option(
+                filter
+                    .action
+                    .as_deref()
+                    .map(|action| audit_events::Column::Action.eq(action)),
+            )
+            .add_option(
+                filter
+                    .subject_hash
+                    .map(|hash| audit_events::Column::SubjectHash.eq(hash.to_vec())),
+            )
+            .add_option(
+                filter
+                    .outcome
+                    .as_deref()
+                    .map(|outcome| audit_events::Column::Outcome.eq(outcome)),
+            )
+            .add_option(
+                filter
+                    .since
+                    .map(|at| audit_events::Column::OccurredAt.gte(at)),
+            )
+            .add_option(
+                filter
+                    .until
+                    .map(|at| audit_events::Column::OccurredAt.lte(at)),
+            )
+            .add_option(before.map(|id| audit_events::Column::Id.lt(id)));
+        audit_events::Entity::find()
+            .filter(condition)
+            .order_by_desc(audit_events::Column::Id)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(into_audit_record)
+            .collect()
+    }
+}
+
+fn into_audit_record(model: audit_events::Model) -> StorageResult<AuditEventRecord> {
+    let actor = AuditActor::parse(&model.actor)
+        .ok_or_else(|| StorageError::Database(format!("unknown audit actor {}", model.actor)))?;
+    let subject_hash = <[u8; 32]>::try_from(model.subject_hash.as_slice())
+        .map_err(|_| StorageError::Database("audit subject hash is not 32 bytes".into()))?;
+    Ok(AuditEventRecord {
+        id: model.id,
+        event: NewAuditEvent {
+            occurred_at: model.occurred_at,
+            actor,
+            action: model.action,
+            subject_hash,
+            outcome: model.outcome,
+            reason: model.reason,
+            operator: model.operator,
+            key_id: model.key_id,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::audit_subject_hash;
+    use chrono::Utc;
+
+    fn event(action: &str, subject: &str, actor: AuditActor) -> NewAuditEvent {
+        NewAuditEvent {
+            occurred_at: Utc::now(),
+            actor,
+            action: action.to_string(),
+            subject_hash: audit_subject_hash(subject),
+            outcome: "ok".to_string(),
+            reason: None,
+            operator: None,
+            key_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn audit_events_list_newest_first_with_filters() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        for (action, subject, actor) in [
+            ("redeem", "aa", AuditActor::Public),
+            ("token.revoke", "bb", AuditActor::Internal),
+            ("redeem", "bb", AuditActor::Public),
+        ] {
+            storage
+                .record_audit_event(event(action, subject, actor))
+                .await
+                .unwrap();
+        }
+
+        let all = storage
+            .list_audit_events(&AuditFilter::default(), None, 10)
+            .await
+            .unwrap();
+        let ids: Vec<i64> = all.iter().map(|record| record.id).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids.windows(2).all(|pair| pair[0] > pair[1]));
+
+        let page = storage
+            .list_audit_events(&AuditFilter::default(), Some(ids[0]), 1)
+            .await
+            .unwrap();
+        assert_eq!(page[0].id, ids[1]);
+
+        let about_bb = AuditFilter {
+            subject_hash: Some(audit_subject_hash("BB")),
+            actor: Some(AuditActor::Public),
+            ..AuditFilter::default()
+        };
+        let found = storage
+            .list_audit_events(&about_bb, None, 10)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].event.action, "redeem");
+    }
+}
@@ -0,0 +1,57 @@
+use std::env;
+use std::process;
+
+use anon_ticket_storage::SeaOrmStorage;
+
+const CONFIRM_FLAG: &str = "--yes-rewrite-this-copy";
+
+#[tokio::main]
+async fn main() {
+    let mut args = env::args().skip(1);
+    let (Some(database_url), Some(flag)) = (args.next(), args.next()) else {
+        eprintln!("Usage: anonymize_db <database_url> {CONFIRM_FLAG}");
+        eprintln!("Irreversibly scrubs PIDs, txids, and tokens. Run it on a copy only.");
+        process::exit(1);
+    };
+    if flag != CONFIRM_FLAG {
+        eprintln!("refusing to run without {CONFIRM_FLAG}");
+        process::exit(1);
+    }
+
+    let storage = match SeaOrmStorage::connect(&database_url).await {
+        Ok(storage) => storage,
+        Err(err) => {
+            eprintln!("failed to open database: {err}");
+            process::exit(1);
+        }
+    };
+
+    match storage.anonymize().await {
+        Ok(report) => {
+            println!("payments: {}", report.payments);
+            println!("payment_renewals: {}", report.payment_renewals);
+            println!("payment_transfers: {}", report.payment_transfers);
+            println!("service_tokens: {}", report.service_tokens);
+            println!("token_validations: {}", report.token_validations);
+            println!("checkout_bindings: {}", report.checkout_bindings);
+            println!("payment_quotes: {}", report.payment_quotes);
+            println!("credits: {}", report.credits);
+            println!("refunds: {}", report.refunds);
+            println!("subaddresses: {}", report.subaddresses);
+            println!("tombstones: {}", report.tombstones);
+            println!(
+                "webhook_dead_letters (deleted): {}",
+                report.webhook_dead_letters
+            );
+            println!("webhook_events (deleted): {}", report.webhook_events);
+            println!("idempotency_keys (deleted): {}", report.idempotency_keys);
+            println!("audit_events (deleted): {}", report.audit_events);
+            println!("pending_payments (deleted): {}", report.pending_payments);
+            println!("skipped_transfers (deleted): {}", report.skipped_transfers);
+        }
+        Err(err) => {
+            eprintln!("anonymization failed (nothing was changed): {err}");
+            process::exit(1);
+        }
+    }
+}
@@ -1,16 +1,40 @@
+use std::time::Duration;
+
 use anon_ticket_domain::storage::StorageResult;
-use sea_orm::Database;
+use sea_orm::{ConnectOptions, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection};
 
-use crate::{errors::StorageError, prepare_connection, SeaOrmStorage};
+use crate::{
+    configure_sqlite, errors::StorageError, migration::run_migrations, schema_drift::verify_schema,
+    SeaOrmStorage,
+};
 
 #[derive(Default)]
 pub struct StorageBuilder {
     database_url: Option<String>,
+    connection: Option<DatabaseConnection>,
+    read_replica: Option<(String, Duration)>,
+    max_connections: Option<u32>,
+    min_connections: Option<u32>,
+    acquire_timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    log_statements: Option<bool>,
+    skip_migrations: bool,
+    skip_pragmas: bool,
+    read_only: bool,
 }
 
 impl StorageBuilder {
     pub fn new() -> Self {
-        Self { database_url: None }
+        Self::default()
+    }
+
+    /// Reuses a pool the embedder already owns instead of opening a second
+    /// one. Takes precedence over [`database_url`](Self::database_url).
+    pub fn from_connection(db: DatabaseConnection) -> Self {
+        Self {
+            connection: Some(db),
+            ..Self::default()
+        }
     }
 
     pub fn database_url(mut self, url: impl Into<String>) -> Self {
@@ -18,14 +42,188 @@ impl StorageBuilder {
         self
     }
 
-    pub async fn build(self) -> StorageResult<SeaOrmStorage> {
-        let url = self
-            .database_url
-            .ok_or_else(|| StorageError::Database("missing database url".into()))?;
-        let db = Database::connect(url)
+    /// See [`SeaOrmStorage::with_read_replica`].
+    pub fn read_replica(mut self, url: impl Into<String>, hedge_after: Duration) -> Self {
+        self.read_replica = Some((url.into(), hedge_after));
+        self
+    }
+
+    /// Caps each pool this builder opens, the replica's included. The pool
+    /// settings are ignored with [`from_connection`](Self::from_connection),
+    /// and unset ones keep SeaORM's defaults.
+    pub fn max_connections(mut self, max: u32) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    pub fn min_connections(mut self, min: u32) -> Self {
+        self.min_connections = Some(min);
+        self
+    }
+
+    /// How long a query waits for a free connection before failing.
+    pub fn acquire_timeout(mut self, timeout: Duration) -> Self {
+        self.acquire_timeout = Some(timeout);
+        self
+    }
+
+    /// How long an unused connection above the minimum stays open.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Turns sqlx's per-statement logging on or off; SeaORM enables it.
+    pub fn log_statements(mut self, enabled: bool) -> Self {
+        self.log_statements = Some(enabled);
+        self
+    }
+
+    fn connect_options(&self, url: String) -> ConnectOptions {
+        let mut options = ConnectOptions::new(url);
+        if let Some(max) = self.max_connections {
+            options.max_connections(max);
+        }
+        if let Some(min) = self.min_connections {
+            options.min_connections(min);
+        }
+        if let Some(timeout) = self.acquire_timeout {
+            options.acquire_timeout(timeout);
+        }
+        if let Some(timeout) = self.idle_timeout {
+            options.idle_timeout(timeout);
+        }
+        if let Some(enabled) = self.log_statements {
+            options.sqlx_logging(enabled);
+        }
+        options
+    }
+
+    /// Skips table creation for schemas managed outside this crate. The
+    /// schema drift check still runs.
+    pub fn skip_migrations(mut self, skip: bool) -> Self {
+        self.skip_migrations = skip;
+        self
+    }
+
+    /// Skips the SQLite WAL/synchronous pragmas, e.g. when the embedder has
+    /// already configured the connection.
+    pub fn skip_pragmas(mut self, skip: bool) -> Self {
+        self.skip_pragmas = skip;
+        self
+    }
+
+    /// Builds a handle whose writes fail with `StorageError::ReadOnly`, for
+    /// reporting replicas pointed at a production database. Implies
+    /// [`skip_migrations`](Self::skip_migrations) and
+    /// [`skip_pragmas`](Self::skip_pragmas), since both write.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub async fn build(mut self) -> StorageResult<SeaOrmStorage> {
+        let db = match (self.connection.take(), self.database_url.take()) {
+            (Some(db), _) => db,
+            (None, Some(url)) => Database::connect(self.connect_options(url))
+                .await
+                .map_err(StorageError::from_source)?,
+            (None, None) => return Err(StorageError::Database("missing database url".into())),
+        };
+        if !(self.skip_pragmas || self.read_only)
+            && db.get_database_backend() == DatabaseBackend::Sqlite
+        {
+            configure_sqlite(&db).await?;
+        }
+        if !(self.skip_migrations || self.read_only) {
+            run_migrations(&db).await?;
+        }
+        verify_schema(&db).await?;
+        let mut storage = SeaOrmStorage::from_connection(db).with_read_only(self.read_only);
+        if let Some((url, hedge_after)) = self.read_replica.take() {
+            let replica = Database::connect(self.connect_options(url))
+                .await
+                .map_err(StorageError::from_source)?;
+            storage = storage.with_replica_connection(replica, hedge_after);
+        }
+        Ok(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::PaymentId;
+    use anon_ticket_domain::storage::PaymentStore;
+    use sea_orm::TransactionTrait;
+
+    #[tokio::test]
+    async fn reuses_an_existing_pool() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let storage = StorageBuilder::from_connection(db.clone())
+            .build()
+            .await
+            .unwrap();
+        let pid = PaymentId::parse("0707070707070707").unwrap();
+        assert!(storage.find_payment(&pid).await.unwrap().is_none());
+        // The caller's handle sees the tables created through the builder.
+        verify_schema(&db).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pool_settings_reach_the_pool() {
+        let storage = StorageBuilder::new()
+            .database_url("sqlite::memory:")
+            .max_connections(1)
+            .min_connections(1)
+            .acquire_timeout(Duration::from_millis(200))
+            .idle_timeout(Duration::from_secs(60))
+            .log_statements(false)
+            .build()
+            .await
+            .unwrap();
+        storage.ping().await.unwrap();
+        // The only connection is held, so the next query times out.
+        let txn = storage.connection().begin().await.unwrap();
+        let usage = storage.pool_usage().unwrap();
+        assert_eq!((usage.in_use(), usage.max), (1, 1));
+        assert!(storage.ping().await.is_err());
+        txn.rollback().await.unwrap();
+        storage.ping().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn skipped_migrations_still_check_the_schema() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let err = StorageBuilder::from_connection(db)
+            .skip_migrations(true)
+            .skip_pragmas(true)
+            .build()
+            .await
+            .err()
+            .expect("empty database has no schema")
+            .to_string();
+        assert!(err.contains("table payments is missing"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn read_only_handle_rejects_writes() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        StorageBuilder::from_connection(db.clone())
+            .build()
+            .await
+            .unwrap();
+        let storage = StorageBuilder::from_connection(db)
+            .read_only(true)
+            .build()
             .await
-            .map_err(StorageError::from_source)?;
-        prepare_connection(&db).await?;
-        Ok(SeaOrmStorage::from_connection(db))
+            .unwrap();
+        assert!(storage.is_read_only());
+        let pid = PaymentId::parse("0707070707070707").unwrap();
+        assert_eq!(
+            storage.claim_payment(&pid).await.err(),
+            Some(StorageError::ReadOnly)
+        );
+        assert!(storage.find_payment(&pid).await.unwrap().is_none());
     }
 }
@@ -0,0 +1,176 @@
+//! Cross-instance change notifications over PostgreSQL `LISTEN/NOTIFY`.
+//!
+//! A payment ingested by the monitor next to one API replica is otherwise
+//! invisible to the others until they restart, and their Bloom filters turn
+//! its redemption away. Writers `pg_notify` [`CHANGES_CHANNEL`] once a payment
+//! is inserted or a token revoked; every replica holding a
+//! [`ChangeListener`] hears about it. Notifications sent while a listener
+//! was disconnected are lost, which it reports as [`Change::Gap`].
+//!
+//! Other backends have no feed: `payments.created_at` is the block time, not
+//! the insert time, so it cannot serve as a polling cursor.
+
+use anon_ticket_domain::model::{PaymentId, ServiceToken};
+use anon_ticket_domain::storage::StorageResult;
+use sea_orm::{ConnectionTrait, DatabaseBackend, Statement};
+use tracing::warn;
+
+use crate::SeaOrmStorage;
+
+/// Postgres notification channel shared by every instance.
+pub const CHANGES_CHANNEL: &str = "anon_ticket_changes";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    PaymentInserted(PaymentId),
+    TokenRevoked(ServiceToken),
+    /// The listener reconnected; changes in between were missed.
+    Gap,
+}
+
+impl Change {
+    fn payload(&self) -> Option<String> {
+        match self {
+            Change::PaymentInserted(pid) => Some(format!("pid:{}", pid.to_hex())),
+            Change::TokenRevoked(token) => Some(format!("token:{}", token.to_hex())),
+            Change::Gap => None,
+        }
+    }
+
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    fn parse(payload: &str) -> Option<Self> {
+        match payload.split_once(':')? {
+            ("pid", hex) => PaymentId::parse(hex).ok().map(Change::PaymentInserted),
+            ("token", hex) => ServiceToken::parse(hex).ok().map(Change::TokenRevoked),
+            _ => None,
+        }
+    }
+}
+
+impl SeaOrmStorage {
+    /// Announces a committed change to other instances. Best effort: the
+    /// change itself is already durable, so a failed notify is only logged
+    /// and counted in `storage_change_notify_errors_total`.
+    pub(crate) async fn publish_change(&self, change: Change) {
+        let db = self.connection();
+        if db.get_database_backend() != DatabaseBackend::Postgres {
+            return;
+        }
+        let Some(payload) = change.payload() else {
+            return;
+        };
+        let notify = Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            "SELECT pg_notify($1, $2)",
+            [CHANGES_CHANNEL.into(), payload.into()],
+        );
+        if let Err(err) = db.execute(notify).await {
+            warn!(error = %err, "failed to publish change notification");
+            metrics::counter!("storage_change_notify_errors_total").increment(1);
+        }
+    }
+
+    /// Subscribes to changes published by any instance sharing this
+    /// database. `None` unless the backend is Postgres. The listener holds
+    /// one connection of the pool for as long as it lives.
+    pub async fn listen_changes(&self) -> StorageResult<Option<ChangeListener>> {
+        if self.connection().get_database_backend() != DatabaseBackend::Postgres {
+            return Ok(None);
+        }
+        ChangeListener::connect(self).await.map(Some)
+    }
+}
+
+/// Receives [`Change`]s; see [`SeaOrmStorage::listen_changes`].
+pub struct ChangeListener {
+    #[cfg(feature = "postgres")]
+    listener: sqlx::postgres::PgListener,
+}
+
+impl ChangeListener {
+    #[cfg(feature = "postgres")]
+    async fn connect(storage: &SeaOrmStorage) -> StorageResult<Self> {
+        use crate::errors::StorageError;
+
+        let pool = storage.connection().get_postgres_connection_pool();
+        let mut listener = sqlx::postgres::PgListener::connect_with(pool)
+            .await
+            .map_err(StorageError::from_source)?;
+        listener
+            .listen(CHANGES_CHANNEL)
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(Self { listener })
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    async fn connect(_storage: &SeaOrmStorage) -> StorageResult<Self> {
+        unreachable!("a Postgres connection requires the `postgres` feature")
+    }
+
+    /// Waits for the next change. Payloads this build does not understand
+    /// are skipped; connection errors are returned and the next call
+    /// reconnects.
+    pub async fn recv(&mut self) -> StorageResult<Change> {
+        #[cfg(feature = "postgres")]
+        {
+            use crate::errors::StorageError;
+
+            loop {
+                let Some(notification) = self
+                    .listener
+                    .try_recv()
+                    .await
+                    .map_err(StorageError::from_source)?
+                else {
+                    return Ok(Change::Gap);
+                };
+                match Change::parse(notification.payload()) {
+                    Some(change) => return Ok(change),
+                    None => warn!(
+                        payload = notification.payload(),
+                        "ignoring unrecognised change notification"
+                    ),
+                }
+            }
+        }
+        #[cfg(not(feature = "postgres"))]
+        unreachable!("a Postgres connection requires the `postgres` feature")
+    }
+}
+
+impl std::fmt::Debug for ChangeListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChangeListener")
+            .field("channel", &CHANGES_CHANNEL)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payloads_round_trip() {
+        let pid = PaymentId::parse("0123456789abcdef").unwrap();
+        let token = ServiceToken::from_bytes([7; 32]);
+        for change in [Change::PaymentInserted(pid), Change::TokenRevoked(token)] {
+            let payload = change.payload().unwrap();
+            assert_eq!(Change::parse(&payload), Some(change));
+        }
+        assert_eq!(Change::Gap.payload(), None);
+        assert_eq!(Change::parse("pid:not-hex"), None);
+        assert_eq!(Change::parse("quote:00"), None);
+    }
+
+    #[tokio::test]
+    async fn sqlite_has_no_change_feed() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        assert!(storage.listen_changes().await.unwrap().is_none());
+        // Publishing is a no-op rather than an error.
+        storage
+            .publish_change(Change::TokenRevoked(ServiceToken::from_bytes([1; 32])))
+            .await;
+    }
+}
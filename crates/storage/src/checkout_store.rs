@@ -0,0 +1,79 @@
+use anon_ticket_domain::model::{CheckoutTerms, NewCheckoutBinding, PaymentId};
+use anon_ticket_domain::storage::{CheckoutStore, StorageResult};
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, EntityTrait, QueryFilter, Set, TransactionTrait,
+};
+
+use crate::entity::{checkout_bindings, checkout_terms};
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl CheckoutStore for SeaOrmStorage {
+    async fn insert_checkout(&self, binding: NewCheckoutBinding) -> StorageResult<bool> {
+        self.ensure_writable()?;
+        let pid = binding.pid.into_bytes().to_vec();
+        let model = checkout_bindings::ActiveModel {
+            pid: Set(pid.clone()),
+            secret_hash: Set(binding.secret_hash.to_vec()),
+            created_at: Set(binding.created_at),
+        };
+        let txn = self
+            .connection()
+            .begin()
+            .await
+            .map_err(StorageError::from_source)?;
+        let inserted = checkout_bindings::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(checkout_bindings::Column::Pid)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        if inserted == 0 {
+            return Ok(false);
+        }
+        if let Some(terms) = binding.terms {
+            checkout_terms::Entity::insert(checkout_terms::ActiveModel {
+                pid: Set(pid),
+                preset: Set(terms.preset),
+                expected_amount: Set(terms.expected_amount),
+                expires_at: Set(terms.expires_at),
+            })
+            .exec_without_returning(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        }
+        txn.commit().await.map_err(StorageError::from_source)?;
+        Ok(true)
+    }
+
+    async fn find_checkout_secret_hash(&self, pid: &PaymentId) -> StorageResult<Option<[u8; 32]>> {
+        let maybe = checkout_bindings::Entity::find()
+            .filter(checkout_bindings::Column::Pid.eq(pid.as_bytes().to_vec()))
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        maybe
+            .map(|model| {
+                model.secret_hash.try_into().map_err(|_| {
+                    StorageError::Database("checkout secret hash must be 32 bytes".into())
+                })
+            })
+            .transpose()
+    }
+
+    async fn find_checkout_terms(&self, pid: &PaymentId) -> StorageResult<Option<CheckoutTerms>> {
+        let maybe = checkout_terms::Entity::find_by_id(pid.as_bytes().to_vec())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(maybe.map(|model| CheckoutTerms {
+            preset: model.preset,
+            expected_amount: model.expected_amount,
+            expires_at: model.expires_at,
+        }))
+    }
+}
@@ -0,0 +1,278 @@
+use anon_ticket_domain::model::{
+    CreditStatus, NewTokenCredit, PaymentId, ServiceToken, TokenCredit,
+};
+use anon_ticket_domain::storage::{CreditStore, StorageResult};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveEnum, ColumnTrait, Condition, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
+
+use crate::entity::credits::{self, CreditStatusDb};
+use crate::entity::service_tokens;
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl CreditStore for SeaOrmStorage {
+    async fn record_credit(&self, credit: NewTokenCredit) -> StorageResult<Option<TokenCredit>> {
+        self.ensure_writable()?;
+        let token = credit.token.as_bytes().to_vec();
+        let inserted = credits::Entity::insert(credits::ActiveModel {
+            token: Set(token.clone()),
+            pid: Set(credit.pid.as_bytes().to_vec()),
+            amount: Set(credit.amount),
+            status: Set(status_to_db(credit.status)),
+            created_at: Set(credit.created_at),
+            hold_until: Set(credit.hold_until),
+            resolved_at: Set(None),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(credits::Column::Token)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        if inserted == 0 {
+            return Ok(None);
+        }
+        credits::Entity::find()
+            .filter(credits::Column::Token.eq(token))
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .map(into_credit)
+            .transpose()
+    }
+
+    async fn find_credit(&self, id: i64) -> StorageResult<Option<TokenCredit>> {
+        credits::Entity::find_by_id(id)
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .map(into_credit)
+            .transpose()
+    }
+
+    async fn list_credits(
+        &self,
+        status: Option<CreditStatus>,
+        before: Option<i64>,
+        limit: u64,
+    ) -> StorageResult<Vec<TokenCredit>> {
+        let condition = Condition::all()
+            .add_option(status.map(|status| credits::Column::Status.eq(status_to_db(status))))
+            .add_option(before.map(|id| credits::Column::Id.lt(id)));
+        credits::Entity::find()
+            .filter(condition)
+            .order_by_desc(credits::Column::Id)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(into_credit)
+            .collect()
+    }
+
+    async fn resolve_credit(
+        &self,
+        id: i64,
+        status: CreditStatus,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<TokenCredit>> {
+        self.ensure_writable()?;
+        let txn = self
+            .connection()
+            .begin()
+            .await
+            .map_err(StorageError::from_source)?;
+        let Some(credit) = credits::Entity::find_by_id(id)
+            .one(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+        else {
+            return Ok(None);
+        };
+        // Conditional on the status so two resolutions cannot both apply.
+        let resolved = credits::Entity::update_many()
+            .col_expr(
+                credits::Column::Status,
+                Expr::value(status_to_db(status).to_value()),
+            )
+            .col_expr(credits::Column::ResolvedAt, Expr::value(at))
+            .filter(credits::Column::Id.eq(id))
+            .filter(credits::Column::Status.eq(CreditStatusDb::Held))
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if resolved == 0 {
+            return Ok(None);
+        }
+        if status == CreditStatus::Credited {
+            // A purged token has nothing left to credit; the record still
+            // shows where the excess went.
+            service_tokens::Entity::update_many()
+                .col_expr(
+                    service_tokens::Column::Amount,
+                    Expr::col(service_tokens::Column::Amount).add(credit.amount),
+                )
+                .filter(service_tokens::Column::Token.eq(credit.token.clone()))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+        }
+        txn.commit().await.map_err(StorageError::from_source)?;
+        into_credit(credits::Model {
+            status: status_to_db(status),
+            resolved_at: Some(at),
+            ..credit
+        })
+        .map(Some)
+    }
+
+    async fn release_expired_holds(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        self.ensure_writable()?;
+        let expired: Vec<i64> = credits::Entity::find()
+            .select_only()
+            .column(credits::Column::Id)
+            .filter(credits::Column::Status.eq(CreditStatusDb::Held))
+            .filter(credits::Column::HoldUntil.lte(now))
+            .into_tuple()
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        let mut released = 0;
+        for id in expired {
+            // An operator may have resolved it since the scan.
+            if self
+                .resolve_credit(id, CreditStatus::Credited, now)
+                .await?
+                .is_some()
+            {
+                released += 1;
+            }
+        }
+        Ok(released)
+    }
+}
+
+fn status_to_db(status: CreditStatus) -> CreditStatusDb {
+    match status {
+        CreditStatus::Credited => CreditStatusDb::Credited,
+        CreditStatus::Held => CreditStatusDb::Held,
+        CreditStatus::Refunded => CreditStatusDb::Refunded,
+    }
+}
+
+fn into_credit(model: credits::Model) -> StorageResult<TokenCredit> {
+    Ok(TokenCredit {
+        id: model.id,
+        token: ServiceToken::try_from(model.token)
+            .map_err(|err| StorageError::Database(err.to_string()))?,
+        pid: PaymentId::try_from(model.pid)
+            .map_err(|err| StorageError::Database(err.to_string()))?,
+        amount: model.amount,
+        status: match model.status {
+            CreditStatusDb::Credited => CreditStatus::Credited,
+            CreditStatusDb::Held => CreditStatus::Held,
+            CreditStatusDb::Refunded => CreditStatus::Refunded,
+        },
+        created_at: model.created_at,
+        hold_until: model.hold_until,
+        resolved_at: model.resolved_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::NewServiceToken;
+    use anon_ticket_domain::storage::TokenStore;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn held_credits_are_refunded_or_released_to_the_token() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+        let mut ids = Vec::new();
+        for (seed, hold_until) in [
+            (1u8, now - Duration::minutes(1)),
+            (2u8, now + Duration::hours(1)),
+        ] {
+            let token = ServiceToken::from_bytes([seed; 32]);
+            let pid = PaymentId::try_from(vec![seed; 8]).unwrap();
+            storage
+                .insert_token(NewServiceToken {
+                    token: token.clone(),
+                    pid: pid.clone(),
+                    amount: 1_000,
+                    issued_at: now,
+                    abuse_score: 0,
+                    expires_at: None,
+                    tier: None,
+                })
+                .await
+                .unwrap();
+            let credit = NewTokenCredit {
+                token,
+                pid,
+                amount: 250,
+                status: CreditStatus::Held,
+                created_at: now,
+                hold_until: Some(hold_until),
+            };
+            let recorded = storage.record_credit(credit.clone()).await.unwrap();
+            ids.push(recorded.expect("first credit recorded").id);
+            assert_eq!(storage.record_credit(credit).await.unwrap(), None);
+        }
+        let balance = |seed: u8| {
+            let storage = &storage;
+            async move {
+                storage
+                    .find_token(&ServiceToken::from_bytes([seed; 32]))
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .amount
+            }
+        };
+
+        assert_eq!(storage.release_expired_holds(now).await.unwrap(), 1);
+        assert_eq!(balance(1).await, 1_250);
+        let released = storage.find_credit(ids[0]).await.unwrap().unwrap();
+        assert_eq!(released.status, CreditStatus::Credited);
+        assert_eq!(released.resolved_at, Some(now));
+
+        let refunded = storage
+            .resolve_credit(ids[1], CreditStatus::Refunded, now)
+            .await
+            .unwrap()
+            .expect("held credit resolves");
+        assert_eq!(refunded.status, CreditStatus::Refunded);
+        assert_eq!(balance(2).await, 1_000);
+        assert_eq!(
+            storage
+                .resolve_credit(ids[1], CreditStatus::Credited, now)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let held = storage
+            .list_credits(Some(CreditStatus::Held), None, 10)
+            .await
+            .unwrap();
+        assert!(held.is_empty());
+        let all = storage.list_credits(None, None, 10).await.unwrap();
+        assert_eq!(
+            all.iter().map(|credit| credit.id).collect::<Vec<_>>(),
+            vec![ids[1], ids[0]]
+        );
+    }
+}
@@ -14,15 +14,23 @@ pub mod payments {
         #[sea_orm(default_expr = "Expr::current_timestamp()")]
         pub created_at: DateTimeUtc,
         pub claimed_at: Option<DateTimeUtc>,
+        pub address_id: Option<String>,
     }
 
+    /// The stored [`PaymentState`](anon_ticket_domain::model::PaymentState)s.
     #[derive(Copy, Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
     #[sea_orm(rs_type = "i8", db_type = "TinyInteger")]
     pub enum PaymentStatusDb {
         #[sea_orm(num_value = 0)]
-        Unclaimed,
+        Confirmed,
         #[sea_orm(num_value = 1)]
         Claimed,
+        #[sea_orm(num_value = 2)]
+        Expired,
+        #[sea_orm(num_value = 3)]
+        Reorged,
+        #[sea_orm(num_value = 4)]
+        Refunded,
     }
 
     #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
@@ -48,6 +56,11 @@ pub mod service_tokens {
         pub revoke_reason: Option<String>,
         #[sea_orm(default_value = 0)]
         pub abuse_score: i16,
+        pub expires_at: Option<DateTimeUtc>,
+        pub tier: Option<String>,
+        pub suspended_at: Option<DateTimeUtc>,
+        pub suspend_reason: Option<String>,
+        pub suspended_until: Option<DateTimeUtc>,
     }
 
     #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
@@ -72,3 +85,473 @@ pub mod monitor_state {
 
     impl ActiveModelBehavior for ActiveModel {}
 }
+
+pub mod tombstones {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "tombstones")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id_hash: Vec<u8>,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub kind: TombstoneKindDb,
+        pub deleted_at: DateTimeUtc,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+    #[sea_orm(rs_type = "i8", db_type = "TinyInteger")]
+    pub enum TombstoneKindDb {
+        #[sea_orm(num_value = 0)]
+        Payment,
+        #[sea_orm(num_value = 1)]
+        Token,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod checkout_bindings {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "checkout_bindings")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub pid: Vec<u8>,
+        pub secret_hash: Vec<u8>,
+        pub created_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod checkout_terms {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "checkout_terms")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub pid: Vec<u8>,
+        pub preset: String,
+        pub expected_amount: i64,
+        pub expires_at: Option<DateTimeUtc>,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod payment_quotes {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "payment_quotes")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub pid: Vec<u8>,
+        pub expected_amount: i64,
+        pub created_at: DateTimeUtc,
+        pub expires_at: DateTimeUtc,
+        pub status: QuoteStatusDb,
+        pub received_amount: Option<i64>,
+        pub address_id: Option<String>,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+    #[sea_orm(rs_type = "i8", db_type = "TinyInteger")]
+    pub enum QuoteStatusDb {
+        #[sea_orm(num_value = 0)]
+        Open,
+        #[sea_orm(num_value = 1)]
+        Paid,
+        #[sea_orm(num_value = 2)]
+        Expired,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod payment_renewals {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "payment_renewals")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub txid: String,
+        pub pid: Vec<u8>,
+        pub amount: i64,
+        pub block_height: i64,
+        pub detected_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod payment_transfers {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "payment_transfers")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub txid: String,
+        pub pid: Vec<u8>,
+        pub amount: i64,
+        pub block_height: i64,
+        pub detected_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod pending_payments {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "pending_payments")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub txid: String,
+        pub pid: Vec<u8>,
+        pub amount: i64,
+        pub block_height: Option<i64>,
+        pub confirmations: i64,
+        pub first_seen_at: DateTimeUtc,
+        pub last_seen_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod skipped_transfers {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "skipped_transfers")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub txid: String,
+        pub wallet: String,
+        pub reason: String,
+        pub payment_id: Option<String>,
+        /// Decimal text: the reported amount may not fit a `BIGINT`.
+        pub amount: String,
+        pub block_height: Option<i64>,
+        pub timestamp: i64,
+        pub skipped_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod subaddresses {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "subaddresses")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub pid: Vec<u8>,
+        pub account_index: i64,
+        pub minor_index: i64,
+        pub address: String,
+        pub created_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod token_expiries {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "token_expiries")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub token: Vec<u8>,
+        pub expires_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod token_validations {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "token_validations")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub token: Vec<u8>,
+        pub first_validated_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod token_reviews {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "token_reviews")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub token: Vec<u8>,
+        pub reason: String,
+        pub flagged_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod monitor_checkpoints {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "monitor_checkpoints")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub height: i64,
+        pub block_hash: Option<String>,
+        pub tick_time: DateTimeUtc,
+        pub batch_size: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod webhook_events {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "webhook_events")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: String,
+        pub txid: String,
+        pub pid: Vec<u8>,
+        pub payload: String,
+        pub created_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod webhook_deliveries {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "webhook_deliveries")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub event_id: String,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub url: String,
+        pub status: DeliveryStatusDb,
+        pub attempts: i32,
+        pub last_error: Option<String>,
+        pub last_attempt_at: Option<DateTimeUtc>,
+        pub delivered_at: Option<DateTimeUtc>,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+    #[sea_orm(rs_type = "i8", db_type = "TinyInteger")]
+    pub enum DeliveryStatusDb {
+        #[sea_orm(num_value = 0)]
+        Pending,
+        #[sea_orm(num_value = 1)]
+        Delivered,
+        #[sea_orm(num_value = 2)]
+        Failed,
+        #[sea_orm(num_value = 3)]
+        Requested,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod webhook_dead_letters {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "webhook_dead_letters")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub event_id: String,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub url: String,
+        pub pid: Vec<u8>,
+        pub payload: String,
+        pub attempts: i32,
+        pub last_error: String,
+        pub failed_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod idempotency_keys {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "idempotency_keys")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub key_hash: Vec<u8>,
+        pub request_hash: Vec<u8>,
+        pub pid: Vec<u8>,
+        pub status: i32,
+        pub body: String,
+        pub created_at: DateTimeUtc,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod audit_events {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "audit_events")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: i64,
+        pub occurred_at: DateTimeUtc,
+        pub actor: String,
+        pub action: String,
+        pub subject_hash: Vec<u8>,
+        pub outcome: String,
+        pub reason: Option<String>,
+        pub operator: Option<String>,
+        pub key_id: Option<String>,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod credits {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "credits")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: i64,
+        pub token: Vec<u8>,
+        pub pid: Vec<u8>,
+        pub amount: i64,
+        pub status: CreditStatusDb,
+        pub created_at: DateTimeUtc,
+        pub hold_until: Option<DateTimeUtc>,
+        pub resolved_at: Option<DateTimeUtc>,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+    #[sea_orm(rs_type = "i8", db_type = "TinyInteger")]
+    pub enum CreditStatusDb {
+        #[sea_orm(num_value = 0)]
+        Credited,
+        #[sea_orm(num_value = 1)]
+        Held,
+        #[sea_orm(num_value = 2)]
+        Refunded,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod refunds {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "refunds")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: i64,
+        pub pid: Vec<u8>,
+        pub address: String,
+        pub amount: i64,
+        pub status: RefundStatusDb,
+        pub requested_at: DateTimeUtc,
+        pub resolved_at: Option<DateTimeUtc>,
+        pub txid: Option<String>,
+        pub error: Option<String>,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+    #[sea_orm(rs_type = "i8", db_type = "TinyInteger")]
+    pub enum RefundStatusDb {
+        #[sea_orm(num_value = 0)]
+        Pending,
+        #[sea_orm(num_value = 1)]
+        Sending,
+        #[sea_orm(num_value = 2)]
+        Sent,
+        #[sea_orm(num_value = 3)]
+        Failed,
+    }
+
+    #[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
@@ -0,0 +1,167 @@
+use anon_ticket_domain::model::{IdempotentResponse, PaymentId};
+use anon_ticket_domain::storage::{IdempotencyStore, StorageResult};
+use chrono::{DateTime, Utc};
+use sea_orm::{sea_query::OnConflict, ColumnTrait, EntityTrait, QueryFilter, Set};
+
+use crate::entity::idempotency_keys;
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl IdempotencyStore for SeaOrmStorage {
+    async fn insert_idempotent_response(
+        &self,
+        response: IdempotentResponse,
+    ) -> StorageResult<bool> {
+        self.ensure_writable()?;
+        let inserted = idempotency_keys::Entity::insert(idempotency_keys::ActiveModel {
+            key_hash: Set(response.key_hash.to_vec()),
+            request_hash: Set(response.request_hash.to_vec()),
+            pid: Set(response.pid.as_bytes().to_vec()),
+            status: Set(i32::from(response.status)),
+            body: Set(response.body),
+            created_at: Set(response.created_at),
+        })
+        .on_conflict(
+            OnConflict::column(idempotency_keys::Column::KeyHash)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        Ok(inserted > 0)
+    }
+
+    async fn find_idempotent_response(
+        &self,
+        key_hash: &[u8; 32],
+    ) -> StorageResult<Option<IdempotentResponse>> {
+        idempotency_keys::Entity::find_by_id(key_hash.to_vec())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .map(into_response)
+            .transpose()
+    }
+
+    async fn prune_idempotent_responses(&self, before: DateTime<Utc>) -> StorageResult<u64> {
+        self.ensure_writable()?;
+        let result = idempotency_keys::Entity::delete_many()
+            .filter(idempotency_keys::Column::CreatedAt.lt(before))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(result.rows_affected)
+    }
+}
+
+fn into_response(model: idempotency_keys::Model) -> StorageResult<IdempotentResponse> {
+    let hash = |bytes: Vec<u8>| -> StorageResult<[u8; 32]> {
+        bytes
+            .try_into()
+            .map_err(|_| StorageError::Database("idempotency hash must be 32 bytes".into()))
+    };
+    Ok(IdempotentResponse {
+        key_hash: hash(model.key_hash)?,
+        request_hash: hash(model.request_hash)?,
+        pid: PaymentId::try_from(model.pid)
+            .map_err(|err| StorageError::Database(err.to_string()))?,
+        status: u16::try_from(model.status)
+            .map_err(|_| StorageError::Database("idempotent status out of range".into()))?,
+        body: model.body,
+        created_at: model.created_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::{NewPayment, NewServiceToken, ServiceToken};
+    use anon_ticket_domain::storage::{PaymentStore, TokenStore, TombstoneStore};
+    use chrono::Duration;
+
+    const PID: &str = "0123456789abcdef";
+
+    fn response(key: u8, body: &str, created_at: DateTime<Utc>) -> IdempotentResponse {
+        IdempotentResponse {
+            key_hash: [key; 32],
+            request_hash: [1; 32],
+            pid: PaymentId::parse(PID).unwrap(),
+            status: 200,
+            body: body.to_string(),
+            created_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn first_response_wins_and_prune_expires_it() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+        assert!(storage
+            .insert_idempotent_response(response(7, "first", now))
+            .await
+            .unwrap());
+        assert!(!storage
+            .insert_idempotent_response(response(7, "second", now))
+            .await
+            .unwrap());
+        let stored = storage
+            .find_idempotent_response(&[7; 32])
+            .await
+            .unwrap()
+            .expect("recorded");
+        assert_eq!(stored, response(7, "first", stored.created_at));
+        assert!(storage
+            .find_idempotent_response(&[8; 32])
+            .await
+            .unwrap()
+            .is_none());
+
+        let past = now - Duration::hours(1);
+        assert_eq!(storage.prune_idempotent_responses(past).await.unwrap(), 0);
+        let future = now + Duration::hours(1);
+        assert_eq!(storage.prune_idempotent_responses(future).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn purging_the_token_drops_its_responses() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let pid = PaymentId::parse(PID).unwrap();
+        let token = ServiceToken::from_bytes([9u8; 32]);
+        storage
+            .insert_payment(NewPayment {
+                pid: pid.clone(),
+                txid: "tx".into(),
+                amount: 1,
+                block_height: 1,
+                detected_at: Utc::now(),
+                address_id: None,
+            })
+            .await
+            .unwrap();
+        storage
+            .insert_token(NewServiceToken {
+                token: token.clone(),
+                pid,
+                amount: 1,
+                issued_at: Utc::now(),
+                abuse_score: 0,
+                expires_at: None,
+                tier: None,
+            })
+            .await
+            .unwrap();
+        storage
+            .insert_idempotent_response(response(7, "{}", Utc::now()))
+            .await
+            .unwrap();
+
+        assert!(storage.purge_token(&token).await.unwrap());
+        assert!(storage
+            .find_idempotent_response(&[7; 32])
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
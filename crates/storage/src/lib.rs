@@ -2,37 +2,102 @@
 //! keeping the database backend swappable (SQLite by default, PostgreSQL via
 //! feature flag).
 
+/// Version of this crate, reported in the API's build info.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+mod anonymize;
+mod audit_store;
 mod builder;
+mod changefeed;
+mod checkout_store;
+mod credit_store;
 mod entity;
 mod errors;
+mod idempotency_store;
 mod migration;
 mod monitor_state_store;
 mod payment_store;
+mod pending_store;
+mod quote_store;
+mod refund_store;
+mod renewal_store;
+mod replica;
+mod schema_drift;
+mod skipped_store;
+mod subaddress_store;
 mod token_store;
+mod tombstone_store;
+mod webhook_store;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use anon_ticket_domain::model::PaymentId;
 use anon_ticket_domain::storage::StorageResult;
-use builder::StorageBuilder;
 use errors::StorageError;
-use migration::run_migrations;
+use replica::ReadReplica;
+
+pub use anonymize::AnonymizeReport;
+pub use builder::StorageBuilder;
+pub use changefeed::{Change, ChangeListener, CHANGES_CHANNEL};
+pub use migration::schema_fingerprint;
+pub use replica::DEFAULT_HEDGE_AFTER;
 use sea_orm::{ConnectionTrait, Database, DatabaseBackend, DatabaseConnection, Statement};
 
+/// Connections of a pool at one instant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolUsage {
+    /// Open connections, idle or in use.
+    pub open: u32,
+    pub idle: u32,
+    /// `DATABASE_MAX_CONNECTIONS`, or the driver's default.
+    pub max: u32,
+}
+
+impl PoolUsage {
+    pub fn in_use(&self) -> u32 {
+        self.open.saturating_sub(self.idle)
+    }
+}
+
 /// Shared storage handle used by the HTTP API and monitor services.
 #[derive(Clone)]
 pub struct SeaOrmStorage {
     db: Arc<DatabaseConnection>,
+    replica: Option<Arc<ReadReplica>>,
+    read_only: bool,
 }
 
 impl SeaOrmStorage {
     /// Connects to the provided database URL and ensures the schema is present.
     pub async fn connect(database_url: &str) -> StorageResult<Self> {
-        let db = Database::connect(database_url)
+        StorageBuilder::new()
+            .database_url(database_url)
+            .build()
+            .await
+    }
+
+    /// Routes [`find_payment`](anon_ticket_domain::storage::PaymentStore::find_payment)
+    /// through `replica_url` first, hedging with the primary after
+    /// `hedge_after`. The replica is never migrated or written to.
+    pub async fn with_read_replica(
+        self,
+        replica_url: &str,
+        hedge_after: Duration,
+    ) -> StorageResult<Self> {
+        let db = Database::connect(replica_url)
             .await
             .map_err(StorageError::from_source)?;
-        prepare_connection(&db).await?;
-        Ok(Self { db: Arc::new(db) })
+        Ok(self.with_replica_connection(db, hedge_after))
+    }
+
+    pub(crate) fn with_replica_connection(
+        mut self,
+        db: DatabaseConnection,
+        hedge_after: Duration,
+    ) -> Self {
+        self.replica = Some(Arc::new(ReadReplica { db, hedge_after }));
+        self
     }
 
     pub fn builder() -> StorageBuilder {
@@ -40,13 +105,104 @@ impl SeaOrmStorage {
     }
 
     pub(crate) fn from_connection(db: DatabaseConnection) -> Self {
-        Self { db: Arc::new(db) }
+        Self {
+            db: Arc::new(db),
+            replica: None,
+            read_only: false,
+        }
+    }
+
+    /// Makes every mutating trait method fail with [`StorageError::ReadOnly`]
+    /// before touching the database. Lookups are unaffected.
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub(crate) fn ensure_writable(&self) -> StorageResult<()> {
+        if self.read_only {
+            Err(StorageError::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn replica(&self) -> Option<&ReadReplica> {
+        self.replica.as_deref()
     }
 
     pub fn connection(&self) -> &DatabaseConnection {
         self.db.as_ref()
     }
 
+    /// `sqlite`, `postgres` or `mysql`, as reported at startup.
+    pub fn backend_name(&self) -> &'static str {
+        match self.connection().get_database_backend() {
+            DatabaseBackend::Sqlite => "sqlite",
+            DatabaseBackend::Postgres => "postgres",
+            DatabaseBackend::MySql => "mysql",
+        }
+    }
+
+    /// Usage of the primary's pool; `None` for a backend this build cannot
+    /// inspect.
+    pub fn pool_usage(&self) -> Option<PoolUsage> {
+        let db = self.connection();
+        match db.get_database_backend() {
+            #[cfg(feature = "sqlite")]
+            DatabaseBackend::Sqlite => {
+                let pool = db.get_sqlite_connection_pool();
+                Some(PoolUsage {
+                    open: pool.size(),
+                    idle: pool.num_idle() as u32,
+                    max: pool.options().get_max_connections(),
+                })
+            }
+            #[cfg(feature = "postgres")]
+            DatabaseBackend::Postgres => {
+                let pool = db.get_postgres_connection_pool();
+                Some(PoolUsage {
+                    open: pool.size(),
+                    idle: pool.num_idle() as u32,
+                    max: pool.options().get_max_connections(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Round-trips to the primary database; readiness probes use it.
+    pub async fn ping(&self) -> StorageResult<()> {
+        self.connection()
+            .ping()
+            .await
+            .map_err(StorageError::from_source)
+    }
+
+    /// Closes the connection pools. Returns `false` without closing when other
+    /// clones of this handle are still alive; their pools then close on drop.
+    pub async fn close(self) -> StorageResult<bool> {
+        let Ok(db) = Arc::try_unwrap(self.db) else {
+            return Ok(false);
+        };
+        if let Some(replica) = self
+            .replica
+            .and_then(|replica| Arc::try_unwrap(replica).ok())
+        {
+            replica
+                .db
+                .close()
+                .await
+                .map_err(StorageError::from_source)?;
+        }
+        db.close().await.map_err(StorageError::from_source)?;
+        Ok(true)
+    }
+
     /// Returns all persisted payment IDs. Intended for boot-time Bloom/cache
     /// prewarming; callers should be prepared for the memory cost of loading
     /// the full set.
@@ -67,14 +223,69 @@ impl SeaOrmStorage {
             .collect::<Result<Vec<_>, _>>()
             .map_err(|err| StorageError::Database(err.to_string()))
     }
-}
 
-pub(crate) async fn prepare_connection(db: &DatabaseConnection) -> StorageResult<()> {
-    if db.get_database_backend() == DatabaseBackend::Sqlite {
-        configure_sqlite(db).await?;
+    /// Payment IDs with their block height, optionally only those at or
+    /// above `min_height`. Used to catch a restored Bloom snapshot up.
+    pub async fn payment_heights(
+        &self,
+        min_height: Option<i64>,
+    ) -> StorageResult<Vec<(PaymentId, i64)>> {
+        use crate::entity::payments;
+        use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+
+        let mut query = payments::Entity::find()
+            .select_only()
+            .column(payments::Column::Pid)
+            .column(payments::Column::BlockHeight);
+        if let Some(height) = min_height {
+            query = query.filter(payments::Column::BlockHeight.gte(height));
+        }
+        let raw: Vec<(Vec<u8>, i64)> = query
+            .into_tuple()
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+
+        raw.into_iter()
+            .map(|(pid, height)| PaymentId::try_from(pid).map(|pid| (pid, height)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| StorageError::Database(err.to_string()))
+    }
+
+    /// Number of payments below `height`, to check that nothing was added
+    /// under a Bloom snapshot's watermark since it was taken.
+    pub async fn count_payments_below(&self, height: i64) -> StorageResult<u64> {
+        use crate::entity::payments;
+        use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+
+        payments::Entity::find()
+            .filter(payments::Column::BlockHeight.lt(height))
+            .count(self.connection())
+            .await
+            .map_err(StorageError::from_source)
     }
 
-    run_migrations(db).await
+    /// Most recently detected payment IDs, newest first. Used by the
+    /// cache/Bloom consistency audit.
+    pub async fn recent_payment_ids(&self, limit: u64) -> StorageResult<Vec<PaymentId>> {
+        use crate::entity::payments;
+        use sea_orm::{EntityTrait, QueryOrder, QuerySelect};
+
+        let raw: Vec<Vec<u8>> = payments::Entity::find()
+            .select_only()
+            .column(payments::Column::Pid)
+            .order_by_desc(payments::Column::CreatedAt)
+            .limit(limit)
+            .into_tuple()
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+
+        raw.into_iter()
+            .map(PaymentId::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| StorageError::Database(err.to_string()))
+    }
 }
 
 pub(crate) async fn configure_sqlite(db: &DatabaseConnection) -> StorageResult<()> {
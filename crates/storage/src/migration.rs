@@ -1,12 +1,156 @@
-use sea_orm::sea_query::{ColumnDef, Expr, Table, TableCreateStatement};
-use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection};
+use sea_orm::sea_query::{
+    Alias, ColumnDef, ColumnSpec, Expr, Index, IndexCreateStatement, Table, TableCreateStatement,
+};
+use std::time::Duration;
 
-use crate::entity::{monitor_state, payments, service_tokens};
+use chrono::Utc;
+use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection, Statement, TransactionTrait};
+use tracing::info;
+
+use crate::entity::{
+    audit_events, checkout_bindings, checkout_terms, credits, idempotency_keys,
+    monitor_checkpoints, monitor_state, payment_quotes, payment_renewals, payment_transfers,
+    payments, pending_payments, refunds, service_tokens, skipped_transfers, subaddresses,
+    token_expiries, token_reviews, token_validations, tombstones, webhook_dead_letters,
+    webhook_deliveries, webhook_events,
+};
+use crate::errors::StorageError;
+use crate::schema_drift::{live_columns, table_name};
 use anon_ticket_domain::storage::StorageResult;
 
+/// Advisory-lock key shared by every replica ("anontick" in ASCII).
+const MIGRATION_LOCK_KEY: i64 = 0x616e_6f6e_7469_636b;
+/// SQLite lock rows older than this are assumed to belong to a crashed peer.
+const SQLITE_LOCK_STALE_SECS: i64 = 60;
+const SQLITE_LOCK_POLL: Duration = Duration::from_millis(200);
+
+/// Creates the schema while holding a cross-process lock, so replicas that
+/// boot together do not race on DDL. Postgres uses a transaction-scoped
+/// advisory lock; SQLite uses a single-row lock table.
 pub async fn run_migrations(db: &DatabaseConnection) -> StorageResult<()> {
+    match db.get_database_backend() {
+        DatabaseBackend::Postgres => migrate_with_advisory_lock(db).await,
+        _ => migrate_with_lock_table(db).await,
+    }
+}
+
+async fn migrate_with_advisory_lock(db: &DatabaseConnection) -> StorageResult<()> {
+    let backend = db.get_database_backend();
+    let txn = db.begin().await.map_err(StorageError::from_source)?;
+    let acquired = txn
+        .query_one(Statement::from_sql_and_values(
+            backend,
+            "SELECT pg_try_advisory_xact_lock($1) AS acquired",
+            [MIGRATION_LOCK_KEY.into()],
+        ))
+        .await
+        .map_err(StorageError::from_source)?
+        .map(|row| row.try_get::<bool>("", "acquired"))
+        .transpose()
+        .map_err(StorageError::from_source)?
+        .unwrap_or(false);
+    if !acquired {
+        info!("waiting for peer migration (advisory lock held)");
+        txn.execute(Statement::from_sql_and_values(
+            backend,
+            "SELECT pg_advisory_xact_lock($1)",
+            [MIGRATION_LOCK_KEY.into()],
+        ))
+        .await
+        .map_err(StorageError::from_source)?;
+    }
+    apply_schema(&txn).await?;
+    // Committing releases the lock.
+    txn.commit().await.map_err(StorageError::from_source)
+}
+
+async fn migrate_with_lock_table(db: &DatabaseConnection) -> StorageResult<()> {
+    let backend = db.get_database_backend();
+    db.execute(Statement::from_string(
+        backend,
+        "CREATE TABLE IF NOT EXISTS schema_migration_lock \
+         (id INTEGER PRIMARY KEY CHECK (id = 1), acquired_at INTEGER NOT NULL)",
+    ))
+    .await
+    .map_err(StorageError::from_source)?;
+
+    let mut waiting = false;
+    loop {
+        let now = Utc::now().timestamp();
+        db.execute(Statement::from_sql_and_values(
+            backend,
+            "DELETE FROM schema_migration_lock WHERE acquired_at < ?",
+            [(now - SQLITE_LOCK_STALE_SECS).into()],
+        ))
+        .await
+        .map_err(StorageError::from_source)?;
+        let inserted = db
+            .execute(Statement::from_sql_and_values(
+                backend,
+                "INSERT OR IGNORE INTO schema_migration_lock (id, acquired_at) VALUES (1, ?)",
+                [now.into()],
+            ))
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected();
+        if inserted == 1 {
+            break;
+        }
+        if !waiting {
+            info!("waiting for peer migration (lock table held)");
+            waiting = true;
+        }
+        tokio::time::sleep(SQLITE_LOCK_POLL).await;
+    }
+
+    let result = apply_schema(db).await;
+    db.execute(Statement::from_string(
+        backend,
+        "DELETE FROM schema_migration_lock WHERE id = 1",
+    ))
+    .await
+    .map_err(StorageError::from_source)?;
+    result
+}
+
+async fn apply_schema<C: ConnectionTrait>(db: &C) -> StorageResult<()> {
     let backend = db.get_database_backend();
+    for table in schema_tables() {
+        add_missing_columns(db, backend, &table).await?;
+        create_table(db, backend, table).await?;
+    }
+    for index in schema_indexes() {
+        create_index(db, backend, index).await?;
+    }
+    Ok(())
+}
+
+/// Short, stable digest of the schema [`run_migrations`] creates. Migrations
+/// are idempotent DDL rather than numbered steps, so this stands in for a
+/// migration version: two builds with the same fingerprint expect the same
+/// tables, columns and indexes.
+pub fn schema_fingerprint() -> String {
+    // FNV-1a over the SQLite rendering, which does not depend on the backend
+    // in use.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let tables = schema_tables()
+        .into_iter()
+        .map(|table| DatabaseBackend::Sqlite.build(&table).to_string());
+    let indexes = schema_indexes()
+        .into_iter()
+        .map(|index| DatabaseBackend::Sqlite.build(&index).to_string());
+    for statement in tables.chain(indexes) {
+        for byte in statement.bytes().chain([b';']) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
 
+/// Tables owned by this crate, as created by [`run_migrations`]. Also the
+/// reference the post-migration drift check compares the live schema against.
+pub(crate) fn schema_tables() -> Vec<TableCreateStatement> {
     let payments_table = Table::create()
         .if_not_exists()
         .table(payments::Entity)
@@ -47,8 +191,12 @@ pub async fn run_migrations(db: &DatabaseConnection) -> StorageResult<()> {
                 .date_time()
                 .null(),
         )
+        .col(
+            ColumnDef::new(payments::Column::AddressId)
+                .string_len(32)
+                .null(),
+        )
         .to_owned();
-    create_table(db, backend, payments_table).await?;
 
     let service_tokens_table = Table::create()
         .if_not_exists()
@@ -91,8 +239,28 @@ pub async fn run_migrations(db: &DatabaseConnection) -> StorageResult<()> {
                 .not_null()
                 .default(0),
         )
+        .col(
+            ColumnDef::new(service_tokens::Column::ExpiresAt)
+                .date_time()
+                .null(),
+        )
+        .col(ColumnDef::new(service_tokens::Column::Tier).string().null())
+        .col(
+            ColumnDef::new(service_tokens::Column::SuspendedAt)
+                .date_time()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(service_tokens::Column::SuspendReason)
+                .string()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(service_tokens::Column::SuspendedUntil)
+                .date_time()
+                .null(),
+        )
         .to_owned();
-    create_table(db, backend, service_tokens_table).await?;
 
     let monitor_table = Table::create()
         .if_not_exists()
@@ -109,19 +277,974 @@ pub async fn run_migrations(db: &DatabaseConnection) -> StorageResult<()> {
                 .not_null(),
         )
         .to_owned();
-    create_table(db, backend, monitor_table).await?;
 
-    Ok(())
-}
+    let tombstones_table = Table::create()
+        .if_not_exists()
+        .table(tombstones::Entity)
+        .col(
+            ColumnDef::new(tombstones::Column::IdHash)
+                .binary_len(32)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(tombstones::Column::Kind)
+                .tiny_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(tombstones::Column::DeletedAt)
+                .date_time()
+                .not_null()
+                .default(Expr::current_timestamp()),
+        )
+        .primary_key(
+            Index::create()
+                .col(tombstones::Column::IdHash)
+                .col(tombstones::Column::Kind),
+        )
+        .to_owned();
 
-async fn create_table(
-    db: &DatabaseConnection,
-    backend: DatabaseBackend,
-    mut statement: TableCreateStatement,
-) -> StorageResult<()> {
-    statement.if_not_exists();
-    db.execute(backend.build(&statement))
-        .await
-        .map_err(crate::errors::StorageError::from_source)?;
-    Ok(())
+    let checkout_table = Table::create()
+        .if_not_exists()
+        .table(checkout_bindings::Entity)
+        .col(
+            ColumnDef::new(checkout_bindings::Column::Pid)
+                .binary_len(8)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(checkout_bindings::Column::SecretHash)
+                .binary_len(32)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(checkout_bindings::Column::CreatedAt)
+                .date_time()
+                .not_null()
+                .default(Expr::current_timestamp()),
+        )
+        .to_owned();
+
+    let checkout_terms_table = Table::create()
+        .if_not_exists()
+        .table(checkout_terms::Entity)
+        .col(
+            ColumnDef::new(checkout_terms::Column::Pid)
+                .binary_len(8)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(checkout_terms::Column::Preset)
+                .string_len(64)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(checkout_terms::Column::ExpectedAmount)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(checkout_terms::Column::ExpiresAt)
+                .date_time()
+                .null(),
+        )
+        .to_owned();
+
+    let quotes_table = Table::create()
+        .if_not_exists()
+        .table(payment_quotes::Entity)
+        .col(
+            ColumnDef::new(payment_quotes::Column::Pid)
+                .binary_len(8)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(payment_quotes::Column::ExpectedAmount)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_quotes::Column::CreatedAt)
+                .date_time()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_quotes::Column::ExpiresAt)
+                .date_time()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_quotes::Column::Status)
+                .tiny_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_quotes::Column::ReceivedAmount)
+                .big_integer()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(payment_quotes::Column::AddressId)
+                .string_len(32)
+                .null(),
+        )
+        .to_owned();
+
+    let renewals_table = Table::create()
+        .if_not_exists()
+        .table(payment_renewals::Entity)
+        .col(
+            ColumnDef::new(payment_renewals::Column::Txid)
+                .string_len(64)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(payment_renewals::Column::Pid)
+                .binary_len(8)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_renewals::Column::Amount)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_renewals::Column::BlockHeight)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_renewals::Column::DetectedAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let transfers_table = Table::create()
+        .if_not_exists()
+        .table(payment_transfers::Entity)
+        .col(
+            ColumnDef::new(payment_transfers::Column::Txid)
+                .string_len(64)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(payment_transfers::Column::Pid)
+                .binary_len(8)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_transfers::Column::Amount)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_transfers::Column::BlockHeight)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(payment_transfers::Column::DetectedAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let pending_table = Table::create()
+        .if_not_exists()
+        .table(pending_payments::Entity)
+        .col(
+            ColumnDef::new(pending_payments::Column::Txid)
+                .string_len(64)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(pending_payments::Column::Pid)
+                .binary_len(8)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(pending_payments::Column::Amount)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(pending_payments::Column::BlockHeight)
+                .big_integer()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(pending_payments::Column::Confirmations)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(pending_payments::Column::FirstSeenAt)
+                .date_time()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(pending_payments::Column::LastSeenAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let skipped_table = Table::create()
+        .if_not_exists()
+        .table(skipped_transfers::Entity)
+        .col(
+            ColumnDef::new(skipped_transfers::Column::Txid)
+                .string_len(64)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(skipped_transfers::Column::Wallet)
+                .string_len(64)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(skipped_transfers::Column::Reason)
+                .string_len(32)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(skipped_transfers::Column::PaymentId)
+                .string_len(64)
+                .null(),
+        )
+        .col(
+            ColumnDef::new(skipped_transfers::Column::Amount)
+                .string_len(32)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(skipped_transfers::Column::BlockHeight)
+                .big_integer()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(skipped_transfers::Column::Timestamp)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(skipped_transfers::Column::SkippedAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let subaddresses_table = Table::create()
+        .if_not_exists()
+        .table(subaddresses::Entity)
+        .col(
+            ColumnDef::new(subaddresses::Column::Pid)
+                .binary_len(8)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(subaddresses::Column::AccountIndex)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(subaddresses::Column::MinorIndex)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(subaddresses::Column::Address)
+                .string_len(128)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(subaddresses::Column::CreatedAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let expiries_table = Table::create()
+        .if_not_exists()
+        .table(token_expiries::Entity)
+        .col(
+            ColumnDef::new(token_expiries::Column::Token)
+                .binary_len(32)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(token_expiries::Column::ExpiresAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let validations_table = Table::create()
+        .if_not_exists()
+        .table(token_validations::Entity)
+        .col(
+            ColumnDef::new(token_validations::Column::Token)
+                .binary_len(32)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(token_validations::Column::FirstValidatedAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let reviews_table = Table::create()
+        .if_not_exists()
+        .table(token_reviews::Entity)
+        .col(
+            ColumnDef::new(token_reviews::Column::Token)
+                .binary_len(32)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(token_reviews::Column::Reason)
+                .string_len(128)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(token_reviews::Column::FlaggedAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let checkpoints_table = Table::create()
+        .if_not_exists()
+        .table(monitor_checkpoints::Entity)
+        .col(
+            ColumnDef::new(monitor_checkpoints::Column::Height)
+                .big_integer()
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(monitor_checkpoints::Column::BlockHash).string_len(64))
+        .col(
+            ColumnDef::new(monitor_checkpoints::Column::TickTime)
+                .date_time()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(monitor_checkpoints::Column::BatchSize)
+                .integer()
+                .not_null(),
+        )
+        .to_owned();
+
+    let dead_letters_table = Table::create()
+        .if_not_exists()
+        .table(webhook_dead_letters::Entity)
+        .col(
+            ColumnDef::new(webhook_dead_letters::Column::EventId)
+                .string_len(64)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_dead_letters::Column::Url)
+                .string_len(2048)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_dead_letters::Column::Pid)
+                .binary_len(8)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_dead_letters::Column::Payload)
+                .text()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_dead_letters::Column::Attempts)
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_dead_letters::Column::LastError)
+                .text()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_dead_letters::Column::FailedAt)
+                .date_time()
+                .not_null(),
+        )
+        .primary_key(
+            Index::create()
+                .col(webhook_dead_letters::Column::EventId)
+                .col(webhook_dead_letters::Column::Url),
+        )
+        .to_owned();
+
+    let webhook_events_table = Table::create()
+        .if_not_exists()
+        .table(webhook_events::Entity)
+        .col(
+            ColumnDef::new(webhook_events::Column::Id)
+                .string_len(26)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(webhook_events::Column::Txid)
+                .string_len(64)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_events::Column::Pid)
+                .binary_len(8)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_events::Column::Payload)
+                .text()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_events::Column::CreatedAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let webhook_deliveries_table = Table::create()
+        .if_not_exists()
+        .table(webhook_deliveries::Entity)
+        .col(
+            ColumnDef::new(webhook_deliveries::Column::EventId)
+                .string_len(26)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_deliveries::Column::Url)
+                .string_len(2048)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_deliveries::Column::Status)
+                .tiny_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_deliveries::Column::Attempts)
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(webhook_deliveries::Column::LastError)
+                .text()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(webhook_deliveries::Column::LastAttemptAt)
+                .date_time()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(webhook_deliveries::Column::DeliveredAt)
+                .date_time()
+                .null(),
+        )
+        .primary_key(
+            Index::create()
+                .col(webhook_deliveries::Column::EventId)
+                .col(webhook_deliveries::Column::Url),
+        )
+        .to_owned();
+
+    let idempotency_table = Table::create()
+        .if_not_exists()
+        .table(idempotency_keys::Entity)
+        .col(
+            ColumnDef::new(idempotency_keys::Column::KeyHash)
+                .binary_len(32)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(idempotency_keys::Column::RequestHash)
+                .binary_len(32)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(idempotency_keys::Column::Pid)
+                .binary_len(8)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(idempotency_keys::Column::Status)
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(idempotency_keys::Column::Body)
+                .text()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(idempotency_keys::Column::CreatedAt)
+                .date_time()
+                .not_null(),
+        )
+        .to_owned();
+
+    let audit_table = Table::create()
+        .if_not_exists()
+        .table(audit_events::Entity)
+        .col(
+            ColumnDef::new(audit_events::Column::Id)
+                .big_integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(audit_events::Column::OccurredAt)
+                .date_time()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(audit_events::Column::Actor)
+                .string_len(16)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(audit_events::Column::Action)
+                .string_len(64)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(audit_events::Column::SubjectHash)
+                .binary_len(32)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(audit_events::Column::Outcome)
+                .string_len(64)
+                .not_null(),
+        )
+        .col(ColumnDef::new(audit_events::Column::Reason).text())
+        .col(ColumnDef::new(audit_events::Column::Operator).text())
+        .col(ColumnDef::new(audit_events::Column::KeyId).text())
+        .to_owned();
+
+    let credits_table = Table::create()
+        .if_not_exists()
+        .table(credits::Entity)
+        .col(
+            ColumnDef::new(credits::Column::Id)
+                .big_integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(credits::Column::Token)
+                .binary_len(32)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(credits::Column::Pid)
+                .binary_len(8)
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(credits::Column::Amount)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(credits::Column::Status)
+                .tiny_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(credits::Column::CreatedAt)
+                .date_time()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(credits::Column::HoldUntil)
+                .date_time()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(credits::Column::ResolvedAt)
+                .date_time()
+                .null(),
+        )
+        .to_owned();
+
+    let refunds_table = Table::create()
+        .if_not_exists()
+        .table(refunds::Entity)
+        .col(
+            ColumnDef::new(refunds::Column::Id)
+                .big_integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(refunds::Column::Pid)
+                .binary_len(8)
+                .not_null(),
+        )
+        .col(ColumnDef::new(refunds::Column::Address).text().not_null())
+        .col(
+            ColumnDef::new(refunds::Column::Amount)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(refunds::Column::Status)
+                .tiny_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(refunds::Column::RequestedAt)
+                .date_time()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(refunds::Column::ResolvedAt)
+                .date_time()
+                .null(),
+        )
+        .col(ColumnDef::new(refunds::Column::Txid).string_len(64).null())
+        .col(ColumnDef::new(refunds::Column::Error).text().null())
+        .to_owned();
+
+    vec![
+        payments_table,
+        service_tokens_table,
+        monitor_table,
+        tombstones_table,
+        checkout_table,
+        checkout_terms_table,
+        quotes_table,
+        renewals_table,
+        transfers_table,
+        pending_table,
+        skipped_table,
+        subaddresses_table,
+        expiries_table,
+        validations_table,
+        reviews_table,
+        checkpoints_table,
+        dead_letters_table,
+        webhook_events_table,
+        webhook_deliveries_table,
+        idempotency_table,
+        audit_table,
+        credits_table,
+        refunds_table,
+    ]
+}
+
+fn schema_indexes() -> Vec<IndexCreateStatement> {
+    vec![
+        Index::create()
+            .if_not_exists()
+            .name("idx_service_tokens_pid")
+            .table(service_tokens::Entity)
+            .col(service_tokens::Column::Pid)
+            .to_owned(),
+        // The token janitor scans by expiry.
+        Index::create()
+            .if_not_exists()
+            .name("idx_service_tokens_expires_at")
+            .table(service_tokens::Entity)
+            .col(service_tokens::Column::ExpiresAt)
+            .to_owned(),
+        // The revocation list scans recent revocations.
+        Index::create()
+            .if_not_exists()
+            .name("idx_service_tokens_revoked_at")
+            .table(service_tokens::Entity)
+            .col(service_tokens::Column::RevokedAt)
+            .to_owned(),
+        Index::create()
+            .if_not_exists()
+            .name("idx_tombstones_deleted_at")
+            .table(tombstones::Entity)
+            .col(tombstones::Column::DeletedAt)
+            .to_owned(),
+        Index::create()
+            .if_not_exists()
+            .name("idx_payment_renewals_pid")
+            .table(payment_renewals::Entity)
+            .col(payment_renewals::Column::Pid)
+            .to_owned(),
+        Index::create()
+            .if_not_exists()
+            .name("idx_payment_transfers_pid")
+            .table(payment_transfers::Entity)
+            .col(payment_transfers::Column::Pid)
+            .to_owned(),
+        Index::create()
+            .if_not_exists()
+            .name("idx_pending_payments_pid")
+            .table(pending_payments::Entity)
+            .col(pending_payments::Column::Pid)
+            .to_owned(),
+        // Pruning scans by the last sighting.
+        Index::create()
+            .if_not_exists()
+            .name("idx_pending_payments_last_seen_at")
+            .table(pending_payments::Entity)
+            .col(pending_payments::Column::LastSeenAt)
+            .to_owned(),
+        // The expiry sweep scans open quotes by deadline.
+        Index::create()
+            .if_not_exists()
+            .name("idx_payment_quotes_status_expires_at")
+            .table(payment_quotes::Entity)
+            .col(payment_quotes::Column::Status)
+            .col(payment_quotes::Column::ExpiresAt)
+            .to_owned(),
+        Index::create()
+            .if_not_exists()
+            .unique()
+            .name("idx_subaddresses_index")
+            .table(subaddresses::Entity)
+            .col(subaddresses::Column::AccountIndex)
+            .col(subaddresses::Column::MinorIndex)
+            .to_owned(),
+        Index::create()
+            .if_not_exists()
+            .name("idx_idempotency_keys_pid")
+            .table(idempotency_keys::Entity)
+            .col(idempotency_keys::Column::Pid)
+            .to_owned(),
+        Index::create()
+            .if_not_exists()
+            .name("idx_idempotency_keys_created_at")
+            .table(idempotency_keys::Entity)
+            .col(idempotency_keys::Column::CreatedAt)
+            .to_owned(),
+        // Forensics start from a PID or token.
+        Index::create()
+            .if_not_exists()
+            .name("idx_audit_events_subject_hash")
+            .table(audit_events::Entity)
+            .col(audit_events::Column::SubjectHash)
+            .to_owned(),
+        // One credit per token; redeem retries must not record it twice.
+        Index::create()
+            .if_not_exists()
+            .unique()
+            .name("idx_credits_token")
+            .table(credits::Entity)
+            .col(credits::Column::Token)
+            .to_owned(),
+        // The hold janitor scans by status and deadline.
+        Index::create()
+            .if_not_exists()
+            .name("idx_credits_status_hold_until")
+            .table(credits::Entity)
+            .col(credits::Column::Status)
+            .col(credits::Column::HoldUntil)
+            .to_owned(),
+        // The amount check sums a payment's refunds; the sender polls by
+        // status.
+        Index::create()
+            .if_not_exists()
+            .name("idx_refunds_pid")
+            .table(refunds::Entity)
+            .col(refunds::Column::Pid)
+            .to_owned(),
+        Index::create()
+            .if_not_exists()
+            .name("idx_refunds_status")
+            .table(refunds::Entity)
+            .col(refunds::Column::Status)
+            .to_owned(),
+        // One event per txid, so re-ingestion reuses the original id.
+        Index::create()
+            .if_not_exists()
+            .unique()
+            .name("idx_webhook_events_txid")
+            .table(webhook_events::Entity)
+            .col(webhook_events::Column::Txid)
+            .to_owned(),
+        Index::create()
+            .if_not_exists()
+            .name("idx_webhook_events_pid")
+            .table(webhook_events::Entity)
+            .col(webhook_events::Column::Pid)
+            .to_owned(),
+        // The dispatcher polls for requested redeliveries.
+        Index::create()
+            .if_not_exists()
+            .name("idx_webhook_deliveries_status")
+            .table(webhook_deliveries::Entity)
+            .col(webhook_deliveries::Column::Status)
+            .to_owned(),
+    ]
+}
+
+async fn create_index<C: ConnectionTrait>(
+    db: &C,
+    backend: DatabaseBackend,
+    statement: IndexCreateStatement,
+) -> StorageResult<()> {
+    db.execute(backend.build(&statement))
+        .await
+        .map_err(StorageError::from_source)?;
+    Ok(())
+}
+
+/// Brings a table created by an older build up to date by adding the
+/// nullable columns it lacks. Anything else, including a missing `NOT NULL`
+/// column, is left to the drift check.
+async fn add_missing_columns<C: ConnectionTrait>(
+    db: &C,
+    backend: DatabaseBackend,
+    table: &TableCreateStatement,
+) -> StorageResult<()> {
+    let name = table_name(table);
+    let live = live_columns(db, backend, &name).await?;
+    if live.is_empty() {
+        return Ok(());
+    }
+    for column in table.get_columns() {
+        let column_name = column.get_column_name();
+        let nullable = !column
+            .get_column_spec()
+            .iter()
+            .any(|spec| matches!(spec, ColumnSpec::NotNull));
+        if !nullable || live.iter().any(|have| have.name == column_name) {
+            continue;
+        }
+        info!(table = %name, column = %column_name, "adding column");
+        let alter = Table::alter()
+            .table(Alias::new(&name))
+            .add_column(&mut column.clone())
+            .to_owned();
+        db.execute(backend.build(&alter))
+            .await
+            .map_err(StorageError::from_source)?;
+    }
+    Ok(())
+}
+
+async fn create_table<C: ConnectionTrait>(
+    db: &C,
+    backend: DatabaseBackend,
+    mut statement: TableCreateStatement,
+) -> StorageResult<()> {
+    statement.if_not_exists();
+    db.execute(backend.build(&statement))
+        .await
+        .map_err(StorageError::from_source)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeaOrmStorage;
+
+    #[tokio::test]
+    async fn sqlite_migration_waits_for_peer_lock() {
+        let path = std::env::temp_dir().join(format!(
+            "anon-ticket-migration-lock-{}.db",
+            std::process::id()
+        ));
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let holder = SeaOrmStorage::connect(&url).await.expect("first replica");
+        let db = holder.connection();
+        db.execute(Statement::from_sql_and_values(
+            DatabaseBackend::Sqlite,
+            "INSERT INTO schema_migration_lock (id, acquired_at) VALUES (1, ?)",
+            [Utc::now().timestamp().into()],
+        ))
+        .await
+        .unwrap();
+
+        let peer_url = url.clone();
+        let peer = tokio::spawn(async move { SeaOrmStorage::connect(&peer_url).await });
+        tokio::time::sleep(SQLITE_LOCK_POLL * 3).await;
+        assert!(!peer.is_finished(), "peer must wait for the lock");
+
+        db.execute(Statement::from_string(
+            DatabaseBackend::Sqlite,
+            "DELETE FROM schema_migration_lock",
+        ))
+        .await
+        .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), peer)
+            .await
+            .expect("peer proceeds once the lock is released")
+            .unwrap()
+            .expect("peer migrates");
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[tokio::test]
+    async fn nullable_columns_are_added_to_older_tables() {
+        let path = std::env::temp_dir().join(format!(
+            "anon-ticket-migration-columns-{}.db",
+            std::process::id()
+        ));
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let raw = sea_orm::Database::connect(&url).await.unwrap();
+        // `service_tokens` as created before tokens could expire.
+        raw.execute(Statement::from_string(
+            DatabaseBackend::Sqlite,
+            "CREATE TABLE service_tokens (token binary(32) NOT NULL PRIMARY KEY, \
+             pid binary(8) NOT NULL, amount bigint NOT NULL, \
+             issued_at text NOT NULL DEFAULT CURRENT_TIMESTAMP, \
+             revoked_at text, revoke_reason text, \
+             abuse_score integer NOT NULL DEFAULT 0)",
+        ))
+        .await
+        .unwrap();
+        raw.close().await.unwrap();
+
+        let storage = SeaOrmStorage::connect(&url)
+            .await
+            .expect("older table is upgraded in place");
+        let columns = crate::schema_drift::live_columns(
+            storage.connection(),
+            DatabaseBackend::Sqlite,
+            "service_tokens",
+        )
+        .await
+        .unwrap();
+        assert!(columns.iter().any(|col| col.name == "expires_at"));
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[test]
+    fn schema_fingerprint_is_stable() {
+        let fingerprint = schema_fingerprint();
+        assert_eq!(fingerprint.len(), 16);
+        assert!(fingerprint.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(fingerprint, schema_fingerprint());
+    }
 }
@@ -1,36 +1,570 @@
+use anon_ticket_domain::config::PRIMARY_WALLET;
+use anon_ticket_domain::model::{
+    MonitorCheckpoint, PaymentState, PaymentTransition, ReorgRollback,
+};
 use anon_ticket_domain::storage::{MonitorStateStore, StorageResult};
-use sea_orm::{sea_query::OnConflict, EntityTrait, Set};
+use chrono::Utc;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ColumnTrait, Condition, DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    Set,
+};
 
-use crate::entity::monitor_state;
+use crate::entity::payments::PaymentStatusDb;
+use crate::entity::{
+    monitor_checkpoints, monitor_state, payment_renewals, payment_transfers, payments,
+    service_tokens, token_reviews,
+};
 use crate::errors::StorageError;
+use crate::payment_store::{state_from_db, transition_stored};
 use crate::SeaOrmStorage;
 
 const LAST_HEIGHT_KEY: &str = "last_processed_height";
+/// Checkpoints kept by `record_checkpoint` (about a day of blocks); older ones
+/// are pruned.
+const RETAINED_CHECKPOINTS: u64 = 720;
+
+/// The primary wallet keeps the key it had before other wallets could be
+/// polled; the rest are suffixed with their name.
+fn cursor_key(wallet: &str) -> String {
+    if wallet == PRIMARY_WALLET {
+        LAST_HEIGHT_KEY.to_string()
+    } else {
+        format!("{LAST_HEIGHT_KEY}:{wallet}")
+    }
+}
 
 #[async_trait::async_trait]
 impl MonitorStateStore for SeaOrmStorage {
-    async fn last_processed_height(&self) -> StorageResult<Option<u64>> {
-        let maybe = monitor_state::Entity::find_by_id(LAST_HEIGHT_KEY.to_string())
+    async fn last_processed_height(&self, wallet: &str) -> StorageResult<Option<u64>> {
+        let maybe = monitor_state::Entity::find_by_id(cursor_key(wallet))
             .one(self.connection())
             .await
             .map_err(StorageError::from_source)?;
         Ok(maybe.map(|model| model.value_int as u64))
     }
 
-    async fn upsert_last_processed_height(&self, height: u64) -> StorageResult<()> {
+    async fn upsert_last_processed_height(&self, wallet: &str, height: u64) -> StorageResult<u64> {
+        self.ensure_writable()?;
         let active = monitor_state::ActiveModel {
-            key: Set(LAST_HEIGHT_KEY.to_string()),
+            key: Set(cursor_key(wallet)),
             value_int: Set(height as i64),
         };
         monitor_state::Entity::insert(active)
             .on_conflict(
                 OnConflict::column(monitor_state::Column::Key)
                     .update_column(monitor_state::Column::ValueInt)
+                    .action_and_where(
+                        Expr::col((monitor_state::Entity, monitor_state::Column::ValueInt))
+                            .lt(height as i64),
+                    )
                     .to_owned(),
             )
-            .exec(self.connection())
+            .exec_without_returning(self.connection())
             .await
             .map_err(StorageError::from_source)?;
+        Ok(self.last_processed_height(wallet).await?.unwrap_or(height))
+    }
+
+    async fn record_checkpoint(&self, checkpoint: MonitorCheckpoint) -> StorageResult<()> {
+        self.ensure_writable()?;
+        monitor_checkpoints::Entity::insert(monitor_checkpoints::ActiveModel {
+            height: Set(checkpoint.height as i64),
+            block_hash: Set(checkpoint.block_hash),
+            tick_time: Set(checkpoint.tick_time),
+            batch_size: Set(checkpoint.batch_size as i32),
+        })
+        .on_conflict(
+            OnConflict::column(monitor_checkpoints::Column::Height)
+                .update_columns([
+                    monitor_checkpoints::Column::BlockHash,
+                    monitor_checkpoints::Column::TickTime,
+                    monitor_checkpoints::Column::BatchSize,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+
+        let oldest_kept = monitor_checkpoints::Entity::find()
+            .order_by_desc(monitor_checkpoints::Column::Height)
+            .offset(RETAINED_CHECKPOINTS - 1)
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        if let Some(oldest_kept) = oldest_kept {
+            monitor_checkpoints::Entity::delete_many()
+                .filter(monitor_checkpoints::Column::Height.lt(oldest_kept.height))
+                .exec(self.connection())
+                .await
+                .map_err(StorageError::from_source)?;
+        }
         Ok(())
     }
+
+    async fn recent_checkpoints(&self, limit: u64) -> StorageResult<Vec<MonitorCheckpoint>> {
+        let rows = monitor_checkpoints::Entity::find()
+            .order_by_desc(monitor_checkpoints::Column::Height)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(rows.into_iter().map(checkpoint_from_row).collect())
+    }
+
+    async fn find_checkpoint_at_or_below(
+        &self,
+        height: u64,
+    ) -> StorageResult<Option<MonitorCheckpoint>> {
+        let row = monitor_checkpoints::Entity::find()
+            .filter(monitor_checkpoints::Column::Height.lte(height as i64))
+            .order_by_desc(monitor_checkpoints::Column::Height)
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(row.map(checkpoint_from_row))
+    }
+
+    async fn rollback_to_height(&self, height: u64) -> StorageResult<ReorgRollback> {
+        self.ensure_writable()?;
+        let floor = height as i64;
+        let txn = self.begin().await?;
+
+        monitor_checkpoints::Entity::delete_many()
+            .filter(monitor_checkpoints::Column::Height.gte(floor))
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        let mut report = ReorgRollback {
+            renewals_removed: payment_renewals::Entity::delete_many()
+                .filter(payment_renewals::Column::BlockHeight.gte(floor))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?
+                .rows_affected,
+            ..ReorgRollback::default()
+        };
+        let reason = format!("reorg at height {height}");
+        let flagged_at = Utc::now();
+
+        // Orphaned top-ups come off their payment's amount while it is
+        // unclaimed; once claimed, the token is reviewed instead.
+        let transfers = payment_transfers::Entity::find()
+            .filter(payment_transfers::Column::BlockHeight.gte(floor))
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for transfer in transfers {
+            let debited = payments::Entity::update_many()
+                .col_expr(
+                    payments::Column::Amount,
+                    Expr::col(payments::Column::Amount).sub(transfer.amount),
+                )
+                .filter(payments::Column::Pid.eq(transfer.pid.clone()))
+                .filter(payments::Column::Status.eq(PaymentStatusDb::Confirmed))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            if debited.rows_affected == 0 {
+                report.tokens_flagged +=
+                    flag_tokens(&txn, transfer.pid.clone(), &reason, flagged_at).await?;
+            }
+            payment_transfers::Entity::delete_by_id(transfer.txid)
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            report.transfers_removed += 1;
+        }
+
+        let orphaned = payments::Entity::find()
+            .filter(payments::Column::BlockHeight.gte(floor))
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        for payment in orphaned {
+            let next = state_from_db(payment.status).apply(PaymentTransition::Reorg);
+            if next == Ok(PaymentState::Detected) {
+                // Its remaining transfers are re-delivered with it when re-mined.
+                report.transfers_removed += payment_transfers::Entity::delete_many()
+                    .filter(payment_transfers::Column::Pid.eq(payment.pid.clone()))
+                    .exec(&txn)
+                    .await
+                    .map_err(StorageError::from_source)?
+                    .rows_affected;
+                payments::Entity::delete_by_id(payment.pid)
+                    .exec(&txn)
+                    .await
+                    .map_err(StorageError::from_source)?;
+                report.payments_removed += 1;
+                continue;
+            }
+            if next.is_ok() {
+                transition_stored(&txn, &payment.pid, PaymentTransition::Reorg).await?;
+                report.payments_reorged += 1;
+            }
+            report.tokens_flagged += flag_tokens(&txn, payment.pid, &reason, flagged_at).await?;
+        }
+
+        // Only lowered, never raised: a wallet still behind the fork has
+        // blocks between its cursor and `height` left to scan.
+        monitor_state::Entity::update_many()
+            .col_expr(monitor_state::Column::ValueInt, Expr::value(floor))
+            .filter(
+                Condition::any()
+                    .add(monitor_state::Column::Key.eq(LAST_HEIGHT_KEY))
+                    .add(monitor_state::Column::Key.starts_with(format!("{LAST_HEIGHT_KEY}:"))),
+            )
+            .filter(monitor_state::Column::ValueInt.gt(floor))
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        txn.commit().await.map_err(StorageError::from_source)?;
+        Ok(report)
+    }
+}
+
+/// Puts every token issued for `pid` under review; it stays usable until
+/// reviewed. Returns how many were newly flagged.
+async fn flag_tokens(
+    txn: &DatabaseTransaction,
+    pid: Vec<u8>,
+    reason: &str,
+    flagged_at: chrono::DateTime<Utc>,
+) -> StorageResult<u64> {
+    let tokens = service_tokens::Entity::find()
+        .filter(service_tokens::Column::Pid.eq(pid))
+        .all(txn)
+        .await
+        .map_err(StorageError::from_source)?;
+    let mut flagged = 0;
+    for token in tokens {
+        flagged += token_reviews::Entity::insert(token_reviews::ActiveModel {
+            token: Set(token.token),
+            reason: Set(reason.to_string()),
+            flagged_at: Set(flagged_at),
+        })
+        .on_conflict(
+            OnConflict::column(token_reviews::Column::Token)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(txn)
+        .await
+        .map_err(StorageError::from_source)?;
+    }
+    Ok(flagged)
+}
+
+impl SeaOrmStorage {
+    /// Moves `wallet`'s cursor back to `height` so the monitor scans again
+    /// from there on its next poll. Unlike `rollback_to_height` nothing is
+    /// removed: transfers already recorded are recognised by txid and
+    /// skipped. A cursor already at or below `height` is left alone. Returns
+    /// the cursor as it was.
+    pub async fn rewind_cursor(&self, wallet: &str, height: u64) -> StorageResult<Option<u64>> {
+        self.ensure_writable()?;
+        let previous = self.last_processed_height(wallet).await?;
+        if previous.is_some_and(|previous| previous <= height) {
+            return Ok(previous);
+        }
+        monitor_state::Entity::insert(monitor_state::ActiveModel {
+            key: Set(cursor_key(wallet)),
+            value_int: Set(height as i64),
+        })
+        .on_conflict(
+            OnConflict::column(monitor_state::Column::Key)
+                .update_column(monitor_state::Column::ValueInt)
+                .to_owned(),
+        )
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        Ok(previous)
+    }
+}
+
+fn checkpoint_from_row(row: monitor_checkpoints::Model) -> MonitorCheckpoint {
+    MonitorCheckpoint {
+        height: row.height as u64,
+        block_hash: row.block_hash,
+        tick_time: row.tick_time,
+        batch_size: row.batch_size as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::{derive_service_token, NewPayment, NewServiceToken, PaymentId};
+    use anon_ticket_domain::storage::{PaymentStore, TokenStore};
+
+    fn payment(pid: &str, txid: &str, block_height: i64) -> NewPayment {
+        NewPayment {
+            pid: PaymentId::parse(pid).unwrap(),
+            txid: txid.repeat(32),
+            amount: 100,
+            block_height,
+            detected_at: Utc::now(),
+            address_id: None,
+        }
+    }
+
+    fn checkpoint(height: u64) -> MonitorCheckpoint {
+        MonitorCheckpoint {
+            height,
+            block_hash: Some(format!("{height:064x}")),
+            tick_time: Utc::now(),
+            batch_size: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn rollback_removes_unclaimed_and_flags_claimed_payments() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let kept = payment("1111111111111111", "aa", 90);
+        let orphaned = payment("2222222222222222", "bb", 105);
+        let claimed = payment("3333333333333333", "cc", 110);
+        for payment in [&kept, &orphaned, &claimed] {
+            storage.insert_payment(payment.clone()).await.unwrap();
+        }
+        storage.claim_payment(&claimed.pid).await.unwrap().unwrap();
+        let token = derive_service_token(&claimed.pid, &claimed.txid);
+        storage
+            .insert_token(NewServiceToken {
+                token: token.clone(),
+                pid: claimed.pid.clone(),
+                amount: claimed.amount,
+                issued_at: Utc::now(),
+                abuse_score: 0,
+                expires_at: None,
+                tier: None,
+            })
+            .await
+            .unwrap();
+        for height in [95, 100, 110] {
+            storage.record_checkpoint(checkpoint(height)).await.unwrap();
+        }
+        storage
+            .upsert_last_processed_height(PRIMARY_WALLET, 110)
+            .await
+            .unwrap();
+
+        let report = storage.rollback_to_height(100).await.unwrap();
+        assert_eq!(report.payments_removed, 1);
+        assert_eq!(report.payments_reorged, 1);
+        assert_eq!(report.tokens_flagged, 1);
+        assert!(storage.find_payment(&kept.pid).await.unwrap().is_some());
+        assert!(storage.find_payment(&orphaned.pid).await.unwrap().is_none());
+        let reorged = storage.find_payment(&claimed.pid).await.unwrap().unwrap();
+        assert_eq!(reorged.status, PaymentState::Reorged);
+        assert!(storage.claim_payment(&claimed.pid).await.unwrap().is_none());
+        let review = storage.find_token_review(&token).await.unwrap().unwrap();
+        assert_eq!(review.reason, "reorg at height 100");
+        assert_eq!(
+            storage.last_processed_height(PRIMARY_WALLET).await.unwrap(),
+            Some(100)
+        );
+        let heights: Vec<u64> = storage
+            .recent_checkpoints(10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|checkpoint| checkpoint.height)
+            .collect();
+        assert_eq!(heights, vec![95]);
+
+        // Flagging is idempotent across repeated rollbacks.
+        let report = storage.rollback_to_height(100).await.unwrap();
+        assert_eq!(report.tokens_flagged, 0);
+        assert_eq!(report.payments_reorged, 0);
+
+        // Re-mined, the payment is claimed again.
+        storage.insert_payment(claimed.clone()).await.unwrap();
+        let remined = storage.find_payment(&claimed.pid).await.unwrap().unwrap();
+        assert_eq!(remined.status, PaymentState::Claimed);
+    }
+
+    #[tokio::test]
+    async fn rollback_debits_orphaned_top_ups() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let first = payment("1111111111111111", "aa", 90);
+        let kept_top_up = NewPayment {
+            txid: "bb".repeat(32),
+            block_height: 95,
+            ..first.clone()
+        };
+        let orphaned_top_up = NewPayment {
+            txid: "cc".repeat(32),
+            block_height: 105,
+            ..first.clone()
+        };
+        for transfer in [&first, &kept_top_up, &orphaned_top_up] {
+            storage.insert_payment(transfer.clone()).await.unwrap();
+        }
+        assert_eq!(
+            storage
+                .find_payment(&first.pid)
+                .await
+                .unwrap()
+                .unwrap()
+                .amount,
+            300
+        );
+
+        let report = storage.rollback_to_height(100).await.unwrap();
+        assert_eq!(report.transfers_removed, 1);
+        assert_eq!(report.payments_removed, 0);
+        let payment = storage.find_payment(&first.pid).await.unwrap().unwrap();
+        assert_eq!(payment.amount, 200);
+        let transfers = storage.find_transfers(&first.pid).await.unwrap();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].txid, kept_top_up.txid);
+
+        // Re-mined, the top-up is credited again.
+        storage.insert_payment(orphaned_top_up).await.unwrap();
+        let payment = storage.find_payment(&first.pid).await.unwrap().unwrap();
+        assert_eq!(payment.amount, 300);
+    }
+
+    #[tokio::test]
+    async fn cursor_never_moves_backwards() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        assert_eq!(
+            storage
+                .upsert_last_processed_height(PRIMARY_WALLET, 500)
+                .await
+                .unwrap(),
+            500
+        );
+        assert_eq!(
+            storage
+                .upsert_last_processed_height(PRIMARY_WALLET, 520)
+                .await
+                .unwrap(),
+            520
+        );
+        assert_eq!(
+            storage
+                .upsert_last_processed_height(PRIMARY_WALLET, 10)
+                .await
+                .unwrap(),
+            520
+        );
+        assert_eq!(
+            storage.last_processed_height(PRIMARY_WALLET).await.unwrap(),
+            Some(520)
+        );
+
+        // Reorg rollbacks are the one way back.
+        storage.rollback_to_height(480).await.unwrap();
+        assert_eq!(
+            storage.last_processed_height(PRIMARY_WALLET).await.unwrap(),
+            Some(480)
+        );
+    }
+
+    #[tokio::test]
+    async fn wallets_keep_separate_cursors() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        storage
+            .upsert_last_processed_height(PRIMARY_WALLET, 350)
+            .await
+            .unwrap();
+        storage
+            .upsert_last_processed_height("hot", 700)
+            .await
+            .unwrap();
+        storage
+            .upsert_last_processed_height("cold", 300)
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.last_processed_height("hot").await.unwrap(),
+            Some(700)
+        );
+        assert_eq!(storage.last_processed_height("warm").await.unwrap(), None);
+
+        // A reorg rewinds every wallet that got past the fork, and leaves
+        // the ones still behind it, the primary included, where they are.
+        storage.rollback_to_height(400).await.unwrap();
+        assert_eq!(
+            storage.last_processed_height(PRIMARY_WALLET).await.unwrap(),
+            Some(350)
+        );
+        assert_eq!(
+            storage.last_processed_height("hot").await.unwrap(),
+            Some(400)
+        );
+        assert_eq!(
+            storage.last_processed_height("cold").await.unwrap(),
+            Some(300)
+        );
+    }
+
+    #[tokio::test]
+    async fn block_hash_checkpoints_are_bounded() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        for height in 0..RETAINED_CHECKPOINTS + 10 {
+            storage.record_checkpoint(checkpoint(height)).await.unwrap();
+        }
+        let checkpoints = storage.recent_checkpoints(1000).await.unwrap();
+        assert_eq!(checkpoints.len() as u64, RETAINED_CHECKPOINTS);
+        assert_eq!(checkpoints[0].height, RETAINED_CHECKPOINTS + 9);
+        assert_eq!(checkpoints.last().unwrap().height, 10);
+    }
+
+    #[tokio::test]
+    async fn restart_point_is_newest_checkpoint_at_or_below() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        for height in [100, 110, 120] {
+            storage.record_checkpoint(checkpoint(height)).await.unwrap();
+        }
+        let restart = |height| {
+            let storage = &storage;
+            async move {
+                storage
+                    .find_checkpoint_at_or_below(height)
+                    .await
+                    .unwrap()
+                    .map(|checkpoint| checkpoint.height)
+            }
+        };
+        assert_eq!(restart(115).await, Some(110));
+        assert_eq!(restart(120).await, Some(120));
+        assert_eq!(restart(99).await, None);
+    }
+
+    #[tokio::test]
+    async fn rewinding_keeps_ingested_payments() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let kept = payment("1111111111111111", "aa", 105);
+        storage.insert_payment(kept.clone()).await.unwrap();
+        storage
+            .upsert_last_processed_height(PRIMARY_WALLET, 120)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.rewind_cursor(PRIMARY_WALLET, 100).await.unwrap(),
+            Some(120)
+        );
+        assert_eq!(
+            storage.last_processed_height(PRIMARY_WALLET).await.unwrap(),
+            Some(100)
+        );
+        assert!(storage.find_payment(&kept.pid).await.unwrap().is_some());
+
+        // Never moves a cursor forward.
+        storage.rewind_cursor(PRIMARY_WALLET, 110).await.unwrap();
+        assert_eq!(
+            storage.last_processed_height(PRIMARY_WALLET).await.unwrap(),
+            Some(100)
+        );
+        assert_eq!(storage.rewind_cursor("cold", 50).await.unwrap(), None);
+        assert_eq!(
+            storage.last_processed_height("cold").await.unwrap(),
+            Some(50)
+        );
+    }
 }
@@ -1,32 +1,41 @@
 use anon_ticket_domain::model::{
-    ClaimOutcome, NewPayment, PaymentId, PaymentRecord, PaymentStatus,
+    ClaimOutcome, NewPayment, PaymentFilter, PaymentId, PaymentRecord, PaymentState,
+    PaymentTransfer, PaymentTransition,
 };
 use anon_ticket_domain::storage::{PaymentStore, StorageResult};
 use chrono::Utc;
-use sea_orm::sea_query::{PostgresQueryBuilder, Query, SqliteQueryBuilder};
+use sea_orm::sea_query::{Expr, PostgresQueryBuilder, Query, SqliteQueryBuilder};
 use sea_orm::ActiveEnum;
 use sea_orm::{
-    ColumnTrait, ConnectionTrait, DatabaseBackend, EntityTrait, FromQueryResult, QueryFilter, Set,
-    Statement,
+    ColumnTrait, Condition, ConnectionTrait, DatabaseBackend, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, FromQueryResult, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set, Statement,
 };
+use tracing::instrument;
 
+use crate::changefeed::Change;
 use crate::entity::payments::{self, PaymentStatusDb};
+use crate::entity::{payment_renewals, payment_transfers};
 use crate::errors::StorageError;
+use crate::replica::hedged;
 use crate::SeaOrmStorage;
 
 #[async_trait::async_trait]
 impl PaymentStore for SeaOrmStorage {
+    #[instrument(name = "storage.insert_payment", skip_all)]
     async fn insert_payment(&self, payment: NewPayment) -> StorageResult<()> {
+        self.ensure_writable()?;
         let model = payments::ActiveModel {
-            pid: Set(payment.pid.into_bytes().to_vec()),
-            txid: Set(payment.txid),
+            pid: Set(payment.pid.as_bytes().to_vec()),
+            txid: Set(payment.txid.clone()),
             amount: Set(payment.amount),
             block_height: Set(payment.block_height),
-            status: Set(PaymentStatusDb::Unclaimed),
+            status: Set(PaymentStatusDb::Confirmed),
             created_at: Set(payment.detected_at),
+            address_id: Set(payment.address_id.clone()),
             ..Default::default()
         };
-        payments::Entity::insert(model)
+        let inserted = payments::Entity::insert(model)
             .on_conflict(
                 sea_orm::sea_query::OnConflict::column(payments::Column::Pid)
                     .do_nothing()
@@ -35,10 +44,20 @@ impl PaymentStore for SeaOrmStorage {
             .exec_without_returning(self.connection())
             .await
             .map_err(StorageError::from_source)?;
-        Ok(())
+        if inserted == 0 {
+            if !self.add_transfer(&payment).await? {
+                self.record_renewal(&payment).await?;
+            }
+        } else {
+            self.publish_change(Change::PaymentInserted(payment.pid))
+                .await;
+        }
+        self.forget_pending(&payment.txid).await
     }
 
+    #[instrument(name = "storage.claim_payment", skip_all)]
     async fn claim_payment(&self, pid: &PaymentId) -> StorageResult<Option<ClaimOutcome>> {
+        self.ensure_writable()?;
         let now = Utc::now();
         let backend = self.connection().get_database_backend();
 
@@ -50,7 +69,7 @@ impl PaymentStore for SeaOrmStorage {
         );
         query.value(payments::Column::ClaimedAt, now);
         query.and_where(payments::Column::Pid.eq(pid.as_bytes().to_vec()));
-        query.and_where(payments::Column::Status.eq(PaymentStatusDb::Unclaimed));
+        query.and_where(payments::Column::Status.is_in(stored_sources(PaymentTransition::Claim)));
         query.returning_all();
 
         let (sql, values) = match backend {
@@ -80,18 +99,301 @@ impl PaymentStore for SeaOrmStorage {
             txid: updated.txid,
             amount: updated.amount,
             block_height: updated.block_height,
+            detected_at: updated.created_at,
             claimed_at: updated.claimed_at.unwrap_or(now),
         }))
     }
 
+    /// Hedged across the read replica when one is configured; see
+    /// [`crate::replica`]. Use [`SeaOrmStorage::find_payment_primary`] for
+    /// read-after-write checks.
+    #[instrument(name = "storage.find_payment", skip_all)]
     async fn find_payment(&self, pid: &PaymentId) -> StorageResult<Option<PaymentRecord>> {
-        let maybe = payments::Entity::find()
+        match self.replica() {
+            Some(replica) => {
+                hedged(
+                    replica.hedge_after,
+                    find_payment_on(&replica.db, pid),
+                    || find_payment_on(self.connection(), pid),
+                )
+                .await
+            }
+            None => find_payment_on(self.connection(), pid).await,
+        }
+    }
+
+    async fn unclaim_payment(&self, pid: &PaymentId) -> StorageResult<Option<PaymentRecord>> {
+        self.ensure_writable()?;
+        let result = payments::Entity::update_many()
+            .col_expr(
+                payments::Column::Status,
+                Expr::value(PaymentStatusDb::Confirmed.to_value()),
+            )
+            .col_expr(
+                payments::Column::ClaimedAt,
+                Expr::value(Option::<chrono::DateTime<Utc>>::None),
+            )
             .filter(payments::Column::Pid.eq(pid.as_bytes().to_vec()))
-            .one(self.connection())
+            .filter(payments::Column::Status.is_in(stored_sources(PaymentTransition::Unclaim)))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        if result.rows_affected == 0 {
+            return Ok(None);
+        }
+        self.find_payment_primary(pid).await
+    }
+
+    async fn list_payments(
+        &self,
+        filter: &PaymentFilter,
+        after: Option<&PaymentId>,
+        limit: u64,
+    ) -> StorageResult<Vec<PaymentRecord>> {
+        let mut condition = Condition::all()
+            .add_option(filter.status.map(|status| match state_to_db(status) {
+                Some(status) => payments::Column::Status.eq(status),
+                // Never stored, so nothing matches.
+                None => payments::Column::Status.is_null(),
+            }))
+            .add_option(
+                filter
+                    .min_height
+                    .map(|h| payments::Column::BlockHeight.gte(h)),
+            )
+            .add_option(
+                filter
+                    .max_height
+                    .map(|h| payments::Column::BlockHeight.lte(h)),
+            )
+            .add_option(filter.min_amount.map(|a| payments::Column::Amount.gte(a)))
+            .add_option(filter.max_amount.map(|a| payments::Column::Amount.lte(a)));
+        if let Some(after) = after {
+            condition = condition.add(payments::Column::Pid.gt(after.as_bytes().to_vec()));
+        }
+        payments::Entity::find()
+            .filter(condition)
+            .order_by_asc(payments::Column::Pid)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(payment_to_record)
+            .collect()
+    }
+
+    async fn find_transfers(&self, pid: &PaymentId) -> StorageResult<Vec<PaymentTransfer>> {
+        let rows = payment_transfers::Entity::find()
+            .filter(payment_transfers::Column::Pid.eq(pid.as_bytes().to_vec()))
+            .order_by_asc(payment_transfers::Column::DetectedAt)
+            .order_by_asc(payment_transfers::Column::Txid)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(rows
+            .into_iter()
+            .map(|row| PaymentTransfer {
+                pid: pid.clone(),
+                txid: row.txid,
+                amount: row.amount,
+                block_height: row.block_height,
+                detected_at: row.detected_at,
+            })
+            .collect())
+    }
+
+    async fn find_payment_by_txid(&self, txid: &str) -> StorageResult<Option<PaymentRecord>> {
+        let db = self.connection();
+        let first = payments::Entity::find()
+            .filter(payments::Column::Txid.eq(txid))
+            .one(db)
+            .await
+            .map_err(StorageError::from_source)?;
+        if let Some(model) = first {
+            return payment_to_record(model).map(Some);
+        }
+        let later = payment_transfers::Entity::find_by_id(txid.to_string())
+            .one(db)
+            .await
+            .map_err(StorageError::from_source)?
+            .map(|row| row.pid);
+        let pid = match later {
+            Some(pid) => Some(pid),
+            None => payment_renewals::Entity::find_by_id(txid.to_string())
+                .one(db)
+                .await
+                .map_err(StorageError::from_source)?
+                .map(|row| row.pid),
+        };
+        let Some(pid) = pid else {
+            return Ok(None);
+        };
+        let pid =
+            PaymentId::try_from(pid).map_err(|err| StorageError::Database(err.to_string()))?;
+        find_payment_on(db, &pid).await
+    }
+}
+
+impl SeaOrmStorage {
+    /// Adds a further transfer to an unclaimed payment's amount. Returns
+    /// `false` when the payment is missing or claimed, including by a claim
+    /// racing this call, so the caller can keep the transfer as a renewal.
+    /// Re-delivered transfers are recognised by txid and return `true`.
+    async fn add_transfer(&self, payment: &NewPayment) -> StorageResult<bool> {
+        let key = payment.pid.as_bytes().to_vec();
+        let txn = self.begin().await?;
+        let Some(existing) = payments::Entity::find_by_id(key.clone())
+            .one(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+        else {
+            return Ok(false);
+        };
+        if existing.txid == payment.txid {
+            // Re-mined after a reorg that hit the claimed payment.
+            if transition_stored(&txn, &key, PaymentTransition::Confirm)
+                .await?
+                .is_some()
+            {
+                txn.commit().await.map_err(StorageError::from_source)?;
+            }
+            return Ok(true);
+        }
+        if already_credited(&txn, &payment.txid).await? {
+            return Ok(true);
+        }
+        if existing.status != PaymentStatusDb::Confirmed {
+            return Ok(false);
+        }
+        let added = payment_transfers::Entity::insert(payment_transfers::ActiveModel {
+            txid: Set(payment.txid.clone()),
+            pid: Set(key.clone()),
+            amount: Set(payment.amount),
+            block_height: Set(payment.block_height),
+            detected_at: Set(payment.detected_at),
+        })
+        .on_conflict(
+            sea_orm::sea_query::OnConflict::column(payment_transfers::Column::Txid)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await
+        .map_err(StorageError::from_source)?;
+        if added == 0 {
+            return Ok(true);
+        }
+        let credited = payments::Entity::update_many()
+            .col_expr(
+                payments::Column::Amount,
+                Expr::col(payments::Column::Amount).add(payment.amount),
+            )
+            .filter(payments::Column::Pid.eq(key))
+            .filter(payments::Column::Status.eq(PaymentStatusDb::Confirmed))
+            .exec(&txn)
             .await
             .map_err(StorageError::from_source)?;
-        maybe.map(payment_to_record).transpose()
+        if credited.rows_affected == 0 {
+            // Claimed since it was read; dropping `txn` rolls the insert back.
+            return Ok(false);
+        }
+        txn.commit().await.map_err(StorageError::from_source)?;
+        Ok(true)
+    }
+
+    /// Reads a payment from the primary, bypassing any read replica.
+    pub async fn find_payment_primary(
+        &self,
+        pid: &PaymentId,
+    ) -> StorageResult<Option<PaymentRecord>> {
+        find_payment_on(self.connection(), pid).await
+    }
+}
+
+/// Whether `txid` was already credited as a transfer or a renewal, so a
+/// rescan after a claim or an unclaim does not count it twice.
+async fn already_credited(txn: &DatabaseTransaction, txid: &str) -> StorageResult<bool> {
+    let transfers = payment_transfers::Entity::find_by_id(txid.to_string())
+        .count(txn)
+        .await
+        .map_err(StorageError::from_source)?;
+    let renewals = payment_renewals::Entity::find_by_id(txid.to_string())
+        .count(txn)
+        .await
+        .map_err(StorageError::from_source)?;
+    Ok(transfers + renewals > 0)
+}
+
+async fn find_payment_on(
+    db: &DatabaseConnection,
+    pid: &PaymentId,
+) -> StorageResult<Option<PaymentRecord>> {
+    let maybe = payments::Entity::find()
+        .filter(payments::Column::Pid.eq(pid.as_bytes().to_vec()))
+        .one(db)
+        .await
+        .map_err(StorageError::from_source)?;
+    maybe.map(payment_to_record).transpose()
+}
+
+/// `None` for the states that precede storage.
+fn state_to_db(state: PaymentState) -> Option<PaymentStatusDb> {
+    match state {
+        PaymentState::Detected | PaymentState::Pending => None,
+        PaymentState::Confirmed => Some(PaymentStatusDb::Confirmed),
+        PaymentState::Claimed => Some(PaymentStatusDb::Claimed),
+        PaymentState::Expired => Some(PaymentStatusDb::Expired),
+        PaymentState::Reorged => Some(PaymentStatusDb::Reorged),
+        PaymentState::Refunded => Some(PaymentStatusDb::Refunded),
+    }
+}
+
+pub(crate) fn state_from_db(status: PaymentStatusDb) -> PaymentState {
+    match status {
+        PaymentStatusDb::Confirmed => PaymentState::Confirmed,
+        PaymentStatusDb::Claimed => PaymentState::Claimed,
+        PaymentStatusDb::Expired => PaymentState::Expired,
+        PaymentStatusDb::Reorged => PaymentState::Reorged,
+        PaymentStatusDb::Refunded => PaymentState::Refunded,
+    }
+}
+
+/// Stored states `transition` may start from, for conditional updates.
+fn stored_sources(transition: PaymentTransition) -> Vec<PaymentStatusDb> {
+    transition.sources().filter_map(state_to_db).collect()
+}
+
+/// Moves a stored payment along `transition`, conditional on its state so
+/// two transitions racing each other cannot both apply. Returns the new
+/// state, or `None` when the payment is missing or its state does not allow
+/// the transition. Transitions that leave storage, such as a reorg of an
+/// unclaimed payment, are the caller's to carry out.
+pub(crate) async fn transition_stored<C: ConnectionTrait>(
+    db: &C,
+    pid: &[u8],
+    transition: PaymentTransition,
+) -> StorageResult<Option<PaymentState>> {
+    for from in transition.sources() {
+        let (Some(from_db), Ok(to)) = (state_to_db(from), from.apply(transition)) else {
+            continue;
+        };
+        let Some(to_db) = state_to_db(to) else {
+            continue;
+        };
+        let updated = payments::Entity::update_many()
+            .col_expr(payments::Column::Status, Expr::value(to_db.to_value()))
+            .filter(payments::Column::Pid.eq(pid.to_vec()))
+            .filter(payments::Column::Status.eq(from_db))
+            .exec(db)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if updated > 0 {
+            return Ok(Some(to));
+        }
     }
+    Ok(None)
 }
 
 fn payment_to_record(model: payments::Model) -> StorageResult<PaymentRecord> {
@@ -102,12 +404,75 @@ fn payment_to_record(model: payments::Model) -> StorageResult<PaymentRecord> {
         txid: model.txid,
         amount: model.amount,
         block_height: model.block_height,
-        status: match model.status {
-            PaymentStatusDb::Unclaimed => PaymentStatus::Unclaimed,
-            PaymentStatusDb::Claimed => PaymentStatus::Claimed,
-        },
+        status: state_from_db(model.status),
         created_at: model.created_at,
         claimed_at: model.claimed_at,
         pid,
+        address_id: model.address_id,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::storage::RenewalStore;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn transfers_to_an_unclaimed_pid_accumulate() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let pid = PaymentId::parse("0123456789abcdef").unwrap();
+        let first = NewPayment {
+            pid: pid.clone(),
+            txid: "aa".repeat(32),
+            amount: 100,
+            block_height: 10,
+            detected_at: Utc::now(),
+            address_id: Some("primary".into()),
+        };
+        let second = NewPayment {
+            txid: "bb".repeat(32),
+            amount: 40,
+            block_height: 11,
+            detected_at: first.detected_at + Duration::minutes(5),
+            ..first.clone()
+        };
+        // Rescans re-deliver both; neither is counted twice.
+        for transfer in [&first, &second, &first, &second] {
+            storage.insert_payment(transfer.clone()).await.unwrap();
+        }
+
+        let payment = storage.find_payment(&pid).await.unwrap().unwrap();
+        assert_eq!(payment.txid, first.txid);
+        assert_eq!(payment.amount, 140);
+        assert_eq!(payment.address_id.as_deref(), Some("primary"));
+        let transfers = storage.find_transfers(&pid).await.unwrap();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].amount, 40);
+
+        let claimed = storage.claim_payment(&pid).await.unwrap().unwrap();
+        assert_eq!(claimed.amount, 140);
+
+        // After the claim, the known top-up stays a transfer and a new
+        // transfer is a renewal.
+        storage.insert_payment(second).await.unwrap();
+        let third = NewPayment {
+            txid: "cc".repeat(32),
+            ..first.clone()
+        };
+        storage.insert_payment(third).await.unwrap();
+        assert_eq!(storage.find_renewals(&pid).await.unwrap().len(), 1);
+        assert_eq!(storage.find_transfers(&pid).await.unwrap().len(), 1);
+        let payment = storage.find_payment(&pid).await.unwrap().unwrap();
+        assert_eq!(payment.amount, 140);
+        for txid in ["aa", "bb", "cc"].map(|byte| byte.repeat(32)) {
+            let found = storage.find_payment_by_txid(&txid).await.unwrap().unwrap();
+            assert_eq!(found.pid, pid);
+        }
+        assert!(storage
+            .find_payment_by_txid(&"dd".repeat(32))
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,151 @@
+use anon_ticket_domain::model::{PaymentId, PendingTransfer};
+use anon_ticket_domain::storage::{PendingPaymentStore, StorageResult};
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+
+use crate::entity::pending_payments;
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl PendingPaymentStore for SeaOrmStorage {
+    async fn record_pending(&self, transfers: Vec<PendingTransfer>) -> StorageResult<()> {
+        self.ensure_writable()?;
+        if transfers.is_empty() {
+            return Ok(());
+        }
+        let rows = transfers
+            .into_iter()
+            .map(|transfer| pending_payments::ActiveModel {
+                txid: Set(transfer.txid),
+                pid: Set(transfer.pid.as_bytes().to_vec()),
+                amount: Set(transfer.amount),
+                block_height: Set(transfer.block_height),
+                confirmations: Set(i64::try_from(transfer.confirmations).unwrap_or(i64::MAX)),
+                first_seen_at: Set(transfer.first_seen_at),
+                last_seen_at: Set(transfer.last_seen_at),
+            });
+        pending_payments::Entity::insert_many(rows)
+            .on_conflict(
+                OnConflict::column(pending_payments::Column::Txid)
+                    .update_columns([
+                        pending_payments::Column::Pid,
+                        pending_payments::Column::Amount,
+                        pending_payments::Column::BlockHeight,
+                        pending_payments::Column::Confirmations,
+                        pending_payments::Column::LastSeenAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(())
+    }
+
+    async fn find_pending(&self, pid: &PaymentId) -> StorageResult<Vec<PendingTransfer>> {
+        let rows = pending_payments::Entity::find()
+            .filter(pending_payments::Column::Pid.eq(pid.as_bytes().to_vec()))
+            .order_by_asc(pending_payments::Column::FirstSeenAt)
+            .order_by_asc(pending_payments::Column::Txid)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(rows
+            .into_iter()
+            .map(|row| PendingTransfer {
+                txid: row.txid,
+                pid: pid.clone(),
+                amount: row.amount,
+                block_height: row.block_height,
+                confirmations: u64::try_from(row.confirmations).unwrap_or_default(),
+                first_seen_at: row.first_seen_at,
+                last_seen_at: row.last_seen_at,
+            })
+            .collect())
+    }
+
+    async fn prune_pending(&self, before: DateTime<Utc>) -> StorageResult<u64> {
+        self.ensure_writable()?;
+        let result = pending_payments::Entity::delete_many()
+            .filter(pending_payments::Column::LastSeenAt.lt(before))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(result.rows_affected)
+    }
+}
+
+impl SeaOrmStorage {
+    /// Drops `txid`'s pending row once the transfer is ingested.
+    pub(crate) async fn forget_pending(&self, txid: &str) -> StorageResult<()> {
+        pending_payments::Entity::delete_many()
+            .filter(pending_payments::Column::Txid.eq(txid))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::NewPayment;
+    use anon_ticket_domain::storage::PaymentStore;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn pending_transfers_are_refreshed_promoted_and_pruned() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let pid = PaymentId::parse("0123456789abcdef").unwrap();
+        let start = Utc::now();
+        let pending = |txid: &str, block_height, confirmations, seen_at| PendingTransfer {
+            txid: txid.repeat(32),
+            pid: pid.clone(),
+            amount: 500,
+            block_height,
+            confirmations,
+            first_seen_at: seen_at,
+            last_seen_at: seen_at,
+        };
+
+        storage
+            .record_pending(vec![
+                pending("aa", None, 0, start),
+                pending("bb", None, 0, start),
+            ])
+            .await
+            .unwrap();
+        let later = start + Duration::minutes(2);
+        storage
+            .record_pending(vec![pending("aa", Some(100), 1, later)])
+            .await
+            .unwrap();
+        let found = storage.find_pending(&pid).await.unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].block_height, Some(100));
+        assert_eq!(found[0].confirmations, 1);
+        assert_eq!(found[0].first_seen_at, start);
+        assert_eq!(found[0].last_seen_at, later);
+
+        storage
+            .insert_payment(NewPayment {
+                pid: pid.clone(),
+                txid: "aa".repeat(32),
+                amount: 500,
+                block_height: 100,
+                detected_at: later,
+                address_id: None,
+            })
+            .await
+            .unwrap();
+        let found = storage.find_pending(&pid).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].txid, "bb".repeat(32));
+
+        assert_eq!(storage.prune_pending(later).await.unwrap(), 1);
+        assert!(storage.find_pending(&pid).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use anon_ticket_domain::model::{NewPaymentQuote, PaymentId, PaymentQuote, QuoteStatus};
+use anon_ticket_domain::storage::{QuoteStore, StorageResult};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ActiveEnum, ColumnTrait, EntityTrait, QueryFilter, QuerySelect, Set,
+};
+
+use crate::entity::payment_quotes::{self, QuoteStatusDb};
+use crate::entity::{payment_renewals, payments};
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl QuoteStore for SeaOrmStorage {
+    async fn insert_quote(&self, quote: NewPaymentQuote) -> StorageResult<bool> {
+        self.ensure_writable()?;
+        let inserted = payment_quotes::Entity::insert(payment_quotes::ActiveModel {
+            pid: Set(quote.pid.as_bytes().to_vec()),
+            expected_amount: Set(quote.expected_amount),
+            created_at: Set(quote.created_at),
+            expires_at: Set(quote.expires_at),
+            status: Set(QuoteStatusDb::Open),
+            received_amount: Set(None),
+            address_id: Set(quote.address_id),
+        })
+        .on_conflict(
+            OnConflict::column(payment_quotes::Column::Pid)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        Ok(inserted > 0)
+    }
+
+    async fn find_quote(&self, pid: &PaymentId) -> StorageResult<Option<PaymentQuote>> {
+        payment_quotes::Entity::find_by_id(pid.as_bytes().to_vec())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .map(into_quote)
+            .transpose()
+    }
+
+    async fn mark_quote_paid(&self, pid: &PaymentId) -> StorageResult<bool> {
+        self.ensure_writable()?;
+        let result = payment_quotes::Entity::update_many()
+            .col_expr(
+                payment_quotes::Column::Status,
+                Expr::value(QuoteStatusDb::Paid.to_value()),
+            )
+            .filter(payment_quotes::Column::Pid.eq(pid.as_bytes().to_vec()))
+            .filter(payment_quotes::Column::Status.ne(QuoteStatusDb::Paid))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(result.rows_affected > 0)
+    }
+
+    async fn settle_quote(&self, pid: &PaymentId) -> StorageResult<Option<PaymentQuote>> {
+        self.ensure_writable()?;
+        let key = pid.as_bytes().to_vec();
+        let Some(original) = payments::Entity::find_by_id(key.clone())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+        else {
+            return Ok(None);
+        };
+        let renewals: Vec<i64> = payment_renewals::Entity::find()
+            .select_only()
+            .column(payment_renewals::Column::Amount)
+            .filter(payment_renewals::Column::Pid.eq(key.clone()))
+            .into_tuple()
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        let received = renewals
+            .into_iter()
+            .fold(original.amount, i64::saturating_add);
+        payment_quotes::Entity::update_many()
+            .col_expr(
+                payment_quotes::Column::Status,
+                Expr::value(QuoteStatusDb::Paid.to_value()),
+            )
+            .col_expr(
+                payment_quotes::Column::ReceivedAmount,
+                Expr::value(received),
+            )
+            .filter(payment_quotes::Column::Pid.eq(key))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        self.find_quote(pid).await
+    }
+
+    async fn expire_quotes(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        self.ensure_writable()?;
+        let result = payment_quotes::Entity::update_many()
+            .col_expr(
+                payment_quotes::Column::Status,
+                Expr::value(QuoteStatusDb::Expired.to_value()),
+            )
+            .filter(payment_quotes::Column::Status.eq(QuoteStatusDb::Open))
+            .filter(payment_quotes::Column::ExpiresAt.lt(now))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(result.rows_affected)
+    }
+}
+
+fn into_quote(model: payment_quotes::Model) -> StorageResult<PaymentQuote> {
+    Ok(PaymentQuote {
+        pid: PaymentId::try_from(model.pid)
+            .map_err(|err| StorageError::Database(err.to_string()))?,
+        expected_amount: model.expected_amount,
+        created_at: model.created_at,
+        expires_at: model.expires_at,
+        status: match model.status {
+            QuoteStatusDb::Open => QuoteStatus::Open,
+            QuoteStatusDb::Paid => QuoteStatus::Paid,
+            QuoteStatusDb::Expired => QuoteStatus::Expired,
+        },
+        received_amount: model.received_amount,
+        address_id: model.address_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn quotes_move_from_open_to_expired_or_paid() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+        let quote = |pid: &str, expires_at| NewPaymentQuote {
+            pid: PaymentId::parse(pid).unwrap(),
+            expected_amount: 1_000,
+            created_at: now,
+            expires_at,
+            address_id: None,
+        };
+        let stale = quote("0a0a0a0a0a0a0a0a", now - Duration::minutes(1));
+        let fresh = quote("0b0b0b0b0b0b0b0b", now + Duration::hours(1));
+        assert!(storage.insert_quote(stale.clone()).await.unwrap());
+        assert!(storage.insert_quote(fresh.clone()).await.unwrap());
+        assert!(!storage.insert_quote(fresh.clone()).await.unwrap());
+
+        assert_eq!(storage.expire_quotes(now).await.unwrap(), 1);
+        assert_eq!(storage.expire_quotes(now).await.unwrap(), 0);
+        let status = |quote: Option<PaymentQuote>| quote.unwrap().status;
+        assert_eq!(
+            status(storage.find_quote(&stale.pid).await.unwrap()),
+            QuoteStatus::Expired
+        );
+        assert_eq!(
+            status(storage.find_quote(&fresh.pid).await.unwrap()),
+            QuoteStatus::Open
+        );
+
+        // A payment mined before the deadline can still land after the sweep.
+        assert!(storage.mark_quote_paid(&stale.pid).await.unwrap());
+        assert!(!storage.mark_quote_paid(&stale.pid).await.unwrap());
+        assert_eq!(
+            status(storage.find_quote(&stale.pid).await.unwrap()),
+            QuoteStatus::Paid
+        );
+        let missing = PaymentId::parse("0c0c0c0c0c0c0c0c").unwrap();
+        assert!(!storage.mark_quote_paid(&missing).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn settling_reconciles_payments_and_top_ups() {
+        use anon_ticket_domain::model::{NewPayment, Reconciliation};
+        use anon_ticket_domain::storage::PaymentStore;
+
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+        let pid = PaymentId::parse("0d0d0d0d0d0d0d0d").unwrap();
+        storage
+            .insert_quote(NewPaymentQuote {
+                pid: pid.clone(),
+                expected_amount: 1_000,
+                created_at: now,
+                expires_at: now + Duration::hours(1),
+                address_id: None,
+            })
+            .await
+            .unwrap();
+        // Nothing to settle before a payment lands.
+        assert!(storage.settle_quote(&pid).await.unwrap().is_none());
+
+        let payment = NewPayment {
+            pid: pid.clone(),
+            txid: "aa".repeat(32),
+            amount: 600,
+            block_height: 10,
+            detected_at: now,
+            address_id: None,
+        };
+        storage.insert_payment(payment.clone()).await.unwrap();
+        let quote = storage.settle_quote(&pid).await.unwrap().unwrap();
+        assert_eq!(quote.status, QuoteStatus::Paid);
+        assert_eq!(quote.received_amount, Some(600));
+        assert_eq!(quote.reconciliation(), Some(Reconciliation::Underpaid));
+
+        for (txid, amount) in [("bb", 400), ("cc", 5)] {
+            storage
+                .insert_payment(NewPayment {
+                    txid: txid.repeat(32),
+                    amount,
+                    ..payment.clone()
+                })
+                .await
+                .unwrap();
+        }
+        // Settling twice does not count anything twice.
+        storage.settle_quote(&pid).await.unwrap();
+        let quote = storage.settle_quote(&pid).await.unwrap().unwrap();
+        assert_eq!(quote.received_amount, Some(1_005));
+        assert_eq!(quote.reconciliation(), Some(Reconciliation::Overpaid));
+    }
+}
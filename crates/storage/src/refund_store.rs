@@ -0,0 +1,328 @@
+use anon_ticket_domain::model::{
+    NewRefund, PaymentId, PaymentTransition, Refund, RefundOutcome, RefundStatus,
+};
+use anon_ticket_domain::storage::{RefundStore, StorageResult};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveEnum, ColumnTrait, Condition, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set, TransactionTrait,
+};
+
+use crate::entity::payments;
+use crate::entity::refunds::{self, RefundStatusDb};
+use crate::errors::StorageError;
+use crate::payment_store::transition_stored;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl RefundStore for SeaOrmStorage {
+    async fn record_refund(&self, refund: NewRefund) -> StorageResult<Option<Refund>> {
+        self.ensure_writable()?;
+        let pid = refund.pid.as_bytes().to_vec();
+        let txn = self
+            .connection()
+            .begin()
+            .await
+            .map_err(StorageError::from_source)?;
+        // A no-op write locks the payment row, so concurrent requests for the
+        // same PID are checked against each other's refunds.
+        let locked = payments::Entity::update_many()
+            .col_expr(
+                payments::Column::Amount,
+                Expr::col(payments::Column::Amount).into(),
+            )
+            .filter(payments::Column::Pid.eq(pid.clone()))
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if locked == 0 {
+            return Ok(None);
+        }
+        let paid: Option<i64> = payments::Entity::find_by_id(pid.clone())
+            .select_only()
+            .column(payments::Column::Amount)
+            .into_tuple()
+            .one(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        let committed: Vec<i64> = refunds::Entity::find()
+            .select_only()
+            .column(refunds::Column::Amount)
+            .filter(refunds::Column::Pid.eq(pid.clone()))
+            .filter(refunds::Column::Status.ne(RefundStatusDb::Failed))
+            .into_tuple()
+            .all(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        let total = committed
+            .into_iter()
+            .fold(refund.amount, i64::saturating_add);
+        if paid.is_none_or(|paid| total > paid) {
+            return Ok(None);
+        }
+        let id = refunds::Entity::insert(refunds::ActiveModel {
+            pid: Set(pid),
+            address: Set(refund.address),
+            amount: Set(refund.amount),
+            status: Set(RefundStatusDb::Pending),
+            requested_at: Set(refund.requested_at),
+            resolved_at: Set(None),
+            txid: Set(None),
+            error: Set(None),
+            ..Default::default()
+        })
+        .exec(&txn)
+        .await
+        .map_err(StorageError::from_source)?
+        .last_insert_id;
+        txn.commit().await.map_err(StorageError::from_source)?;
+        self.find_refund(id).await
+    }
+
+    async fn find_refund(&self, id: i64) -> StorageResult<Option<Refund>> {
+        refunds::Entity::find_by_id(id)
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .map(into_refund)
+            .transpose()
+    }
+
+    async fn list_refunds(
+        &self,
+        status: Option<RefundStatus>,
+        before: Option<i64>,
+        limit: u64,
+    ) -> StorageResult<Vec<Refund>> {
+        let condition = Condition::all()
+            .add_option(status.map(|status| refunds::Column::Status.eq(status_to_db(status))))
+            .add_option(before.map(|id| refunds::Column::Id.lt(id)));
+        refunds::Entity::find()
+            .filter(condition)
+            .order_by_desc(refunds::Column::Id)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(into_refund)
+            .collect()
+    }
+
+    async fn claim_refund(&self, id: i64) -> StorageResult<Option<Refund>> {
+        self.ensure_writable()?;
+        let claimed = refunds::Entity::update_many()
+            .col_expr(
+                refunds::Column::Status,
+                Expr::value(RefundStatusDb::Sending.to_value()),
+            )
+            .filter(refunds::Column::Id.eq(id))
+            .filter(refunds::Column::Status.eq(RefundStatusDb::Pending))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if claimed == 0 {
+            return Ok(None);
+        }
+        self.find_refund(id).await
+    }
+
+    async fn resolve_refund(
+        &self,
+        id: i64,
+        outcome: RefundOutcome,
+        at: DateTime<Utc>,
+    ) -> StorageResult<Option<Refund>> {
+        self.ensure_writable()?;
+        let status = status_to_db(outcome.status());
+        let (txid, error) = match outcome {
+            RefundOutcome::Sent { txid } => (Some(txid), None),
+            RefundOutcome::Failed { error } => (None, Some(error)),
+        };
+        // Conditional on the status so two resolutions cannot both apply.
+        let resolved = refunds::Entity::update_many()
+            .col_expr(refunds::Column::Status, Expr::value(status.to_value()))
+            .col_expr(refunds::Column::ResolvedAt, Expr::value(at))
+            .col_expr(refunds::Column::Txid, Expr::value(txid))
+            .col_expr(refunds::Column::Error, Expr::value(error))
+            .filter(refunds::Column::Id.eq(id))
+            .filter(
+                refunds::Column::Status.is_in([RefundStatusDb::Pending, RefundStatusDb::Sending]),
+            )
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if resolved == 0 {
+            return Ok(None);
+        }
+        let refund = self.find_refund(id).await?;
+        if let Some(refund) = refund.as_ref().filter(|r| r.status == RefundStatus::Sent) {
+            self.mark_refunded_in_full(&refund.pid).await?;
+        }
+        Ok(refund)
+    }
+}
+
+impl SeaOrmStorage {
+    /// Moves the payment to refunded once sent refunds cover all of it.
+    async fn mark_refunded_in_full(&self, pid: &PaymentId) -> StorageResult<()> {
+        let paid: Option<i64> = payments::Entity::find_by_id(pid.as_bytes().to_vec())
+            .select_only()
+            .column(payments::Column::Amount)
+            .into_tuple()
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        let sent: Vec<i64> = refunds::Entity::find()
+            .select_only()
+            .column(refunds::Column::Amount)
+            .filter(refunds::Column::Pid.eq(pid.as_bytes().to_vec()))
+            .filter(refunds::Column::Status.eq(RefundStatusDb::Sent))
+            .into_tuple()
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        let sent = sent.into_iter().fold(0, i64::saturating_add);
+        if paid.is_some_and(|paid| sent >= paid) {
+            transition_stored(self.connection(), pid.as_bytes(), PaymentTransition::Refund).await?;
+        }
+        Ok(())
+    }
+}
+
+fn status_to_db(status: RefundStatus) -> RefundStatusDb {
+    match status {
+        RefundStatus::Pending => RefundStatusDb::Pending,
+        RefundStatus::Sending => RefundStatusDb::Sending,
+        RefundStatus::Sent => RefundStatusDb::Sent,
+        RefundStatus::Failed => RefundStatusDb::Failed,
+    }
+}
+
+fn into_refund(model: refunds::Model) -> StorageResult<Refund> {
+    Ok(Refund {
+        id: model.id,
+        pid: PaymentId::try_from(model.pid)
+            .map_err(|err| StorageError::Database(err.to_string()))?,
+        address: model.address,
+        amount: model.amount,
+        status: match model.status {
+            RefundStatusDb::Pending => RefundStatus::Pending,
+            RefundStatusDb::Sending => RefundStatus::Sending,
+            RefundStatusDb::Sent => RefundStatus::Sent,
+            RefundStatusDb::Failed => RefundStatus::Failed,
+        },
+        requested_at: model.requested_at,
+        resolved_at: model.resolved_at,
+        txid: model.txid,
+        error: model.error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::{NewPayment, PaymentState};
+    use anon_ticket_domain::storage::PaymentStore;
+
+    #[tokio::test]
+    async fn refunds_stay_within_the_payment_and_close_once() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+        let pid = PaymentId::parse("0123456789abcdef").unwrap();
+        let refund = |amount| NewRefund {
+            pid: pid.clone(),
+            address: "refund-address".to_string(),
+            amount,
+            requested_at: now,
+        };
+        assert_eq!(storage.record_refund(refund(100)).await.unwrap(), None);
+        storage
+            .insert_payment(NewPayment {
+                pid: pid.clone(),
+                txid: "a".repeat(64),
+                amount: 1_000,
+                block_height: 10,
+                detected_at: now,
+                address_id: None,
+            })
+            .await
+            .unwrap();
+
+        let first = storage.record_refund(refund(600)).await.unwrap().unwrap();
+        assert_eq!(first.status, RefundStatus::Pending);
+        assert_eq!(storage.record_refund(refund(500)).await.unwrap(), None);
+        let second = storage.record_refund(refund(400)).await.unwrap().unwrap();
+
+        let claimed = storage.claim_refund(first.id).await.unwrap().unwrap();
+        assert_eq!(claimed.status, RefundStatus::Sending);
+        assert_eq!(storage.claim_refund(first.id).await.unwrap(), None);
+        let sent = storage
+            .resolve_refund(
+                first.id,
+                RefundOutcome::Sent {
+                    txid: "b".repeat(64),
+                },
+                now,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sent.status, RefundStatus::Sent);
+        assert_eq!(sent.txid, Some("b".repeat(64)));
+        assert_eq!(sent.resolved_at, Some(now));
+        let again = RefundOutcome::Failed {
+            error: "late".to_string(),
+        };
+        assert_eq!(
+            storage.resolve_refund(first.id, again, now).await.unwrap(),
+            None
+        );
+
+        // A failed refund frees its amount for another attempt.
+        let cancelled = RefundOutcome::Failed {
+            error: "wrong address".to_string(),
+        };
+        storage
+            .resolve_refund(second.id, cancelled, now)
+            .await
+            .unwrap()
+            .unwrap();
+        let third = storage.record_refund(refund(400)).await.unwrap().unwrap();
+        let payment = storage.find_payment(&pid).await.unwrap().unwrap();
+        assert_eq!(payment.status, PaymentState::Confirmed);
+
+        let pending = storage
+            .list_refunds(Some(RefundStatus::Pending), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            pending.iter().map(|refund| refund.id).collect::<Vec<_>>(),
+            vec![third.id]
+        );
+        let all = storage
+            .list_refunds(None, Some(third.id), 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            all.iter().map(|refund| refund.id).collect::<Vec<_>>(),
+            vec![second.id, first.id]
+        );
+
+        // Sent in full, the payment can no longer be claimed.
+        let sent = RefundOutcome::Sent {
+            txid: "c".repeat(64),
+        };
+        storage
+            .resolve_refund(third.id, sent, now)
+            .await
+            .unwrap()
+            .unwrap();
+        let payment = storage.find_payment(&pid).await.unwrap().unwrap();
+        assert_eq!(payment.status, PaymentState::Refunded);
+        assert!(storage.claim_payment(&pid).await.unwrap().is_none());
+    }
+}
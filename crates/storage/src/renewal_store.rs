@@ -0,0 +1,104 @@
+use anon_ticket_domain::model::{NewPayment, PaymentId, RenewalRecord};
+use anon_ticket_domain::storage::{RenewalStore, StorageResult};
+use sea_orm::{sea_query::OnConflict, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+
+use crate::entity::{payment_renewals, payments};
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+impl SeaOrmStorage {
+    /// Keeps a later payment to an already-known PID instead of dropping it.
+    /// Re-delivery of the original transaction is not a renewal.
+    pub(crate) async fn record_renewal(&self, payment: &NewPayment) -> StorageResult<()> {
+        let original = payments::Entity::find_by_id(payment.pid.as_bytes().to_vec())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        if original.is_none_or(|original| original.txid == payment.txid) {
+            return Ok(());
+        }
+        payment_renewals::Entity::insert(payment_renewals::ActiveModel {
+            txid: Set(payment.txid.clone()),
+            pid: Set(payment.pid.as_bytes().to_vec()),
+            amount: Set(payment.amount),
+            block_height: Set(payment.block_height),
+            detected_at: Set(payment.detected_at),
+        })
+        .on_conflict(
+            OnConflict::column(payment_renewals::Column::Txid)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl RenewalStore for SeaOrmStorage {
+    async fn find_renewals(&self, pid: &PaymentId) -> StorageResult<Vec<RenewalRecord>> {
+        let rows = payment_renewals::Entity::find()
+            .filter(payment_renewals::Column::Pid.eq(pid.as_bytes().to_vec()))
+            .order_by_asc(payment_renewals::Column::DetectedAt)
+            .order_by_asc(payment_renewals::Column::Txid)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(rows
+            .into_iter()
+            .map(|row| RenewalRecord {
+                pid: pid.clone(),
+                txid: row.txid,
+                amount: row.amount,
+                block_height: row.block_height,
+                detected_at: row.detected_at,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::storage::PaymentStore;
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn payment_to_a_claimed_pid_is_kept_as_a_renewal() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let pid = PaymentId::parse("0123456789abcdef").unwrap();
+        let first = NewPayment {
+            pid: pid.clone(),
+            txid: "aa".repeat(32),
+            amount: 100,
+            block_height: 10,
+            detected_at: Utc::now(),
+            address_id: None,
+        };
+        storage.insert_payment(first.clone()).await.unwrap();
+        // Rescans re-deliver the original transaction; that is not a renewal.
+        storage.insert_payment(first.clone()).await.unwrap();
+        assert!(storage.find_renewals(&pid).await.unwrap().is_empty());
+        storage.claim_payment(&pid).await.unwrap().unwrap();
+
+        let renewal = NewPayment {
+            txid: "bb".repeat(32),
+            amount: 250,
+            block_height: 20,
+            detected_at: first.detected_at + Duration::days(30),
+            ..first.clone()
+        };
+        storage.insert_payment(renewal.clone()).await.unwrap();
+        storage.insert_payment(renewal).await.unwrap();
+
+        let renewals = storage.find_renewals(&pid).await.unwrap();
+        assert_eq!(renewals.len(), 1);
+        assert_eq!(renewals[0].amount, 250);
+        let payment = storage.find_payment(&pid).await.unwrap().unwrap();
+        assert_eq!(payment.txid, first.txid);
+        assert_eq!(payment.amount, 100);
+        assert!(storage.find_transfers(&pid).await.unwrap().is_empty());
+    }
+}
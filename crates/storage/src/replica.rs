@@ -0,0 +1,118 @@
+//! Hedged reads against an optional read replica.
+//!
+//! Lookups go to the replica first. A miss or error falls back to the primary
+//! immediately; a replica that has not answered within `hedge_after` is raced
+//! against the primary so replica lag never adds more than the deadline to the
+//! claim path. Outcomes are counted in `storage_hedged_reads_total{outcome}`.
+
+use std::future::Future;
+use std::time::Duration;
+
+use anon_ticket_domain::storage::StorageResult;
+use metrics::counter;
+use sea_orm::DatabaseConnection;
+
+/// How long the replica gets before the primary is queried as well.
+pub const DEFAULT_HEDGE_AFTER: Duration = Duration::from_millis(50);
+
+pub(crate) struct ReadReplica {
+    pub(crate) db: DatabaseConnection,
+    pub(crate) hedge_after: Duration,
+}
+
+/// Runs `replica` then, if needed, `primary`. Both futures must perform the
+/// same lookup against their respective connections.
+pub(crate) async fn hedged<T, R, P>(
+    hedge_after: Duration,
+    replica: R,
+    primary: impl FnOnce() -> P,
+) -> StorageResult<Option<T>>
+where
+    R: Future<Output = StorageResult<Option<T>>>,
+    P: Future<Output = StorageResult<Option<T>>>,
+{
+    tokio::pin!(replica);
+    match tokio::time::timeout(hedge_after, &mut replica).await {
+        Ok(Ok(Some(found))) => {
+            record("replica_hit");
+            return Ok(Some(found));
+        }
+        Ok(Ok(None)) => {
+            record("replica_miss");
+            return primary().await;
+        }
+        Ok(Err(_)) => {
+            record("replica_error");
+            return primary().await;
+        }
+        Err(_) => {}
+    }
+
+    // Deadline passed: query the primary but keep the replica in the race.
+    let primary = primary();
+    tokio::pin!(primary);
+    tokio::select! {
+        result = &mut primary => {
+            record("primary_after_deadline");
+            result
+        }
+        result = &mut replica => match result {
+            Ok(Some(found)) => {
+                record("replica_late_hit");
+                Ok(Some(found))
+            }
+            _ => {
+                record("primary_after_deadline");
+                primary.await
+            }
+        },
+    }
+}
+
+fn record(outcome: &'static str) {
+    counter!("storage_hedged_reads_total", "outcome" => outcome).increment(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    async fn answer(value: Option<u8>, delay_ms: u64) -> StorageResult<Option<u8>> {
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        Ok(value)
+    }
+
+    #[tokio::test]
+    async fn replica_hit_skips_primary() {
+        let called = AtomicBool::new(false);
+        let found = hedged(Duration::from_millis(50), answer(Some(1), 0), || {
+            called.store(true, Ordering::SeqCst);
+            answer(Some(2), 0)
+        })
+        .await
+        .unwrap();
+        assert_eq!(found, Some(1));
+        assert!(!called.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn replica_miss_falls_back_to_primary() {
+        let found = hedged(Duration::from_millis(50), answer(None, 0), || {
+            answer(Some(2), 0)
+        })
+        .await
+        .unwrap();
+        assert_eq!(found, Some(2));
+    }
+
+    #[tokio::test]
+    async fn lagging_replica_is_hedged_by_primary() {
+        let found = hedged(Duration::from_millis(10), answer(Some(1), 5_000), || {
+            answer(Some(2), 0)
+        })
+        .await
+        .unwrap();
+        assert_eq!(found, Some(2));
+    }
+}
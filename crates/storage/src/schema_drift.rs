@@ -0,0 +1,229 @@
+//! Post-migration check that the live schema still matches what this crate
+//! would create.
+//!
+//! Migrations only ever `CREATE ... IF NOT EXISTS`, so a table created by an
+//! older build (or altered by hand) keeps its old shape: a PID column declared
+//! `binary(32)` silently accepts 8-byte values and nothing complains until the
+//! data is wrong. Comparing declared types and nullability at startup turns
+//! that into a boot failure that names every offending column.
+
+use sea_orm::sea_query::{
+    ColumnSpec, PostgresQueryBuilder, SqliteQueryBuilder, TableBuilder, TableCreateStatement,
+    TableRef,
+};
+use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection, Statement};
+
+use crate::errors::StorageError;
+use crate::migration::schema_tables;
+use anon_ticket_domain::storage::StorageResult;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ColumnShape {
+    pub(crate) name: String,
+    ty: String,
+    not_null: bool,
+}
+
+/// Fails with a `schema drift detected` error listing every mismatch between
+/// the live tables and [`schema_tables`].
+pub(crate) async fn verify_schema(db: &DatabaseConnection) -> StorageResult<()> {
+    let backend = db.get_database_backend();
+    let mut problems = Vec::new();
+    for table in schema_tables() {
+        let name = table_name(&table);
+        let expected = expected_columns(backend, &table);
+        let live = live_columns(db, backend, &name).await?;
+        problems.extend(compare(&name, &expected, &live));
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(StorageError::Database(format!(
+            "schema drift detected: {}",
+            problems.join("; ")
+        )))
+    }
+}
+
+fn compare(table: &str, expected: &[ColumnShape], live: &[ColumnShape]) -> Vec<String> {
+    if live.is_empty() {
+        return vec![format!("table {table} is missing")];
+    }
+
+    let mut problems = Vec::new();
+    for want in expected {
+        match live.iter().find(|col| col.name == want.name) {
+            None => problems.push(format!("{table}.{} is missing", want.name)),
+            Some(have) => {
+                if have.ty != want.ty {
+                    problems.push(format!(
+                        "{table}.{} has type {} (expected {})",
+                        want.name, have.ty, want.ty
+                    ));
+                }
+                if have.not_null != want.not_null {
+                    problems.push(format!(
+                        "{table}.{} is {} (expected {})",
+                        want.name,
+                        nullability(have.not_null),
+                        nullability(want.not_null)
+                    ));
+                }
+            }
+        }
+    }
+    for have in live {
+        if !expected.iter().any(|col| col.name == have.name) {
+            problems.push(format!(
+                "{table}.{} is not defined by this build",
+                have.name
+            ));
+        }
+    }
+    problems
+}
+
+fn nullability(not_null: bool) -> &'static str {
+    if not_null {
+        "NOT NULL"
+    } else {
+        "nullable"
+    }
+}
+
+pub(crate) fn table_name(table: &TableCreateStatement) -> String {
+    match table.get_table_name() {
+        Some(TableRef::Table(iden)) => iden.to_string(),
+        other => panic!("schema tables are plain table references, got {other:?}"),
+    }
+}
+
+fn expected_columns(backend: DatabaseBackend, table: &TableCreateStatement) -> Vec<ColumnShape> {
+    table
+        .get_columns()
+        .iter()
+        .map(|col| {
+            let mut ty = String::new();
+            if let Some(column_type) = col.get_column_type() {
+                match backend {
+                    DatabaseBackend::Postgres => {
+                        PostgresQueryBuilder.prepare_column_type(column_type, &mut ty)
+                    }
+                    _ => SqliteQueryBuilder.prepare_column_type(column_type, &mut ty),
+                }
+            }
+            let auto_increment = col
+                .get_column_spec()
+                .iter()
+                .any(|spec| matches!(spec, ColumnSpec::AutoIncrement));
+            // SQLite only auto-increments an `integer primary key`.
+            if auto_increment && backend == DatabaseBackend::Sqlite {
+                ty = "integer".to_string();
+            }
+            ColumnShape {
+                name: col.get_column_name(),
+                ty: normalize_type(&ty),
+                not_null: col
+                    .get_column_spec()
+                    .iter()
+                    .any(|spec| matches!(spec, ColumnSpec::NotNull)),
+            }
+        })
+        .collect()
+}
+
+/// Empty when the table does not exist.
+pub(crate) async fn live_columns<C: ConnectionTrait>(
+    db: &C,
+    backend: DatabaseBackend,
+    table: &str,
+) -> StorageResult<Vec<ColumnShape>> {
+    let statement = match backend {
+        DatabaseBackend::Postgres => Statement::from_sql_and_values(
+            backend,
+            "SELECT a.attname AS name, format_type(a.atttypid, a.atttypmod) AS ty, \
+             a.attnotnull AS not_null \
+             FROM pg_attribute a \
+             WHERE a.attrelid = to_regclass($1) AND a.attnum > 0 AND NOT a.attisdropped",
+            [table.into()],
+        ),
+        _ => Statement::from_sql_and_values(
+            backend,
+            "SELECT name, type AS ty, \"notnull\" <> 0 AS not_null FROM pragma_table_info(?)",
+            [table.into()],
+        ),
+    };
+
+    db.query_all(statement)
+        .await
+        .map_err(StorageError::from_source)?
+        .into_iter()
+        .map(|row| {
+            Ok(ColumnShape {
+                name: row.try_get("", "name").map_err(StorageError::from_source)?,
+                ty: normalize_type(
+                    &row.try_get::<String>("", "ty")
+                        .map_err(StorageError::from_source)?,
+                ),
+                not_null: row
+                    .try_get("", "not_null")
+                    .map_err(StorageError::from_source)?,
+            })
+        })
+        .collect()
+}
+
+/// Folds spelling differences between what sea-query emits and what the
+/// catalog reports (`varchar` vs `character varying`, case).
+fn normalize_type(ty: &str) -> String {
+    ty.trim()
+        .to_ascii_lowercase()
+        .replace("character varying", "varchar")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SeaOrmStorage;
+    use sea_orm::Database;
+
+    #[tokio::test]
+    async fn fresh_schema_passes_drift_check() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        verify_schema(storage.connection()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn mismatched_column_length_fails_fast() {
+        let path = std::env::temp_dir().join(format!(
+            "anon-ticket-schema-drift-{}.db",
+            std::process::id()
+        ));
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let raw = Database::connect(&url).await.unwrap();
+        raw.execute(Statement::from_string(
+            DatabaseBackend::Sqlite,
+            "CREATE TABLE payments (pid binary(32) NOT NULL PRIMARY KEY, \
+             txid text(64) NOT NULL, amount bigint NOT NULL, block_height bigint NOT NULL, \
+             status integer NOT NULL, created_at text NOT NULL, claimed_at text)",
+        ))
+        .await
+        .unwrap();
+        raw.close().await.unwrap();
+
+        let err = SeaOrmStorage::connect(&url)
+            .await
+            .err()
+            .expect("drifted schema must be rejected")
+            .to_string();
+        assert!(
+            err.contains("payments.pid has type binary(32) (expected binary(8))"),
+            "{err}"
+        );
+
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+}
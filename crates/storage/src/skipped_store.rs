@@ -0,0 +1,142 @@
+use anon_ticket_domain::model::SkippedTransfer;
+use anon_ticket_domain::storage::{SkippedTransferStore, StorageResult};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set};
+
+use crate::entity::skipped_transfers;
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl SkippedTransferStore for SeaOrmStorage {
+    async fn record_skipped(&self, transfer: SkippedTransfer) -> StorageResult<()> {
+        self.ensure_writable()?;
+        let row = skipped_transfers::ActiveModel {
+            txid: Set(transfer.txid),
+            wallet: Set(transfer.wallet),
+            reason: Set(transfer.reason),
+            payment_id: Set(transfer.payment_id),
+            amount: Set(transfer.amount),
+            block_height: Set(transfer.block_height),
+            timestamp: Set(i64::try_from(transfer.timestamp).unwrap_or(i64::MAX)),
+            skipped_at: Set(transfer.skipped_at),
+        };
+        skipped_transfers::Entity::insert(row)
+            .on_conflict(
+                OnConflict::column(skipped_transfers::Column::Txid)
+                    .update_columns([
+                        skipped_transfers::Column::Wallet,
+                        skipped_transfers::Column::Reason,
+                        skipped_transfers::Column::PaymentId,
+                        skipped_transfers::Column::Amount,
+                        skipped_transfers::Column::BlockHeight,
+                        skipped_transfers::Column::Timestamp,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(())
+    }
+
+    async fn find_skipped(&self, txid: &str) -> StorageResult<Option<SkippedTransfer>> {
+        let row = skipped_transfers::Entity::find_by_id(txid.to_string())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(row.map(skipped_from_row))
+    }
+
+    async fn list_skipped(
+        &self,
+        after: Option<&str>,
+        limit: u64,
+    ) -> StorageResult<Vec<SkippedTransfer>> {
+        let mut query = skipped_transfers::Entity::find();
+        if let Some(after) = after {
+            query = query.filter(skipped_transfers::Column::Txid.gt(after));
+        }
+        let rows = query
+            .order_by_asc(skipped_transfers::Column::Txid)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(rows.into_iter().map(skipped_from_row).collect())
+    }
+
+    async fn delete_skipped(&self, txid: &str) -> StorageResult<bool> {
+        self.ensure_writable()?;
+        let result = skipped_transfers::Entity::delete_by_id(txid.to_string())
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(result.rows_affected > 0)
+    }
+}
+
+fn skipped_from_row(row: skipped_transfers::Model) -> SkippedTransfer {
+    SkippedTransfer {
+        txid: row.txid,
+        wallet: row.wallet,
+        reason: row.reason,
+        payment_id: row.payment_id,
+        amount: row.amount,
+        block_height: row.block_height,
+        timestamp: u64::try_from(row.timestamp).unwrap_or_default(),
+        skipped_at: row.skipped_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn skipped_transfers_are_refreshed_listed_and_dismissed() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let start = Utc::now();
+        let skipped = |txid: &str, reason: &str, skipped_at| SkippedTransfer {
+            txid: txid.repeat(32),
+            wallet: "primary".into(),
+            reason: reason.into(),
+            payment_id: Some("not-a-pid".into()),
+            amount: "18446744073709551615".into(),
+            block_height: Some(100),
+            timestamp: 1_700_000_000,
+            skipped_at,
+        };
+
+        storage
+            .record_skipped(skipped("bb", "invalid_pid", start))
+            .await
+            .unwrap();
+        storage
+            .record_skipped(skipped("aa", "invalid_pid", start))
+            .await
+            .unwrap();
+        let later = start + Duration::minutes(5);
+        storage
+            .record_skipped(skipped("bb", "amount_overflow", later))
+            .await
+            .unwrap();
+
+        let found = storage.find_skipped(&"bb".repeat(32)).await.unwrap();
+        let found = found.expect("journaled");
+        assert_eq!(found.reason, "amount_overflow");
+        assert_eq!(found.skipped_at, start);
+        assert_eq!(found.amount, "18446744073709551615");
+
+        let page = storage.list_skipped(None, 1).await.unwrap();
+        assert_eq!(page[0].txid, "aa".repeat(32));
+        let page = storage.list_skipped(Some(&page[0].txid), 10).await.unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].txid, "bb".repeat(32));
+
+        assert!(storage.delete_skipped(&"aa".repeat(32)).await.unwrap());
+        assert!(!storage.delete_skipped(&"aa".repeat(32)).await.unwrap());
+        assert_eq!(storage.list_skipped(None, 10).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,121 @@
+use anon_ticket_domain::model::{PaymentId, SubaddressRecord};
+use anon_ticket_domain::storage::{StorageResult, SubaddressStore};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
+
+use crate::entity::subaddresses;
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl SubaddressStore for SeaOrmStorage {
+    async fn insert_subaddress(&self, subaddress: SubaddressRecord) -> StorageResult<()> {
+        self.ensure_writable()?;
+        subaddresses::Entity::insert(subaddresses::ActiveModel {
+            pid: Set(subaddress.pid.as_bytes().to_vec()),
+            account_index: Set(i64::from(subaddress.account_index)),
+            minor_index: Set(i64::from(subaddress.minor_index)),
+            address: Set(subaddress.address),
+            created_at: Set(subaddress.created_at),
+        })
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        Ok(())
+    }
+
+    async fn find_subaddress_by_index(
+        &self,
+        account_index: u32,
+        minor_index: u32,
+    ) -> StorageResult<Option<SubaddressRecord>> {
+        subaddresses::Entity::find()
+            .filter(subaddresses::Column::AccountIndex.eq(i64::from(account_index)))
+            .filter(subaddresses::Column::MinorIndex.eq(i64::from(minor_index)))
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .map(into_record)
+            .transpose()
+    }
+
+    async fn find_subaddress_by_pid(
+        &self,
+        pid: &PaymentId,
+    ) -> StorageResult<Option<SubaddressRecord>> {
+        subaddresses::Entity::find_by_id(pid.as_bytes().to_vec())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .map(into_record)
+            .transpose()
+    }
+}
+
+fn into_record(model: subaddresses::Model) -> StorageResult<SubaddressRecord> {
+    let index = |value: i64| {
+        u32::try_from(value)
+            .map_err(|_| StorageError::Database(format!("subaddress index {value} out of range")))
+    };
+    Ok(SubaddressRecord {
+        pid: PaymentId::try_from(model.pid)
+            .map_err(|err| StorageError::Database(err.to_string()))?,
+        account_index: index(model.account_index)?,
+        minor_index: index(model.minor_index)?,
+        address: model.address,
+        created_at: model.created_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::NewPayment;
+    use anon_ticket_domain::storage::{PaymentStore, TombstoneStore};
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn maps_subaddress_index_to_pid() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let pid = PaymentId::parse("0123456789abcdef").unwrap();
+        let record = SubaddressRecord {
+            pid: pid.clone(),
+            account_index: 0,
+            minor_index: 7,
+            address: "8subaddress".into(),
+            created_at: Utc::now(),
+        };
+        storage.insert_subaddress(record.clone()).await.unwrap();
+
+        assert_eq!(
+            storage.find_subaddress_by_index(0, 7).await.unwrap(),
+            Some(record.clone())
+        );
+        assert_eq!(storage.find_subaddress_by_index(1, 7).await.unwrap(), None);
+        assert_eq!(
+            storage.find_subaddress_by_pid(&pid).await.unwrap(),
+            Some(record.clone())
+        );
+
+        // Each index belongs to exactly one order.
+        let other = SubaddressRecord {
+            pid: PaymentId::parse("fedcba9876543210").unwrap(),
+            ..record
+        };
+        assert!(storage.insert_subaddress(other).await.is_err());
+
+        // The mapping goes with the payment when it is purged.
+        storage
+            .insert_payment(NewPayment {
+                pid: pid.clone(),
+                txid: "aa".repeat(32),
+                amount: 100,
+                block_height: 10,
+                detected_at: Utc::now(),
+                address_id: None,
+            })
+            .await
+            .unwrap();
+        assert!(storage.purge_payment(&pid).await.unwrap());
+        assert_eq!(storage.find_subaddress_by_pid(&pid).await.unwrap(), None);
+    }
+}
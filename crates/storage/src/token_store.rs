@@ -1,23 +1,35 @@
 use anon_ticket_domain::model::{
-    NewServiceToken, PaymentId, RevokeTokenRequest, ServiceToken, ServiceTokenRecord,
+    MergeTokensRequest, NewServiceToken, PaymentId, RevocationCursor, RevokeTokenRequest,
+    ServiceToken, ServiceTokenRecord, SplitTokenOutcome, SplitTokenRequest, SuspendTokenRequest,
+    TokenFilter, TokenReview, TokenRevocation, MERGED_REVOKE_REASON,
 };
 use anon_ticket_domain::storage::{StorageResult, TokenStore};
-use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{Expr, OnConflict, Query},
+    ActiveModelTrait, ColumnTrait, Condition, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    Set, TransactionTrait, UpdateMany,
+};
+use tracing::instrument;
 
-use crate::entity::service_tokens;
+use crate::changefeed::Change;
+use crate::entity::{payments, service_tokens, token_expiries, token_reviews, token_validations};
 use crate::errors::StorageError;
 use crate::SeaOrmStorage;
 
 #[async_trait::async_trait]
 impl TokenStore for SeaOrmStorage {
+    #[instrument(name = "storage.insert_token", skip_all)]
     async fn insert_token(&self, token: NewServiceToken) -> StorageResult<ServiceTokenRecord> {
+        self.ensure_writable()?;
         let model = service_tokens::ActiveModel {
             token: Set(token.token.into_bytes().to_vec()),
             pid: Set(token.pid.into_bytes().to_vec()),
             amount: Set(token.amount),
             issued_at: Set(token.issued_at),
             abuse_score: Set(token.abuse_score),
+            expires_at: Set(token.expires_at),
+            tier: Set(token.tier),
             ..Default::default()
         };
         let created = model
@@ -27,6 +39,7 @@ impl TokenStore for SeaOrmStorage {
         token_to_record(created)
     }
 
+    #[instrument(name = "storage.find_token", skip_all)]
     async fn find_token(&self, token: &ServiceToken) -> StorageResult<Option<ServiceTokenRecord>> {
         let maybe = service_tokens::Entity::find()
             .filter(service_tokens::Column::Token.eq(token.as_bytes().to_vec()))
@@ -36,10 +49,24 @@ impl TokenStore for SeaOrmStorage {
         maybe.map(token_to_record).transpose()
     }
 
+    #[instrument(name = "storage.find_token_by_pid", skip_all)]
+    async fn find_token_by_pid(
+        &self,
+        pid: &PaymentId,
+    ) -> StorageResult<Option<ServiceTokenRecord>> {
+        let maybe = service_tokens::Entity::find()
+            .filter(service_tokens::Column::Pid.eq(pid.as_bytes().to_vec()))
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        maybe.map(token_to_record).transpose()
+    }
+
     async fn revoke_token(
         &self,
         request: RevokeTokenRequest,
     ) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.ensure_writable()?;
         let maybe = service_tokens::Entity::find()
             .filter(service_tokens::Column::Token.eq(request.token.as_bytes().to_vec()))
             .one(self.connection())
@@ -63,8 +90,470 @@ impl TokenStore for SeaOrmStorage {
             .update(self.connection())
             .await
             .map_err(StorageError::from_source)?;
+        self.publish_change(Change::TokenRevoked(request.token))
+            .await;
         token_to_record(updated).map(Some)
     }
+
+    async fn suspend_token(
+        &self,
+        request: SuspendTokenRequest,
+    ) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.ensure_writable()?;
+        let now = Utc::now();
+        let suspended = service_tokens::Entity::update_many()
+            .col_expr(service_tokens::Column::SuspendedAt, Expr::value(now))
+            .col_expr(
+                service_tokens::Column::SuspendReason,
+                Expr::value(request.reason),
+            )
+            .col_expr(
+                service_tokens::Column::SuspendedUntil,
+                Expr::value(request.until),
+            )
+            .filter(service_tokens::Column::Token.eq(request.token.as_bytes().to_vec()))
+            .filter(usable_at(now))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if suspended == 0 {
+            return Ok(None);
+        }
+        self.find_token(&request.token).await
+    }
+
+    async fn resume_token(
+        &self,
+        token: &ServiceToken,
+    ) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.ensure_writable()?;
+        let resumed = clear_suspension()
+            .filter(service_tokens::Column::Token.eq(token.as_bytes().to_vec()))
+            .filter(service_tokens::Column::RevokedAt.is_null())
+            .filter(service_tokens::Column::SuspendedAt.is_not_null())
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if resumed == 0 {
+            return Ok(None);
+        }
+        self.find_token(token).await
+    }
+
+    async fn lift_lapsed_suspensions(&self, now: DateTime<Utc>) -> StorageResult<u64> {
+        self.ensure_writable()?;
+        Ok(clear_suspension()
+            .filter(service_tokens::Column::SuspendedAt.is_not_null())
+            .filter(service_tokens::Column::SuspendedUntil.lte(now))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected)
+    }
+
+    async fn debit_token(&self, token: &ServiceToken, amount: i64) -> StorageResult<Option<i64>> {
+        self.ensure_writable()?;
+        let key = token.as_bytes().to_vec();
+        let txn = self
+            .connection()
+            .begin()
+            .await
+            .map_err(StorageError::from_source)?;
+        let debited = service_tokens::Entity::update_many()
+            .col_expr(
+                service_tokens::Column::Amount,
+                Expr::col(service_tokens::Column::Amount).sub(amount),
+            )
+            .filter(service_tokens::Column::Token.eq(key.clone()))
+            .filter(usable_at(Utc::now()))
+            .filter(service_tokens::Column::Amount.gte(amount))
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if debited == 0 {
+            return Ok(None);
+        }
+        // Read inside the transaction so a concurrent debit cannot show up in
+        // this caller's balance.
+        let balance = service_tokens::Entity::find_by_id(key)
+            .one(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .ok_or_else(|| StorageError::Database("debited token vanished".into()))?
+            .amount;
+        txn.commit().await.map_err(StorageError::from_source)?;
+        Ok(Some(balance))
+    }
+
+    async fn split_token(
+        &self,
+        request: SplitTokenRequest,
+    ) -> StorageResult<Option<SplitTokenOutcome>> {
+        self.ensure_writable()?;
+        let source = request.token.as_bytes().to_vec();
+        let txn = self
+            .connection()
+            .begin()
+            .await
+            .map_err(StorageError::from_source)?;
+        // Guarded debit: concurrent splits cannot overdraw or touch a token
+        // revoked or suspended in between.
+        let debited = service_tokens::Entity::update_many()
+            .col_expr(
+                service_tokens::Column::Amount,
+                Expr::col(service_tokens::Column::Amount).sub(request.amount),
+            )
+            .filter(service_tokens::Column::Token.eq(source.clone()))
+            .filter(usable_at(request.issued_at))
+            .filter(service_tokens::Column::Amount.gte(request.amount))
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if debited == 0 {
+            return Ok(None);
+        }
+        let source = service_tokens::Entity::find_by_id(source)
+            .one(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .ok_or_else(|| StorageError::Database("split source vanished".into()))?;
+        let split = service_tokens::ActiveModel {
+            token: Set(request.new_token.into_bytes().to_vec()),
+            pid: Set(request.new_pid.into_bytes().to_vec()),
+            amount: Set(request.amount),
+            issued_at: Set(request.issued_at),
+            abuse_score: Set(0),
+            expires_at: Set(source.expires_at),
+            tier: Set(source.tier.clone()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(StorageError::from_source)?;
+        txn.commit().await.map_err(StorageError::from_source)?;
+        Ok(Some(SplitTokenOutcome {
+            source: token_to_record(source)?,
+            split: token_to_record(split)?,
+        }))
+    }
+
+    async fn merge_tokens(
+        &self,
+        request: MergeTokensRequest,
+    ) -> StorageResult<Option<ServiceTokenRecord>> {
+        self.ensure_writable()?;
+        let txn = self
+            .connection()
+            .begin()
+            .await
+            .map_err(StorageError::from_source)?;
+        let mut amount: i64 = 0;
+        let mut lifetimes = Vec::with_capacity(request.tokens.len());
+        // The merged token keeps the tier of its largest source.
+        let mut tier: Option<(i64, Option<String>)> = None;
+        for token in &request.tokens {
+            let bytes = token.as_bytes().to_vec();
+            // Guarded revoke: a source revoked, suspended or merged
+            // concurrently aborts the whole merge (the transaction rolls back on drop).
+            let revoked = service_tokens::Entity::update_many()
+                .col_expr(
+                    service_tokens::Column::RevokedAt,
+                    Expr::value(request.issued_at),
+                )
+                .col_expr(
+                    service_tokens::Column::RevokeReason,
+                    Expr::value(MERGED_REVOKE_REASON),
+                )
+                .filter(service_tokens::Column::Token.eq(bytes.clone()))
+                .filter(usable_at(request.issued_at))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?
+                .rows_affected;
+            if revoked == 0 {
+                return Ok(None);
+            }
+            let source = service_tokens::Entity::find_by_id(bytes)
+                .one(&txn)
+                .await
+                .map_err(StorageError::from_source)?
+                .ok_or_else(|| StorageError::Database("merge source vanished".into()))?;
+            amount = amount
+                .checked_add(source.amount)
+                .ok_or_else(|| StorageError::Database("merged balance overflows".into()))?;
+            lifetimes.push(source.expires_at);
+            if tier
+                .as_ref()
+                .is_none_or(|(largest, _)| source.amount > *largest)
+            {
+                tier = Some((source.amount, source.tier.clone()));
+            }
+        }
+        // The merged balance lives as long as the longest-lived source, so
+        // merging never cuts a fresh token short; one without a lifetime
+        // leaves the merged token without one.
+        let expires_at = if lifetimes.contains(&None) {
+            None
+        } else {
+            lifetimes.into_iter().flatten().max()
+        };
+        let merged = service_tokens::ActiveModel {
+            token: Set(request.new_token.as_bytes().to_vec()),
+            pid: Set(request.new_pid.into_bytes().to_vec()),
+            amount: Set(amount),
+            issued_at: Set(request.issued_at),
+            abuse_score: Set(0),
+            expires_at: Set(expires_at),
+            tier: Set(tier.and_then(|(_, tier)| tier)),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(StorageError::from_source)?;
+        if let Some(expires_at) = request.expires_at {
+            token_expiries::Entity::insert(token_expiries::ActiveModel {
+                token: Set(request.new_token.into_bytes().to_vec()),
+                expires_at: Set(expires_at),
+            })
+            .exec_without_returning(&txn)
+            .await
+            .map_err(StorageError::from_source)?;
+        }
+        txn.commit().await.map_err(StorageError::from_source)?;
+        for token in request.tokens {
+            self.publish_change(Change::TokenRevoked(token)).await;
+        }
+        token_to_record(merged).map(Some)
+    }
+
+    async fn find_pinned_expiry(
+        &self,
+        token: &ServiceToken,
+    ) -> StorageResult<Option<DateTime<Utc>>> {
+        let maybe = token_expiries::Entity::find_by_id(token.as_bytes().to_vec())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(maybe.map(|row| row.expires_at))
+    }
+
+    async fn record_first_validation(
+        &self,
+        token: &ServiceToken,
+        at: DateTime<Utc>,
+    ) -> StorageResult<bool> {
+        self.ensure_writable()?;
+        let model = token_validations::ActiveModel {
+            token: Set(token.as_bytes().to_vec()),
+            first_validated_at: Set(at),
+        };
+        let inserted = token_validations::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(token_validations::Column::Token)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(inserted > 0)
+    }
+
+    async fn find_token_review(&self, token: &ServiceToken) -> StorageResult<Option<TokenReview>> {
+        let maybe = token_reviews::Entity::find_by_id(token.as_bytes().to_vec())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(maybe.map(|row| TokenReview {
+            token: token.clone(),
+            reason: row.reason,
+            flagged_at: row.flagged_at,
+        }))
+    }
+
+    async fn expired_tokens(
+        &self,
+        before: DateTime<Utc>,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceToken>> {
+        service_tokens::Entity::find()
+            .filter(service_tokens::Column::ExpiresAt.lt(before))
+            .order_by_asc(service_tokens::Column::ExpiresAt)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(|row| {
+                ServiceToken::try_from(row.token)
+                    .map_err(|err| StorageError::Database(err.to_string()))
+            })
+            .collect()
+    }
+
+    async fn revoked_tokens(
+        &self,
+        after: Option<RevocationCursor>,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceTokenRecord>> {
+        let column = service_tokens::Column::RevokedAt;
+        let (condition, skip) = match after {
+            Some(cursor) => (column.gte(cursor.revoked_at), cursor.skip),
+            None => (column.is_not_null(), 0),
+        };
+        service_tokens::Entity::find()
+            .filter(condition)
+            .order_by_asc(service_tokens::Column::RevokedAt)
+            .order_by_asc(service_tokens::Column::Token)
+            .offset(skip)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(token_to_record)
+            .collect()
+    }
+
+    async fn list_tokens(
+        &self,
+        filter: &TokenFilter,
+        after: Option<&ServiceToken>,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceTokenRecord>> {
+        let mut condition = Condition::all()
+            .add_option(filter.revocation.map(|revocation| {
+                match revocation {
+                    TokenRevocation::Active => Condition::all()
+                        .add(service_tokens::Column::RevokedAt.is_null())
+                        .add(not_suspended_at(Utc::now())),
+                    TokenRevocation::Suspended => Condition::all()
+                        .add(service_tokens::Column::RevokedAt.is_null())
+                        .add(suspended_at(Utc::now())),
+                    TokenRevocation::Revoked => {
+                        Condition::all().add(service_tokens::Column::RevokedAt.is_not_null())
+                    }
+                }
+            }))
+            .add_option(
+                filter
+                    .min_amount
+                    .map(|a| service_tokens::Column::Amount.gte(a)),
+            )
+            .add_option(
+                filter
+                    .max_amount
+                    .map(|a| service_tokens::Column::Amount.lte(a)),
+            );
+        if filter.min_height.is_some() || filter.max_height.is_some() {
+            let mut payments_in_range = Query::select();
+            payments_in_range
+                .column(payments::Column::Pid)
+                .from(payments::Entity)
+                .cond_where(
+                    Condition::all()
+                        .add_option(
+                            filter
+                                .min_height
+                                .map(|h| payments::Column::BlockHeight.gte(h)),
+                        )
+                        .add_option(
+                            filter
+                                .max_height
+                                .map(|h| payments::Column::BlockHeight.lte(h)),
+                        ),
+                );
+            condition = condition
+                .add(service_tokens::Column::Pid.in_subquery(payments_in_range.to_owned()));
+        }
+        if let Some(after) = after {
+            condition = condition.add(service_tokens::Column::Token.gt(after.as_bytes().to_vec()));
+        }
+        service_tokens::Entity::find()
+            .filter(condition)
+            .order_by_asc(service_tokens::Column::Token)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(token_to_record)
+            .collect()
+    }
+
+    async fn find_tokens_in_range(
+        &self,
+        first: &ServiceToken,
+        last: &ServiceToken,
+        limit: u64,
+    ) -> StorageResult<Vec<ServiceTokenRecord>> {
+        service_tokens::Entity::find()
+            .filter(service_tokens::Column::Token.gte(first.as_bytes().to_vec()))
+            .filter(service_tokens::Column::Token.lte(last.as_bytes().to_vec()))
+            .order_by_asc(service_tokens::Column::Token)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(token_to_record)
+            .collect()
+    }
+}
+
+/// Guard for balance-moving updates and suspension: only tokens
+/// [`TokenState::Active`](anon_ticket_domain::model::TokenState::Active) at
+/// `now` by their own lifetime match. Subscription expiry is not stored and
+/// is left to the caller.
+fn usable_at(now: DateTime<Utc>) -> Condition {
+    Condition::all()
+        .add(service_tokens::Column::RevokedAt.is_null())
+        .add(not_suspended_at(now))
+        .add(
+            Condition::any()
+                .add(service_tokens::Column::ExpiresAt.is_null())
+                .add(service_tokens::Column::ExpiresAt.gt(now)),
+        )
+}
+
+/// Tokens without a suspension in force at `now`: never suspended, resumed,
+/// or past `suspended_until` but not swept yet.
+fn not_suspended_at(now: DateTime<Utc>) -> Condition {
+    Condition::any()
+        .add(service_tokens::Column::SuspendedAt.is_null())
+        .add(service_tokens::Column::SuspendedUntil.lte(now))
+}
+
+/// The complement of [`not_suspended_at`], spelled out because negating it
+/// would turn a NULL `suspended_until` into an unknown rather than a match.
+fn suspended_at(now: DateTime<Utc>) -> Condition {
+    Condition::all()
+        .add(service_tokens::Column::SuspendedAt.is_not_null())
+        .add(
+            Condition::any()
+                .add(service_tokens::Column::SuspendedUntil.is_null())
+                .add(service_tokens::Column::SuspendedUntil.gt(now)),
+        )
+}
+
+fn clear_suspension() -> UpdateMany<service_tokens::Entity> {
+    service_tokens::Entity::update_many()
+        .col_expr(
+            service_tokens::Column::SuspendedAt,
+            Expr::value(Option::<DateTime<Utc>>::None),
+        )
+        .col_expr(
+            service_tokens::Column::SuspendReason,
+            Expr::value(Option::<String>::None),
+        )
+        .col_expr(
+            service_tokens::Column::SuspendedUntil,
+            Expr::value(Option::<DateTime<Utc>>::None),
+        )
 }
 
 fn token_to_record(model: service_tokens::Model) -> StorageResult<ServiceTokenRecord> {
@@ -81,5 +570,366 @@ fn token_to_record(model: service_tokens::Model) -> StorageResult<ServiceTokenRe
         revoked_at: model.revoked_at,
         revoke_reason: model.revoke_reason,
         abuse_score: model.abuse_score,
+        expires_at: model.expires_at,
+        tier: model.tier,
+        suspended_at: model.suspended_at,
+        suspend_reason: model.suspend_reason,
+        suspended_until: model.suspended_until,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::TokenState;
+    use chrono::Duration;
+
+    fn pid(byte: u8) -> PaymentId {
+        PaymentId::try_from(vec![byte; 8]).unwrap()
+    }
+
+    fn new_token(byte: u8, expires_at: Option<DateTime<Utc>>) -> NewServiceToken {
+        NewServiceToken {
+            token: ServiceToken::from_bytes([byte; 32]),
+            pid: pid(byte),
+            amount: 100,
+            issued_at: Utc::now() - Duration::days(2),
+            abuse_score: 0,
+            expires_at,
+            tier: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_are_frozen_and_listed_oldest_first() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+        let older = storage
+            .insert_token(new_token(1, Some(now - Duration::days(1))))
+            .await
+            .unwrap();
+        let newer = storage
+            .insert_token(new_token(2, Some(now - Duration::hours(1))))
+            .await
+            .unwrap();
+        let live = storage
+            .insert_token(new_token(3, Some(now + Duration::days(1))))
+            .await
+            .unwrap();
+        storage.insert_token(new_token(4, None)).await.unwrap();
+
+        assert_eq!(storage.debit_token(&older.token, 1).await.unwrap(), None);
+        assert_eq!(storage.debit_token(&live.token, 1).await.unwrap(), Some(99));
+        let split = storage
+            .split_token(SplitTokenRequest {
+                token: live.token.clone(),
+                amount: 9,
+                new_token: ServiceToken::from_bytes([5; 32]),
+                new_pid: pid(5),
+                issued_at: now,
+            })
+            .await
+            .unwrap()
+            .expect("live token splits");
+        assert_eq!(split.split.expires_at, live.expires_at);
+        let merge = |tokens: Vec<ServiceToken>, byte: u8| MergeTokensRequest {
+            tokens,
+            new_token: ServiceToken::from_bytes([byte; 32]),
+            new_pid: pid(byte),
+            issued_at: now,
+            expires_at: None,
+        };
+        assert!(storage
+            .merge_tokens(merge(vec![live.token.clone(), older.token.clone()], 6))
+            .await
+            .unwrap()
+            .is_none());
+        let later = storage
+            .insert_token(new_token(8, Some(now + Duration::days(3))))
+            .await
+            .unwrap();
+        let merged = storage
+            .merge_tokens(merge(vec![live.token.clone(), later.token.clone()], 7))
+            .await
+            .unwrap()
+            .expect("live tokens merge");
+        assert_eq!(merged.expires_at, later.expires_at);
+        let merged = storage
+            .merge_tokens(merge(
+                vec![merged.token, ServiceToken::from_bytes([4; 32])],
+                9,
+            ))
+            .await
+            .unwrap()
+            .expect("live tokens merge");
+        assert_eq!(merged.expires_at, None);
+
+        assert_eq!(
+            storage.expired_tokens(now, 10).await.unwrap(),
+            vec![older.token.clone(), newer.token]
+        );
+        assert_eq!(
+            storage.expired_tokens(now, 1).await.unwrap(),
+            vec![older.token]
+        );
+    }
+
+    #[tokio::test]
+    async fn suspended_tokens_are_frozen_until_resumed() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let token = ServiceToken::from_bytes([1; 32]);
+        storage.insert_token(new_token(1, None)).await.unwrap();
+        let suspend = || SuspendTokenRequest {
+            token: token.clone(),
+            reason: Some("abuse".into()),
+            until: None,
+        };
+
+        let suspended = storage.suspend_token(suspend()).await.unwrap().unwrap();
+        assert_eq!(suspended.state(Utc::now()), TokenState::Suspended);
+        assert_eq!(suspended.suspend_reason.as_deref(), Some("abuse"));
+        assert!(storage.suspend_token(suspend()).await.unwrap().is_none());
+        assert!(storage.debit_token(&token, 10).await.unwrap().is_none());
+        let filter = TokenFilter {
+            revocation: Some(TokenRevocation::Suspended),
+            ..TokenFilter::default()
+        };
+        assert_eq!(
+            storage.list_tokens(&filter, None, 10).await.unwrap().len(),
+            1
+        );
+
+        let resumed = storage.resume_token(&token).await.unwrap().unwrap();
+        assert_eq!(resumed.state(Utc::now()), TokenState::Active);
+        assert_eq!(resumed.suspended_at, None);
+        assert!(storage.resume_token(&token).await.unwrap().is_none());
+        assert_eq!(storage.debit_token(&token, 10).await.unwrap(), Some(90));
+
+        // Revocation is permanent: a revoked token cannot be suspended or
+        // resumed, even if it was suspended first.
+        storage.suspend_token(suspend()).await.unwrap().unwrap();
+        let revoked = storage
+            .revoke_token(RevokeTokenRequest {
+                token: token.clone(),
+                reason: None,
+                abuse_score: None,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(revoked.state(Utc::now()), TokenState::Revoked);
+        assert!(storage.resume_token(&token).await.unwrap().is_none());
+        assert!(storage.suspend_token(suspend()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn timed_suspensions_lapse_and_are_swept() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+        for byte in 1..=2u8 {
+            storage.insert_token(new_token(byte, None)).await.unwrap();
+        }
+        let suspend = |byte: u8, until| SuspendTokenRequest {
+            token: ServiceToken::from_bytes([byte; 32]),
+            reason: None,
+            until: Some(until),
+        };
+        let lapsed = storage
+            .suspend_token(suspend(1, now - Duration::seconds(1)))
+            .await
+            .unwrap()
+            .unwrap();
+        let pending = storage
+            .suspend_token(suspend(2, now + Duration::hours(1)))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lapsed.state(now), TokenState::Active);
+        assert_eq!(pending.state(now), TokenState::Suspended);
+        assert_eq!(pending.suspended_until, Some(now + Duration::hours(1)));
+
+        // A lapsed suspension stops applying before the sweep clears it.
+        assert_eq!(
+            storage.debit_token(&lapsed.token, 10).await.unwrap(),
+            Some(90)
+        );
+        assert!(storage
+            .debit_token(&pending.token, 10)
+            .await
+            .unwrap()
+            .is_none());
+        let suspended = TokenFilter {
+            revocation: Some(TokenRevocation::Suspended),
+            ..TokenFilter::default()
+        };
+        let listed = storage.list_tokens(&suspended, None, 10).await.unwrap();
+        assert_eq!(listed, vec![pending.clone()]);
+
+        assert_eq!(storage.lift_lapsed_suspensions(now).await.unwrap(), 1);
+        let lifted = storage.find_token(&lapsed.token).await.unwrap().unwrap();
+        assert_eq!((lifted.suspended_at, lifted.suspended_until), (None, None));
+        assert_eq!(storage.lift_lapsed_suspensions(now).await.unwrap(), 0);
+        assert_eq!(
+            storage
+                .lift_lapsed_suspensions(now + Duration::hours(1))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn revocation_feed_pages_through_shared_timestamps() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        for byte in 1..=4u8 {
+            storage.insert_token(new_token(byte, None)).await.unwrap();
+        }
+        for byte in [3, 1, 4] {
+            storage
+                .revoke_token(RevokeTokenRequest {
+                    token: ServiceToken::from_bytes([byte; 32]),
+                    reason: None,
+                    abuse_score: None,
+                })
+                .await
+                .unwrap();
+        }
+        // Tokens revoked in one instant are ordered by token.
+        let instant = Utc::now();
+        service_tokens::Entity::update_many()
+            .col_expr(service_tokens::Column::RevokedAt, Expr::value(instant))
+            .filter(service_tokens::Column::Token.is_in([vec![1; 32], vec![4; 32]]))
+            .exec(storage.connection())
+            .await
+            .unwrap();
+
+        let mut cursor = None;
+        let mut seen = Vec::new();
+        loop {
+            let page = storage.revoked_tokens(cursor, 1).await.unwrap();
+            if page.is_empty() {
+                break;
+            }
+            seen.extend(page.iter().map(|record| record.token.as_bytes()[0]));
+            cursor = RevocationCursor::advance(cursor, &page);
+        }
+        assert_eq!(seen, [3, 1, 4]);
+        assert_eq!(
+            cursor,
+            Some(RevocationCursor {
+                revoked_at: instant,
+                skip: 2
+            })
+        );
+
+        storage
+            .revoke_token(RevokeTokenRequest {
+                token: ServiceToken::from_bytes([2; 32]),
+                reason: None,
+                abuse_score: None,
+            })
+            .await
+            .unwrap();
+        let page = storage.revoked_tokens(cursor, 10).await.unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].token, ServiceToken::from_bytes([2; 32]));
+    }
+
+    #[tokio::test]
+    async fn list_tokens_filters_and_pages_by_token() {
+        use anon_ticket_domain::model::NewPayment;
+        use anon_ticket_domain::storage::PaymentStore;
+
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        for byte in 1..=4u8 {
+            storage
+                .insert_payment(NewPayment {
+                    pid: pid(byte),
+                    txid: format!("tx{byte}"),
+                    amount: 100,
+                    block_height: i64::from(byte) * 10,
+                    detected_at: Utc::now(),
+                    address_id: None,
+                })
+                .await
+                .unwrap();
+            storage.insert_token(new_token(byte, None)).await.unwrap();
+        }
+        storage
+            .revoke_token(RevokeTokenRequest {
+                token: ServiceToken::from_bytes([2; 32]),
+                reason: None,
+                abuse_score: None,
+            })
+            .await
+            .unwrap();
+        let bytes = |records: Vec<ServiceTokenRecord>| -> Vec<u8> {
+            records.iter().map(|r| r.token.as_bytes()[0]).collect()
+        };
+
+        let all = TokenFilter::default();
+        assert_eq!(
+            bytes(storage.list_tokens(&all, None, 10).await.unwrap()),
+            [1, 2, 3, 4]
+        );
+        let from = |revoked_at| {
+            Some(RevocationCursor {
+                revoked_at,
+                skip: 0,
+            })
+        };
+        assert_eq!(
+            bytes(
+                storage
+                    .revoked_tokens(from(Utc::now() - Duration::minutes(1)), 10)
+                    .await
+                    .unwrap()
+            ),
+            [2]
+        );
+        assert!(storage
+            .revoked_tokens(from(Utc::now() + Duration::minutes(1)), 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let first = storage.list_tokens(&all, None, 2).await.unwrap();
+        let cursor = first.last().unwrap().token.clone();
+        assert_eq!(
+            bytes(storage.list_tokens(&all, Some(&cursor), 2).await.unwrap()),
+            [3, 4]
+        );
+
+        let revoked = TokenFilter {
+            revocation: Some(TokenRevocation::Revoked),
+            ..TokenFilter::default()
+        };
+        assert_eq!(
+            bytes(storage.list_tokens(&revoked, None, 10).await.unwrap()),
+            [2]
+        );
+        let active_in_range = TokenFilter {
+            revocation: Some(TokenRevocation::Active),
+            min_height: Some(20),
+            max_height: Some(30),
+            ..TokenFilter::default()
+        };
+        assert_eq!(
+            bytes(
+                storage
+                    .list_tokens(&active_in_range, None, 10)
+                    .await
+                    .unwrap()
+            ),
+            [3]
+        );
+
+        for (prefix, expected) in [("0303", &[3][..]), ("0", &[1, 2, 3, 4]), ("05", &[])] {
+            let (first, last) = ServiceToken::prefix_range(prefix).unwrap();
+            let found = storage
+                .find_tokens_in_range(&first, &last, 10)
+                .await
+                .unwrap();
+            assert_eq!(bytes(found), expected, "prefix {prefix}");
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use anon_ticket_domain::model::{
+    tombstone_hash, PaymentId, PaymentTransition, ServiceToken, TombstoneKind, TombstoneRecord,
+};
+use anon_ticket_domain::storage::{StorageResult, TombstoneStore};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{OnConflict, Query},
+    ColumnTrait, DatabaseTransaction, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set, TransactionTrait,
+};
+
+use crate::entity::tombstones::{self, TombstoneKindDb};
+use crate::entity::{
+    idempotency_keys, payment_quotes, payment_renewals, payment_transfers, payments,
+    service_tokens, subaddresses, token_expiries, token_reviews, token_validations,
+    webhook_dead_letters, webhook_deliveries, webhook_events,
+};
+use crate::errors::StorageError;
+use crate::payment_store::transition_stored;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl TombstoneStore for SeaOrmStorage {
+    async fn purge_payment(&self, pid: &PaymentId) -> StorageResult<bool> {
+        self.ensure_writable()?;
+        let txn = self.begin().await?;
+        let deleted = payments::Entity::delete_many()
+            .filter(payments::Column::Pid.eq(pid.as_bytes().to_vec()))
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if deleted > 0 {
+            payment_renewals::Entity::delete_many()
+                .filter(payment_renewals::Column::Pid.eq(pid.as_bytes().to_vec()))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            payment_transfers::Entity::delete_many()
+                .filter(payment_transfers::Column::Pid.eq(pid.as_bytes().to_vec()))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            subaddresses::Entity::delete_by_id(pid.as_bytes().to_vec())
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            payment_quotes::Entity::delete_by_id(pid.as_bytes().to_vec())
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            webhook_dead_letters::Entity::delete_many()
+                .filter(webhook_dead_letters::Column::Pid.eq(pid.as_bytes().to_vec()))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            webhook_deliveries::Entity::delete_many()
+                .filter(
+                    webhook_deliveries::Column::EventId.in_subquery(
+                        Query::select()
+                            .column(webhook_events::Column::Id)
+                            .from(webhook_events::Entity)
+                            .and_where(webhook_events::Column::Pid.eq(pid.as_bytes().to_vec()))
+                            .to_owned(),
+                    ),
+                )
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            webhook_events::Entity::delete_many()
+                .filter(webhook_events::Column::Pid.eq(pid.as_bytes().to_vec()))
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            delete_idempotent_responses(&txn, pid.as_bytes().to_vec()).await?;
+            insert_tombstone(&txn, TombstoneKind::Payment, pid.as_bytes()).await?;
+        }
+        txn.commit().await.map_err(StorageError::from_source)?;
+        Ok(deleted > 0)
+    }
+
+    async fn purge_token(&self, token: &ServiceToken) -> StorageResult<bool> {
+        self.ensure_writable()?;
+        let txn = self.begin().await?;
+        let Some(existing) = service_tokens::Entity::find_by_id(token.as_bytes().to_vec())
+            .one(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+        else {
+            return Ok(false);
+        };
+        let deleted = service_tokens::Entity::delete_by_id(existing.token)
+            .exec(&txn)
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected;
+        if deleted > 0 {
+            // Replayable redeem responses would still hand the token out.
+            delete_idempotent_responses(&txn, existing.pid.clone()).await?;
+            token_validations::Entity::delete_by_id(token.as_bytes().to_vec())
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            token_expiries::Entity::delete_by_id(token.as_bytes().to_vec())
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            token_reviews::Entity::delete_by_id(token.as_bytes().to_vec())
+                .exec(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            insert_tombstone(&txn, TombstoneKind::Token, token.as_bytes()).await?;
+            let expired = existing.expires_at.is_some_and(|at| at <= Utc::now());
+            let remaining = service_tokens::Entity::find()
+                .filter(service_tokens::Column::Pid.eq(existing.pid.clone()))
+                .count(&txn)
+                .await
+                .map_err(StorageError::from_source)?;
+            if expired && remaining == 0 {
+                transition_stored(&txn, &existing.pid, PaymentTransition::Expire).await?;
+            }
+        }
+        txn.commit().await.map_err(StorageError::from_source)?;
+        Ok(deleted > 0)
+    }
+
+    async fn find_tombstone(
+        &self,
+        kind: TombstoneKind,
+        id_hash: &[u8; 32],
+    ) -> StorageResult<Option<TombstoneRecord>> {
+        let maybe = tombstones::Entity::find()
+            .filter(tombstones::Column::IdHash.eq(id_hash.to_vec()))
+            .filter(tombstones::Column::Kind.eq(kind_to_db(kind)))
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        maybe.map(tombstone_to_record).transpose()
+    }
+
+    async fn tombstones_since(
+        &self,
+        since: DateTime<Utc>,
+        limit: u64,
+    ) -> StorageResult<Vec<TombstoneRecord>> {
+        tombstones::Entity::find()
+            .filter(tombstones::Column::DeletedAt.gte(since))
+            .order_by_asc(tombstones::Column::DeletedAt)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(tombstone_to_record)
+            .collect()
+    }
+
+    async fn prune_tombstones(&self, before: DateTime<Utc>) -> StorageResult<u64> {
+        self.ensure_writable()?;
+        let result = tombstones::Entity::delete_many()
+            .filter(tombstones::Column::DeletedAt.lt(before))
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(result.rows_affected)
+    }
+}
+
+impl SeaOrmStorage {
+    pub(crate) async fn begin(&self) -> StorageResult<DatabaseTransaction> {
+        self.connection()
+            .begin()
+            .await
+            .map_err(StorageError::from_source)
+    }
+}
+
+async fn delete_idempotent_responses(txn: &DatabaseTransaction, pid: Vec<u8>) -> StorageResult<()> {
+    idempotency_keys::Entity::delete_many()
+        .filter(idempotency_keys::Column::Pid.eq(pid))
+        .exec(txn)
+        .await
+        .map_err(StorageError::from_source)?;
+    Ok(())
+}
+
+async fn insert_tombstone(
+    txn: &DatabaseTransaction,
+    kind: TombstoneKind,
+    id: &[u8],
+) -> StorageResult<()> {
+    let model = tombstones::ActiveModel {
+        id_hash: Set(tombstone_hash(kind, id).to_vec()),
+        kind: Set(kind_to_db(kind)),
+        deleted_at: Set(Utc::now()),
+    };
+    // A re-created and re-purged identifier refreshes its existing tombstone.
+    tombstones::Entity::insert(model)
+        .on_conflict(
+            OnConflict::columns([tombstones::Column::IdHash, tombstones::Column::Kind])
+                .update_column(tombstones::Column::DeletedAt)
+                .to_owned(),
+        )
+        .exec_without_returning(txn)
+        .await
+        .map_err(StorageError::from_source)?;
+    Ok(())
+}
+
+fn kind_to_db(kind: TombstoneKind) -> TombstoneKindDb {
+    match kind {
+        TombstoneKind::Payment => TombstoneKindDb::Payment,
+        TombstoneKind::Token => TombstoneKindDb::Token,
+    }
+}
+
+fn tombstone_to_record(model: tombstones::Model) -> StorageResult<TombstoneRecord> {
+    let id_hash: [u8; 32] = model
+        .id_hash
+        .try_into()
+        .map_err(|_| StorageError::Database("tombstone hash must be 32 bytes".into()))?;
+    Ok(TombstoneRecord {
+        kind: match model.kind {
+            TombstoneKindDb::Payment => TombstoneKind::Payment,
+            TombstoneKindDb::Token => TombstoneKind::Token,
+        },
+        id_hash,
+        deleted_at: model.deleted_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::{NewPayment, NewServiceToken, PaymentState};
+    use anon_ticket_domain::storage::{PaymentStore, TokenStore};
+
+    const PID: &str = "0123456789abcdef";
+
+    async fn seeded() -> (SeaOrmStorage, PaymentId, ServiceToken) {
+        let storage = SeaOrmStorage::connect("sqlite::memory:")
+            .await
+            .expect("storage inits");
+        let pid = PaymentId::parse(PID).unwrap();
+        let token = ServiceToken::from_bytes([9u8; 32]);
+        storage
+            .insert_payment(NewPayment {
+                pid: pid.clone(),
+                txid: "tx".into(),
+                amount: 1,
+                block_height: 1,
+                detected_at: Utc::now(),
+                address_id: None,
+            })
+            .await
+            .unwrap();
+        storage
+            .insert_token(NewServiceToken {
+                token: token.clone(),
+                pid: pid.clone(),
+                amount: 1,
+                issued_at: Utc::now(),
+                abuse_score: 0,
+                expires_at: None,
+                tier: None,
+            })
+            .await
+            .unwrap();
+        (storage, pid, token)
+    }
+
+    #[tokio::test]
+    async fn purge_replaces_rows_with_tombstones() {
+        let (storage, pid, token) = seeded().await;
+        let before = Utc::now() - chrono::Duration::seconds(1);
+
+        assert!(storage
+            .record_first_validation(&token, Utc::now())
+            .await
+            .unwrap());
+        assert!(storage.purge_payment(&pid).await.unwrap());
+        assert!(storage.purge_token(&token).await.unwrap());
+        // The validation marker goes with the token.
+        assert!(storage
+            .record_first_validation(&token, Utc::now())
+            .await
+            .unwrap());
+        assert!(!storage.purge_payment(&pid).await.unwrap());
+        assert!(storage.find_payment(&pid).await.unwrap().is_none());
+        assert!(storage.find_token(&token).await.unwrap().is_none());
+
+        let hash = tombstone_hash(TombstoneKind::Payment, pid.as_bytes());
+        let tombstone = storage
+            .find_tombstone(TombstoneKind::Payment, &hash)
+            .await
+            .unwrap()
+            .expect("tombstone written");
+        assert_eq!(tombstone.kind, TombstoneKind::Payment);
+        assert!(storage
+            .find_tombstone(TombstoneKind::Token, &hash)
+            .await
+            .unwrap()
+            .is_none());
+
+        let since = storage.tombstones_since(before, 10).await.unwrap();
+        assert_eq!(since.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn purging_the_last_expired_token_expires_its_payment() {
+        let (storage, pid, token) = seeded().await;
+        storage.claim_payment(&pid).await.unwrap().unwrap();
+        let expired = ServiceToken::from_bytes([8u8; 32]);
+        storage
+            .insert_token(NewServiceToken {
+                token: expired.clone(),
+                pid: pid.clone(),
+                amount: 1,
+                issued_at: Utc::now(),
+                abuse_score: 0,
+                expires_at: Some(Utc::now() - chrono::Duration::seconds(1)),
+                tier: None,
+            })
+            .await
+            .unwrap();
+
+        // Purged before expiry, with another token for the PID left.
+        assert!(storage.purge_token(&token).await.unwrap());
+        let payment = storage.find_payment(&pid).await.unwrap().unwrap();
+        assert_eq!(payment.status, PaymentState::Claimed);
+
+        assert!(storage.purge_token(&expired).await.unwrap());
+        let payment = storage.find_payment(&pid).await.unwrap().unwrap();
+        assert_eq!(payment.status, PaymentState::Expired);
+        assert!(storage.claim_payment(&pid).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn prune_drops_only_expired_tombstones() {
+        let (storage, pid, _) = seeded().await;
+        storage.purge_payment(&pid).await.unwrap();
+
+        let past = Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(storage.prune_tombstones(past).await.unwrap(), 0);
+        let future = Utc::now() + chrono::Duration::hours(1);
+        assert_eq!(storage.prune_tombstones(future).await.unwrap(), 1);
+        assert!(storage.tombstones_since(past, 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,469 @@
+use anon_ticket_domain::model::{
+    DeliveryStatus, OutboxStats, PaymentId, WebhookDeadLetter, WebhookDelivery, WebhookEvent,
+};
+use anon_ticket_domain::storage::{StorageResult, WebhookStore};
+use sea_orm::{
+    sea_query::{Expr, OnConflict, Query},
+    ActiveEnum, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    Set,
+};
+
+use crate::entity::webhook_deliveries::{self, DeliveryStatusDb};
+use crate::entity::{webhook_dead_letters, webhook_events};
+use crate::errors::StorageError;
+use crate::SeaOrmStorage;
+
+#[async_trait::async_trait]
+impl WebhookStore for SeaOrmStorage {
+    async fn record_webhook_event(&self, event: WebhookEvent) -> StorageResult<WebhookEvent> {
+        self.ensure_writable()?;
+        webhook_events::Entity::insert(webhook_events::ActiveModel {
+            id: Set(event.id),
+            txid: Set(event.txid.clone()),
+            pid: Set(event.pid.as_bytes().to_vec()),
+            payload: Set(event.payload),
+            created_at: Set(event.created_at),
+        })
+        .on_conflict(
+            OnConflict::column(webhook_events::Column::Txid)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        webhook_events::Entity::find()
+            .filter(webhook_events::Column::Txid.eq(event.txid))
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .ok_or_else(|| StorageError::Database("webhook event vanished".into()))
+            .and_then(into_event)
+    }
+
+    async fn find_webhook_event(&self, id: &str) -> StorageResult<Option<WebhookEvent>> {
+        webhook_events::Entity::find_by_id(id.to_string())
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .map(into_event)
+            .transpose()
+    }
+
+    async fn record_delivery(&self, delivery: WebhookDelivery) -> StorageResult<()> {
+        self.ensure_writable()?;
+        webhook_deliveries::Entity::insert(webhook_deliveries::ActiveModel {
+            event_id: Set(delivery.event_id),
+            url: Set(delivery.url),
+            status: Set(status_to_db(delivery.status)),
+            attempts: Set(i32::try_from(delivery.attempts).unwrap_or(i32::MAX)),
+            last_error: Set(delivery.last_error),
+            last_attempt_at: Set(delivery.last_attempt_at),
+            delivered_at: Set(delivery.delivered_at),
+        })
+        .on_conflict(
+            OnConflict::columns([
+                webhook_deliveries::Column::EventId,
+                webhook_deliveries::Column::Url,
+            ])
+            .update_columns([
+                webhook_deliveries::Column::Status,
+                webhook_deliveries::Column::Attempts,
+                webhook_deliveries::Column::LastError,
+                webhook_deliveries::Column::LastAttemptAt,
+                webhook_deliveries::Column::DeliveredAt,
+            ])
+            .to_owned(),
+        )
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        Ok(())
+    }
+
+    async fn find_delivery(
+        &self,
+        event_id: &str,
+        url: &str,
+    ) -> StorageResult<Option<WebhookDelivery>> {
+        Ok(
+            webhook_deliveries::Entity::find_by_id((event_id.to_string(), url.to_string()))
+                .one(self.connection())
+                .await
+                .map_err(StorageError::from_source)?
+                .map(into_delivery),
+        )
+    }
+
+    async fn list_deliveries(&self, event_id: &str) -> StorageResult<Vec<WebhookDelivery>> {
+        Ok(webhook_deliveries::Entity::find()
+            .filter(webhook_deliveries::Column::EventId.eq(event_id))
+            .order_by_asc(webhook_deliveries::Column::Url)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(into_delivery)
+            .collect())
+    }
+
+    async fn request_redelivery(&self, event_id: &str) -> StorageResult<u64> {
+        self.ensure_writable()?;
+        Ok(webhook_deliveries::Entity::update_many()
+            .col_expr(
+                webhook_deliveries::Column::Status,
+                Expr::value(DeliveryStatusDb::Requested.to_value()),
+            )
+            .filter(webhook_deliveries::Column::EventId.eq(event_id))
+            .filter(
+                webhook_deliveries::Column::Status
+                    .is_in([DeliveryStatusDb::Delivered, DeliveryStatusDb::Failed]),
+            )
+            .exec(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .rows_affected)
+    }
+
+    async fn claim_redeliveries(&self, limit: u64) -> StorageResult<Vec<WebhookDelivery>> {
+        self.ensure_writable()?;
+        let requested = webhook_deliveries::Entity::find()
+            .filter(webhook_deliveries::Column::Status.eq(DeliveryStatusDb::Requested))
+            .order_by_asc(webhook_deliveries::Column::EventId)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        let mut claimed = Vec::with_capacity(requested.len());
+        for model in requested {
+            // Conditional on the status so two dispatchers cannot both
+            // claim it.
+            let updated = webhook_deliveries::Entity::update_many()
+                .col_expr(
+                    webhook_deliveries::Column::Status,
+                    Expr::value(DeliveryStatusDb::Pending.to_value()),
+                )
+                .filter(webhook_deliveries::Column::EventId.eq(model.event_id.clone()))
+                .filter(webhook_deliveries::Column::Url.eq(model.url.clone()))
+                .filter(webhook_deliveries::Column::Status.eq(DeliveryStatusDb::Requested))
+                .exec(self.connection())
+                .await
+                .map_err(StorageError::from_source)?
+                .rows_affected;
+            if updated > 0 {
+                claimed.push(into_delivery(webhook_deliveries::Model {
+                    status: DeliveryStatusDb::Pending,
+                    ..model
+                }));
+            }
+        }
+        Ok(claimed)
+    }
+
+    async fn outbox_stats(&self) -> StorageResult<OutboxStats> {
+        let undelivered = || {
+            webhook_deliveries::Column::Status
+                .is_in([DeliveryStatusDb::Pending, DeliveryStatusDb::Requested])
+        };
+        let backlog = webhook_deliveries::Entity::find()
+            .filter(undelivered())
+            .count(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        let oldest_undelivered_at = webhook_events::Entity::find()
+            .filter(
+                webhook_events::Column::Id.in_subquery(
+                    Query::select()
+                        .column(webhook_deliveries::Column::EventId)
+                        .from(webhook_deliveries::Entity)
+                        .and_where(undelivered())
+                        .to_owned(),
+                ),
+            )
+            .order_by_asc(webhook_events::Column::CreatedAt)
+            .one(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .map(|event| event.created_at);
+        let failed: Vec<(String, i64)> = webhook_deliveries::Entity::find()
+            .select_only()
+            .column(webhook_deliveries::Column::Url)
+            .column_as(webhook_deliveries::Column::EventId.count(), "failed")
+            .filter(webhook_deliveries::Column::Status.eq(DeliveryStatusDb::Failed))
+            .group_by(webhook_deliveries::Column::Url)
+            .into_tuple()
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?;
+        Ok(OutboxStats {
+            backlog,
+            oldest_undelivered_at,
+            failed_by_url: failed
+                .into_iter()
+                .map(|(url, count)| (url, count as u64))
+                .collect(),
+        })
+    }
+
+    async fn record_dead_letter(&self, letter: WebhookDeadLetter) -> StorageResult<()> {
+        self.ensure_writable()?;
+        webhook_dead_letters::Entity::insert(webhook_dead_letters::ActiveModel {
+            event_id: Set(letter.event_id),
+            url: Set(letter.url),
+            pid: Set(letter.pid.as_bytes().to_vec()),
+            payload: Set(letter.payload),
+            attempts: Set(i32::try_from(letter.attempts).unwrap_or(i32::MAX)),
+            last_error: Set(letter.last_error),
+            failed_at: Set(letter.failed_at),
+        })
+        .on_conflict(
+            OnConflict::columns([
+                webhook_dead_letters::Column::EventId,
+                webhook_dead_letters::Column::Url,
+            ])
+            .update_columns([
+                webhook_dead_letters::Column::Payload,
+                webhook_dead_letters::Column::Attempts,
+                webhook_dead_letters::Column::LastError,
+                webhook_dead_letters::Column::FailedAt,
+            ])
+            .to_owned(),
+        )
+        .exec_without_returning(self.connection())
+        .await
+        .map_err(StorageError::from_source)?;
+        Ok(())
+    }
+
+    async fn recent_dead_letters(&self, limit: u64) -> StorageResult<Vec<WebhookDeadLetter>> {
+        webhook_dead_letters::Entity::find()
+            .order_by_desc(webhook_dead_letters::Column::FailedAt)
+            .limit(limit)
+            .all(self.connection())
+            .await
+            .map_err(StorageError::from_source)?
+            .into_iter()
+            .map(into_dead_letter)
+            .collect()
+    }
+}
+
+fn status_to_db(status: DeliveryStatus) -> DeliveryStatusDb {
+    match status {
+        DeliveryStatus::Pending => DeliveryStatusDb::Pending,
+        DeliveryStatus::Delivered => DeliveryStatusDb::Delivered,
+        DeliveryStatus::Failed => DeliveryStatusDb::Failed,
+        DeliveryStatus::Requested => DeliveryStatusDb::Requested,
+    }
+}
+
+fn into_event(model: webhook_events::Model) -> StorageResult<WebhookEvent> {
+    Ok(WebhookEvent {
+        id: model.id,
+        txid: model.txid,
+        pid: PaymentId::try_from(model.pid)
+            .map_err(|err| StorageError::Database(err.to_string()))?,
+        payload: model.payload,
+        created_at: model.created_at,
+    })
+}
+
+fn into_delivery(model: webhook_deliveries::Model) -> WebhookDelivery {
+    WebhookDelivery {
+        event_id: model.event_id,
+        url: model.url,
+        status: match model.status {
+            DeliveryStatusDb::Pending => DeliveryStatus::Pending,
+            DeliveryStatusDb::Delivered => DeliveryStatus::Delivered,
+            DeliveryStatusDb::Failed => DeliveryStatus::Failed,
+            DeliveryStatusDb::Requested => DeliveryStatus::Requested,
+        },
+        attempts: u32::try_from(model.attempts).unwrap_or_default(),
+        last_error: model.last_error,
+        last_attempt_at: model.last_attempt_at,
+        delivered_at: model.delivered_at,
+    }
+}
+
+fn into_dead_letter(model: webhook_dead_letters::Model) -> StorageResult<WebhookDeadLetter> {
+    Ok(WebhookDeadLetter {
+        event_id: model.event_id,
+        url: model.url,
+        pid: PaymentId::try_from(model.pid)
+            .map_err(|err| StorageError::Database(err.to_string()))?,
+        payload: model.payload,
+        attempts: u32::try_from(model.attempts).unwrap_or_default(),
+        last_error: model.last_error,
+        failed_at: model.failed_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn dead_letters_upsert_per_event_and_url() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+        let letter = |url: &str, attempts, failed_at| WebhookDeadLetter {
+            event_id: "aa".repeat(32),
+            url: url.to_string(),
+            pid: PaymentId::parse("0a0a0a0a0a0a0a0a").unwrap(),
+            payload: "{}".to_string(),
+            attempts,
+            last_error: "connection refused".to_string(),
+            failed_at,
+        };
+        storage
+            .record_dead_letter(letter("https://a.example", 3, now - Duration::minutes(1)))
+            .await
+            .unwrap();
+        storage
+            .record_dead_letter(letter("https://b.example", 3, now))
+            .await
+            .unwrap();
+        // A replayed event that fails again replaces its record.
+        storage
+            .record_dead_letter(letter("https://a.example", 5, now + Duration::minutes(1)))
+            .await
+            .unwrap();
+
+        let recent = storage.recent_dead_letters(10).await.unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].url, "https://a.example");
+        assert_eq!(recent[0].attempts, 5);
+        assert_eq!(storage.recent_dead_letters(1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn events_are_recorded_once_and_redelivered_on_request() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        let now = Utc::now();
+        let event = |id: &str| WebhookEvent {
+            id: id.to_string(),
+            txid: "tx-1".to_string(),
+            pid: PaymentId::parse("0a0a0a0a0a0a0a0a").unwrap(),
+            payload: format!("{{\"id\":\"{id}\"}}"),
+            created_at: now,
+        };
+        let first = storage
+            .record_webhook_event(event("01J0000000000000000000000A"))
+            .await
+            .unwrap();
+        // Re-ingesting the txid keeps the original event.
+        let again = storage
+            .record_webhook_event(event("01J0000000000000000000000B"))
+            .await
+            .unwrap();
+        assert_eq!(again, first);
+        assert_eq!(
+            storage.find_webhook_event(&first.id).await.unwrap(),
+            Some(first.clone())
+        );
+
+        let delivery = |url: &str, status| WebhookDelivery {
+            event_id: first.id.clone(),
+            url: url.to_string(),
+            status,
+            attempts: 1,
+            last_error: None,
+            last_attempt_at: Some(now),
+            delivered_at: None,
+        };
+        for (url, status) in [
+            ("https://a.example", DeliveryStatus::Delivered),
+            ("https://b.example", DeliveryStatus::Pending),
+        ] {
+            storage
+                .record_delivery(delivery(url, status))
+                .await
+                .unwrap();
+        }
+
+        // Only the finished delivery is requested again, and it is claimed
+        // once.
+        assert_eq!(storage.request_redelivery(&first.id).await.unwrap(), 1);
+        let claimed = storage.claim_redeliveries(10).await.unwrap();
+        assert_eq!(
+            claimed,
+            vec![delivery("https://a.example", DeliveryStatus::Pending)]
+        );
+        assert!(storage.claim_redeliveries(10).await.unwrap().is_empty());
+        let statuses: Vec<_> = storage
+            .list_deliveries(&first.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|delivery| (delivery.url, delivery.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("https://a.example".to_string(), DeliveryStatus::Pending),
+                ("https://b.example".to_string(), DeliveryStatus::Pending),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn outbox_stats_cover_undelivered_and_failed_deliveries() {
+        let storage = SeaOrmStorage::connect("sqlite::memory:").await.unwrap();
+        assert_eq!(
+            storage.outbox_stats().await.unwrap(),
+            OutboxStats::default()
+        );
+        let now = Utc::now();
+        let mut oldest = None;
+        for (id, age, statuses) in [
+            (
+                "01J0000000000000000000000A",
+                Duration::minutes(10),
+                [DeliveryStatus::Failed, DeliveryStatus::Pending],
+            ),
+            (
+                "01J0000000000000000000000B",
+                Duration::zero(),
+                [DeliveryStatus::Requested, DeliveryStatus::Delivered],
+            ),
+        ] {
+            let event = storage
+                .record_webhook_event(WebhookEvent {
+                    id: id.to_string(),
+                    txid: format!("tx-{id}"),
+                    pid: PaymentId::parse("0a0a0a0a0a0a0a0a").unwrap(),
+                    payload: "{}".to_string(),
+                    created_at: now - age,
+                })
+                .await
+                .unwrap();
+            oldest.get_or_insert(event.created_at);
+            for (url, status) in ["https://a.example", "https://b.example"]
+                .into_iter()
+                .zip(statuses)
+            {
+                storage
+                    .record_delivery(WebhookDelivery {
+                        event_id: event.id.clone(),
+                        url: url.to_string(),
+                        status,
+                        attempts: 1,
+                        last_error: None,
+                        last_attempt_at: Some(now),
+                        delivered_at: None,
+                    })
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let stats = storage.outbox_stats().await.unwrap();
+        assert_eq!(stats.backlog, 2);
+        assert_eq!(stats.oldest_undelivered_at, oldest);
+        assert_eq!(
+            stats.failed_by_url.into_iter().collect::<Vec<_>>(),
+            vec![("https://a.example".to_string(), 1)]
+        );
+    }
+}
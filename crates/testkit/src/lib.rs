@@ -0,0 +1,470 @@
+//! Deterministic fixture builders shared by the workspace test suites.
+//!
+//! Fixtures insert rows exclusively through the domain storage traits, so the
+//! same builders work against `SeaOrmStorage`, mocks, or fault-injecting
+//! wrappers. Every default (PID, txid, timestamps) is fixed, which keeps test
+//! output stable across runs.
+
+use anon_ticket_domain::model::{
+    derive_service_token, NewPayment, NewServiceToken, PaymentId, PaymentRecord,
+    RevokeTokenRequest, ServiceToken, ServiceTokenRecord, SuspendTokenRequest,
+};
+use anon_ticket_domain::storage::{PaymentStore, StorageError, StorageResult, TokenStore};
+use chrono::{DateTime, TimeZone, Utc};
+
+/// Atomic units (piconero) per XMR.
+pub const PICONERO_PER_XMR: i64 = 1_000_000_000_000;
+
+/// PID used when a fixture does not override it.
+pub const DEFAULT_PID: &str = "0123456789abcdef";
+
+/// Txid used when a fixture does not override it.
+pub const DEFAULT_TXID: &str = "tx1";
+
+/// Returns the default fixture PID.
+pub fn default_pid() -> PaymentId {
+    PaymentId::parse(DEFAULT_PID).expect("fixture pid is valid")
+}
+
+/// Returns a distinct, deterministic PID for the `n`-th fixture in a test.
+pub fn nth_pid(n: u64) -> PaymentId {
+    PaymentId::parse(&format!("{n:016x}")).expect("formatted pid is valid")
+}
+
+/// Fixed instant used for detection/issuance timestamps.
+pub fn fixture_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
+        .single()
+        .expect("fixture timestamp is unambiguous")
+}
+
+/// Converts a decimal XMR amount into atomic units.
+pub fn xmr(amount: f64) -> i64 {
+    (amount * PICONERO_PER_XMR as f64).round() as i64
+}
+
+/// Builder for rows in the `payments` table.
+#[derive(Debug, Clone)]
+pub struct PaymentFixture {
+    pid: PaymentId,
+    txid: String,
+    amount: i64,
+    block_height: i64,
+    detected_at: DateTime<Utc>,
+    claimed: bool,
+}
+
+impl PaymentFixture {
+    /// A confirmed payment that has been ingested but not yet redeemed.
+    pub fn confirmed() -> Self {
+        Self {
+            pid: default_pid(),
+            txid: DEFAULT_TXID.to_string(),
+            amount: 42,
+            block_height: 100,
+            detected_at: fixture_time(),
+            claimed: false,
+        }
+    }
+
+    /// A payment that has already been claimed through `claim_payment`.
+    pub fn claimed() -> Self {
+        Self {
+            claimed: true,
+            ..Self::confirmed()
+        }
+    }
+
+    pub fn pid(mut self, pid: PaymentId) -> Self {
+        self.pid = pid;
+        self
+    }
+
+    pub fn txid(mut self, txid: impl Into<String>) -> Self {
+        self.txid = txid.into();
+        self
+    }
+
+    /// Sets the amount in atomic units.
+    pub fn amount(mut self, amount: i64) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    /// Sets the amount in XMR (converted to atomic units).
+    pub fn amount_xmr(self, amount: f64) -> Self {
+        self.amount(xmr(amount))
+    }
+
+    pub fn block_height(mut self, height: i64) -> Self {
+        self.block_height = height;
+        self
+    }
+
+    pub fn detected_at(mut self, detected_at: DateTime<Utc>) -> Self {
+        self.detected_at = detected_at;
+        self
+    }
+
+    /// Returns the insert payload without touching storage.
+    pub fn build(&self) -> NewPayment {
+        NewPayment {
+            pid: self.pid.clone(),
+            txid: self.txid.clone(),
+            amount: self.amount,
+            block_height: self.block_height,
+            detected_at: self.detected_at,
+            address_id: None,
+        }
+    }
+
+    /// Deterministic service token a redeem of this payment would issue.
+    pub fn expected_token(&self) -> ServiceToken {
+        derive_service_token(&self.pid, &self.txid)
+    }
+
+    /// Inserts (and optionally claims) the payment, returning the stored row.
+    pub async fn insert<S>(self, store: &S) -> StorageResult<PaymentRecord>
+    where
+        S: PaymentStore + ?Sized,
+    {
+        store.insert_payment(self.build()).await?;
+        if self.claimed {
+            store.claim_payment(&self.pid).await?;
+        }
+        store
+            .find_payment(&self.pid)
+            .await?
+            .ok_or_else(|| StorageError::Database("fixture payment missing after insert".into()))
+    }
+}
+
+/// Builder for rows in the `service_tokens` table.
+#[derive(Debug, Clone)]
+pub struct TokenFixture {
+    token: Option<ServiceToken>,
+    pid: PaymentId,
+    amount: i64,
+    issued_at: DateTime<Utc>,
+    abuse_score: i16,
+    expires_at: Option<DateTime<Utc>>,
+    tier: Option<String>,
+    revoke_reason: Option<Option<String>>,
+    suspend_reason: Option<Option<String>>,
+    suspended_until: Option<DateTime<Utc>>,
+}
+
+impl TokenFixture {
+    /// A token that has been issued and not revoked.
+    pub fn active() -> Self {
+        Self {
+            token: None,
+            pid: default_pid(),
+            amount: 42,
+            issued_at: fixture_time(),
+            abuse_score: 0,
+            expires_at: None,
+            tier: None,
+            revoke_reason: None,
+            suspend_reason: None,
+            suspended_until: None,
+        }
+    }
+
+    /// A token that is revoked immediately after insertion.
+    pub fn revoked() -> Self {
+        Self {
+            revoke_reason: Some(Some("fixture".to_string())),
+            ..Self::active()
+        }
+    }
+
+    /// A token that is suspended immediately after insertion.
+    pub fn suspended() -> Self {
+        Self {
+            suspend_reason: Some(Some("fixture".to_string())),
+            ..Self::active()
+        }
+    }
+
+    /// Overrides the token value; defaults to `derive_service_token(pid, DEFAULT_TXID)`.
+    pub fn token(mut self, token: ServiceToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    pub fn pid(mut self, pid: PaymentId) -> Self {
+        self.pid = pid;
+        self
+    }
+
+    pub fn amount(mut self, amount: i64) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    pub fn amount_xmr(self, amount: f64) -> Self {
+        self.amount(xmr(amount))
+    }
+
+    pub fn issued_at(mut self, issued_at: DateTime<Utc>) -> Self {
+        self.issued_at = issued_at;
+        self
+    }
+
+    pub fn abuse_score(mut self, score: i16) -> Self {
+        self.abuse_score = score;
+        self
+    }
+
+    pub fn expires_at(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn tier(mut self, tier: impl Into<String>) -> Self {
+        self.tier = Some(tier.into());
+        self
+    }
+
+    /// Lifts the suspension at `until`; only meaningful for
+    /// `TokenFixture::suspended()`.
+    pub fn suspended_until(mut self, until: DateTime<Utc>) -> Self {
+        self.suspended_until = Some(until);
+        self
+    }
+
+    /// Sets the revoke or suspend reason; only meaningful for
+    /// `TokenFixture::revoked()` and `TokenFixture::suspended()`.
+    pub fn reason(mut self, reason: impl Into<String>) -> Self {
+        if self.revoke_reason.is_some() {
+            self.revoke_reason = Some(Some(reason.into()));
+        } else if self.suspend_reason.is_some() {
+            self.suspend_reason = Some(Some(reason.into()));
+        }
+        self
+    }
+
+    /// The token value that `insert` will persist.
+    pub fn value(&self) -> ServiceToken {
+        self.token
+            .clone()
+            .unwrap_or_else(|| derive_service_token(&self.pid, DEFAULT_TXID))
+    }
+
+    /// Returns the insert payload without touching storage.
+    pub fn build(&self) -> NewServiceToken {
+        NewServiceToken {
+            token: self.value(),
+            pid: self.pid.clone(),
+            amount: self.amount,
+            issued_at: self.issued_at,
+            abuse_score: self.abuse_score,
+            expires_at: self.expires_at,
+            tier: self.tier.clone(),
+        }
+    }
+
+    /// Inserts (and optionally revokes or suspends) the token, returning the
+    /// stored row.
+    pub async fn insert<S>(self, store: &S) -> StorageResult<ServiceTokenRecord>
+    where
+        S: TokenStore + ?Sized,
+    {
+        let record = store.insert_token(self.build()).await?;
+        let missing = || StorageError::Database("fixture token missing after insert".into());
+        match (self.revoke_reason, self.suspend_reason) {
+            (Some(reason), _) => store
+                .revoke_token(RevokeTokenRequest {
+                    token: record.token,
+                    reason,
+                    abuse_score: None,
+                })
+                .await?
+                .ok_or_else(missing),
+            (None, Some(reason)) => store
+                .suspend_token(SuspendTokenRequest {
+                    token: record.token,
+                    reason,
+                    until: self.suspended_until,
+                })
+                .await?
+                .ok_or_else(missing),
+            (None, None) => Ok(record),
+        }
+    }
+}
+
+/// Checks an outbound event body against the schema its `schema` field
+/// names in [`anon_ticket_domain::events`], panicking with every violation.
+pub fn assert_event_matches_schema(payload: &str) {
+    let instance: serde_json::Value =
+        serde_json::from_str(payload).unwrap_or_else(|err| panic!("{err}: {payload}"));
+    let id = instance["schema"]
+        .as_str()
+        .unwrap_or_else(|| panic!("event has no `schema` field: {payload}"));
+    let registered = anon_ticket_domain::events::event_schema(id)
+        .unwrap_or_else(|| panic!("`{id}` is not a registered event schema"));
+    let schema: serde_json::Value =
+        serde_json::from_str(registered.schema).expect("registered schema is JSON");
+    let compiled = jsonschema::JSONSchema::compile(&schema)
+        .unwrap_or_else(|err| panic!("`{id}` is not a valid schema: {err}"));
+    let errors: Vec<String> = match compiled.validate(&instance) {
+        Ok(()) => return,
+        Err(errors) => errors
+            .map(|err| format!("{}: {err}", err.instance_path))
+            .collect(),
+    };
+    panic!(
+        "event does not match `{id}`:\n{}\n{payload}",
+        errors.join("\n")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anon_ticket_domain::model::PaymentState;
+    use anon_ticket_storage::SeaOrmStorage;
+
+    async fn storage() -> SeaOrmStorage {
+        SeaOrmStorage::connect("sqlite::memory:")
+            .await
+            .expect("storage inits")
+    }
+
+    #[test]
+    fn xmr_converts_to_atomic_units() {
+        assert_eq!(xmr(0.1), 100_000_000_000);
+        assert_eq!(xmr(1.0), PICONERO_PER_XMR);
+    }
+
+    #[test]
+    fn nth_pid_is_distinct_and_valid() {
+        assert_ne!(nth_pid(1), nth_pid(2));
+        assert_eq!(nth_pid(255).to_hex(), "00000000000000ff");
+    }
+
+    #[tokio::test]
+    async fn payment_fixtures_insert_consistent_rows() {
+        let storage = storage().await;
+        let confirmed = PaymentFixture::confirmed()
+            .pid(nth_pid(1))
+            .amount_xmr(0.1)
+            .insert(&storage)
+            .await
+            .unwrap();
+        assert_eq!(confirmed.status, PaymentState::Confirmed);
+        assert_eq!(confirmed.amount, xmr(0.1));
+
+        let claimed = PaymentFixture::claimed()
+            .pid(nth_pid(2))
+            .insert(&storage)
+            .await
+            .unwrap();
+        assert_eq!(claimed.status, PaymentState::Claimed);
+        assert!(claimed.claimed_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn flaky_store_fails_then_passes_through() {
+        use anon_ticket_domain::services::fault::FaultConfig;
+        use anon_ticket_domain::storage::{FlakyStore, PaymentStore};
+        use std::time::Duration;
+
+        let failing = FlakyStore::new(
+            storage().await,
+            FaultConfig::new(1.0, Duration::ZERO).unwrap(),
+        );
+        assert!(PaymentFixture::confirmed().insert(&failing).await.is_err());
+
+        let healthy = FlakyStore::new(storage().await, FaultConfig::default());
+        PaymentFixture::confirmed().insert(&healthy).await.unwrap();
+        assert!(healthy
+            .inner()
+            .find_payment(&default_pid())
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn flaky_source_injects_rpc_errors() {
+        use anon_ticket_domain::services::fault::FaultConfig;
+        use anon_ticket_monitor::rpc::{FlakySource, TransferSource, TransfersResponse};
+        use anon_ticket_monitor::MonitorError;
+        use std::time::Duration;
+
+        struct FixedHeight;
+
+        #[async_trait::async_trait]
+        impl TransferSource for FixedHeight {
+            async fn fetch_transfers(
+                &self,
+                _start_height: u64,
+                _max_height: u64,
+            ) -> Result<TransfersResponse, MonitorError> {
+                Ok(TransfersResponse::default())
+            }
+
+            async fn wallet_height(&self) -> Result<u64, MonitorError> {
+                Ok(7)
+            }
+        }
+
+        let failing = FlakySource::new(FixedHeight, FaultConfig::new(1.0, Duration::ZERO).unwrap());
+        assert!(matches!(
+            failing.wallet_height().await,
+            Err(MonitorError::Rpc(_))
+        ));
+        let healthy = FlakySource::new(FixedHeight, FaultConfig::default());
+        assert_eq!(healthy.wallet_height().await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn token_fixtures_insert_consistent_rows() {
+        let storage = storage().await;
+        let active = TokenFixture::active().insert(&storage).await.unwrap();
+        assert!(active.revoked_at.is_none());
+
+        let revoked = TokenFixture::revoked()
+            .pid(nth_pid(3))
+            .reason("abuse")
+            .insert(&storage)
+            .await
+            .unwrap();
+        assert!(revoked.revoked_at.is_some());
+        assert_eq!(revoked.revoke_reason.as_deref(), Some("abuse"));
+
+        let suspended = TokenFixture::suspended()
+            .pid(nth_pid(4))
+            .insert(&storage)
+            .await
+            .unwrap();
+        assert!(suspended.revoked_at.is_none());
+        assert_eq!(suspended.suspend_reason.as_deref(), Some("fixture"));
+    }
+
+    #[test]
+    fn event_schemas_accept_their_events_only() {
+        assert_event_matches_schema(
+            r#"{"schema":"payment.detected.v1","pid":"0123456789abcdef","amount":5,"block_height":9,"confirmations":1}"#,
+        );
+        assert_event_matches_schema(
+            r#"{"schema":"token.revoked.v1","id":"01ARZ3NDEKTSV4RRFFQ69G5FAV","event":"token.revoked","pid":"0123456789abcdef","reason":null,"abuse_score":0,"revoked_at":"2024-01-01T00:00:00Z"}"#,
+        );
+        for invalid in [
+            // Missing a required field.
+            r#"{"schema":"payment.detected.v1","pid":"0123456789abcdef","amount":5,"block_height":9}"#,
+            // A field the version does not define.
+            r#"{"schema":"payment.claimable.v1","pid":"0123456789abcdef","amount":5,"block_height":9,"token":"x"}"#,
+            r#"{"schema":"payment.detected.v2","pid":"0123456789abcdef"}"#,
+        ] {
+            let payload = invalid.to_string();
+            assert!(
+                std::panic::catch_unwind(|| assert_event_matches_schema(&payload)).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+}
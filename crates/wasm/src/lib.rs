@@ -0,0 +1,107 @@
+//! Browser bindings for the client-side half of a checkout: generating a PID,
+//! building and decoding integrated addresses, and fingerprinting a service
+//! token. A frontend can show payment instructions with these without a round
+//! trip to the API.
+//!
+//! Failures surface as JS `Error`s carrying the domain error's message.
+
+use std::fmt::Display;
+
+use anon_ticket_domain::integrated_address::{self, IntegratedAddressError};
+use anon_ticket_domain::model::{token_subject, PaymentId, ServiceToken, TokenFormatError};
+use wasm_bindgen::prelude::*;
+
+/// Standard address and payment ID recovered from an integrated address.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedIntegratedAddress {
+    #[wasm_bindgen(js_name = primaryAddress)]
+    pub primary_address: String,
+    #[wasm_bindgen(js_name = paymentId)]
+    pub payment_id: String,
+}
+
+/// A random PID as 16 hex characters, from the browser's CSPRNG.
+#[wasm_bindgen(js_name = generatePid)]
+pub fn generate_pid() -> Result<String, JsError> {
+    PaymentId::generate()
+        .map(|pid| pid.to_hex())
+        .map_err(js_error)
+}
+
+/// Integrated address paying `pid` into the standard `primary_address`.
+#[wasm_bindgen(js_name = buildIntegratedAddress)]
+pub fn build_integrated_address(primary_address: &str, pid: &str) -> Result<String, JsError> {
+    integrated_address_for(primary_address, pid).map_err(js_error)
+}
+
+#[wasm_bindgen(js_name = decodeIntegratedAddress)]
+pub fn decode_integrated_address(address: &str) -> Result<DecodedIntegratedAddress, JsError> {
+    decode(address).map_err(js_error)
+}
+
+/// Hex SHA3-256 of `token`, the form the revocation list exports, so a client
+/// can look its token up without sending it anywhere.
+#[wasm_bindgen(js_name = tokenFingerprint)]
+pub fn token_fingerprint(token: &str) -> Result<String, JsError> {
+    fingerprint(token).map_err(js_error)
+}
+
+fn integrated_address_for(
+    primary_address: &str,
+    pid: &str,
+) -> Result<String, IntegratedAddressError> {
+    let pid = PaymentId::parse(pid)
+        .map_err(|err| IntegratedAddressError::InvalidPaymentId(err.to_string()))?;
+    integrated_address::build_integrated_address(primary_address, &pid)
+}
+
+fn decode(address: &str) -> Result<DecodedIntegratedAddress, IntegratedAddressError> {
+    let (primary_address, pid) = integrated_address::decode_integrated_address(address)?;
+    Ok(DecodedIntegratedAddress {
+        primary_address,
+        payment_id: pid.to_hex(),
+    })
+}
+
+fn fingerprint(token: &str) -> Result<String, TokenFormatError> {
+    ServiceToken::parse(token).map(|token| token_subject(&token))
+}
+
+// `JsError::new` calls into JS, so the helpers above return domain errors and
+// only the exports convert them; that keeps the helpers testable on the host.
+fn js_error(err: impl Display) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMARY_MAINNET: &str =
+        "4ADT1BtbxqEWeMKp9GgPr2NeyJXXtNxvoDawpyA4WpzFcGcoHUvXeijE66DNfohE9r1bQYaBiQjEtKE7CtkTdLwiDznFzra";
+
+    #[test]
+    fn integrated_addresses_round_trip_and_tokens_fingerprint() {
+        let integrated = integrated_address_for(PRIMARY_MAINNET, "0123456789abcdef").unwrap();
+        assert_eq!(
+            decode(&integrated).unwrap(),
+            DecodedIntegratedAddress {
+                primary_address: PRIMARY_MAINNET.to_string(),
+                payment_id: "0123456789abcdef".to_string(),
+            }
+        );
+        assert!(matches!(
+            integrated_address_for(PRIMARY_MAINNET, "not-a-pid"),
+            Err(IntegratedAddressError::InvalidPaymentId(_))
+        ));
+        assert_eq!(
+            decode(PRIMARY_MAINNET),
+            Err(IntegratedAddressError::MissingPaymentId)
+        );
+
+        let token = ServiceToken::from_bytes([7; 32]);
+        assert_eq!(fingerprint(&token.to_hex()).unwrap(), token_subject(&token));
+        assert_eq!(fingerprint("abc"), Err(TokenFormatError::WrongLength));
+    }
+}